@@ -4,10 +4,16 @@
     view_transformations::position_world_to_clip
 }
 
+#include "include/lighting.wgsl"
+
 struct Vertex {
     @builtin(instance_index) instance_index: u32,
     @location(0) pos: vec4<i32>,
     @location(1) norm: vec4<f32>,
+    // [sky, block, ao, 0], sky and block in 0..=15, ao in 0..=3
+    @location(2) light: vec4<u32>,
+    // [u, v, explicit, 0], u and v in 1/16ths of the texture
+    @location(3) uv: vec4<u32>,
 }
 
 struct Fragment {
@@ -15,6 +21,9 @@ struct Fragment {
     @location(0) uv: vec2<f32>,
     @location(1) texture: u32,
     @location(2) brightness: f32,
+    @location(3) light: f32,
+    // 1 if the uv is explicit and shouldn't be wrapped.
+    @location(4) @interpolate(flat) explicit_uv: u32,
 }
 
 struct BlockTexture {
@@ -25,6 +34,10 @@ struct BlockTexture {
 @group(#{MATERIAL_BIND_GROUP}) @binding(0) var atlas_texture: texture_2d_array<f32>;
 @group(#{MATERIAL_BIND_GROUP}) @binding(1) var atlas_sampler: sampler;
 @group(#{MATERIAL_BIND_GROUP}) @binding(2) var<storage, read> table: array<BlockTexture>;
+// fragments below this alpha are discarded, 0.0 unless this is the cutout pass.
+@group(#{MATERIAL_BIND_GROUP}) @binding(3) var<uniform> alpha_cutoff: f32;
+// fraction of the sky light taken away for the time of day, 0.0 during the day.
+@group(#{MATERIAL_BIND_GROUP}) @binding(4) var<uniform> sky_darkness: f32;
 
 @vertex
 fn vertex(v: Vertex) -> Fragment {
@@ -45,17 +58,32 @@ fn vertex(v: Vertex) -> Fragment {
     // write descriptor vars to fragment
     out.texture = desc.index;
 
-    // Infer UVs from world-space coordinates and the base brightness based on direction.
-    out.uv = uv_from_normal(pos, v.norm.xyz);
-    out.brightness = compute_brightness(v.norm.xyz);
+    // Infer UVs from world-space coordinates unless they are explicit,
+    // and the base brightness based on direction.
+    if v.uv.z != 0u {
+        out.uv = vec2<f32>(v.uv.xy) / 16.0;
+    } else {
+        out.uv = uv_from_normal(pos, v.norm.xyz);
+    }
+    out.explicit_uv = v.uv.z;
+    out.brightness = face_brightness(v.norm.xyz);
+    out.light = voxel_light(f32(v.light.x), f32(v.light.y), f32(v.light.z), sky_darkness);
 
     return out;
 }
 
 @fragment
 fn fragment(f: Fragment) -> @location(0) vec4<f32> {
-    let color = textureSample(atlas_texture, atlas_sampler, fract(f.uv), f.texture);
-    return vec4<f32>(color.rgb * f.brightness, color.a);
+    // explicit uvs are in [0,1] and are clamped so the far edge doesn't wrap around.
+    var uv = fract(f.uv);
+    if f.explicit_uv != 0u {
+        uv = clamp(f.uv, vec2<f32>(0.0), vec2<f32>(0.9999));
+    }
+    let color = textureSample(atlas_texture, atlas_sampler, uv, f.texture);
+    if color.a < alpha_cutoff {
+        discard;
+    }
+    return vec4<f32>(color.rgb * f.brightness * f.light, color.a);
 }
 
 fn uv_from_normal(pos: vec3<f32>, norm: vec3<f32>) -> vec2<f32> {
@@ -71,24 +99,3 @@ fn uv_from_normal(pos: vec3<f32>, norm: vec3<f32>) -> vec2<f32> {
         return vec2<f32>(pos.x, pos.y);
     }
 }
-
-fn compute_brightness(norm: vec3<f32>) -> f32 {
-    let abs = abs(norm);
-    let is_y_face = abs.y > 0.9;
-    let is_x_face = abs.x > 0.9;
-    let is_z_face = abs.z > 0.9;
-
-    if (is_y_face) {
-        if (norm.y > 0.0) {
-            return 1.0;
-        } else {
-            return 0.6;
-        }
-    } else if (is_x_face) {
-        return 0.85;
-    } else if (is_z_face) {
-        return 0.75;
-    } else {
-        return 0.8;
-    }
-}
@@ -0,0 +1,89 @@
+#import bevy_pbr::{
+    mesh_functions,
+    view_transformations::position_world_to_clip
+}
+
+#include "include/lighting.wgsl"
+
+struct Vertex {
+    @builtin(instance_index) instance_index: u32,
+    // [x, y, z, axis], the corner of the unit prism and the index of the axis of its face.
+    @location(0) corner: vec4<u32>,
+    // index of the column the vertex belongs to.
+    @location(1) column: u32,
+}
+
+struct Fragment {
+    @builtin(position) clip_pos: vec4<f32>,
+    @location(0) color: vec3<f32>,
+}
+
+struct Column {
+    // bottom corner of the column, relative to the origin of the region.
+    pos: vec3<i32>,
+    height: u32,
+    size: u32,
+    // sRGB color of the top of the column, packed as RGBA.
+    color: u32,
+}
+
+@group(#{MATERIAL_BIND_GROUP}) @binding(0) var<storage, read> columns: array<Column>;
+// fraction of the sky light taken away for the time of day, 0.0 during the day.
+@group(#{MATERIAL_BIND_GROUP}) @binding(1) var<uniform> sky_darkness: f32;
+
+@vertex
+fn vertex(v: Vertex) -> Fragment {
+    var out: Fragment;
+
+    // template meshes have room for more columns than the region has,
+    // the prisms of the columns that don't exist are collapsed to a point.
+    if v.column >= arrayLength(&columns) {
+        out.clip_pos = vec4<f32>(0.0, 0.0, 0.0, 1.0);
+        out.color = vec3<f32>(0.0);
+        return out;
+    }
+
+    let column = columns[v.column];
+    let size = vec3<f32>(f32(column.size), f32(column.height), f32(column.size));
+    let pos = vec3<f32>(column.pos) + vec3<f32>(v.corner.xyz) * size;
+
+    var world_from_local = mesh_functions::get_world_from_local(v.instance_index);
+    let world_pos = mesh_functions::mesh_position_local_to_world(world_from_local, vec4<f32>(pos, 1.0));
+    out.clip_pos = position_world_to_clip(world_pos.xyz);
+
+    // the tops of columns are open to the sky, so they are lit by it.
+    let light = voxel_light(15.0, 0.0, 3.0, sky_darkness);
+    let color = srgb_to_linear(unpack4x8unorm(column.color).rgb);
+    out.color = color * face_brightness(axis_normal(v.corner.w)) * light;
+
+    return out;
+}
+
+@fragment
+fn fragment(f: Fragment) -> @location(0) vec4<f32> {
+    return vec4<f32>(f.color, 1.0);
+}
+
+// Normal of the face along the axis with this index, in the order of `math::axis::Axis`.
+fn axis_normal(axis: u32) -> vec3<f32> {
+    switch axis {
+        case 0u: {
+            return vec3<f32>(1.0, 0.0, 0.0);
+        }
+        case 1u: {
+            return vec3<f32>(-1.0, 0.0, 0.0);
+        }
+        case 2u: {
+            return vec3<f32>(0.0, 1.0, 0.0);
+        }
+        case 3u: {
+            return vec3<f32>(0.0, -1.0, 0.0);
+        }
+        case 4u: {
+            return vec3<f32>(0.0, 0.0, 1.0);
+        }
+        default: {
+            return vec3<f32>(0.0, 0.0, -1.0);
+        }
+    }
+}
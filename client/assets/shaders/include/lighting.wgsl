@@ -0,0 +1,35 @@
+// Lighting shared by the block shaders, include with `#include "include/lighting.wgsl"`.
+
+// Base brightness of a face by its normal, so the sides of blocks can be told apart.
+fn face_brightness(norm: vec3<f32>) -> f32 {
+    let abs = abs(norm);
+    let is_y_face = abs.y > 0.9;
+    let is_x_face = abs.x > 0.9;
+    let is_z_face = abs.z > 0.9;
+
+    if (is_y_face) {
+        if (norm.y > 0.0) {
+            return 1.0;
+        } else {
+            return 0.6;
+        }
+    } else if (is_x_face) {
+        return 0.85;
+    } else if (is_z_face) {
+        return 0.75;
+    } else {
+        return 0.8;
+    }
+}
+
+// Light level from the brightest of sky and block light in 0..=15, darkened by AO in 0..=3.
+// Sky light is darkened at night by the sky darkness, block light isn't.
+fn voxel_light(sky: f32, block: f32, ao: f32, sky_darkness: f32) -> f32 {
+    let level = max(sky * (1.0 - sky_darkness), block) / 15.0;
+    // keep a little light so caves aren't pitch black.
+    return max(level, 0.05) * mix(0.45, 1.0, ao / 3.0);
+}
+
+fn srgb_to_linear(color: vec3<f32>) -> vec3<f32> {
+    return pow(color, vec3<f32>(2.2));
+}
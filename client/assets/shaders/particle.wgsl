@@ -0,0 +1,53 @@
+
+#import bevy_pbr::{
+    mesh_functions,
+    view_transformations::position_world_to_clip
+}
+
+struct Vertex {
+    @builtin(instance_index) instance_index: u32,
+    @location(0) pos: vec3<f32>,
+    // [u, v, texture, light], u and v in 0..1 of the texture, light in 0..1
+    @location(2) uv: vec4<f32>,
+}
+
+struct Fragment {
+    @builtin(position) clip_pos: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) @interpolate(flat) texture: u32,
+    @location(2) light: f32,
+}
+
+struct BlockTexture {
+    @location(0) index: u32,
+    @location(1) flags: u32,
+}
+
+@group(#{MATERIAL_BIND_GROUP}) @binding(0) var atlas_texture: texture_2d_array<f32>;
+@group(#{MATERIAL_BIND_GROUP}) @binding(1) var atlas_sampler: sampler;
+@group(#{MATERIAL_BIND_GROUP}) @binding(2) var<storage, read> table: array<BlockTexture>;
+
+@vertex
+fn vertex(v: Vertex) -> Fragment {
+    var out: Fragment;
+
+    // particles are already in world space, but go through the mesh transform anyway.
+    var world_from_local = mesh_functions::get_world_from_local(v.instance_index);
+    let world_pos = mesh_functions::mesh_position_local_to_world(world_from_local, vec4<f32>(v.pos, 1.0));
+    out.clip_pos = position_world_to_clip(world_pos.xyz);
+
+    out.texture = table[u32(v.uv.z)].index;
+    out.uv = v.uv.xy;
+    out.light = v.uv.w;
+
+    return out;
+}
+
+@fragment
+fn fragment(f: Fragment) -> @location(0) vec4<f32> {
+    let color = textureSample(atlas_texture, atlas_sampler, f.uv, f.texture);
+    if color.a < 0.5 {
+        discard;
+    }
+    return vec4<f32>(color.rgb * f.light, 1.0);
+}
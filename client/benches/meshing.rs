@@ -0,0 +1,73 @@
+//! Greedy meshing of the faces of a subchunk of terrain.
+
+use std::hint::black_box;
+
+use bevy::math::{IVec3, ivec3};
+use client::render::chunk::combiner::QuadCombiner;
+use criterion::{BatchSize, Criterion, criterion_group, criterion_main};
+use data::blockstates::{
+    Transparency,
+    quad::{FULL_BLOCK, Normal, QuadLight},
+};
+use math::axis::Axis;
+
+/// Texture of the top faces of the terrain, and of the rest of the faces.
+const GRASS: i16 = 3;
+const DIRT: i16 = 2;
+
+/// Height of the surface of the terrain in the subchunk, in 0..32.
+fn surface(x: i32, z: i32) -> i32 {
+    let hills = (x as f32 * 0.3).sin() * 5.0 + (z as f32 * 0.25).cos() * 5.0;
+    16 + hills as i32
+}
+
+fn is_solid(pos: IVec3) -> bool {
+    (0..32).contains(&pos.x) && (0..32).contains(&pos.z) && pos.y < surface(pos.x, pos.z)
+}
+
+/// Add the faces of the terrain that aren't covered by a neighbour, like the mesher does.
+fn add_terrain(combiner: &mut QuadCombiner) {
+    for y in 0..32 {
+        for x in 0..32 {
+            for z in 0..32 {
+                let pos = ivec3(x, y, z);
+                if !is_solid(pos) {
+                    continue;
+                }
+
+                let offs = [(x * 16) as i16, (y * 16) as i16, (z * 16) as i16];
+                for axis in Axis::ALL {
+                    if is_solid(pos + axis.as_ivec3()) {
+                        continue;
+                    }
+
+                    let texture = if axis == Axis::PosY { GRASS } else { DIRT };
+                    let quad = FULL_BLOCK[axis].offset_with_texture(offs, texture);
+                    combiner.add(
+                        quad,
+                        QuadLight::FULL,
+                        Transparency::Opaque,
+                        Normal::Aligned(axis),
+                    );
+                }
+            }
+        }
+    }
+}
+
+fn greedy_mesh(c: &mut Criterion) {
+    c.bench_function("greedy_mesh_terrain", |b| {
+        b.iter_batched(
+            || {
+                let mut combiner = QuadCombiner::new();
+                add_terrain(&mut combiner);
+                combiner
+            },
+            |mut combiner| black_box(combiner.combine(Transparency::Opaque)),
+            BatchSize::LargeInput,
+        )
+    });
+}
+
+criterion_group!(benches, greedy_mesh);
+criterion_main!(benches);
@@ -0,0 +1,264 @@
+//! Sound effects and ambient audio.
+//!
+//! Sounds are registered by name in `Registry<SoundEvent>`, and played by writing a
+//! `PlaySound` message. Sounds with a position are played relative to the camera,
+//! which carries the `SpatialListener`. Every sound has a category, with a volume
+//! the player can change in the options menu.
+
+use bevy::{audio::Volume, prelude::*};
+use data::registry::Registry;
+
+use crate::{
+    events::{BlockEffect, BlockEffectKind},
+    player::{PlayerBody, physics::CharacterBody},
+    settings::Settings,
+};
+
+/// Distance walked on the ground between footsteps.
+const FOOTSTEP_DISTANCE: f32 = 1.7;
+
+/// Categories of sounds, each with its own volume setting.
+#[derive(Component, Copy, Clone, Eq, PartialEq, Debug)]
+pub enum SoundCategory {
+    Blocks,
+    Footsteps,
+    Ambient,
+}
+
+impl SoundCategory {
+    /// Volume of the category, from 0 to 1.
+    /// Doesn't include the master volume, which is applied to every sound.
+    pub fn volume(self, settings: &Settings) -> f32 {
+        match self {
+            Self::Blocks => settings.block_volume,
+            Self::Footsteps => settings.footstep_volume,
+            Self::Ambient => settings.ambient_volume,
+        }
+    }
+}
+
+/// A sound that can be played by name.
+pub struct SoundEvent {
+    pub category: SoundCategory,
+
+    /// One of these is picked at random each time the sound plays.
+    pub variants: Vec<Handle<AudioSource>>,
+
+    /// Volume of the sound before the category volume is applied.
+    pub volume: f32,
+
+    /// Playback speed is randomly changed by up to this much,
+    /// so repeated sounds don't all sound the same.
+    pub pitch_variance: f32,
+}
+
+/// Definition of a sound, which is turned into a `SoundEvent` on startup.
+pub struct SoundDefinition {
+    pub name: String,
+    pub category: SoundCategory,
+    pub files: Vec<String>,
+    pub volume: f32,
+    pub pitch_variance: f32,
+}
+
+impl SoundDefinition {
+    pub fn new(name: impl Into<String>, category: SoundCategory, files: &[&str]) -> Self {
+        Self {
+            name: name.into(),
+            category,
+            files: files.iter().map(|file| file.to_string()).collect(),
+            volume: 1.0,
+            pitch_variance: 0.1,
+        }
+    }
+}
+
+/// Every sound known to the client.
+#[derive(Resource)]
+pub struct SoundDefinitions(pub Vec<SoundDefinition>);
+
+impl Default for SoundDefinitions {
+    fn default() -> Self {
+        Self(vec![
+            SoundDefinition::new(
+                "block.break",
+                SoundCategory::Blocks,
+                &["sounds/block/break1.wav", "sounds/block/break2.wav"],
+            ),
+            SoundDefinition::new("block.place", SoundCategory::Blocks, &["sounds/block/place.wav"]),
+            SoundDefinition {
+                volume: 0.5,
+                ..SoundDefinition::new(
+                    "player.footstep",
+                    SoundCategory::Footsteps,
+                    &["sounds/player/step1.wav", "sounds/player/step2.wav"],
+                )
+            },
+            SoundDefinition {
+                volume: 0.3,
+                pitch_variance: 0.0,
+                ..SoundDefinition::new("ambient.wind", SoundCategory::Ambient, &["sounds/ambient/wind.wav"])
+            },
+        ])
+    }
+}
+
+/// Play a sound by name.
+#[derive(Message, Clone)]
+pub struct PlaySound {
+    pub name: &'static str,
+
+    /// Position the sound comes from, or "None" if it has no position.
+    pub pos: Option<Vec3>,
+}
+
+impl PlaySound {
+    pub fn at(name: &'static str, pos: Vec3) -> Self {
+        Self {
+            name,
+            pos: Some(pos),
+        }
+    }
+}
+
+/// The looping ambient sound, which plays while in game.
+#[derive(Component)]
+pub struct AmbientSound;
+
+/// Load the files of every sound definition and register them.
+pub fn register_sounds(
+    defs: Res<SoundDefinitions>,
+    assets: Res<AssetServer>,
+    mut registry: ResMut<Registry<SoundEvent>>,
+) {
+    for def in &defs.0 {
+        registry.insert(
+            def.name.clone(),
+            SoundEvent {
+                category: def.category,
+                variants: def.files.iter().map(|file| assets.load(file)).collect(),
+                volume: def.volume,
+                pitch_variance: def.pitch_variance,
+            },
+        );
+    }
+}
+
+/// Spawn an audio player for every sound that was requested.
+/// Players are despawned once they finish.
+pub fn play_sounds(
+    registry: Res<Registry<SoundEvent>>,
+    settings: Res<Settings>,
+    mut requests: MessageReader<PlaySound>,
+    mut commands: Commands,
+    mut seed: Local<u64>,
+) {
+    let mut random = || {
+        *seed = seed.wrapping_add(1);
+        (fxhash::hash64(&*seed) >> 40) as f32 / (1u64 << 24) as f32
+    };
+
+    for request in requests.read() {
+        let Some(sound) = registry.get_by_name(request.name) else {
+            warn!("[C190] Sound '{}' is not registered.", request.name);
+            continue;
+        };
+
+        if sound.variants.is_empty() {
+            continue;
+        }
+
+        let variant = (random() * sound.variants.len() as f32) as usize;
+        let handle = sound.variants[variant.min(sound.variants.len() - 1)].clone();
+        let speed = 1.0 + (random() * 2.0 - 1.0) * sound.pitch_variance;
+        let volume = sound.volume * sound.category.volume(&settings);
+
+        commands.spawn((
+            sound.category,
+            AudioPlayer(handle),
+            PlaybackSettings::DESPAWN
+                .with_volume(Volume::Linear(volume))
+                .with_speed(speed)
+                .with_spatial(request.pos.is_some()),
+            Transform::from_translation(request.pos.unwrap_or_default()),
+        ));
+    }
+}
+
+/// Play the sounds of blocks that were broken or placed.
+pub fn play_block_sounds(mut effects: MessageReader<BlockEffect>, mut sounds: MessageWriter<PlaySound>) {
+    for effect in effects.read() {
+        let name = match effect.kind {
+            BlockEffectKind::Break => "block.break",
+            BlockEffectKind::Place => "block.place",
+        };
+        sounds.write(PlaySound::at(name, effect.pos.as_vec3() + 0.5));
+    }
+}
+
+/// Play footsteps while the player walks on the ground.
+pub fn play_footsteps(
+    time: Res<Time>,
+    player: Single<(&Transform, &CharacterBody), With<PlayerBody>>,
+    mut sounds: MessageWriter<PlaySound>,
+    mut walked: Local<f32>,
+) {
+    let (transform, body) = *player;
+    if !body.on_ground || body.flying {
+        return;
+    }
+
+    *walked += body.velocity.xz().length() * time.delta_secs();
+    if *walked >= FOOTSTEP_DISTANCE {
+        *walked = 0.0;
+        sounds.write(PlaySound::at("player.footstep", transform.translation));
+    }
+}
+
+/// Start the ambient sound.
+/// Should fire on enter into AppState::InGame
+pub fn spawn_ambient_sound(
+    registry: Res<Registry<SoundEvent>>,
+    settings: Res<Settings>,
+    mut commands: Commands,
+) {
+    let Some(sound) = registry.get_by_name("ambient.wind") else {
+        return;
+    };
+
+    let Some(handle) = sound.variants.first() else {
+        return;
+    };
+
+    let volume = sound.volume * sound.category.volume(&settings);
+    commands.spawn((
+        AmbientSound,
+        sound.category,
+        AudioPlayer(handle.clone()),
+        PlaybackSettings::LOOP.with_volume(Volume::Linear(volume)),
+    ));
+}
+
+/// Stop the ambient sound.
+/// Should fire on exit from AppState::InGame
+pub fn despawn_ambient_sound(mut commands: Commands, ambient: Query<Entity, With<AmbientSound>>) {
+    for entity in &ambient {
+        commands.entity(entity).despawn();
+    }
+}
+
+/// Apply changes to category volumes to the sounds that are already playing.
+pub fn apply_sound_volumes(
+    settings: Res<Settings>,
+    registry: Res<Registry<SoundEvent>>,
+    mut ambient: Query<&mut AudioSink, With<AmbientSound>>,
+) {
+    let Some(sound) = registry.get_by_name("ambient.wind") else {
+        return;
+    };
+
+    let volume = sound.volume * sound.category.volume(&settings);
+    for mut sink in &mut ambient {
+        sink.set_volume(Volume::Linear(volume));
+    }
+}
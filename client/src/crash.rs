@@ -0,0 +1,347 @@
+//! Crash reports, written when the client panics.
+//!
+//! The panic hook writes the panic message, a backtrace, and what the client was doing
+//! to `logs/crash_<time>.txt` in the root directory. The hook can't reach the ECS world,
+//! so what the client was doing is copied to a static every second. The path of the
+//! last report is kept in `logs/last-crash`, and the next launch shows a dialog over the
+//! title menu offering to open it.
+
+use std::{
+    backtrace::Backtrace,
+    fmt::Write,
+    fs, io,
+    panic::{self, PanicHookInfo},
+    path::{Path, PathBuf},
+    process::Command,
+    thread,
+    time::Duration,
+};
+
+use bevy::{prelude::*, render::renderer::RenderAdapterInfo};
+use data::{info::RootPath, locale::Locale, logging::LOGS_DIR};
+use parking_lot::Mutex;
+use world::{World, region::chunk::flags::ChunkState};
+
+use crate::{
+    screenshot::timestamp,
+    sequences::connect::ConnectSeqInfo,
+    singleplayer::Singleplayer,
+    states::AppState,
+    ui::{
+        UiVars,
+        button::{ButtonAction, ButtonClicked, ButtonVisuals},
+        menus::Menu,
+    },
+};
+
+/// Time between updates of the context written to crash reports.
+const UPDATE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// What the client was doing, written to crash reports.
+static CONTEXT: Mutex<CrashContext> = Mutex::new(CrashContext::new());
+
+struct CrashContext {
+    /// Directory reports are written to, None until the hook is installed.
+    dir: Option<PathBuf>,
+    app_state: String,
+    menu: String,
+    server: String,
+    loaded_chunks: usize,
+    adapter: String,
+}
+
+impl CrashContext {
+    const fn new() -> Self {
+        Self {
+            dir: None,
+            app_state: String::new(),
+            menu: String::new(),
+            server: String::new(),
+            loaded_chunks: 0,
+            adapter: String::new(),
+        }
+    }
+}
+
+pub fn logs_dir(root: &RootPath) -> PathBuf {
+    root.join(LOGS_DIR)
+}
+
+/// File holding the path of the last crash report, until its dialog is dismissed.
+fn last_crash_path(dir: &Path) -> PathBuf {
+    dir.join("last-crash")
+}
+
+/// Install the panic hook that writes crash reports.
+/// The default hook still runs first, so the panic is printed as before.
+pub fn install_crash_reporter(root: Res<RootPath>) {
+    CONTEXT.lock().dir = Some(logs_dir(&root));
+
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        default_hook(info);
+        match write_report(info) {
+            Ok(Some(path)) => eprintln!("Crash report saved to '{}'", path.display()),
+            Ok(None) => {}
+            Err(e) => eprintln!("[C230] Failed to write crash report with error: '{e}'"),
+        }
+    }));
+}
+
+/// Write the crash report and remember its path for the next launch.
+/// Returns None if the hook wasn't installed with a directory yet.
+fn write_report(info: &PanicHookInfo) -> io::Result<Option<PathBuf>> {
+    // the panic may have happened while the context was locked, on this thread.
+    let context = CONTEXT.try_lock();
+    let Some(dir) = context.as_ref().and_then(|ctx| ctx.dir.clone()) else {
+        return Ok(None);
+    };
+
+    let message = info
+        .payload()
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| info.payload().downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "Box<dyn Any>".into());
+    let location = info
+        .location()
+        .map_or("unknown".into(), |loc| loc.to_string());
+
+    let time = timestamp();
+    let thread = thread::current();
+
+    let mut report = String::new();
+    let _ = writeln!(report, "Open Voxel crash report, {time} UTC");
+    let _ = writeln!(report, "Version: {}", env!("CARGO_PKG_VERSION"));
+    let _ = writeln!(report, "Thread: {}", thread.name().unwrap_or("unnamed"));
+    let _ = writeln!(report, "Panic: {message}");
+    let _ = writeln!(report, "Location: {location}");
+    let _ = writeln!(report);
+    match &context {
+        Some(ctx) => {
+            let _ = writeln!(report, "App state: {}", ctx.app_state);
+            let _ = writeln!(report, "Menu: {}", ctx.menu);
+            let _ = writeln!(report, "Server: {}", ctx.server);
+            let _ = writeln!(report, "Loaded chunks: {}", ctx.loaded_chunks);
+            let _ = writeln!(report, "GPU: {}", ctx.adapter);
+        }
+        None => {
+            let _ = writeln!(report, "Context: unavailable");
+        }
+    }
+    let _ = writeln!(report);
+    let _ = writeln!(report, "Backtrace:\n{}", Backtrace::force_capture());
+
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(format!("crash_{time}.txt"));
+    fs::write(&path, report)?;
+    fs::write(last_crash_path(&dir), path.to_string_lossy().as_bytes())?;
+    Ok(Some(path))
+}
+
+/// Copy what the client is doing to the context of crash reports, every second.
+pub fn update_crash_context(
+    time: Res<Time>,
+    app_state: Res<State<AppState>>,
+    menu: Res<State<Menu>>,
+    world: Res<World>,
+    connect: Option<Res<ConnectSeqInfo>>,
+    singleplayer: Option<Res<Singleplayer>>,
+    adapter: Option<Res<RenderAdapterInfo>>,
+    mut since_update: Local<Duration>,
+) {
+    *since_update += time.delta();
+    if *since_update < UPDATE_INTERVAL {
+        return;
+    }
+    *since_update = Duration::ZERO;
+
+    let server = match (singleplayer, connect) {
+        (Some(sp), _) => format!("singleplayer, world '{}'", sp.world_dir.display()),
+        (None, Some(info)) => info.addr_string.clone(),
+        (None, None) => "none".into(),
+    };
+    let loaded_chunks = world
+        .regions()
+        .flat_map(|region| region.chunks())
+        .filter(|chunk| chunk.load_state() == ChunkState::Loaded)
+        .count();
+
+    let mut context = CONTEXT.lock();
+    context.app_state = format!("{:?}", app_state.get());
+    context.menu = format!("{:?}", menu.get());
+    context.server = server;
+    context.loaded_chunks = loaded_chunks;
+    if let Some(adapter) = adapter {
+        context.adapter = format!(
+            "{} ({:?}, driver {} {})",
+            adapter.name, adapter.backend, adapter.driver, adapter.driver_info
+        );
+    }
+}
+
+/// The report of the crash in the last session, until its dialog is dismissed.
+#[derive(Resource)]
+pub struct PendingCrashReport(PathBuf);
+
+/// Look for the report of a crash in the last session.
+pub fn find_crash_report(root: Res<RootPath>, mut commands: Commands) {
+    let marker = last_crash_path(&logs_dir(&root));
+    let Ok(path) = fs::read_to_string(&marker) else {
+        return;
+    };
+
+    let path = PathBuf::from(path.trim());
+    if path.is_file() {
+        info!(
+            "Found crash report of the last session: '{}'",
+            path.display()
+        );
+        commands.insert_resource(PendingCrashReport(path));
+    } else {
+        // the report was deleted, there's nothing to offer.
+        let _ = fs::remove_file(marker);
+    }
+}
+
+/// Attached to the buttons of the crash dialog.
+#[derive(Component, Copy, Clone, Debug)]
+pub enum CrashDialogButton {
+    Open,
+    Dismiss,
+}
+
+/// The dialog offering to open the last crash report.
+#[derive(Component)]
+pub struct CrashDialog;
+
+/// Draw the crash dialog over the title menu, if the last session crashed.
+/// Should fire on enter into Menu::Title
+#[rustfmt::skip]
+pub fn draw_crash_dialog(
+    report: Option<Res<PendingCrashReport>>,
+    locale: Res<Locale>,
+    vars: Res<UiVars>,
+    mut commands: Commands,
+) {
+    let Some(report) = report else {
+        return;
+    };
+
+    commands.spawn((
+        CrashDialog,
+        DespawnOnExit(Menu::Title),
+        GlobalZIndex(1),
+        Node {
+            position_type: PositionType::Absolute,
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..default()
+        },
+        BackgroundColor(Color::BLACK.with_alpha(0.6)),
+    )).with_children(|parent| {
+        parent.spawn((
+            // Box with the message and buttons.
+            Node {
+                width: Val::Percent(60.0),
+                max_width: Val::Px(800.0),
+                display: Display::Flex,
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                padding: UiRect::all(Val::Px(30.0)),
+                ..default()
+            },
+            BackgroundColor(vars.colors.panel),
+        )).with_children(|parent| {
+            parent.spawn((
+                Text::new(locale.get("ui.crash.title")),
+                TextLayout::new_with_justify(Justify::Center),
+                TextFont {
+                    font_size: 30.0,
+                    ..default()
+                },
+                Node::default(),
+            ));
+
+            parent.spawn((
+                Text::new(format!("{}: {}", locale.get("ui.crash.saved"), report.0.display())),
+                TextLayout::new_with_justify(Justify::Center),
+                TextFont {
+                    font: vars.font(),
+                    font_size: 16.0,
+                    ..default()
+                },
+                Node {
+                    margin: UiRect::top(Val::Px(20.0)),
+                    ..default()
+                },
+            ));
+
+            parent.spawn((
+                CrashDialogButton::Open,
+                ButtonAction::None,
+                ButtonVisuals::text(locale.get("ui.crash.open"), Val::Percent(100.0)).bundle(&vars),
+            ));
+
+            parent.spawn((
+                CrashDialogButton::Dismiss,
+                ButtonAction::None,
+                ButtonVisuals::text(locale.get("ui.crash.dismiss"), Val::Percent(100.0)).bundle(&vars),
+            ));
+        });
+    });
+}
+
+/// Open the report or dismiss the dialog when its buttons are clicked.
+/// Either way the dialog isn't shown again for this report.
+pub fn handle_crash_dialog_clicks(
+    mut clicks: MessageReader<ButtonClicked>,
+    buttons: Query<&CrashDialogButton>,
+    dialogs: Query<Entity, With<CrashDialog>>,
+    report: Option<Res<PendingCrashReport>>,
+    root: Res<RootPath>,
+    mut commands: Commands,
+) {
+    let Some(report) = report else {
+        return;
+    };
+
+    let Some(button) = clicks
+        .read()
+        .find_map(|click| buttons.get(click.entity).ok())
+    else {
+        return;
+    };
+
+    if let CrashDialogButton::Open = button
+        && let Err(e) = open_file(&report.0)
+    {
+        error!(
+            "[C231] Failed to open crash report: '{}' with error: '{e}'",
+            report.0.display()
+        );
+    }
+
+    if let Err(e) = fs::remove_file(last_crash_path(&logs_dir(&root))) {
+        warn!("[C232] Failed to forget the last crash report with error: '{e}'");
+    }
+
+    for dialog in &dialogs {
+        commands.entity(dialog).despawn();
+    }
+    commands.remove_resource::<PendingCrashReport>();
+}
+
+/// Open a file with the program the platform picks for it.
+fn open_file(path: &Path) -> io::Result<()> {
+    #[cfg(target_os = "windows")]
+    let mut command = Command::new("explorer");
+    #[cfg(target_os = "macos")]
+    let mut command = Command::new("open");
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    let mut command = Command::new("xdg-open");
+
+    command.arg(path).spawn().map(|_| ())
+}
@@ -0,0 +1,197 @@
+//! Performance overlay, for profiling meshing and chunk streaming.
+//!
+//! Bevy's frame time and render diagnostics are shown next to counters of the chunk
+//! pipeline: the remesh queue, subchunks meshed per tick, chunk requests waiting on
+//! the server, chunk meshes drawn, meshes hidden by occlusion culling, chunks drawn as
+//! columns and the memory held by the client world.
+//! In singleplayer, the seed of the world is shown too, unless it is hidden in the options.
+//! The overlay is toggled with the "toggle-perf" action.
+
+use std::fmt::Write;
+
+use bevy::{
+    diagnostic::{
+        Diagnostic, DiagnosticPath, Diagnostics, DiagnosticsStore, FrameTimeDiagnosticsPlugin,
+        RegisterDiagnostic,
+    },
+    prelude::*,
+    render::diagnostic::RenderDiagnosticsPlugin,
+};
+use world::World;
+
+use crate::{
+    render::chunk::{
+        ChunkMaterial, ChunkMeshIndex, ChunkRenderQueue, ChunkRenderer, columns::FarColumns,
+        occlusion::OcclusionCuller,
+    },
+    settings::Settings,
+    singleplayer::Singleplayer,
+    states::AppState,
+    ui::UiVars,
+    world::requests::ChunkRequests,
+};
+
+/// Seconds between updates of the overlay text.
+const REFRESH_INTERVAL: f32 = 0.25;
+
+pub const CHUNK_QUEUE: DiagnosticPath = DiagnosticPath::const_new("chunks/remesh_queue");
+pub const CHUNKS_MESHED: DiagnosticPath = DiagnosticPath::const_new("chunks/meshed");
+pub const CHUNK_REQUESTS: DiagnosticPath = DiagnosticPath::const_new("chunks/requests");
+pub const CHUNK_MESHES: DiagnosticPath = DiagnosticPath::const_new("chunks/meshes");
+pub const CHUNK_DRAWS: DiagnosticPath = DiagnosticPath::const_new("chunks/draws");
+pub const OCCLUDED_MESHES: DiagnosticPath = DiagnosticPath::const_new("chunks/occluded");
+pub const COLUMN_CHUNKS: DiagnosticPath = DiagnosticPath::const_new("chunks/columns");
+pub const WORLD_MEMORY: DiagnosticPath = DiagnosticPath::const_new("world/memory");
+
+/// Adds the frame time and render diagnostics, and registers the chunk diagnostics.
+pub struct PerfDiagnosticsPlugin;
+
+impl Plugin for PerfDiagnosticsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((
+            FrameTimeDiagnosticsPlugin::default(),
+            RenderDiagnosticsPlugin,
+        ))
+        .register_diagnostic(Diagnostic::new(CHUNK_QUEUE))
+        .register_diagnostic(Diagnostic::new(CHUNKS_MESHED))
+        .register_diagnostic(Diagnostic::new(CHUNK_REQUESTS))
+        .register_diagnostic(Diagnostic::new(CHUNK_MESHES))
+        .register_diagnostic(Diagnostic::new(CHUNK_DRAWS))
+        .register_diagnostic(Diagnostic::new(OCCLUDED_MESHES))
+        .register_diagnostic(Diagnostic::new(COLUMN_CHUNKS))
+        .register_diagnostic(Diagnostic::new(WORLD_MEMORY).with_suffix(" MiB"));
+    }
+}
+
+/// The text of the performance overlay.
+#[derive(Component)]
+pub struct PerfOverlay;
+
+/// Record the counters of the chunk pipeline.
+pub fn measure_chunk_diagnostics(
+    mut diagnostics: Diagnostics,
+    queue: Res<ChunkRenderQueue>,
+    renderer: Res<ChunkRenderer>,
+    requests: Res<ChunkRequests>,
+    index: Res<ChunkMeshIndex>,
+    columns: Res<FarColumns>,
+    culler: Res<OcclusionCuller>,
+    world: Res<World>,
+    draws: Query<&ViewVisibility, With<MeshMaterial3d<ChunkMaterial>>>,
+) {
+    diagnostics.add_measurement(&CHUNK_QUEUE, || queue.len() as f64);
+    diagnostics.add_measurement(&CHUNKS_MESHED, || renderer.last_meshed() as f64);
+    diagnostics.add_measurement(&CHUNK_REQUESTS, || requests.num_pending() as f64);
+    diagnostics.add_measurement(&CHUNK_MESHES, || index.len() as f64);
+    diagnostics.add_measurement(&CHUNK_DRAWS, || {
+        draws.iter().filter(|visible| visible.get()).count() as f64
+    });
+    diagnostics.add_measurement(&OCCLUDED_MESHES, || culler.hidden() as f64);
+    diagnostics.add_measurement(&COLUMN_CHUNKS, || columns.len() as f64);
+    diagnostics.add_measurement(&WORLD_MEMORY, || {
+        world.heap_size() as f64 / (1024.0 * 1024.0)
+    });
+}
+
+/// Show or hide the overlay when the "toggle-perf" action fires.
+pub fn handle_toggle_perf_overlay(
+    app_state: Res<State<AppState>>,
+    vars: Res<UiVars>,
+    overlay: Query<Entity, With<PerfOverlay>>,
+    mut commands: Commands,
+) {
+    if *app_state != AppState::InGame {
+        return;
+    }
+
+    if let Ok(entity) = overlay.single() {
+        commands.entity(entity).despawn();
+        return;
+    }
+
+    commands.spawn((
+        PerfOverlay,
+        DespawnOnExit(AppState::InGame),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(8.0),
+            right: Val::Px(8.0),
+            padding: UiRect::all(Val::Px(6.0)),
+            ..default()
+        },
+        BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.5)),
+        Text::new(""),
+        TextFont {
+            font: vars.font(),
+            font_size: 14.0,
+            ..default()
+        },
+        TextColor(Color::WHITE),
+        Pickable::IGNORE,
+    ));
+}
+
+/// Write the current diagnostics into the overlay.
+pub fn update_perf_overlay(
+    time: Res<Time>,
+    store: Res<DiagnosticsStore>,
+    settings: Res<Settings>,
+    singleplayer: Option<Res<Singleplayer>>,
+    mut text: Single<&mut Text, With<PerfOverlay>>,
+    mut since_refresh: Local<f32>,
+) {
+    *since_refresh += time.delta_secs();
+    if *since_refresh < REFRESH_INTERVAL && !text.0.is_empty() {
+        return;
+    }
+    *since_refresh = 0.0;
+
+    let value = |path: &DiagnosticPath| {
+        store
+            .get(path)
+            .and_then(|diagnostic| diagnostic.smoothed())
+            .unwrap_or(0.0)
+    };
+
+    // render diagnostics are nested, only the top level spans are summed.
+    let render_time = |suffix: &str| {
+        store
+            .iter()
+            .filter(|diagnostic| {
+                let path = diagnostic.path().as_str();
+                path.starts_with("render/")
+                    && path.split('/').count() == 3
+                    && path.ends_with(suffix)
+            })
+            .filter_map(|diagnostic| diagnostic.smoothed())
+            .sum::<f64>()
+    };
+
+    let mut out = String::new();
+    let _ = writeln!(out, "fps: {:.0}", value(&FrameTimeDiagnosticsPlugin::FPS));
+    let _ = writeln!(
+        out,
+        "frame: {:.2} ms",
+        value(&FrameTimeDiagnosticsPlugin::FRAME_TIME)
+    );
+    let _ = writeln!(out, "render cpu: {:.2} ms", render_time("elapsed_cpu"));
+    let _ = writeln!(out, "render gpu: {:.2} ms", render_time("elapsed_gpu"));
+    let _ = writeln!(out, "remesh queue: {:.0}", value(&CHUNK_QUEUE));
+    let _ = writeln!(out, "meshed/tick: {:.1}", value(&CHUNKS_MESHED));
+    let _ = writeln!(out, "chunk requests: {:.0}", value(&CHUNK_REQUESTS));
+    let _ = writeln!(
+        out,
+        "chunk draws: {:.0} / {:.0}",
+        value(&CHUNK_DRAWS),
+        value(&CHUNK_MESHES)
+    );
+    let _ = writeln!(out, "occluded meshes: {:.0}", value(&OCCLUDED_MESHES));
+    let _ = writeln!(out, "column chunks: {:.0}", value(&COLUMN_CHUNKS));
+    let _ = write!(out, "world memory: {:.1} MiB", value(&WORLD_MEMORY));
+    if let Some(singleplayer) = singleplayer
+        && settings.show_seed
+    {
+        let _ = write!(out, "\nseed: {}", singleplayer.seed);
+    }
+    text.0 = out;
+}
@@ -1,5 +1,7 @@
 use bevy::prelude::*;
+use data::registry::RegistryRemap;
 use protocol::{ExitCode, session::Session, types::RegistrySyncPacket};
+use world::voxel::Voxel;
 
 /// Sent when the server sends a map of entry names to indices.
 #[derive(Message)]
@@ -7,12 +9,60 @@ pub struct SyncRegistries {
     pub payload: RegistrySyncPacket,
 }
 
+/// A registry was reordered to match the server's.
+#[derive(Message, Clone)]
+pub struct RegistryRemapped {
+    /// Name the registry was initialized with, see `AppExt::init_sync_registry`.
+    pub registry: String,
+
+    /// The IDs entries had before, mapped to the IDs they have now.
+    pub remap: RegistryRemap,
+}
+
 /// The player is fully connected and ready to enter the simulation.
 #[derive(Message)]
 pub struct PlayerConnected {
     pub session: Session,
 }
 
+/// The connection to the server was closed, or couldn't be established.
+#[derive(Message, Clone)]
+pub struct Disconnected {
+    pub exit: ExitCode,
+}
+
 /// The player submitted a message through the chat box.
 #[derive(Message, Clone)]
 pub struct ChatBoxSubmit(pub String);
+
+/// A chunk was removed from the client world.
+#[derive(Message, Clone)]
+pub struct ChunkUnloaded {
+    pub origin: IVec2,
+}
+
+/// A block update was applied to the client world.
+#[derive(Message, Clone)]
+pub struct BlockUpdated {
+    /// Position of the voxel that changed.
+    pub pos: IVec3,
+}
+
+/// A block was broken or placed, by this player or another one.
+/// Unlike `BlockUpdated`, this isn't sent when a predicted edit is rolled back.
+#[derive(Message, Clone)]
+pub struct BlockEffect {
+    /// Position of the voxel that changed.
+    pub pos: IVec3,
+
+    /// The voxel that was broken or placed.
+    pub voxel: Voxel,
+
+    pub kind: BlockEffectKind,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum BlockEffectKind {
+    Break,
+    Place,
+}
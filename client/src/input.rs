@@ -1,11 +1,47 @@
 use bevy::{ecs::system::SystemId, prelude::*};
 use fxhash::FxHashMap;
+use serde::{Deserialize, Serialize};
 
-use crate::{focus::Focus, states::AppState, ui::menus::Menu};
+use crate::{
+    focus::{Focus, Focused},
+    states::AppState,
+    ui::{
+        chat::ChatContainer,
+        menus::{Menu, controls::RebindCapture},
+    },
+};
+
+/// How far a gamepad axis has to be pushed before an action bound to it activates.
+const AXIS_THRESHOLD: f32 = 0.5;
+
+/// Stick input within this distance of the center is ignored, so worn sticks
+/// that don't return to the center don't move the player.
+const STICK_DEAD_ZONE: f32 = 0.15;
+
+/// Where input is currently going, which decides the actions that can activate.
+/// Follows the focus, see `update_actions`.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub enum ActionContext {
+    /// The player has focus and is playing.
+    Gameplay,
+
+    /// Nothing has focus, a menu is open.
+    #[default]
+    Menu,
+
+    /// The chat box has focus.
+    Chat,
+}
+
+impl ActionContext {
+    /// Every context, for actions that are never disabled.
+    pub const ALL: [Self; 3] = [Self::Gameplay, Self::Menu, Self::Chat];
+}
 
 #[derive(Resource, Default)]
 pub struct Actions {
     bindings: FxHashMap<String, Binding>,
+    context: ActionContext,
 }
 
 impl Actions {
@@ -27,14 +63,20 @@ impl Actions {
         }
     }
 
-    /// Add a new binding with the given buttons.
-    pub fn add(&mut self, label: impl Into<String>, buttons: impl IntoIterator<Item = Button>) {
+    /// Add a new binding with the given buttons, that can only activate in these contexts.
+    pub fn add(
+        &mut self,
+        label: impl Into<String>,
+        contexts: &[ActionContext],
+        buttons: impl IntoIterator<Item = Button>,
+    ) {
         let buttons = buttons.into_iter().collect::<Vec<_>>();
         self.bindings.insert(
             label.into(),
             Binding {
                 default: buttons.clone(),
                 custom: buttons,
+                contexts: contexts.to_vec(),
                 triggers: Vec::new(),
                 time_active: 0,
                 prev_time: 0,
@@ -42,6 +84,89 @@ impl Actions {
         );
     }
 
+    /// The context actions are currently activated in.
+    pub fn context(&self) -> ActionContext {
+        self.context
+    }
+
+    /// Replace the buttons bound to an action.
+    pub fn set_buttons(
+        &mut self,
+        label: impl AsRef<str>,
+        buttons: impl IntoIterator<Item = Button>,
+    ) {
+        if let Some(binding) = self.get_mut(label.as_ref()) {
+            binding.custom = buttons.into_iter().collect();
+        }
+    }
+
+    /// Bind a button to an action in place of the buttons of the same device,
+    /// so rebinding a key keeps the gamepad button and the other way around.
+    pub fn rebind(&mut self, label: impl AsRef<str>, button: Button) {
+        if let Some(binding) = self.get_mut(label.as_ref()) {
+            binding
+                .custom
+                .retain(|other| other.is_gamepad() != button.is_gamepad());
+            binding.custom.push(button);
+        }
+    }
+
+    /// Restore the default buttons of an action.
+    pub fn reset_buttons(&mut self, label: impl AsRef<str>) {
+        if let Some(binding) = self.get_mut(label.as_ref()) {
+            binding.custom = binding.default.clone();
+        }
+    }
+
+    /// Get the buttons currently bound to an action.
+    pub fn buttons(&self, label: impl AsRef<str>) -> &[Button] {
+        self.get(label.as_ref())
+            .map_or(&[], |binding| binding.custom.as_slice())
+    }
+
+    /// Restore the default buttons of every action.
+    pub fn reset_all(&mut self) {
+        for binding in self.bindings.values_mut() {
+            binding.custom = binding.default.clone();
+        }
+    }
+
+    /// Names of every action, sorted.
+    pub fn labels(&self) -> Vec<&str> {
+        let mut labels = self.bindings.keys().map(|s| s.as_str()).collect::<Vec<_>>();
+        labels.sort_unstable();
+        labels
+    }
+
+    /// Whether any button of this action is also bound to another action
+    /// that can activate in the same context.
+    pub fn has_conflict(&self, label: impl AsRef<str>) -> bool {
+        let label = label.as_ref();
+        let Some(this) = self.bindings.get(label) else {
+            return false;
+        };
+
+        self.bindings.iter().any(|(other, binding)| {
+            other != label
+                && binding
+                    .contexts
+                    .iter()
+                    .any(|ctx| this.contexts.contains(ctx))
+                && binding
+                    .custom
+                    .iter()
+                    .any(|button| this.custom.contains(button))
+        })
+    }
+
+    /// Iterate the actions whose buttons differ from their defaults.
+    pub fn customized(&self) -> impl Iterator<Item = (&str, &[Button])> {
+        self.bindings
+            .iter()
+            .filter(|(_, binding)| binding.custom != binding.default)
+            .map(|(label, binding)| (label.as_str(), binding.custom.as_slice()))
+    }
+
     pub fn add_trigger(&mut self, label: impl AsRef<str>, system: SystemId) {
         if let Some(binding) = self.get_mut(label.as_ref()) {
             binding.triggers.push(system);
@@ -72,6 +197,8 @@ impl Actions {
 pub struct Binding {
     default: Vec<Button>,
     custom: Vec<Button>,
+    /// Contexts the action can activate in.
+    contexts: Vec<ActionContext>,
     triggers: Vec<SystemId>,
     time_active: u64,
     prev_time: u64,
@@ -91,10 +218,61 @@ impl Binding {
     }
 }
 
-#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
 pub enum Button {
     Key(KeyCode),
     Mouse(MouseButton),
+    Gamepad(GamepadButton),
+
+    /// A gamepad axis pushed past `AXIS_THRESHOLD` in the positive or negative direction.
+    Axis(GamepadAxis, bool),
+}
+
+impl Button {
+    /// Whether the button is on a gamepad, instead of the keyboard or mouse.
+    pub fn is_gamepad(&self) -> bool {
+        matches!(self, Self::Gamepad(_) | Self::Axis(..))
+    }
+
+    /// Whether the button is held down on the keyboard, mouse or any gamepad.
+    fn is_pressed(
+        &self,
+        keys: &ButtonInput<KeyCode>,
+        mouse: &ButtonInput<MouseButton>,
+        gamepads: &Query<&Gamepad>,
+    ) -> bool {
+        match self {
+            Self::Key(code) => keys.pressed(*code),
+            Self::Mouse(button) => mouse.pressed(*button),
+            Self::Gamepad(button) => gamepads.iter().any(|pad| pad.pressed(*button)),
+            Self::Axis(axis, positive) => gamepads.iter().any(|pad| {
+                let value = pad.get(*axis).unwrap_or(0.0);
+                if *positive {
+                    value > AXIS_THRESHOLD
+                } else {
+                    value < -AXIS_THRESHOLD
+                }
+            }),
+        }
+    }
+
+    /// Name of the button, as shown in the controls menu.
+    pub fn name(&self) -> String {
+        match self {
+            Self::Key(code) => {
+                let name = format!("{code:?}");
+                match name.strip_prefix("Key").or_else(|| name.strip_prefix("Digit")) {
+                    Some(short) => short.to_string(),
+                    None => name,
+                }
+            }
+            Self::Mouse(button) => format!("Mouse {button:?}"),
+            Self::Gamepad(button) => format!("Pad {button:?}"),
+            Self::Axis(axis, positive) => {
+                format!("Pad {axis:?}{}", if *positive { "+" } else { "-" })
+            }
+        }
+    }
 }
 
 impl From<KeyCode> for Button {
@@ -109,26 +287,89 @@ impl From<MouseButton> for Button {
     }
 }
 
+impl From<GamepadButton> for Button {
+    fn from(value: GamepadButton) -> Self {
+        Self::Gamepad(value)
+    }
+}
+
+/// Position of the gamepad sticks with the dead zone removed, in the range [-1, 1].
+/// Y is up on both sticks.
+#[derive(Resource, Default)]
+pub struct GamepadSticks {
+    pub left: Vec2,
+    pub right: Vec2,
+}
+
+/// Read the sticks of the first gamepad that has one pushed.
+pub fn update_gamepad_sticks(gamepads: Query<&Gamepad>, mut sticks: ResMut<GamepadSticks>) {
+    let stick = |pad: &Gamepad, x: GamepadAxis, y: GamepadAxis| {
+        let value = Vec2::new(pad.get(x).unwrap_or(0.0), pad.get(y).unwrap_or(0.0));
+        apply_dead_zone(value)
+    };
+
+    sticks.left = Vec2::ZERO;
+    sticks.right = Vec2::ZERO;
+    for pad in &gamepads {
+        let left = stick(pad, GamepadAxis::LeftStickX, GamepadAxis::LeftStickY);
+        let right = stick(pad, GamepadAxis::RightStickX, GamepadAxis::RightStickY);
+        if left != Vec2::ZERO || right != Vec2::ZERO {
+            sticks.left = left;
+            sticks.right = right;
+            break;
+        }
+    }
+}
+
+/// Zero within the dead zone, and scaled so the edge of the dead zone is zero
+/// instead of jumping to it.
+fn apply_dead_zone(stick: Vec2) -> Vec2 {
+    let len = stick.length();
+    if len <= STICK_DEAD_ZONE {
+        return Vec2::ZERO;
+    }
+
+    let scaled = ((len - STICK_DEAD_ZONE) / (1.0 - STICK_DEAD_ZONE)).min(1.0);
+    stick / len * scaled
+}
+
+/// Activate the actions of the current context whose buttons are held,
+/// and run the handlers of those that just activated.
 pub fn update_actions(
     mut actions: ResMut<Actions>,
     mut commands: Commands,
+    focus: Focus,
+    chat: Query<(), (With<ChatContainer>, With<Focused>)>,
     keys: Res<ButtonInput<KeyCode>>,
     mouse: Res<ButtonInput<MouseButton>>,
+    gamepads: Query<&Gamepad>,
 ) {
+    let context = if focus.player_has_focus() {
+        ActionContext::Gameplay
+    } else if !chat.is_empty() {
+        ActionContext::Chat
+    } else {
+        ActionContext::Menu
+    };
+
+    if actions.context != context {
+        info!("Action context changed to {context:?}.");
+        actions.context = context;
+    }
+
     for binding in actions.bindings.values_mut() {
         binding.prev_time = binding.time_active;
 
-        if !binding.custom.iter().any(|button| {
-            if match button {
-                Button::Key(code) => keys.pressed(*code),
-                Button::Mouse(btn) => mouse.pressed(*btn),
-            } {
-                binding.time_active += 1;
-                true
-            } else {
-                false
-            }
-        }) {
+        // actions outside the context are released, even while their buttons are held.
+        let pressed = binding.contexts.contains(&context)
+            && binding
+                .custom
+                .iter()
+                .any(|button| button.is_pressed(&keys, &mouse, &gamepads));
+
+        if pressed {
+            binding.time_active += 1;
+        } else {
             binding.time_active = 0;
         }
 
@@ -170,7 +411,13 @@ pub fn handle_close_menu_transitions(
     curr_menu: Res<State<Menu>>,
     mut next_menu: ResMut<NextState<Menu>>,
     app_state: Res<State<AppState>>,
+    capture: Res<RebindCapture>,
 ) {
+    // escape cancels the rebind instead, see `capture_rebind`.
+    if capture.is_capturing() {
+        return;
+    }
+
     match app_state.get() {
         AppState::InMenus => {
             // App is in-menus.
@@ -185,10 +432,26 @@ pub fn handle_close_menu_transitions(
                         // return to Title Menu
                         next_menu.set(Menu::Title);
                     }
+                    Menu::CreateWorld | Menu::EditWorld => {
+                        // return to WorldSelect Menu
+                        next_menu.set(Menu::WorldSelect);
+                    }
                     Menu::ServerSelect => {
                         // return to Title Menu
                         next_menu.set(Menu::Title);
                     }
+                    Menu::Disconnected => {
+                        // return to Title Menu
+                        next_menu.set(Menu::Title);
+                    }
+                    Menu::Options => {
+                        // return to Title Menu
+                        next_menu.set(Menu::Title);
+                    }
+                    Menu::Controls | Menu::ResourcePacks => {
+                        // return to Options Menu
+                        next_menu.set(Menu::Options);
+                    }
                     other => {
                         error!(
                             "[C414] Menu '{other:?}' not meant to be reachable while in the title menu."
@@ -224,6 +487,17 @@ pub fn handle_close_menu_transitions(
                         // return to Pause menu.
                         next_menu.set(Menu::Pause);
                     }
+                    Menu::Controls | Menu::ResourcePacks => {
+                        // return to Options menu.
+                        next_menu.set(Menu::Options);
+                    }
+                    Menu::BlockPalette => {
+                        // close the palette without picking a block.
+                        next_menu.set(Menu::None);
+                        focus.to_player();
+                    }
+                    // can't leave until loading finishes, see `loading::finish_loading`.
+                    Menu::Loading => {}
                     Menu::None => {}
                     // menu not meant to be reachable while in-game.
                     other => {
@@ -0,0 +1,23 @@
+//! The client as a library, so the game (see main.rs) and the benchmarks
+//! can share its modules.
+
+pub mod audio;
+pub mod crash;
+pub mod diagnostics;
+pub mod events;
+pub mod focus;
+pub mod input;
+pub mod locale;
+pub mod net;
+pub mod packs;
+pub mod player;
+pub mod recipes;
+pub mod render;
+pub mod screenshot;
+pub mod sequences;
+pub mod settings;
+pub mod singleplayer;
+pub mod states;
+pub mod ui;
+pub mod window;
+pub mod world;
@@ -0,0 +1,33 @@
+use bevy::prelude::*;
+use data::{fs::packs::AssetPackReader, info::RootPath, locale::Locale};
+
+use crate::{packs::data_dirs, settings::Settings};
+
+/// Read the selected language again, should run when the settings or mounted packs change.
+/// Keys missing from the language are looked up in its fallbacks, see `Locale::load`.
+pub fn load_locale(
+    root: Res<RootPath>,
+    settings: Res<Settings>,
+    packs: Res<AssetPackReader>,
+    mut locale: ResMut<Locale>,
+) {
+    if !packs.is_changed() && settings.language == locale.language() {
+        return;
+    }
+
+    let dirs = data_dirs(&root, &packs);
+    let (loaded, errors) = Locale::load(&dirs, &settings.language);
+    for err in errors {
+        error!("{err}");
+    }
+
+    if !loaded.languages().contains(&settings.language) {
+        warn!(
+            "[C210] Language '{}' isn't in the assets or any resource pack.",
+            settings.language
+        );
+    }
+
+    info!("Loaded language '{}'.", loaded.language());
+    *locale = loaded;
+}
@@ -1,73 +1,121 @@
-use bevy::{prelude::*, window::WindowMode};
+use bevy::{log::LogPlugin, prelude::*, ui::UiSystems, window::WindowMode};
 use data::{
     OpenvoxelDataPlugin,
+    blocks::Block,
+    blockstates::BlockState,
+    fs::packs::AssetPackReader,
+    items::{self, Item},
+    locale::Locale,
+    recipes::Recipe,
     registry::Registry,
-    sequence::{SequenceEnded, Sequences, SequencesPlugin},
+    sequence::{Sequence, SequenceEnded, Sequences, SequencesPlugin},
+    tags::Tags,
 };
 use protocol::packet::SentBy;
 
-use crate::{
-    events::{PlayerConnected, SyncRegistries},
-    focus::{Focus, PlayerFocusedSet, PlayerNotFocusedSet},
-    input::{Actions, Button},
-    net::channel::Channel,
-    player::Player,
-    render::atlases::{BlockTextureMeta, TextureArrayPlugin},
-    sequences::{connect::ConnectSeq, starting::StartupSeq},
-    settings::Settings,
-    states::{AppState, CursorMode, IntoSetConfigs},
-    ui::menus::Menu,
-    window::WindowState,
+use client::{
+    audio, crash, diagnostics,
+    events::{
+        self, BlockEffect, BlockUpdated, ChunkUnloaded, Disconnected, PlayerConnected,
+        RegistryRemapped, SyncRegistries,
+    },
+    focus::{self, Focus, PlayerFocusedSet, PlayerNotFocusedSet},
+    input::{self, ActionContext, Actions, Button},
+    locale,
+    net::{self, channel::Channel},
+    packs,
+    player::{self, Player},
+    recipes,
+    render::{
+        self,
+        atlases::{BlockTextureMeta, TextureArray, TextureArrayPlugin},
+    },
+    screenshot,
+    sequences::{self, connect::ConnectSeq, starting::StartupSeq},
+    settings::{self, Settings},
+    singleplayer,
+    states::{self, AppState, CursorMode, IntoSetConfigs},
+    ui::{
+        self, UiVars,
+        hud::{DrawHudWidget, HudSlot, HudWidgets},
+        menus::Menu,
+    },
+    window::{self, WindowState},
+    world,
 };
 
-pub mod events;
-pub mod focus;
-pub mod input;
-pub mod net;
-pub mod player;
-pub mod render;
-pub mod sequences;
-pub mod settings;
-pub mod states;
-pub mod ui;
-pub mod window;
-pub mod world;
-
 #[rustfmt::skip]
 fn main() -> AppExit {
     App::new()
         .add_plugins((
+            // before DefaultPlugins, so its own LogPlugin is disabled.
+            settings::logging_plugin(),
             OpenvoxelDataPlugin,
+            packs::ResourcePackPlugin,
             DefaultPlugins
+                .build()
+                .disable::<LogPlugin>()
                 .set(ImagePlugin::default_nearest())
                 .set(WindowPlugin {
                     primary_window: Some(Window {
                         visible: false,
-                        resolution: (1280, 720).into(),
+                        resolution: window::DEFAULT_SIZE.into(),
                         title: "Open Voxel".into(),
                         mode: WindowMode::Windowed,
                         ..default()
                     }),
                     ..default()
                 }),
-            SequencesPlugin::<ConnectSeq>::default(),
+            SequencesPlugin::<ConnectSeq>::default().fail_to(Menu::Disconnected),
             SequencesPlugin::<StartupSeq>::default(),
             MaterialPlugin::<render::chunk::ChunkMaterial>::default(),
-            TextureArrayPlugin::<BlockTextureMeta>::default()
-                .with_file("textures/blocks/stone.png"),
+            MaterialPlugin::<render::chunk::columns::ColumnMaterial>::default(),
+            MaterialPlugin::<render::particles::ParticleMaterial>::default(),
+            TextureArrayPlugin::<BlockTextureMeta>::default(),
+            diagnostics::PerfDiagnosticsPlugin,
         ))
         // initialize resources
         .insert_resource(Time::<Fixed>::from_hz(30.0))
         .insert_resource(::world::World::new(256, -128))
+        .init_resource::<::world::time::WorldTime>()
+        .init_resource::<world::time::SkyDarkness>()
         .init_resource::<Settings>()
         .init_resource::<focus::FocusManager>()
         .init_resource::<input::Actions>()
+        .init_resource::<input::GamepadSticks>()
+        .init_resource::<ui::menus::controls::RebindCapture>()
+        .init_resource::<ui::menus::world_select::EditingWorld>()
         .init_resource::<ui::hint::HintTextContent>()
         .init_resource::<ui::UiVars>()
         .init_resource::<ui::util::UiLabels>()
         .init_resource::<ui::chat::ChatBox>()
+        .init_resource::<ui::hud::HudWidgets>()
+        .init_resource::<ui::hud::HudVisible>()
+        .init_resource::<ui::hud::stats::PlayerStats>()
         .init_resource::<render::chunk::ChunkRenderQueue>()
         .init_resource::<render::chunk::ChunkRenderer>()
+        .init_resource::<render::chunk::ChunkMeshIndex>()
+        .init_resource::<render::chunk::pool::ChunkMeshPool>()
+        .init_resource::<render::chunk::columns::FarColumns>()
+        .init_resource::<render::chunk::occlusion::OcclusionCuller>()
+        .init_resource::<render::particles::Particles>()
+        .init_resource::<render::shaders::ShaderFiles>()
+        .init_resource::<audio::SoundDefinitions>()
+        .init_resource::<Registry<audio::SoundEvent>>()
+        .init_resource::<Registry<Block>>()
+        .init_resource::<Tags<BlockState>>()
+        .init_resource::<world::blocks::BlockDefinitions>()
+        .init_resource::<player::target::TargetedBlock>()
+        .init_resource::<player::interact::HeldItem>()
+        .init_resource::<player::camera::CameraMode>()
+        .init_resource::<player::remote::RemotePlayers>()
+        .init_resource::<player::appearance::Skins>()
+        .init_resource::<player::appearance::RemoteAppearances>()
+        .init_resource::<player::appearance::SentAppearance>()
+        .init_resource::<player::login::PlayerKey>()
+        .init_resource::<player::interact::PendingEdits>()
+        .init_resource::<world::requests::ChunkRequests>()
+        .init_resource::<world::cache::ChunkCache>()
         // initialize states
         .init_state::<AppState>()
         .init_state::<CursorMode>()
@@ -75,27 +123,91 @@ fn main() -> AppExit {
         .init_state::<WindowState>()
         // initialize registries that need to be synchronized with the server on join.
         .init_sync_registry::<Channel>("channels")
+        .insert_resource(items::builtin_items())
+        .init_sync_registry::<Item>("items")
+        .init_sync_registry::<Recipe>("recipes")
+        .init_sync_registry_with_placeholder::<BlockState>("block_states", BlockState::empty)
         // initialize channels for sending/receiving packets.
         .add_channel("player-input", SentBy::Client)
         .add_channel("chunk-data", SentBy::Server)
+        .add_channel("block-edit", SentBy::Client)
+        .add_channel("block-update", SentBy::Server)
+        .add_channel("player-snapshot", SentBy::Server)
+        .add_channel("player-removed", SentBy::Server)
+        .add_channel("player-appearance", SentBy::Both)
+        .add_channel("player-login", SentBy::Client)
+        .add_channel("chat-send", SentBy::Client)
+        .add_channel("chat-message", SentBy::Server)
+        .add_channel("command-completions", SentBy::Server)
+        .add_channel("chunk-unload", SentBy::Server)
+        .add_channel("world-time", SentBy::Server)
+        .add_channel("player-stats", SentBy::Server)
+        .add_channel("chunk-request", SentBy::Client)
+        .add_channel("chunk-revisions", SentBy::Client)
+        .add_channel("chunk-cached", SentBy::Server)
+        .add_channel("multi-block-update", SentBy::Server)
+        .add_channel("player-position", SentBy::Server)
         // add messages
         .add_message::<SyncRegistries>()
+        .add_message::<RegistryRemapped>()
         .add_message::<PlayerConnected>()
+        .add_message::<Disconnected>()
+        .add_message::<ChunkUnloaded>()
+        .add_message::<BlockUpdated>()
+        .add_message::<BlockEffect>()
+        .add_message::<audio::PlaySound>()
         .add_message::<focus::FocusRequested>()
         .add_message::<focus::FocusChanged>()
         .add_message::<ui::button::ButtonClicked>()
+        .add_message::<singleplayer::LaunchSingleplayer>()
         // add keybinds
-        .add_action("forward", [KeyCode::KeyW.into()])
-        .add_action("back", [KeyCode::KeyS.into()])
-        .add_action("left", [KeyCode::KeyA.into()])
-        .add_action("right", [KeyCode::KeyD.into()])
-        .add_action("interact", [MouseButton::Right.into()])
-        .add_action("punch", [MouseButton::Left.into()])
-        .add_action("close-menu", [KeyCode::Escape.into()])
-        .add_action("focus-chatbox", [KeyCode::KeyT.into(), KeyCode::Slash.into()])
+        .add_action("forward", &[ActionContext::Gameplay], [
+            KeyCode::KeyW.into(),
+            Button::Axis(GamepadAxis::LeftStickY, true),
+        ])
+        .add_action("back", &[ActionContext::Gameplay], [
+            KeyCode::KeyS.into(),
+            Button::Axis(GamepadAxis::LeftStickY, false),
+        ])
+        .add_action("left", &[ActionContext::Gameplay], [
+            KeyCode::KeyA.into(),
+            Button::Axis(GamepadAxis::LeftStickX, false),
+        ])
+        .add_action("right", &[ActionContext::Gameplay], [
+            KeyCode::KeyD.into(),
+            Button::Axis(GamepadAxis::LeftStickX, true),
+        ])
+        .add_action("jump", &[ActionContext::Gameplay], [KeyCode::Space.into(), GamepadButton::South.into()])
+        .add_action("descend", &[ActionContext::Gameplay], [KeyCode::KeyC.into(), GamepadButton::East.into()])
+        .add_action("interact", &[ActionContext::Gameplay], [MouseButton::Right.into(), GamepadButton::LeftTrigger2.into()])
+        .add_action("punch", &[ActionContext::Gameplay], [MouseButton::Left.into(), GamepadButton::RightTrigger2.into()])
+        .add_action("sprint", &[ActionContext::Gameplay], [KeyCode::ControlLeft.into(), GamepadButton::LeftThumb.into()])
+        .add_action("sneak", &[ActionContext::Gameplay], [KeyCode::ShiftLeft.into(), GamepadButton::RightThumb.into()])
+        .add_action("toggle-fly", &[ActionContext::Gameplay], [KeyCode::KeyF.into(), GamepadButton::DPadUp.into()])
+        .add_action("camera-mode", &[ActionContext::Gameplay], [KeyCode::F5.into(), GamepadButton::Select.into()])
+        .add_action("close-menu", &ActionContext::ALL, [KeyCode::Escape.into(), GamepadButton::Start.into()])
+        .add_action("focus-chatbox", &[ActionContext::Gameplay], [KeyCode::KeyT.into(), KeyCode::Slash.into()])
+        .add_action("screenshot", &ActionContext::ALL, [KeyCode::F2.into()])
+        .add_action("toggle-hud", &ActionContext::ALL, [KeyCode::F1.into()])
+        .add_action("toggle-perf", &ActionContext::ALL, [KeyCode::F4.into()])
+        .add_action("open-palette", &[ActionContext::Gameplay], [KeyCode::KeyE.into(), GamepadButton::North.into()])
+        .add_action("toggle-fullscreen", &ActionContext::ALL, [KeyCode::F11.into()])
         // add action handlers
         .add_action_handler("close-menu", input::handle_close_menu_transitions)
         .add_action_handler("focus-chatbox", ui::chat::handle_focus_chatbox)
+        .add_action_handler("punch", player::interact::handle_punch)
+        .add_action_handler("interact", player::interact::handle_interact)
+        .add_action_handler("toggle-fly", player::physics::handle_toggle_fly)
+        .add_action_handler("camera-mode", player::camera::handle_cycle_camera_mode)
+        .add_action_handler("screenshot", screenshot::handle_screenshot)
+        .add_action_handler("toggle-hud", ui::hud::handle_toggle_hud)
+        .add_action_handler("toggle-perf", diagnostics::handle_toggle_perf_overlay)
+        .add_action_handler("open-palette", ui::menus::palette::handle_open_palette)
+        .add_action_handler("toggle-fullscreen", window::handle_toggle_fullscreen)
+        // add hud widgets
+        .add_hud_widget(HudSlot::Center, ui::hud::crosshair::draw)
+        .add_hud_widget(HudSlot::BottomCenter, ui::hud::stats::draw_health)
+        .add_hud_widget(HudSlot::BottomCenter, ui::hud::stats::draw_hunger)
         // configure system sets
         .configure_set_all(PlayerFocusedSet)
         .configure_set_all(PlayerNotFocusedSet)
@@ -103,6 +215,11 @@ fn main() -> AppExit {
         .add_systems(Startup, (
             player::spawn_player,
             ui::UiVars::load,
+            settings::load_settings,
+            player::login::load_player_key,
+            audio::register_sounds,
+            crash::install_crash_reporter,
+            crash::find_crash_report,
         ))
         // Add update systems
         .add_systems(First, (
@@ -110,8 +227,34 @@ fn main() -> AppExit {
             ui::util::on_ui_label_remove,
         ))
         .add_systems(PreUpdate, (
-            input::update_actions,
+            (
+                input::update_gamepad_sticks,
+                input::update_actions,
+            ),
             net::update::client_recv,
+            ui::chat::recv_command_completions
+                .after(net::update::client_recv),
+            (
+                packs::apply_resource_packs
+                    .run_if(resource_changed::<Settings>),
+                (
+                    locale::load_locale,
+                    ui::apply_ui_settings,
+                    ui::menus::redraw_menu
+                        .run_if(resource_changed::<Locale>.or(resource_changed::<UiVars>)),
+                    ui::hud::redraw_hud
+                        .run_if(resource_changed::<UiVars>),
+                ).chain().run_if(resource_changed::<Settings>.or(resource_changed::<AssetPackReader>)),
+                world::blocks::load_block_definitions
+                    .run_if(resource_changed::<AssetPackReader>),
+                recipes::load_recipes
+                    .run_if(resource_changed::<AssetPackReader>),
+                player::appearance::load_skins
+                    .run_if(resource_changed::<AssetPackReader>),
+                render::shaders::reload_shaders,
+                world::blocks::collect_block_textures
+                    .run_if(resource_changed::<world::blocks::BlockDefinitions>),
+            ).chain(),
         ))
         .add_systems(Update, (
             close_on_q,
@@ -123,33 +266,192 @@ fn main() -> AppExit {
             ui::hint::insert_hint_text_visuals,
             ui::hint::update_hint_text_entities,
             (
-                world::io::recv_chunk_data,
+                ui::elements::text_field::select_text_fields,
+                ui::elements::text_field::update_text_fields,
+            ).chain(),
+            (
+                ui::menus::world_select::handle_world_entry_clicks
+                    .run_if(in_state(Menu::WorldSelect)),
+                ui::menus::world_select::handle_create_world_clicks
+                    .run_if(in_state(Menu::CreateWorld)),
+                ui::menus::world_select::handle_edit_world_clicks
+                    .run_if(in_state(Menu::EditWorld)),
+                ui::menus::disconnected::handle_reconnect_clicks
+                    .run_if(in_state(Menu::Disconnected)),
+                singleplayer::launch_singleplayer,
+            ).chain(),
+            (
+                ui::menus::connecting::handle_cancel_clicks
+                    .run_if(in_state(Menu::Connecting)),
+                ui::menus::disconnected::forward_connect_failures,
+                ui::menus::disconnected::handle_disconnect,
+            ).chain(),
+            (
+                ui::menus::options::handle_option_clicks
+                    .run_if(in_state(Menu::Options)),
+                ui::menus::pause::handle_pause_clicks
+                    .run_if(in_state(Menu::Pause)),
+                ui::menus::packs::handle_pack_clicks
+                    .run_if(in_state(Menu::ResourcePacks)),
+                crash::handle_crash_dialog_clicks
+                    .run_if(in_state(Menu::Title)),
+            ),
+            (
+                ui::menus::controls::handle_binding_clicks,
+                ui::menus::controls::capture_rebind,
+                ui::menus::controls::update_binding_entries,
+            ).chain().run_if(in_state(Menu::Controls)),
+            (
+                ui::menus::palette::handle_palette_clicks,
+                ui::menus::palette::filter_palette_entries,
+                ui::menus::palette::scroll_palette,
+            ).chain().run_if(in_state(Menu::BlockPalette)),
+            (
+                settings::apply_settings,
+                audio::apply_sound_volumes,
+                render::chunk::apply_mesher_settings,
+            ).run_if(resource_changed::<Settings>),
+            (
+                (
+                    world::evict::recv_chunk_unloads,
+                    world::io::recv_chunk_data,
+                    world::cache::recv_cached_chunks,
+                    world::evict::evict_distant_chunks,
+                    world::cache::send_chunk_revisions,
+                    world::requests::request_missing_chunks,
+                ).chain(),
+                (
+                    world::time::recv_world_time,
+                    world::time::advance_world_time,
+                    world::time::update_sky_darkness,
+                    render::skybox::update_skybox,
+                    render::chunk::apply_sky_darkness,
+                    render::chunk::columns::apply_column_sky_darkness,
+                ).chain(),
+                (
+                    (
+                        audio::play_block_sounds,
+                        audio::play_footsteps,
+                        audio::play_sounds,
+                    ).chain(),
+                    (
+                        render::particles::emit_block_particles,
+                        render::particles::update_particles,
+                        render::particles::write_particle_mesh,
+                    ).chain(),
+                ).after(player::interact::expire_pending_edits)
+                    .after(player::camera::update_camera_position),
+                player::interact::recv_block_updates
+                    .after(world::io::recv_chunk_data),
+                player::interact::recv_multi_block_updates
+                    .after(player::interact::recv_block_updates),
+                player::interact::expire_pending_edits
+                    .after(player::interact::recv_block_updates),
                 player::player_apply_look_deltas,
                 player::player_compute_look_deltas
                     .before(player::player_apply_look_deltas)
                     .run_if(in_state(CursorMode::Locked)),
-                player::player_apply_move_deltas,
-                player::player_compute_move_deltas
-                    .before(player::player_apply_move_deltas),
+                player::physics::player_apply_physics
+                    .run_if(not(in_state(Menu::Loading))),
+                player::recv_position_corrections
+                    .before(player::physics::player_apply_physics),
+                (
+                    player::player_compute_move_deltas,
+                    player::player_clear_input
+                        .in_set(PlayerNotFocusedSet),
+                ).before(player::physics::player_apply_physics)
+                    .before(player::player_apply_look_deltas),
+                player::movement::player_update_movement_state
+                    .before(player::physics::player_apply_physics),
+                player::target::update_targeted_block
+                    .after(player::player_apply_look_deltas)
+                    .after(player::physics::player_apply_physics),
+                render::highlight::update_block_highlight
+                    .after(player::target::update_targeted_block),
+                render::chunk::occlusion::cull_occluded_subchunks
+                    .after(player::camera::update_camera_position),
+                player::camera::update_camera_position
+                    .after(player::player_apply_look_deltas)
+                    .after(player::physics::player_apply_physics),
+                player::camera::update_player_model_visibility
+                    .run_if(resource_changed::<player::camera::CameraMode>),
+                (
+                    player::remote::recv_player_snapshots,
+                    player::remote::despawn_remote_players
+                        .after(player::remote::recv_player_snapshots),
+                    player::remote::interpolate_remote_players
+                        .after(player::remote::despawn_remote_players),
+                    ui::world_text::update_world_labels
+                        .after(player::remote::interpolate_remote_players),
+                    player::login::send_player_login
+                        .before(player::appearance::send_player_appearance),
+                    player::appearance::send_player_appearance,
+                    player::appearance::recv_player_appearances,
+                    player::remote::apply_remote_appearances
+                        .after(player::appearance::recv_player_appearances)
+                        .after(player::remote::despawn_remote_players),
+                ),
+                (
+                    ui::menus::loading::update_loading_progress,
+                    ui::menus::loading::finish_loading,
+                ).chain().run_if(in_state(Menu::Loading)),
+                (
+                    (
+                        ui::chat::handle_chat_clicks,
+                        ui::chat::update_chatbox,
+                        ui::chat::recv_chat_messages,
+                        ui::chat::scroll_chat,
+                        ui::chat::update_chat_hover,
+                        ui::chat::fade_chat_lines,
+                    ).chain(),
+                    (
+                        ui::hud::stats::recv_player_stats,
+                        ui::hud::stats::update_stat_bars
+                            .run_if(resource_changed::<ui::hud::stats::PlayerStats>),
+                    ).chain(),
+                    (
+                        diagnostics::measure_chunk_diagnostics,
+                        diagnostics::update_perf_overlay,
+                    ).chain(),
+                ),
             ).run_if(in_state(AppState::InGame)),
             sequences::connect::establish_initial_connection
                 .run_if(in_state(ConnectSeq::Establishing)),
             sequences::connect::authenticate_connection
                 .run_if(in_state(ConnectSeq::Authenticating)),
-            sequences::connect::synchronize_registries
-                .run_if(in_state(ConnectSeq::Syncronizing)),
-            render::skybox::load_skybox_assets
-                .run_if(in_state(StartupSeq::LoadTextures))
+            (
+                world::blocks::register_block_states
+                    .run_if(
+                        resource_exists_and_changed::<TextureArray<BlockTextureMeta>>
+                            .or(on_message::<RegistryRemapped>),
+                    ),
+                world::blocks::load_block_tags
+                    .run_if(resource_changed::<Registry<Block>>),
+            ).chain(),
         ))
         .add_systems(FixedUpdate, (
             (
-                ui::chat::update_chatbox,
                 player::send_player_input_update,
-                render::chunk::render_chunks
+                render::chunk::render_chunks,
+                render::chunk::despawn_unloaded_chunk_meshes
+                    .before(render::chunk::render_chunks),
+                render::chunk::queue_block_update_remesh
+                    .before(render::chunk::render_chunks),
+                render::chunk::queue_blend_resort
+                    .before(render::chunk::render_chunks),
+                render::chunk::queue_lod_changes
+                    .before(render::chunk::render_chunks),
+                render::chunk::columns::build_far_columns
+                    .after(render::chunk::render_chunks),
             ).run_if(in_state(AppState::InGame)),
         ))
         .add_systems(PostUpdate, (
             net::update::clear_channels,
+            net::update::log_packet_trace,
+            ui::apply_text_size_floor
+                .before(UiSystems::Prepare),
+            window::record_window_state
+                .run_if(in_state(WindowState::Ready)),
         ))
         .add_systems(FixedPostUpdate, (
             net::update::client_flush,
@@ -157,16 +459,22 @@ fn main() -> AppExit {
         .add_systems(Last, (
             focus::update_focus_manager,
             ui::menus::starting::update_progress_bar,
+            crash::update_crash_context,
             (
                 data::util::transition(Menu::Title),
+                data::util::transition(AppState::InMenus),
             ).run_if(on_message::<SequenceEnded<StartupSeq>>),
             (
                 data::util::transition(AppState::InGame),
             ).run_if(on_message::<SequenceEnded<ConnectSeq>>),
+            // keep the window state recorded since the settings were last saved.
+            settings::save_settings
+                .run_if(on_message::<AppExit>),
         ))
         // Add Transitional Systems
         .add_systems(OnEnter(Menu::Connecting), (
             trigger_connect_sequence,
+            ui::menus::connecting::draw,
         ))
         .add_systems(OnEnter(Menu::Starting), (
             data::util::transition(StartupSeq::first())
@@ -175,14 +483,75 @@ fn main() -> AppExit {
         ))
         .add_systems(OnEnter(Menu::Title), (
             ui::menus::title::draw,
+            crash::draw_crash_dialog,
+        ))
+        .add_systems(OnEnter(Menu::WorldSelect), (
+            ui::menus::world_select::draw,
+        ))
+        .add_systems(OnEnter(Menu::Loading), (
+            ui::menus::loading::draw,
+        ))
+        .add_systems(OnEnter(Menu::Disconnected), (
+            ui::menus::disconnected::draw,
+        ))
+        .add_systems(OnEnter(Menu::CreateWorld), (
+            ui::menus::world_select::draw_create_world,
+        ))
+        .add_systems(OnEnter(Menu::EditWorld), (
+            ui::menus::world_select::draw_edit_world,
+        ))
+        .add_systems(OnEnter(Menu::Pause), (
+            ui::menus::pause::draw,
+        ))
+        .add_systems(OnEnter(Menu::Options), (
+            ui::menus::options::draw,
+        ))
+        .add_systems(OnExit(Menu::Options), (
+            settings::save_settings,
+        ))
+        .add_systems(OnEnter(Menu::Controls), (
+            ui::menus::controls::draw,
+        ))
+        .add_systems(OnExit(Menu::Controls), (
+            ui::menus::controls::RebindCapture::clear,
+            settings::save_settings,
+        ))
+        .add_systems(OnEnter(Menu::ResourcePacks), (
+            ui::menus::packs::draw,
+        ))
+        .add_systems(OnExit(Menu::ResourcePacks), (
+            settings::save_settings,
+        ))
+        .add_systems(OnEnter(Menu::BlockPalette), (
+            ui::menus::palette::draw,
         ))
         .add_systems(OnEnter(AppState::InGame), (
             player::on_connect_success,
+            world::disk_cache::open_disk_cache,
+            ui::menus::loading::begin_loading,
             render::skybox::spawn_skybox,
+            render::highlight::spawn_block_highlight,
+            render::particles::spawn_particle_mesh,
+            audio::spawn_ambient_sound,
             ui::chat::draw_chatbox,
+            ui::hud::draw_hud,
         ))
         .add_systems(OnExit(AppState::InGame), (
             render::skybox::despawn_skybox,
+            render::highlight::despawn_block_highlight,
+            render::particles::despawn_particle_mesh,
+            audio::despawn_ambient_sound,
+            player::remote::despawn_all_remote_players,
+            player::appearance::reset_appearances,
+            player::login::reset_player_login,
+            render::chunk::despawn_all_chunk_meshes,
+            singleplayer::stop_singleplayer,
+            ui::chat::reset_chatbox,
+            ui::hud::stats::reset_player_stats,
+            world::evict::clear_world,
+            world::time::reset_world_time,
+            world::requests::reset_chunk_requests,
+            world::cache::clear_chunk_cache,
         ))
         .add_systems(OnEnter(CursorMode::Normal), window::apply_cursor_changes)
         .add_systems(OnEnter(CursorMode::Locked), window::apply_cursor_changes)
@@ -190,9 +559,11 @@ fn main() -> AppExit {
 }
 
 pub trait AppExt {
+    /// Add an action that can only activate in these contexts.
     fn add_action(
         &mut self,
         action: impl AsRef<str>,
+        contexts: &[ActionContext],
         buttons: impl IntoIterator<Item = Button>,
     ) -> &mut Self;
 
@@ -212,19 +583,35 @@ pub trait AppExt {
     fn init_sync_registry<T>(&mut self, name: impl Into<String>) -> &mut Self
     where
         T: Send + Sync + 'static;
+
+    /// Initialize a registry that is synchronized with the server on connection, where
+    /// entries the server has and the client doesn't are filled with a placeholder instead
+    /// of failing the connection. Used for registries whose ids the server keeps for
+    /// entries that are gone, like block states saved in its region files.
+    fn init_sync_registry_with_placeholder<T>(
+        &mut self,
+        name: impl Into<String>,
+        placeholder: fn() -> T,
+    ) -> &mut Self
+    where
+        T: Send + Sync + 'static;
+
+    /// Add a widget that is drawn into a slot of the HUD when joining a game.
+    fn add_hud_widget(&mut self, slot: HudSlot, draw: DrawHudWidget) -> &mut Self;
 }
 
 impl AppExt for App {
     fn add_action(
         &mut self,
         action: impl AsRef<str>,
+        contexts: &[ActionContext],
         buttons: impl IntoIterator<Item = Button>,
     ) -> &mut Self {
         self.main_mut()
             .world_mut()
             .get_resource_mut::<Actions>()
             .unwrap()
-            .add(action.as_ref(), buttons);
+            .add(action.as_ref(), contexts, buttons);
         self
     }
 
@@ -251,6 +638,15 @@ impl AppExt for App {
             .configure_sets(PostUpdate, set.cfg())
     }
 
+    fn add_hud_widget(&mut self, slot: HudSlot, draw: DrawHudWidget) -> &mut Self {
+        self.main_mut()
+            .world_mut()
+            .get_resource_mut::<HudWidgets>()
+            .unwrap()
+            .add(slot, draw);
+        self
+    }
+
     fn add_channel(&mut self, name: impl Into<String>, sent_by: SentBy) -> &mut Self {
         let name = name.into();
         self.main_mut()
@@ -267,19 +663,43 @@ impl AppExt for App {
     where
         T: Send + Sync + 'static,
     {
-        // let name = name.into();
-        // let f = move |mut evs: MessageReader<SyncRegistries>, mut registry: ResMut<Registry<T>>| {
-        //     for ev in evs.read() {
-        //         let to = ev.payload.registries.get(&*name).unwrap();
-        //         registry.make_compliant(to).unwrap();
-        //     }
-        // };
+        add_sync_registry::<T>(self, name.into(), None)
+    }
 
-        self.init_resource::<Registry<T>>()
-        // .add_systems(OnEnter(ConnectSeq::Syncronizing), f)
+    fn init_sync_registry_with_placeholder<T>(
+        &mut self,
+        name: impl Into<String>,
+        placeholder: fn() -> T,
+    ) -> &mut Self
+    where
+        T: Send + Sync + 'static,
+    {
+        add_sync_registry(self, name.into(), Some(placeholder))
     }
 }
 
+fn add_sync_registry<T>(app: &mut App, name: String, placeholder: Option<fn() -> T>) -> &mut App
+where
+    T: Send + Sync + 'static,
+{
+    let sync = move |seq: Res<Sequence<ConnectSeq>>,
+                     mut msgs: MessageReader<SyncRegistries>,
+                     mut registry: ResMut<Registry<T>>,
+                     mut remapped: MessageWriter<RegistryRemapped>| {
+        sequences::connect::synchronize_registry(
+            &name,
+            placeholder,
+            &seq,
+            &mut msgs,
+            &mut registry,
+            &mut remapped,
+        );
+    };
+
+    app.init_resource::<Registry<T>>()
+        .add_systems(Update, sync.run_if(in_state(ConnectSeq::Syncronizing)))
+}
+
 fn close_on_q(input: Res<ButtonInput<KeyCode>>, mut exit: MessageWriter<AppExit>) {
     if input.pressed(KeyCode::KeyQ) {
         exit.write(AppExit::Success);
@@ -287,15 +707,21 @@ fn close_on_q(input: Res<ButtonInput<KeyCode>>, mut exit: MessageWriter<AppExit>
 }
 
 fn trigger_connect_sequence(
+    info: Option<Res<sequences::connect::ConnectSeqInfo>>,
+    settings: Res<Settings>,
     mut state: ResMut<NextState<ConnectSeq>>,
     mut commands: Commands,
     mut app_state: ResMut<NextState<AppState>>,
 ) {
     use data::sequence::Sequences;
     use sequences::connect::ConnectSeqInfo;
-    commands.insert_resource(ConnectSeqInfo {
-        addr_string: "127.0.0.1:51423".into(),
-    });
+
+    // singleplayer inserts the address of the integrated server before transitioning.
+    if info.is_none() {
+        commands.insert_resource(ConnectSeqInfo {
+            addr_string: settings.server_address.clone(),
+        });
+    }
     state.set(ConnectSeq::first());
     app_state.set(AppState::InSequence);
 }
@@ -4,29 +4,38 @@ use std::{
     io::{self, Write},
     net::{SocketAddr, TcpStream, ToSocketAddrs, UdpSocket},
     sync::Arc,
+    time::Duration,
 };
 
 use protocol::{
     ChannelId, ExitCode, Packet,
     codec::{TcpDecoder, TcpEncoder, UdpDecoder, UdpEncoder},
     session::Session,
+    trace::{Direction, PacketTrace},
     types::AuthRequest,
 };
 
 pub mod channel;
 pub mod update;
 
+/// How long to wait for the server to accept the TCP connection,
+/// per address the server address resolves to.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
 #[derive(Resource, Default)]
 pub struct Client {
     transport: Option<Transport>,
     packets: Vec<Packet>,
     authenticated: bool,
+
+    /// Packets sent and received, while tracing is on.
+    trace: PacketTrace,
 }
 
 impl Client {
     pub async fn connect(addr: impl ToSocketAddrs) -> io::Result<Self> {
         // establish connection
-        let mut stream = TcpStream::connect(addr)?;
+        let mut stream = connect_with_timeout(addr, CONNECT_TIMEOUT)?;
         stream.set_nonblocking(true)?;
         stream.set_nodelay(true)?;
 
@@ -51,6 +60,7 @@ impl Client {
             )),
             packets: Vec::new(),
             authenticated: false,
+            trace: PacketTrace::default(),
         })
     }
 
@@ -76,13 +86,19 @@ impl Client {
 
     pub fn udp_send(&mut self, channel: ChannelId, payload: impl AsRef<[u8]>) {
         if let Some(transport) = &mut self.transport {
-            transport.udp_send(channel, payload.as_ref());
+            let payload = payload.as_ref();
+            self.trace
+                .record_raw(Direction::Sent, channel, transport.session, payload.len());
+            transport.udp_send(channel, payload);
         }
     }
 
     pub fn tcp_send(&mut self, channel: ChannelId, payload: impl AsRef<[u8]>) {
         if let Some(transport) = &mut self.transport {
-            transport.tcp_send(channel, payload.as_ref())
+            let payload = payload.as_ref();
+            self.trace
+                .record_raw(Direction::Sent, channel, transport.session, payload.len());
+            transport.tcp_send(channel, payload)
         }
     }
 
@@ -101,9 +117,18 @@ impl Client {
             }
         }
 
+        for packet in &self.packets {
+            self.trace.record(Direction::Received, packet);
+        }
+
         Ok(std::mem::take(&mut self.packets))
     }
 
+    /// Logging of the packets sent and received, see `protocol::trace`.
+    pub fn trace_mut(&mut self) -> &mut PacketTrace {
+        &mut self.trace
+    }
+
     pub fn take_packets(&mut self) -> Vec<Packet> {
         std::mem::take(&mut self.packets)
     }
@@ -120,6 +145,24 @@ impl Client {
     }
 }
 
+/// Connect to the first address `addr` resolves to that accepts within the timeout.
+fn connect_with_timeout(addr: impl ToSocketAddrs, timeout: Duration) -> io::Result<TcpStream> {
+    let mut last_err = None;
+    for addr in addr.to_socket_addrs()? {
+        match TcpStream::connect_timeout(&addr, timeout) {
+            Ok(stream) => return Ok(stream),
+            Err(e) => last_err = Some(e),
+        }
+    }
+
+    Err(last_err.unwrap_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "[N443] Server address did not resolve to any socket address.",
+        )
+    }))
+}
+
 pub struct Transport {
     pub tcp_encoder: TcpEncoder,
     pub tcp_decoder: TcpDecoder,
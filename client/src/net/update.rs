@@ -10,13 +10,15 @@ use std::{
 use protocol::{
     ChannelId, ExitCode, Packet,
     codec::{TcpDecoder, TcpEncoder, UdpDecoder, UdpEncoder},
+    exit::ExitStatus,
     packet::SentBy,
     session::Session,
+    trace::MAX_TRACES_PER_SECOND,
     types::{AuthAccepted, AuthRequest},
 };
 
 use crate::{
-    events::{PlayerConnected, SyncRegistries},
+    events::{Disconnected, PlayerConnected, SyncRegistries},
     net::{Client, channel::Channel},
 };
 pub fn client_recv(
@@ -24,9 +26,17 @@ pub fn client_recv(
     mut channels: ResMut<Registry<Channel>>,
     mut sync_msgs: MessageWriter<SyncRegistries>,
     mut connect_msgs: MessageWriter<PlayerConnected>,
+    mut disconnect_msgs: MessageWriter<Disconnected>,
 ) {
     if let Some(client) = &mut client {
-        let mut packets = client.recv().unwrap();
+        let mut packets = match client.recv() {
+            Ok(packets) => packets,
+            Err(exit) => {
+                warn!("[C170] Disconnected from server with exit: '{exit}'");
+                disconnect_msgs.write(Disconnected { exit });
+                return;
+            }
+        };
         for packet in packets.drain(..) {
             match packet.channel {
                 channel if !packet.channel.is_special() => {
@@ -39,8 +49,19 @@ pub fn client_recv(
                     }
                 }
                 ChannelId::SYNC_DATA => {
-                    let payload = serde_json::from_slice(&packet.payload).unwrap();
-                    sync_msgs.write(SyncRegistries { payload });
+                    if let Some(payload) = packet.json() {
+                        sync_msgs.write(SyncRegistries { payload });
+                    } else {
+                        let exit: ExitCode = (
+                            ExitStatus::ProtocolViolation,
+                            "[C172]",
+                            "The registry sync payload couldn't be decoded.",
+                        )
+                            .into();
+                        warn!("[C172] Disconnected from server with exit: '{exit}'");
+                        disconnect_msgs.write(Disconnected { exit });
+                        return;
+                    }
                 }
                 ChannelId::AUTH_REQ => {
                     if !client.authenticated {
@@ -64,9 +85,40 @@ pub fn client_recv(
     }
 }
 
-pub fn client_flush(mut client: Option<ResMut<Client>>) {
-    if let Some(client) = &mut client {
-        client.flush().unwrap()
+pub fn client_flush(
+    mut client: Option<ResMut<Client>>,
+    mut disconnect_msgs: MessageWriter<Disconnected>,
+) {
+    if let Some(client) = &mut client
+        && let Err(exit) = client.flush()
+    {
+        warn!("[C171] Disconnected from server while flushing with exit: '{exit}'");
+        disconnect_msgs.write(Disconnected { exit });
+    }
+}
+
+/// Log the packets traced this frame, with the names of their channels.
+pub fn log_packet_trace(client: Option<ResMut<Client>>, channels: Res<Registry<Channel>>) {
+    let Some(mut client) = client else {
+        return;
+    };
+
+    let trace = client.trace_mut();
+    for traced in trace.drain() {
+        let name = traced
+            .channel
+            .special_name()
+            .or_else(|| channels.get(traced.channel).map(|entry| entry.name))
+            .unwrap_or("unknown");
+        info!(target: "packets", "{}", traced.describe(name));
+    }
+
+    let dropped = trace.take_dropped();
+    if dropped != 0 {
+        info!(
+            target: "packets",
+            "{dropped} packets weren't traced, over the limit of {MAX_TRACES_PER_SECOND} per second."
+        );
     }
 }
 
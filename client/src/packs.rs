@@ -0,0 +1,164 @@
+//! Resource packs, which replace the client's assets with their own.
+//!
+//! Packs are directories in `resourcepacks` with the same layout as the assets
+//! directory, so a pack only needs the files it replaces. The default asset source
+//! reads through the mounted packs before falling back to the assets directory.
+//! Enabled packs are saved in the settings, and when they change the textures that
+//! were already loaded are read again, which rebuilds the texture array.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use bevy::{
+    asset::io::{
+        AssetReader, AssetReaderError, AssetSource, AssetSourceId, ErasedAssetReader, PathStream,
+        Reader, VecReader,
+    },
+    prelude::*,
+};
+use data::{fs::packs::AssetPackReader, info::RootPath};
+
+use crate::{
+    render::atlases::{BlockTextureMeta, TextureArray},
+    settings::Settings,
+};
+
+/// Replaces the default asset source with one that reads through the mounted packs.
+/// Must be added before the `AssetPlugin`.
+pub struct ResourcePackPlugin;
+
+impl Plugin for ResourcePackPlugin {
+    fn build(&self, app: &mut App) {
+        let packs = app
+            .world_mut()
+            .get_resource_or_init::<AssetPackReader>()
+            .clone();
+
+        app.register_asset_source(
+            AssetSourceId::Default,
+            AssetSource::build().with_reader(move || {
+                Box::new(PackAssetReader {
+                    packs: packs.clone(),
+                    fallback: AssetSource::get_default_reader("assets".to_string())(),
+                })
+            }),
+        );
+    }
+}
+
+/// Reads assets from the first mounted pack that has them, or the assets directory.
+/// Directories are only read from the assets directory.
+struct PackAssetReader {
+    packs: AssetPackReader,
+    fallback: Box<dyn ErasedAssetReader>,
+}
+
+impl AssetReader for PackAssetReader {
+    async fn read<'a>(&'a self, path: &'a Path) -> Result<impl Reader + 'a, AssetReaderError> {
+        match self.packs.resolve(path) {
+            Some(file) => read_pack_file(&file),
+            None => self.fallback.read(path).await,
+        }
+    }
+
+    async fn read_meta<'a>(&'a self, path: &'a Path) -> Result<impl Reader + 'a, AssetReaderError> {
+        match self.packs.resolve(meta_path(path)) {
+            Some(file) => read_pack_file(&file),
+            None => self.fallback.read_meta(path).await,
+        }
+    }
+
+    async fn read_directory<'a>(
+        &'a self,
+        path: &'a Path,
+    ) -> Result<Box<PathStream>, AssetReaderError> {
+        self.fallback.read_directory(path).await
+    }
+
+    async fn is_directory<'a>(&'a self, path: &'a Path) -> Result<bool, AssetReaderError> {
+        self.fallback.is_directory(path).await
+    }
+}
+
+fn read_pack_file<'a>(path: &Path) -> Result<Box<dyn Reader + 'a>, AssetReaderError> {
+    let data = fs::read(path).map_err(|e| AssetReaderError::Io(Arc::new(e)))?;
+    Ok(Box::new(VecReader::new(data)))
+}
+
+/// Path of the meta file of an asset, which is the asset path with ".meta" appended.
+fn meta_path(path: &Path) -> PathBuf {
+    let mut meta = path.as_os_str().to_owned();
+    meta.push(".meta");
+    PathBuf::from(meta)
+}
+
+pub fn packs_dir(root: &RootPath) -> PathBuf {
+    root.join("resourcepacks")
+}
+
+/// The client's own assets, which packs are layered over.
+pub fn assets_dir(root: &RootPath) -> PathBuf {
+    root.join("assets")
+}
+
+/// Directories that data files like block definitions are read from,
+/// every mounted pack in order of priority, then the assets directory.
+pub fn data_dirs(root: &RootPath, packs: &AssetPackReader) -> Vec<PathBuf> {
+    let mut dirs = packs
+        .packs()
+        .into_iter()
+        .map(|pack| pack.path)
+        .collect::<Vec<_>>();
+    dirs.push(assets_dir(root));
+    dirs
+}
+
+/// Mount the packs enabled in the settings, highest priority first.
+/// If the mounted packs changed, textures that were already loaded are reloaded.
+pub fn apply_resource_packs(
+    root: Res<RootPath>,
+    settings: Res<Settings>,
+    server: Res<AssetServer>,
+    array: Option<Res<TextureArray<BlockTextureMeta>>>,
+    mut packs: ResMut<AssetPackReader>,
+) {
+    let dir = packs_dir(&root);
+    let enabled = settings
+        .resource_packs
+        .iter()
+        .filter(|name| {
+            let exists = dir.join(name).is_dir();
+            if !exists {
+                warn!(
+                    "[C200] Resource pack '{name}' is enabled, but isn't in: '{}'",
+                    dir.display()
+                );
+            }
+            exists
+        })
+        .collect::<Vec<_>>();
+
+    let mounted = packs.packs();
+    if mounted
+        .iter()
+        .map(|pack| pack.name.as_str())
+        .eq(enabled.iter().map(|name| name.as_str()))
+    {
+        return;
+    }
+
+    packs.unmount_all();
+    for (i, name) in enabled.iter().enumerate() {
+        packs.mount(dir.join(name), i as u32);
+    }
+    info!("Mounted {} resource packs.", enabled.len());
+
+    if let Some(array) = array {
+        for name in array.names() {
+            server.reload(name.to_owned());
+        }
+    }
+}
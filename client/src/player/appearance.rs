@@ -0,0 +1,138 @@
+//! How players look to each other.
+//!
+//! A skin is a directory in `skins` of the assets or a resource pack, with a `head.png`
+//! and a `body.png` drawn on each face of the head and body of the model. The name of the
+//! skin and the colors of the player, chosen in the settings, are sent to the server when
+//! joining. Other players are drawn with their skin when this client has a skin with the
+//! same name, and with their colors otherwise.
+
+use std::fs;
+
+use bevy::prelude::*;
+use data::{fs::packs::AssetPackReader, info::RootPath, registry::Registry};
+use fxhash::FxHashMap;
+use protocol::{session::Session, types::PlayerAppearance};
+
+use crate::{
+    net::{Client, channel::Channel},
+    packs::data_dirs,
+    settings::Settings,
+};
+
+/// Skins in the assets and mounted packs, by the hash of their name.
+#[derive(Resource, Default)]
+pub struct Skins(FxHashMap<u64, String>);
+
+impl Skins {
+    /// Name of the skin with this `PlayerAppearance::skin_hash`.
+    pub fn get(&self, hash: u64) -> Option<&str> {
+        self.0.get(&hash).map(String::as_str)
+    }
+
+    /// Asset path of the texture of a part of the skin with this name.
+    pub fn texture_path(name: &str, part: SkinPart) -> String {
+        let file = match part {
+            SkinPart::Head => "head.png",
+            SkinPart::Body => "body.png",
+        };
+        format!("skins/{name}/{file}")
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum SkinPart {
+    Head,
+    Body,
+}
+
+/// The appearance of other players, as last sent by the server.
+#[derive(Resource, Default)]
+pub struct RemoteAppearances(FxHashMap<Session, PlayerAppearance>);
+
+impl RemoteAppearances {
+    pub fn get(&self, session: Session) -> Option<&PlayerAppearance> {
+        self.0.get(&session)
+    }
+
+    pub fn remove(&mut self, session: Session) -> Option<PlayerAppearance> {
+        self.0.remove(&session)
+    }
+}
+
+/// The appearance last sent to the server, None if nothing was sent since joining.
+#[derive(Resource, Default)]
+pub struct SentAppearance(Option<PlayerAppearance>);
+
+/// Find the skins again, should run when the mounted packs change.
+pub fn load_skins(root: Res<RootPath>, packs: Res<AssetPackReader>, mut skins: ResMut<Skins>) {
+    skins.0.clear();
+    for dir in data_dirs(&root, &packs) {
+        let Ok(entries) = fs::read_dir(dir.join("skins")) else {
+            continue;
+        };
+
+        for entry in entries.flatten() {
+            if entry.path().is_dir()
+                && let Some(name) = entry.file_name().to_str()
+            {
+                let hash = PlayerAppearance::skin_hash(name);
+                skins.0.entry(hash).or_insert_with(|| name.to_owned());
+            }
+        }
+    }
+
+    info!("Found {} skins.", skins.0.len());
+}
+
+/// The appearance of this player, from the settings.
+pub fn local_appearance(settings: &Settings) -> PlayerAppearance {
+    PlayerAppearance {
+        session: 0,
+        skin: PlayerAppearance::skin_hash(&settings.skin),
+        colors: settings.skin_colors.map(|[r, g, b]| [r, g, b, 255]),
+    }
+}
+
+/// Send the appearance in the settings to the server, when joining and when it changes.
+pub fn send_player_appearance(
+    settings: Res<Settings>,
+    channels: Res<Registry<Channel>>,
+    mut client: ResMut<Client>,
+    mut sent: ResMut<SentAppearance>,
+) {
+    let appearance = local_appearance(&settings);
+    if sent.0 == Some(appearance) {
+        return;
+    }
+
+    let channel = channels.resolve("player-appearance").unwrap().into();
+    client.tcp_send(channel, bytemuck::bytes_of(&appearance));
+    sent.0 = Some(appearance);
+}
+
+/// Keep the appearances of other players sent by the server.
+pub fn recv_player_appearances(
+    channels: Res<Registry<Channel>>,
+    client: Res<Client>,
+    mut appearances: ResMut<RemoteAppearances>,
+) {
+    for packet in channels.get_by_name("player-appearance").unwrap().recv() {
+        if let Some(appearance) = packet.cast::<PlayerAppearance>()
+            && Session(appearance.session) != client.session()
+        {
+            appearances
+                .0
+                .insert(Session(appearance.session), appearance);
+        }
+    }
+}
+
+/// Forget the appearances of other players when leaving the game,
+/// and send this player's again when joining the next.
+pub fn reset_appearances(
+    mut appearances: ResMut<RemoteAppearances>,
+    mut sent: ResMut<SentAppearance>,
+) {
+    appearances.0.clear();
+    sent.0 = None;
+}
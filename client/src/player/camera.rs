@@ -0,0 +1,113 @@
+use std::f32::consts::PI;
+
+use bevy::prelude::*;
+use data::{
+    blockstates::{BlockState, ModelData},
+    registry::Registry,
+};
+use world::World;
+
+use crate::{
+    focus::Focus,
+    player::{MainCamera, PlayerHead},
+    states::AppState,
+};
+
+/// How far the camera is placed from the head in third person.
+const THIRD_PERSON_DISTANCE: f32 = 4.0;
+
+/// Distance kept between the camera and blocks behind it,
+/// so the near plane doesn't clip into them.
+const CAMERA_MARGIN: f32 = 0.2;
+
+/// Where the camera is placed relative to the head of the player.
+#[derive(Resource, Copy, Clone, Default, PartialEq, Eq, Debug)]
+pub enum CameraMode {
+    /// At the eyes of the player.
+    #[default]
+    FirstPerson,
+
+    /// Behind the player, looking the same direction.
+    ThirdPersonBack,
+
+    /// In front of the player, looking back at them.
+    ThirdPersonFront,
+}
+
+impl CameraMode {
+    /// The next mode in the cycle.
+    pub const fn next(self) -> Self {
+        match self {
+            Self::FirstPerson => Self::ThirdPersonBack,
+            Self::ThirdPersonBack => Self::ThirdPersonFront,
+            Self::ThirdPersonFront => Self::FirstPerson,
+        }
+    }
+}
+
+/// Parts of the player model, hidden in first person so they don't block the view.
+#[derive(Component)]
+pub struct PlayerModel;
+
+/// Cycle the camera mode when the "camera-mode" action fires.
+pub fn handle_cycle_camera_mode(
+    app_state: Res<State<AppState>>,
+    focus: Focus,
+    mut mode: ResMut<CameraMode>,
+) {
+    if *app_state == AppState::InGame && focus.player_has_focus() {
+        *mode = mode.next();
+    }
+}
+
+/// Place the camera for the current mode, moving it closer to the head
+/// if a block is between them.
+pub fn update_camera_position(
+    mode: Res<CameraMode>,
+    head: Single<&GlobalTransform, With<PlayerHead>>,
+    mut camera: Single<&mut Transform, With<MainCamera>>,
+    world: Res<World>,
+    blocks: Res<Registry<BlockState>>,
+) {
+    let dir = match *mode {
+        CameraMode::FirstPerson => {
+            camera.translation = Vec3::ZERO;
+            camera.rotation = Quat::IDENTITY;
+            return;
+        }
+        CameraMode::ThirdPersonBack => head.back().as_vec3(),
+        CameraMode::ThirdPersonFront => head.forward().as_vec3(),
+    };
+
+    let distance = world
+        .raycast(head.translation(), dir, THIRD_PERSON_DISTANCE, |_, state| {
+            blocks
+                .get(state.voxel)
+                .is_some_and(|block| !matches!(block.model, ModelData::Empty))
+        })
+        .map_or(THIRD_PERSON_DISTANCE, |hit| {
+            (hit.distance - CAMERA_MARGIN).max(0.0)
+        });
+
+    // the camera is a child of the head, so the offset is in head space.
+    if *mode == CameraMode::ThirdPersonBack {
+        camera.translation = Vec3::Z * distance;
+        camera.rotation = Quat::IDENTITY;
+    } else {
+        camera.translation = Vec3::NEG_Z * distance;
+        camera.rotation = Quat::from_rotation_y(PI);
+    }
+}
+
+/// Show the player model in third person, and hide it in first person.
+pub fn update_player_model_visibility(
+    mode: Res<CameraMode>,
+    mut q: Query<&mut Visibility, With<PlayerModel>>,
+) {
+    for mut visibility in &mut q {
+        *visibility = match *mode {
+            CameraMode::FirstPerson => Visibility::Hidden,
+            _ => Visibility::Inherited,
+        };
+    }
+}
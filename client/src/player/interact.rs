@@ -0,0 +1,300 @@
+use std::time::{Duration, Instant};
+
+use bevy::prelude::*;
+use data::registry::Registry;
+use protocol::types::{BlockEditRequest, BlockUpdate, MultiBlockUpdate};
+use world::{
+    World,
+    voxel::{Voxel, VoxelState},
+};
+
+use crate::{
+    events::{BlockEffect, BlockEffectKind, BlockUpdated},
+    focus::Focus,
+    net::{Client, channel::Channel},
+    player::target::TargetedBlock,
+    states::AppState,
+};
+
+/// Predicted edits that haven't been answered by the server
+/// after this long are rolled back.
+const PREDICTION_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Most break effects shown for one multi block update.
+const MAX_MULTI_BLOCK_EFFECTS: usize = 32;
+
+/// The voxel placed by the "interact" action.
+#[derive(Resource, Copy, Clone)]
+pub struct HeldItem(pub Voxel);
+
+impl Default for HeldItem {
+    fn default() -> Self {
+        Self(Voxel(1))
+    }
+}
+
+/// Edits applied to the client world before the server confirmed them.
+#[derive(Resource)]
+pub struct PendingEdits {
+    next_sequence: u32,
+    pending: Vec<PendingEdit>,
+}
+
+struct PendingEdit {
+    sequence: u32,
+    pos: IVec3,
+    /// State of the voxel before the edit was predicted, restored if it times out.
+    previous: VoxelState,
+    sent_at: Instant,
+}
+
+impl Default for PendingEdits {
+    fn default() -> Self {
+        Self {
+            // zero is reserved for updates that weren't requested by this client.
+            next_sequence: 1,
+            pending: Vec::new(),
+        }
+    }
+}
+
+impl PendingEdits {
+    fn push(&mut self, pos: IVec3, previous: VoxelState) -> u32 {
+        let sequence = self.next_sequence;
+        self.next_sequence = self.next_sequence.checked_add(1).unwrap_or(1);
+        self.pending.push(PendingEdit {
+            sequence,
+            pos,
+            previous,
+            sent_at: Instant::now(),
+        });
+        sequence
+    }
+
+    /// Whether an unanswered edit has been predicted at this position.
+    pub fn is_pending(&self, pos: IVec3) -> bool {
+        self.pending.iter().any(|edit| edit.pos == pos)
+    }
+}
+
+/// Break the targeted block when the "punch" action fires.
+pub fn handle_punch(
+    target: Res<TargetedBlock>,
+    app_state: Res<State<AppState>>,
+    focus: Focus,
+    channels: Res<Registry<Channel>>,
+    client: Option<ResMut<Client>>,
+    mut world: ResMut<World>,
+    mut edits: ResMut<PendingEdits>,
+    mut updated: MessageWriter<BlockUpdated>,
+    mut effects: MessageWriter<BlockEffect>,
+) {
+    if *app_state != AppState::InGame || !focus.player_has_focus() {
+        return;
+    }
+
+    let Some(mut client) = client else {
+        return;
+    };
+
+    let Some(hit) = target.hit else {
+        return;
+    };
+
+    world.set_voxel(hit.pos, Voxel::AIR);
+    let request = BlockEditRequest {
+        sequence: edits.push(hit.pos, hit.state),
+        pos: hit.pos,
+        face: hit.face.map_or(u8::MAX, |face| face as u8),
+        action: BlockEditRequest::BREAK,
+        item: 0,
+    };
+
+    let channel = channels.resolve("block-edit").unwrap().into();
+    client.tcp_send(channel, bytemuck::bytes_of(&request));
+    updated.write(BlockUpdated { pos: hit.pos });
+    effects.write(BlockEffect {
+        pos: hit.pos,
+        voxel: hit.state.voxel,
+        kind: BlockEffectKind::Break,
+    });
+}
+
+/// Place the held block against the targeted face when the "interact" action fires.
+pub fn handle_interact(
+    target: Res<TargetedBlock>,
+    held: Res<HeldItem>,
+    app_state: Res<State<AppState>>,
+    focus: Focus,
+    channels: Res<Registry<Channel>>,
+    client: Option<ResMut<Client>>,
+    mut world: ResMut<World>,
+    mut edits: ResMut<PendingEdits>,
+    mut updated: MessageWriter<BlockUpdated>,
+    mut effects: MessageWriter<BlockEffect>,
+) {
+    if *app_state != AppState::InGame || !focus.player_has_focus() {
+        return;
+    }
+
+    let Some(mut client) = client else {
+        return;
+    };
+
+    let Some(hit) = target.hit else {
+        return;
+    };
+
+    // blocks can only be placed into air.
+    let pos = hit.adjacent();
+    let Some(previous) = world.get_state(pos).filter(|s| s.voxel == Voxel::AIR) else {
+        return;
+    };
+
+    world.set_voxel(pos, held.0);
+    let request = BlockEditRequest {
+        sequence: edits.push(pos, previous),
+        pos: hit.pos,
+        face: hit.face.map_or(u8::MAX, |face| face as u8),
+        action: BlockEditRequest::PLACE,
+        item: held.0.0,
+    };
+
+    let channel = channels.resolve("block-edit").unwrap().into();
+    client.tcp_send(channel, bytemuck::bytes_of(&request));
+    updated.write(BlockUpdated { pos });
+    effects.write(BlockEffect {
+        pos,
+        voxel: held.0,
+        kind: BlockEffectKind::Place,
+    });
+}
+
+/// Apply authoritative block updates from the server, and reconcile predicted edits.
+///
+/// An update is not applied while a newer prediction at the same position is
+/// waiting for an answer, since the answer to that prediction will follow it.
+pub fn recv_block_updates(
+    channels: Res<Registry<Channel>>,
+    mut world: ResMut<World>,
+    mut edits: ResMut<PendingEdits>,
+    mut updated: MessageWriter<BlockUpdated>,
+    mut effects: MessageWriter<BlockEffect>,
+) {
+    let channel = channels.get_by_name("block-update").unwrap();
+    for packet in channel.recv() {
+        let Some(update) = packet.cast::<BlockUpdate>() else {
+            continue;
+        };
+
+        if update.sequence != 0 {
+            edits.pending.retain(|edit| edit.sequence != update.sequence);
+        }
+
+        if edits.is_pending(update.pos) {
+            continue;
+        }
+
+        let voxel = Voxel(update.voxel);
+        let Some(previous) = world.get_state(update.pos).map(|s| s.voxel) else {
+            continue;
+        };
+
+        if previous != voxel {
+            world.set_voxel(update.pos, voxel);
+            updated.write(BlockUpdated { pos: update.pos });
+
+            // a block replaced by another shows the old one breaking.
+            effects.write(if voxel == Voxel::AIR || previous != Voxel::AIR {
+                BlockEffect {
+                    pos: update.pos,
+                    voxel: previous,
+                    kind: BlockEffectKind::Break,
+                }
+            } else {
+                BlockEffect {
+                    pos: update.pos,
+                    voxel,
+                    kind: BlockEffectKind::Place,
+                }
+            });
+        }
+    }
+}
+
+/// Apply updates of many voxels at once, like the blocks broken by an explosion,
+/// or a sapling growing into a tree. Only the first few blocks replaced by air show
+/// an effect, so large explosions don't spawn thousands of particles.
+pub fn recv_multi_block_updates(
+    channels: Res<Registry<Channel>>,
+    edits: Res<PendingEdits>,
+    mut world: ResMut<World>,
+    mut updated: MessageWriter<BlockUpdated>,
+    mut effects: MessageWriter<BlockEffect>,
+) {
+    let channel = channels.get_by_name("multi-block-update").unwrap();
+    for packet in channel.recv() {
+        let Some(voxels) = MultiBlockUpdate::decode(&packet.payload) else {
+            warn!("[C192] Received a malformed multi block update.");
+            continue;
+        };
+
+        let mut effects_left = MAX_MULTI_BLOCK_EFFECTS;
+        for (pos, voxel) in voxels {
+            if edits.is_pending(pos) {
+                continue;
+            }
+
+            let voxel = Voxel(voxel);
+            let Some(previous) = world.get_state(pos).map(|s| s.voxel) else {
+                continue;
+            };
+
+            if previous != voxel {
+                world.set_voxel(pos, voxel);
+                updated.write(BlockUpdated { pos });
+
+                if voxel == Voxel::AIR && effects_left != 0 {
+                    effects_left -= 1;
+                    effects.write(BlockEffect {
+                        pos,
+                        voxel: previous,
+                        kind: BlockEffectKind::Break,
+                    });
+                }
+            }
+        }
+    }
+}
+
+/// Roll back predicted edits the server never answered.
+pub fn expire_pending_edits(
+    mut world: ResMut<World>,
+    mut edits: ResMut<PendingEdits>,
+    mut updated: MessageWriter<BlockUpdated>,
+) {
+    let now = Instant::now();
+    let mut i = 0;
+    while i < edits.pending.len() {
+        if now.duration_since(edits.pending[i].sent_at) < PREDICTION_TIMEOUT {
+            i += 1;
+            continue;
+        }
+
+        let edit = edits.pending.remove(i);
+        warn!(
+            "[C140] Block edit at {} was not answered by the server, rolling back.",
+            edit.pos
+        );
+
+        // later predictions at the same position were made on top of this one,
+        // so they restore to the state before it if they also time out.
+        match edits.pending.iter_mut().find(|e| e.pos == edit.pos) {
+            Some(later) => later.previous = edit.previous,
+            None => {
+                world.set_state(edit.pos, edit.previous);
+                updated.write(BlockUpdated { pos: edit.pos });
+            }
+        }
+    }
+}
@@ -0,0 +1,54 @@
+//! Logging in to servers with the key they know this player by, see `PlayerLogin`.
+//!
+//! The key is generated the first time the game runs, and sent right after joining,
+//! so servers give this player the same name every time they join.
+
+use bevy::prelude::*;
+use data::{
+    info::{self, PLAYER_KEY_FILE, RootPath},
+    registry::Registry,
+};
+use protocol::types::PlayerLogin;
+
+use crate::net::{Client, channel::Channel};
+
+/// The key of this player, and whether it was sent since joining.
+#[derive(Resource, Default)]
+pub struct PlayerKey {
+    /// None if the key couldn't be read or created.
+    login: Option<PlayerLogin>,
+    sent: bool,
+}
+
+/// Read the key of this player, or create it.
+pub fn load_player_key(root: Res<RootPath>, mut key: ResMut<PlayerKey>) {
+    match info::load_player_key(&root) {
+        Ok(login) => key.login = Some(login),
+        Err(e) => error!(
+            "[C193] Failed to read or create the player key '{}', servers won't know who this player is: {e}",
+            root.join(PLAYER_KEY_FILE).display()
+        ),
+    }
+}
+
+/// Send the key to the server once after joining.
+pub fn send_player_login(
+    channels: Res<Registry<Channel>>,
+    mut client: ResMut<Client>,
+    mut key: ResMut<PlayerKey>,
+) {
+    if key.sent {
+        return;
+    }
+    key.sent = true;
+
+    if let Some(login) = key.login {
+        let channel = channels.resolve("player-login").unwrap().into();
+        client.tcp_send(channel, bytemuck::bytes_of(&login));
+    }
+}
+
+/// Send the key again when joining the next game.
+pub fn reset_player_login(mut key: ResMut<PlayerKey>) {
+    key.sent = false;
+}
@@ -1,24 +1,47 @@
 use bevy::{input::mouse::AccumulatedMouseMotion, prelude::*};
 use data::registry::Registry;
-use protocol::{packet::Version, session::Session, types::PlayerInputUpdate};
+use protocol::{
+    packet::Version,
+    session::Session,
+    types::{MovementState, PlayerInputUpdate, PlayerPositionCorrection},
+};
 
+pub mod appearance;
+pub mod camera;
 pub mod input;
+pub mod interact;
+pub mod login;
+pub mod movement;
+pub mod physics;
+pub mod remote;
+pub mod target;
 
 use crate::{
-    focus::{Focus, Focused},
+    focus::Focused,
+    input::{Actions, GamepadSticks},
     net::{Client, channel::Channel},
 };
 
+/// Radians turned per second with the look stick pushed all the way.
+const STICK_LOOK_SPEED: f32 = 3.0;
+
 #[derive(Component)]
 pub struct Player {
     pub session: Session,
     pub version: Version,
+
+    /// The last position correction of the server that was applied.
+    pub correction: u32,
 }
 
 #[derive(Component, Default)]
 pub struct PlayerController {
     pub look_deltas: Vec2,
     pub move_deltas: Vec3,
+    pub movement: MovementState,
+
+    /// Time (elapsed seconds) "forward" was last pressed, for double-tap sprinting.
+    pub last_forward_press: Option<f32>,
 }
 
 #[derive(Component, Default)]
@@ -27,122 +50,160 @@ pub struct MainCamera;
 #[derive(Component)]
 pub struct PlayerHead;
 
+/// Height of the camera above the feet of the player.
+pub const EYE_HEIGHT: f32 = 1.62;
+
 #[derive(Component)]
 pub struct PlayerBody;
 
 pub fn on_connect_success(
-    player: Single<(Entity, &mut Player, &mut Transform)>,
-    mut focus: Focus,
+    player: Single<(Entity, &mut Player, &mut Transform, &mut physics::CharacterBody)>,
     client: Res<Client>,
 ) {
-    let (_, mut player, mut transform) = player.into_inner();
+    let (_, mut player, mut transform, mut body) = player.into_inner();
     info!("Player connected with session: {:?}", client.session());
     player.session = client.session();
     player.version = Version::ZERO;
+    player.correction = 0;
     transform.translation = vec3(0.0, 64.0, 0.0);
-    focus.to_player();
+    body.velocity = Vec3::ZERO;
 }
 
 /// Player spawns at program start, but can't move until they join a game.
+///
+/// The body only rotates around the Y axis, and the head only pitches,
+/// so the model and camera agree on where the player is looking in every camera mode.
 #[rustfmt::skip]
-pub fn spawn_player(mut commands: Commands) {
+pub fn spawn_player(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let material = materials.add(Color::srgb(0.3, 0.45, 0.7));
+    let torso = meshes.add(Cuboid::new(0.6, 1.4, 0.3));
+    let head = meshes.add(Cuboid::from_length(0.5));
+
     commands
         .spawn((
             Player {
                 session: Session::ZERO,
                 version: Version::ZERO,
+                correction: 0,
             },
             PlayerBody,
             PlayerController::default(),
-            InheritedVisibility::VISIBLE,
+            physics::CharacterBody::default(),
+            Visibility::Visible,
             Transform::default(),
         ))
-        .with_child((
-            PlayerHead,
-            Transform::default(),
-            Camera3d::default(),
-            MainCamera,
-        ));
+        .with_children(|body| {
+            body.spawn((
+                camera::PlayerModel,
+                Mesh3d(torso),
+                MeshMaterial3d(material.clone()),
+                Transform::from_xyz(0.0, 0.7, 0.0),
+                Visibility::Hidden,
+            ));
+            body.spawn((
+                PlayerHead,
+                Transform::from_xyz(0.0, EYE_HEIGHT, 0.0),
+                Visibility::Inherited,
+            ))
+            .with_children(|head| {
+                head.spawn((
+                    camera::PlayerModel,
+                    Mesh3d(head),
+                    MeshMaterial3d(material),
+                    Transform::default(),
+                    Visibility::Hidden,
+                ));
+                head.spawn((
+                    Camera3d::default(),
+                    MainCamera,
+                    SpatialListener::new(0.3),
+                    Transform::default(),
+                ));
+            });
+        });
 }
 
 pub fn player_compute_look_deltas(
     mut player: Single<&mut PlayerController>,
     motion: Res<AccumulatedMouseMotion>,
+    sticks: Res<GamepadSticks>,
+    time: Res<Time>,
 ) {
-    player.look_deltas = motion.delta * 0.005;
+    // the stick turns at a speed instead of by a distance, and pushing it up looks up.
+    let stick = sticks.right * Vec2::new(1.0, -1.0) * STICK_LOOK_SPEED * time.delta_secs();
+    player.look_deltas = motion.delta * 0.005 + stick;
 }
 
 pub fn player_compute_move_deltas(
-    mut player: Query<(&mut PlayerController, &Children), With<Focused>>,
-    q_head: Query<&Transform, With<PlayerHead>>,
-    buttons: Res<ButtonInput<KeyCode>>,
+    mut player: Query<(&mut PlayerController, &Transform), With<Focused>>,
+    actions: Res<Actions>,
+    sticks: Res<GamepadSticks>,
 ) {
-    if let Ok((mut player, children)) = player.single_mut() {
+    if let Ok((mut player, transform)) = player.single_mut() {
         player.move_deltas = Vec3::ZERO;
 
-        for child in children {
-            if let Ok(head_pos) = q_head.get(*child) {
-                let forward = head_pos.forward().as_vec3().with_y(0.0).normalize();
-                let right = head_pos.right().as_vec3().with_y(0.0).normalize();
+        // the body only has yaw, so these are always horizontal.
+        let forward = transform.forward().as_vec3();
+        let right = transform.right().as_vec3();
 
-                if buttons.pressed(KeyCode::KeyW) {
-                    player.move_deltas += forward;
-                }
+        if actions.is_activated("forward") {
+            player.move_deltas += forward;
+        }
 
-                if buttons.pressed(KeyCode::KeyS) {
-                    player.move_deltas -= forward;
-                }
+        if actions.is_activated("back") {
+            player.move_deltas -= forward;
+        }
 
-                if buttons.pressed(KeyCode::KeyA) {
-                    player.move_deltas -= right;
-                }
+        if actions.is_activated("left") {
+            player.move_deltas -= right;
+        }
 
-                if buttons.pressed(KeyCode::KeyD) {
-                    player.move_deltas += right;
-                }
+        if actions.is_activated("right") {
+            player.move_deltas += right;
+        }
 
-                if buttons.pressed(KeyCode::KeyC) {
-                    player.move_deltas.y -= 1.0;
-                }
+        if actions.is_activated("descend") {
+            player.move_deltas.y -= 1.0;
+        }
 
-                if buttons.pressed(KeyCode::Space) {
-                    player.move_deltas.y += 1.0;
-                }
+        if actions.is_activated("jump") {
+            player.move_deltas.y += 1.0;
+        }
 
-                return;
-            }
+        // the stick also activates the actions above, but with how far it's pushed.
+        if sticks.left != Vec2::ZERO {
+            let walk = forward * sticks.left.y + right * sticks.left.x;
+            player.move_deltas = walk.with_y(player.move_deltas.y);
         }
     }
 }
 
-pub fn player_apply_move_deltas(
-    player: Single<(&mut PlayerController, &mut Transform)>,
-    time: Res<Time>,
-) {
-    let (mut player, mut transform) = player.into_inner();
-    if player.move_deltas != Vec3::ZERO {
-        let deltas = player.move_deltas * time.delta_secs() * 0.01;
-        transform.translation += deltas.normalize();
-        player.move_deltas = Vec3::ZERO;
-    }
+/// Drop the movement and look input while the player doesn't have focus,
+/// so the player doesn't keep walking after a menu or the chat is opened.
+pub fn player_clear_input(mut player: Single<&mut PlayerController>) {
+    player.move_deltas = Vec3::ZERO;
+    player.look_deltas = Vec2::ZERO;
 }
 
+/// Apply yaw to the body and pitch to the head.
 pub fn player_apply_look_deltas(
-    player: Single<(&PlayerController, &Children), With<Player>>,
-    mut q_head: Query<&mut Transform, With<PlayerHead>>,
+    player: Single<(&PlayerController, &mut Transform, &Children), With<Player>>,
+    mut q_head: Query<&mut Transform, (With<PlayerHead>, Without<Player>)>,
 ) {
     const PITCH_LIMIT: f32 = 1.5707964 - 0.01;
 
-    let (player, children) = player.into_inner();
+    let (player, mut body, children) = player.into_inner();
     if player.look_deltas != Vec2::ZERO {
+        body.rotate_y(-player.look_deltas.x);
         for child in children {
             if let Ok(mut transform) = q_head.get_mut(*child) {
-                let (yaw, pitch, roll) = transform.rotation.to_euler(EulerRot::YXZ);
-                transform.rotation = Quat::from_euler(
-                    EulerRot::YXZ,
-                    yaw - player.look_deltas.x,
+                let (_, pitch, _) = transform.rotation.to_euler(EulerRot::YXZ);
+                transform.rotation = Quat::from_rotation_x(
                     (pitch - player.look_deltas.y).clamp(-PITCH_LIMIT, PITCH_LIMIT),
-                    roll,
                 );
             }
         }
@@ -151,17 +212,36 @@ pub fn player_apply_look_deltas(
 
 pub fn send_player_input_update(
     channels: Res<Registry<Channel>>,
-    player: Single<(&mut Player, &Transform)>,
+    player: Single<(&mut Player, &PlayerController, &Transform)>,
+    head: Single<&GlobalTransform, With<PlayerHead>>,
     mut client: ResMut<Client>,
 ) {
     let channel = channels.resolve("player-input").unwrap().into();
-    let (mut player, transform) = player.into_inner();
+    let (mut player, controller, transform) = player.into_inner();
 
     let update = PlayerInputUpdate {
         version: player.version.next(),
         translation: transform.translation,
-        look_dir: Quat::from_rotation_x(1.0),
+        look_dir: head.rotation(),
+        movement: controller.movement as u32,
+        correction: player.correction,
+        _pad: [0; 2],
     };
 
     client.tcp_send(channel, bytemuck::bytes_of(&update));
 }
+
+/// Move the player back to where the server kept them, after it rejected a movement.
+pub fn recv_position_corrections(
+    channels: Res<Registry<Channel>>,
+    player: Single<(&mut Player, &mut Transform, &mut physics::CharacterBody)>,
+) {
+    let (mut player, mut transform, mut body) = player.into_inner();
+    for packet in channels.get_by_name("player-position").unwrap().recv() {
+        if let Some(correction) = packet.cast::<PlayerPositionCorrection>() {
+            transform.translation = correction.translation;
+            body.velocity = Vec3::ZERO;
+            player.correction = correction.correction;
+        }
+    }
+}
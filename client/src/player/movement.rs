@@ -0,0 +1,62 @@
+use bevy::prelude::*;
+use data::{blockstates::BlockState, registry::Registry};
+use protocol::types::MovementState;
+use world::World;
+
+use crate::{
+    focus::Focused,
+    input::Actions,
+    player::{PlayerController, physics::CharacterBody},
+};
+
+/// Two presses of "forward" within this many seconds start sprinting.
+const DOUBLE_TAP_WINDOW: f32 = 0.3;
+
+/// Choose the movement state of the player from its input and surroundings.
+///
+/// Flying and swimming are decided by the body, sneaking takes priority over
+/// sprinting, and sprinting continues until "forward" is released.
+pub fn player_update_movement_state(
+    player: Single<(&mut PlayerController, &CharacterBody, &Transform, Has<Focused>)>,
+    actions: Res<Actions>,
+    world: Res<World>,
+    blocks: Res<Registry<BlockState>>,
+    time: Res<Time>,
+) {
+    let (mut controller, body, transform, focused) = player.into_inner();
+
+    let now = time.elapsed_secs();
+    let double_tapped = focused
+        && actions.just_activated("forward")
+        && controller
+            .last_forward_press
+            .is_some_and(|last| now - last < DOUBLE_TAP_WINDOW);
+    if focused && actions.just_activated("forward") {
+        controller.last_forward_press = Some(now);
+    }
+
+    // input only counts while the player has focus.
+    let pressed = |action: &str| focused && actions.is_activated(action);
+
+    let center = transform.translation + Vec3::Y * body.height * 0.5;
+    let in_liquid = world
+        .get_state(center.floor().as_ivec3())
+        .and_then(|state| blocks.get(state.voxel))
+        .is_some_and(|block| block.liquid);
+
+    controller.movement = if body.flying {
+        MovementState::Flying
+    } else if in_liquid {
+        MovementState::Swimming
+    } else if pressed("sneak") {
+        MovementState::Sneaking
+    } else if pressed("forward")
+        && (controller.movement == MovementState::Sprinting
+            || pressed("sprint")
+            || double_tapped)
+    {
+        MovementState::Sprinting
+    } else {
+        MovementState::Walking
+    };
+}
@@ -0,0 +1,328 @@
+use bevy::prelude::*;
+use data::{blockstates::BlockState, registry::Registry};
+use math::collide::{Aabb, SKIN, sweep_aabb};
+use protocol::types::MovementState;
+use world::World;
+
+use crate::{
+    focus::Focus,
+    player::{Player, PlayerController},
+    states::AppState,
+};
+
+/// Acceleration due to gravity, in voxels per second squared.
+const GRAVITY: f32 = 32.0;
+
+/// The fastest the player can fall.
+const TERMINAL_VELOCITY: f32 = 60.0;
+
+/// Vertical velocity applied when jumping, enough to clear one block.
+const JUMP_SPEED: f32 = 9.0;
+
+/// Horizontal speed when walking.
+const WALK_SPEED: f32 = 4.3;
+
+/// Horizontal speed when sprinting.
+const SPRINT_SPEED: f32 = 5.6;
+
+/// Horizontal speed when sneaking.
+const SNEAK_SPEED: f32 = 1.3;
+
+/// Speed in any direction when swimming.
+const SWIM_SPEED: f32 = 2.0;
+
+/// Gravity is scaled by this amount in liquids.
+const SWIM_GRAVITY_SCALE: f32 = 0.25;
+
+/// Speed in any direction when flying.
+const FLY_SPEED: f32 = 12.0;
+
+/// The collision box and movement state of the player.
+#[derive(Component)]
+pub struct CharacterBody {
+    /// Velocity in voxels per second.
+    pub velocity: Vec3,
+
+    /// Half of the width of the collision box on the X and Z axes.
+    pub half_width: f32,
+
+    /// Height of the collision box, from the feet.
+    pub height: f32,
+
+    /// Height of the ledges that can be walked onto without jumping.
+    pub step_height: f32,
+
+    /// Whether the body is standing on a solid voxel.
+    pub on_ground: bool,
+
+    /// Flying disables gravity and collision.
+    pub flying: bool,
+}
+
+impl Default for CharacterBody {
+    fn default() -> Self {
+        Self {
+            velocity: Vec3::ZERO,
+            half_width: 0.3,
+            height: 1.8,
+            step_height: 0.6,
+            on_ground: false,
+            flying: false,
+        }
+    }
+}
+
+impl CharacterBody {
+    /// The collision box with its feet at `pos`.
+    fn aabb(&self, pos: Vec3) -> Aabb {
+        Aabb::from_feet(pos, self.half_width, self.height)
+    }
+
+    /// Whether there is a solid voxel directly below the feet.
+    fn supported(&self, pos: Vec3, solid: &impl Fn(IVec3) -> bool) -> bool {
+        self.aabb(pos - Vec3::Y * SKIN * 2.0).collides(solid)
+    }
+
+    /// Move along a single axis, stopping against the first solid voxel.
+    /// Returns true if the movement was blocked.
+    fn move_axis(
+        &self,
+        pos: &mut Vec3,
+        axis: usize,
+        delta: f32,
+        solid: &impl Fn(IVec3) -> bool,
+    ) -> bool {
+        let mut velocity = Vec3::ZERO;
+        velocity[axis] = delta;
+        let sweep = sweep_aabb(solid, self.aabb(*pos), velocity);
+        *pos += sweep.offset;
+        sweep.is_hit()
+    }
+
+    /// Move by the velocity for `dt` seconds, landing on, stepping onto and stopping
+    /// against solid voxels. Sneaking stops the body from walking off of ledges.
+    fn move_and_collide(
+        &mut self,
+        pos: &mut Vec3,
+        dt: f32,
+        sneaking: bool,
+        solid: &impl Fn(IVec3) -> bool,
+    ) {
+        // vertical first, so the body lands before it moves sideways.
+        let falling = self.velocity.y <= 0.0;
+        if self.move_axis(pos, 1, self.velocity.y * dt, solid) {
+            self.velocity.y = 0.0;
+            self.on_ground = falling;
+        } else {
+            self.on_ground = false;
+        }
+
+        let edge_guard = sneaking && self.on_ground;
+
+        for axis in [0, 2] {
+            let delta = self.velocity[axis] * dt;
+            let before = *pos;
+            if !self.move_axis(pos, axis, delta, solid) {
+                if edge_guard && !self.supported(*pos, solid) {
+                    *pos = before;
+                    self.velocity[axis] = 0.0;
+                }
+                continue;
+            }
+
+            // try to walk up onto the ledge, then settle back down onto it.
+            if self.on_ground {
+                let mut raised = before;
+                if !self.move_axis(&mut raised, 1, self.step_height, solid)
+                    && !self.move_axis(&mut raised, axis, delta, solid)
+                {
+                    self.move_axis(&mut raised, 1, -self.step_height, solid);
+                    *pos = raised;
+                    continue;
+                }
+            }
+
+            self.velocity[axis] = 0.0;
+        }
+    }
+}
+
+/// Whether a voxel blocks movement. Voxels in unloaded chunks are solid,
+/// so the player doesn't fall out of the world before the terrain arrives.
+/// Liquids and blocks without a collision box, like plants, are not solid.
+fn is_solid(world: &World, blocks: &Registry<BlockState>, pos: IVec3) -> bool {
+    let Some(state) = world.get_state(pos) else {
+        return true;
+    };
+
+    blocks
+        .get(state.voxel)
+        .is_some_and(BlockState::blocks_movement)
+}
+
+/// Toggle flying when the "toggle-fly" action fires.
+pub fn handle_toggle_fly(
+    app_state: Res<State<AppState>>,
+    focus: Focus,
+    mut body: Single<&mut CharacterBody, With<Player>>,
+) {
+    if *app_state == AppState::InGame && focus.player_has_focus() {
+        body.flying = !body.flying;
+        body.velocity = Vec3::ZERO;
+    }
+}
+
+/// Apply the movement input, gravity and collision to the player.
+pub fn player_apply_physics(
+    player: Single<(&mut PlayerController, &mut CharacterBody, &mut Transform)>,
+    world: Res<World>,
+    blocks: Res<Registry<BlockState>>,
+    time: Res<Time>,
+) {
+    let (mut controller, mut body, mut transform) = player.into_inner();
+    let dt = time.delta_secs();
+    let input = controller.move_deltas;
+    controller.move_deltas = Vec3::ZERO;
+
+    if body.flying {
+        body.velocity = input.clamp_length_max(1.0) * FLY_SPEED;
+        body.on_ground = false;
+        transform.translation += body.velocity * dt;
+        return;
+    }
+
+    let speed = match controller.movement {
+        MovementState::Sprinting => SPRINT_SPEED,
+        MovementState::Sneaking => SNEAK_SPEED,
+        MovementState::Swimming => SWIM_SPEED,
+        _ => WALK_SPEED,
+    };
+
+    let walk = input.with_y(0.0).clamp_length_max(1.0) * speed;
+    body.velocity.x = walk.x;
+    body.velocity.z = walk.z;
+    if controller.movement == MovementState::Swimming {
+        // swim up while jump is held, otherwise sink slowly.
+        body.velocity.y = if input.y > 0.0 {
+            SWIM_SPEED
+        } else {
+            (body.velocity.y - GRAVITY * SWIM_GRAVITY_SCALE * dt).max(-SWIM_SPEED)
+        };
+    } else {
+        if input.y > 0.0 && body.on_ground {
+            body.velocity.y = JUMP_SPEED;
+        }
+        body.velocity.y = (body.velocity.y - GRAVITY * dt).max(-TERMINAL_VELOCITY);
+    }
+
+    let solid = |pos: IVec3| is_solid(&world, &blocks, pos);
+    let mut pos = transform.translation;
+
+    let sneaking = controller.movement == MovementState::Sneaking;
+    body.move_and_collide(&mut pos, dt, sneaking, &solid);
+    transform.translation = pos;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DT: f32 = 0.05;
+
+    /// Walk along X for some ticks, with gravity, returning the position of the feet.
+    fn walk(
+        body: &mut CharacterBody,
+        mut pos: Vec3,
+        speed: f32,
+        ticks: usize,
+        sneaking: bool,
+        solid: impl Fn(IVec3) -> bool,
+    ) -> Vec3 {
+        for _ in 0..ticks {
+            body.velocity.x = speed;
+            body.velocity.y = (body.velocity.y - GRAVITY * DT).max(-TERMINAL_VELOCITY);
+            body.move_and_collide(&mut pos, DT, sneaking, &solid);
+        }
+        pos
+    }
+
+    #[test]
+    fn lands_on_the_floor() {
+        let mut body = CharacterBody::default();
+        let pos = walk(&mut body, Vec3::new(0.5, 3.0, 0.5), 0.0, 40, false, |p| {
+            p.y < 0
+        });
+        assert!(body.on_ground);
+        assert_eq!(body.velocity.y, 0.0);
+        assert!((0.0..0.01).contains(&pos.y), "{pos}");
+    }
+
+    #[test]
+    fn stops_against_walls() {
+        let mut body = CharacterBody::default();
+        let solid = |p: IVec3| p.y < 0 || p.x >= 2;
+        let pos = walk(
+            &mut body,
+            Vec3::new(0.5, 0.5, 0.5),
+            WALK_SPEED,
+            20,
+            false,
+            solid,
+        );
+        assert_eq!(body.velocity.x, 0.0);
+        assert!((1.69..=1.7).contains(&pos.x), "{pos}");
+        assert!(pos.y < 0.01, "{pos}");
+    }
+
+    #[test]
+    fn steps_onto_ledges_below_the_step_height() {
+        let ledge = |p: IVec3| p.y < 0 || (p.y == 0 && p.x >= 2);
+
+        // a full voxel is higher than the default step, so it is a wall.
+        let mut body = CharacterBody::default();
+        let pos = walk(
+            &mut body,
+            Vec3::new(0.5, 0.5, 0.5),
+            WALK_SPEED,
+            20,
+            false,
+            ledge,
+        );
+        assert!(pos.x <= 1.7 && pos.y < 0.01, "{pos}");
+
+        let mut body = CharacterBody {
+            step_height: 1.1,
+            ..default()
+        };
+        let pos = walk(
+            &mut body,
+            Vec3::new(0.5, 0.5, 0.5),
+            WALK_SPEED,
+            20,
+            false,
+            ledge,
+        );
+        assert!(body.on_ground);
+        assert!(pos.x > 2.3, "{pos}");
+        assert!((1.0..1.01).contains(&pos.y), "{pos}");
+    }
+
+    #[test]
+    fn sneaking_stops_at_edges() {
+        let mut body = CharacterBody::default();
+        let floor = |p: IVec3| p.y < 0 && p.x < 2;
+        let pos = walk(
+            &mut body,
+            Vec3::new(0.5, 0.5, 0.5),
+            SNEAK_SPEED,
+            60,
+            true,
+            floor,
+        );
+        assert!(body.on_ground);
+        assert!((1.69..2.3).contains(&pos.x), "{pos}");
+
+        let pos = walk(&mut body, pos, SNEAK_SPEED, 60, false, floor);
+        assert!(!body.on_ground && pos.y < 0.0, "{pos}");
+    }
+}
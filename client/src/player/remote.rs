@@ -0,0 +1,304 @@
+//! Other players connected to the server.
+//!
+//! The server sends a snapshot of each player in draw range every tick. Snapshots are
+//! buffered and rendered `INTERPOLATION_DELAY` seconds in the past, so there is always a
+//! pair of snapshots to interpolate between and movement looks smooth despite jitter.
+
+use std::{
+    collections::VecDeque,
+    f32::consts::{PI, TAU},
+};
+
+use bevy::prelude::*;
+use data::registry::Registry;
+use fxhash::FxHashMap;
+use protocol::{
+    packet::Version,
+    session::Session,
+    types::{PlayerAppearance, PlayerRemoved, PlayerSnapshot},
+};
+
+use crate::{
+    net::{Client, channel::Channel},
+    player::{
+        EYE_HEIGHT,
+        appearance::{RemoteAppearances, SkinPart, Skins},
+    },
+    ui::world_text::WorldLabel,
+};
+
+/// How far in the past remote players are rendered.
+const INTERPOLATION_DELAY: f32 = 0.1;
+
+/// Remote players that haven't been updated for this long have
+/// left draw range, and are despawned.
+const STALE_TIMEOUT: f32 = 5.0;
+
+/// Height of the name tag above the feet of the player.
+const NAME_TAG_HEIGHT: f32 = 2.2;
+
+/// Name tags further than this from the camera are hidden.
+const NAME_TAG_DISTANCE: f32 = 48.0;
+
+/// Map of sessions to the entities of remote players.
+#[derive(Resource, Default)]
+pub struct RemotePlayers(FxHashMap<Session, Entity>);
+
+/// Another player connected to the server.
+#[derive(Component)]
+pub struct RemotePlayer {
+    pub session: Session,
+    version: Version,
+    snapshots: VecDeque<Snapshot>,
+    body: Entity,
+    head: Entity,
+
+    /// The appearance the model is drawn with, None until it is first applied.
+    appearance: Option<PlayerAppearance>,
+}
+
+#[derive(Component)]
+pub struct RemotePlayerHead;
+
+#[derive(Copy, Clone)]
+struct Snapshot {
+    /// Time (elapsed seconds) the snapshot was received.
+    time: f32,
+    translation: Vec3,
+    yaw: f32,
+    pitch: f32,
+}
+
+impl Snapshot {
+    fn lerp(&self, rhs: &Self, t: f32) -> Self {
+        // interpolate yaw along the shortest arc.
+        let yaw = (rhs.yaw - self.yaw + PI).rem_euclid(TAU) - PI;
+        Self {
+            time: self.time + (rhs.time - self.time) * t,
+            translation: self.translation.lerp(rhs.translation, t),
+            yaw: self.yaw + yaw * t,
+            pitch: self.pitch + (rhs.pitch - self.pitch) * t,
+        }
+    }
+}
+
+/// Meshes shared by every remote player.
+pub struct RemotePlayerAssets {
+    torso: Handle<Mesh>,
+    head: Handle<Mesh>,
+}
+
+/// Buffer snapshots of remote players, spawning players that aren't known yet.
+pub fn recv_player_snapshots(
+    mut commands: Commands,
+    channels: Res<Registry<Channel>>,
+    client: Res<Client>,
+    time: Res<Time>,
+    mut players: ResMut<RemotePlayers>,
+    mut q: Query<&mut RemotePlayer>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut assets: Local<Option<RemotePlayerAssets>>,
+) {
+    let now = time.elapsed_secs();
+    for packet in channels.get_by_name("player-snapshot").unwrap().recv() {
+        let Some(update) = packet.cast::<PlayerSnapshot>() else {
+            continue;
+        };
+
+        let session = Session(update.session);
+        if session == client.session() {
+            continue;
+        }
+
+        let snapshot = Snapshot {
+            time: now,
+            translation: update.translation,
+            yaw: update.yaw,
+            pitch: update.pitch,
+        };
+
+        if let Some(&entity) = players.0.get(&session) {
+            if let Ok(mut player) = q.get_mut(entity)
+                && player.version.update(update.version)
+            {
+                player.snapshots.push_back(snapshot);
+            }
+            continue;
+        }
+
+        let assets = assets.get_or_insert_with(|| RemotePlayerAssets {
+            torso: meshes.add(Cuboid::new(0.6, 1.4, 0.3)),
+            head: meshes.add(Cuboid::from_length(0.5)),
+        });
+        let entity = spawn_remote_player(&mut commands, assets, session, update.version, snapshot);
+        players.0.insert(session, entity);
+    }
+}
+
+fn spawn_remote_player(
+    commands: &mut Commands,
+    assets: &RemotePlayerAssets,
+    session: Session,
+    version: Version,
+    snapshot: Snapshot,
+) -> Entity {
+    let player = commands.spawn_empty().id();
+    commands.spawn(
+        WorldLabel::new(player, Vec3::Y * NAME_TAG_HEIGHT)
+            .with_max_distance(NAME_TAG_DISTANCE)
+            .bundle(format!("Player {}", session.index())),
+    );
+
+    // materials are added once the appearance of the player is applied.
+    let body = commands
+        .spawn((
+            Mesh3d(assets.torso.clone()),
+            Transform::from_xyz(0.0, 0.7, 0.0),
+        ))
+        .id();
+    let head = commands
+        .spawn((
+            RemotePlayerHead,
+            Mesh3d(assets.head.clone()),
+            Transform::from_xyz(0.0, EYE_HEIGHT, 0.0)
+                .with_rotation(Quat::from_rotation_x(snapshot.pitch)),
+        ))
+        .id();
+
+    commands
+        .entity(player)
+        .insert((
+            RemotePlayer {
+                session,
+                version,
+                snapshots: VecDeque::from([snapshot]),
+                body,
+                head,
+                appearance: None,
+            },
+            Transform::from_translation(snapshot.translation)
+                .with_rotation(Quat::from_rotation_y(snapshot.yaw)),
+            Visibility::Visible,
+        ))
+        .add_children(&[body, head]);
+
+    player
+}
+
+/// Move remote players to their interpolated position.
+pub fn interpolate_remote_players(
+    time: Res<Time>,
+    mut q: Query<(&mut RemotePlayer, &mut Transform, &Children)>,
+    mut heads: Query<&mut Transform, (With<RemotePlayerHead>, Without<RemotePlayer>)>,
+) {
+    let render_time = time.elapsed_secs() - INTERPOLATION_DELAY;
+    for (mut player, mut transform, children) in &mut q {
+        // drop snapshots that are older than the pair surrounding the render time.
+        while player.snapshots.len() > 2 && player.snapshots[1].time <= render_time {
+            player.snapshots.pop_front();
+        }
+
+        let sample = match (player.snapshots.front(), player.snapshots.get(1)) {
+            (Some(a), Some(b)) if b.time > a.time => {
+                a.lerp(b, ((render_time - a.time) / (b.time - a.time)).clamp(0.0, 1.0))
+            }
+            (Some(a), _) => *a,
+            _ => continue,
+        };
+
+        transform.translation = sample.translation;
+        transform.rotation = Quat::from_rotation_y(sample.yaw);
+        for child in children {
+            if let Ok(mut head) = heads.get_mut(*child) {
+                head.rotation = Quat::from_rotation_x(sample.pitch);
+            }
+        }
+    }
+}
+
+/// Despawn remote players that left the server, or haven't been updated in a while.
+pub fn despawn_remote_players(
+    mut commands: Commands,
+    channels: Res<Registry<Channel>>,
+    time: Res<Time>,
+    mut players: ResMut<RemotePlayers>,
+    mut appearances: ResMut<RemoteAppearances>,
+    q: Query<&RemotePlayer>,
+) {
+    let now = time.elapsed_secs();
+    let mut removed = channels
+        .get_by_name("player-removed")
+        .unwrap()
+        .recv()
+        .filter_map(|packet| packet.cast::<PlayerRemoved>())
+        .map(|removed| Session(removed.session))
+        .collect::<Vec<_>>();
+
+    // players that only left draw range keep their appearance for when they return.
+    for session in &removed {
+        appearances.remove(*session);
+    }
+
+    for player in &q {
+        if player
+            .snapshots
+            .back()
+            .is_some_and(|last| now - last.time > STALE_TIMEOUT)
+        {
+            removed.push(player.session);
+        }
+    }
+
+    for session in removed {
+        if let Some(entity) = players.0.remove(&session) {
+            // its name tag is despawned once the player is gone, see `WorldLabel`.
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+/// Draw remote players with their skin, or their colors if this client doesn't have it.
+/// Players the server hasn't sent an appearance for are drawn with the default colors.
+pub fn apply_remote_appearances(
+    mut commands: Commands,
+    appearances: Res<RemoteAppearances>,
+    skins: Res<Skins>,
+    server: Res<AssetServer>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut q: Query<&mut RemotePlayer>,
+) {
+    for mut player in &mut q {
+        let appearance = appearances.get(player.session).copied().unwrap_or_default();
+        if player.appearance == Some(appearance) {
+            continue;
+        }
+
+        player.appearance = Some(appearance);
+        let skin = skins.get(appearance.skin);
+        let parts = [(SkinPart::Body, player.body), (SkinPart::Head, player.head)];
+        for ((part, entity), [r, g, b, a]) in parts.into_iter().zip(appearance.colors) {
+            let material = match skin {
+                Some(name) => StandardMaterial {
+                    base_color_texture: Some(server.load(Skins::texture_path(name, part))),
+                    ..default()
+                },
+                None => StandardMaterial::from(Color::srgba_u8(r, g, b, a)),
+            };
+            commands
+                .entity(entity)
+                .insert(MeshMaterial3d(materials.add(material)));
+        }
+    }
+}
+
+/// Despawn every remote player when leaving the game.
+pub fn despawn_all_remote_players(
+    mut commands: Commands,
+    mut players: ResMut<RemotePlayers>,
+    q: Query<Entity, With<RemotePlayer>>,
+) {
+    for entity in &q {
+        commands.entity(entity).despawn();
+    }
+    players.0.clear();
+}
@@ -0,0 +1,57 @@
+use bevy::prelude::*;
+use data::{
+    blockstates::{BlockState, ModelData},
+    registry::Registry,
+};
+use world::{RayHit, World};
+
+use crate::player::PlayerHead;
+
+/// The block the player is looking at, if any is within reach.
+#[derive(Resource)]
+pub struct TargetedBlock {
+    /// The block and face that was hit.
+    pub hit: Option<RayHit>,
+
+    /// How far away (in voxels) blocks can be targeted.
+    pub reach: f32,
+}
+
+impl Default for TargetedBlock {
+    fn default() -> Self {
+        Self {
+            hit: None,
+            reach: 6.0,
+        }
+    }
+}
+
+/// Raycast from the head to find the block the player is looking at.
+/// The head is used rather than the camera, so third person targets the same block.
+/// Blocks without a model, like air, can't be targeted.
+pub fn update_targeted_block(
+    head: Single<&GlobalTransform, With<PlayerHead>>,
+    world: Res<World>,
+    blocks: Res<Registry<BlockState>>,
+    mut target: ResMut<TargetedBlock>,
+) {
+    let origin = head.translation();
+    let dir = head.forward().as_vec3();
+    let hit = world.raycast(origin, dir, target.reach, |_, state| {
+        blocks
+            .get(state.voxel)
+            .is_some_and(|block| !matches!(block.model, ModelData::Empty))
+    });
+
+    // only write if the targeted block or face changed, so systems
+    // can react with `resource_changed`.
+    let changed = match (&target.hit, &hit) {
+        (Some(a), Some(b)) => a.pos != b.pos || a.face != b.face || a.state != b.state,
+        (None, None) => false,
+        _ => true,
+    };
+
+    if changed {
+        target.hit = hit;
+    }
+}
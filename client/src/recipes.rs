@@ -0,0 +1,30 @@
+use bevy::prelude::*;
+use data::{
+    fs::packs::AssetPackReader,
+    info::RootPath,
+    items::Item,
+    recipes::{Recipe, read_recipes},
+    registry::Registry,
+};
+
+use crate::packs::data_dirs;
+
+/// Read the recipes again, should run when the mounted packs change.
+/// Recipes that fail to load are skipped, and joining a server that has
+/// a recipe the client doesn't fails while synchronizing registries.
+pub fn load_recipes(
+    root: Res<RootPath>,
+    packs: Res<AssetPackReader>,
+    items: Res<Registry<Item>>,
+    mut recipes: ResMut<Registry<Recipe>>,
+) {
+    let dirs = data_dirs(&root, &packs);
+
+    let (loaded, errors) = read_recipes(&dirs, &items);
+    for err in errors {
+        error!("{err}");
+    }
+
+    info!("Loaded {} recipes.", loaded.iter().count());
+    *recipes = loaded;
+}
@@ -15,7 +15,7 @@ use bevy::{
 use data::sequence::{RivuletState, Sequence};
 use fxhash::FxHashMap;
 use image::{RgbaImage, imageops};
-use portable_atomic::{AtomicUsize, Ordering};
+use parking_lot::Mutex;
 use serde::{Deserialize, Serialize, de::DeserializeOwned};
 
 use crate::sequences::starting::StartupSeq;
@@ -80,6 +80,11 @@ impl<M: TextureMeta> Plugin for TextureArrayPlugin<M> {
                     .run_if(in_state(StartupSeq::LoadTextures)),
                 build_in_startup::<M>
                     .run_if(in_state(StartupSeq::BuildTextureArrays)),
+                (
+                    load_new_sources::<M>
+                        .run_if(resource_changed::<TextureArraySources<M>>),
+                    rebuild_texture_array::<M>,
+                ).chain().run_if(resource_exists::<TextureArray<M>>),
             ))
         ;
     }
@@ -108,9 +113,7 @@ fn load_in_startup<M: TextureMeta>(
                     TextureSource::File(path) => {
                         handles.push(assets.load(path));
                     }
-                    TextureSource::Folder(_path) => {
-                        unimplemented!()
-                    }
+                    TextureSource::Folder(path) => skip_folder::<M>(path),
                 }
             }
 
@@ -120,6 +123,76 @@ fn load_in_startup<M: TextureMeta>(
     }
 }
 
+/// Load sources that were added after the array was built,
+/// for example by block definitions that were loaded later.
+fn load_new_sources<M: TextureMeta>(
+    sources: Res<TextureArraySources<M>>,
+    mut array: ResMut<TextureArray<M>>,
+    assets: Res<AssetServer>,
+) {
+    for source in sources.iter() {
+        match source {
+            TextureSource::File(path) => {
+                if !array.resolver.contains_key(path) && !array.pending.contains(path) {
+                    array.handles.push(assets.load(path));
+                    array.pending.push(path.clone());
+                    array.dirty = true;
+                }
+            }
+            TextureSource::Folder(path) => skip_folder::<M>(path),
+        }
+    }
+}
+
+/// Folders can't be listed through the asset server yet, so their textures aren't loaded.
+fn skip_folder<M: TextureMeta>(path: &str) {
+    warn!(
+        "[C194] Texture folder '{path}' of '{}' was skipped, only files can be loaded.",
+        TextureArray::<M>::type_path()
+    );
+}
+
+/// Rebuild the array in-place when one of its textures is modified
+/// (hot-reloaded, or replaced by a resource pack) or new textures were added.
+/// The image and table handles don't change, so materials don't need to be updated.
+///
+/// Hot-reloading requires the `bevy/file_watcher` feature.
+fn rebuild_texture_array<M: TextureMeta>(
+    mut events: MessageReader<AssetEvent<Texture<M>>>,
+    mut array: ResMut<TextureArray<M>>,
+    server: Res<AssetServer>,
+    textures: Res<Assets<Texture<M>>>,
+    mut images: ResMut<Assets<Image>>,
+    mut buffers: ResMut<Assets<ShaderStorageBuffer>>,
+) {
+    for event in events.read() {
+        if let AssetEvent::Modified { id } = event {
+            if array.handles.iter().any(|handle| handle.id() == *id) {
+                array.dirty = true;
+            }
+        }
+    }
+
+    if !array.dirty {
+        return;
+    }
+
+    // wait until every texture has finished (re)loading.
+    let mut builder = TextureArrayBuilder::new(array.handles.clone());
+    builder.count_ready(&server, &textures);
+    if !builder.is_ready() {
+        return;
+    }
+
+    builder.process(usize::MAX, &textures);
+    builder.finish_into(&mut array, &mut images, &mut buffers);
+    info!(
+        "Rebuilt texture array '{}' with {} textures.",
+        TextureArray::<M>::type_path(),
+        array.handles.len()
+    );
+}
+
 fn build_in_startup<M: TextureMeta>(
     seq: Res<Sequence<StartupSeq>>,
     mut builder: ResMut<TextureArrayBuilder<M>>,
@@ -171,8 +244,18 @@ impl<M: TextureMeta> TextureArraySources<M> {
         self.sources.push(TextureSource::Folder(path.into()));
     }
 
+    /// Add a file, if it hasn't been added already.
     pub fn add_file(&mut self, path: impl Into<String>) {
-        self.sources.push(TextureSource::File(path.into()))
+        let path = path.into();
+        if !self.contains_file(&path) {
+            self.sources.push(TextureSource::File(path))
+        }
+    }
+
+    pub fn contains_file(&self, path: &str) -> bool {
+        self.sources
+            .iter()
+            .any(|source| matches!(source, TextureSource::File(p) if p == path))
     }
 }
 
@@ -209,6 +292,18 @@ pub struct TextureArray<M: TextureMeta> {
 
     /// The GPU-side descriptor array for textures.
     gpu_data: Handle<ShaderStorageBuffer>,
+
+    /// Average color of each texture, by array index.
+    colors: Vec<[u8; 4]>,
+
+    /// Every texture in the array, kept so the array can be rebuilt.
+    handles: Vec<Handle<Texture<M>>>,
+
+    /// Paths of textures added since the last build, that aren't in the resolver yet.
+    pending: Vec<String>,
+
+    /// Whether the array needs to be rebuilt.
+    dirty: bool,
     _marker: PhantomData<M>,
 }
 
@@ -217,6 +312,21 @@ impl<M: TextureMeta> TextureArray<M> {
         self.resolver.get(name.as_ref()).copied()
     }
 
+    /// Names of every texture in the array.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.resolver.keys().map(String::as_str)
+    }
+
+    /// Whether the array is waiting to be rebuilt.
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    /// Whether a texture with this name is in the array.
+    pub fn contains(&self, name: impl AsRef<str>) -> bool {
+        self.resolver.contains_key(name.as_ref())
+    }
+
     pub fn image(&self) -> Handle<Image> {
         self.images.clone()
     }
@@ -224,10 +334,19 @@ impl<M: TextureMeta> TextureArray<M> {
     pub fn table(&self) -> Handle<ShaderStorageBuffer> {
         self.gpu_data.clone()
     }
+
+    /// The average color of the texture at this array index, for things too far away to
+    /// show the texture itself. Transparent pixels count less, so leaves keep their green.
+    pub fn average_color(&self, idx: usize) -> Option<[u8; 4]> {
+        self.colors.get(idx).copied()
+    }
 }
 
 #[derive(Resource)]
 pub struct TextureArrayBuilder<M: TextureMeta> {
+    /// Every texture that will be in the array.
+    handles: Vec<Handle<Texture<M>>>,
+
     /// Textures that are yet to be added to the array.
     remaining: Vec<Handle<Texture<M>>>,
 
@@ -251,12 +370,16 @@ pub struct TextureArrayBuilder<M: TextureMeta> {
 
     /// The output ShaderStorageBuffer
     gpu_data: Vec<M::GpuRepr>,
+
+    /// Average color of each texture.
+    colors: Vec<[u8; 4]>,
 }
 
 impl<M: TextureMeta> TextureArrayBuilder<M> {
     pub fn new(handles: Vec<Handle<Texture<M>>>) -> Self {
         Self {
             total: handles.len(),
+            handles: handles.clone(),
             remaining: handles,
             is_all_loaded: false,
             resolver: FxHashMap::default(),
@@ -264,6 +387,7 @@ impl<M: TextureMeta> TextureArrayBuilder<M> {
             max_index: 0,
             image: RgbaImage::new(0, 0),
             gpu_data: Vec::new(),
+            colors: Vec::new(),
         }
     }
 
@@ -291,7 +415,8 @@ impl<M: TextureMeta> TextureArrayBuilder<M> {
         }
 
         while let Some(i) = failed.pop() {
-            self.remaining.swap_remove(i);
+            let handle = self.remaining.swap_remove(i);
+            self.handles.retain(|h| h.id() != handle.id());
             self.total -= 1;
         }
 
@@ -313,9 +438,14 @@ impl<M: TextureMeta> TextureArrayBuilder<M> {
             }
         }
 
+        // textures with mixed resolutions are scaled to the largest, which
+        // is rounded to a power of two so every level of the mip chain is whole.
+        self.tile_size = self.tile_size.next_power_of_two();
+
         self.image = RgbaImage::new(self.tile_size, self.tile_size * (self.max_index as u32 + 1));
         self.gpu_data
             .resize_with(self.max_index + 1, || M::GpuRepr::default());
+        self.colors.resize(self.max_index + 1, [0; 4]);
 
         self.is_all_loaded = true;
         sum
@@ -332,6 +462,7 @@ impl<M: TextureMeta> TextureArrayBuilder<M> {
     fn put(&mut self, tex: &Texture<M>) {
         // create gpu descriptor for the texture.
         self.gpu_data[tex.idx] = M::as_gpu(&tex);
+        self.colors[tex.idx] = average_color(&tex.data);
 
         // resize to fit dimensions if needed.
         let resized = (tex.data.dimensions() != (self.tile_size, self.tile_size)).then(|| {
@@ -382,12 +513,16 @@ impl<M: TextureMeta> TextureArrayBuilder<M> {
             debug_assert_ne!(self.image.height(), 0);
 
             // convert to bevy image array
-            let image = convert_rgba_image_to_bevy_texture_array(self.image, self.total as u32);
+            let image = convert_rgba_image_to_bevy_texture_array(self.image, self.layers());
             let handle = images.add(image);
             TextureArray {
                 resolver: self.resolver,
                 gpu_data: buffers.add(ShaderStorageBuffer::from(self.gpu_data)),
+                colors: self.colors,
                 images: handle,
+                handles: self.handles,
+                pending: Vec::new(),
+                dirty: false,
                 _marker: PhantomData,
             }
         } else {
@@ -395,11 +530,39 @@ impl<M: TextureMeta> TextureArrayBuilder<M> {
             TextureArray {
                 resolver: FxHashMap::default(),
                 gpu_data: Handle::default(),
+                colors: Vec::new(),
                 images: Handle::default(),
+                handles: Vec::new(),
+                pending: Vec::new(),
+                dirty: false,
                 _marker: PhantomData,
             }
         }
     }
+
+    /// Replace the contents of an existing array, keeping its image and table handles.
+    pub fn finish_into(
+        self,
+        array: &mut TextureArray<M>,
+        images: &mut Assets<Image>,
+        buffers: &mut Assets<ShaderStorageBuffer>,
+    ) {
+        let layers = self.layers();
+        let image = convert_rgba_image_to_bevy_texture_array(self.image, layers);
+        let _ = images.insert(array.images.id(), image);
+        let _ = buffers.insert(array.gpu_data.id(), ShaderStorageBuffer::from(self.gpu_data));
+
+        array.resolver = self.resolver;
+        array.colors = self.colors;
+        array.handles = self.handles;
+        array.pending.clear();
+        array.dirty = false;
+    }
+
+    /// Number of layers in the output image.
+    fn layers(&self) -> u32 {
+        self.max_index as u32 + 1
+    }
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -414,15 +577,16 @@ pub enum TextureError {
 
 #[derive(TypePath)]
 pub struct TextureLoader<M: TextureMeta> {
-    next_id: AtomicUsize,
+    /// Index of each texture by path, so reloaded textures
+    /// keep the index they had when first loaded.
+    ids: Mutex<FxHashMap<String, usize>>,
     _marker: PhantomData<M>,
 }
 
 impl<M: TextureMeta> Default for TextureLoader<M> {
     fn default() -> Self {
         Self {
-            // 0 is always the debug texture
-            next_id: AtomicUsize::new(1),
+            ids: Mutex::new(FxHashMap::default()),
             _marker: PhantomData,
         }
     }
@@ -441,34 +605,84 @@ impl<M: TextureMeta> AssetLoader for TextureLoader<M> {
         &self,
         reader: &mut dyn bevy::asset::io::Reader,
         settings: &Self::Settings,
-        _load_context: &mut bevy::asset::LoadContext<'_>,
+        load_context: &mut bevy::asset::LoadContext<'_>,
     ) -> Result<Self::Asset, Self::Error> {
         let mut buf = Vec::new();
         reader.read_to_end(&mut buf).await?;
         let img = image::load_from_memory_with_format(&buf, image::ImageFormat::Png)?.to_rgba8();
+
+        let idx = {
+            let mut ids = self.ids.lock();
+            // 0 is always the debug texture
+            let next = ids.len() + 1;
+            *ids.entry(load_context.asset_path().to_string()).or_insert(next)
+        };
+
         Ok(Texture {
             meta: settings.clone(),
             data: img,
-            idx: self.next_id.fetch_add(1, Ordering::Relaxed),
+            idx,
         })
     }
 }
 
+/// Average color of an image, with each pixel weighted by its alpha.
+/// Colors stay in sRGB, like the pixels of the texture.
+fn average_color(img: &RgbaImage) -> [u8; 4] {
+    let mut sum = [0u64; 3];
+    let mut alpha = 0u64;
+    for pixel in img.pixels() {
+        let a = pixel[3] as u64;
+        for (sum, channel) in sum.iter_mut().zip(&pixel.0) {
+            *sum += *channel as u64 * a;
+        }
+        alpha += a;
+    }
+
+    if alpha == 0 {
+        return [0; 4];
+    }
+
+    let pixels = (img.width() as u64 * img.height() as u64).max(1);
+    let [r, g, b] = sum.map(|sum| (sum / alpha) as u8);
+    [r, g, b, (alpha / pixels) as u8]
+}
+
+/// Convert a column of square tiles into an array texture with a full mip chain.
 fn convert_rgba_image_to_bevy_texture_array(img: RgbaImage, layers: u32) -> Image {
     let (width, height) = img.dimensions();
-    let raw = img.into_raw();
+    let tile = height / layers;
+    let mip_levels = u32::min(width, tile).max(1).ilog2() + 1;
+
+    // layer-major order, each layer is followed by its mips.
+    let mut raw = Vec::with_capacity(img.as_raw().len() * 4 / 3);
+    for layer in 0..layers {
+        let mut mip = imageops::crop_imm(&img, 0, layer * tile, width, tile).to_image();
+        raw.extend_from_slice(mip.as_raw());
+        for _ in 1..mip_levels {
+            mip = imageops::resize(
+                &mip,
+                u32::max(mip.width() / 2, 1),
+                u32::max(mip.height() / 2, 1),
+                imageops::FilterType::Triangle,
+            );
+            raw.extend_from_slice(mip.as_raw());
+        }
+    }
 
-    Image::new(
+    let mut image = Image::new_uninit(
         Extent3d {
             width,
-            height: (height / layers),
+            height: tile,
             depth_or_array_layers: layers,
         },
         TextureDimension::D2,
-        raw,
         TextureFormat::Rgba8UnormSrgb,
         RenderAssetUsages::all(),
-    )
+    );
+    image.texture_descriptor.mip_level_count = mip_levels;
+    image.data = Some(raw);
+    image
 }
 
 fn get_debug_texture() -> RgbaImage {
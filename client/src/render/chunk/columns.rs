@@ -0,0 +1,386 @@
+//! Column prisms drawn in place of the meshes of chunks beyond the column radius.
+//!
+//! Each chunk is split into columns of `COLUMN_SIZE`² voxels, reduced to the height of its
+//! top block and the average color of that block's top texture. The columns of every far
+//! chunk in a region are written to one storage buffer, and drawn by a single template mesh
+//! whose vertices only know which column they belong to, so far chunks aren't meshed at all
+//! and each region is one draw. The shader moves and sizes the prisms, see columns.wgsl.
+
+use std::collections::VecDeque;
+
+use bevy::{
+    asset::RenderAssetUsages,
+    camera::visibility::NoFrustumCulling,
+    light::NotShadowCaster,
+    mesh::{Indices, MeshVertexAttribute, PrimitiveTopology, VertexAttributeValues, VertexFormat},
+    prelude::*,
+    render::{
+        render_resource::{AsBindGroup, ShaderType},
+        storage::ShaderStorageBuffer,
+    },
+};
+use data::{
+    blockstates::{BlockState, ModelData, quad::FULL_BLOCK},
+    registry::Registry,
+};
+use fxhash::{FxHashMap, FxHashSet};
+use math::axis::Axis;
+use world::{World, region::chunk::flags::ChunkState};
+
+use super::SKY_DARKNESS_STEP;
+use crate::{
+    render::{
+        atlases::{BlockTextureMeta, TextureArray},
+        shaders::COLUMN_SHADER,
+    },
+    world::time::SkyDarkness,
+};
+
+/// Width of a column, in voxels. Must divide 32.
+const COLUMN_SIZE: i32 = 4;
+
+/// Most chunks whose columns are built per frame.
+const CHUNKS_PER_FRAME: usize = 16;
+
+/// How far below its top a column reaches when its neighbour isn't loaded,
+/// so there is no gap to see through at the edge of the loaded world.
+const SKIRT_DEPTH: i32 = 16;
+
+/// Fewest columns a template mesh has room for.
+const MIN_CAPACITY: u32 = 64;
+
+/// `[x, y, z, axis]`, the corner of the unit prism and the index of the `Axis` of its face.
+/// Shares the id of `Mesh::ATTRIBUTE_POSITION`, so it is bound to location 0.
+pub const ATTRIBUTE_PRISM_CORNER: MeshVertexAttribute =
+    MeshVertexAttribute::new("prism_corner", 0, VertexFormat::Uint8x4);
+
+/// Index of the column the vertex belongs to in the storage buffer.
+/// Shares the id of `Mesh::ATTRIBUTE_NORMAL`, so it is bound to location 1.
+pub const ATTRIBUTE_PRISM_COLUMN: MeshVertexAttribute =
+    MeshVertexAttribute::new("prism_column", 1, VertexFormat::Uint32);
+
+/// A column prism, as read by columns.wgsl.
+#[derive(ShaderType, Copy, Clone, Default, Debug)]
+pub struct ColumnInstance {
+    /// Bottom corner of the column, relative to the origin of its region.
+    pub pos: IVec3,
+    pub height: u32,
+    pub size: u32,
+
+    /// sRGB color of the top of the column, packed as RGBA.
+    pub color: u32,
+}
+
+#[derive(Asset, TypePath, AsBindGroup, Clone, Debug)]
+pub struct ColumnMaterial {
+    /// The `ColumnInstance`s of a region.
+    #[storage(0, read_only)]
+    pub columns: Handle<ShaderStorageBuffer>,
+
+    /// Fraction of the sky light taken away, see `SkyDarkness`.
+    #[uniform(1)]
+    pub sky_darkness: f32,
+}
+
+impl Material for ColumnMaterial {
+    fn vertex_shader() -> bevy::shader::ShaderRef {
+        COLUMN_SHADER.into()
+    }
+
+    fn fragment_shader() -> bevy::shader::ShaderRef {
+        COLUMN_SHADER.into()
+    }
+}
+
+/// The chunks drawn as columns, and the entity that draws the columns of each region.
+#[derive(Resource, Default)]
+pub struct FarColumns {
+    /// Columns of each chunk by its origin, in the region they are in.
+    regions: FxHashMap<IVec2, FxHashMap<IVec2, Vec<ColumnInstance>>>,
+
+    /// Entity drawing the columns of each region.
+    entities: FxHashMap<IVec2, Entity>,
+
+    /// Chunks whose columns need to be built.
+    queue: VecDeque<IVec2>,
+    queued: FxHashSet<IVec2>,
+
+    /// Regions whose columns changed since they were last written to their buffer.
+    dirty: FxHashSet<IVec2>,
+
+    /// Template meshes by the number of columns they have room for.
+    templates: FxHashMap<u32, Handle<Mesh>>,
+}
+
+impl FarColumns {
+    /// Draw the chunk containing this XZ position as columns, (re)building them.
+    pub fn add_chunk(&mut self, origin: IVec2) {
+        let origin = origin & !31;
+        if self.queued.insert(origin) {
+            self.queue.push_back(origin);
+        }
+    }
+
+    /// Stop drawing the chunk containing this XZ position as columns.
+    pub fn remove_chunk(&mut self, origin: IVec2) {
+        let origin = origin & !31;
+        if self.queued.remove(&origin) {
+            self.queue.retain(|queued| *queued != origin);
+        }
+
+        let region = origin & !511;
+        if let Some(chunks) = self.regions.get_mut(&region)
+            && chunks.remove(&origin).is_some()
+        {
+            self.dirty.insert(region);
+        }
+    }
+
+    /// Number of chunks drawn as columns.
+    pub fn len(&self) -> usize {
+        self.regions.values().map(|chunks| chunks.len()).sum()
+    }
+
+    /// Forget every chunk, returning the entities of the regions.
+    pub fn drain(&mut self) -> impl Iterator<Item = Entity> {
+        self.regions.clear();
+        self.queue.clear();
+        self.queued.clear();
+        self.dirty.clear();
+        self.entities.drain().map(|(_, entity)| entity)
+    }
+
+    /// The template mesh with room for at least this many columns.
+    fn template(&mut self, columns: usize, meshes: &mut Assets<Mesh>) -> Handle<Mesh> {
+        let capacity = (columns as u32).next_power_of_two().max(MIN_CAPACITY);
+        self.templates
+            .entry(capacity)
+            .or_insert_with(|| meshes.add(template_mesh(capacity)))
+            .clone()
+    }
+}
+
+/// Build the columns of queued chunks, and write the columns of the regions
+/// that changed to their buffers.
+pub fn build_far_columns(
+    mut columns: ResMut<FarColumns>,
+    world: Res<World>,
+    blocks: Res<Registry<BlockState>>,
+    atlas: Res<TextureArray<BlockTextureMeta>>,
+    darkness: Res<SkyDarkness>,
+    handles: Query<&MeshMaterial3d<ColumnMaterial>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColumnMaterial>>,
+    mut buffers: ResMut<Assets<ShaderStorageBuffer>>,
+    mut commands: Commands,
+) {
+    let columns = &mut *columns;
+    for _ in 0..CHUNKS_PER_FRAME {
+        let Some(origin) = columns.queue.pop_front() else {
+            break;
+        };
+        columns.queued.remove(&origin);
+
+        let region = origin & !511;
+        let chunks = columns.regions.entry(region).or_default();
+        match build_chunk_columns(&world, &blocks, &atlas, origin) {
+            Some(built) => chunks.insert(origin, built),
+            None => chunks.remove(&origin),
+        };
+        columns.dirty.insert(region);
+    }
+
+    for region in columns.dirty.drain().collect::<Vec<_>>() {
+        let instances = columns
+            .regions
+            .get(&region)
+            .into_iter()
+            .flat_map(|chunks| chunks.values().flatten().copied())
+            .collect::<Vec<_>>();
+
+        if instances.is_empty() {
+            columns.regions.remove(&region);
+            if let Some(entity) = columns.entities.remove(&region) {
+                commands.entity(entity).despawn();
+            }
+            continue;
+        }
+
+        let mesh = columns.template(instances.len(), &mut meshes);
+        let buffer = buffers.add(ShaderStorageBuffer::from(instances));
+        let material = columns
+            .entities
+            .get(&region)
+            .and_then(|entity| handles.get(*entity).ok());
+
+        match material {
+            // a new buffer is made, since the bind group of the material keeps the old one.
+            Some(handle) => {
+                if let Some(material) = materials.get_mut(&handle.0) {
+                    material.columns = buffer;
+                }
+                let entity = columns.entities[&region];
+                commands.entity(entity).insert(Mesh3d(mesh));
+            }
+            None => {
+                let entity = commands
+                    .spawn((
+                        Transform::from_xyz(region.x as f32, 0.0, region.y as f32),
+                        Mesh3d(mesh),
+                        MeshMaterial3d(materials.add(ColumnMaterial {
+                            columns: buffer,
+                            sky_darkness: darkness.value,
+                        })),
+                        // the template mesh has no bounds, its vertices are placed by the shader.
+                        NoFrustumCulling,
+                        NotShadowCaster,
+                    ))
+                    .id();
+                columns.entities.insert(region, entity);
+            }
+        }
+    }
+}
+
+/// Darken the sky light of the columns.
+pub fn apply_column_sky_darkness(
+    darkness: Res<SkyDarkness>,
+    mut materials: ResMut<Assets<ColumnMaterial>>,
+) {
+    let changed = materials
+        .iter()
+        .filter(|(_, material)| (material.sky_darkness - darkness.value).abs() >= SKY_DARKNESS_STEP)
+        .map(|(id, _)| id)
+        .collect::<Vec<_>>();
+
+    for id in changed {
+        if let Some(material) = materials.get_mut(id) {
+            material.sky_darkness = darkness.value;
+        }
+    }
+}
+
+/// Build the columns of a chunk, or None if it isn't loaded.
+fn build_chunk_columns(
+    world: &World,
+    blocks: &Registry<BlockState>,
+    atlas: &TextureArray<BlockTextureMeta>,
+    origin: IVec2,
+) -> Option<Vec<ColumnInstance>> {
+    let loaded = world
+        .get_chunk(origin)
+        .is_some_and(|chunk| chunk.load_state() == ChunkState::Loaded);
+    if !loaded {
+        return None;
+    }
+
+    // tops of the columns of the chunk, and a border of columns in the neighbouring chunks.
+    let n = 32 / COLUMN_SIZE;
+    let size = n + 2;
+    let tops = (0..size * size)
+        .map(|i| {
+            let cell = IVec2::new(i % size - 1, i / size - 1);
+            let xz = origin + cell * COLUMN_SIZE + COLUMN_SIZE / 2;
+            sample_top(world, blocks, atlas, xz)
+        })
+        .collect::<Vec<_>>();
+
+    let region = origin & !511;
+    let mut columns = Vec::with_capacity((n * n) as usize);
+    for z in 0..n {
+        for x in 0..n {
+            let top_at = |dx: i32, dz: i32| tops[((z + 1 + dz) * size + x + 1 + dx) as usize];
+            let Some((top, color)) = top_at(0, 0) else {
+                continue;
+            };
+
+            // the sides reach down to the lowest neighbour, so there are no gaps between them.
+            let bottom = [(1, 0), (-1, 0), (0, 1), (0, -1)]
+                .into_iter()
+                .map(|(dx, dz)| top_at(dx, dz).map_or(top - SKIRT_DEPTH, |(top, _)| top))
+                .fold(top - 1, i32::min)
+                .max(world.min_y());
+
+            let pos = origin - region + IVec2::new(x, z) * COLUMN_SIZE;
+            columns.push(ColumnInstance {
+                pos: IVec3::new(pos.x, bottom, pos.y),
+                height: (top - bottom) as u32,
+                size: COLUMN_SIZE as u32,
+                color: u32::from_le_bytes(color),
+            });
+        }
+    }
+
+    Some(columns)
+}
+
+/// The Y above the highest block of the column at `xz`, and the color of its top face.
+/// Blocks without a model, and crosses like flowers, are looked through.
+fn sample_top(
+    world: &World,
+    blocks: &Registry<BlockState>,
+    atlas: &TextureArray<BlockTextureMeta>,
+    xz: IVec2,
+) -> Option<(i32, [u8; 4])> {
+    for y in (world.min_y()..world.max_y()).rev() {
+        let state = world.get_state(IVec3::new(xz.x, y, xz.y))?;
+        let Some(block) = blocks.get(state.voxel) else {
+            continue;
+        };
+
+        let texture = match &block.model {
+            ModelData::Empty | ModelData::Cross { .. } => continue,
+            ModelData::Full { textures } => textures[Axis::PosY],
+            ModelData::Elements(elements) => {
+                match elements
+                    .iter()
+                    .find_map(|element| element.faces[Axis::PosY])
+                {
+                    Some(face) => face.texture,
+                    None => continue,
+                }
+            }
+        };
+
+        let color = atlas.average_color(texture as usize).unwrap_or([255; 4]);
+        return Some((y + 1, color));
+    }
+
+    None
+}
+
+/// A mesh of `capacity` unit prisms, each with the index of its column.
+/// The bottom faces are left out, since they are never seen from above the terrain.
+fn template_mesh(capacity: u32) -> Mesh {
+    let faces = Axis::ALL.into_iter().filter(|axis| *axis != Axis::NegY);
+    let num_verts = capacity as usize * 4 * faces.clone().count();
+
+    let mut corners = Vec::with_capacity(num_verts);
+    let mut columns = Vec::with_capacity(num_verts);
+    let mut indices = Vec::with_capacity(num_verts / 4 * 6);
+    for column in 0..capacity {
+        for axis in faces.clone() {
+            let base = corners.len() as u32;
+            for vert in FULL_BLOCK[axis].0 {
+                let [x, y, z] = vert.pos.map(|v| (v / 16) as u8);
+                corners.push([x, y, z, axis as u8]);
+                columns.push(column);
+            }
+            indices.extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3]);
+        }
+    }
+
+    Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::RENDER_WORLD,
+    )
+    .with_inserted_indices(Indices::U32(indices))
+    .with_inserted_attribute(
+        ATTRIBUTE_PRISM_CORNER,
+        VertexAttributeValues::Uint8x4(corners),
+    )
+    .with_inserted_attribute(
+        ATTRIBUTE_PRISM_COLUMN,
+        VertexAttributeValues::Uint32(columns),
+    )
+}
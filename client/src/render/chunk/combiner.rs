@@ -1,15 +1,40 @@
 use bevy::{
     asset::RenderAssetUsages,
+    math::Vec3,
     mesh::{
         Indices, Mesh, MeshVertexAttribute, PrimitiveTopology, VertexAttributeValues, VertexFormat,
     },
 };
 use data::blockstates::{
     Transparency,
-    quad::{Normal, Quad, Vertex},
+    quad::{Normal, Quad, QuadLight, QuadUv, Vertex},
 };
 use math::axis::{AxisArray, AxisMask};
 
+/// Voxel-space position and texture index, `[x, y, z, texture]`.
+pub const ATTRIBUTE_VOXEL_POS: MeshVertexAttribute =
+    MeshVertexAttribute::new("voxel_pos", 0, VertexFormat::Sint16x4);
+
+/// Face normal, `w` is the axis index or 6 if unaligned.
+pub const ATTRIBUTE_VOXEL_NORM: MeshVertexAttribute =
+    MeshVertexAttribute::new("voxel_norm", 1, VertexFormat::Snorm8x4);
+
+/// Per-vertex `[sky, block, ao, 0]`, see `QuadLight`.
+pub const ATTRIBUTE_VOXEL_LIGHT: MeshVertexAttribute =
+    MeshVertexAttribute::new("voxel_light", 2, VertexFormat::Uint8x4);
+
+/// Per-vertex `[u, v, explicit, 0]`, see `QuadUv`.
+pub const ATTRIBUTE_VOXEL_UV: MeshVertexAttribute =
+    MeshVertexAttribute::new("voxel_uv", 3, VertexFormat::Uint8x4);
+
+/// A quad and its per-vertex lighting and texture coordinates.
+#[derive(Copy, Clone)]
+struct LitQuad {
+    verts: [Vertex; 4],
+    light: QuadLight,
+    uv: QuadUv,
+}
+
 #[derive(Default)]
 pub struct QuadCombiner {
     groups: [Group; 3],
@@ -22,8 +47,28 @@ impl QuadCombiner {
         }
     }
 
-    pub fn add(&mut self, quad: Quad, alpha: Transparency, normal: Normal) {
-        self.groups[alpha as usize].push(quad.0, normal)
+    pub fn add(&mut self, quad: Quad, light: QuadLight, alpha: Transparency, normal: Normal) {
+        self.add_with_uv(quad, light, QuadUv::WORLD, alpha, normal)
+    }
+
+    /// Add a quad with explicit texture coordinates.
+    /// Quads with explicit texture coordinates are never merged.
+    pub fn add_with_uv(
+        &mut self,
+        quad: Quad,
+        light: QuadLight,
+        uv: QuadUv,
+        alpha: Transparency,
+        normal: Normal,
+    ) {
+        self.groups[alpha as usize].push(
+            LitQuad {
+                verts: quad.0,
+                light,
+                uv,
+            },
+            normal,
+        )
     }
 
     pub fn clear_all(&mut self) {
@@ -44,12 +89,23 @@ impl QuadCombiner {
         group.combine_on_axes(axes);
         group.build_on_axes(axes, unaligned)
     }
+
+    /// Combine the quads and build a mesh with the quads sorted back-to-front
+    /// from the eye, which is relative to the mesh origin in voxels.
+    ///
+    /// Blended geometry is drawn in index order, so the quads of a mesh
+    /// have to be sorted for overlapping transparent faces to look right.
+    pub fn combine_sorted(&mut self, alpha: Transparency, eye: Vec3) -> Option<Mesh> {
+        let group = &mut self.groups[alpha as usize];
+        group.combine_on_axes(AxisMask::full());
+        group.build_sorted(eye * 16.0)
+    }
 }
 
 #[derive(Default)]
 struct Group {
-    aligned: AxisArray<Vec<[Vertex; 4]>>,
-    unaligned: Vec<[Vertex; 4]>,
+    aligned: AxisArray<Vec<LitQuad>>,
+    unaligned: Vec<LitQuad>,
     normals: Vec<[i8; 4]>,
 }
 
@@ -62,11 +118,11 @@ impl Group {
         }
     }
 
-    fn push(&mut self, verts: [Vertex; 4], norm: Normal) {
+    fn push(&mut self, quad: LitQuad, norm: Normal) {
         match norm {
-            Normal::Aligned(axis) => self.aligned[axis].push(verts),
+            Normal::Aligned(axis) => self.aligned[axis].push(quad),
             Normal::Unaligned(norm) => {
-                self.unaligned.push(verts);
+                self.unaligned.push(quad);
                 self.normals.push([norm[0], norm[1], norm[2], 6]);
             }
         }
@@ -103,182 +159,443 @@ impl Group {
 
         // allocate space in the output
         let num_verts = num_quads * 4;
-        let mut verts = Vec::with_capacity(num_verts);
+        let mut verts = Vec::<Vertex>::with_capacity(num_verts);
         let mut norms = Vec::with_capacity(num_verts);
+        let mut lights = Vec::<[u8; 4]>::with_capacity(num_verts);
+        let mut uvs = Vec::<[u8; 4]>::with_capacity(num_verts);
 
-        // build vertex and normal buffer
+        // build vertex, normal, light and uv buffer
         if unaligned {
-            verts.extend_from_slice(self.unaligned.as_flattened());
-            norms.extend_from_slice(&self.normals);
+            for (quad, norm) in self.unaligned.iter().zip(&self.normals) {
+                verts.extend_from_slice(&quad.verts);
+                lights.extend_from_slice(&quad.light.0);
+                uvs.extend_from_slice(&quad.uv.0);
+                norms.resize(norms.len() + 4, *norm);
+            }
         }
         for (axis, quads) in self.aligned.iter_in(axes) {
-            verts.extend_from_slice(quads.as_flattened());
+            for quad in quads {
+                verts.extend_from_slice(&quad.verts);
+                lights.extend_from_slice(&quad.light.0);
+                uvs.extend_from_slice(&quad.uv.0);
+            }
             let norm = Normal::Aligned(axis).to_array();
             norms.resize(norms.len() + quads.len() * 4, norm);
         }
 
-        // Build index buffer.
-        let idx = if num_verts > 65535 {
-            let mut idx = Vec::<u32>::with_capacity(num_quads * 6);
-            for i in 0..num_quads as u32 {
-                let base = i * 4;
-                idx.extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3])
-            }
-            Indices::U32(idx)
-        } else {
-            let mut idx = Vec::<u16>::with_capacity(num_quads * 6);
-            for i in 0..num_quads as u16 {
-                let base = i * 4;
-                idx.extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3])
-            }
-            Indices::U16(idx)
-        };
-
-        // we have to do this because bytemuck refuses to cast Vec<Vertex> to Vec<[i16; 4]> because
-        // I've aligned Vertex to 8-bytes to make copying efficient, and [i16; 4] is aligned to 2 bytes.
-        let verts = unsafe {
-            debug_assert_eq!(
-                std::mem::size_of::<Vertex>(),
-                std::mem::size_of::<[i16; 4]>()
-            );
-            let (ptr, len, cap) = verts.into_raw_parts();
-            Vec::from_raw_parts(ptr as *mut [i16; 4], len, cap)
-        };
-
-        // put it all together
-        Some(
-            Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::all())
-                .with_inserted_indices(idx)
-                .with_inserted_attribute(
-                    MeshVertexAttribute::new("voxel_pos", 0, VertexFormat::Sint16x4),
-                    VertexAttributeValues::Sint16x4(verts),
-                )
-                .with_inserted_attribute(
-                    MeshVertexAttribute::new("voxel_norm", 1, VertexFormat::Snorm8x4),
-                    VertexAttributeValues::Snorm8x4(norms),
-                ),
-        )
+        Some(build_mesh(verts, norms, lights, uvs))
+    }
+
+    /// Construct a mesh from the (already combined!!!) quads,
+    /// ordered from furthest to nearest to the eye.
+    fn build_sorted(&self, eye: Vec3) -> Option<Mesh> {
+        let mut quads = self
+            .unaligned
+            .iter()
+            .zip(self.normals.iter().copied())
+            .chain(self.aligned.iter().flat_map(|(axis, quads)| {
+                let norm = Normal::Aligned(axis).to_array();
+                quads.iter().map(move |quad| (quad, norm))
+            }))
+            .map(|(quad, norm)| (quad_distance_sq(quad, eye), quad, norm))
+            .collect::<Vec<_>>();
+
+        if quads.is_empty() {
+            return None;
+        }
+
+        quads.sort_unstable_by(|a, b| b.0.total_cmp(&a.0));
+
+        let num_verts = quads.len() * 4;
+        let mut verts = Vec::<Vertex>::with_capacity(num_verts);
+        let mut norms = Vec::with_capacity(num_verts);
+        let mut lights = Vec::<[u8; 4]>::with_capacity(num_verts);
+        let mut uvs = Vec::<[u8; 4]>::with_capacity(num_verts);
+        for (_, quad, norm) in quads {
+            verts.extend_from_slice(&quad.verts);
+            lights.extend_from_slice(&quad.light.0);
+            uvs.extend_from_slice(&quad.uv.0);
+            norms.resize(norms.len() + 4, norm);
+        }
+
+        Some(build_mesh(verts, norms, lights, uvs))
     }
 }
 
+/// Squared distance from the center of the quad to the eye.
+fn quad_distance_sq(quad: &LitQuad, eye: Vec3) -> f32 {
+    let sum = quad.verts.iter().fold(Vec3::ZERO, |acc, v| {
+        acc + Vec3::new(v.pos[0] as f32, v.pos[1] as f32, v.pos[2] as f32)
+    });
+    (sum / 4.0).distance_squared(eye)
+}
+
+/// Build the index buffer and put the vertex buffers together into a mesh.
+fn build_mesh(
+    verts: Vec<Vertex>,
+    norms: Vec<[i8; 4]>,
+    lights: Vec<[u8; 4]>,
+    uvs: Vec<[u8; 4]>,
+) -> Mesh {
+    let num_verts = verts.len();
+    let num_quads = num_verts / 4;
+
+    // Build index buffer.
+    let idx = if num_verts > 65535 {
+        let mut idx = Vec::<u32>::with_capacity(num_quads * 6);
+        for i in 0..num_quads as u32 {
+            let base = i * 4;
+            idx.extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3])
+        }
+        Indices::U32(idx)
+    } else {
+        let mut idx = Vec::<u16>::with_capacity(num_quads * 6);
+        for i in 0..num_quads as u16 {
+            let base = i * 4;
+            idx.extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3])
+        }
+        Indices::U16(idx)
+    };
+
+    // we have to do this because bytemuck refuses to cast Vec<Vertex> to Vec<[i16; 4]> because
+    // I've aligned Vertex to 8-bytes to make copying efficient, and [i16; 4] is aligned to 2 bytes.
+    let verts = unsafe {
+        debug_assert_eq!(
+            std::mem::size_of::<Vertex>(),
+            std::mem::size_of::<[i16; 4]>()
+        );
+        let (ptr, len, cap) = verts.into_raw_parts();
+        Vec::from_raw_parts(ptr as *mut [i16; 4], len, cap)
+    };
+
+    // put it all together
+    Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::all())
+        .with_inserted_indices(idx)
+        .with_inserted_attribute(ATTRIBUTE_VOXEL_POS, VertexAttributeValues::Sint16x4(verts))
+        .with_inserted_attribute(ATTRIBUTE_VOXEL_NORM, VertexAttributeValues::Snorm8x4(norms))
+        .with_inserted_attribute(ATTRIBUTE_VOXEL_LIGHT, VertexAttributeValues::Uint8x4(lights))
+        .with_inserted_attribute(ATTRIBUTE_VOXEL_UV, VertexAttributeValues::Uint8x4(uvs))
+}
+
 mod combiner_fn {
 
+    // Greedy meshing of axis-aligned quads.
     // Sorting algorithm: IpnSort via `sort_unstable`.
-    // Storage: quad buffer, no sort buffer.
+    // Storage: quad buffer + rect buffer.
+    //
+    // Quads are converted to rectangles in the (u,v) plane of the face, then merged
+    // in two passes. The first pass merges quads into strips along U, the second
+    // merges strips with the same U extent along V. Quads are only merged if they
+    // are in the same plane, have the same texture, have the same winding, are
+    // uniformly lit with the same light, and have grid-locked texture coordinates.
 
-    use data::blockstates::quad::Vertex;
+    use data::blockstates::quad::{QuadLight, QuadUv, Vertex};
     use math::axis::Axis;
 
-    pub fn combine_quads(quads: &mut Vec<[Vertex; 4]>, axis: Axis) {
+    use super::LitQuad;
+
+    pub fn combine_quads(quads: &mut Vec<LitQuad>, axis: Axis) {
         if quads.len() < 2 {
             return;
         }
-        match axis {
-            Axis::PosX => {
-                sort::<3, 0, 2, 1>(quads);
-                combine::<0, 3, 1, 2>(quads);
-                if quads.len() < 2 {
-                    return;
-                }
-                sort::<3, 0, 1, 2>(quads);
-                combine::<1, 0, 2, 3>(quads);
+
+        let (d, u, v) = plane_dims(axis);
+
+        // Quads that aren't rectangles in the face plane can't be merged, keep them as-is.
+        let mut rects = Vec::with_capacity(quads.len());
+        quads.retain(|quad| match Rect::from_quad(quad, d, u, v) {
+            Some(rect) => {
+                rects.push(rect);
+                false
             }
-            Axis::NegX => {
-                sort::<2, 0, 2, 1>(quads);
-                combine::<0, 3, 1, 2>(quads);
-                if quads.len() < 2 {
-                    return;
+            None => true,
+        });
+
+        if rects.len() >= 2 {
+            // merge along U into strips.
+            rects.sort_unstable_by_key(|r| (r.d, r.texture, r.corners, r.light, r.v0, r.v1, r.u0));
+            merge(&mut rects, |a, b| {
+                if a.same_key(b) && a.v0 == b.v0 && a.v1 == b.v1 && a.u1 == b.u0 {
+                    a.u1 = b.u1;
+                    true
+                } else {
+                    false
                 }
-                sort::<2, 0, 1, 2>(quads);
-                combine::<0, 1, 3, 2>(quads);
-            }
-            Axis::PosY => {
-                sort::<3, 1, 0, 2>(quads);
-                combine::<0, 3, 1, 2>(quads);
-                if quads.len() < 2 {
-                    return;
+            });
+
+            // merge strips along V.
+            rects.sort_unstable_by_key(|r| (r.d, r.texture, r.corners, r.light, r.u0, r.u1, r.v0));
+            merge(&mut rects, |a, b| {
+                if a.same_key(b) && a.u0 == b.u0 && a.u1 == b.u1 && a.v1 == b.v0 {
+                    a.v1 = b.v1;
+                    true
+                } else {
+                    false
                 }
-                sort::<3, 1, 2, 0>(quads);
-                combine::<1, 0, 2, 3>(quads);
+            });
+        }
+
+        quads.extend(rects.iter().map(|rect| rect.to_quad(d, u, v)));
+    }
+
+    /// Index of the (depth, u, v) dimensions of a face on this axis.
+    const fn plane_dims(axis: Axis) -> (usize, usize, usize) {
+        match axis {
+            Axis::PosX | Axis::NegX => (0, 2, 1),
+            Axis::PosY | Axis::NegY => (1, 0, 2),
+            Axis::PosZ | Axis::NegZ => (2, 0, 1),
+        }
+    }
+
+    /// Merge adjacent rects in-place, where `f` merges `b` into `a` and returns true if it could.
+    fn merge(rects: &mut Vec<Rect>, mut f: impl FnMut(&mut Rect, &Rect) -> bool) {
+        let mut k = 0;
+        for j in 1..rects.len() {
+            let next = rects[j];
+            if !f(&mut rects[k], &next) {
+                k += 1;
+                rects[k] = next;
             }
-            Axis::NegY => {
-                sort::<2, 1, 0, 2>(quads);
-                combine::<0, 3, 1, 2>(quads);
-                if quads.len() < 2 {
-                    return;
-                }
-                sort::<2, 1, 2, 0>(quads);
-                combine::<0, 1, 3, 2>(quads);
+        }
+        rects.truncate(k + 1);
+    }
+
+    /// A quad as a rectangle in the plane of its face.
+    #[derive(Copy, Clone, Debug)]
+    struct Rect {
+        /// Position of the plane on the depth axis.
+        d: i16,
+        texture: i16,
+
+        /// For each vertex, 2 bits that describe which corner it is at.
+        /// Bit 0 is set if at u1, bit 1 is set if at v1. This preserves winding.
+        corners: u8,
+
+        /// Light of every vertex.
+        light: [u8; 4],
+
+        u0: i16,
+        u1: i16,
+        v0: i16,
+        v1: i16,
+    }
+
+    impl Rect {
+        fn from_quad(quad: &LitQuad, d: usize, u: usize, v: usize) -> Option<Self> {
+            if !quad.light.is_uniform() || !quad.uv.is_world() {
+                return None;
             }
-            Axis::PosZ => {
-                sort::<3, 2, 0, 1>(quads);
-                combine::<0, 3, 1, 2>(quads);
-                if quads.len() < 2 {
-                    return;
+
+            let light = quad.light.0[0];
+            let quad = &quad.verts;
+            let texture = quad[0].texture;
+            let depth = quad[0].pos[d];
+            let (mut u0, mut u1, mut v0, mut v1) = (i16::MAX, i16::MIN, i16::MAX, i16::MIN);
+            for vert in quad {
+                if vert.pos[d] != depth || vert.texture != texture {
+                    return None;
                 }
-                sort::<3, 2, 1, 0>(quads);
-                combine::<1, 0, 2, 3>(quads);
+                u0 = u0.min(vert.pos[u]);
+                u1 = u1.max(vert.pos[u]);
+                v0 = v0.min(vert.pos[v]);
+                v1 = v1.max(vert.pos[v]);
             }
-            Axis::NegZ => {
-                sort::<2, 2, 0, 1>(quads);
-                combine::<0, 3, 1, 2>(quads);
-                if quads.len() < 2 {
-                    return;
-                }
-                sort::<2, 2, 1, 0>(quads);
-                combine::<0, 1, 3, 2>(quads);
+
+            if u0 == u1 || v0 == v1 {
+                return None;
             }
-        }
-    }
 
-    fn sort<
-        const SV: usize, // index of vertex to sort on
-        const A1: usize, // index of least significant dimension
-        const A2: usize, // index of middle dimension
-        const A3: usize, // index of linear dimension
-    >(
-        quads: &mut Vec<[Vertex; 4]>,
-    ) {
-        quads.sort_unstable_by(|a, b| {
-            let (a, b) = (a[SV].pos, b[SV].pos);
-            if a[A1] != b[A1] {
-                return a[A1].cmp(&b[A1]);
+            let mut corners = 0;
+            let mut seen = 0u8;
+            for (i, vert) in quad.iter().enumerate() {
+                let cu = if vert.pos[u] == u1 {
+                    1
+                } else if vert.pos[u] == u0 {
+                    0
+                } else {
+                    return None;
+                };
+                let cv = if vert.pos[v] == v1 {
+                    1
+                } else if vert.pos[v] == v0 {
+                    0
+                } else {
+                    return None;
+                };
+                let corner = cu | (cv << 1);
+                seen |= 1 << corner;
+                corners |= corner << (i * 2);
             }
-            if a[A2] != b[A2] {
-                return a[A2].cmp(&b[A2]);
+
+            // every corner of the rectangle must be used exactly once.
+            if seen != 0b1111 {
+                return None;
             }
-            a[A3].cmp(&b[A3])
-        });
-    }
 
-    fn combine<const A1: usize, const B1: usize, const A2: usize, const B2: usize>(
-        quads: &mut Vec<[Vertex; 4]>,
-    ) {
-        let mut i = 0;
-        let mut j = 1;
-        let mut k = 0;
+            Some(Self {
+                d: depth,
+                texture,
+                corners,
+                light,
+                u0,
+                u1,
+                v0,
+                v1,
+            })
+        }
 
-        loop {
-            if quads[j - 1][A1] != quads[j][B1] || quads[j - 1][A2] != quads[j][B2] {
-                quads[k][A1] = quads[j - 1][A1];
-                quads[k][A2] = quads[j - 1][A2];
-                quads[k][B1] = quads[i][B1];
-                quads[k][B2] = quads[i][B2];
-                k += 1;
-                i = j;
+        fn same_key(&self, other: &Self) -> bool {
+            self.d == other.d
+                && self.texture == other.texture
+                && self.corners == other.corners
+                && self.light == other.light
+        }
+
+        fn to_quad(&self, d: usize, u: usize, v: usize) -> LitQuad {
+            LitQuad {
+                verts: std::array::from_fn(|i| {
+                    let corner = (self.corners >> (i * 2)) & 0b11;
+                    let mut pos = [0; 3];
+                    pos[d] = self.d;
+                    pos[u] = if corner & 1 != 0 { self.u1 } else { self.u0 };
+                    pos[v] = if corner & 2 != 0 { self.v1 } else { self.v0 };
+                    Vertex::new(pos, self.texture)
+                }),
+                light: QuadLight([self.light; 4]),
+                uv: QuadUv::WORLD,
             }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::IVec3;
+    use data::blockstates::quad::FULL_BLOCK;
+    use math::axis::Axis;
+
+    use super::*;
+
+    /// Light of a face in the shade, to tell it apart from `QuadLight::FULL`.
+    const SHADE: QuadLight = QuadLight([[8, 0, 3, 0]; 4]);
+
+    /// The face of the voxel on this axis.
+    fn face(axis: Axis, voxel: IVec3, texture: i16, light: QuadLight) -> LitQuad {
+        let offs = (voxel * 16).to_array().map(|n| n as i16);
+        LitQuad {
+            verts: FULL_BLOCK[axis].offset_with_texture(offs, texture).0,
+            light,
+            uv: QuadUv::WORLD,
+        }
+    }
+
+    /// Faces on the top of the voxels, textured and lit alike.
+    fn top_faces(voxels: impl IntoIterator<Item = (i32, i32)>) -> Vec<LitQuad> {
+        voxels
+            .into_iter()
+            .map(|(x, z)| face(Axis::PosY, IVec3::new(x, 0, z), 1, QuadLight::FULL))
+            .collect()
+    }
+
+    /// Faces on the top of a row of voxels along X.
+    fn row(textures: [i16; 4], lights: [QuadLight; 4]) -> Vec<LitQuad> {
+        (0..4)
+            .map(|x| {
+                face(
+                    Axis::PosY,
+                    IVec3::new(x as i32, 0, 0),
+                    textures[x],
+                    lights[x],
+                )
+            })
+            .collect()
+    }
 
-            j += 1;
-            if j == quads.len() {
-                quads[k][A1] = quads[j - 1][A1];
-                quads[k][A2] = quads[j - 1][A2];
-                quads[k][B1] = quads[i][B1];
-                quads[k][B2] = quads[i][B2];
-                break;
+    /// Which way the vertices of the quad wind around it.
+    fn winding(quad: &LitQuad) -> IVec3 {
+        let p = quad.verts.map(|v| IVec3::from_array(v.pos.map(i32::from)));
+        (p[1] - p[0]).cross(p[2] - p[0]).signum()
+    }
+
+    #[test]
+    fn flat_plane_merges_into_one_quad() {
+        let mut quads = top_faces((0..4).flat_map(|x| (0..4).map(move |z| (x, z))));
+        let y = quads[0].verts[0].pos[1];
+        combiner_fn::combine_quads(&mut quads, Axis::PosY);
+
+        assert_eq!(quads.len(), 1);
+        for vert in quads[0].verts {
+            assert_eq!(vert.texture, 1);
+            assert_eq!(vert.pos[1], y);
+            assert!([0, 64].contains(&vert.pos[0]) && [0, 64].contains(&vert.pos[2]));
+        }
+        assert_eq!(quads[0].light, QuadLight::FULL);
+    }
+
+    #[test]
+    fn differing_texture_or_light_is_not_merged() {
+        let mut quads = row([1, 1, 2, 2], [QuadLight::FULL; 4]);
+        combiner_fn::combine_quads(&mut quads, Axis::PosY);
+        assert_eq!(quads.len(), 2);
+
+        let mut quads = row(
+            [1; 4],
+            [QuadLight::FULL, QuadLight::FULL, SHADE, QuadLight::FULL],
+        );
+        combiner_fn::combine_quads(&mut quads, Axis::PosY);
+        assert_eq!(quads.len(), 3);
+
+        // faces with ambient occlusion on some corners are never merged.
+        let mut occluded = SHADE;
+        occluded.0[0][2] = 1;
+        let mut quads = row([1; 4], [occluded; 4]);
+        combiner_fn::combine_quads(&mut quads, Axis::PosY);
+        assert_eq!(quads.len(), 4);
+    }
+
+    #[test]
+    fn merges_keep_winding_and_normal() {
+        for axis in Axis::ALL {
+            // the two axes of the plane of the faces.
+            let [a, b] = [IVec3::X, IVec3::Y, IVec3::Z]
+                .into_iter()
+                .filter(|dir| dir.dot(axis.as_ivec3()) == 0)
+                .collect::<Vec<_>>()[..]
+            else {
+                unreachable!();
+            };
+
+            let expected = winding(&face(axis, IVec3::ZERO, 1, QuadLight::FULL));
+            let mut combiner = QuadCombiner::new();
+            for voxel in [IVec3::ZERO, a, b, a + b] {
+                let quad = face(axis, voxel, 1, QuadLight::FULL);
+                combiner.add(
+                    Quad(quad.verts),
+                    quad.light,
+                    Transparency::Opaque,
+                    Normal::Aligned(axis),
+                );
             }
+
+            let group = &mut combiner.groups[Transparency::Opaque as usize];
+            group.combine_on_axes(AxisMask::full());
+            assert_eq!(group.aligned[axis].len(), 1, "{axis:?}");
+            assert_eq!(winding(&group.aligned[axis][0]), expected, "{axis:?}");
+
+            let mesh = group.build_on_axes(AxisMask::full(), true).unwrap();
+            let Some(VertexAttributeValues::Snorm8x4(norms)) = mesh.attribute(ATTRIBUTE_VOXEL_NORM)
+            else {
+                panic!("The mesh has no normals.");
+            };
+            assert_eq!(norms, &[Normal::Aligned(axis).to_array(); 4]);
         }
+    }
 
-        debug_assert!(k < quads.len());
-        unsafe { quads.set_len(k + 1) }
+    #[test]
+    fn l_shape_merges_into_two_quads() {
+        // a strip of three along X, and a column of two more along Z from its start.
+        let mut quads = top_faces([(0, 0), (1, 0), (2, 0), (0, 1), (0, 2)]);
+        combiner_fn::combine_quads(&mut quads, Axis::PosY);
+        assert_eq!(quads.len(), 2);
     }
 }
@@ -0,0 +1,110 @@
+//! Simplified meshes for subchunks beyond the full-detail radius.
+//!
+//! The subchunk is downsampled into cells of `scale`³ voxels. A cell is solid if at
+//! least half of its voxels are opaque cubes, and it takes the textures of the highest
+//! opaque cube inside it, so the surface of the terrain keeps its look from a distance.
+//! Only the opaque pass is built, transparent and non-cube blocks are dropped.
+
+use bevy::math::{IVec3, ivec3};
+use data::{
+    blockstates::{
+        BlockState, ModelData, Transparency,
+        quad::{FULL_BLOCK, Normal, Quad, QuadLight},
+    },
+    registry::Registry,
+};
+use math::axis::{Axis, AxisArray};
+
+use super::{GetBlock, combiner::QuadCombiner};
+
+/// Build the mesh of the subchunk at `origin`, downsampled by `scale`.
+/// `scale` must be a power of two no larger than 32.
+pub fn build_lod_subchunk<G: GetBlock>(
+    combiner: &mut QuadCombiner,
+    get: &G,
+    blocks: &Registry<BlockState>,
+    origin: IVec3,
+    scale: i32,
+) {
+    // cells of the subchunk, and a border of cells in the neighbouring
+    // subchunks so faces on the boundary can be culled.
+    let n = 32 / scale;
+    let size = n + 2;
+    let index = |c: IVec3| ((c.y + 1) * size * size + (c.x + 1) * size + (c.z + 1)) as usize;
+    let mut cells = vec![None; (size * size * size) as usize];
+    for y in -1..=n {
+        for x in -1..=n {
+            for z in -1..=n {
+                let c = ivec3(x, y, z);
+                cells[index(c)] = sample_cell(get, blocks, origin + c * scale, scale);
+            }
+        }
+    }
+
+    for y in 0..n {
+        for x in 0..n {
+            for z in 0..n {
+                let c = ivec3(x, y, z);
+                let Some(textures) = cells[index(c)] else {
+                    continue;
+                };
+
+                let offs = (c * scale * 16).to_array().map(|v| v as i16);
+                for axis in Axis::ALL {
+                    if cells[index(c + axis.as_ivec3())].is_some() {
+                        continue;
+                    }
+
+                    let quad = scale_quad(FULL_BLOCK[axis], scale as i16)
+                        .offset_with_texture(offs, textures[axis] as i16);
+                    let normal = Normal::Aligned(axis);
+                    combiner.add(quad, QuadLight::FULL, Transparency::Opaque, normal);
+                }
+            }
+        }
+    }
+}
+
+/// Get the textures of a cell, or "None" if less than half of it is opaque.
+fn sample_cell<'a, G: GetBlock>(
+    get: &G,
+    blocks: &'a Registry<BlockState>,
+    min: IVec3,
+    scale: i32,
+) -> Option<&'a AxisArray<u16>> {
+    let mut count = 0;
+    let mut top = None;
+    for y in (0..scale).rev() {
+        for x in 0..scale {
+            for z in 0..scale {
+                let Some(state) = get
+                    .get_block(min + ivec3(x, y, z))
+                    .and_then(|state| blocks.get(state.voxel))
+                else {
+                    continue;
+                };
+
+                if let ModelData::Full { textures } = &state.model
+                    && state.transparency == Transparency::Opaque
+                {
+                    count += 1;
+                    top.get_or_insert(textures);
+                }
+            }
+        }
+    }
+
+    if count * 2 >= scale * scale * scale {
+        top
+    } else {
+        None
+    }
+}
+
+/// Scale a quad relative to the origin of its voxel.
+fn scale_quad(quad: Quad, scale: i16) -> Quad {
+    Quad(quad.0.map(|mut vert| {
+        vert.pos = vert.pos.map(|v| v * scale);
+        vert
+    }))
+}
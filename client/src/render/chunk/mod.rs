@@ -3,16 +3,36 @@ use std::collections::VecDeque;
 use bevy::{
     prelude::*,
     render::{render_resource::AsBindGroup, storage::ShaderStorageBuffer},
+    tasks::ComputeTaskPool,
 };
-use data::blockstates::Transparency;
+use data::{
+    blockstates::{BlockState, Transparency},
+    registry::Registry,
+};
+use fxhash::{FxHashMap, FxHashSet};
+use math::axis::Axis;
 use world::{Region, VoxelState, World, region::chunk_is_fully_contained};
 
-use crate::render::{
-    atlases::{BlockTextureMeta, TextureArray},
-    chunk::combiner::QuadCombiner,
+use crate::{
+    events::{BlockUpdated, ChunkUnloaded},
+    player::MainCamera,
+    render::{
+        atlases::{BlockTextureMeta, TextureArray},
+        chunk::{
+            columns::FarColumns, combiner::QuadCombiner, occlusion::FaceConnections,
+            pool::ChunkMeshPool,
+        },
+        shaders::CHUNK_SHADER,
+    },
+    settings::Settings,
+    world::time::SkyDarkness,
 };
 
+pub mod columns;
 pub mod combiner;
+pub mod lod;
+pub mod occlusion;
+pub mod pool;
 
 #[derive(Asset, TypePath, AsBindGroup, Clone, Debug)]
 pub struct ChunkMaterial {
@@ -27,118 +47,703 @@ pub struct ChunkMaterial {
     #[storage(2, read_only)]
     pub table: Handle<ShaderStorageBuffer>,
 
+    /// Fragments with an alpha below this value are discarded.
+    /// Only used by the `Mask` pass, 0.0 otherwise.
+    #[uniform(3)]
+    pub alpha_cutoff: f32,
+
+    /// Fraction of the sky light taken away, see `SkyDarkness`.
+    #[uniform(4)]
+    pub sky_darkness: f32,
+
     /// Transparency mode of the quads in the mesh.
     pub alpha: AlphaMode,
 }
 
+impl ChunkMaterial {
+    /// Construct the material used by the quads with this transparency.
+    pub fn new(atlas: &TextureArray<BlockTextureMeta>, transparency: Transparency) -> Self {
+        let (alpha, alpha_cutoff) = match transparency {
+            Transparency::Opaque => (AlphaMode::Opaque, 0.0),
+            Transparency::Mask => (AlphaMode::Mask(0.5), 0.5),
+            Transparency::Blend => (AlphaMode::Blend, 0.0),
+        };
+
+        Self {
+            atlas: atlas.image(),
+            table: atlas.table(),
+            alpha_cutoff,
+            sky_darkness: 0.0,
+            alpha,
+        }
+    }
+}
+
 impl Material for ChunkMaterial {
     fn alpha_mode(&self) -> AlphaMode {
         self.alpha
     }
 
     fn vertex_shader() -> bevy::shader::ShaderRef {
-        "shaders/chunk.wgsl".into()
+        CHUNK_SHADER.into()
     }
 
     fn fragment_shader() -> bevy::shader::ShaderRef {
-        "shaders/chunk.wgsl".into()
+        CHUNK_SHADER.into()
     }
 }
 
-struct Task {
-    origin: IVec2,
-}
-
+/// Queue of subchunks that need to be (re)meshed.
+/// Subchunks that are already queued are not added twice.
 #[derive(Resource, Default)]
 pub struct ChunkRenderQueue {
-    queue: VecDeque<Task>,
+    queue: VecDeque<SubchunkPos>,
+    queued: FxHashSet<SubchunkPos>,
 }
 
 impl ChunkRenderQueue {
-    pub fn add(&mut self, origin: IVec2) {
-        self.queue.push_back(Task {
-            origin: IVec2 {
-                x: origin.x & !31,
-                y: origin.y & !31,
-            },
-        });
+    /// Queue every subchunk in the chunk containing this XZ position.
+    pub fn add(&mut self, origin: IVec2, world: &World) {
+        let origin = IVec2::new(origin.x & !31, origin.y & !31);
+        for y in (world.min_y()..world.max_y()).step_by(32) {
+            self.add_subchunk(SubchunkPos(origin.extend(y).xzy()));
+        }
+    }
+
+    /// Queue a single subchunk, returning "false" if it was already queued.
+    pub fn add_subchunk(&mut self, pos: SubchunkPos) -> bool {
+        if self.queued.insert(pos) {
+            self.queue.push_back(pos);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Queue the subchunk containing a voxel that was changed.
+    /// If the voxel is on the border of the subchunk, the neighbouring
+    /// subchunk on that side is queued too, since its faces may be exposed or hidden.
+    pub fn add_voxel(&mut self, pt: IVec3) {
+        let pos = SubchunkPos::containing(pt);
+        self.add_subchunk(pos);
+
+        let local = pt - pos.0;
+        for axis in Axis::ALL {
+            let offs = axis.as_ivec3();
+            let n = local + offs;
+            if n.cmplt(IVec3::ZERO).any() || n.cmpge(IVec3::splat(32)).any() {
+                self.add_subchunk(SubchunkPos(pos.0 + offs * 32));
+            }
+        }
+    }
+
+    /// Number of subchunks waiting to be meshed.
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Forget the queued subchunks of the chunk containing this XZ position.
+    pub fn remove_chunk(&mut self, origin: IVec2) {
+        let origin = IVec2::new(origin.x & !31, origin.y & !31);
+        self.queue.retain(|pos| pos.0.xz() != origin);
+        self.queued.retain(|pos| pos.0.xz() != origin);
+    }
+
+    fn take(&mut self, limit: usize) -> Vec<SubchunkPos> {
+        let n = usize::min(self.queue.len(), limit);
+        let taken = self.queue.drain(0..n).collect::<Vec<_>>();
+        for pos in &taken {
+            self.queued.remove(pos);
+        }
+        taken
+    }
+}
+
+/// The render passes of a subchunk, indexed by `Transparency`.
+const PASSES: [Transparency; 3] = [
+    Transparency::Opaque,
+    Transparency::Blend,
+    Transparency::Mask,
+];
+
+/// How much meshing work is done each frame, from the video options.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct MesherBudget {
+    /// Number of tasks that mesh subchunks in parallel.
+    pub workers: usize,
+
+    /// Most subchunks meshed per frame.
+    pub meshes_per_frame: usize,
+
+    /// Most finished meshes written to their entities, and uploaded to the GPU, per frame.
+    pub uploads_per_frame: usize,
+}
+
+impl MesherBudget {
+    /// The budget of the settings, where 0 picks a value from the number of cores.
+    pub fn from_settings(settings: &Settings) -> Self {
+        let auto = Self::auto();
+        let or_auto = |value: u32, auto: usize| match value {
+            0 => auto,
+            value => value as usize,
+        };
+
+        Self {
+            workers: or_auto(settings.mesh_workers, auto.workers),
+            meshes_per_frame: or_auto(settings.meshes_per_frame, auto.meshes_per_frame),
+            uploads_per_frame: or_auto(settings.mesh_uploads_per_frame, auto.uploads_per_frame),
+        }
     }
 
-    fn take(&mut self, limit: usize) -> impl Iterator<Item = Task> {
-        self.queue.drain(0..usize::min(self.queue.len(), limit))
+    /// Half of the cores mesh, so the main and render threads keep theirs.
+    pub fn auto() -> Self {
+        let cores = std::thread::available_parallelism().map_or(4, |n| n.get());
+        let workers = (cores / 2).clamp(1, 8);
+        Self {
+            workers,
+            meshes_per_frame: workers * 8,
+            uploads_per_frame: workers * 8,
+        }
     }
 }
 
+/// Meshes of a subchunk in each pass, indexed by `Transparency`,
+/// waiting to be written to its entities.
+struct MeshedSubchunk {
+    pos: SubchunkPos,
+    scale: i32,
+    meshes: [Option<Mesh>; 3],
+
+    /// None for subchunks drawn as columns.
+    connections: Option<FaceConnections>,
+}
+
 #[derive(Resource)]
 pub struct ChunkRenderer {
-    chunks_per_tick: usize,
-    combiner: QuadCombiner,
+    budget: MesherBudget,
+
+    /// One combiner per worker.
+    combiners: Vec<QuadCombiner>,
+
+    /// Meshes that were built but not uploaded yet, oldest first.
+    ready: VecDeque<MeshedSubchunk>,
+
+    /// Number of subchunks meshed in the last tick.
+    last_meshed: usize,
+
+    /// Shared material of each pass, indexed by `Transparency`.
+    /// Created the first time a subchunk is meshed.
+    materials: Option<[Handle<ChunkMaterial>; 3]>,
+
+    /// Camera position that blended meshes were last sorted from.
+    sorted_from: Vec3,
+
+    /// How far the camera can move before blended meshes are re-sorted.
+    resort_distance: f32,
+
+    /// Blended meshes further than this from the camera are not re-sorted,
+    /// since overlapping faces are too small to notice at a distance.
+    resort_radius: f32,
+
+    /// Most blended meshes re-sorted each time the camera moved far enough,
+    /// nearest first, since each of them is a full remesh.
+    resort_limit: usize,
+
+    /// Chunks further than this from the camera (in chunks) are meshed
+    /// at a lower level of detail, see `lod::build_lod_subchunk`.
+    full_detail_radius: i32,
+
+    /// How many voxels per axis are merged into one cell in LOD meshes.
+    lod_scale: i32,
+
+    /// Chunks further than this from the camera (in chunks) aren't meshed,
+    /// they are drawn as column prisms instead, see `columns`.
+    column_radius: i32,
+
+    /// Chunk the camera was in when LOD levels were last updated.
+    lod_center: Option<IVec2>,
 }
 
 impl Default for ChunkRenderer {
     fn default() -> Self {
         Self {
-            chunks_per_tick: 1,
-            combiner: QuadCombiner::new(),
+            budget: MesherBudget::auto(),
+            combiners: Vec::new(),
+            ready: VecDeque::new(),
+            last_meshed: 0,
+            materials: None,
+            sorted_from: Vec3::ZERO,
+            resort_distance: 4.0,
+            resort_radius: 96.0,
+            resort_limit: 8,
+            full_detail_radius: 8,
+            lod_scale: 4,
+            column_radius: 12,
+            lod_center: None,
+        }
+    }
+}
+
+impl ChunkRenderer {
+    /// Set the radius (in chunks) around the camera that is meshed at full detail.
+    pub fn set_full_detail_radius(&mut self, radius: i32) {
+        self.full_detail_radius = radius.max(1);
+    }
+
+    pub fn full_detail_radius(&self) -> i32 {
+        self.full_detail_radius
+    }
+
+    /// Set the radius (in chunks) around the camera beyond which chunks are drawn as columns.
+    pub fn set_column_radius(&mut self, radius: i32) {
+        self.column_radius = radius.max(self.full_detail_radius);
+    }
+
+    pub fn column_radius(&self) -> i32 {
+        self.column_radius
+    }
+
+    pub fn last_meshed(&self) -> usize {
+        self.last_meshed
+    }
+
+    pub fn set_budget(&mut self, budget: MesherBudget) {
+        self.budget = MesherBudget {
+            workers: budget.workers.max(1),
+            meshes_per_frame: budget.meshes_per_frame.max(1),
+            uploads_per_frame: budget.uploads_per_frame.max(1),
+        };
+    }
+
+    pub fn budget(&self) -> MesherBudget {
+        self.budget
+    }
+
+    /// Number of meshes waiting to be uploaded.
+    pub fn pending_uploads(&self) -> usize {
+        self.ready.len()
+    }
+
+    /// Drop the meshes waiting to be uploaded of the chunk containing this XZ position.
+    pub fn remove_chunk(&mut self, origin: IVec2) {
+        let origin = IVec2::new(origin.x & !31, origin.y & !31);
+        self.ready.retain(|meshed| meshed.pos.0.xz() != origin);
+    }
+
+    /// The scale a subchunk should be meshed at when the camera is in chunk `center`,
+    /// or 0 if its chunk is drawn as columns.
+    fn lod_for(&self, pos: SubchunkPos, center: IVec2) -> i32 {
+        let dist = ((pos.origin().xz() >> 5) - center).abs().max_element();
+        if dist > self.column_radius {
+            0
+        } else if dist > self.full_detail_radius {
+            self.lod_scale
+        } else {
+            1
+        }
+    }
+}
+
+/// Origin of a subchunk, in world-space voxel coordinates.
+/// Always a multiple of 32 on each axis.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct SubchunkPos(IVec3);
+
+impl SubchunkPos {
+    /// Get the position of the subchunk containing this point.
+    pub const fn containing(pt: IVec3) -> Self {
+        Self(IVec3::new(pt.x & !31, pt.y & !31, pt.z & !31))
+    }
+
+    pub const fn origin(self) -> IVec3 {
+        self.0
+    }
+}
+
+/// Map of subchunks to the entities that hold their mesh in each pass.
+/// Passes of a subchunk without any quads don't have an entity.
+#[derive(Resource, Default)]
+pub struct ChunkMeshIndex {
+    entities: FxHashMap<(SubchunkPos, Transparency), Entity>,
+
+    /// Scale each subchunk was last meshed at, 1 is full detail and 0 is columns.
+    lods: FxHashMap<SubchunkPos, i32>,
+
+    /// Faces of each meshed subchunk that see each other, for occlusion culling.
+    connections: FxHashMap<SubchunkPos, FaceConnections>,
+}
+
+impl ChunkMeshIndex {
+    /// Get the mesh entity of a subchunk in a pass.
+    pub fn get(&self, pos: SubchunkPos, alpha: Transparency) -> Option<Entity> {
+        self.entities.get(&(pos, alpha)).copied()
+    }
+
+    /// Assign the mesh entity of a subchunk in a pass, returning the previous entity.
+    pub fn insert(
+        &mut self,
+        pos: SubchunkPos,
+        alpha: Transparency,
+        entity: Entity,
+    ) -> Option<Entity> {
+        self.entities.insert((pos, alpha), entity)
+    }
+
+    /// Remove the mesh entity of a subchunk in a pass.
+    pub fn remove(&mut self, pos: SubchunkPos, alpha: Transparency) -> Option<Entity> {
+        self.entities.remove(&(pos, alpha))
+    }
+
+    /// Get the scale the subchunk was last meshed at.
+    pub fn lod(&self, pos: SubchunkPos) -> Option<i32> {
+        self.lods.get(&pos).copied()
+    }
+
+    /// Remove the mesh entities of every subchunk in the chunk with this origin.
+    pub fn remove_chunk(&mut self, origin: IVec2) -> Vec<Entity> {
+        let origin = IVec2::new(origin.x & !31, origin.y & !31);
+        self.lods.retain(|pos, _| pos.0.xz() != origin);
+        self.connections.retain(|pos, _| pos.0.xz() != origin);
+        self.entities
+            .extract_if(|(pos, _), _| pos.0.xz() == origin)
+            .map(|(_, entity)| entity)
+            .collect()
+    }
+
+    /// Iterate the subchunks that have a mesh in this pass.
+    pub fn subchunks_in(&self, alpha: Transparency) -> impl Iterator<Item = SubchunkPos> {
+        self.entities
+            .keys()
+            .filter(move |(_, a)| *a == alpha)
+            .map(|(pos, _)| *pos)
+    }
+
+    /// Number of meshes across all passes.
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    /// Remove all entries, returning their entities.
+    pub fn drain(&mut self) -> impl Iterator<Item = Entity> {
+        self.lods.clear();
+        self.connections.clear();
+        self.entities.drain().map(|(_, entity)| entity)
+    }
+}
+
+/// Smallest change of the sky darkness that is applied to the materials,
+/// since changing a material uploads it to the GPU again.
+const SKY_DARKNESS_STEP: f32 = 1.0 / 256.0;
+
+/// Darken the sky light of every chunk material.
+pub fn apply_sky_darkness(
+    darkness: Res<SkyDarkness>,
+    renderer: Res<ChunkRenderer>,
+    mut materials: ResMut<Assets<ChunkMaterial>>,
+) {
+    let Some(passes) = &renderer.materials else {
+        return;
+    };
+
+    for handle in passes {
+        let changed = materials.get(handle).is_some_and(|material| {
+            (material.sky_darkness - darkness.value).abs() >= SKY_DARKNESS_STEP
+        });
+        if changed && let Some(material) = materials.get_mut(handle) {
+            material.sky_darkness = darkness.value;
         }
     }
 }
 
+/// Apply the mesher budget of the video options.
+pub fn apply_mesher_settings(settings: Res<Settings>, mut renderer: ResMut<ChunkRenderer>) {
+    renderer.set_budget(MesherBudget::from_settings(&settings));
+}
+
+/// Mesh queued subchunks on the workers, and write finished meshes to their entities.
+/// Meshing stops while a frame's worth of meshes is still waiting to be uploaded.
 pub fn render_chunks(
     mut tasks: ResMut<ChunkRenderQueue>,
     mut renderer: ResMut<ChunkRenderer>,
+    mut index: ResMut<ChunkMeshIndex>,
+    mut pool: ResMut<ChunkMeshPool>,
+    mut columns: ResMut<FarColumns>,
     atlas: Res<TextureArray<BlockTextureMeta>>,
+    blocks: Res<Registry<BlockState>>,
+    camera: Query<&GlobalTransform, With<MainCamera>>,
+    handles: Query<&Mesh3d>,
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ChunkMaterial>>,
     world: Res<World>,
 ) {
-    let stone_texture = atlas.resolve("textures/blocks/stone.png").unwrap() as i16;
-
-    for task in tasks.take(renderer.chunks_per_tick) {
-        renderer.combiner.clear_all();
-        let origin = ivec3(task.origin.x, world.min_y(), task.origin.y);
-        let is_contained = chunk_is_fully_contained(origin.xz());
-        if let Some(region) = world.get_region(origin.xz()) {
-            for y in (origin.y..world.max_y()).step_by(32) {
-                let origin = origin.with_y(y);
-                if is_contained {
-                    // build using `Region::get_state()`.
-                    subchunk_fn::build_subchunk(
-                        &mut renderer.combiner,
-                        region,
-                        origin,
-                        stone_texture,
-                    );
-                } else {
-                    // build using `World::get_state()`.
-                    subchunk_fn::build_subchunk(
-                        &mut renderer.combiner,
-                        &*world,
-                        origin,
-                        stone_texture,
-                    );
+    let eye = camera
+        .single()
+        .map(|transform| transform.translation())
+        .unwrap_or(renderer.sorted_from);
+
+    let renderer = &mut *renderer;
+    let passes = renderer
+        .materials
+        .get_or_insert_with(|| PASSES.map(|alpha| materials.add(ChunkMaterial::new(&atlas, alpha))))
+        .clone();
+
+    let budget = renderer.budget;
+    let room = budget
+        .uploads_per_frame
+        .saturating_sub(renderer.ready.len());
+    let center = eye.floor().as_ivec3().xz() >> 5;
+    let jobs = tasks
+        .take(budget.meshes_per_frame.min(room))
+        .into_iter()
+        .filter(|pos| (world.min_y()..world.max_y()).contains(&pos.origin().y))
+        .map(|pos| (pos, renderer.lod_for(pos, center)))
+        .collect::<Vec<_>>();
+    renderer.last_meshed = jobs.len();
+
+    if !jobs.is_empty() {
+        let workers = budget.workers.min(jobs.len());
+        if renderer.combiners.len() < workers {
+            renderer.combiners.resize_with(workers, QuadCombiner::new);
+        }
+
+        let (world, blocks) = (&*world, &*blocks);
+        let batches = jobs.chunks(jobs.len().div_ceil(workers));
+        let meshed = ComputeTaskPool::get().scope(|scope| {
+            for (combiner, batch) in renderer.combiners.iter_mut().zip(batches) {
+                scope.spawn(async move {
+                    batch
+                        .iter()
+                        .filter_map(|&(pos, scale)| {
+                            mesh_subchunk(combiner, world, blocks, pos, scale, eye)
+                        })
+                        .collect::<Vec<_>>()
+                });
+            }
+        });
+        renderer.ready.extend(meshed.into_iter().flatten());
+    }
+
+    for _ in 0..budget.uploads_per_frame {
+        let Some(meshed) = renderer.ready.pop_front() else {
+            break;
+        };
+
+        let (pos, origin) = (meshed.pos, meshed.pos.origin());
+        index.lods.insert(pos, meshed.scale);
+        match meshed.connections {
+            Some(connections) => index.connections.insert(pos, connections),
+            None => index.connections.remove(&pos),
+        };
+        if meshed.scale == 0 {
+            columns.add_chunk(origin.xz());
+        } else {
+            columns.remove_chunk(origin.xz());
+        }
+
+        for (alpha, mesh) in PASSES.into_iter().zip(meshed.meshes) {
+            match (mesh, index.get(pos, alpha)) {
+                // write the new mesh into the asset of the existing entity.
+                (Some(mesh), Some(entity)) => {
+                    let asset = handles
+                        .get(entity)
+                        .ok()
+                        .and_then(|handle| meshes.get_mut(&handle.0));
+                    match asset {
+                        Some(asset) => *asset = mesh,
+                        None => {
+                            let handle = pool.acquire(&mut meshes, mesh);
+                            commands.entity(entity).insert(Mesh3d(handle));
+                        }
+                    }
+                }
+                // first time this subchunk has had a mesh in this pass.
+                (Some(mesh), None) => {
+                    let entity = commands
+                        .spawn((
+                            Transform {
+                                translation: origin.as_vec3(),
+                                ..default()
+                            },
+                            Mesh3d(pool.acquire(&mut meshes, mesh)),
+                            MeshMaterial3d(passes[alpha as usize].clone()),
+                        ))
+                        .id();
+                    index.insert(pos, alpha, entity);
+                }
+                // subchunk used to have quads in this pass but is now empty.
+                (None, Some(entity)) => {
+                    if let Ok(handle) = handles.get(entity) {
+                        pool.release(&meshes, handle.0.clone());
+                    }
+                    commands.entity(entity).despawn();
+                    index.remove(pos, alpha);
                 }
+                (None, None) => {}
             }
         }
+    }
+}
+
+/// Mesh a subchunk at this scale on a worker, subchunks drawn as columns get no meshes.
+/// Returns None if the region of the subchunk isn't loaded.
+fn mesh_subchunk(
+    combiner: &mut QuadCombiner,
+    world: &World,
+    blocks: &Registry<BlockState>,
+    pos: SubchunkPos,
+    scale: i32,
+    eye: Vec3,
+) -> Option<MeshedSubchunk> {
+    let origin = pos.origin();
+    let region = world.get_region(origin.xz())?;
+    if scale == 0 {
+        return Some(MeshedSubchunk {
+            pos,
+            scale,
+            meshes: [None, None, None],
+            connections: None,
+        });
+    }
+
+    combiner.clear_all();
+    if scale > 1 {
+        // samples a border of cells outside the subchunk, so always use `World`.
+        lod::build_lod_subchunk(combiner, world, blocks, origin, scale);
+    } else if chunk_is_fully_contained(origin.xz()) {
+        // build using `Region::get_state()`.
+        subchunk_fn::build_subchunk(combiner, region, blocks, origin);
+    } else {
+        // build using `World::get_state()`.
+        subchunk_fn::build_subchunk(combiner, world, blocks, origin);
+    }
+
+    let meshes = PASSES.map(|alpha| match alpha {
+        // blended quads are sorted back-to-front from the camera.
+        Transparency::Blend => combiner.combine_sorted(alpha, eye - origin.as_vec3()),
+        _ => combiner.combine(alpha),
+    });
+
+    // every voxel of the subchunk is in the region, so connectivity is found from it.
+    let connections = Some(occlusion::face_connections(region, blocks, origin));
+    Some(MeshedSubchunk {
+        pos,
+        scale,
+        meshes,
+        connections,
+    })
+}
+
+/// Queue subchunks whose level of detail changed because the camera moved into another chunk.
+pub fn queue_lod_changes(
+    camera: Query<&GlobalTransform, With<MainCamera>>,
+    mut renderer: ResMut<ChunkRenderer>,
+    mut queue: ResMut<ChunkRenderQueue>,
+    index: Res<ChunkMeshIndex>,
+) {
+    let Ok(eye) = camera.single().map(|transform| transform.translation()) else {
+        return;
+    };
+
+    let center = eye.floor().as_ivec3().xz() >> 5;
+    if renderer.lod_center == Some(center) {
+        return;
+    }
+
+    renderer.lod_center = Some(center);
+    for (pos, scale) in &index.lods {
+        if renderer.lod_for(*pos, center) != *scale {
+            queue.add_subchunk(*pos);
+        }
+    }
+}
+
+/// Queue subchunks with blended meshes near the camera for remeshing
+/// when the camera has moved far enough that their sort order is stale.
+/// Re-sorts wait while the queue has a frame of meshing in it, so they don't
+/// hold back chunks that were loaded or changed, and only the nearest are queued.
+pub fn queue_blend_resort(
+    camera: Query<&GlobalTransform, With<MainCamera>>,
+    mut renderer: ResMut<ChunkRenderer>,
+    mut queue: ResMut<ChunkRenderQueue>,
+    index: Res<ChunkMeshIndex>,
+) {
+    let Ok(eye) = camera.single().map(|transform| transform.translation()) else {
+        return;
+    };
+
+    if eye.distance(renderer.sorted_from) < renderer.resort_distance
+        || queue.len() >= renderer.budget.meshes_per_frame
+    {
+        return;
+    }
+
+    renderer.sorted_from = eye;
+    let mut nearby = index
+        .subchunks_in(Transparency::Blend)
+        .map(|pos| {
+            let center = pos.origin().as_vec3() + Vec3::splat(16.0);
+            (pos, center.distance(eye))
+        })
+        .filter(|(_, distance)| *distance <= renderer.resort_radius)
+        .collect::<Vec<_>>();
+    nearby.sort_unstable_by(|a, b| a.1.total_cmp(&b.1));
+    for (pos, _) in nearby.into_iter().take(renderer.resort_limit) {
+        queue.add_subchunk(pos);
+    }
+}
+
+/// Queue the subchunks affected by block updates for remeshing.
+pub fn queue_block_update_remesh(
+    mut msgs: MessageReader<BlockUpdated>,
+    mut queue: ResMut<ChunkRenderQueue>,
+) {
+    for msg in msgs.read() {
+        queue.add_voxel(msg.pos);
+    }
+}
 
-        if let Some(mesh) = renderer.combiner.combine(Transparency::Opaque) {
-            commands.spawn((
-                Transform {
-                    translation: origin.as_vec3(),
-                    ..default()
-                },
-                Mesh3d(meshes.add(mesh)),
-                MeshMaterial3d(materials.add(ChunkMaterial {
-                    atlas: atlas.image(),
-                    table: atlas.table(),
-                    alpha: AlphaMode::Opaque,
-                })),
-            ));
+/// Despawn the meshes of chunks that were unloaded from the client world,
+/// returning their mesh assets to the pool.
+pub fn despawn_unloaded_chunk_meshes(
+    mut msgs: MessageReader<ChunkUnloaded>,
+    mut index: ResMut<ChunkMeshIndex>,
+    mut queue: ResMut<ChunkRenderQueue>,
+    mut renderer: ResMut<ChunkRenderer>,
+    mut columns: ResMut<FarColumns>,
+    mut pool: ResMut<ChunkMeshPool>,
+    handles: Query<&Mesh3d>,
+    meshes: Res<Assets<Mesh>>,
+    mut commands: Commands,
+) {
+    for msg in msgs.read() {
+        queue.remove_chunk(msg.origin);
+        renderer.remove_chunk(msg.origin);
+        columns.remove_chunk(msg.origin);
+        for entity in index.remove_chunk(msg.origin) {
+            if let Ok(handle) = handles.get(entity) {
+                pool.release(&meshes, handle.0.clone());
+            }
+            commands.entity(entity).despawn();
         }
     }
 }
 
+/// Despawn all chunk meshes and columns, should run when leaving the game.
+pub fn despawn_all_chunk_meshes(
+    mut index: ResMut<ChunkMeshIndex>,
+    mut renderer: ResMut<ChunkRenderer>,
+    mut columns: ResMut<FarColumns>,
+    mut pool: ResMut<ChunkMeshPool>,
+    mut commands: Commands,
+) {
+    renderer.ready.clear();
+    pool.clear();
+    for entity in index.drain().chain(columns.drain()) {
+        commands.entity(entity).despawn();
+    }
+}
+
 trait GetBlock {
     fn get_block(&self, pos: IVec3) -> Option<VoxelState>;
 }
@@ -158,43 +763,266 @@ impl GetBlock for Region {
 mod subchunk_fn {
     use super::GetBlock;
     use bevy::math::{IVec3, ivec3};
-    use data::blockstates::{
-        Transparency,
-        quad::{FULL_BLOCK, Normal},
+    use data::{
+        blockstates::{
+            BlockState, ModelData, Transparency,
+            element::Element,
+            quad::{CROSS, CROSS_UV, FULL_BLOCK, Normal, Quad, QuadLight, QuadUv},
+        },
+        registry::Registry,
     };
     use math::axis::{Axis, AxisArray};
-    use world::{Voxel, World};
+    use world::VoxelState;
 
     use crate::render::chunk::combiner::QuadCombiner;
 
     pub fn build_subchunk<G: GetBlock>(
         combiner: &mut QuadCombiner,
         get: &G,
+        blocks: &Registry<BlockState>,
         origin: IVec3,
-        stone_texture: i16,
     ) {
         for y in 0..32 {
-            let offs_y = ((origin.y + y) * 16) as i16;
+            let offs_y = (y * 16) as i16;
             for x in 0..32 {
                 let offs_x = (x * 16) as i16;
                 for z in 0..32 {
                     let pt = origin + ivec3(x, y, z);
-                    let center = get.get_block(pt).unwrap();
-                    if center.voxel == Voxel(1) {
-                        let offs = [offs_x, offs_y, (z * 16) as i16];
-                        for axis in Axis::ALL {
-                            if get
-                                .get_block(axis + pt)
-                                .is_none_or(|state| state.voxel == Voxel::AIR)
-                            {
-                                let quad =
-                                    FULL_BLOCK[axis].offset_with_texture(offs, stone_texture);
-                                combiner.add(quad, Transparency::Opaque, Normal::Aligned(axis));
-                            }
+                    let Some(state) = get.get_block(pt) else {
+                        continue;
+                    };
+
+                    let Some(center) = blocks.get(state.voxel) else {
+                        continue;
+                    };
+
+                    let offs = [offs_x, offs_y, (z * 16) as i16];
+                    match &center.model {
+                        ModelData::Empty => {}
+                        ModelData::Full { textures } => {
+                            build_full(combiner, get, blocks, center, pt, offs, textures)
                         }
+                        ModelData::Elements(elements) => {
+                            build_elements(combiner, get, blocks, center, pt, offs, elements)
+                        }
+                        ModelData::Cross { texture } => {
+                            build_cross(combiner, state, center, offs, *texture)
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn build_full<G: GetBlock>(
+        combiner: &mut QuadCombiner,
+        get: &G,
+        blocks: &Registry<BlockState>,
+        center: &BlockState,
+        pt: IVec3,
+        offs: [i16; 3],
+        textures: &AxisArray<u16>,
+    ) {
+        for axis in Axis::ALL {
+            if is_face_covered(get, blocks, center, pt, axis) {
+                continue;
+            }
+
+            let quad = FULL_BLOCK[axis].offset_with_texture(offs, textures[axis] as i16);
+            let light = face_light(get, blocks, pt, axis);
+            add_face(combiner, center, quad, light, QuadUv::WORLD, Normal::Aligned(axis));
+        }
+    }
+
+    fn build_elements<G: GetBlock>(
+        combiner: &mut QuadCombiner,
+        get: &G,
+        blocks: &Registry<BlockState>,
+        center: &BlockState,
+        pt: IVec3,
+        offs: [i16; 3],
+        elements: &[Element],
+    ) {
+        // the neighbour on each side, and whether it hides every face on the boundary.
+        let neighbors = AxisArray::from_fn(|axis| neighbor(get, blocks, pt, axis));
+        let covered = AxisArray::from_fn(|axis| {
+            neighbors[axis].is_some_and(|n| n.coverages.occludes(axis.invert(), &center.coverages))
+        });
+
+        for element in elements {
+            for axis in Axis::ALL {
+                let Some(face) = element.faces[axis] else {
+                    continue;
+                };
+
+                if element.is_on_boundary(axis) {
+                    if covered[axis] {
+                        continue;
+                    }
+
+                    // the neighbour may still hide this face if it only hides part of the side,
+                    // like the bottom half of a stair next to a slab.
+                    let texture = match center.transparency {
+                        Transparency::Opaque => 0,
+                        _ => face.texture,
+                    };
+                    if let Some(n) = neighbors[axis]
+                        && !n.coverages.is_empty(axis.invert())
+                        && n.coverages
+                            .occludes_face(axis.invert(), &element.mask(axis), texture)
+                    {
+                        continue;
+                    }
+                }
+
+                let quad = element.quad(axis, face.texture as i16).offset(offs);
+                let light = face_light(get, blocks, pt, axis);
+                let uv = element.uv(axis);
+                add_face(combiner, center, quad, light, uv, Normal::Aligned(axis));
+            }
+        }
+    }
+
+    fn build_cross(
+        combiner: &mut QuadCombiner,
+        state: VoxelState,
+        center: &BlockState,
+        offs: [i16; 3],
+        texture: u16,
+    ) {
+        // crosses are lit by the voxel they are in, without occlusion.
+        let light = [state.light.ambient_intensity(), state.light.torch_intensity(), 3, 0];
+        let light = QuadLight([light; 4]);
+
+        for ((quad, normal), uv) in CROSS.into_iter().zip(CROSS_UV) {
+            let quad = quad.offset_with_texture(offs, texture as i16);
+            combiner.add_with_uv(quad, light, uv, center.transparency, normal);
+        }
+    }
+
+    /// Whether the face of the block on this side is hidden by its neighbour.
+    /// Faces next to unloaded voxels are kept, since there is
+    /// no way to know if they are covered.
+    fn is_face_covered<G: GetBlock>(
+        get: &G,
+        blocks: &Registry<BlockState>,
+        center: &BlockState,
+        pt: IVec3,
+        axis: Axis,
+    ) -> bool {
+        neighbor(get, blocks, pt, axis)
+            .is_some_and(|n| n.coverages.occludes(axis.invert(), &center.coverages))
+    }
+
+    /// The block next to the voxel on this side, None if it isn't loaded or registered.
+    fn neighbor<'a, G: GetBlock>(
+        get: &G,
+        blocks: &'a Registry<BlockState>,
+        pt: IVec3,
+        axis: Axis,
+    ) -> Option<&'a BlockState> {
+        get.get_block(axis + pt).and_then(|state| blocks.get(state.voxel))
+    }
+
+    /// Add an axis-aligned face to the combiner.
+    fn add_face(
+        combiner: &mut QuadCombiner,
+        center: &BlockState,
+        mut quad: Quad,
+        mut light: QuadLight,
+        mut uv: QuadUv,
+        normal: Normal,
+    ) {
+        // split the quad along the diagonal with the least occlusion
+        // so the AO gradient is symmetric.
+        let (d0, d1) = light.ao_diagonals();
+        if d0 < d1 {
+            quad = quad.rotate();
+            light = light.rotate();
+            uv = uv.rotate();
+        }
+
+        combiner.add_with_uv(quad, light, uv, center.transparency, normal);
+    }
+
+    /// Whether the voxel blocks light and occludes its neighbours.
+    /// Voxels without a registered block state are treated as open.
+    fn is_opaque(blocks: &Registry<BlockState>, state: VoxelState) -> bool {
+        blocks.get(state.voxel).is_some_and(|b| b.is_opaque_cube())
+    }
+
+    /// Compute the smooth light and ambient occlusion of each vertex of
+    /// the face of the voxel at `pt` facing `axis`.
+    ///
+    /// For each vertex, the four cells in front of the face that touch the vertex
+    /// are sampled: the cell directly in front, the two side cells, and the corner cell.
+    /// Light is averaged over the non-opaque cells, and AO is the classic
+    /// `3 - (side1 + side2 + corner)`, or 0 if both sides are opaque.
+    fn face_light<G: GetBlock>(
+        get: &G,
+        blocks: &Registry<BlockState>,
+        pt: IVec3,
+        axis: Axis,
+    ) -> QuadLight {
+        let front = pt + axis.as_ivec3();
+        let (u, v) = plane_axes(axis);
+        let template = FULL_BLOCK[axis];
+
+        QuadLight(std::array::from_fn(|i| {
+            // direction of the vertex from the center of the face.
+            let pos = template.0[i].pos;
+            let du = if pos[u] == 0 { -1 } else { 1 };
+            let dv = if pos[v] == 0 { -1 } else { 1 };
+            let mut eu = IVec3::ZERO;
+            let mut ev = IVec3::ZERO;
+            eu[u] = du;
+            ev[v] = dv;
+
+            let samples = [
+                get.get_block(front),
+                get.get_block(front + eu),
+                get.get_block(front + ev),
+                get.get_block(front + eu + ev),
+            ];
+            let opaque = samples.map(|s| s.is_some_and(|s| is_opaque(blocks, s)));
+
+            let ao = if opaque[1] && opaque[2] {
+                0
+            } else {
+                3 - (opaque[1] as u8 + opaque[2] as u8 + opaque[3] as u8)
+            };
+
+            // average light over the cells light can pass through.
+            let (mut sky, mut block, mut n) = (0u32, 0u32, 0u32);
+            for (j, state) in samples.iter().enumerate() {
+                // the corner can't leak light if both sides are opaque.
+                if opaque[j] || (j == 3 && opaque[1] && opaque[2]) {
+                    continue;
+                }
+                match state {
+                    Some(state) => {
+                        sky += state.light.ambient_intensity() as u32;
+                        block += state.light.torch_intensity() as u32;
                     }
+                    // unloaded cells are treated as open sky.
+                    None => sky += 15,
                 }
+                n += 1;
             }
+            if n == 0 {
+                [0, 0, ao, 0]
+            } else {
+                [(sky / n) as u8, (block / n) as u8, ao, 0]
+            }
+        }))
+    }
+
+    /// Index of the two dimensions of the face plane on this axis.
+    const fn plane_axes(axis: Axis) -> (usize, usize) {
+        match axis {
+            Axis::PosX | Axis::NegX => (2, 1),
+            Axis::PosY | Axis::NegY => (0, 2),
+            Axis::PosZ | Axis::NegZ => (0, 1),
         }
     }
 }
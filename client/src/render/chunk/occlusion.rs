@@ -0,0 +1,305 @@
+//! Occlusion culling of subchunks, a big win underground where most of the world is hidden.
+//!
+//! When a subchunk is meshed, the voxels that aren't opaque cubes are flood filled to find
+//! which of its faces can see each other through it. Whenever the camera moves into another
+//! subchunk or meshes change, a breadth-first search walks from the subchunk of the camera
+//! into its neighbours, leaving each subchunk only through faces connected to the face it came
+//! in by, and never turning back towards the camera. The meshes of subchunks the search
+//! doesn't reach can't be seen, so they are hidden.
+//!
+//! Subchunks that haven't been meshed yet, and those drawn as columns, are treated as open.
+
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+use data::{blockstates::BlockState, registry::Registry};
+use fxhash::{FxHashMap, FxHashSet};
+use math::axis::{Axis, AxisMask};
+use world::World;
+
+use super::{ChunkMeshIndex, ChunkRenderer, GetBlock, SubchunkPos};
+use crate::player::MainCamera;
+
+/// Pairs of faces of a subchunk connected by voxels that aren't opaque cubes.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct FaceConnections(u64);
+
+impl FaceConnections {
+    /// Every face sees every other face, like a subchunk of air.
+    pub const ALL: Self = Self((1 << 36) - 1);
+
+    /// No face sees another, like a subchunk of stone.
+    pub const NONE: Self = Self(0);
+
+    const fn bit(a: Axis, b: Axis) -> u64 {
+        1 << (a as u32 * 6 + b as u32)
+    }
+
+    /// Whether these faces can see each other through the subchunk.
+    pub const fn connects(self, a: Axis, b: Axis) -> bool {
+        self.0 & Self::bit(a, b) != 0
+    }
+
+    /// Connect every face in the mask with every other.
+    fn connect_all(&mut self, faces: AxisMask) {
+        for a in faces {
+            for b in faces {
+                self.0 |= Self::bit(a, b);
+            }
+        }
+    }
+}
+
+/// Flood fill the voxels of the subchunk at this origin that aren't opaque cubes,
+/// connecting the faces touched by each region of them.
+pub(super) fn face_connections<G: GetBlock>(
+    get: &G,
+    blocks: &Registry<BlockState>,
+    origin: IVec3,
+) -> FaceConnections {
+    let index = |p: IVec3| (p.y << 10 | p.x << 5 | p.z) as usize;
+    let mut open = vec![false; 32 * 32 * 32];
+    let mut num_open = 0;
+    for y in 0..32 {
+        for x in 0..32 {
+            for z in 0..32 {
+                let p = IVec3::new(x, y, z);
+                let opaque = get
+                    .get_block(origin + p)
+                    .and_then(|state| blocks.get(state.voxel))
+                    .is_some_and(|block| block.is_opaque_cube());
+                open[index(p)] = !opaque;
+                num_open += !opaque as usize;
+            }
+        }
+    }
+
+    if num_open == 0 {
+        return FaceConnections::NONE;
+    } else if num_open == open.len() {
+        return FaceConnections::ALL;
+    }
+
+    let mut connections = FaceConnections::NONE;
+    let mut stack = Vec::new();
+    for start in 0..open.len() {
+        if !open[start] {
+            continue;
+        }
+
+        open[start] = false;
+        stack.push(start);
+        let mut faces = AxisMask::empty();
+        while let Some(i) = stack.pop() {
+            let p = IVec3::new((i >> 5 & 31) as i32, (i >> 10) as i32, (i & 31) as i32);
+            for axis in Axis::ALL {
+                let n = axis + p;
+                if n.cmplt(IVec3::ZERO).any() || n.cmpge(IVec3::splat(32)).any() {
+                    faces |= axis;
+                } else if open[index(n)] {
+                    open[index(n)] = false;
+                    stack.push(index(n));
+                }
+            }
+        }
+
+        connections.connect_all(faces);
+        if connections == FaceConnections::ALL {
+            break;
+        }
+    }
+
+    connections
+}
+
+/// Result of the last search from the camera.
+#[derive(Resource, Default)]
+pub struct OcclusionCuller {
+    /// Subchunk the camera was in when the search last ran.
+    camera: Option<SubchunkPos>,
+
+    /// Subchunks reached by the last search.
+    reached: FxHashSet<SubchunkPos>,
+
+    /// Number of meshes hidden by the last search.
+    hidden: usize,
+}
+
+impl OcclusionCuller {
+    /// Number of meshes hidden by the last search.
+    pub fn hidden(&self) -> usize {
+        self.hidden
+    }
+}
+
+/// Hide the meshes of subchunks that can't be seen from the subchunk of the camera.
+pub fn cull_occluded_subchunks(
+    camera: Query<&GlobalTransform, With<MainCamera>>,
+    renderer: Res<ChunkRenderer>,
+    index: Res<ChunkMeshIndex>,
+    world: Res<World>,
+    mut culler: ResMut<OcclusionCuller>,
+    mut visibilities: Query<&mut Visibility>,
+) {
+    let Ok(eye) = camera.single().map(|transform| transform.translation()) else {
+        return;
+    };
+
+    let start = SubchunkPos::containing(eye.floor().as_ivec3());
+    if culler.camera == Some(start) && !index.is_changed() {
+        return;
+    }
+
+    let culler = &mut *culler;
+    culler.camera = Some(start);
+
+    // above or below the world, every subchunk may be seen.
+    let heights = world.min_y()..world.max_y();
+    let searched = heights.contains(&start.origin().y);
+    if searched {
+        search(
+            &mut culler.reached,
+            &index.connections,
+            start,
+            renderer.column_radius(),
+            heights,
+        );
+    }
+
+    culler.hidden = 0;
+    for (&(pos, _), &entity) in &index.entities {
+        let shown = !searched || culler.reached.contains(&pos);
+        if let Ok(mut visibility) = visibilities.get_mut(entity) {
+            visibility.set_if_neq(match shown {
+                true => Visibility::Inherited,
+                false => Visibility::Hidden,
+            });
+        }
+        culler.hidden += !shown as usize;
+    }
+}
+
+/// Find the subchunks that can be seen from the start, within the radius in chunks.
+fn search(
+    reached: &mut FxHashSet<SubchunkPos>,
+    connections: &FxHashMap<SubchunkPos, FaceConnections>,
+    start: SubchunkPos,
+    radius: i32,
+    heights: std::ops::Range<i32>,
+) {
+    let center = start.origin().xz() >> 5;
+    let in_range = |pos: SubchunkPos| {
+        heights.contains(&pos.origin().y)
+            && ((pos.origin().xz() >> 5) - center).abs().max_element() <= radius
+    };
+
+    reached.clear();
+    reached.insert(start);
+    let mut queue = VecDeque::from([(start, None::<Axis>, AxisMask::empty())]);
+    while let Some((pos, entered, directions)) = queue.pop_front() {
+        let faces = connections
+            .get(&pos)
+            .copied()
+            .unwrap_or(FaceConnections::ALL);
+
+        for axis in Axis::ALL {
+            // turning back could only reach subchunks hidden behind the ones already passed.
+            if directions.has(axis.invert()) {
+                continue;
+            }
+
+            if let Some(from) = entered
+                && !faces.connects(from, axis)
+            {
+                continue;
+            }
+
+            let next = SubchunkPos(pos.origin() + axis.as_ivec3() * 32);
+            if in_range(next) && reached.insert(next) {
+                queue.push_back((next, Some(axis.invert()), directions | axis));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use data::blockstates::Transparency;
+    use math::axis::AxisArray;
+    use world::{Voxel, VoxelState};
+
+    use super::*;
+
+    /// Stone where the closure is true, air elsewhere.
+    struct Voxels<F>(F);
+
+    impl<F: Fn(IVec3) -> bool> GetBlock for Voxels<F> {
+        fn get_block(&self, pos: IVec3) -> Option<VoxelState> {
+            Some(VoxelState {
+                voxel: Voxel((self.0)(pos) as u16),
+                ..VoxelState::AIR
+            })
+        }
+    }
+
+    fn blocks() -> Registry<BlockState> {
+        let mut blocks = Registry::new();
+        blocks.insert("air", BlockState::empty());
+        blocks.insert(
+            "stone",
+            BlockState::full(AxisArray::new([1; 6]), Transparency::Opaque),
+        );
+        blocks
+    }
+
+    #[test]
+    fn solid_and_open_subchunks() {
+        let blocks = blocks();
+        let solid = face_connections(&Voxels(|_| true), &blocks, IVec3::ZERO);
+        assert_eq!(solid, FaceConnections::NONE);
+
+        let open = face_connections(&Voxels(|_| false), &blocks, IVec3::ZERO);
+        assert_eq!(open, FaceConnections::ALL);
+    }
+
+    #[test]
+    fn tunnel_connects_only_its_ends() {
+        let blocks = blocks();
+        let origin = IVec3::new(32, -64, 0);
+        let tunnel = |p: IVec3| (p - origin).yz() != IVec2::new(16, 16);
+        let connections = face_connections(&Voxels(tunnel), &blocks, origin);
+
+        assert!(connections.connects(Axis::NegX, Axis::PosX));
+        assert!(connections.connects(Axis::PosX, Axis::NegX));
+        assert!(!connections.connects(Axis::NegX, Axis::PosY));
+        assert!(!connections.connects(Axis::PosZ, Axis::NegZ));
+        assert!(!connections.connects(Axis::PosY, Axis::NegY));
+    }
+
+    #[test]
+    fn search_stops_at_solid_subchunks() {
+        // a wall of stone subchunks one chunk east of the start, through all of the range.
+        let mut connections = FxHashMap::default();
+        for z in -2..=2 {
+            connections.insert(
+                SubchunkPos(IVec3::new(32, 0, z * 32)),
+                FaceConnections::NONE,
+            );
+        }
+
+        let mut reached = FxHashSet::default();
+        let start = SubchunkPos(IVec3::ZERO);
+        search(&mut reached, &connections, start, 2, 0..32);
+
+        // the wall itself can be seen, but not what is behind it.
+        assert!(reached.contains(&SubchunkPos(IVec3::new(32, 0, 0))));
+        assert!(reached.contains(&SubchunkPos(IVec3::new(32, 0, 64))));
+        assert!(!reached.contains(&SubchunkPos(IVec3::new(64, 0, 0))));
+        assert!(!reached.contains(&SubchunkPos(IVec3::new(64, 0, -64))));
+
+        // subchunks missing from the connections are open.
+        assert!(reached.contains(&SubchunkPos(IVec3::new(-64, 0, 64))));
+        assert!(!reached.contains(&SubchunkPos(IVec3::new(0, 32, 0))));
+        assert_eq!(reached.len(), 20);
+    }
+}
@@ -0,0 +1,92 @@
+//! Reusing the mesh assets of subchunks.
+//!
+//! Remeshing a subchunk writes the new mesh into the asset it already has, instead of
+//! adding a new asset for every remesh. When a subchunk loses its mesh, because its
+//! chunk was unloaded or the pass became empty, the asset goes back into the pool,
+//! keyed by its size, and is reused by the next subchunk of about the same size.
+
+use std::collections::BTreeMap;
+
+use bevy::prelude::*;
+
+/// Most meshes kept in the pool. Past this, the largest pooled meshes are dropped.
+const POOL_CAPACITY: usize = 256;
+
+/// Mesh assets that aren't used by any subchunk, but are kept around for reuse.
+#[derive(Resource, Default)]
+pub struct ChunkMeshPool {
+    /// Free meshes, keyed by the log2 of their number of vertices.
+    free: BTreeMap<u32, Vec<Handle<Mesh>>>,
+    len: usize,
+}
+
+impl ChunkMeshPool {
+    /// Size class of a mesh, meshes in the same class are within 2x of each other.
+    fn size_class(mesh: &Mesh) -> u32 {
+        (mesh.count_vertices().max(1) as u32).ilog2()
+    }
+
+    /// Get a handle to an asset holding this mesh, reusing a pooled asset of
+    /// the same or the next larger size class if there is one.
+    pub fn acquire(&mut self, meshes: &mut Assets<Mesh>, mesh: Mesh) -> Handle<Mesh> {
+        let class = Self::size_class(&mesh);
+        let reused = self
+            .free
+            .range(class..=class + 1)
+            .next()
+            .map(|(class, _)| *class);
+
+        if let Some(class) = reused {
+            // classes without any free meshes are never kept in the map.
+            let handles = self.free.get_mut(&class).unwrap();
+            let handle = handles.pop().unwrap();
+            if handles.is_empty() {
+                self.free.remove(&class);
+            }
+
+            self.len -= 1;
+            if let Some(asset) = meshes.get_mut(&handle) {
+                *asset = mesh;
+                return handle;
+            }
+
+            // the asset was removed from under the pool, don't reuse the handle.
+            return meshes.add(mesh);
+        }
+
+        meshes.add(mesh)
+    }
+
+    /// Return the mesh of a subchunk to the pool.
+    /// If the pool is over capacity, the largest meshes are dropped.
+    pub fn release(&mut self, meshes: &Assets<Mesh>, handle: Handle<Mesh>) {
+        let Some(mesh) = meshes.get(&handle) else {
+            return;
+        };
+
+        self.free
+            .entry(Self::size_class(mesh))
+            .or_default()
+            .push(handle);
+        self.len += 1;
+
+        while self.len > POOL_CAPACITY {
+            let Some(mut largest) = self.free.last_entry() else {
+                break;
+            };
+
+            // dropping the last handle removes the asset.
+            largest.get_mut().pop();
+            self.len -= 1;
+            if largest.get().is_empty() {
+                largest.remove();
+            }
+        }
+    }
+
+    /// Drop every pooled mesh.
+    pub fn clear(&mut self) {
+        self.free.clear();
+        self.len = 0;
+    }
+}
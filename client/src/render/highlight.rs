@@ -0,0 +1,85 @@
+use bevy::{
+    asset::RenderAssetUsages,
+    mesh::{Indices, PrimitiveTopology},
+    prelude::*,
+};
+
+use crate::player::target::TargetedBlock;
+
+/// The wireframe box drawn around the targeted block.
+#[derive(Component)]
+pub struct BlockHighlight;
+
+/// Slightly larger than a voxel, so the lines don't z-fight with its faces.
+const HIGHLIGHT_SIZE: f32 = 1.004;
+
+pub fn spawn_block_highlight(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.spawn((
+        BlockHighlight,
+        Mesh3d(meshes.add(wireframe_cube())),
+        MeshMaterial3d(materials.add(StandardMaterial {
+            base_color: Color::srgba(0.0, 0.0, 0.0, 0.8),
+            alpha_mode: AlphaMode::Blend,
+            unlit: true,
+            ..default()
+        })),
+        Transform::from_scale(Vec3::splat(HIGHLIGHT_SIZE)),
+        Visibility::Hidden,
+    ));
+}
+
+pub fn despawn_block_highlight(
+    mut commands: Commands,
+    highlight: Query<Entity, With<BlockHighlight>>,
+) {
+    for entity in &highlight {
+        commands.entity(entity).despawn();
+    }
+}
+
+/// Move the highlight to the targeted block, or hide it if nothing is targeted.
+pub fn update_block_highlight(
+    target: Res<TargetedBlock>,
+    mut highlight: Query<(&mut Transform, &mut Visibility), With<BlockHighlight>>,
+) {
+    for (mut transform, mut visibility) in &mut highlight {
+        match target.hit {
+            Some(hit) => {
+                transform.translation = hit.pos.as_vec3() + Vec3::splat(0.5);
+                *visibility = Visibility::Visible;
+            }
+            None => *visibility = Visibility::Hidden,
+        }
+    }
+}
+
+/// The 12 edges of a unit cube centered on the origin, as a line list.
+fn wireframe_cube() -> Mesh {
+    let corners = (0..8)
+        .map(|i| {
+            [
+                if i & 1 == 0 { -0.5 } else { 0.5 },
+                if i & 2 == 0 { -0.5 } else { 0.5 },
+                if i & 4 == 0 { -0.5 } else { 0.5 },
+            ]
+        })
+        .collect::<Vec<[f32; 3]>>();
+
+    // every pair of corners that differ in exactly one axis is an edge.
+    let mut edges = Vec::with_capacity(24);
+    for a in 0..8u16 {
+        for bit in [1, 2, 4] {
+            if a & bit == 0 {
+                edges.extend_from_slice(&[a, a | bit]);
+            }
+        }
+    }
+
+    Mesh::new(PrimitiveTopology::LineList, RenderAssetUsages::all())
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, corners)
+        .with_inserted_indices(Indices::U16(edges))
+}
@@ -1,3 +1,6 @@
 pub mod atlases;
 pub mod chunk;
+pub mod highlight;
+pub mod particles;
+pub mod shaders;
 pub mod skybox;
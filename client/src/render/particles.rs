@@ -0,0 +1,369 @@
+//! Particles shown when blocks are broken and placed.
+//!
+//! Particles are small billboards showing part of the block's texture. They are kept
+//! in a fixed-size pool and drawn with a single mesh that is rewritten every frame,
+//! so emitting particles never allocates. When the pool is full, the oldest
+//! particles are replaced.
+
+use bevy::{
+    asset::RenderAssetUsages,
+    camera::visibility::NoFrustumCulling,
+    light::NotShadowCaster,
+    mesh::{Indices, MeshVertexAttribute, PrimitiveTopology, VertexAttributeValues, VertexFormat},
+    prelude::*,
+    render::{render_resource::AsBindGroup, storage::ShaderStorageBuffer},
+};
+use data::{
+    blockstates::{BlockState, ModelData},
+    registry::Registry,
+};
+use world::{World, voxel::Voxel};
+
+use crate::{
+    events::{BlockEffect, BlockEffectKind},
+    player::MainCamera,
+    render::atlases::{BlockTextureMeta, TextureArray},
+};
+
+/// Most particles that can exist at once.
+const MAX_PARTICLES: usize = 1024;
+
+/// Particles emitted when a block breaks, on each axis.
+const BREAK_GRID: usize = 4;
+
+/// Particles emitted when a block is placed.
+const PLACE_PARTICLES: usize = 8;
+
+/// Downward acceleration of particles, in blocks per second squared.
+const GRAVITY: f32 = 20.0;
+
+/// Fraction of horizontal velocity kept per second while on the ground.
+const GROUND_FRICTION: f32 = 0.02;
+
+/// Size of the part of the texture shown on a particle, from 0 to 1.
+const UV_SIZE: f32 = 0.25;
+
+/// Particle position and `[u, v, texture, light]`, see particle.wgsl.
+/// Shares the id of `Mesh::ATTRIBUTE_UV_0`, so it is bound to location 2.
+pub const ATTRIBUTE_PARTICLE_UV: MeshVertexAttribute =
+    MeshVertexAttribute::new("particle_uv", 2, VertexFormat::Float32x4);
+
+#[derive(Asset, TypePath, AsBindGroup, Clone, Debug)]
+pub struct ParticleMaterial {
+    /// Comes from TextureArray::<BlockTextureMeta>.images
+    #[texture(0, dimension = "2d_array")]
+    #[sampler(1)]
+    pub atlas: Handle<Image>,
+
+    /// Comes from TextureArray::<BlockTextureMeta>.gpu_data.
+    #[storage(2, read_only)]
+    pub table: Handle<ShaderStorageBuffer>,
+}
+
+impl Material for ParticleMaterial {
+    fn vertex_shader() -> bevy::shader::ShaderRef {
+        "shaders/particle.wgsl".into()
+    }
+
+    fn fragment_shader() -> bevy::shader::ShaderRef {
+        "shaders/particle.wgsl".into()
+    }
+}
+
+/// The mesh all particles are drawn with.
+#[derive(Component)]
+pub struct ParticleMesh;
+
+#[derive(Copy, Clone)]
+struct Particle {
+    pos: Vec3,
+    vel: Vec3,
+
+    /// Corner of the part of the texture shown.
+    uv: Vec2,
+    texture: u16,
+    light: f32,
+
+    /// Half of the width of the particle.
+    size: f32,
+    age: f32,
+    lifetime: f32,
+}
+
+impl Particle {
+    const DEAD: Self = Self {
+        pos: Vec3::ZERO,
+        vel: Vec3::ZERO,
+        uv: Vec2::ZERO,
+        texture: 0,
+        light: 0.0,
+        size: 0.0,
+        age: 0.0,
+        lifetime: 0.0,
+    };
+
+    fn is_alive(&self) -> bool {
+        self.age < self.lifetime
+    }
+}
+
+/// Pool of particles, replaced in the order they were emitted.
+#[derive(Resource)]
+pub struct Particles {
+    pool: Vec<Particle>,
+    next: usize,
+    seed: u64,
+}
+
+impl Default for Particles {
+    fn default() -> Self {
+        Self {
+            pool: vec![Particle::DEAD; MAX_PARTICLES],
+            next: 0,
+            seed: 0,
+        }
+    }
+}
+
+impl Particles {
+    fn emit(&mut self, particle: Particle) {
+        self.pool[self.next] = particle;
+        self.next = (self.next + 1) % MAX_PARTICLES;
+    }
+
+    /// A pseudo-random number from 0 to 1.
+    fn random(&mut self) -> f32 {
+        self.seed = self.seed.wrapping_add(1);
+        (fxhash::hash64(&self.seed) >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    /// A random point on the texture, so the part shown stays inside of it.
+    fn random_uv(&mut self) -> Vec2 {
+        Vec2::new(self.random(), self.random()) * (1.0 - UV_SIZE)
+    }
+
+    pub fn clear(&mut self) {
+        self.pool.fill(Particle::DEAD);
+        self.next = 0;
+    }
+}
+
+pub fn spawn_particle_mesh(
+    mut commands: Commands,
+    atlas: Res<TextureArray<BlockTextureMeta>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ParticleMaterial>>,
+) {
+    let num_vertices = MAX_PARTICLES * 4;
+    let mut indices = Vec::with_capacity(MAX_PARTICLES * 6);
+    for i in 0..MAX_PARTICLES as u32 {
+        let base = i * 4;
+        indices.extend([base, base + 1, base + 2, base, base + 2, base + 3]);
+    }
+
+    let mesh = Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::all())
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, vec![[0.0f32; 3]; num_vertices])
+        .with_inserted_attribute(
+            ATTRIBUTE_PARTICLE_UV,
+            VertexAttributeValues::Float32x4(vec![[0.0; 4]; num_vertices]),
+        )
+        .with_inserted_indices(Indices::U32(indices));
+
+    commands.spawn((
+        ParticleMesh,
+        Mesh3d(meshes.add(mesh)),
+        MeshMaterial3d(materials.add(ParticleMaterial {
+            atlas: atlas.image(),
+            table: atlas.table(),
+        })),
+        Transform::default(),
+        Visibility::Hidden,
+        // the mesh bounds don't follow the particles.
+        NoFrustumCulling,
+        NotShadowCaster,
+    ));
+}
+
+pub fn despawn_particle_mesh(
+    mut commands: Commands,
+    mesh: Query<Entity, With<ParticleMesh>>,
+    mut particles: ResMut<Particles>,
+) {
+    for entity in &mesh {
+        commands.entity(entity).despawn();
+    }
+
+    particles.clear();
+}
+
+/// Emit particles for blocks that were broken or placed.
+pub fn emit_block_particles(
+    world: Res<World>,
+    blocks: Res<Registry<BlockState>>,
+    mut effects: MessageReader<BlockEffect>,
+    mut particles: ResMut<Particles>,
+) {
+    for effect in effects.read() {
+        let Some(texture) = blocks.get(effect.voxel).and_then(|b| b.icon_texture()) else {
+            continue;
+        };
+
+        let light = world
+            .get_state(effect.pos)
+            .map_or(1.0, |state| {
+                let intensity = state.light.intensity;
+                (intensity & 0xF).max(intensity >> 4) as f32 / 15.0
+            })
+            .max(0.1);
+
+        let origin = effect.pos.as_vec3();
+        match effect.kind {
+            BlockEffectKind::Break => {
+                // a grid of particles through the block, bursting out from the center.
+                for i in 0..BREAK_GRID.pow(3) {
+                    let cell = UVec3::new(
+                        (i % BREAK_GRID) as u32,
+                        (i / BREAK_GRID % BREAK_GRID) as u32,
+                        (i / (BREAK_GRID * BREAK_GRID)) as u32,
+                    );
+                    let offset = (cell.as_vec3() + 0.5) / BREAK_GRID as f32;
+                    let jitter = Vec3::new(particles.random(), particles.random(), particles.random());
+                    let vel = (offset - 0.5) * 3.0 + (jitter - 0.5) + Vec3::Y * 2.0;
+                    let particle = Particle {
+                        pos: origin + offset,
+                        vel,
+                        uv: particles.random_uv(),
+                        texture,
+                        light,
+                        size: 0.05 + particles.random() * 0.04,
+                        age: 0.0,
+                        lifetime: 0.5 + particles.random() * 0.7,
+                    };
+                    particles.emit(particle);
+                }
+            }
+            BlockEffectKind::Place => {
+                // a few particles along the bottom edges, pushed outwards.
+                for _ in 0..PLACE_PARTICLES {
+                    let angle = particles.random() * std::f32::consts::TAU;
+                    let dir = Vec3::new(angle.cos(), 0.0, angle.sin());
+                    let edge = dir / dir.x.abs().max(dir.z.abs());
+                    let particle = Particle {
+                        pos: origin + Vec3::new(0.5, 0.05, 0.5) + edge * 0.5,
+                        vel: dir * 1.5 + Vec3::Y * 1.0,
+                        uv: particles.random_uv(),
+                        texture,
+                        light,
+                        size: 0.04 + particles.random() * 0.02,
+                        age: 0.0,
+                        lifetime: 0.3 + particles.random() * 0.2,
+                    };
+                    particles.emit(particle);
+                }
+            }
+        }
+    }
+}
+
+/// Move particles, and stop them when they land on a block.
+pub fn update_particles(
+    time: Res<Time>,
+    world: Res<World>,
+    blocks: Res<Registry<BlockState>>,
+    mut particles: ResMut<Particles>,
+) {
+    let dt = time.delta_secs();
+    let is_solid = |pos: Vec3| {
+        world.get_state(pos.floor().as_ivec3()).is_some_and(|state| {
+            state.voxel != Voxel::AIR
+                && blocks
+                    .get(state.voxel)
+                    .is_some_and(|b| !matches!(b.model, ModelData::Empty | ModelData::Cross { .. }))
+        })
+    };
+
+    for particle in &mut particles.pool {
+        if !particle.is_alive() {
+            continue;
+        }
+
+        particle.age += dt;
+        particle.vel.y -= GRAVITY * dt;
+
+        // vertical and horizontal movement are blocked separately,
+        // so particles slide along the ground instead of sticking to it.
+        let step = particle.vel * dt;
+        let below = particle.pos + Vec3::Y * (step.y - particle.size);
+        if step.y < 0.0 && is_solid(below) {
+            particle.pos.y = below.floor().y + 1.0 + particle.size;
+            particle.vel.y = 0.0;
+            let friction = GROUND_FRICTION.powf(dt);
+            particle.vel.x *= friction;
+            particle.vel.z *= friction;
+        } else if step.y > 0.0 && is_solid(particle.pos + Vec3::Y * (step.y + particle.size)) {
+            particle.vel.y = 0.0;
+        } else {
+            particle.pos.y += step.y;
+        }
+
+        let horizontal = particle.pos + step.with_y(0.0);
+        if is_solid(horizontal) {
+            particle.vel.x = 0.0;
+            particle.vel.z = 0.0;
+        } else {
+            particle.pos = horizontal;
+        }
+    }
+}
+
+/// Write the particles into the particle mesh as quads facing the camera.
+pub fn write_particle_mesh(
+    particles: Res<Particles>,
+    camera: Single<&GlobalTransform, With<MainCamera>>,
+    mesh: Single<(&Mesh3d, &mut Visibility), With<ParticleMesh>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    let (handle, mut visibility) = mesh.into_inner();
+    // nothing needs to be written or drawn once all particles are gone.
+    if particles.pool.iter().all(|p| !p.is_alive()) {
+        visibility.set_if_neq(Visibility::Hidden);
+        return;
+    }
+
+    visibility.set_if_neq(Visibility::Visible);
+    let Some(mesh) = meshes.get_mut(&handle.0) else {
+        return;
+    };
+
+    let right = camera.right().as_vec3();
+    let up = camera.up().as_vec3();
+
+    if let Some(VertexAttributeValues::Float32x3(positions)) =
+        mesh.attribute_mut(Mesh::ATTRIBUTE_POSITION)
+    {
+        for (particle, quad) in particles.pool.iter().zip(positions.chunks_exact_mut(4)) {
+            if !particle.is_alive() {
+                // collapse the quad, so nothing is drawn.
+                quad.fill([0.0; 3]);
+                continue;
+            }
+
+            let (r, u) = (right * particle.size, up * particle.size);
+            let corners = [-r - u, r - u, r + u, -r + u];
+            for (vertex, corner) in quad.iter_mut().zip(corners) {
+                *vertex = (particle.pos + corner).to_array();
+            }
+        }
+    }
+
+    if let Some(VertexAttributeValues::Float32x4(uvs)) = mesh.attribute_mut(ATTRIBUTE_PARTICLE_UV) {
+        for (particle, quad) in particles.pool.iter().zip(uvs.chunks_exact_mut(4)) {
+            let (min, max) = (particle.uv, particle.uv + UV_SIZE);
+            // textures have +V pointing down.
+            let corners = [[min.x, max.y], [max.x, max.y], [max.x, min.y], [min.x, min.y]];
+            for (vertex, [u, v]) in quad.iter_mut().zip(corners) {
+                *vertex = [u, v, particle.texture as f32, particle.light];
+            }
+        }
+    }
+}
@@ -0,0 +1,238 @@
+//! Shaders with includes of shared snippets, reloaded when their files change.
+//!
+//! The shaders of the chunk materials are read from the mounted packs or the assets directory,
+//! and every `#include "path"` line is replaced by the file at that path in the `shaders`
+//! directory. A file is only included once, so snippets may include each other.
+//! The expanded source is written to a shader asset with a fixed handle, which the material
+//! uses. The files that were read are checked for changes every second, and when one changed
+//! every shader is expanded again, so Bevy rebuilds the pipelines of the materials.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use bevy::{asset::uuid_handle, prelude::*};
+use data::{fs::packs::AssetPackReader, info::RootPath};
+use fxhash::{FxHashMap, FxHashSet};
+
+use crate::packs::data_dirs;
+
+/// The chunk shader, expanded from `shaders/chunk.wgsl`.
+pub const CHUNK_SHADER: Handle<Shader> = uuid_handle!("61e76e4c-a455-4ef5-986a-b2c29be9df1a");
+
+/// The far column shader, expanded from `shaders/columns.wgsl`.
+pub const COLUMN_SHADER: Handle<Shader> = uuid_handle!("bc2a5ec6-a88e-4d9c-bdd0-534e3fddd168");
+
+/// Time between checks for changed shader files.
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Shaders expanded from files, by their path in the `shaders` directory.
+const SHADERS: [(&str, Handle<Shader>); 2] = [
+    ("chunk.wgsl", CHUNK_SHADER),
+    ("columns.wgsl", COLUMN_SHADER),
+];
+
+/// Files read by the last expansion of the shaders.
+#[derive(Resource, Default)]
+pub struct ShaderFiles {
+    /// Time of the last modification of each file, None if it couldn't be read.
+    modified: FxHashMap<PathBuf, Option<SystemTime>>,
+    since_check: Duration,
+}
+
+impl ShaderFiles {
+    /// Whether any of the files changed on disk since they were read.
+    fn changed_on_disk(&self) -> bool {
+        self.modified
+            .iter()
+            .any(|(path, modified)| last_modified(path) != *modified)
+    }
+}
+
+/// Expand the shaders again when the mounted packs change or any of their files changed.
+pub fn reload_shaders(
+    time: Res<Time>,
+    root: Res<RootPath>,
+    packs: Res<AssetPackReader>,
+    mut files: ResMut<ShaderFiles>,
+    mut shaders: ResMut<Assets<Shader>>,
+) {
+    files.since_check += time.delta();
+    if !packs.is_changed() {
+        if files.since_check < CHECK_INTERVAL {
+            return;
+        }
+
+        files.since_check = Duration::ZERO;
+        if !files.changed_on_disk() {
+            return;
+        }
+    }
+
+    let dirs = data_dirs(&root, &packs);
+    files.modified.clear();
+    for (name, handle) in SHADERS {
+        let mut expander = Expander {
+            dirs: &dirs,
+            included: FxHashSet::default(),
+            modified: &mut files.modified,
+        };
+
+        let mut source = String::new();
+        if let Err(e) = expander.expand(name, &mut source) {
+            error!("[C220] Failed to load shader '{name}' with error: '{e}'");
+            continue;
+        }
+
+        let shader = Shader::from_wgsl(source, format!("shaders/{name}"));
+        if let Err(e) = shaders.insert(&handle, shader) {
+            error!("[C221] Failed to replace shader '{name}' with error: '{e}'");
+        }
+    }
+
+    info!(
+        "Loaded {} shaders from {} files.",
+        SHADERS.len(),
+        files.modified.len()
+    );
+}
+
+/// Replaces the includes of shaders with the files they name.
+struct Expander<'a> {
+    /// Directories searched for each file, in order of priority.
+    dirs: &'a [PathBuf],
+
+    /// Files already included into this shader.
+    included: FxHashSet<String>,
+
+    /// Every file read or searched for, with the time of its last modification.
+    modified: &'a mut FxHashMap<PathBuf, Option<SystemTime>>,
+}
+
+impl Expander<'_> {
+    /// Write the file at this path in the `shaders` directory to the output,
+    /// unless it was already included.
+    fn expand(&mut self, name: &str, out: &mut String) -> Result<(), ShaderError> {
+        if !self.included.insert(name.to_owned()) {
+            return Ok(());
+        }
+
+        // the paths searched before the file are recorded too, so adding
+        // a missing file or one of higher priority reloads the shaders.
+        let mut found = None;
+        for dir in self.dirs {
+            let path = dir.join("shaders").join(name);
+            self.modified.insert(path.clone(), last_modified(&path));
+            if path.is_file() {
+                found = Some(path);
+                break;
+            }
+        }
+
+        let path = found.ok_or_else(|| ShaderError::NotFound(name.to_owned()))?;
+        let source = fs::read_to_string(&path)?;
+        for (i, line) in source.lines().enumerate() {
+            let Some(include) = line.trim().strip_prefix("#include") else {
+                out.push_str(line);
+                out.push('\n');
+                continue;
+            };
+
+            let include = include
+                .trim()
+                .strip_prefix('"')
+                .and_then(|include| include.strip_suffix('"'))
+                .ok_or_else(|| ShaderError::Include(name.to_owned(), i + 1))?;
+            self.expand(include, out)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn last_modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum ShaderError {
+    #[error("IO Error while reading shader: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("'{0}' isn't in the assets or any resource pack")]
+    NotFound(String),
+    #[error("Include on line {1} of '{0}' should be a quoted path")]
+    Include(String, usize),
+}
+
+#[cfg(test)]
+mod tests {
+    use data::util::TempDir;
+
+    use super::*;
+
+    /// Write the files to the `shaders` directory of a new directory.
+    fn write_shaders(name: &str, files: &[(&str, &str)]) -> TempDir {
+        let dir = TempDir::new(&format!("shaders-{name}"));
+        fs::create_dir_all(dir.join("shaders")).unwrap();
+        for (file, source) in files {
+            fs::write(dir.join("shaders").join(file), source).unwrap();
+        }
+        dir
+    }
+
+    fn expand(dir: &Path, name: &str) -> (Result<String, ShaderError>, ShaderFiles) {
+        let mut files = ShaderFiles::default();
+        let mut expander = Expander {
+            dirs: &[dir.to_path_buf()],
+            included: FxHashSet::default(),
+            modified: &mut files.modified,
+        };
+
+        let mut source = String::new();
+        let result = expander.expand(name, &mut source).map(|()| source);
+        (result, files)
+    }
+
+    #[test]
+    fn includes_are_expanded_once() {
+        let dir = write_shaders(
+            "includes",
+            &[
+                (
+                    "main.wgsl",
+                    "#include \"a.wgsl\"\n#include \"b.wgsl\"\nmain",
+                ),
+                ("a.wgsl", "  #include \"b.wgsl\"\na"),
+                ("b.wgsl", "b"),
+            ],
+        );
+
+        let (source, files) = expand(&dir, "main.wgsl");
+        assert_eq!(source.unwrap(), "b\na\nmain\n");
+        assert_eq!(files.modified.len(), 3);
+        assert!(!files.changed_on_disk());
+
+        let (source, _) = expand(&dir, "a.wgsl");
+        assert_eq!(source.unwrap(), "b\na\n");
+    }
+
+    #[test]
+    fn unquoted_includes_are_errors() {
+        let dir = write_shaders("unquoted", &[("main.wgsl", "main\n#include a.wgsl")]);
+        let (source, _) = expand(&dir, "main.wgsl");
+        assert!(matches!(source, Err(ShaderError::Include(name, 2)) if name == "main.wgsl"));
+    }
+
+    #[test]
+    fn adding_a_missing_file_is_a_change() {
+        let dir = write_shaders("missing", &[("main.wgsl", "#include \"a.wgsl\"")]);
+        let (source, files) = expand(&dir, "main.wgsl");
+        assert!(matches!(source, Err(ShaderError::NotFound(name)) if name == "a.wgsl"));
+        assert!(!files.changed_on_disk());
+
+        fs::write(dir.join("shaders/a.wgsl"), "a").unwrap();
+        assert!(files.changed_on_disk());
+    }
+}
@@ -1,125 +1,270 @@
+//! Procedural sky, driven by the world time.
+//!
+//! The sky is a dome of vertex colors around the camera, fading from the horizon to the
+//! zenith, with sun and moon billboards and a field of stars that turns with them at
+//! night. Everything is drawn just inside the far plane, so terrain is always in front.
+
+use std::f32::consts::TAU;
+
 use bevy::{
-    asset::LoadState,
-    core_pipeline::Skybox,
+    asset::RenderAssetUsages,
+    light::NotShadowCaster,
+    mesh::{Indices, PrimitiveTopology, VertexAttributeValues},
     prelude::*,
-    render::render_resource::{TextureViewDescriptor, TextureViewDimension},
 };
-use data::sequence::{RivuletState, Sequence};
-use fxhash::FxHashMap;
+use world::time::WorldTime;
 
-use crate::{player::MainCamera, sequences::starting::StartupSeq};
+use crate::player::MainCamera;
 
-#[derive(Resource, Default)]
-pub struct SkyboxAssets {
-    handles: FxHashMap<String, SkyboxAsset>,
-}
+/// Number of stars in the star field.
+const NUM_STARS: usize = 800;
 
-impl SkyboxAssets {
-    pub fn get(&self, name: impl AsRef<str>) -> Option<Handle<Image>> {
-        let name = name.as_ref();
-        if let Some(handle) = self.handles.get(name) {
-            if !handle.loading {
-                return Some(handle.handle.clone());
-            } else {
-                warn!("[C910] Skybox image with name: '{name}' exists but is still loading.")
-            }
-        } else {
-            warn!("[C909] Skybox image with name: '{name}' did not exist.");
-        }
+/// Distance of the sky from the camera, as a fraction of the far plane.
+const DOME_DISTANCE: f32 = 0.9;
+const STARS_DISTANCE: f32 = 0.88;
+const SUN_DISTANCE: f32 = 0.86;
 
-        None
-    }
-}
+/// Size of the sun and moon billboards, as a fraction of their distance.
+const SUN_SIZE: f32 = 0.12;
+const MOON_SIZE: f32 = 0.08;
 
-struct SkyboxAsset {
-    handle: Handle<Image>,
-    loading: bool,
-}
+/// Illuminance of the sun at noon.
+const SUN_ILLUMINANCE: f32 = 10_000.0;
 
-impl SkyboxAsset {
-    fn new(handle: Handle<Image>) -> Self {
-        Self {
-            handle,
-            loading: true,
-        }
-    }
-}
+const DAY_ZENITH: Color = Color::srgb(0.32, 0.52, 0.95);
+const DAY_HORIZON: Color = Color::srgb(0.7, 0.82, 1.0);
+const NIGHT_ZENITH: Color = Color::srgb(0.005, 0.006, 0.02);
+const NIGHT_HORIZON: Color = Color::srgb(0.03, 0.04, 0.09);
+const SUNSET_HORIZON: Color = Color::srgb(1.0, 0.5, 0.25);
+
+#[derive(Component)]
+pub struct SkyDome;
+
+#[derive(Component)]
+pub struct SkyStars;
+
+#[derive(Component)]
+pub struct SkySun;
+
+#[derive(Component)]
+pub struct SkyMoon;
+
+/// The directional light cast by the sun.
+#[derive(Component)]
+pub struct SkyLight;
 
 pub fn spawn_skybox(
     mut commands: Commands,
-    assets: Res<SkyboxAssets>,
-    camera: Single<Entity, With<MainCamera>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
 ) {
-    commands.entity(*camera).insert((Skybox {
-        image: assets.get("day").unwrap(),
-        brightness: 1000.0,
+    let mut dome = Sphere::new(1.0).mesh().uv(32, 16);
+    let num_vertices = dome.count_vertices();
+    dome.insert_attribute(Mesh::ATTRIBUTE_COLOR, vec![[1.0; 4]; num_vertices]);
+
+    // the camera is inside of the sky, so nothing is culled.
+    let sky_material = |color: Color, alpha_mode: AlphaMode| StandardMaterial {
+        base_color: color,
+        alpha_mode,
+        unlit: true,
+        fog_enabled: false,
+        cull_mode: None,
+        double_sided: true,
         ..default()
-    },));
-}
+    };
 
-pub fn despawn_skybox(mut commands: Commands, camera: Single<Entity, With<MainCamera>>) {
-    commands.entity(*camera).remove::<Skybox>();
+    commands.spawn((
+        SkyDome,
+        Mesh3d(meshes.add(dome)),
+        MeshMaterial3d(materials.add(sky_material(Color::WHITE, AlphaMode::Opaque))),
+        Transform::default(),
+        NotShadowCaster,
+    ));
+
+    commands.spawn((
+        SkyStars,
+        Mesh3d(meshes.add(star_field())),
+        MeshMaterial3d(materials.add(sky_material(Color::WHITE, AlphaMode::Blend))),
+        Transform::default(),
+        NotShadowCaster,
+    ));
+
+    commands.spawn((
+        SkySun,
+        Mesh3d(meshes.add(Rectangle::new(1.0, 1.0))),
+        MeshMaterial3d(materials.add(sky_material(Color::srgb(1.0, 0.95, 0.7), AlphaMode::Opaque))),
+        Transform::default(),
+        NotShadowCaster,
+    ));
+
+    commands.spawn((
+        SkyMoon,
+        Mesh3d(meshes.add(Rectangle::new(1.0, 1.0))),
+        MeshMaterial3d(materials.add(sky_material(Color::srgb(0.85, 0.87, 0.95), AlphaMode::Opaque))),
+        Transform::default(),
+        NotShadowCaster,
+    ));
+
+    commands.spawn((
+        SkyLight,
+        DirectionalLight {
+            illuminance: SUN_ILLUMINANCE,
+            ..default()
+        },
+        Transform::default(),
+    ));
 }
 
-/// Runs in the LoadTextures schedule.
-pub fn load_skybox_assets(
-    sequence: Res<Sequence<StartupSeq>>,
-    assets: Res<AssetServer>,
-    mut images: ResMut<Assets<Image>>,
-    mut skyboxes: Option<ResMut<SkyboxAssets>>,
+pub fn despawn_skybox(
     mut commands: Commands,
+    sky: Query<
+        Entity,
+        Or<(
+            With<SkyDome>,
+            With<SkyStars>,
+            With<SkySun>,
+            With<SkyMoon>,
+            With<SkyLight>,
+        )>,
+    >,
 ) {
-    let mut rivulet = sequence.get("load-skyboxes");
-    match rivulet.state {
-        RivuletState::Uninit => {
-            rivulet.state = RivuletState::InProgress;
-            let mut handles = FxHashMap::default();
-            handles.insert(
-                "day".into(),
-                SkyboxAsset::new(assets.load("skybox/day/cubemap.png")),
-            );
-            commands.insert_resource(SkyboxAssets { handles });
-        }
-        RivuletState::InProgress => {
-            let mut num_finished = 0;
-            let skyboxes = skyboxes.as_mut().unwrap();
-            for (name, handle) in &mut skyboxes.handles {
-                if handle.loading {
-                    if let Some(state) = assets.get_load_state(&handle.handle) {
-                        match state {
-                            LoadState::NotLoaded => {}
-                            LoadState::Loading => {}
-                            LoadState::Loaded => {
-                                let image = images.get_mut(&handle.handle).unwrap();
-                                image.reinterpret_stacked_2d_as_array(
-                                    image.height() / image.width(),
-                                );
-                                image.texture_view_descriptor = Some(TextureViewDescriptor {
-                                    dimension: Some(TextureViewDimension::Cube),
-                                    ..default()
-                                });
-                                num_finished += 1;
-                                handle.loading = false;
-                            }
-                            LoadState::Failed(e) => {
-                                panic!(
-                                    "[C811] Failed to load skybox '{}' with error: '{e:?}'",
-                                    name
-                                )
-                            }
-                        }
-                    }
-                } else {
-                    num_finished += 1;
-                }
-            }
-
-            let total = skyboxes.handles.len();
-            rivulet.progress = total as f32 / num_finished as f32;
-            if total == num_finished {
-                rivulet.state = RivuletState::Finished;
-            }
+    for entity in &sky {
+        commands.entity(entity).despawn();
+    }
+}
+
+/// Move the sky with the camera, and update it to the time of day.
+pub fn update_skybox(
+    time: Res<WorldTime>,
+    camera: Single<(&GlobalTransform, &Projection), With<MainCamera>>,
+    mut dome: Single<(&mut Transform, &Mesh3d), With<SkyDome>>,
+    mut stars: Single<
+        (&mut Transform, &MeshMaterial3d<StandardMaterial>),
+        (With<SkyStars>, Without<SkyDome>),
+    >,
+    mut sun: Single<&mut Transform, (With<SkySun>, Without<SkyDome>, Without<SkyStars>)>,
+    mut moon: Single<
+        &mut Transform,
+        (With<SkyMoon>, Without<SkyDome>, Without<SkyStars>, Without<SkySun>),
+    >,
+    mut light: Single<
+        (&mut Transform, &mut DirectionalLight),
+        (
+            With<SkyLight>,
+            Without<SkyDome>,
+            Without<SkyStars>,
+            Without<SkySun>,
+            Without<SkyMoon>,
+        ),
+    >,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let (camera, projection) = *camera;
+    let eye = camera.translation();
+    let far = match projection {
+        Projection::Perspective(perspective) => perspective.far,
+        _ => 1000.0,
+    };
+
+    let sun_dir = time.sun_direction();
+    let moon_dir = time.moon_direction();
+    let daylight = time.daylight();
+
+    // dome
+    let (dome_transform, dome_mesh) = &mut *dome;
+    **dome_transform = Transform::from_translation(eye).with_scale(Vec3::splat(far * DOME_DISTANCE));
+    if let Some(mesh) = meshes.get_mut(&dome_mesh.0) {
+        color_dome(mesh, sun_dir, daylight);
+    }
+
+    // stars turn with the sun, and fade in at night.
+    let (stars_transform, stars_material) = &mut *stars;
+    **stars_transform = Transform::from_translation(eye)
+        .with_rotation(time.sky_rotation())
+        .with_scale(Vec3::splat(far * STARS_DISTANCE));
+    if let Some(material) = materials.get_mut(&stars_material.0) {
+        material.base_color = Color::srgba(1.0, 1.0, 1.0, (1.0 - daylight * 1.5).max(0.0));
+    }
+
+    // billboards face the camera.
+    let distance = far * SUN_DISTANCE;
+    let billboard = |dir: Vec3, size: f32| {
+        Transform::from_translation(eye + dir * distance)
+            .looking_at(eye, Vec3::Y)
+            .with_scale(Vec3::splat(distance * size))
+    };
+    **sun = billboard(sun_dir, SUN_SIZE);
+    **moon = billboard(moon_dir, MOON_SIZE);
+
+    // light comes from the sun during the day, and dimly from the moon at night.
+    let (light_transform, directional) = &mut *light;
+    let light_dir = if sun_dir.y > 0.0 { sun_dir } else { moon_dir };
+    **light_transform = Transform::default().looking_to(-light_dir, Vec3::Y);
+    directional.illuminance = SUN_ILLUMINANCE * daylight.max(0.02);
+}
+
+/// Assign the colors of the dome for the position of the sun.
+fn color_dome(mesh: &mut Mesh, sun_dir: Vec3, daylight: f32) {
+    let Some(VertexAttributeValues::Float32x3(positions)) = mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+    else {
+        return;
+    };
+
+    let mix = |a: Color, b: Color, t: f32| a.mix(&b, t.clamp(0.0, 1.0));
+    let zenith = mix(NIGHT_ZENITH, DAY_ZENITH, daylight);
+    let horizon = mix(NIGHT_HORIZON, DAY_HORIZON, daylight);
+
+    // the horizon glows near the sun while it rises and sets.
+    let twilight = (1.0 - sun_dir.y.abs() / 0.3).max(0.0);
+    let sun_xz = sun_dir.with_y(0.0).normalize_or_zero();
+
+    let colors = positions
+        .iter()
+        .map(|pos| {
+            let dir = Vec3::from_array(*pos).normalize_or_zero();
+            let color = if dir.y >= 0.0 {
+                let glow = twilight * dir.with_y(0.0).normalize_or_zero().dot(sun_xz).max(0.0);
+                let horizon = mix(horizon, SUNSET_HORIZON, glow * (1.0 - dir.y * 3.0));
+                mix(horizon, zenith, dir.y.powf(0.6))
+            } else {
+                // below the horizon fades to a darker color.
+                mix(horizon, horizon.darker(0.3), -dir.y * 4.0)
+            };
+            color.to_linear().to_f32_array()
+        })
+        .collect::<Vec<_>>();
+
+    mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, colors);
+}
+
+/// Build a mesh of small quads scattered over a unit sphere.
+fn star_field() -> Mesh {
+    let mut positions = Vec::with_capacity(NUM_STARS * 4);
+    let mut indices = Vec::with_capacity(NUM_STARS * 6);
+
+    for i in 0..NUM_STARS {
+        // uniformly distributed directions from a hash of the index.
+        let hash = fxhash::hash64(&i);
+        let u = (hash & 0xFFFF) as f32 / 65535.0;
+        let v = ((hash >> 16) & 0xFFFF) as f32 / 65535.0;
+        let size = 0.002 + ((hash >> 32) & 0xFF) as f32 / 255.0 * 0.003;
+
+        let y = u * 2.0 - 1.0;
+        let r = (1.0 - y * y).sqrt();
+        let theta = v * TAU;
+        let dir = Vec3::new(r * theta.cos(), y, r * theta.sin());
+
+        // a quad facing the center of the sphere.
+        let (a, b) = dir.any_orthonormal_pair();
+        let base = positions.len() as u32;
+        for corner in [-a - b, a - b, a + b, -a + b] {
+            positions.push((dir + corner * size).to_array());
         }
-        RivuletState::Finished => {}
+        indices.extend([base, base + 1, base + 2, base, base + 2, base + 3]);
     }
+
+    Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::RENDER_WORLD)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+        .with_inserted_indices(Indices::U32(indices))
 }
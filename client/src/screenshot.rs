@@ -0,0 +1,84 @@
+//! Saving screenshots of the window.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use bevy::{
+    prelude::*,
+    render::view::screenshot::{Screenshot, ScreenshotCaptured, save_to_disk},
+};
+use data::{fs::save::civil_from_days, info::RootPath, locale::Locale};
+
+use crate::{states::AppState, ui::chat::ChatBox};
+
+pub fn screenshots_dir(root: &RootPath) -> PathBuf {
+    root.join("screenshots")
+}
+
+/// Capture the next frame and save it to the screenshots directory
+/// when the "screenshot" action fires.
+pub fn handle_screenshot(
+    root: Res<RootPath>,
+    locale: Res<Locale>,
+    app_state: Res<State<AppState>>,
+    mut commands: Commands,
+) {
+    let dir = screenshots_dir(&root);
+    if let Err(e) = fs::create_dir_all(&dir) {
+        error!(
+            "[C191] Failed to create screenshots directory: '{}' with error: '{e}'",
+            dir.display()
+        );
+        return;
+    }
+
+    let (path, name) = screenshot_path(&dir);
+    let message = format!("{} {name}", locale.get("chat.screenshot.saved"));
+    let in_game = *app_state == AppState::InGame;
+
+    commands
+        .spawn(Screenshot::primary_window())
+        .observe(save_to_disk(path))
+        .observe(
+            move |_: On<ScreenshotCaptured>, mut chat: ResMut<ChatBox>| {
+                info!("{message}");
+                // the chat is only drawn in game.
+                if in_game {
+                    chat.notify(message.clone());
+                }
+            },
+        );
+}
+
+/// Path of a new screenshot in the directory, and its file name.
+/// Screenshots taken in the same second get a number appended.
+fn screenshot_path(dir: &Path) -> (PathBuf, String) {
+    let stem = timestamp();
+    let mut name = format!("{stem}.png");
+    let mut n = 1;
+    while dir.join(&name).exists() {
+        n += 1;
+        name = format!("{stem}_{n}.png");
+    }
+    (dir.join(&name), name)
+}
+
+/// The current UTC time, like `2025-01-31_14.05.09`.
+pub fn timestamp() -> String {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+
+    let (year, month, day) = civil_from_days((secs / 86400) as i64);
+    let time = secs % 86400;
+    format!(
+        "{year:04}-{month:02}-{day:02}_{:02}.{:02}.{:02}",
+        time / 3600,
+        time / 60 % 60,
+        time % 60
+    )
+}
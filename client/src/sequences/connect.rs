@@ -1,4 +1,4 @@
-use std::io;
+use std::{io, time::Duration};
 
 use bevy::{
     prelude::*,
@@ -10,8 +10,8 @@ use data::{
 };
 
 use crate::{
-    events::{PlayerConnected, SyncRegistries},
-    net::{Client, channel::Channel},
+    events::{PlayerConnected, RegistryRemapped, SyncRegistries},
+    net::Client,
 };
 
 #[derive(Default, States, Eq, PartialEq, Debug, Clone, Hash)]
@@ -40,8 +40,43 @@ impl Sequences for ConnectSeq {
             Self::Syncronizing => None,
         }
     }
+
+    /// Registries are synchronized by a rivulet each, which start on their own.
+    fn steps(&self) -> &'static [&'static str] {
+        match *self {
+            Self::Establishing => &["establish"],
+            Self::Authenticating => &["authenticate"],
+            Self::Inactive | Self::Syncronizing => &[],
+        }
+    }
+
+    /// Establishing includes resolving the address and the TCP connect timeout.
+    fn timeout(&self) -> Option<Duration> {
+        match *self {
+            Self::Inactive => None,
+            Self::Establishing => Some(Duration::from_secs(15)),
+            Self::Authenticating => Some(Duration::from_secs(10)),
+            Self::Syncronizing => Some(Duration::from_secs(10)),
+        }
+    }
+
+    fn timeout_error(&self, timeout: Duration, _: Option<&str>) -> RivuletError {
+        RivuletError {
+            err_code: TIMEOUT_ERR_CODE,
+            err_text: format!(
+                "The server did not respond within {}s while {self:?}.",
+                timeout.as_secs()
+            ),
+        }
+    }
 }
 
+/// Error code of a connect sequence stage that timed out.
+pub const TIMEOUT_ERR_CODE: &str = "[N442]";
+
+/// Error code of a registry that couldn't be synchronized with the server.
+pub const SYNC_ERR_CODE: &str = "[N444]";
+
 #[derive(Resource)]
 pub struct ConnectSeqInfo {
     pub addr_string: String,
@@ -92,18 +127,62 @@ pub fn authenticate_connection(
     }
 }
 
-/// Wait for the server to send a registry synchronization payload.
-pub fn synchronize_registries(
-    seq: Res<Sequence<ConnectSeq>>,
-    mut msgs: MessageReader<SyncRegistries>,
-    mut channels: ResMut<Registry<Channel>>,
+/// Wait for the server to send a registry synchronization payload,
+/// then reorder the registry with this name to match the server's.
+/// Added for every registry by `AppExt::init_sync_registry`.
+///
+/// With a placeholder, entries the server has and the client doesn't are inserted
+/// before reordering, instead of failing the connection.
+pub fn synchronize_registry<T: Send + Sync + 'static>(
+    name: &str,
+    placeholder: Option<fn() -> T>,
+    seq: &Sequence<ConnectSeq>,
+    msgs: &mut MessageReader<SyncRegistries>,
+    registry: &mut Registry<T>,
+    remapped: &mut MessageWriter<RegistryRemapped>,
 ) {
-    if let Some(mut rivulet) = seq.get_in_progress("synchronize") {
-        if let Some(msg) = msgs.read().next() {
-            channels
-                .make_compliant(msg.payload.get("channels").unwrap())
-                .unwrap();
-            rivulet.state = RivuletState::Finished;
+    let Some(mut rivulet) = seq.get_in_progress(name) else {
+        return;
+    };
+
+    let Some(msg) = msgs.read().next() else {
+        return;
+    };
+
+    rivulet.state = RivuletState::Finished;
+    let Some(entries) = msg.payload.get(name) else {
+        seq.set_error(RivuletError {
+            err_code: SYNC_ERR_CODE,
+            err_text: format!("The server did not send the '{name}' registry."),
+        });
+        return;
+    };
+
+    if let Some(placeholder) = placeholder {
+        let missing = registry.reserve(entries, placeholder);
+        if missing > 0 {
+            warn!(
+                "[C132] The server has {missing} entries of registry '{name}' that the client doesn't, they are left empty."
+            );
+        }
+    }
+
+    match registry.make_compliant(entries) {
+        Ok(remap) => {
+            info!(
+                "Synchronized registry '{name}' with {} entries changing ID.",
+                remap.changed().count()
+            );
+            remapped.write(RegistryRemapped {
+                registry: name.to_string(),
+                remap,
+            });
+        }
+        Err(e) => {
+            seq.set_error(RivuletError {
+                err_code: SYNC_ERR_CODE,
+                err_text: format!("Registry '{name}' doesn't match the server's, {e}."),
+            });
         }
     }
 }
@@ -5,6 +5,8 @@ use data::{
     util::load_assets_from_folder,
 };
 
+use crate::render::atlases::{BlockTextureMeta, TextureMeta};
+
 #[derive(States, Eq, PartialEq, Debug, Default, Clone, Hash)]
 pub enum StartupSeq {
     #[default]
@@ -40,4 +42,12 @@ impl Sequences for StartupSeq {
             _ => return None,
         })
     }
+
+    fn steps(&self) -> &'static [&'static str] {
+        match *self {
+            Self::Inactive => &[],
+            Self::LoadTextures => &[BlockTextureMeta::LOAD_RIVULET_NAME],
+            Self::BuildTextureArrays => &[BlockTextureMeta::BUILD_RIVULET_NAME],
+        }
+    }
 }
@@ -1,20 +1,235 @@
-use bevy::{prelude::*, window::WindowMode};
+use std::{collections::BTreeMap, fs, path::PathBuf};
+
+use bevy::{
+    audio::Volume,
+    prelude::*,
+    window::{PresentMode, PrimaryWindow, WindowMode},
+};
+use data::{
+    info::RootPath,
+    logging::{LOGS_DIR, LogConfig, LoggingPlugin},
+};
+use protocol::types::PlayerAppearance;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    input::{Actions, Button},
+    player::MainCamera,
+    ui::UiPalette,
+    window::{self, WindowState},
+};
 
 /// Settings the client has configured that need to be saved.
-#[derive(Resource, Reflect)]
+/// Persisted to `settings.toml` in the root directory.
+#[derive(Resource, Reflect, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Settings {
     /// Fullscreen, Windowed, or BorderlessFullscreen.
     pub window_mode: WindowMode,
 
+    /// Size of the window while windowed, in logical pixels.
+    pub window_size: UVec2,
+
+    /// Position of the window while windowed, in physical pixels.
+    /// None lets the platform place it.
+    pub window_position: Option<IVec2>,
+
     /// Currently selected localization.
     pub language: String,
+
+    /// Terrain further than this from the camera (in chunks) isn't drawn.
+    pub render_distance: u32,
+
+    /// Whether presentation waits for vertical sync.
+    pub vsync: bool,
+
+    /// Vertical field of view, in degrees.
+    pub fov: f32,
+
+    /// Number of tasks that mesh chunks in parallel, 0 picks it from the number of cores.
+    pub mesh_workers: u32,
+
+    /// Most subchunks meshed per frame, 0 picks it from the number of cores.
+    pub meshes_per_frame: u32,
+
+    /// Most subchunk meshes uploaded to the GPU per frame, 0 picks it from the number of cores.
+    pub mesh_uploads_per_frame: u32,
+
+    /// Volume of all audio, from 0 to 1.
+    pub volume: f32,
+
+    /// Volume of block sounds, from 0 to 1, scaled by the master volume.
+    pub block_volume: f32,
+
+    /// Volume of footsteps, from 0 to 1, scaled by the master volume.
+    pub footstep_volume: f32,
+
+    /// Volume of ambient sounds, from 0 to 1, scaled by the master volume.
+    pub ambient_volume: f32,
+
+    /// Whether name tags and other labels in the world are hidden behind opaque blocks.
+    pub occlude_labels: bool,
+
+    /// Size of menus and the HUD, 1 is the default size.
+    pub ui_scale: f32,
+
+    /// Colors menus and the HUD are drawn with.
+    pub ui_palette: UiPalette,
+
+    /// Whether the seed of singleplayer worlds is shown in the performance overlay,
+    /// which can be turned off to keep it out of recordings.
+    pub show_seed: bool,
+
+    /// Name of the skin other players see this player with, empty for none.
+    /// Skins are directories in `skins` of the assets or a resource pack.
+    pub skin: String,
+
+    /// sRGB colors of the body and head, for players that don't have the skin.
+    pub skin_colors: [[u8; 3]; 2],
+
+    /// Address of the server joined from the Multiplayer menu.
+    pub server_address: String,
+
+    /// Names of the enabled resource packs, highest priority first.
+    pub resource_packs: Vec<String>,
+
+    /// Buttons of actions that were rebound, by action name.
+    /// Actions that aren't listed use their defaults.
+    #[reflect(ignore)]
+    pub bindings: BTreeMap<String, Vec<Button>>,
+
+    /// Log levels and files, read before the game starts.
+    /// `RUST_LOG` overrides the levels when it is set.
+    #[reflect(ignore)]
+    pub logging: LogConfig,
 }
 
 impl Default for Settings {
     fn default() -> Self {
         Self {
             window_mode: WindowMode::Windowed,
+            window_size: window::DEFAULT_SIZE,
+            window_position: None,
             language: "en-us".into(),
+            render_distance: 16,
+            vsync: true,
+            fov: 70.0,
+            mesh_workers: 0,
+            meshes_per_frame: 0,
+            mesh_uploads_per_frame: 0,
+            volume: 1.0,
+            block_volume: 1.0,
+            footstep_volume: 1.0,
+            ambient_volume: 1.0,
+            occlude_labels: true,
+            ui_scale: 1.0,
+            ui_palette: UiPalette::Default,
+            show_seed: true,
+            skin: String::new(),
+            skin_colors: PlayerAppearance::DEFAULT_COLORS.map(|[r, g, b, _]| [r, g, b]),
+            server_address: "127.0.0.1:51423".into(),
+            resource_packs: Vec::new(),
+            bindings: BTreeMap::new(),
+            logging: LogConfig::default(),
+        }
+    }
+}
+
+impl Settings {
+    pub fn path(root: &RootPath) -> PathBuf {
+        root.join("settings.toml")
+    }
+
+    /// Read the settings file, falling back to defaults if it
+    /// doesn't exist or can't be parsed.
+    pub fn load(root: &RootPath) -> Self {
+        let path = Self::path(root);
+        let Ok(text) = fs::read_to_string(&path) else {
+            return Self::default();
+        };
+
+        toml::from_str(&text).unwrap_or_else(|e| {
+            warn!(
+                "[C150] Failed to parse settings file: '{}' with error: '{e}', using defaults.",
+                path.display()
+            );
+            Self::default()
+        })
+    }
+
+    pub fn save(&self, root: &RootPath) {
+        let path = Self::path(root);
+        let result = toml::to_string_pretty(self)
+            .map_err(|e| e.to_string())
+            .and_then(|text| fs::write(&path, text).map_err(|e| e.to_string()));
+
+        if let Err(e) = result {
+            error!(
+                "[C151] Failed to save settings file: '{}' with error: '{e}'",
+                path.display()
+            );
         }
     }
 }
+
+/// Logging configured by the settings file, written to the logs directory.
+/// The settings are read here too, since logging starts before they are loaded.
+pub fn logging_plugin() -> LoggingPlugin {
+    let root = RootPath::default();
+    LoggingPlugin {
+        config: Settings::load(&root).logging,
+        dir: Some(root.join(LOGS_DIR)),
+        ..default()
+    }
+}
+
+/// Load the settings file and apply the saved bindings.
+/// Runs on startup, after every action has been added.
+pub fn load_settings(
+    root: Res<RootPath>,
+    mut settings: ResMut<Settings>,
+    mut actions: ResMut<Actions>,
+) {
+    *settings = Settings::load(&root);
+    for (label, buttons) in &settings.bindings {
+        actions.set_buttons(label, buttons.iter().copied());
+    }
+}
+
+/// Record the current bindings and save the settings file.
+pub fn save_settings(root: Res<RootPath>, mut settings: ResMut<Settings>, actions: Res<Actions>) {
+    settings.bindings = actions
+        .customized()
+        .map(|(label, buttons)| (label.to_string(), buttons.to_vec()))
+        .collect();
+    settings.save(&root);
+}
+
+/// Apply settings that can change while the game is running.
+pub fn apply_settings(
+    settings: Res<Settings>,
+    window_state: Res<State<WindowState>>,
+    mut window: Single<&mut Window, With<PrimaryWindow>>,
+    mut projection: Single<&mut Projection, With<MainCamera>>,
+    volume: Option<ResMut<GlobalVolume>>,
+) {
+    window.present_mode = if settings.vsync {
+        PresentMode::AutoVsync
+    } else {
+        PresentMode::AutoNoVsync
+    };
+
+    // the window mode is applied once the window is made visible.
+    if *window_state.get() == WindowState::Ready {
+        window.mode = settings.window_mode;
+    }
+
+    if let Projection::Perspective(perspective) = &mut **projection {
+        perspective.fov = settings.fov.to_radians();
+        perspective.far = settings.render_distance as f32 * 32.0;
+    }
+
+    if let Some(mut volume) = volume {
+        volume.volume = Volume::Linear(settings.volume);
+    }
+}
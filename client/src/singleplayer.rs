@@ -0,0 +1,89 @@
+//! Singleplayer is just multiplayer with a server running in the background.
+//!
+//! When a world is chosen in the WorldSelect menu, an `IntegratedServer` is started on
+//! an ephemeral localhost port and the connect sequence is pointed at it.
+
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+use data::fs::save::WorldInfo;
+use server::IntegratedServer;
+use world::World;
+
+use crate::{sequences::connect::ConnectSeqInfo, ui::menus::Menu};
+
+/// Request to start an integrated server for this world and connect to it.
+#[derive(Message, Clone)]
+pub struct LaunchSingleplayer {
+    /// Directory of the world save.
+    pub world_dir: PathBuf,
+}
+
+/// The integrated server that is currently running, if any.
+/// Removing this resource stops the server.
+#[derive(Resource, Deref)]
+pub struct Singleplayer {
+    #[deref]
+    pub server: IntegratedServer,
+
+    /// Directory of the world save.
+    pub world_dir: PathBuf,
+
+    /// Seed the world is generated with.
+    pub seed: i64,
+}
+
+/// Start the integrated server and transition to the connecting menu.
+pub fn launch_singleplayer(
+    mut msgs: MessageReader<LaunchSingleplayer>,
+    mut next_menu: ResMut<NextState<Menu>>,
+    mut commands: Commands,
+) {
+    // only the last request matters if more than one was sent.
+    let Some(msg) = msgs.read().last() else {
+        return;
+    };
+
+    info!("Launching singleplayer world: '{}'", msg.world_dir.display());
+    let info = WorldInfo::load_or_default(&msg.world_dir).and_then(|mut info| {
+        info.touch();
+        info.save(&msg.world_dir)?;
+        Ok(info)
+    });
+
+    let info = match info {
+        Ok(info) => info,
+        Err(e) => {
+            error!(
+                "[C121] Failed to read world info: '{}' with error: '{e}'",
+                msg.world_dir.display()
+            );
+            return;
+        }
+    };
+
+    match IntegratedServer::spawn(&msg.world_dir, &info) {
+        Ok(server) => {
+            // the client world needs the same height as the server world.
+            commands.insert_resource(World::new(info.max_y, info.min_y));
+            commands.insert_resource(ConnectSeqInfo {
+                addr_string: server.addr().to_string(),
+            });
+            commands.insert_resource(Singleplayer {
+                server,
+                world_dir: msg.world_dir.clone(),
+                seed: info.seed,
+            });
+            next_menu.set(Menu::Connecting);
+        }
+        Err(e) => {
+            error!("[C120] Failed to start integrated server with error: '{e}'");
+        }
+    }
+}
+
+/// Stop the integrated server when leaving the game.
+pub fn stop_singleplayer(mut commands: Commands) {
+    commands.remove_resource::<Singleplayer>();
+    commands.remove_resource::<ConnectSeqInfo>();
+}
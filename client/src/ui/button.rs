@@ -90,7 +90,8 @@ impl ButtonVisuals {
             Self::Text { content, width } => (
                 Text::new(content),
                 TextLayout::new_with_justify(Justify::Center),
-                BackgroundColor(Color::srgb(0.1, 0.1, 0.1)),
+                TextColor(vars.colors.text),
+                BackgroundColor(vars.colors.button),
                 TextFont {
                     font: vars.font.clone(),
                     font_size: 20.0,
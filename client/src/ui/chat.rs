@@ -1,16 +1,157 @@
-use bevy::{input::keyboard::KeyboardInput, prelude::*};
-use data::text::{SpecialKey, TextRecorder};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bevy::{
+    input::{keyboard::KeyboardInput, mouse::MouseWheel},
+    prelude::*,
+};
+use data::{
+    locale::Locale,
+    logging::{LogLevels, run_log_command},
+    registry::Registry,
+    text::{
+        SpecialKey, TextHistory, TextRecorder,
+        rich::{ChatMessage, ClickAction, RichText, Span},
+    },
+};
+use protocol::types::{ChatSend, CommandCompletion, CommandCompletions};
 
 use crate::{
     focus::{Focus, Focused},
     input::Actions,
+    net::{Client, channel::Channel},
     states::AppState,
     ui::UiVars,
 };
 
-#[derive(Resource, Default)]
+/// Max number of lines kept in the chat pane.
+const MAX_LINES: usize = 100;
+
+/// Max number of sent lines that can be recalled with up/down.
+const MAX_INPUT_HISTORY: usize = 50;
+
+/// Seconds a line stays fully visible while the chat is closed.
+const FADE_DELAY: f32 = 8.0;
+
+/// Seconds a line takes to fade out after `FADE_DELAY`.
+const FADE_DURATION: f32 = 2.0;
+
+/// Messages from the same sender within this many seconds
+/// of each other are grouped under one name.
+const GROUP_WINDOW: u64 = 60;
+
+/// A time divider is drawn before messages that arrive this
+/// many seconds after the previous message.
+const TIME_DIVIDER_GAP: u64 = 300;
+
+/// Pixels scrolled per line of mouse wheel movement.
+const SCROLL_SPEED: f32 = 20.0;
+
+#[derive(Resource)]
 pub struct ChatBox {
     recorder: TextRecorder,
+
+    /// Lines sent by this client, recalled with up/down.
+    history: TextHistory,
+
+    /// Commands that can be autocompleted, sent by the server on join.
+    completions: Vec<CommandCompletion>,
+
+    /// Sender and timestamp of the last message, for grouping.
+    last: Option<(Option<u64>, u64)>,
+
+    /// Messages from the client itself, added with the next received messages.
+    notices: Vec<ChatMessage>,
+}
+
+impl Default for ChatBox {
+    fn default() -> Self {
+        Self {
+            recorder: TextRecorder::default(),
+            history: TextHistory::with_limit(MAX_INPUT_HISTORY),
+            completions: Vec::new(),
+            last: None,
+            notices: Vec::new(),
+        }
+    }
+}
+
+impl ChatBox {
+    /// Commands whose names start with the command being typed,
+    /// or nothing if the input isn't a command.
+    fn matching_commands(&self) -> Vec<&CommandCompletion> {
+        let Some(command) = self.recorder.read().strip_prefix('/') else {
+            return Vec::new();
+        };
+
+        match command.split_once(' ') {
+            // arguments are being typed, only the exact command matches.
+            Some((name, _)) => self.completions.iter().filter(|c| c.name == name).collect(),
+            None => self
+                .completions
+                .iter()
+                .filter(|c| c.name.starts_with(command))
+                .collect(),
+        }
+    }
+
+    /// Complete the command being typed to the longest prefix shared by every match.
+    fn autocomplete(&mut self) {
+        let matches = self.matching_commands();
+        let Some(first) = matches.first() else {
+            return;
+        };
+
+        if self.recorder.read().contains(' ') {
+            return;
+        }
+
+        let mut prefix = first.name.clone();
+        for other in &matches[1..] {
+            let shared = prefix
+                .chars()
+                .zip(other.name.chars())
+                .take_while(|(a, b)| a == b)
+                .count();
+            prefix = prefix.chars().take(shared).collect();
+        }
+
+        let completed = if matches.len() == 1 {
+            format!("/{prefix} ")
+        } else {
+            format!("/{prefix}")
+        };
+        self.recorder.set(completed);
+        self.recorder.go_to_end();
+    }
+
+    /// Usage hints for the commands matching the input.
+    fn suggestions(&self) -> String {
+        self.matching_commands()
+            .iter()
+            .map(|cmd| format!("{} - {}", cmd.usage(), cmd.description))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Show a message in the chat that didn't come from the server.
+    pub fn notify(&mut self, text: impl Into<RichText>) {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        self.notices.push(ChatMessage {
+            sender: None,
+            sender_name: None,
+            text: text.into(),
+            timestamp,
+        });
+    }
+
+    /// Replace the input with a line from the history.
+    fn recall(&mut self, item: Option<String>) {
+        self.recorder.set(item.unwrap_or_default());
+        self.recorder.go_to_end();
+    }
 }
 
 #[derive(Component)]
@@ -22,26 +163,59 @@ pub struct ChatScrollView;
 #[derive(Component)]
 pub struct ChatInputContainer;
 
+/// Usage hints of the commands matching the input.
+#[derive(Component)]
+pub struct ChatSuggestions;
+
+/// A line in the chat pane.
+#[derive(Component)]
+pub struct ChatLine {
+    /// Time (elapsed seconds) the line was received.
+    received: f32,
+}
+
+/// Attached to text in the chat that does something when clicked while the chat is open,
+/// like the name of a sender, which is inserted into the input.
+#[derive(Component)]
+pub struct ChatClick(pub ClickAction);
+
+/// Text shown above the chat while the text this is attached to is hovered.
+#[derive(Component)]
+pub struct ChatHoverText(pub String);
+
+/// Shows the `ChatHoverText` of the hovered text.
+#[derive(Component)]
+pub struct ChatHover;
+
+/// Line drawn under underlined text, faded with the text.
+#[derive(Component)]
+pub struct ChatUnderline;
+
 /// Action handler for "focus-chatbox"
 pub fn handle_focus_chatbox(
     q_box: Query<Entity, With<ChatContainer>>,
-    mut q_input: Query<(&mut InheritedVisibility, &mut Text), With<ChatInputContainer>>,
+    mut q_input: Query<(&mut Visibility, &mut Text), With<ChatInputContainer>>,
     mut data: ResMut<ChatBox>,
+    keys: Res<ButtonInput<KeyCode>>,
     app_state: Res<State<AppState>>,
     mut focus: Focus,
 ) {
     if *app_state == AppState::InGame && focus.player_has_focus() {
         if let Ok(container) = q_box.single() {
-            // clear any existing text
+            // clear any existing text, or start a command if opened with slash.
             data.recorder.clear();
+            data.history.go_to_start();
+            if keys.just_pressed(KeyCode::Slash) {
+                data.recorder.insert('/');
+            }
 
             // transfer focus to the chat box.
             focus.to_entity(container);
 
             // update visibility of input container and clear any existing text.
             if let Ok((mut vis, mut text)) = q_input.single_mut() {
-                *vis = InheritedVisibility::VISIBLE;
-                text.clear();
+                *vis = Visibility::Inherited;
+                text.0 = data.recorder.read().to_owned();
             }
         }
     }
@@ -50,57 +224,446 @@ pub fn handle_focus_chatbox(
 pub fn update_chatbox(
     mut data: ResMut<ChatBox>,
     mut keyboard: MessageReader<KeyboardInput>,
-    q_container: Query<(&Children, Option<&Focused>), With<ChatContainer>>,
-    mut q_input_box: Query<(&mut Text, &mut InheritedVisibility), With<ChatInputContainer>>,
+    q_container: Query<Option<&Focused>, With<ChatContainer>>,
+    mut q_input_box: Query<(&mut Text, &mut Visibility), With<ChatInputContainer>>,
+    mut q_suggestions: Query<
+        (&mut Text, &mut Visibility),
+        (With<ChatSuggestions>, Without<ChatInputContainer>),
+    >,
     mut focus: Focus,
     actions: Res<Actions>,
+    channels: Res<Registry<Channel>>,
+    client: Option<ResMut<Client>>,
+    log_levels: Option<ResMut<LogLevels>>,
 ) {
-    if let Ok((_, focused)) = q_container.single() {
-        if focused.is_some() {
-            let mut lose_focus = false;
-
-            if actions.just_activated("close-menu") {
-                lose_focus = true;
-            } else {
-                for ev in keyboard.read() {
-                    if let Some(special) = data.recorder.update(&ev) {
-                        match special {
-                            SpecialKey::Submit => {
-                                let content = data.recorder.submit();
-                                info!("Chatbox Submit: {content}");
-                                lose_focus = true;
-                                break;
-                            }
-                            SpecialKey::NoEffect => {
-                                info!("Key had no effect.")
-                            }
-                            SpecialKey::Autocomplete => {
-                                info!("Autocomplete Requested")
-                            }
-                            SpecialKey::HistoryUp => {
-                                info!("History Up Requested")
-                            }
-                            SpecialKey::HistoryDown => {
-                                info!("History Down Requested")
-                            }
+    let Ok(focused) = q_container.single() else {
+        return;
+    };
+
+    if focused.is_none() {
+        // input typed while the chat is closed shouldn't show up once it opens.
+        keyboard.clear();
+        return;
+    }
+
+    let mut lose_focus = false;
+    let mut submitted = None;
+
+    if actions.just_activated("close-menu") {
+        lose_focus = true;
+    } else {
+        for ev in keyboard.read() {
+            if let Some(special) = data.recorder.update(ev) {
+                match special {
+                    SpecialKey::Submit => {
+                        submitted = Some(data.recorder.submit());
+                        lose_focus = true;
+                        break;
+                    }
+                    SpecialKey::Autocomplete => data.autocomplete(),
+                    SpecialKey::HistoryUp => {
+                        let item = data.history.prev().map(str::to_owned);
+                        if item.is_some() {
+                            data.recall(item);
                         }
                     }
+                    SpecialKey::HistoryDown => {
+                        let item = data.history.next().map(str::to_owned);
+                        data.recall(item);
+                    }
+                    SpecialKey::NoEffect => {}
                 }
             }
+        }
+    }
+
+    if let Some(content) = submitted.filter(|content| !content.trim().is_empty()) {
+        info!("Chatbox Submit: {content}");
+        data.history.push(content.clone());
+        let words = content.split_whitespace().collect::<Vec<_>>();
+        if let ["/log", args @ ..] = &words[..]
+            && let Some(mut log_levels) = log_levels
+        {
+            // changes the log levels of this client, so it isn't sent to the server.
+            let reply = run_log_command(&mut log_levels, args);
+            data.notify(reply);
+        } else if let Some(mut client) = client {
+            // the server turns its tracing on or off too, and replies.
+            match content.split_whitespace().collect::<Vec<_>>()[..] {
+                ["/trace", "on"] => client.trace_mut().set_enabled(true),
+                ["/trace", "off"] => client.trace_mut().set_enabled(false),
+                _ => {}
+            }
+
+            let channel = channels.resolve("chat-send").unwrap().into();
+            let payload = serde_json::to_vec(&ChatSend { text: content }).unwrap();
+            client.tcp_send(channel, payload);
+        }
+    }
+
+    if let Ok((mut text, mut vis)) = q_input_box.single_mut() {
+        if lose_focus {
+            *vis = Visibility::Hidden;
+            text.clear();
+            focus.to_player();
+        } else {
+            text.0 = data.recorder.read().to_owned();
+        }
+    }
+
+    if let Ok((mut text, mut vis)) = q_suggestions.single_mut() {
+        let suggestions = if lose_focus {
+            String::new()
+        } else {
+            data.suggestions()
+        };
 
-            if let Ok((mut text, mut vis)) = q_input_box.single_mut() {
-                if lose_focus {
-                    *vis = InheritedVisibility::HIDDEN;
-                    text.clear();
-                    focus.to_player();
+        *vis = if suggestions.is_empty() {
+            Visibility::Hidden
+        } else {
+            Visibility::Inherited
+        };
+        text.0 = suggestions;
+    }
+}
+
+/// Store the commands the server allows autocompleting.
+/// Sent right after joining, so this runs outside of InGame.
+pub fn recv_command_completions(channels: Res<Registry<Channel>>, mut data: ResMut<ChatBox>) {
+    for packet in channels.get_by_name("command-completions").unwrap().recv() {
+        if let Some(completions) = packet.json::<CommandCompletions>() {
+            data.completions = completions.commands;
+        }
+    }
+}
+
+/// Add received messages to the chat pane.
+pub fn recv_chat_messages(
+    mut commands: Commands,
+    channels: Res<Registry<Channel>>,
+    time: Res<Time>,
+    vars: Res<UiVars>,
+    locale: Res<Locale>,
+    mut data: ResMut<ChatBox>,
+    mut q_view: Query<(Entity, &mut ScrollPosition, Option<&Children>), With<ChatScrollView>>,
+) {
+    let Ok((view, mut scroll, children)) = q_view.single_mut() else {
+        return;
+    };
+
+    let now = time.elapsed_secs();
+    let mut count = children.map_or(0, |children| children.len());
+    let received = channels
+        .get_by_name("chat-message")
+        .unwrap()
+        .recv()
+        .filter_map(|packet| packet.json::<ChatMessage>());
+    let notices = std::mem::take(&mut data.notices);
+    for msg in received.chain(notices) {
+
+        let gap = data
+            .last
+            .map_or(u64::MAX, |(_, last)| msg.timestamp.saturating_sub(last));
+        if gap >= TIME_DIVIDER_GAP {
+            let divider = commands
+                .spawn((
+                    ChatLine { received: now },
+                    line_node(),
+                    Text::new(format_time(msg.timestamp)),
+                    TextColor(vars.colors.muted),
+                    chat_font(&vars),
+                ))
+                .id();
+            commands.entity(view).add_child(divider);
+            count += 1;
+        }
+
+        let grouped = gap < GROUP_WINDOW
+            && msg.sender.is_some()
+            && data.last.is_some_and(|(sender, _)| sender == msg.sender);
+        data.last = Some((msg.sender, msg.timestamp));
+
+        let line = commands
+            .spawn((ChatLine { received: now }, line_node()))
+            .with_children(|line| {
+                if let Some(name) = msg.sender_name.filter(|_| !grouped) {
+                    line.spawn((
+                        Button,
+                        ChatClick(ClickAction::Insert(format!("{name} "))),
+                        Text::new(format!("<{name}> ")),
+                        TextColor(vars.colors.name),
+                        chat_font(&vars),
+                    ));
+                }
+
+                // messages from the server don't have a sender.
+                let color = if msg.sender.is_some() {
+                    vars.colors.text
                 } else {
-                    text.0 = data.recorder.read().to_owned();
+                    vars.colors.notice
+                };
+                line.spawn(Node {
+                    flex_direction: FlexDirection::Column,
+                    margin: UiRect::left(Val::Px(if grouped { 12.0 } else { 0.0 })),
+                    ..default()
+                })
+                .with_children(|text| {
+                    for row in split_rows(msg.text.spans(&locale)) {
+                        text.spawn(Node {
+                            flex_wrap: FlexWrap::Wrap,
+                            ..default()
+                        })
+                        .with_children(|row_node| {
+                            for span in row {
+                                spawn_span(row_node, span, color, &locale, &vars);
+                            }
+                        });
+                    }
+                });
+            })
+            .id();
+        commands.entity(view).add_child(line);
+        count += 1;
+
+        // scroll to the newest message; layout clamps this to the bottom.
+        scroll.y = f32::MAX;
+    }
+
+    // drop the oldest lines past the limit.
+    if let Some(children) = children
+        && count > MAX_LINES
+    {
+        for &old in children.iter().take(count - MAX_LINES) {
+            commands.entity(old).despawn();
+        }
+    }
+}
+
+/// Scroll the chat pane with the mouse wheel while it is open.
+pub fn scroll_chat(
+    mut wheel: MessageReader<MouseWheel>,
+    q_container: Query<(), (With<ChatContainer>, With<Focused>)>,
+    mut q_view: Query<&mut ScrollPosition, With<ChatScrollView>>,
+) {
+    if q_container.is_empty() {
+        wheel.clear();
+        return;
+    }
+
+    if let Ok(mut scroll) = q_view.single_mut() {
+        for ev in wheel.read() {
+            scroll.y = (scroll.y - ev.y * SCROLL_SPEED).max(0.0);
+        }
+    }
+}
+
+/// Fade out lines while the chat is closed, and show every line while it is open.
+pub fn fade_chat_lines(
+    time: Res<Time>,
+    q_container: Query<(), (With<ChatContainer>, With<Focused>)>,
+    mut q_view: Query<&mut BackgroundColor, With<ChatScrollView>>,
+    q_lines: Query<(Entity, &ChatLine)>,
+    q_children: Query<&Children>,
+    mut q_colors: Query<&mut TextColor>,
+    mut q_shadows: Query<&mut TextShadow>,
+    mut q_underlines: Query<&mut BackgroundColor, (With<ChatUnderline>, Without<ChatScrollView>)>,
+) {
+    let open = !q_container.is_empty();
+    let now = time.elapsed_secs();
+
+    if let Ok(mut background) = q_view.single_mut() {
+        background.0 = Color::srgba(0.0, 0.0, 0.0, if open { 0.4 } else { 0.0 });
+    }
+
+    for (entity, line) in &q_lines {
+        let alpha = if open {
+            1.0
+        } else {
+            1.0 - ((now - line.received - FADE_DELAY) / FADE_DURATION).clamp(0.0, 1.0)
+        };
+
+        let texts = std::iter::once(entity).chain(q_children.iter_descendants(entity));
+        for text in texts {
+            if let Ok(mut color) = q_colors.get_mut(text)
+                && color.0.alpha() != alpha
+            {
+                color.0.set_alpha(alpha);
+            }
+            if let Ok(mut shadow) = q_shadows.get_mut(text)
+                && shadow.color.alpha() != alpha
+            {
+                shadow.color.set_alpha(alpha);
+            }
+            if let Ok(mut underline) = q_underlines.get_mut(text)
+                && underline.0.alpha() != alpha
+            {
+                underline.0.set_alpha(alpha);
+            }
+        }
+    }
+}
+
+/// Run the action of chat text when it is clicked while the chat is open.
+pub fn handle_chat_clicks(
+    q_container: Query<(), (With<ChatContainer>, With<Focused>)>,
+    q_clicks: Query<(&Interaction, &ChatClick), Changed<Interaction>>,
+    channels: Res<Registry<Channel>>,
+    mut data: ResMut<ChatBox>,
+    mut client: Option<ResMut<Client>>,
+) {
+    if q_container.is_empty() {
+        return;
+    }
+
+    for (interaction, click) in &q_clicks {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        match &click.0 {
+            ClickAction::Insert(text) => data.recorder.insert_str(text.clone()),
+            ClickAction::SuggestCommand(text) => {
+                data.recorder.set(text.clone());
+                data.recorder.go_to_end();
+            }
+            ClickAction::RunCommand(text) => {
+                if let Some(client) = client.as_mut() {
+                    let channel = channels.resolve("chat-send").unwrap().into();
+                    let send = ChatSend { text: text.clone() };
+                    client.tcp_send(channel, serde_json::to_vec(&send).unwrap());
                 }
             }
         }
     }
 }
 
+/// Show the hover text of the chat text under the cursor while the chat is open.
+pub fn update_chat_hover(
+    q_container: Query<(), (With<ChatContainer>, With<Focused>)>,
+    q_texts: Query<(&Interaction, &ChatHoverText)>,
+    mut q_hover: Query<(&mut Text, &mut Visibility), With<ChatHover>>,
+) {
+    let Ok((mut text, mut vis)) = q_hover.single_mut() else {
+        return;
+    };
+
+    let hovered = q_texts
+        .iter()
+        .find(|(interaction, _)| **interaction != Interaction::None)
+        .filter(|_| !q_container.is_empty());
+
+    match hovered {
+        Some((_, hover)) => {
+            if text.0 != hover.0 {
+                text.0 = hover.0.clone();
+            }
+            *vis = Visibility::Inherited;
+        }
+        None => *vis = Visibility::Hidden,
+    }
+}
+
+/// Forget the previous game's chat state when leaving the game.
+/// The chat pane itself is despawned with the InGame state.
+pub fn reset_chatbox(mut data: ResMut<ChatBox>) {
+    data.recorder.clear();
+    data.completions.clear();
+    data.last = None;
+    data.notices.clear();
+}
+
+/// Split spans into the rows of text between line breaks.
+fn split_rows(spans: Vec<Span<'_>>) -> Vec<Vec<Span<'_>>> {
+    let mut rows = vec![Vec::new()];
+    for span in spans {
+        for (i, part) in span.text.split('\n').enumerate() {
+            if i > 0 {
+                rows.push(Vec::new());
+            }
+            if !part.is_empty() {
+                rows.last_mut().unwrap().push(Span {
+                    text: part.to_owned(),
+                    ..span.clone()
+                });
+            }
+        }
+    }
+    rows
+}
+
+/// Spawn the text of one span, in `color` if the span doesn't have a color of its own.
+/// There is no italic font, so italic text is drawn upright.
+fn spawn_span(
+    parent: &mut ChildSpawnerCommands,
+    span: Span,
+    color: Color,
+    locale: &Locale,
+    vars: &UiVars,
+) {
+    let color = span.style.color.map_or(color, Color::from);
+    let mut column = parent.spawn(Node {
+        flex_direction: FlexDirection::Column,
+        ..default()
+    });
+
+    if span.click.is_some() || span.hover.is_some() {
+        column.insert(Button);
+    }
+    if let Some(click) = span.click {
+        column.insert(ChatClick(click.clone()));
+    }
+    if let Some(hover) = span.hover {
+        column.insert(ChatHoverText(hover.to_plain(locale)));
+    }
+
+    column.with_children(|column| {
+        let mut text = column.spawn((Text::new(span.text), TextColor(color), chat_font(vars)));
+
+        // bold text is drawn twice, one pixel apart.
+        if span.style.is_bold() {
+            text.insert(TextShadow {
+                offset: Vec2::new(1.0, 0.0),
+                color,
+            });
+        }
+
+        if span.style.is_underlined() {
+            column.spawn((
+                ChatUnderline,
+                BackgroundColor(color),
+                Node {
+                    width: Val::Percent(100.0),
+                    height: Val::Px(1.0),
+                    ..default()
+                },
+            ));
+        }
+    });
+}
+
+fn line_node() -> Node {
+    Node {
+        width: Val::Percent(100.0),
+        flex_wrap: FlexWrap::Wrap,
+        padding: UiRect::horizontal(Val::Px(2.0)),
+        ..default()
+    }
+}
+
+fn chat_font(vars: &UiVars) -> TextFont {
+    TextFont {
+        font: vars.font(),
+        font_size: 10.0,
+        ..default()
+    }
+}
+
+/// Time of day (UTC) of a unix timestamp, as `HH:MM`.
+fn format_time(timestamp: u64) -> String {
+    let minutes = timestamp / 60;
+    format!("{:02}:{:02} UTC", (minutes / 60) % 24, minutes % 60)
+}
+
 /// Run OnEnter(AppState::InGame)
 #[rustfmt::skip]
 pub fn draw_chatbox(
@@ -109,11 +672,11 @@ pub fn draw_chatbox(
 ) {
     commands.spawn((
         ChatContainer,
-        Visibility::Hidden,
         DespawnOnExit(AppState::InGame),
         Node {
+            position_type: PositionType::Absolute,
             width: Val::Vw(30.0),
-            max_height: Val::Vh(30.0),
+            max_height: Val::Vh(40.0),
             flex_direction: FlexDirection::ColumnReverse,
             bottom: Val::Px(0.0),
             left: Val::Px(0.0),
@@ -121,15 +684,12 @@ pub fn draw_chatbox(
             ..default()
         },
     )).with_children(|parent| {
+        // children are laid out bottom to top.
         parent.spawn((
             ChatInputContainer,
             Text::new(""),
-            InheritedVisibility::HIDDEN,
-            TextFont {
-                font: vars.font(),
-                font_size: 10.0,
-                ..default()
-            },
+            Visibility::Hidden,
+            chat_font(&vars),
             BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.7)),
             TextLayout::new_with_justify(Justify::Left),
             Node {
@@ -139,5 +699,46 @@ pub fn draw_chatbox(
                 ..default()
             }
         ));
+
+        parent.spawn((
+            ChatSuggestions,
+            Text::new(""),
+            Visibility::Hidden,
+            chat_font(&vars),
+            TextColor(Color::srgb(0.8, 0.8, 0.8)),
+            BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.7)),
+            Node {
+                width: Val::Percent(100.0),
+                padding: UiRect::all(Val::Px(2.0)),
+                ..default()
+            }
+        ));
+
+        parent.spawn((
+            ChatScrollView,
+            ScrollPosition::default(),
+            BackgroundColor(Color::NONE),
+            Node {
+                width: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                overflow: Overflow::scroll_y(),
+                ..default()
+            },
+        ));
+
+        // above the lines, since children are laid out bottom to top.
+        parent.spawn((
+            ChatHover,
+            Text::new(""),
+            Visibility::Hidden,
+            chat_font(&vars),
+            TextColor(Color::srgb(0.8, 0.8, 0.8)),
+            BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.7)),
+            Node {
+                width: Val::Percent(100.0),
+                padding: UiRect::all(Val::Px(2.0)),
+                ..default()
+            }
+        ));
     });
 }
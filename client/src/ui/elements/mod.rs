@@ -1,5 +1,7 @@
 use bevy::prelude::*;
 
+pub mod text_field;
+
 /// Indicates an element can be selected
 #[derive(Component)]
 pub struct Selectable {}
@@ -0,0 +1,100 @@
+use bevy::{input::keyboard::KeyboardInput, prelude::*};
+use data::text::TextRecorder;
+
+use crate::ui::{
+    UiVars,
+    button::{ButtonAction, ButtonClicked, ButtonVisuals},
+    elements::Selected,
+};
+
+/// A single line of editable text in a menu.
+/// Receives keyboard input while it is `Selected`, which happens when it is clicked.
+#[derive(Component)]
+pub struct TextField {
+    recorder: TextRecorder,
+
+    /// Shown in place of the text while the field is empty.
+    placeholder: String,
+
+    /// Max number of characters that can be entered.
+    max_len: usize,
+}
+
+impl TextField {
+    pub fn new(text: impl Into<String>, placeholder: impl Into<String>, max_len: usize) -> Self {
+        let mut recorder = TextRecorder::default();
+        recorder.set(text);
+        recorder.go_to_end();
+        Self {
+            recorder,
+            placeholder: placeholder.into(),
+            max_len,
+        }
+    }
+
+    pub fn bundle(self, vars: &UiVars) -> impl Bundle {
+        let text = self.display(false);
+        (
+            ButtonAction::None,
+            ButtonVisuals::text(text, Val::Percent(100.0)).bundle(vars),
+            self,
+        )
+    }
+
+    pub fn read(&self) -> &str {
+        self.recorder.read()
+    }
+
+    fn display(&self, selected: bool) -> String {
+        match (self.recorder.read(), selected) {
+            ("", false) => self.placeholder.clone(),
+            (text, false) => text.to_owned(),
+            (text, true) => format!("{text}_"),
+        }
+    }
+}
+
+/// Select a text field when it is clicked, deselecting any other field.
+pub fn select_text_fields(
+    mut commands: Commands,
+    mut clicks: MessageReader<ButtonClicked>,
+    fields: Query<Entity, With<TextField>>,
+    selected: Query<Entity, (With<TextField>, With<Selected>)>,
+) {
+    for click in clicks.read() {
+        if fields.contains(click.entity) {
+            for entity in &selected {
+                commands.entity(entity).remove::<Selected>();
+            }
+            commands.entity(click.entity).insert(Selected);
+        }
+    }
+}
+
+/// Record keyboard input into the selected text field and keep the text of fields up to date.
+pub fn update_text_fields(
+    mut keyboard: MessageReader<KeyboardInput>,
+    mut fields: Query<(&mut TextField, &mut Text, Has<Selected>)>,
+) {
+    let events = keyboard.read().collect::<Vec<_>>();
+    for (mut field, mut text, selected) in &mut fields {
+        if selected {
+            for ev in &events {
+                field.recorder.update(ev);
+            }
+
+            // drop characters past the limit.
+            if field.recorder.len() > field.max_len {
+                let max_len = field.max_len;
+                let truncated = field.recorder.read().chars().take(max_len).collect::<String>();
+                field.recorder.set(truncated);
+                field.recorder.go_to_end();
+            }
+        }
+
+        let display = field.display(selected);
+        if text.0 != display {
+            text.0 = display;
+        }
+    }
+}
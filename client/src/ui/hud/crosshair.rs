@@ -0,0 +1,43 @@
+use bevy::prelude::*;
+
+use crate::ui::UiVars;
+
+const CROSSHAIR_SIZE: f32 = 16.0;
+const CROSSHAIR_THICKNESS: f32 = 2.0;
+
+/// Marker for the crosshair in the middle of the screen.
+#[derive(Component)]
+pub struct Crosshair;
+
+/// Draw the crosshair, a plus made of two bars.
+pub fn draw(parent: &mut ChildSpawnerCommands, vars: &UiVars) {
+    parent
+        .spawn((
+            Crosshair,
+            Node {
+                width: Val::Px(CROSSHAIR_SIZE),
+                height: Val::Px(CROSSHAIR_SIZE),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            Pickable::IGNORE,
+        ))
+        .with_children(|parent| {
+            for (width, height) in [
+                (CROSSHAIR_SIZE, CROSSHAIR_THICKNESS),
+                (CROSSHAIR_THICKNESS, CROSSHAIR_SIZE),
+            ] {
+                parent.spawn((
+                    Node {
+                        position_type: PositionType::Absolute,
+                        width: Val::Px(width),
+                        height: Val::Px(height),
+                        ..default()
+                    },
+                    BackgroundColor(vars.colors.text.with_alpha(0.8)),
+                    Pickable::IGNORE,
+                ));
+            }
+        });
+}
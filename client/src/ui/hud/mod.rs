@@ -0,0 +1,151 @@
+//! The HUD drawn over the world while in game, separate from menus.
+//!
+//! The HUD has a container for each place on the screen widgets can go. Widgets are
+//! registered with `AppExt::add_hud_widget`, and drawn into their slot when the HUD
+//! is drawn. Widgets update themselves with their own systems.
+
+use bevy::prelude::*;
+
+use crate::{states::AppState, ui::UiVars};
+
+pub mod crosshair;
+pub mod stats;
+
+/// Places on the screen HUD widgets can be drawn.
+#[derive(Component, Copy, Clone, Eq, PartialEq, Debug)]
+pub enum HudSlot {
+    /// The middle of the screen.
+    Center,
+
+    /// Above the bottom of the screen, with widgets laid out in a column.
+    BottomCenter,
+}
+
+impl HudSlot {
+    const ALL: [Self; 2] = [Self::Center, Self::BottomCenter];
+
+    fn node(self) -> Node {
+        match self {
+            Self::Center => Node {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            Self::BottomCenter => Node {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.0),
+                bottom: Val::Px(16.0),
+                flex_direction: FlexDirection::ColumnReverse,
+                align_items: AlignItems::Center,
+                row_gap: Val::Px(4.0),
+                ..default()
+            },
+        }
+    }
+}
+
+/// Draws a widget into the slot it was registered with.
+pub type DrawHudWidget = fn(&mut ChildSpawnerCommands, &UiVars);
+
+/// Widgets drawn into the HUD, in the order they were registered.
+#[derive(Resource, Default)]
+pub struct HudWidgets(Vec<(HudSlot, DrawHudWidget)>);
+
+impl HudWidgets {
+    pub fn add(&mut self, slot: HudSlot, draw: DrawHudWidget) {
+        self.0.push((slot, draw));
+    }
+}
+
+/// Whether the HUD is shown, toggled with the "toggle-hud" action.
+#[derive(Resource)]
+pub struct HudVisible(pub bool);
+
+impl Default for HudVisible {
+    fn default() -> Self {
+        Self(true)
+    }
+}
+
+/// Root of the HUD, which every slot is a child of.
+#[derive(Component)]
+pub struct HudRoot;
+
+/// Draw the HUD and every registered widget.
+/// Should fire on enter into AppState::InGame
+pub fn draw_hud(
+    widgets: Res<HudWidgets>,
+    visible: Res<HudVisible>,
+    vars: Res<UiVars>,
+    mut commands: Commands,
+) {
+    commands
+        .spawn((
+            HudRoot,
+            DespawnOnExit(AppState::InGame),
+            Node {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                ..default()
+            },
+            hud_visibility(visible.0),
+            // the HUD shouldn't block clicks meant for the chat or menus.
+            Pickable::IGNORE,
+        ))
+        .with_children(|root| {
+            for slot in HudSlot::ALL {
+                root.spawn((slot, slot.node(), Pickable::IGNORE))
+                    .with_children(|parent| {
+                        for (_, draw) in widgets.0.iter().filter(|(s, _)| *s == slot) {
+                            draw(parent, &vars);
+                        }
+                    });
+            }
+        });
+}
+
+/// Draw the HUD again with the current palette and text size, if it is drawn.
+pub fn redraw_hud(
+    roots: Query<Entity, With<HudRoot>>,
+    widgets: Res<HudWidgets>,
+    visible: Res<HudVisible>,
+    vars: Res<UiVars>,
+    mut commands: Commands,
+) {
+    if roots.is_empty() {
+        return;
+    }
+
+    for root in &roots {
+        commands.entity(root).despawn();
+    }
+    draw_hud(widgets, visible, vars, commands);
+}
+
+/// Show or hide the HUD when the "toggle-hud" action fires.
+pub fn handle_toggle_hud(
+    app_state: Res<State<AppState>>,
+    mut visible: ResMut<HudVisible>,
+    mut root: Query<&mut Visibility, With<HudRoot>>,
+) {
+    if *app_state != AppState::InGame {
+        return;
+    }
+
+    visible.0 = !visible.0;
+    for mut visibility in &mut root {
+        *visibility = hud_visibility(visible.0);
+    }
+}
+
+fn hud_visibility(visible: bool) -> Visibility {
+    if visible {
+        Visibility::Inherited
+    } else {
+        Visibility::Hidden
+    }
+}
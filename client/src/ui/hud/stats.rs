@@ -0,0 +1,115 @@
+//! Health and hunger bars, showing the stats the server replicates to this player.
+
+use bevy::prelude::*;
+use data::registry::Registry;
+use protocol::types::PlayerStatsUpdate;
+
+use crate::{
+    net::channel::Channel,
+    ui::{UiColors, UiVars},
+};
+
+const BAR_WIDTH: f32 = 180.0;
+const BAR_HEIGHT: f32 = 8.0;
+
+/// The stats of this player, as last sent by the server.
+#[derive(Resource, Default)]
+pub struct PlayerStats(pub PlayerStatsUpdate);
+
+/// A stat shown as a bar in the HUD.
+#[derive(Component, Copy, Clone, Debug)]
+pub enum StatBar {
+    Health,
+    Hunger,
+}
+
+impl StatBar {
+    /// Fraction of the bar that is filled, from 0 to 1.
+    fn fill(self, stats: &PlayerStatsUpdate) -> f32 {
+        let (value, max) = match self {
+            Self::Health => (stats.health, stats.max_health),
+            Self::Hunger => (stats.hunger, stats.max_hunger),
+        };
+
+        if max == 0 {
+            0.0
+        } else {
+            (value as f32 / max as f32).clamp(0.0, 1.0)
+        }
+    }
+
+    fn color(self, colors: &UiColors) -> Color {
+        match self {
+            Self::Health => colors.health,
+            Self::Hunger => colors.hunger,
+        }
+    }
+}
+
+/// The filled part of a stat bar.
+#[derive(Component)]
+pub struct StatBarFill;
+
+pub fn draw_health(parent: &mut ChildSpawnerCommands, vars: &UiVars) {
+    draw_bar(parent, StatBar::Health, vars);
+}
+
+pub fn draw_hunger(parent: &mut ChildSpawnerCommands, vars: &UiVars) {
+    draw_bar(parent, StatBar::Hunger, vars);
+}
+
+fn draw_bar(parent: &mut ChildSpawnerCommands, bar: StatBar, vars: &UiVars) {
+    parent
+        .spawn((
+            bar,
+            Node {
+                width: Val::Px(BAR_WIDTH),
+                height: Val::Px(BAR_HEIGHT),
+                border: UiRect::all(Val::Px(1.0)),
+                ..default()
+            },
+            BorderColor::all(Color::srgba(0.0, 0.0, 0.0, 0.8)),
+            BackgroundColor(vars.colors.panel),
+            Pickable::IGNORE,
+        ))
+        .with_child((
+            StatBarFill,
+            Node {
+                width: Val::Percent(0.0),
+                height: Val::Percent(100.0),
+                ..default()
+            },
+            BackgroundColor(bar.color(&vars.colors)),
+            Pickable::IGNORE,
+        ));
+}
+
+/// Update the stats of this player when the server sends them.
+pub fn recv_player_stats(channels: Res<Registry<Channel>>, mut stats: ResMut<PlayerStats>) {
+    let channel = channels.get_by_name("player-stats").unwrap();
+    for packet in channel.recv() {
+        if let Some(update) = packet.cast::<PlayerStatsUpdate>() {
+            stats.0 = update;
+        }
+    }
+}
+
+/// Resize the filled part of every bar to its stat.
+pub fn update_stat_bars(
+    stats: Res<PlayerStats>,
+    bars: Query<(&StatBar, &Children)>,
+    mut fills: Query<&mut Node, With<StatBarFill>>,
+) {
+    for (bar, children) in &bars {
+        for child in children {
+            if let Ok(mut node) = fills.get_mut(*child) {
+                node.width = Val::Percent(bar.fill(&stats.0) * 100.0);
+            }
+        }
+    }
+}
+
+/// Forget the stats of the previous game.
+pub fn reset_player_stats(mut stats: ResMut<PlayerStats>) {
+    stats.0 = PlayerStatsUpdate::default();
+}
@@ -1,9 +1,17 @@
 use bevy::prelude::*;
-use data::locale::Locale;
+use data::{locale::Locale, sequence::Sequence};
+use protocol::exit::ExitStatus;
 
-use crate::ui::{
-    UiVars,
-    menus::{Menu, MenuBody, MenuRoot},
+use crate::{
+    net::Client,
+    sequences::connect::{ConnectSeq, ConnectSeqInfo},
+    singleplayer::Singleplayer,
+    states::AppState,
+    ui::{
+        UiVars,
+        button::{ButtonAction, ButtonClicked, ButtonVisuals},
+        menus::{Menu, MenuBody, MenuRoot},
+    },
 };
 
 /// Text to show whilst connecting to a server..
@@ -26,6 +34,10 @@ pub struct ConnectingText {
     pub cancel_to: Menu,
 }
 
+/// Attached to the button that stops connecting.
+#[derive(Component)]
+pub struct CancelConnectButton;
+
 #[rustfmt::skip]
 pub fn draw(
     mut commands: Commands,
@@ -56,6 +68,47 @@ pub fn draw(
                     ..default()
                 }
             ));
+
+            parent.spawn((
+                CancelConnectButton,
+                ButtonAction::None,
+                ButtonVisuals::text(locale.get("ui.common.cancel"), Val::Percent(80.0)).bundle(&vars),
+            ));
         });
     });
 }
+
+/// Stop the connect sequence and return to the menu the connection was started from.
+pub fn handle_cancel_clicks(
+    mut clicks: MessageReader<ButtonClicked>,
+    buttons: Query<(), With<CancelConnectButton>>,
+    client: Option<ResMut<Client>>,
+    singleplayer: Option<Res<Singleplayer>>,
+    seq: Res<Sequence<ConnectSeq>>,
+    mut next_seq: ResMut<NextState<ConnectSeq>>,
+    mut next_app_state: ResMut<NextState<AppState>>,
+    mut next_menu: ResMut<NextState<Menu>>,
+    mut commands: Commands,
+) {
+    if !clicks.read().any(|click| buttons.contains(click.entity)) {
+        return;
+    }
+
+    info!("Connection cancelled.");
+    if let Some(mut client) = client {
+        client.disconnect(Some(ExitStatus::Disconnected.into()));
+    }
+
+    next_menu.set(if singleplayer.is_some() {
+        Menu::WorldSelect
+    } else {
+        Menu::Title
+    });
+
+    commands.remove_resource::<Client>();
+    commands.remove_resource::<Singleplayer>();
+    commands.remove_resource::<ConnectSeqInfo>();
+    seq.reset();
+    next_seq.set(ConnectSeq::Inactive);
+    next_app_state.set(AppState::InMenus);
+}
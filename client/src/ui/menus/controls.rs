@@ -0,0 +1,189 @@
+use bevy::prelude::*;
+use data::locale::Locale;
+
+use crate::{
+    input::{Actions, Button},
+    ui::{
+        UiVars,
+        button::{ButtonAction, ButtonClicked, ButtonVisuals},
+        menus::{Menu, MenuBody, MenuRoot},
+    },
+};
+
+/// Attached to a button that rebinds the action with this name when clicked.
+#[derive(Component)]
+pub struct BindingEntry(pub String);
+
+/// Attached to the button that restores the default bindings.
+#[derive(Component)]
+pub struct ResetBindings;
+
+/// The action waiting for a button to be pressed, if any.
+#[derive(Resource, Default)]
+pub struct RebindCapture {
+    action: Option<String>,
+}
+
+impl RebindCapture {
+    pub fn is_capturing(&self) -> bool {
+        self.action.is_some()
+    }
+
+    pub fn clear(mut capture: ResMut<Self>) {
+        capture.action = None;
+    }
+}
+
+/// Text of a binding entry, listing the buttons bound to the action.
+fn entry_text(label: &str, actions: &Actions, capture: &RebindCapture, locale: &Locale) -> String {
+    let name = locale.get(format!("action.{label}"));
+    if capture.action.as_deref() == Some(label) {
+        return format!("{name}: {}", locale.get("ui.controls.press-button"));
+    }
+
+    let buttons = actions
+        .buttons(label)
+        .iter()
+        .map(Button::name)
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!("{name}: {buttons}")
+}
+
+/// Draw the controls menu.
+/// Should fire on enter into Menu::Controls
+#[rustfmt::skip]
+pub fn draw(
+    actions: Res<Actions>,
+    capture: Res<RebindCapture>,
+    locale: Res<Locale>,
+    vars: Res<UiVars>,
+    mut commands: Commands,
+) {
+    commands.spawn(MenuRoot::bundle(Menu::Controls)).with_children(|parent| {
+        parent.spawn(MenuBody::bundle(&vars)).with_children(|parent| {
+            parent.spawn((
+                Text::new(locale.get("ui.common.controls")),
+                TextLayout::new_with_justify(Justify::Center),
+                TextFont {
+                    font_size: 40.0,
+                    ..default()
+                },
+                Node::default(),
+            ));
+
+            parent.spawn((
+                // Container for binding entries.
+                Node {
+                    width: Val::Percent(80.0),
+                    height: Val::Percent(100.0),
+                    display: Display::Flex,
+                    flex_direction: FlexDirection::Column,
+                    justify_content: JustifyContent::Start,
+                    overflow: Overflow::scroll_y(),
+                    margin: UiRect::horizontal(Val::Auto),
+                    ..default()
+                },
+            )).with_children(|parent| {
+                for label in actions.labels() {
+                    let text = entry_text(label, &actions, &capture, &locale);
+                    parent.spawn((
+                        BindingEntry(label.to_string()),
+                        ButtonAction::None,
+                        ButtonVisuals::text(text, Val::Percent(100.0)).bundle(&vars),
+                    ));
+                }
+
+                // Restore every default binding.
+                parent.spawn((
+                    ResetBindings,
+                    ButtonAction::None,
+                    ButtonVisuals::text(locale.get("ui.controls.reset"), Val::Percent(100.0)).bundle(&vars),
+                ));
+
+                // Back to the options menu.
+                parent.spawn((
+                    ButtonAction::Transition(Menu::Options),
+                    ButtonVisuals::text(locale.get("ui.common.back"), Val::Percent(100.0)).bundle(&vars),
+                ));
+            });
+        });
+    });
+}
+
+/// Start capturing a button when an entry is clicked, or reset every binding.
+pub fn handle_binding_clicks(
+    mut clicks: MessageReader<ButtonClicked>,
+    entries: Query<&BindingEntry>,
+    resets: Query<(), With<ResetBindings>>,
+    mut capture: ResMut<RebindCapture>,
+    mut actions: ResMut<Actions>,
+) {
+    for click in clicks.read() {
+        if let Ok(entry) = entries.get(click.entity) {
+            capture.action = Some(entry.0.clone());
+        } else if resets.contains(click.entity) {
+            actions.reset_all();
+            capture.action = None;
+        }
+    }
+}
+
+/// Bind the next pressed key, mouse button or gamepad button to the action being captured,
+/// in place of the buttons of the same device. Escape cancels the capture.
+pub fn capture_rebind(
+    keys: Res<ButtonInput<KeyCode>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    gamepads: Query<&Gamepad>,
+    mut capture: ResMut<RebindCapture>,
+    mut actions: ResMut<Actions>,
+) {
+    let Some(label) = capture.action.clone() else {
+        return;
+    };
+
+    if keys.just_pressed(KeyCode::Escape) {
+        capture.action = None;
+        return;
+    }
+
+    let pressed = keys
+        .get_just_pressed()
+        .next()
+        .map(|code| Button::Key(*code))
+        .or_else(|| mouse.get_just_pressed().next().map(|b| Button::Mouse(*b)))
+        .or_else(|| {
+            gamepads
+                .iter()
+                .find_map(|pad| pad.get_just_pressed().next())
+                .map(|b| Button::Gamepad(*b))
+        });
+
+    if let Some(button) = pressed {
+        info!("Bound action '{label}' to '{}'", button.name());
+        actions.rebind(&label, button);
+        capture.action = None;
+    }
+}
+
+/// Keep the text of binding entries up to date, and mark conflicting bindings in red.
+pub fn update_binding_entries(
+    actions: Res<Actions>,
+    capture: Res<RebindCapture>,
+    locale: Res<Locale>,
+    vars: Res<UiVars>,
+    mut entries: Query<(&BindingEntry, &mut Text, &mut TextColor)>,
+) {
+    for (entry, mut text, mut color) in &mut entries {
+        let new = entry_text(&entry.0, &actions, &capture, &locale);
+        if text.0 != new {
+            text.0 = new;
+        }
+
+        color.0 = if actions.has_conflict(&entry.0) {
+            vars.colors.warning
+        } else {
+            vars.colors.text
+        };
+    }
+}
@@ -0,0 +1,202 @@
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+use data::{
+    locale::Locale,
+    sequence::{Sequence, SequenceFailed},
+};
+use protocol::exit::{ExitCode, ExitStatus};
+
+use crate::{
+    events::Disconnected,
+    net::Client,
+    sequences::connect::{ConnectSeq, ConnectSeqInfo, SYNC_ERR_CODE, TIMEOUT_ERR_CODE},
+    singleplayer::{LaunchSingleplayer, Singleplayer},
+    states::AppState,
+    ui::{
+        UiVars,
+        button::{ButtonAction, ButtonClicked, ButtonVisuals},
+        menus::{Menu, MenuBody, MenuRoot},
+    },
+};
+
+/// Why the client was disconnected, and how to reconnect.
+#[derive(Resource)]
+pub struct DisconnectInfo {
+    pub exit: ExitCode,
+    pub reconnect: Option<Reconnect>,
+}
+
+/// What the Reconnect button connects to.
+#[derive(Clone)]
+pub enum Reconnect {
+    /// A server at this address.
+    Address(String),
+
+    /// The singleplayer world in this directory.
+    World(PathBuf),
+}
+
+/// Attached to the button that reconnects to the server that was left.
+#[derive(Component)]
+pub struct ReconnectButton;
+
+/// Turn failures of the connect sequence into disconnections,
+/// so they are shown in the same menu.
+pub fn forward_connect_failures(
+    mut failed: MessageReader<SequenceFailed<ConnectSeq>>,
+    mut disconnect_msgs: MessageWriter<Disconnected>,
+) {
+    for msg in failed.read() {
+        let status = match msg.error.err_code {
+            TIMEOUT_ERR_CODE => ExitStatus::TimedOut,
+            SYNC_ERR_CODE => ExitStatus::ProtocolViolation,
+            _ => ExitStatus::NetworkError,
+        };
+        let exit = (status, msg.error.err_code, &msg.error.err_text).into();
+        disconnect_msgs.write(Disconnected { exit });
+    }
+}
+
+/// Close the connection and show the disconnected menu.
+pub fn handle_disconnect(
+    mut msgs: MessageReader<Disconnected>,
+    singleplayer: Option<Res<Singleplayer>>,
+    connect_info: Option<Res<ConnectSeqInfo>>,
+    seq: Res<Sequence<ConnectSeq>>,
+    mut next_app_state: ResMut<NextState<AppState>>,
+    mut next_menu: ResMut<NextState<Menu>>,
+    mut next_seq: ResMut<NextState<ConnectSeq>>,
+    mut commands: Commands,
+) {
+    // only the first reason matters if more than one was sent.
+    let Some(msg) = msgs.read().next() else {
+        return;
+    };
+
+    let reconnect = match (singleplayer, connect_info) {
+        (Some(singleplayer), _) => Some(Reconnect::World(singleplayer.world_dir.clone())),
+        (None, Some(info)) => Some(Reconnect::Address(info.addr_string.clone())),
+        (None, None) => None,
+    };
+
+    info!("Disconnected with exit: '{}'", msg.exit);
+    commands.insert_resource(DisconnectInfo {
+        exit: msg.exit.clone(),
+        reconnect,
+    });
+
+    commands.remove_resource::<Client>();
+    commands.remove_resource::<Singleplayer>();
+    commands.remove_resource::<ConnectSeqInfo>();
+    // the connection may have dropped part way through the connect sequence.
+    seq.reset();
+    next_seq.set(ConnectSeq::Inactive);
+    next_app_state.set(AppState::InMenus);
+    next_menu.set(Menu::Disconnected);
+}
+
+/// Draw the disconnected menu.
+/// Should fire on enter into Menu::Disconnected
+#[rustfmt::skip]
+pub fn draw(
+    info: Option<Res<DisconnectInfo>>,
+    locale: Res<Locale>,
+    vars: Res<UiVars>,
+    mut commands: Commands,
+) {
+    let exit = info.as_ref().map(|info| info.exit.clone()).unwrap_or_default();
+    let can_reconnect = info.is_some_and(|info| info.reconnect.is_some());
+
+    commands.spawn(MenuRoot::bundle(Menu::Disconnected)).with_children(|parent| {
+        parent.spawn(MenuBody::bundle(&vars)).with_children(|parent| {
+            parent.spawn((
+                Text::new(locale.get("ui.disconnected.title")),
+                TextLayout::new_with_justify(Justify::Center),
+                TextFont {
+                    font_size: 40.0,
+                    ..default()
+                },
+                Node::default(),
+            ));
+
+            parent.spawn((
+                // Container for the reason and buttons.
+                Node {
+                    width: Val::Percent(80.0),
+                    height: Val::Percent(100.0),
+                    display: Display::Flex,
+                    flex_direction: FlexDirection::Column,
+                    justify_content: JustifyContent::Start,
+                    margin: UiRect::horizontal(Val::Auto),
+                    ..default()
+                },
+            )).with_children(|parent| {
+                parent.spawn((
+                    Text::new(exit.status.to_string()),
+                    TextLayout::new_with_justify(Justify::Center),
+                    TextFont {
+                        font: vars.font(),
+                        font_size: 24.0,
+                        ..default()
+                    },
+                    Node::default(),
+                ));
+
+                for detail in [&exit.short, &exit.long].into_iter().flatten() {
+                    parent.spawn((
+                        Text::new(detail.clone()),
+                        TextLayout::new_with_justify(Justify::Center),
+                        TextFont {
+                            font: vars.font(),
+                            font_size: 16.0,
+                            ..default()
+                        },
+                        Node::default(),
+                    ));
+                }
+
+                if can_reconnect {
+                    parent.spawn((
+                        ReconnectButton,
+                        ButtonAction::None,
+                        ButtonVisuals::text(locale.get("ui.disconnected.reconnect"), Val::Percent(100.0)).bundle(&vars),
+                    ));
+                }
+
+                // Back to the title menu.
+                parent.spawn((
+                    ButtonAction::Transition(Menu::Title),
+                    ButtonVisuals::text(locale.get("ui.disconnected.back-to-title"), Val::Percent(100.0)).bundle(&vars),
+                ));
+            });
+        });
+    });
+}
+
+/// Connect again to the server or world that was left.
+pub fn handle_reconnect_clicks(
+    mut clicks: MessageReader<ButtonClicked>,
+    buttons: Query<(), With<ReconnectButton>>,
+    info: Option<Res<DisconnectInfo>>,
+    mut next_menu: ResMut<NextState<Menu>>,
+    mut launch: MessageWriter<LaunchSingleplayer>,
+    mut commands: Commands,
+) {
+    for click in clicks.read() {
+        if !buttons.contains(click.entity) {
+            continue;
+        }
+
+        match info.as_ref().and_then(|info| info.reconnect.clone()) {
+            Some(Reconnect::Address(addr_string)) => {
+                commands.insert_resource(ConnectSeqInfo { addr_string });
+                next_menu.set(Menu::Connecting);
+            }
+            Some(Reconnect::World(world_dir)) => {
+                launch.write(LaunchSingleplayer { world_dir });
+            }
+            None => {}
+        }
+    }
+}
@@ -0,0 +1,238 @@
+//! Loading screen shown after joining, until the terrain around the player is ready.
+//!
+//! The client is already InGame while this is shown, so chunks are received and meshed
+//! as usual. Focus isn't given to the player until loading finishes.
+
+use bevy::prelude::*;
+use data::locale::Locale;
+use world::{World, region::chunk::flags::ChunkState};
+
+use crate::{
+    focus::Focus,
+    player::PlayerBody,
+    render::chunk::ChunkRenderQueue,
+    ui::{
+        UiVars,
+        menus::{Menu, MenuBody, MenuRoot},
+    },
+};
+
+/// Radius (in chunks) around the player that must be loaded before playing.
+const LOADING_RADIUS: i32 = 3;
+
+/// Give up waiting after this many seconds, so a server that never
+/// sends some chunks doesn't leave the player on the loading screen.
+const LOADING_TIMEOUT: f32 = 30.0;
+
+/// Progress towards leaving the loading screen.
+#[derive(Resource, Default)]
+pub struct LoadingProgress {
+    /// Chunks within `LOADING_RADIUS` that are loaded.
+    pub chunks_loaded: usize,
+
+    /// Chunks within `LOADING_RADIUS`.
+    pub chunks_expected: usize,
+
+    /// Subchunks waiting to be meshed.
+    pub meshes_pending: usize,
+
+    /// Time (elapsed seconds) loading started.
+    pub started: f32,
+}
+
+impl LoadingProgress {
+    pub fn is_finished(&self) -> bool {
+        self.chunks_expected != 0
+            && self.chunks_loaded == self.chunks_expected
+            && self.meshes_pending == 0
+    }
+
+    /// Fraction of the work that is done, from 0 to 1.
+    /// Meshing counts for as much as receiving chunks.
+    pub fn fraction(&self) -> f32 {
+        if self.chunks_expected == 0 {
+            return 0.0;
+        }
+
+        let chunks = self.chunks_loaded as f32 / self.chunks_expected as f32;
+        let meshes = if chunks < 1.0 {
+            0.0
+        } else {
+            1.0 / (1.0 + self.meshes_pending as f32 / 32.0)
+        };
+        (chunks + meshes) * 0.5
+    }
+}
+
+/// A line of text on the loading screen.
+#[derive(Component, Copy, Clone)]
+pub enum LoadingLine {
+    Registries,
+    Chunks,
+    Meshes,
+    Total,
+}
+
+impl LoadingLine {
+    fn text(self, progress: &LoadingProgress, locale: &Locale) -> String {
+        match self {
+            // the connect sequence doesn't finish until registries are synced.
+            Self::Registries => format!(
+                "{}: {}",
+                locale.get("ui.loading.registries"),
+                locale.get("ui.loading.done")
+            ),
+            Self::Chunks => format!(
+                "{}: {} / {}",
+                locale.get("ui.loading.chunks"),
+                progress.chunks_loaded,
+                progress.chunks_expected
+            ),
+            Self::Meshes => format!(
+                "{}: {}",
+                locale.get("ui.loading.meshes"),
+                progress.meshes_pending
+            ),
+            Self::Total => format!("{:.0}%", progress.fraction() * 100.0),
+        }
+    }
+}
+
+/// Show the loading screen after joining.
+/// Should fire on enter into AppState::InGame
+pub fn begin_loading(
+    time: Res<Time>,
+    mut next_menu: ResMut<NextState<Menu>>,
+    mut commands: Commands,
+) {
+    commands.insert_resource(LoadingProgress {
+        started: time.elapsed_secs(),
+        ..default()
+    });
+    next_menu.set(Menu::Loading);
+}
+
+/// Draw the loading screen.
+/// Should fire on enter into Menu::Loading
+#[rustfmt::skip]
+pub fn draw(
+    mut commands: Commands,
+    locale: Res<Locale>,
+    vars: Res<UiVars>,
+) {
+    commands.spawn((
+        MenuRoot::bundle(Menu::Loading),
+        // hide the world while it pops in.
+        BackgroundColor(Color::srgb(0.08, 0.08, 0.1)),
+        GlobalZIndex(10),
+    )).with_children(|parent| {
+        parent.spawn(MenuBody::bundle(&vars)).with_children(|parent| {
+            parent.spawn((
+                Text::new(locale.get("ui.loading.title")),
+                TextLayout::new_with_justify(Justify::Center),
+                TextFont {
+                    font_size: 40.0,
+                    ..default()
+                },
+                Node {
+                    width: Val::Percent(100.0),
+                    ..default()
+                },
+            ));
+
+            parent.spawn((
+                // Container for progress lines.
+                Node {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    display: Display::Flex,
+                    flex_direction: FlexDirection::Column,
+                    justify_content: JustifyContent::Start,
+                    ..default()
+                },
+            )).with_children(|parent| {
+                for line in [LoadingLine::Total, LoadingLine::Registries, LoadingLine::Chunks, LoadingLine::Meshes] {
+                    parent.spawn((
+                        line,
+                        Text::new(""),
+                        TextLayout::new_with_justify(Justify::Center),
+                        TextFont {
+                            font: vars.font(),
+                            font_size: 20.0,
+                            ..default()
+                        },
+                        Node {
+                            width: Val::Percent(100.0),
+                            margin: UiRect::vertical(Val::Px(10.0)),
+                            ..default()
+                        },
+                    ));
+                }
+            });
+        });
+    });
+}
+
+/// Count the chunks around the player that are loaded, and the subchunks left to mesh.
+pub fn update_loading_progress(
+    world: Res<World>,
+    queue: Res<ChunkRenderQueue>,
+    player: Single<&Transform, With<PlayerBody>>,
+    locale: Res<Locale>,
+    mut progress: ResMut<LoadingProgress>,
+    mut lines: Query<(&LoadingLine, &mut Text)>,
+) {
+    let center = player.translation.as_ivec3().xz() >> 5;
+    let mut loaded = 0;
+    let mut expected = 0;
+    for x in -LOADING_RADIUS..=LOADING_RADIUS {
+        for z in -LOADING_RADIUS..=LOADING_RADIUS {
+            if x * x + z * z > LOADING_RADIUS * LOADING_RADIUS {
+                continue;
+            }
+
+            expected += 1;
+            let origin = (center + IVec2::new(x, z)) << 5;
+            if world
+                .get_chunk(origin)
+                .is_some_and(|chunk| chunk.load_state() == ChunkState::Loaded)
+            {
+                loaded += 1;
+            }
+        }
+    }
+
+    progress.chunks_loaded = loaded;
+    progress.chunks_expected = expected;
+    progress.meshes_pending = queue.len();
+
+    for (line, mut text) in &mut lines {
+        text.0 = line.text(&progress, &locale);
+    }
+}
+
+/// Leave the loading screen and give focus to the player once
+/// the terrain is ready, or loading has taken too long.
+pub fn finish_loading(
+    time: Res<Time>,
+    progress: Res<LoadingProgress>,
+    mut next_menu: ResMut<NextState<Menu>>,
+    mut focus: Focus,
+) {
+    let timed_out = time.elapsed_secs() - progress.started > LOADING_TIMEOUT;
+    if timed_out {
+        warn!(
+            "[C180] Gave up waiting for terrain after {LOADING_TIMEOUT}s with {} / {} chunks loaded.",
+            progress.chunks_loaded, progress.chunks_expected
+        );
+    }
+
+    if progress.is_finished() || timed_out {
+        info!(
+            "Finished loading in {:.2}s.",
+            time.elapsed_secs() - progress.started
+        );
+        next_menu.set(Menu::None);
+        focus.to_player();
+    }
+}
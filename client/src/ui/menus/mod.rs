@@ -3,6 +3,12 @@ use bevy::prelude::*;
 use crate::ui::UiVars;
 
 pub mod connecting;
+pub mod controls;
+pub mod disconnected;
+pub mod loading;
+pub mod options;
+pub mod packs;
+pub mod palette;
 pub mod pause;
 pub mod server_select;
 pub mod starting;
@@ -26,15 +32,36 @@ pub enum Menu {
     /// Select world to join. (Singleplayer)
     WorldSelect,
 
+    /// Name, seed and height of a new world.
+    CreateWorld,
+
+    /// Rename or delete a world.
+    EditWorld,
+
     /// Select server to join. (Multiplayer)
     ServerSelect,
 
     /// Connecting to Server
     Connecting,
 
+    /// The connection was lost or couldn't be established.
+    Disconnected,
+
+    /// Waiting for the terrain around the player after joining.
+    Loading,
+
     /// Settings menu
     Options,
 
+    /// Rebind the buttons of actions.
+    Controls,
+
+    /// Enable resource packs and change their order.
+    ResourcePacks,
+
+    /// In-game list of blocks to pick the held block from.
+    BlockPalette,
+
     /// No menu currently displayed.
     None,
 }
@@ -82,3 +109,12 @@ impl MenuBody {
         )
     }
 }
+
+/// Draw the current menu again, so its text is in the language that was just loaded.
+/// Menus that start something when entered are left as they are.
+pub fn redraw_menu(menu: Res<State<Menu>>, mut next: ResMut<NextState<Menu>>) {
+    match menu.get() {
+        Menu::Starting | Menu::Connecting | Menu::Loading | Menu::None => {}
+        menu => next.set(*menu),
+    }
+}
@@ -0,0 +1,289 @@
+use bevy::{
+    prelude::*,
+    window::{MonitorSelection, VideoModeSelection, WindowMode},
+};
+use data::locale::Locale;
+
+use crate::{
+    render::chunk::MesherBudget,
+    settings::Settings,
+    states::AppState,
+    ui::{
+        UiPalette, UiVars,
+        button::{ButtonAction, ButtonClicked, ButtonVisuals},
+        menus::{Menu, MenuBody, MenuRoot},
+    },
+};
+
+/// Attached to a button that changes a setting when clicked.
+#[derive(Component, Copy, Clone, Debug)]
+pub enum OptionEntry {
+    Language,
+    RenderDistance,
+    Fov,
+    Vsync,
+    Window,
+    MeshWorkers,
+    MeshesPerFrame,
+    MeshUploadsPerFrame,
+    Volume,
+    BlockVolume,
+    FootstepVolume,
+    AmbientVolume,
+    LabelOcclusion,
+    UiScale,
+    Palette,
+    ShowSeed,
+}
+
+impl OptionEntry {
+    pub const ALL: [Self; 16] = [
+        Self::Language,
+        Self::RenderDistance,
+        Self::Fov,
+        Self::Vsync,
+        Self::Window,
+        Self::MeshWorkers,
+        Self::MeshesPerFrame,
+        Self::MeshUploadsPerFrame,
+        Self::Volume,
+        Self::BlockVolume,
+        Self::FootstepVolume,
+        Self::AmbientVolume,
+        Self::LabelOcclusion,
+        Self::UiScale,
+        Self::Palette,
+        Self::ShowSeed,
+    ];
+
+    /// Change the setting to its next value, wrapping around at the end of the range.
+    pub fn cycle(self, settings: &mut Settings, locale: &Locale) {
+        match self {
+            Self::Language => {
+                let languages = locale.languages();
+                let next = languages
+                    .iter()
+                    .position(|lang| *lang == settings.language)
+                    .map_or(0, |i| (i + 1) % languages.len());
+                if let Some(lang) = languages.get(next) {
+                    settings.language = lang.clone();
+                }
+            }
+            Self::RenderDistance => {
+                settings.render_distance = cycle_step(settings.render_distance, 4, 4, 32);
+            }
+            Self::Fov => {
+                settings.fov = cycle_step(settings.fov as u32, 10, 50, 110) as f32;
+            }
+            Self::Vsync => settings.vsync = !settings.vsync,
+            // windowed, then borderless, then exclusive fullscreen.
+            Self::Window => {
+                settings.window_mode = match settings.window_mode {
+                    WindowMode::Windowed => {
+                        WindowMode::BorderlessFullscreen(MonitorSelection::Current)
+                    }
+                    WindowMode::BorderlessFullscreen(_) => WindowMode::Fullscreen(
+                        MonitorSelection::Current,
+                        VideoModeSelection::Current,
+                    ),
+                    WindowMode::Fullscreen(..) => WindowMode::Windowed,
+                };
+            }
+            // 0 is "Auto", before the smallest value.
+            Self::MeshWorkers => {
+                settings.mesh_workers = cycle_step(settings.mesh_workers, 1, 0, 16);
+            }
+            Self::MeshesPerFrame => {
+                settings.meshes_per_frame = cycle_step(settings.meshes_per_frame, 8, 0, 128);
+            }
+            Self::MeshUploadsPerFrame => {
+                settings.mesh_uploads_per_frame =
+                    cycle_step(settings.mesh_uploads_per_frame, 8, 0, 128);
+            }
+            Self::Volume => cycle_volume(&mut settings.volume),
+            Self::BlockVolume => cycle_volume(&mut settings.block_volume),
+            Self::FootstepVolume => cycle_volume(&mut settings.footstep_volume),
+            Self::AmbientVolume => cycle_volume(&mut settings.ambient_volume),
+            Self::LabelOcclusion => settings.occlude_labels = !settings.occlude_labels,
+            Self::UiScale => {
+                let percent = (settings.ui_scale * 100.0).round() as u32;
+                settings.ui_scale = cycle_step(percent, 25, 75, 200) as f32 / 100.0;
+            }
+            Self::Palette => {
+                let palettes = UiPalette::ALL;
+                let next = palettes
+                    .iter()
+                    .position(|palette| *palette == settings.ui_palette)
+                    .map_or(0, |i| (i + 1) % palettes.len());
+                settings.ui_palette = palettes[next];
+            }
+            Self::ShowSeed => settings.show_seed = !settings.show_seed,
+        }
+    }
+
+    /// Text of the button, with the current value.
+    pub fn text(self, settings: &Settings, locale: &Locale) -> String {
+        let toggle = |on: bool| locale.get(if on { "ui.common.on" } else { "ui.common.off" });
+        let percent = |volume: f32| format!("{}%", (volume * 100.0).round());
+        let auto = MesherBudget::auto();
+        let or_auto = |value: u32, auto: usize| match value {
+            0 => format!("{} ({auto})", locale.get("ui.common.auto")),
+            value => value.to_string(),
+        };
+        let (label, value) = match self {
+            Self::Language => ("ui.options.language", locale.get("language.name")),
+            Self::RenderDistance => (
+                "ui.options.render-distance",
+                settings.render_distance.to_string(),
+            ),
+            Self::Fov => ("ui.options.fov", settings.fov.round().to_string()),
+            Self::Vsync => ("ui.options.vsync", toggle(settings.vsync)),
+            Self::Window => {
+                let mode = match settings.window_mode {
+                    WindowMode::Windowed => "ui.options.window-mode.windowed",
+                    WindowMode::BorderlessFullscreen(_) => "ui.options.window-mode.borderless",
+                    WindowMode::Fullscreen(..) => "ui.options.window-mode.fullscreen",
+                };
+                ("ui.options.window-mode", locale.get(mode))
+            }
+            Self::MeshWorkers => (
+                "ui.options.mesh-workers",
+                or_auto(settings.mesh_workers, auto.workers),
+            ),
+            Self::MeshesPerFrame => (
+                "ui.options.meshes-per-frame",
+                or_auto(settings.meshes_per_frame, auto.meshes_per_frame),
+            ),
+            Self::MeshUploadsPerFrame => (
+                "ui.options.mesh-uploads-per-frame",
+                or_auto(settings.mesh_uploads_per_frame, auto.uploads_per_frame),
+            ),
+            Self::Volume => ("ui.options.volume", percent(settings.volume)),
+            Self::BlockVolume => ("ui.options.block-volume", percent(settings.block_volume)),
+            Self::FootstepVolume => (
+                "ui.options.footstep-volume",
+                percent(settings.footstep_volume),
+            ),
+            Self::AmbientVolume => ("ui.options.ambient-volume", percent(settings.ambient_volume)),
+            Self::LabelOcclusion => (
+                "ui.options.label-occlusion",
+                toggle(settings.occlude_labels),
+            ),
+            Self::UiScale => ("ui.options.ui-scale", percent(settings.ui_scale)),
+            Self::Palette => ("ui.options.palette", locale.get(settings.ui_palette.label())),
+            Self::ShowSeed => ("ui.options.show-seed", toggle(settings.show_seed)),
+        };
+        format!("{}: {value}", locale.get(label))
+    }
+}
+
+/// Step a volume up by 10%, wrapping back to 0% after 100%.
+fn cycle_volume(volume: &mut f32) {
+    let percent = (*volume * 100.0).round() as u32;
+    *volume = cycle_step(percent, 10, 0, 100) as f32 / 100.0;
+}
+
+/// Step `value` up by `step`, wrapping back to `min` once it passes `max`.
+fn cycle_step(value: u32, step: u32, min: u32, max: u32) -> u32 {
+    let next = value.saturating_add(step);
+    if next > max || value < min { min } else { next }
+}
+
+/// Draw the options menu.
+/// Should fire on enter into Menu::Options
+#[rustfmt::skip]
+pub fn draw(
+    settings: Res<Settings>,
+    app_state: Res<State<AppState>>,
+    locale: Res<Locale>,
+    vars: Res<UiVars>,
+    mut commands: Commands,
+) {
+    // options can be opened from the title menu or the pause menu.
+    let back = match app_state.get() {
+        AppState::InGame => Menu::Pause,
+        _ => Menu::Title,
+    };
+
+    commands.spawn(MenuRoot::bundle(Menu::Options)).with_children(|parent| {
+        parent.spawn(MenuBody::bundle(&vars)).with_children(|parent| {
+            parent.spawn((
+                Text::new(locale.get("ui.common.options")),
+                TextLayout::new_with_justify(Justify::Center),
+                TextFont {
+                    font_size: 40.0,
+                    ..default()
+                },
+                Node::default(),
+            ));
+
+            parent.spawn((
+                // Container for option buttons.
+                Node {
+                    width: Val::Percent(80.0),
+                    height: Val::Percent(100.0),
+                    display: Display::Flex,
+                    flex_direction: FlexDirection::Column,
+                    justify_content: JustifyContent::Start,
+                    overflow: Overflow::scroll_y(),
+                    margin: UiRect::horizontal(Val::Auto),
+                    ..default()
+                },
+            )).with_children(|parent| {
+                for entry in OptionEntry::ALL {
+                    parent.spawn((
+                        entry,
+                        ButtonAction::None,
+                        ButtonVisuals::text(entry.text(&settings, &locale), Val::Percent(100.0)).bundle(&vars),
+                    ));
+                }
+
+                // The server address can only be changed in settings.toml for now.
+                parent.spawn((
+                    Text::new(format!("{}: {}", locale.get("ui.options.server-address"), settings.server_address)),
+                    TextLayout::new_with_justify(Justify::Center),
+                    TextFont {
+                        font: vars.font(),
+                        font_size: 16.0,
+                        ..default()
+                    },
+                    Node::default(),
+                ));
+
+                // Transition to the controls menu.
+                parent.spawn((
+                    ButtonAction::Transition(Menu::Controls),
+                    ButtonVisuals::text(locale.get("ui.common.controls"), Val::Percent(100.0)).bundle(&vars),
+                ));
+
+                // Transition to the resource packs menu.
+                parent.spawn((
+                    ButtonAction::Transition(Menu::ResourcePacks),
+                    ButtonVisuals::text(locale.get("ui.options.resource-packs"), Val::Percent(100.0)).bundle(&vars),
+                ));
+
+                // Back to the menu options was opened from.
+                parent.spawn((
+                    ButtonAction::Transition(back),
+                    ButtonVisuals::text(locale.get("ui.common.back"), Val::Percent(100.0)).bundle(&vars),
+                ));
+            });
+        });
+    });
+}
+
+/// Change settings when their buttons are clicked, and update the button text.
+/// Changing the language redraws the menu once the new language is loaded.
+pub fn handle_option_clicks(
+    mut clicks: MessageReader<ButtonClicked>,
+    mut entries: Query<(&OptionEntry, &mut Text)>,
+    mut settings: ResMut<Settings>,
+    locale: Res<Locale>,
+) {
+    for click in clicks.read() {
+        if let Ok((entry, mut text)) = entries.get_mut(click.entity) {
+            entry.cycle(&mut settings, &locale);
+            text.0 = entry.text(&settings, &locale);
+        }
+    }
+}
@@ -0,0 +1,122 @@
+use bevy::prelude::*;
+use data::{fs::packs::discover_packs, info::RootPath, locale::Locale};
+
+use crate::{
+    packs::packs_dir,
+    settings::Settings,
+    ui::{
+        UiVars,
+        button::{ButtonAction, ButtonClicked, ButtonVisuals},
+        menus::{Menu, MenuBody, MenuRoot},
+    },
+};
+
+/// Attached to a button that enables or disables the resource pack with this name.
+#[derive(Component)]
+pub struct PackEntry(pub String);
+
+/// Text of a pack entry, with its place in the priority order if it's enabled.
+fn entry_text(name: &str, settings: &Settings, locale: &Locale) -> String {
+    match settings.resource_packs.iter().position(|pack| pack == name) {
+        Some(i) => format!("{name}: {} ({})", locale.get("ui.common.on"), i + 1),
+        None => format!("{name}: {}", locale.get("ui.common.off")),
+    }
+}
+
+/// Draw the resource pack menu, listing every pack in the packs directory.
+/// Should fire on enter into Menu::ResourcePacks
+#[rustfmt::skip]
+pub fn draw(
+    root: Res<RootPath>,
+    settings: Res<Settings>,
+    locale: Res<Locale>,
+    vars: Res<UiVars>,
+    mut commands: Commands,
+) {
+    let dir = packs_dir(&root);
+    let names = discover_packs(&dir);
+
+    commands.spawn(MenuRoot::bundle(Menu::ResourcePacks)).with_children(|parent| {
+        parent.spawn(MenuBody::bundle(&vars)).with_children(|parent| {
+            parent.spawn((
+                Text::new(locale.get("ui.packs.title")),
+                TextLayout::new_with_justify(Justify::Center),
+                TextFont {
+                    font_size: 40.0,
+                    ..default()
+                },
+                Node::default(),
+            ));
+
+            parent.spawn((
+                // Container for pack entries.
+                Node {
+                    width: Val::Percent(80.0),
+                    height: Val::Percent(100.0),
+                    display: Display::Flex,
+                    flex_direction: FlexDirection::Column,
+                    justify_content: JustifyContent::Start,
+                    overflow: Overflow::scroll_y(),
+                    margin: UiRect::horizontal(Val::Auto),
+                    ..default()
+                },
+            )).with_children(|parent| {
+                for name in names {
+                    let text = entry_text(&name, &settings, &locale);
+                    parent.spawn((
+                        PackEntry(name),
+                        ButtonAction::None,
+                        ButtonVisuals::text(text, Val::Percent(100.0)).bundle(&vars),
+                    ));
+                }
+
+                // Where to put packs, so they show up in the list.
+                parent.spawn((
+                    Text::new(format!("{}: {}", locale.get("ui.packs.hint"), dir.display())),
+                    TextLayout::new_with_justify(Justify::Center),
+                    TextFont {
+                        font: vars.font(),
+                        font_size: 16.0,
+                        ..default()
+                    },
+                    Node::default(),
+                ));
+
+                // Back to the options menu.
+                parent.spawn((
+                    ButtonAction::Transition(Menu::Options),
+                    ButtonVisuals::text(locale.get("ui.common.back"), Val::Percent(100.0)).bundle(&vars),
+                ));
+            });
+        });
+    });
+}
+
+/// Enable or disable packs when their entry is clicked.
+/// Newly enabled packs have the highest priority.
+pub fn handle_pack_clicks(
+    mut clicks: MessageReader<ButtonClicked>,
+    mut entries: Query<(&PackEntry, &mut Text)>,
+    mut settings: ResMut<Settings>,
+    locale: Res<Locale>,
+) {
+    for click in clicks.read() {
+        let Ok((entry, _)) = entries.get(click.entity) else {
+            continue;
+        };
+
+        let name = entry.0.clone();
+        let packs = &mut settings.resource_packs;
+        match packs.iter().position(|pack| *pack == name) {
+            Some(i) => {
+                packs.remove(i);
+            }
+            None => packs.insert(0, name),
+        }
+
+        // the priority of every other enabled pack may have changed.
+        for (entry, mut text) in &mut entries {
+            text.0 = entry_text(&entry.0, &settings, &locale);
+        }
+    }
+}
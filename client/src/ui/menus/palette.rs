@@ -0,0 +1,211 @@
+//! Menu listing every registered block state, to pick the block placed by the player.
+//!
+//! The entries are built from the block state registry when the menu opens, each with a
+//! swatch of the average color of its texture in the block atlas, so blocks added by
+//! packs are listed without being named anywhere. Typing into the search field hides
+//! the entries whose name doesn't contain the text.
+
+use bevy::{input::mouse::MouseWheel, prelude::*};
+use data::{blockstates::BlockState, locale::Locale, registry::Registry};
+use world::voxel::Voxel;
+
+use crate::{
+    focus::Focus,
+    player::interact::HeldItem,
+    render::atlases::{BlockTextureMeta, TextureArray},
+    states::AppState,
+    ui::{
+        UiVars,
+        button::{ButtonAction, ButtonClicked},
+        elements::{Selected, text_field::TextField},
+        menus::{Menu, MenuBody, MenuRoot},
+    },
+};
+
+/// Max number of characters in the search field.
+const MAX_SEARCH_LEN: usize = 32;
+
+/// Pixels scrolled per line of the mouse wheel.
+const SCROLL_SPEED: f32 = 40.0;
+
+/// Attached to a button that gives the player this block state.
+#[derive(Component)]
+pub struct PaletteEntry {
+    pub voxel: Voxel,
+
+    /// Name of the state in the registry, in lowercase.
+    name: String,
+}
+
+/// The field entries are searched with.
+#[derive(Component)]
+pub struct PaletteSearch;
+
+/// The scrolling container of the entries.
+#[derive(Component)]
+pub struct PaletteView;
+
+/// Open the palette when the "open-palette" action fires while the player has focus.
+pub fn handle_open_palette(
+    app_state: Res<State<AppState>>,
+    mut focus: Focus,
+    mut next_menu: ResMut<NextState<Menu>>,
+) {
+    if *app_state == AppState::InGame && focus.player_has_focus() {
+        next_menu.set(Menu::BlockPalette);
+        focus.none();
+    }
+}
+
+/// Draw the block palette, with an entry for every state that has a model.
+/// Should fire on enter into Menu::BlockPalette
+#[rustfmt::skip]
+pub fn draw(
+    states: Res<Registry<BlockState>>,
+    atlas: Res<TextureArray<BlockTextureMeta>>,
+    held: Res<HeldItem>,
+    locale: Res<Locale>,
+    vars: Res<UiVars>,
+    mut commands: Commands,
+) {
+    commands.spawn(MenuRoot::bundle(Menu::BlockPalette)).with_children(|parent| {
+        parent.spawn(MenuBody::bundle(&vars)).with_children(|parent| {
+            parent.spawn((
+                Text::new(locale.get("ui.palette.title")),
+                TextLayout::new_with_justify(Justify::Center),
+                TextFont {
+                    font_size: 40.0,
+                    ..default()
+                },
+                Node::default(),
+            ));
+
+            // selected right away, so typing searches without clicking it first.
+            parent.spawn((
+                PaletteSearch,
+                Selected,
+                TextField::new("", locale.get("ui.palette.search"), MAX_SEARCH_LEN).bundle(&vars),
+            ));
+
+            parent.spawn((
+                // Container for block entries.
+                PaletteView,
+                Node {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    display: Display::Flex,
+                    flex_direction: FlexDirection::Row,
+                    flex_wrap: FlexWrap::Wrap,
+                    align_content: AlignContent::Start,
+                    overflow: Overflow::scroll_y(),
+                    margin: UiRect::bottom(Val::Px(30.0)),
+                    ..default()
+                },
+                ScrollPosition::default(),
+            )).with_children(|parent| {
+                for entry in states.entries() {
+                    // air and other states without a model can't be placed.
+                    let Some(texture) = entry.icon_texture() else {
+                        continue;
+                    };
+
+                    let voxel = Voxel(entry.id.0 as u16);
+                    let [r, g, b, _] = atlas.average_color(texture as usize).unwrap_or([255; 4]);
+                    let background = if voxel == held.0 {
+                        vars.colors.button_selected
+                    } else {
+                        vars.colors.button
+                    };
+                    parent.spawn((
+                        PaletteEntry {
+                            voxel,
+                            name: entry.name.to_lowercase(),
+                        },
+                        ButtonAction::None,
+                        BackgroundColor(background),
+                        Node {
+                            width: Val::Px(280.0),
+                            display: Display::Flex,
+                            align_items: AlignItems::Center,
+                            column_gap: Val::Px(10.0),
+                            padding: UiRect::all(Val::Px(6.0)),
+                            margin: UiRect::all(Val::Px(4.0)),
+                            ..default()
+                        },
+                    )).with_children(|parent| {
+                        parent.spawn((
+                            BackgroundColor(Color::srgb_u8(r, g, b)),
+                            Node {
+                                width: Val::Px(24.0),
+                                height: Val::Px(24.0),
+                                flex_shrink: 0.0,
+                                ..default()
+                            },
+                        ));
+
+                        parent.spawn((
+                            Text::new(entry.name),
+                            TextFont {
+                                font: vars.font(),
+                                font_size: 16.0,
+                                ..default()
+                            },
+                        ));
+                    });
+                }
+            });
+        });
+    });
+}
+
+/// Hide the entries whose name doesn't contain the text of the search field.
+pub fn filter_palette_entries(
+    search: Single<&TextField, With<PaletteSearch>>,
+    mut entries: Query<(&PaletteEntry, &mut Node)>,
+    mut last: Local<Option<String>>,
+) {
+    let text = search.read().trim().to_lowercase();
+    if last.as_deref() == Some(text.as_str()) {
+        return;
+    }
+
+    for (entry, mut node) in &mut entries {
+        node.display = if entry.name.contains(&text) {
+            Display::Flex
+        } else {
+            Display::None
+        };
+    }
+
+    *last = Some(text);
+}
+
+/// Scroll the entries with the mouse wheel.
+pub fn scroll_palette(
+    mut wheel: MessageReader<MouseWheel>,
+    mut view: Single<&mut ScrollPosition, With<PaletteView>>,
+) {
+    for ev in wheel.read() {
+        view.y = (view.y - ev.y * SCROLL_SPEED).max(0.0);
+    }
+}
+
+/// Give the player the block of an entry when it is clicked, and return to the game.
+pub fn handle_palette_clicks(
+    mut clicks: MessageReader<ButtonClicked>,
+    entries: Query<&PaletteEntry>,
+    mut held: ResMut<HeldItem>,
+    mut focus: Focus,
+    mut next_menu: ResMut<NextState<Menu>>,
+) {
+    let Some(entry) = clicks
+        .read()
+        .find_map(|click| entries.get(click.entity).ok())
+    else {
+        return;
+    };
+
+    held.0 = entry.voxel;
+    next_menu.set(Menu::None);
+    focus.to_player();
+}
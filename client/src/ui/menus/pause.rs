@@ -1,25 +1,129 @@
 use bevy::prelude::*;
-use data::locale::Locale;
+use data::{locale::Locale, sequence::Sequence};
+use protocol::exit::ExitStatus;
 
-use crate::ui::{
-    UiVars,
-    button::{ButtonAction, ButtonVisuals},
-    menus::{Menu, MenuBody, MenuRoot},
+use crate::{
+    focus::Focus,
+    net::Client,
+    sequences::connect::{ConnectSeq, ConnectSeqInfo},
+    singleplayer::Singleplayer,
+    states::AppState,
+    ui::{
+        UiVars,
+        button::{ButtonAction, ButtonClicked, ButtonVisuals},
+        menus::{Menu, MenuBody, MenuRoot},
+    },
 };
 
+/// Attached to the buttons of the pause menu that aren't transitions.
+#[derive(Component, Copy, Clone, Debug)]
+pub enum PauseButton {
+    Resume,
+    Disconnect,
+}
+
+/// Draw the pause menu.
+/// Should fire on enter into Menu::Pause
 #[rustfmt::skip]
 pub fn draw(
     locale: Res<Locale>,
     vars: Res<UiVars>,
     mut commands: Commands,
 ) {
-    commands.spawn(MenuRoot::bundle(Menu::Pause))
-        .with_child(MenuBody::bundle(&vars))
-        .with_children(|parent| {
-            // Disconnect to title menu.
+    commands.spawn(MenuRoot::bundle(Menu::Pause)).with_children(|parent| {
+        parent.spawn(MenuBody::bundle(&vars)).with_children(|parent| {
             parent.spawn((
-                ButtonAction::Transition(Menu::Title),
-                ButtonVisuals::text(locale.get("ui.common.disconnect"),  Val::Percent(100.0)).bundle(&vars)
+                Text::new(locale.get("ui.pause.title")),
+                TextLayout::new_with_justify(Justify::Center),
+                TextFont {
+                    font_size: 40.0,
+                    ..default()
+                },
+                Node::default(),
             ));
+
+            parent.spawn((
+                // Container for buttons.
+                Node {
+                    width: Val::Percent(80.0),
+                    height: Val::Percent(100.0),
+                    display: Display::Flex,
+                    flex_direction: FlexDirection::Column,
+                    justify_content: JustifyContent::Start,
+                    margin: UiRect::horizontal(Val::Auto),
+                    ..default()
+                },
+            )).with_children(|parent| {
+                // Close the menu and return focus to the player.
+                parent.spawn((
+                    PauseButton::Resume,
+                    ButtonAction::None,
+                    ButtonVisuals::text(locale.get("ui.pause.resume"), Val::Percent(100.0)).bundle(&vars),
+                ));
+
+                // Transition to options menu.
+                parent.spawn((
+                    ButtonAction::Transition(Menu::Options),
+                    ButtonVisuals::text(locale.get("ui.common.options"), Val::Percent(100.0)).bundle(&vars),
+                ));
+
+                // Leave the game.
+                parent.spawn((
+                    PauseButton::Disconnect,
+                    ButtonAction::None,
+                    ButtonVisuals::text(locale.get("ui.common.disconnect"), Val::Percent(100.0)).bundle(&vars),
+                ));
+            });
         });
+    });
+}
+
+/// Resume or leave the game when the pause menu buttons are clicked.
+///
+/// Leaving closes the connection and returns to the menu the game was joined from,
+/// without showing the disconnected menu.
+pub fn handle_pause_clicks(
+    mut clicks: MessageReader<ButtonClicked>,
+    buttons: Query<&PauseButton>,
+    client: Option<ResMut<Client>>,
+    singleplayer: Option<Res<Singleplayer>>,
+    seq: Res<Sequence<ConnectSeq>>,
+    mut focus: Focus,
+    mut next_seq: ResMut<NextState<ConnectSeq>>,
+    mut next_app_state: ResMut<NextState<AppState>>,
+    mut next_menu: ResMut<NextState<Menu>>,
+    mut commands: Commands,
+) {
+    let Some(button) = clicks
+        .read()
+        .find_map(|click| buttons.get(click.entity).ok())
+    else {
+        return;
+    };
+
+    match button {
+        PauseButton::Resume => {
+            next_menu.set(Menu::None);
+            focus.to_player();
+        }
+        PauseButton::Disconnect => {
+            info!("Disconnecting from the pause menu.");
+            if let Some(mut client) = client {
+                client.disconnect(Some(ExitStatus::Disconnected.into()));
+            }
+
+            next_menu.set(if singleplayer.is_some() {
+                Menu::WorldSelect
+            } else {
+                Menu::Title
+            });
+
+            commands.remove_resource::<Client>();
+            commands.remove_resource::<Singleplayer>();
+            commands.remove_resource::<ConnectSeqInfo>();
+            seq.reset();
+            next_seq.set(ConnectSeq::Inactive);
+            next_app_state.set(AppState::InMenus);
+        }
+    }
 }
@@ -1 +1,462 @@
+use std::path::PathBuf;
 
+use bevy::prelude::*;
+use data::{
+    fs::save::{self, SaveEntry, WORLD_HEIGHTS, WorldInfo},
+    info::RootPath,
+    locale::Locale,
+    tr,
+};
+
+use crate::{
+    singleplayer::LaunchSingleplayer,
+    ui::{
+        UiVars,
+        button::{ButtonAction, ButtonClicked, ButtonVisuals},
+        elements::text_field::TextField,
+        menus::{Menu, MenuBody, MenuRoot},
+    },
+};
+
+/// Max number of characters in the name of a world.
+const MAX_NAME_LEN: usize = 32;
+
+/// Max number of characters in the seed field.
+const MAX_SEED_LEN: usize = 32;
+
+/// Attached to a button that launches the world at this path.
+#[derive(Component, Clone)]
+pub struct WorldEntry(pub PathBuf);
+
+/// Attached to a button that opens the edit menu for the world at this path.
+#[derive(Component, Clone)]
+pub struct EditWorldButton(pub PathBuf);
+
+/// The world being edited in the EditWorld menu.
+#[derive(Resource, Default)]
+pub struct EditingWorld(pub Option<PathBuf>);
+
+/// Text field for the name of a world.
+#[derive(Component)]
+pub struct WorldNameField;
+
+/// Text field for the seed of a new world.
+#[derive(Component)]
+pub struct WorldSeedField;
+
+/// Button that cycles the height of a new world.
+/// Holds an index into `WORLD_HEIGHTS`.
+#[derive(Component)]
+pub struct WorldHeightOption(usize);
+
+/// Button that creates the world and launches it.
+#[derive(Component)]
+pub struct CreateWorldButton;
+
+/// Button that saves the new name of the world being edited.
+#[derive(Component)]
+pub struct RenameWorldButton;
+
+/// Button that deletes the world being edited.
+/// The first click only arms it, the second deletes.
+#[derive(Component)]
+pub struct DeleteWorldButton {
+    armed: bool,
+}
+
+/// Directory containing one sub-directory per world.
+pub fn saves_dir(root: &RootPath) -> PathBuf {
+    root.join("saves")
+}
+
+/// Size of a directory in a human readable unit.
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{bytes} {}", UNITS[0])
+    } else {
+        format!("{size:.1} {}", UNITS[unit])
+    }
+}
+
+/// How long ago the world was last played.
+fn format_last_played(last_played: u64, locale: &Locale) -> String {
+    if last_played == 0 {
+        return locale.get("ui.time.never");
+    }
+
+    let elapsed = save::unix_now().saturating_sub(last_played);
+    let (key, n) = match elapsed {
+        0..60 => return locale.get("ui.time.just-now"),
+        60..3600 => ("ui.time.minutes-ago", elapsed / 60),
+        3600..86400 => ("ui.time.hours-ago", elapsed / 3600),
+        _ => ("ui.time.days-ago", elapsed / 86400),
+    };
+    tr!(locale, key, n)
+}
+
+fn entry_text(entry: &SaveEntry, locale: &Locale) -> String {
+    format!(
+        "{}\n{} - {}: {}",
+        entry.info.name,
+        format_size(entry.size),
+        locale.get("ui.world-select.last-played"),
+        format_last_played(entry.info.last_played, locale),
+    )
+}
+
+fn height_text(height: i32, locale: &Locale) -> String {
+    format!("{}: {height}", locale.get("ui.world-select.height"))
+}
+
+/// Draw the world select menu.
+/// Should fire on enter into Menu::WorldSelect
+#[rustfmt::skip]
+pub fn draw(
+    root: Res<RootPath>,
+    locale: Res<Locale>,
+    vars: Res<UiVars>,
+    mut commands: Commands,
+) {
+    let saves = save::list_saves(&saves_dir(&root));
+
+    commands.spawn(MenuRoot::bundle(Menu::WorldSelect)).with_children(|parent| {
+        parent.spawn(MenuBody::bundle(&vars)).with_children(|parent| {
+            parent.spawn((
+                Text::new(locale.get("ui.common.world-select")),
+                TextLayout::new_with_justify(Justify::Center),
+                TextFont {
+                    font_size: 40.0,
+                    ..default()
+                },
+                Node::default(),
+            ));
+
+            parent.spawn((
+                // Container for world list.
+                Node {
+                    width: Val::Percent(80.0),
+                    height: Val::Percent(100.0),
+                    display: Display::Flex,
+                    flex_direction: FlexDirection::Column,
+                    justify_content: JustifyContent::Start,
+                    overflow: Overflow::scroll_y(),
+                    margin: UiRect::horizontal(Val::Auto),
+                    ..default()
+                },
+            )).with_children(|parent| {
+                for entry in &saves {
+                    parent.spawn((
+                        // Row with the launch and edit buttons of a world.
+                        Node {
+                            width: Val::Percent(100.0),
+                            display: Display::Flex,
+                            flex_direction: FlexDirection::Row,
+                            column_gap: Val::Px(10.0),
+                            ..default()
+                        },
+                    )).with_children(|parent| {
+                        parent.spawn((
+                            WorldEntry(entry.dir.clone()),
+                            ButtonAction::None,
+                            ButtonVisuals::text(entry_text(entry, &locale), Val::Percent(75.0)).bundle(&vars),
+                        ));
+                        parent.spawn((
+                            EditWorldButton(entry.dir.clone()),
+                            ButtonAction::None,
+                            ButtonVisuals::text(locale.get("ui.world-select.edit"), Val::Percent(25.0)).bundle(&vars),
+                        ));
+                    });
+                }
+
+                // Open the create world dialog.
+                parent.spawn((
+                    ButtonAction::Transition(Menu::CreateWorld),
+                    ButtonVisuals::text(locale.get("ui.world-select.create"), Val::Percent(100.0)).bundle(&vars),
+                ));
+
+                // Back to the title menu.
+                parent.spawn((
+                    ButtonAction::Transition(Menu::Title),
+                    ButtonVisuals::text(locale.get("ui.common.back"), Val::Percent(100.0)).bundle(&vars),
+                ));
+            });
+        });
+    });
+}
+
+/// Launch the integrated server when a world entry is clicked,
+/// or open the edit menu when an edit button is clicked.
+pub fn handle_world_entry_clicks(
+    mut clicks: MessageReader<ButtonClicked>,
+    entries: Query<&WorldEntry>,
+    edits: Query<&EditWorldButton>,
+    mut editing: ResMut<EditingWorld>,
+    mut next_menu: ResMut<NextState<Menu>>,
+    mut launch: MessageWriter<LaunchSingleplayer>,
+) {
+    for click in clicks.read() {
+        if let Ok(entry) = entries.get(click.entity) {
+            launch.write(LaunchSingleplayer {
+                world_dir: entry.0.clone(),
+            });
+        } else if let Ok(edit) = edits.get(click.entity) {
+            editing.0 = Some(edit.0.clone());
+            next_menu.set(Menu::EditWorld);
+        }
+    }
+}
+
+/// Draw the create world dialog.
+/// Should fire on enter into Menu::CreateWorld
+#[rustfmt::skip]
+pub fn draw_create_world(
+    locale: Res<Locale>,
+    vars: Res<UiVars>,
+    mut commands: Commands,
+) {
+    // the default height is the one dedicated servers use.
+    let height = WORLD_HEIGHTS.iter().position(|&h| h == 384).unwrap_or(0);
+
+    commands.spawn(MenuRoot::bundle(Menu::CreateWorld)).with_children(|parent| {
+        parent.spawn(MenuBody::bundle(&vars)).with_children(|parent| {
+            parent.spawn((
+                Text::new(locale.get("ui.world-select.create")),
+                TextLayout::new_with_justify(Justify::Center),
+                TextFont {
+                    font_size: 40.0,
+                    ..default()
+                },
+                Node::default(),
+            ));
+
+            parent.spawn((
+                // Container for world settings.
+                Node {
+                    width: Val::Percent(80.0),
+                    height: Val::Percent(100.0),
+                    display: Display::Flex,
+                    flex_direction: FlexDirection::Column,
+                    justify_content: JustifyContent::Start,
+                    margin: UiRect::horizontal(Val::Auto),
+                    ..default()
+                },
+            )).with_children(|parent| {
+                parent.spawn((
+                    WorldNameField,
+                    TextField::new(
+                        locale.get("ui.world-select.new-world"),
+                        locale.get("ui.world-select.name"),
+                        MAX_NAME_LEN,
+                    ).bundle(&vars),
+                ));
+
+                parent.spawn((
+                    WorldSeedField,
+                    TextField::new("", locale.get("ui.world-select.seed"), MAX_SEED_LEN).bundle(&vars),
+                ));
+
+                parent.spawn((
+                    WorldHeightOption(height),
+                    ButtonAction::None,
+                    ButtonVisuals::text(height_text(WORLD_HEIGHTS[height], &locale), Val::Percent(100.0)).bundle(&vars),
+                ));
+
+                parent.spawn((
+                    CreateWorldButton,
+                    ButtonAction::None,
+                    ButtonVisuals::text(locale.get("ui.world-select.create-confirm"), Val::Percent(100.0)).bundle(&vars),
+                ));
+
+                // Back to the world select menu.
+                parent.spawn((
+                    ButtonAction::Transition(Menu::WorldSelect),
+                    ButtonVisuals::text(locale.get("ui.common.cancel"), Val::Percent(100.0)).bundle(&vars),
+                ));
+            });
+        });
+    });
+}
+
+/// Cycle the world height, or create the world and launch it.
+pub fn handle_create_world_clicks(
+    mut clicks: MessageReader<ButtonClicked>,
+    mut heights: Query<(&mut WorldHeightOption, &mut Text)>,
+    creates: Query<(), With<CreateWorldButton>>,
+    name: Single<&TextField, With<WorldNameField>>,
+    seed: Single<&TextField, (With<WorldSeedField>, Without<WorldNameField>)>,
+    root: Res<RootPath>,
+    locale: Res<Locale>,
+    mut launch: MessageWriter<LaunchSingleplayer>,
+) {
+    for click in clicks.read() {
+        if let Ok((mut option, mut text)) = heights.get_mut(click.entity) {
+            option.0 = (option.0 + 1) % WORLD_HEIGHTS.len();
+            text.0 = height_text(WORLD_HEIGHTS[option.0], &locale);
+        } else if creates.contains(click.entity) {
+            let Ok((option, _)) = heights.single() else {
+                continue;
+            };
+
+            let name = match name.read().trim() {
+                "" => locale.get("ui.world-select.new-world"),
+                name => name.to_owned(),
+            };
+            let info = WorldInfo::new(&name, save::parse_seed(seed.read()), WORLD_HEIGHTS[option.0]);
+            let dir = save::new_world_dir(&saves_dir(&root), &name);
+            if let Err(e) = info.save(&dir) {
+                error!(
+                    "[C160] Failed to create world: '{}' with error: '{e}'",
+                    dir.display()
+                );
+                continue;
+            }
+
+            info!("Created world '{name}' in: '{}'", dir.display());
+            launch.write(LaunchSingleplayer { world_dir: dir });
+        }
+    }
+}
+
+/// Draw the edit world menu for the world in `EditingWorld`.
+/// Should fire on enter into Menu::EditWorld
+#[rustfmt::skip]
+pub fn draw_edit_world(
+    editing: Res<EditingWorld>,
+    locale: Res<Locale>,
+    vars: Res<UiVars>,
+    mut next_menu: ResMut<NextState<Menu>>,
+    mut commands: Commands,
+) {
+    let Some(info) = editing.0.as_deref().and_then(|dir| WorldInfo::load_or_default(dir).ok()) else {
+        next_menu.set(Menu::WorldSelect);
+        return;
+    };
+
+    commands.spawn(MenuRoot::bundle(Menu::EditWorld)).with_children(|parent| {
+        parent.spawn(MenuBody::bundle(&vars)).with_children(|parent| {
+            parent.spawn((
+                Text::new(locale.get("ui.world-select.edit")),
+                TextLayout::new_with_justify(Justify::Center),
+                TextFont {
+                    font_size: 40.0,
+                    ..default()
+                },
+                Node::default(),
+            ));
+
+            parent.spawn((
+                // Container for world settings.
+                Node {
+                    width: Val::Percent(80.0),
+                    height: Val::Percent(100.0),
+                    display: Display::Flex,
+                    flex_direction: FlexDirection::Column,
+                    justify_content: JustifyContent::Start,
+                    margin: UiRect::horizontal(Val::Auto),
+                    ..default()
+                },
+            )).with_children(|parent| {
+                parent.spawn((
+                    WorldNameField,
+                    TextField::new(&info.name, locale.get("ui.world-select.name"), MAX_NAME_LEN).bundle(&vars),
+                ));
+
+                // Settings the world was created with can't be changed.
+                parent.spawn((
+                    Text::new(format!(
+                        "{}: {}\n{}",
+                        locale.get("ui.world-select.seed"),
+                        info.seed,
+                        height_text(info.height(), &locale),
+                    )),
+                    TextLayout::new_with_justify(Justify::Center),
+                    TextFont {
+                        font: vars.font(),
+                        font_size: 16.0,
+                        ..default()
+                    },
+                    Node::default(),
+                ));
+
+                parent.spawn((
+                    RenameWorldButton,
+                    ButtonAction::None,
+                    ButtonVisuals::text(locale.get("ui.world-select.rename"), Val::Percent(100.0)).bundle(&vars),
+                ));
+
+                parent.spawn((
+                    DeleteWorldButton { armed: false },
+                    ButtonAction::None,
+                    ButtonVisuals::text(locale.get("ui.world-select.delete"), Val::Percent(100.0)).bundle(&vars),
+                ));
+
+                // Back to the world select menu.
+                parent.spawn((
+                    ButtonAction::Transition(Menu::WorldSelect),
+                    ButtonVisuals::text(locale.get("ui.common.back"), Val::Percent(100.0)).bundle(&vars),
+                ));
+            });
+        });
+    });
+}
+
+/// Rename or delete the world being edited.
+pub fn handle_edit_world_clicks(
+    mut clicks: MessageReader<ButtonClicked>,
+    renames: Query<(), With<RenameWorldButton>>,
+    mut deletes: Query<(&mut DeleteWorldButton, &mut Text)>,
+    name: Single<&TextField, With<WorldNameField>>,
+    editing: Res<EditingWorld>,
+    locale: Res<Locale>,
+    mut next_menu: ResMut<NextState<Menu>>,
+) {
+    let Some(dir) = editing.0.as_deref() else {
+        return;
+    };
+
+    for click in clicks.read() {
+        if renames.contains(click.entity) {
+            let name = name.read().trim();
+            if name.is_empty() {
+                continue;
+            }
+
+            // only the display name changes, the directory keeps its name.
+            let result = WorldInfo::load_or_default(dir).and_then(|mut info| {
+                info.name = name.to_owned();
+                info.save(dir)
+            });
+
+            match result {
+                Ok(()) => next_menu.set(Menu::WorldSelect),
+                Err(e) => error!(
+                    "[C161] Failed to rename world: '{}' with error: '{e}'",
+                    dir.display()
+                ),
+            }
+        } else if let Ok((mut delete, mut text)) = deletes.get_mut(click.entity) {
+            if !delete.armed {
+                delete.armed = true;
+                text.0 = locale.get("ui.world-select.delete-confirm");
+                continue;
+            }
+
+            info!("Deleting world: '{}'", dir.display());
+            match std::fs::remove_dir_all(dir) {
+                Ok(()) => next_menu.set(Menu::WorldSelect),
+                Err(e) => error!(
+                    "[C162] Failed to delete world: '{}' with error: '{e}'",
+                    dir.display()
+                ),
+            }
+        }
+    }
+}
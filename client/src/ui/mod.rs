@@ -1,11 +1,19 @@
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::settings::Settings;
 
 pub mod button;
 pub mod chat;
 pub mod elements;
 pub mod hint;
+pub mod hud;
 pub mod menus;
 pub mod util;
+pub mod world_text;
+
+/// Smallest text on screen, in logical pixels after the UI scale is applied.
+const MIN_TEXT_SIZE: f32 = 12.0;
 
 #[derive(Resource)]
 pub struct UiVars {
@@ -13,6 +21,12 @@ pub struct UiVars {
     pub menu_body_max_width: Val,
     pub menu_body_width: Val,
     pub chat_box_width: Val,
+
+    /// Colors of the palette chosen in the settings.
+    pub colors: UiColors,
+
+    /// Text smaller than this is drawn at this size, so it stays readable at small UI scales.
+    pub min_font_size: f32,
 }
 
 impl UiVars {
@@ -32,6 +46,136 @@ impl Default for UiVars {
             menu_body_max_width: Val::Px(1280.0),
             menu_body_width: Val::Percent(80.0),
             chat_box_width: Val::Vw(10.0),
+            colors: UiPalette::Default.colors(),
+            min_font_size: MIN_TEXT_SIZE,
+        }
+    }
+}
+
+/// Sets of colors the UI can be drawn with, chosen in the settings.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Reflect, Serialize, Deserialize)]
+pub enum UiPalette {
+    #[default]
+    Default,
+
+    /// Opaque backgrounds and brighter colors.
+    HighContrast,
+
+    /// Colors that can be told apart with red-green color blindness,
+    /// from the Okabe-Ito palette.
+    Colorblind,
+}
+
+impl UiPalette {
+    pub const ALL: [Self; 3] = [Self::Default, Self::HighContrast, Self::Colorblind];
+
+    /// Locale key of the name of the palette.
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Default => "ui.options.palette.default",
+            Self::HighContrast => "ui.options.palette.high-contrast",
+            Self::Colorblind => "ui.options.palette.colorblind",
+        }
+    }
+
+    pub fn colors(self) -> UiColors {
+        match self {
+            Self::Default => UiColors {
+                text: Color::WHITE,
+                muted: Color::srgb(0.6, 0.6, 0.6),
+                button: Color::srgb(0.1, 0.1, 0.1),
+                button_selected: Color::srgb(0.3, 0.3, 0.3),
+                panel: Color::srgba(0.0, 0.0, 0.0, 0.5),
+                health: Color::srgb(0.85, 0.15, 0.15),
+                hunger: Color::srgb(0.8, 0.55, 0.2),
+                warning: Color::srgb(0.9, 0.25, 0.2),
+                name: Color::srgb(1.0, 0.85, 0.4),
+                notice: Color::srgb(0.7, 0.8, 1.0),
+            },
+            Self::HighContrast => UiColors {
+                text: Color::WHITE,
+                muted: Color::srgb(0.85, 0.85, 0.85),
+                button: Color::BLACK,
+                button_selected: Color::srgb(0.4, 0.4, 0.4),
+                panel: Color::srgba(0.0, 0.0, 0.0, 0.9),
+                health: Color::srgb(1.0, 0.2, 0.2),
+                hunger: Color::srgb(1.0, 0.85, 0.0),
+                warning: Color::srgb(1.0, 0.4, 0.4),
+                name: Color::srgb(1.0, 0.9, 0.3),
+                notice: Color::srgb(0.6, 0.85, 1.0),
+            },
+            Self::Colorblind => UiColors {
+                text: Color::WHITE,
+                muted: Color::srgb(0.6, 0.6, 0.6),
+                button: Color::srgb(0.1, 0.1, 0.1),
+                button_selected: Color::srgb(0.3, 0.3, 0.3),
+                panel: Color::srgba(0.0, 0.0, 0.0, 0.5),
+                // vermillion and sky blue.
+                health: Color::srgb_u8(213, 94, 0),
+                hunger: Color::srgb_u8(86, 180, 233),
+                // orange, yellow and blue.
+                warning: Color::srgb_u8(230, 159, 0),
+                name: Color::srgb_u8(240, 228, 66),
+                notice: Color::srgb_u8(86, 180, 233),
+            },
+        }
+    }
+}
+
+/// Colors of the menus and the HUD.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct UiColors {
+    pub text: Color,
+
+    /// Text that is less important, like the times between chat messages.
+    pub muted: Color,
+
+    /// Background of buttons.
+    pub button: Color,
+
+    /// Background of the selected button of a list.
+    pub button_selected: Color,
+
+    /// Background of bars and other panels drawn over the world.
+    pub panel: Color,
+
+    pub health: Color,
+    pub hunger: Color,
+
+    /// Text that needs attention, like conflicting bindings.
+    pub warning: Color,
+
+    /// Names of players in the chat.
+    pub name: Color,
+
+    /// Messages from the server in the chat.
+    pub notice: Color,
+}
+
+/// Apply the UI scale and palette in the settings.
+/// Menus and the HUD are drawn again when the palette or text size changes.
+pub fn apply_ui_settings(
+    settings: Res<Settings>,
+    mut vars: ResMut<UiVars>,
+    mut scale: ResMut<UiScale>,
+) {
+    if scale.0 != settings.ui_scale {
+        scale.0 = settings.ui_scale;
+    }
+
+    let colors = settings.ui_palette.colors();
+    let min_font_size = MIN_TEXT_SIZE / settings.ui_scale;
+    if vars.colors != colors || vars.min_font_size != min_font_size {
+        vars.colors = colors;
+        vars.min_font_size = min_font_size;
+    }
+}
+
+/// Raise text smaller than `UiVars::min_font_size` to it.
+pub fn apply_text_size_floor(vars: Res<UiVars>, mut fonts: Query<&mut TextFont>) {
+    for mut font in &mut fonts {
+        if (vars.is_changed() || font.is_added()) && font.font_size < vars.min_font_size {
+            font.font_size = vars.min_font_size;
         }
     }
 }
@@ -0,0 +1,142 @@
+//! Text drawn over entities in the world, like the name tags of other players.
+//!
+//! Labels are UI text kept over a point above their anchor entity, so they always face
+//! the camera and are the same size on screen at any distance. They fade out towards
+//! their furthest distance and are hidden past it. While the "label occlusion" option is
+//! on, labels that can be occluded are hidden when opaque blocks are between them and
+//! the camera. Labels are despawned along with their anchor.
+
+use bevy::prelude::*;
+use data::{blockstates::BlockState, registry::Registry};
+use world::{VoxelState, World};
+
+use crate::{player::MainCamera, settings::Settings, states::AppState};
+
+/// Furthest distance of labels that don't set their own, in voxels.
+pub const DEFAULT_MAX_DISTANCE: f32 = 64.0;
+
+/// Fraction of the furthest distance labels start fading out at.
+const FADE_START: f32 = 0.75;
+
+/// Alpha of the background of labels that aren't faded.
+const BACKGROUND_ALPHA: f32 = 0.4;
+
+/// Text kept over an entity in the world.
+#[derive(Component, Clone, Debug)]
+pub struct WorldLabel {
+    /// Entity the label is drawn over.
+    pub anchor: Entity,
+
+    /// Offset of the point the label is drawn over from the anchor.
+    pub offset: Vec3,
+
+    /// Labels further than this from the camera are hidden.
+    pub max_distance: f32,
+
+    /// Whether opaque blocks between the camera and the label hide it.
+    pub occludable: bool,
+}
+
+impl WorldLabel {
+    pub fn new(anchor: Entity, offset: Vec3) -> Self {
+        Self {
+            anchor,
+            offset,
+            max_distance: DEFAULT_MAX_DISTANCE,
+            occludable: true,
+        }
+    }
+
+    pub fn with_max_distance(mut self, max_distance: f32) -> Self {
+        self.max_distance = max_distance;
+        self
+    }
+
+    /// Draw the label through blocks.
+    pub fn see_through(mut self) -> Self {
+        self.occludable = false;
+        self
+    }
+
+    /// The label with this text, hidden until it is first placed over its anchor.
+    pub fn bundle(self, text: impl Into<String>) -> impl Bundle {
+        (
+            self,
+            DespawnOnExit(AppState::InGame),
+            Text::new(text),
+            TextFont::from_font_size(14.0),
+            TextColor(Color::WHITE),
+            Node {
+                position_type: PositionType::Absolute,
+                padding: UiRect::axes(Val::Px(4.0), Val::Px(1.0)),
+                display: Display::None,
+                ..default()
+            },
+            BackgroundColor(Color::BLACK.with_alpha(BACKGROUND_ALPHA)),
+            Pickable::IGNORE,
+        )
+    }
+}
+
+/// Keep labels over their anchors, fading and hiding them by distance and occlusion.
+pub fn update_world_labels(
+    mut commands: Commands,
+    settings: Res<Settings>,
+    world: Res<World>,
+    blocks: Res<Registry<BlockState>>,
+    camera: Single<(&Camera, &GlobalTransform), With<MainCamera>>,
+    anchors: Query<&GlobalTransform>,
+    mut labels: Query<(
+        Entity,
+        &WorldLabel,
+        &mut Node,
+        &ComputedNode,
+        &mut TextColor,
+        &mut BackgroundColor,
+    )>,
+) {
+    let (camera, camera_transform) = camera.into_inner();
+    let eye = camera_transform.translation();
+    for (entity, label, mut node, computed, mut color, mut background) in &mut labels {
+        let Ok(anchor) = anchors.get(label.anchor) else {
+            commands.entity(entity).despawn();
+            continue;
+        };
+
+        let pos = anchor.translation() + label.offset;
+        let distance = eye.distance(pos);
+        let occluded = || {
+            settings.occlude_labels && label.occludable && is_occluded(&world, &blocks, eye, pos)
+        };
+
+        let viewport = camera.world_to_viewport(camera_transform, pos);
+        match viewport {
+            Ok(viewport) if distance < label.max_distance && !occluded() => {
+                let size = computed.size() * computed.inverse_scale_factor();
+                node.display = Display::Flex;
+                node.left = Val::Px(viewport.x - size.x * 0.5);
+                node.top = Val::Px(viewport.y - size.y);
+
+                let fade = (1.0 - distance / label.max_distance) / (1.0 - FADE_START);
+                let alpha = fade.clamp(0.0, 1.0);
+                color.set_if_neq(TextColor(Color::WHITE.with_alpha(alpha)));
+                background.set_if_neq(BackgroundColor(
+                    Color::BLACK.with_alpha(alpha * BACKGROUND_ALPHA),
+                ));
+            }
+            _ => node.display = Display::None,
+        }
+    }
+}
+
+/// Whether an opaque block is between the eye and the point.
+fn is_occluded(world: &World, blocks: &Registry<BlockState>, eye: Vec3, pos: Vec3) -> bool {
+    let opaque = |_, state: VoxelState| {
+        blocks
+            .get(state.voxel)
+            .is_some_and(BlockState::is_opaque_cube)
+    };
+    world
+        .raycast(eye, pos - eye, eye.distance(pos), opaque)
+        .is_some()
+}
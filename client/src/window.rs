@@ -1,11 +1,21 @@
 use bevy::{
     diagnostic::FrameCount,
     prelude::*,
-    window::{CursorGrabMode, CursorOptions, PrimaryWindow},
+    window::{
+        CursorGrabMode, CursorOptions, Monitor, MonitorSelection, PrimaryWindow, WindowMode,
+        WindowMoved, WindowResized,
+    },
 };
 
 use crate::{settings::Settings, states::CursorMode};
 
+/// Size of the window, in logical pixels, before the size in the settings is restored.
+pub const DEFAULT_SIZE: UVec2 = UVec2::new(1280, 720);
+
+/// Smallest size restored from the settings, so an edited settings file can't
+/// open a window too small to use.
+const MIN_SIZE: UVec2 = UVec2::new(320, 240);
+
 #[derive(States, Default, Clone, Eq, PartialEq, Hash, Debug)]
 pub enum WindowState {
     #[default]
@@ -21,15 +31,68 @@ pub fn make_window_visible(
     mut state: ResMut<NextState<WindowState>>,
     frames: Res<FrameCount>,
     settings: Res<Settings>,
+    monitors: Query<&Monitor>,
 ) {
     let (mut window,) = window.into_inner();
     if frames.0 >= 10 {
+        restore_window(&mut window, &settings, &monitors);
         window.visible = true;
         window.mode = settings.window_mode;
         state.set(WindowState::Ready);
     }
 }
 
+/// Apply the size and position of the window saved in the settings.
+/// A position that isn't on any monitor, like one that was unplugged, is left to the platform.
+fn restore_window(window: &mut Window, settings: &Settings, monitors: &Query<&Monitor>) {
+    let size = settings.window_size.max(MIN_SIZE);
+    window.resolution.set(size.x as f32, size.y as f32);
+
+    if let Some(pos) = settings.window_position
+        && monitors.iter().any(|monitor| {
+            let min = monitor.physical_position;
+            let size = UVec2::new(monitor.physical_width, monitor.physical_height);
+            pos.cmpge(min).all() && pos.cmplt(min + size.as_ivec2()).all()
+        })
+    {
+        window.position = WindowPosition::At(pos);
+    }
+}
+
+/// Keep the size and position of the window in the settings while it is windowed,
+/// so they are restored at the next startup. Nothing needs to react to them until
+/// the settings are saved, so they're changed without change detection.
+pub fn record_window_state(
+    mut resized: MessageReader<WindowResized>,
+    mut moved: MessageReader<WindowMoved>,
+    window: Single<(Entity, &Window), With<PrimaryWindow>>,
+    mut settings: ResMut<Settings>,
+) {
+    let (entity, window) = window.into_inner();
+    let windowed = window.mode == WindowMode::Windowed;
+    let settings = settings.bypass_change_detection();
+
+    for ev in resized.read() {
+        if windowed && ev.window == entity {
+            settings.window_size = Vec2::new(ev.width, ev.height).round().as_uvec2();
+        }
+    }
+
+    for ev in moved.read() {
+        if windowed && ev.window == entity {
+            settings.window_position = Some(ev.position);
+        }
+    }
+}
+
+/// Switch between windowed and borderless fullscreen when the "toggle-fullscreen" action fires.
+pub fn handle_toggle_fullscreen(mut settings: ResMut<Settings>) {
+    settings.window_mode = match settings.window_mode {
+        WindowMode::Windowed => WindowMode::BorderlessFullscreen(MonitorSelection::Current),
+        _ => WindowMode::Windowed,
+    };
+}
+
 pub fn apply_cursor_changes(
     mut cursor: Single<&mut CursorOptions, With<PrimaryWindow>>,
     window_state: Res<State<WindowState>>,
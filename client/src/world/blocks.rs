@@ -0,0 +1,120 @@
+use bevy::prelude::*;
+use data::{
+    blocks::{
+        Block,
+        def::{BlockDef, read_block_defs},
+        register_blocks,
+    },
+    blockstates::BlockState,
+    fs::packs::AssetPackReader,
+    info::RootPath,
+    registry::{Registry, RegistryId},
+    tags::{TagDefs, Tags},
+};
+
+use crate::{
+    packs::data_dirs,
+    render::atlases::{BlockTextureMeta, TextureArray, TextureArraySources},
+};
+
+/// Every block known to the client, sorted by name.
+/// Read from the `blocks` folder of the mounted packs and the assets directory.
+#[derive(Resource, Default)]
+pub struct BlockDefinitions(pub Vec<BlockDef>);
+
+impl BlockDefinitions {
+    /// Paths of every texture used by a block, without duplicates.
+    pub fn textures(&self) -> Vec<&str> {
+        let mut textures = Vec::new();
+        for texture in self.0.iter().flat_map(|def| def.textures()) {
+            if !textures.contains(&texture) {
+                textures.push(texture);
+            }
+        }
+        textures
+    }
+}
+
+/// Read the block definitions again, should run when the mounted packs change.
+/// Files that fail to load are skipped.
+pub fn load_block_definitions(
+    root: Res<RootPath>,
+    packs: Res<AssetPackReader>,
+    mut defs: ResMut<BlockDefinitions>,
+) {
+    let dirs = data_dirs(&root, &packs);
+
+    let (loaded, errors) = read_block_defs(&dirs);
+    for err in errors {
+        error!("{err}");
+    }
+
+    info!("Loaded {} block definitions.", loaded.len());
+    defs.0 = loaded;
+}
+
+/// Read the block tags again, should run when blocks are registered.
+/// Tags list block names, and every state of a block has the tags of the block.
+pub fn load_block_tags(
+    root: Res<RootPath>,
+    packs: Res<AssetPackReader>,
+    blocks: Res<Registry<Block>>,
+    mut tags: ResMut<Tags<BlockState>>,
+) {
+    let dirs = data_dirs(&root, &packs);
+
+    let (defs, read_errors) = TagDefs::read(&dirs, "blocks");
+    let (resolved, errors) = defs.resolve(|name| {
+        blocks
+            .get_by_name(name)
+            .map(|block| block.states.clone().map(RegistryId))
+    });
+
+    for err in read_errors.into_iter().chain(errors) {
+        error!("{err}");
+    }
+
+    info!("Loaded {} block tags.", resolved.len());
+    *tags = resolved;
+}
+
+/// Add the textures referenced by block definitions to the block texture array.
+pub fn collect_block_textures(
+    defs: Res<BlockDefinitions>,
+    mut sources: ResMut<TextureArraySources<BlockTextureMeta>>,
+) {
+    for texture in defs.textures() {
+        sources.add_file(texture);
+    }
+}
+
+/// Register the block states of every definition, in order.
+/// Runs again when the texture array is rebuilt, so texture indices stay up to date,
+/// and when registries are synchronized, so blocks know the new ids of their states.
+///
+/// The index of each entry must match the `Voxel` id used by the server,
+/// which the "block_states" registry is synchronized with on join.
+pub fn register_block_states(
+    defs: Res<BlockDefinitions>,
+    atlas: Res<TextureArray<BlockTextureMeta>>,
+    mut registry: ResMut<Registry<BlockState>>,
+    mut blocks: ResMut<Registry<Block>>,
+) {
+    // textures that are still loading would resolve to the debug texture.
+    if atlas.is_dirty() {
+        return;
+    }
+
+    let texture = |name: &str| {
+        atlas.resolve(name).unwrap_or_else(|| {
+            warn!("[C130] Block texture '{name}' is not in the texture array, using the debug texture.");
+            0
+        }) as u16
+    };
+
+    for name in register_blocks(&defs.0, &mut registry, &mut blocks, texture) {
+        warn!(
+            "[C131] States of block '{name}' changed since it was first registered, its properties may pick the wrong state."
+        );
+    }
+}
@@ -0,0 +1,183 @@
+//! Copies of chunks the client unloaded, so they don't have to be sent again.
+//!
+//! The zipped data of the chunks the server sends is kept, up to a budget. When a
+//! chunk is unloaded, the client tells the server which revision of it it still has.
+//! If the chunk comes back into range unchanged, the server sends a `ChunkRevision`
+//! instead of the chunk data, and the copy is loaded from here.
+//!
+//! On multiplayer servers, the chunks are also kept on disk with a `DiskCache`,
+//! so they can be loaded on the next visit.
+
+use bevy::prelude::*;
+use data::registry::Registry;
+use fxhash::FxHashMap;
+use protocol::{bytes::Bytes, types::ChunkRevision};
+use world::{World, region::format::UnzippedChunk};
+
+use crate::{
+    events::ChunkUnloaded,
+    net::{Client, channel::Channel},
+    render::chunk::ChunkRenderQueue,
+    world::{disk_cache::DiskCache, io::load_chunk},
+};
+
+/// Most chunks kept in the cache. Past this, the oldest copies are dropped.
+const CACHE_BUDGET: usize = 2048;
+
+/// Most revisions sent to the server in one packet.
+pub const MAX_REVISIONS_PER_PACKET: usize = 256;
+
+/// Zipped copies of the chunks received from the server.
+#[derive(Resource, Default)]
+pub struct ChunkCache {
+    chunks: FxHashMap<IVec2, CachedChunk>,
+
+    /// Incremented every time a chunk is stored, to find the oldest copies.
+    counter: u64,
+
+    /// Files of the chunks of the server, if it is a multiplayer server.
+    disk: Option<DiskCache>,
+}
+
+struct CachedChunk {
+    revision: u64,
+    data: Bytes,
+    stored: u64,
+}
+
+impl ChunkCache {
+    /// Keep the chunks on disk too, and use the copies already there.
+    pub fn set_disk(&mut self, disk: DiskCache) {
+        self.disk = Some(disk);
+    }
+
+    /// Store a copy of the zipped data of a chunk, replacing the previous copy.
+    pub fn insert(&mut self, origin: IVec2, revision: u64, data: &[u8]) {
+        // copied, so the cache doesn't keep the whole receive buffer alive.
+        let data = Bytes::copy_from_slice(data);
+        if let Some(disk) = &mut self.disk {
+            disk.write(origin, revision, data.clone());
+        }
+
+        self.counter += 1;
+        self.chunks.insert(
+            origin,
+            CachedChunk {
+                revision,
+                data,
+                stored: self.counter,
+            },
+        );
+
+        if self.chunks.len() > CACHE_BUDGET {
+            // drop an eighth of the cache at once, so this doesn't run for every chunk.
+            let mut ages = self
+                .chunks
+                .iter()
+                .map(|(origin, chunk)| (chunk.stored, *origin))
+                .collect::<Vec<_>>();
+            ages.sort_unstable();
+            for (_, origin) in ages.into_iter().take(CACHE_BUDGET / 8) {
+                self.chunks.remove(&origin);
+            }
+        }
+    }
+
+    /// The zipped data of a chunk, if the copy is of this revision.
+    pub fn get(&self, origin: IVec2, revision: u64) -> Option<Bytes> {
+        match self.chunks.get(&origin) {
+            Some(chunk) if chunk.revision == revision => Some(chunk.data.clone()),
+            _ => self.disk.as_ref()?.read(origin, revision),
+        }
+    }
+
+    /// The revision of the copy of a chunk.
+    pub fn revision(&self, origin: IVec2) -> Option<u64> {
+        match self.chunks.get(&origin) {
+            Some(chunk) => Some(chunk.revision),
+            None => self.disk.as_ref()?.revision(origin),
+        }
+    }
+
+    /// Drop the copy of a chunk, in memory and on disk.
+    pub fn remove(&mut self, origin: IVec2) {
+        self.chunks.remove(&origin);
+        if let Some(disk) = &mut self.disk {
+            disk.remove(origin);
+        }
+    }
+}
+
+/// Tell the server the revisions of the chunks that were unloaded
+/// and still have a copy in the cache.
+pub fn send_chunk_revisions(
+    channels: Res<Registry<Channel>>,
+    cache: Res<ChunkCache>,
+    client: Option<ResMut<Client>>,
+    mut unloaded: MessageReader<ChunkUnloaded>,
+) {
+    let Some(mut client) = client else {
+        unloaded.clear();
+        return;
+    };
+
+    let revisions = unloaded
+        .read()
+        .filter_map(|msg| {
+            let revision = cache.revision(msg.origin)?;
+            Some(ChunkRevision {
+                origin: msg.origin,
+                revision,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let channel = channels.resolve("chunk-revisions").unwrap().into();
+    for batch in revisions.chunks(MAX_REVISIONS_PER_PACKET) {
+        client.tcp_send(channel, bytemuck::cast_slice(batch));
+    }
+}
+
+/// Load the chunks the server says are unchanged from the cache.
+///
+/// If the copy was dropped in the meantime, nothing is loaded,
+/// and the chunk is requested again like any other missing chunk.
+pub fn recv_cached_chunks(
+    channels: Res<Registry<Channel>>,
+    mut cache: ResMut<ChunkCache>,
+    mut world: ResMut<World>,
+    mut queue: ResMut<ChunkRenderQueue>,
+) {
+    let channel = channels.get_by_name("chunk-cached").unwrap();
+    for packet in channel.recv() {
+        let Some(cached) = packet.cast::<ChunkRevision>() else {
+            continue;
+        };
+
+        let Some(data) = cache.get(cached.origin, cached.revision) else {
+            debug!(
+                "[C182] Chunk at {} revision {} is no longer cached.",
+                cached.origin, cached.revision
+            );
+            continue;
+        };
+
+        match UnzippedChunk::unzip(&data) {
+            Ok(unzip) => {
+                load_chunk(unzip, &mut world, &mut queue);
+            }
+            Err(e) => {
+                warn!(
+                    "[C185] Cached chunk at {} failed to unzip with error: '{e}'",
+                    cached.origin
+                );
+                cache.remove(cached.origin);
+            }
+        }
+    }
+}
+
+/// Drop all cached chunks, should run when leaving the game.
+pub fn clear_chunk_cache(mut cache: ResMut<ChunkCache>) {
+    *cache = ChunkCache::default();
+}
@@ -0,0 +1,231 @@
+//! Copies of chunks from multiplayer servers, kept on disk between sessions.
+//!
+//! Each server has a directory under "cache/chunks", named after its address,
+//! with one file per chunk named "x.z.revision.chunk". On join, the revisions of the
+//! cached chunks are sent to the server, so chunks that didn't change since the last
+//! visit are loaded from disk instead of being sent again.
+//!
+//! The files of all servers together are capped in size. When over the cap, the
+//! files that were used least recently are deleted when joining a server.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use bevy::{prelude::*, tasks::IoTaskPool};
+use data::{info::RootPath, registry::Registry};
+use fxhash::FxHashMap;
+use protocol::{bytes::Bytes, types::ChunkRevision};
+
+use crate::{
+    net::{Client, channel::Channel},
+    sequences::connect::ConnectSeqInfo,
+    singleplayer::Singleplayer,
+    world::cache::{ChunkCache, MAX_REVISIONS_PER_PACKET},
+};
+
+/// Most bytes of chunk files kept for all servers together.
+const DISK_BUDGET: u64 = 512 * 1024 * 1024;
+
+/// Most revisions sent to the server on join, the most recently used first.
+/// The server doesn't remember more than this per player.
+const MAX_JOIN_REVISIONS: usize = 4096;
+
+/// The chunk files of one server.
+pub struct DiskCache {
+    dir: PathBuf,
+
+    /// Revisions of the chunks that have a file, by origin.
+    index: FxHashMap<IVec2, u64>,
+}
+
+impl DiskCache {
+    /// Open the cache directory of the server at this address, creating it if needed.
+    pub fn open(root: &Path, server: &str) -> std::io::Result<Self> {
+        let name = server
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect::<String>();
+        let dir = root.join(name);
+        fs::create_dir_all(&dir)?;
+
+        let mut index = FxHashMap::default();
+        for entry in fs::read_dir(&dir)?.flatten() {
+            if let Some((origin, revision)) = parse_file_name(&entry.file_name().to_string_lossy())
+            {
+                index.insert(origin, revision);
+            }
+        }
+
+        Ok(Self { dir, index })
+    }
+
+    /// Forget the file of a chunk, and delete it.
+    pub fn remove(&mut self, origin: IVec2) {
+        if let Some(revision) = self.index.remove(&origin) {
+            let _ = fs::remove_file(self.path(origin, revision));
+        }
+    }
+
+    /// The revision of the file of a chunk.
+    pub fn revision(&self, origin: IVec2) -> Option<u64> {
+        self.index.get(&origin).copied()
+    }
+
+    /// Read the file of a chunk, if it is of this revision.
+    pub fn read(&self, origin: IVec2, revision: u64) -> Option<Bytes> {
+        if self.revision(origin) != Some(revision) {
+            return None;
+        }
+
+        let path = self.path(origin, revision);
+        let data = fs::read(&path).ok()?;
+
+        // the file was used, so it is the last to be deleted.
+        if let Ok(file) = fs::File::options().write(true).open(&path) {
+            let _ = file.set_modified(SystemTime::now());
+        }
+
+        Some(Bytes::from(data))
+    }
+
+    /// Write the file of a chunk in the background, replacing the file of its previous revision.
+    pub fn write(&mut self, origin: IVec2, revision: u64, data: Bytes) {
+        let previous = self.index.insert(origin, revision);
+        if previous == Some(revision) {
+            return;
+        }
+
+        let stale = previous.map(|previous| self.path(origin, previous));
+        let path = self.path(origin, revision);
+        IoTaskPool::get()
+            .spawn(async move {
+                if let Some(stale) = stale {
+                    let _ = fs::remove_file(stale);
+                }
+                // written under another name first, so a half-written file is never read.
+                let temp = path.with_extension("tmp");
+                let result = fs::write(&temp, &data).and_then(|_| fs::rename(&temp, &path));
+                if let Err(e) = result {
+                    warn!(
+                        "[C184] Failed to write chunk cache file: '{}' with error: '{e}'",
+                        path.display()
+                    );
+                }
+            })
+            .detach();
+    }
+
+    /// The revisions of the chunks with files, the most recently used first.
+    pub fn revisions(&self, limit: usize) -> Vec<ChunkRevision> {
+        let mut files = self
+            .index
+            .iter()
+            .map(|(origin, revision)| {
+                let modified = fs::metadata(self.path(*origin, *revision))
+                    .and_then(|meta| meta.modified())
+                    .unwrap_or(SystemTime::UNIX_EPOCH);
+                (modified, *origin, *revision)
+            })
+            .collect::<Vec<_>>();
+        files.sort_unstable_by(|a, b| b.0.cmp(&a.0));
+
+        files
+            .into_iter()
+            .take(limit)
+            .map(|(_, origin, revision)| ChunkRevision { origin, revision })
+            .collect()
+    }
+
+    fn path(&self, origin: IVec2, revision: u64) -> PathBuf {
+        self.dir
+            .join(format!("{}.{}.{revision}.chunk", origin.x, origin.y))
+    }
+}
+
+/// Parse the origin and revision from the name of a chunk file.
+fn parse_file_name(name: &str) -> Option<(IVec2, u64)> {
+    let mut parts = name.strip_suffix(".chunk")?.split('.');
+    let x = parts.next()?.parse().ok()?;
+    let z = parts.next()?.parse().ok()?;
+    let revision = parts.next()?.parse().ok()?;
+    parts.next().is_none().then_some((ivec2(x, z), revision))
+}
+
+/// Delete the least recently used chunk files of all servers until they fit in the budget.
+fn trim_disk_cache(root: &Path, budget: u64) {
+    let mut files = Vec::new();
+    let mut total = 0;
+    for server in fs::read_dir(root).into_iter().flatten().flatten() {
+        for entry in fs::read_dir(server.path()).into_iter().flatten().flatten() {
+            let Ok(meta) = entry.metadata() else {
+                continue;
+            };
+            let modified = meta.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+            total += meta.len();
+            files.push((modified, meta.len(), entry.path()));
+        }
+    }
+
+    if total <= budget {
+        return;
+    }
+
+    files.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+    for (_, len, path) in files {
+        if total <= budget {
+            break;
+        }
+        if fs::remove_file(&path).is_ok() {
+            total -= len;
+        }
+    }
+}
+
+/// Open the disk cache of the server when joining it, and send the server
+/// the revisions of the cached chunks. Singleplayer worlds are already on
+/// disk, so they aren't cached.
+pub fn open_disk_cache(
+    root: Res<RootPath>,
+    info: Option<Res<ConnectSeqInfo>>,
+    singleplayer: Option<Res<Singleplayer>>,
+    channels: Res<Registry<Channel>>,
+    client: Option<ResMut<Client>>,
+    mut cache: ResMut<ChunkCache>,
+) {
+    let (Some(info), Some(mut client)) = (info, client) else {
+        return;
+    };
+    if singleplayer.is_some() {
+        return;
+    }
+
+    let dir = root.join("cache/chunks");
+    trim_disk_cache(&dir, DISK_BUDGET);
+    let disk = match DiskCache::open(&dir, &info.addr_string) {
+        Ok(disk) => disk,
+        Err(e) => {
+            warn!(
+                "[C183] Failed to open chunk cache: '{}' with error: '{e}'",
+                dir.display()
+            );
+            return;
+        }
+    };
+
+    let revisions = disk.revisions(MAX_JOIN_REVISIONS);
+    let channel = channels.resolve("chunk-revisions").unwrap().into();
+    for batch in revisions.chunks(MAX_REVISIONS_PER_PACKET) {
+        client.tcp_send(channel, bytemuck::cast_slice(batch));
+    }
+
+    cache.set_disk(disk);
+}
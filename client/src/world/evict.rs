@@ -0,0 +1,122 @@
+//! Dropping world data the client no longer needs.
+//!
+//! Chunks are unloaded when the server says they left its draw distance, or when
+//! they are further from the player than the render distance. Regions without any
+//! loaded chunks are removed from the World, and the number of regions is capped,
+//! so the client doesn't keep every chunk it was ever sent.
+
+use bevy::prelude::*;
+use data::registry::Registry;
+use protocol::types::ChunkUnload;
+use world::{World, region::chunk::flags::ChunkState};
+
+use crate::{
+    events::ChunkUnloaded, net::channel::Channel, player::PlayerBody,
+    render::chunk::ChunkRenderQueue, settings::Settings,
+};
+
+/// Chunks are kept this many chunks beyond the render distance, so terrain on
+/// the edge isn't dropped and received again while moving back and forth.
+const EVICTION_MARGIN: i32 = 2;
+
+/// Most regions the client world may hold. Past this, the regions furthest from
+/// the player are dropped, even if they have loaded chunks. The largest render
+/// distance needs at most 25 regions.
+const REGION_BUDGET: usize = 36;
+
+/// Unload the chunks the server says left the player's draw distance.
+pub fn recv_chunk_unloads(
+    channels: Res<Registry<Channel>>,
+    mut world: ResMut<World>,
+    mut unloaded: MessageWriter<ChunkUnloaded>,
+) {
+    let channel = channels.get_by_name("chunk-unload").unwrap();
+    for packet in channel.recv() {
+        let Some(unload) = packet.cast::<ChunkUnload>() else {
+            continue;
+        };
+
+        if let Some(chunk) = world.get_chunk_mut(unload.origin)
+            && chunk.load_state() == ChunkState::Loaded
+        {
+            chunk.unload();
+            unloaded.write(ChunkUnloaded {
+                origin: chunk.origin().xz(),
+            });
+        }
+    }
+}
+
+/// Unload chunks further from the player than the render distance, and remove
+/// regions that have no loaded chunks or are over the region budget.
+pub fn evict_distant_chunks(
+    settings: Res<Settings>,
+    player: Single<&Transform, With<PlayerBody>>,
+    mut world: ResMut<World>,
+    mut unloaded: MessageWriter<ChunkUnloaded>,
+) {
+    let center = player.translation.as_ivec3().xz();
+    let range = (settings.render_distance as i32 + EVICTION_MARGIN) * 32;
+
+    let mut empty = Vec::new();
+    for region in world.regions_mut() {
+        let mut any_loaded = false;
+        for chunk in region.chunks_mut() {
+            if chunk.load_state() != ChunkState::Loaded {
+                continue;
+            }
+
+            let origin = chunk.origin().xz();
+            if (origin + 16).chebyshev_distance(center) as i32 > range {
+                chunk.unload();
+                unloaded.write(ChunkUnloaded { origin });
+            } else {
+                any_loaded = true;
+            }
+        }
+
+        if !any_loaded {
+            empty.push(region.id());
+        }
+    }
+
+    for id in empty {
+        world.remove(id);
+    }
+
+    if world.num_regions() > REGION_BUDGET {
+        let mut furthest = world
+            .regions()
+            .map(|region| {
+                let dist = (region.id().as_ivec2() + 256).chebyshev_distance(center);
+                (region.id(), dist)
+            })
+            .collect::<Vec<_>>();
+        furthest.sort_unstable_by_key(|(_, dist)| std::cmp::Reverse(*dist));
+
+        let excess = world.num_regions() - REGION_BUDGET;
+        for (id, _) in furthest.into_iter().take(excess) {
+            let Some(region) = world.remove(id) else {
+                continue;
+            };
+
+            for chunk in region.chunks() {
+                if chunk.load_state() == ChunkState::Loaded {
+                    unloaded.write(ChunkUnloaded {
+                        origin: chunk.origin().xz(),
+                    });
+                }
+            }
+        }
+
+        warn!(
+            "[C181] Client world was over its budget of {REGION_BUDGET} regions, dropped {excess}."
+        );
+    }
+}
+
+/// Drop all world data, should run when leaving the game.
+pub fn clear_world(mut world: ResMut<World>, mut queue: ResMut<ChunkRenderQueue>) {
+    world.clear();
+    *queue = ChunkRenderQueue::default();
+}
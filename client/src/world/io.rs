@@ -1,19 +1,46 @@
 use bevy::prelude::*;
 use data::registry::Registry;
-use world::region::format::UnzippedChunk;
+use world::region::{chunk::flags::ChunkState, format::UnzippedChunk};
 
-use crate::{net::channel::Channel, render::chunk::ChunkRenderQueue};
+use crate::{net::channel::Channel, render::chunk::ChunkRenderQueue, world::cache::ChunkCache};
 use ::world::World;
 
 pub fn recv_chunk_data(
     channels: Res<Registry<Channel>>,
     mut world: ResMut<World>,
     mut queue: ResMut<ChunkRenderQueue>,
+    mut cache: ResMut<ChunkCache>,
 ) {
     let channel = channels.get_by_name("chunk-data").unwrap();
     for packet in channel.recv() {
         let unzip = UnzippedChunk::unzip(&packet.payload).unwrap();
-        let success = world.read_unzipped_chunk(unzip, true).unwrap();
-        queue.add(success.origin.xz());
+        let (origin, revision) = load_chunk(unzip, &mut world, &mut queue);
+        cache.insert(origin, revision, &packet.payload);
     }
 }
+
+/// Read unzipped chunk data into the world and queue it for rendering,
+/// returning the origin and revision of the chunk.
+pub fn load_chunk(
+    unzip: UnzippedChunk,
+    world: &mut World,
+    queue: &mut ChunkRenderQueue,
+) -> (IVec2, u64) {
+    let revision = unzip.header().map_or(0, |header| header.revision);
+    let success = world.read_unzipped_chunk(unzip, true).unwrap();
+    let origin = success.origin.xz();
+    queue.add(origin, world);
+
+    // faces on the borders of loaded neighbours may now be hidden.
+    for offs in [IVec2::X, IVec2::NEG_X, IVec2::Y, IVec2::NEG_Y] {
+        let neighbour = origin + offs * 32;
+        if world
+            .get_chunk(neighbour)
+            .is_some_and(|chunk| chunk.load_state() == ChunkState::Loaded)
+        {
+            queue.add(neighbour, world);
+        }
+    }
+
+    (origin, revision)
+}
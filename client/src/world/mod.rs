@@ -1 +1,7 @@
+pub mod blocks;
+pub mod cache;
+pub mod disk_cache;
+pub mod evict;
 pub mod io;
+pub mod requests;
+pub mod time;
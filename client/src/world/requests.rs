@@ -0,0 +1,103 @@
+//! Asking the server for chunks the client is missing.
+//!
+//! The server sends chunks on its own as the player moves, but chunks can go missing,
+//! for example when they were evicted and the player walked back. The client walks
+//! outwards from the player's chunk and requests the closest chunks it doesn't have,
+//! with only a few requests in flight at once so the server isn't flooded.
+
+use bevy::prelude::*;
+use data::registry::Registry;
+use fxhash::FxHashMap;
+use math::space::spiral::Spiral;
+use protocol::types::ChunkRequest;
+use world::{World, region::chunk::flags::ChunkState};
+
+use crate::{
+    net::{Client, channel::Channel},
+    player::PlayerBody,
+    settings::Settings,
+};
+
+/// Most requests waiting on a response at once.
+const MAX_IN_FLIGHT: usize = 8;
+
+/// Seconds before a request with no response is sent again.
+const REQUEST_TIMEOUT: f32 = 5.0;
+
+/// Chunks the client has requested and is waiting on.
+#[derive(Resource, Default)]
+pub struct ChunkRequests {
+    /// Origins of the requested chunks, and when they were requested.
+    pending: FxHashMap<IVec2, f32>,
+
+    /// Chunk offsets around the player in the order they are requested,
+    /// and the render distance they were built for.
+    spiral: Vec<IVec2>,
+    radius: u32,
+}
+
+impl ChunkRequests {
+    /// Number of requests waiting on a response.
+    pub fn num_pending(&self) -> usize {
+        self.pending.len()
+    }
+}
+
+/// Request the missing chunks closest to the player.
+pub fn request_missing_chunks(
+    time: Res<Time>,
+    settings: Res<Settings>,
+    world: Res<World>,
+    channels: Res<Registry<Channel>>,
+    player: Single<&Transform, With<PlayerBody>>,
+    client: Option<ResMut<Client>>,
+    mut requests: ResMut<ChunkRequests>,
+) {
+    let Some(mut client) = client else {
+        return;
+    };
+
+    if requests.spiral.is_empty() || requests.radius != settings.render_distance {
+        requests.radius = settings.render_distance;
+        requests.spiral = Spiral::new(settings.render_distance).collect();
+    }
+
+    let now = time.elapsed_secs();
+    let is_loaded = |origin: IVec2| {
+        world
+            .get_chunk(origin)
+            .is_some_and(|chunk| chunk.load_state() == ChunkState::Loaded)
+    };
+
+    requests
+        .pending
+        .retain(|origin, at| !is_loaded(*origin) && now - *at < REQUEST_TIMEOUT);
+
+    if requests.pending.len() >= MAX_IN_FLIGHT {
+        return;
+    }
+
+    let center = player.translation.as_ivec3().xz() >> 5;
+    let channel = channels.resolve("chunk-request").unwrap().into();
+    let ChunkRequests {
+        pending, spiral, ..
+    } = &mut *requests;
+
+    for offset in spiral.iter() {
+        let origin = (center + offset) << 5;
+        if pending.contains_key(&origin) || is_loaded(origin) {
+            continue;
+        }
+
+        client.tcp_send(channel, bytemuck::bytes_of(&ChunkRequest { origin }));
+        pending.insert(origin, now);
+        if pending.len() >= MAX_IN_FLIGHT {
+            break;
+        }
+    }
+}
+
+/// Forget pending requests, should run when leaving the game.
+pub fn reset_chunk_requests(mut requests: ResMut<ChunkRequests>) {
+    requests.pending.clear();
+}
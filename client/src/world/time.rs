@@ -0,0 +1,62 @@
+//! Keeping the world time in sync with the server.
+//!
+//! The server sends the time when the player joins and every few seconds after,
+//! and the client advances it at the tick rate in between. The darkness of the sky is
+//! sent with the time, and follows the time of day in between.
+
+use bevy::prelude::*;
+use data::registry::Registry;
+use protocol::types::WorldTimeSync;
+use world::time::{TICKS_PER_SECOND, WorldTime};
+
+use crate::net::channel::Channel;
+
+/// Fraction of the sky light taken away, used by the chunk shader.
+#[derive(Resource, Default)]
+pub struct SkyDarkness {
+    pub value: f32,
+
+    /// Difference between the darkness sent by the server and the darkness of the
+    /// time of day, kept between syncs in case the server darkens the sky more.
+    offset: f32,
+}
+
+/// Set the world time and sky darkness to the ones sent by the server.
+pub fn recv_world_time(
+    channels: Res<Registry<Channel>>,
+    mut time: ResMut<WorldTime>,
+    mut darkness: ResMut<SkyDarkness>,
+) {
+    let channel = channels.get_by_name("world-time").unwrap();
+    for packet in channel.recv() {
+        if let Some(sync) = packet.cast::<WorldTimeSync>() {
+            time.tick = sync.tick;
+            darkness.offset = sync.sky_darkness - time.sky_darkness();
+        }
+    }
+}
+
+/// Advance the world time between syncs, one tick per 1/30th of a second.
+pub fn advance_world_time(
+    real: Res<Time>,
+    mut time: ResMut<WorldTime>,
+    mut accumulated: Local<f32>,
+) {
+    let tick_secs = 1.0 / TICKS_PER_SECOND as f32;
+    *accumulated += real.delta_secs();
+    while *accumulated >= tick_secs {
+        *accumulated -= tick_secs;
+        time.tick += 1;
+    }
+}
+
+/// Darken the sky for the time of day.
+pub fn update_sky_darkness(time: Res<WorldTime>, mut darkness: ResMut<SkyDarkness>) {
+    darkness.value = (time.sky_darkness() + darkness.offset).clamp(0.0, 1.0);
+}
+
+/// Reset the world time, should run when leaving the game.
+pub fn reset_world_time(mut time: ResMut<WorldTime>, mut darkness: ResMut<SkyDarkness>) {
+    *time = WorldTime::default();
+    *darkness = SkyDarkness::default();
+}
@@ -0,0 +1,842 @@
+//! Block definitions, read from the `blocks` folder of asset packs.
+//!
+//! Each block is a JSON file named after the block, like `blocks/stone.json`:
+//!
+//! ```json
+//! {
+//!     "hardness": 1.5,
+//!     "model": "full",
+//!     "textures": { "all": "textures/blocks/stone.png" }
+//! }
+//! ```
+//!
+//! Every field is optional. Textures of a full cube are looked up by side ("up",
+//! "north", ...), then "side" for the horizontal sides, then "all". Faces of elements
+//! name a texture path, or a key of `textures` with a leading '#'.
+//!
+//! A block with `properties` has a state for every combination of their values.
+//! `states` change the fields of the states that match a list of property values,
+//! so a stair only needs its model facing east, and a rotation for the other sides:
+//!
+//! ```json
+//! {
+//!     "properties": { "facing": ["east", "north", "west", "south"] },
+//!     "states": {
+//!         "facing=north": { "rotation": 90 },
+//!         "facing=west": { "rotation": 180 },
+//!         "facing=south": { "rotation": 270 }
+//!     }
+//! }
+//! ```
+//!
+//! A block with `growth` grows on random ticks. Each time it grows, its `property` is set
+//! to the next value, and once it has the last value, or if it doesn't set a property, it's
+//! replaced by the blocks of its `structure` template, like a sapling growing into a tree:
+//!
+//! ```json
+//! {
+//!     "model": "cross",
+//!     "textures": { "all": "textures/blocks/wheat.png" },
+//!     "properties": { "age": ["0", "1", "2", "3"] },
+//!     "growth": { "property": "age", "chance": 0.25, "min_light": 9, "soil": ["farmland"] }
+//! }
+//! ```
+
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use math::axis::{Axis, AxisArray};
+use serde::Deserialize;
+
+use crate::{
+    blocks::variant::{MAX_STATES, Property, Variants},
+    blockstates::{
+        BlockState, Transparency,
+        coverage::Coverages,
+        element::{Element, ElementFace},
+    },
+    fs::path::{FileExt, iter_files_in_dir},
+};
+
+/// Name reserved for the empty block, which is always the first block state.
+pub const AIR: &str = "air";
+
+/// Hardness of blocks that don't set it.
+pub const DEFAULT_HARDNESS: f32 = 1.0;
+
+/// A block, or one of its states, as it's written in a definition file.
+#[derive(Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct BlockDef {
+    /// Name of the file, without the extension.
+    #[serde(skip)]
+    pub name: String,
+
+    pub transparency: Option<Transparency>,
+
+    /// How long the block takes to break.
+    pub hardness: Option<f32>,
+
+    /// Liquids can be swum through, and don't block movement.
+    pub liquid: Option<bool>,
+
+    pub coverage: Option<CoverageDef>,
+    pub model: Option<ModelKind>,
+
+    /// Texture paths by side or key. States add to the textures of the block.
+    #[serde(default)]
+    pub textures: BTreeMap<String, String>,
+
+    /// Boxes of the "elements" model.
+    pub elements: Option<Vec<ElementDef>>,
+
+    /// Degrees the model is turned around the y axis, so a model facing east faces
+    /// north when turned by 90. Must be a multiple of 90.
+    pub rotation: Option<u16>,
+
+    /// Properties of the block, and the values each can have.
+    #[serde(default)]
+    pub properties: BTreeMap<String, Vec<String>>,
+
+    /// Fields of the states that match a list of property values, like "half=top,facing=east".
+    /// Every matching entry is applied, in order of their keys.
+    #[serde(default)]
+    pub states: BTreeMap<String, BlockDef>,
+
+    /// How the block grows on random ticks. Only the block can set it, not its states.
+    pub growth: Option<GrowthDef>,
+}
+
+/// How a block grows, checked each time one of its voxels gets a random tick.
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct GrowthDef {
+    /// Property set to its next value when the block grows, like "age" of a crop.
+    pub property: Option<String>,
+
+    /// Name of the structure template the block is replaced by once it's fully grown.
+    pub structure: Option<String>,
+
+    /// Chance of growing on each random tick, between 0 and 1.
+    #[serde(default = "GrowthDef::always")]
+    pub chance: f32,
+
+    /// Least light level the block needs to grow, from 0 to 15.
+    #[serde(default)]
+    pub min_light: u8,
+
+    /// Block names, or block tags with a leading '#', of which one must be below
+    /// the block for it to grow. Any block will do if it's empty.
+    #[serde(default)]
+    pub soil: Vec<String>,
+}
+
+impl GrowthDef {
+    const fn always() -> f32 {
+        1.0
+    }
+
+    fn validate(&self, properties: &BTreeMap<String, Vec<String>>) -> Result<(), String> {
+        if self.property.is_none() && self.structure.is_none() {
+            return Err(
+                "growth needs a property to step, a structure to grow into, or both".into(),
+            );
+        }
+
+        if let Some(property) = &self.property
+            && !properties.contains_key(property)
+        {
+            return Err(format!("growth steps the unknown property '{property}'"));
+        }
+
+        if !(0.0..=1.0).contains(&self.chance) {
+            return Err(format!(
+                "growth chance must be between 0 and 1, found {}",
+                self.chance
+            ));
+        }
+
+        if self.min_light > 15 {
+            return Err(format!(
+                "growth min_light must be at most 15, found {}",
+                self.min_light
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Deserialize, Copy, Clone, Debug, Default, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ModelKind {
+    #[default]
+    Empty,
+    Full,
+    Cross,
+    Elements,
+}
+
+/// How the block hides the faces of its neighbours.
+#[derive(Deserialize, Copy, Clone, Debug, Default, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum CoverageDef {
+    /// Computed from the model.
+    #[default]
+    Auto,
+
+    /// Never hides its neighbours, like leaves that should show the leaves behind them.
+    None,
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ElementDef {
+    /// Minimum corner of the box, in 1/16ths of a voxel.
+    pub from: [i16; 3],
+
+    /// Maximum corner of the box, in 1/16ths of a voxel.
+    pub to: [i16; 3],
+
+    /// Faces of the box by side. Sides that aren't listed are not drawn.
+    #[serde(default)]
+    pub faces: BTreeMap<String, FaceDef>,
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(untagged)]
+pub enum FaceDef {
+    Texture(String),
+    Mapped {
+        texture: String,
+
+        /// Region of the texture as `[u0, v0, u1, v1]`, in 1/16ths of the texture.
+        uv: Option<[u8; 4]>,
+    },
+}
+
+impl FaceDef {
+    fn texture(&self) -> &str {
+        match self {
+            Self::Texture(texture) | Self::Mapped { texture, .. } => texture,
+        }
+    }
+
+    fn uv(&self) -> Option<[u8; 4]> {
+        match self {
+            Self::Texture(_) => None,
+            Self::Mapped { uv, .. } => *uv,
+        }
+    }
+}
+
+impl BlockDef {
+    /// Parse and validate the definition of a block.
+    pub fn parse(name: impl Into<String>, data: &[u8]) -> Result<Self, BlockDefErrorKind> {
+        let mut def = serde_json::from_slice::<Self>(data).map_err(BlockDefErrorKind::Parse)?;
+        def.name = name.into();
+        def.validate()?;
+        Ok(def)
+    }
+
+    /// The properties of the block.
+    pub fn variants(&self) -> Variants {
+        Variants::new(
+            self.properties
+                .iter()
+                .map(|(name, values)| Property {
+                    name: name.clone(),
+                    values: values.clone(),
+                })
+                .collect(),
+        )
+    }
+
+    /// Every state of the block in registry order, with their registry name and
+    /// property bits. A block without properties has one state named after the block,
+    /// the others are named like "stair[facing=north,half=top]".
+    pub fn expand(&self) -> Vec<(String, u32, BlockDef)> {
+        let variants = self.variants();
+        let selectors = self
+            .states
+            .iter()
+            .filter_map(|(selector, state)| Some((variants.parse_selector(selector).ok()?, state)))
+            .collect::<Vec<_>>();
+
+        (0..variants.num_states())
+            .map(|index| {
+                let bits = variants.index_to_bits(index);
+                let name = match self.properties.is_empty() {
+                    true => self.name.clone(),
+                    false => format!("{}[{}]", self.name, variants.state_name(bits)),
+                };
+
+                let state = selectors
+                    .iter()
+                    .filter(|(selector, _)| variants.matches(bits, selector))
+                    .fold(self.with(&BlockDef::default()), |def, (_, state)| {
+                        def.with(state)
+                    });
+                (name, bits, state)
+            })
+            .collect()
+    }
+
+    /// This block with the fields set by one of its states.
+    fn with(&self, state: &BlockDef) -> BlockDef {
+        let mut textures = self.textures.clone();
+        textures.extend(state.textures.clone());
+        BlockDef {
+            name: self.name.clone(),
+            transparency: state.transparency.or(self.transparency),
+            hardness: state.hardness.or(self.hardness),
+            liquid: state.liquid.or(self.liquid),
+            coverage: state.coverage.or(self.coverage),
+            model: state.model.or(self.model),
+            textures,
+            elements: state.elements.clone().or_else(|| self.elements.clone()),
+            rotation: state.rotation.or(self.rotation),
+            properties: BTreeMap::new(),
+            states: BTreeMap::new(),
+            growth: None,
+        }
+    }
+
+    /// Number of quarter turns of the model.
+    fn turns(&self) -> u16 {
+        self.rotation.unwrap_or(0) / 90 % 4
+    }
+
+    /// Texture path of a side of a full cube.
+    fn side_texture(&self, axis: Axis) -> Option<&str> {
+        let horizontal = matches!(axis.abs(), Axis::PosX | Axis::PosZ);
+        self.textures
+            .get(axis.as_dir_str())
+            .or_else(|| horizontal.then(|| self.textures.get("side")).flatten())
+            .or_else(|| self.textures.get("all"))
+            .map(String::as_str)
+    }
+
+    /// Texture path of a cross.
+    fn cross_texture(&self) -> Option<&str> {
+        self.textures
+            .get("cross")
+            .or_else(|| self.textures.get("all"))
+            .map(String::as_str)
+    }
+
+    /// Texture path of a face of an element, following '#' references into `textures`.
+    fn face_texture<'a>(&'a self, face: &'a FaceDef) -> Option<&'a str> {
+        match face.texture().strip_prefix('#') {
+            Some(key) => self.textures.get(key).map(String::as_str),
+            None => Some(face.texture()),
+        }
+    }
+
+    /// Paths of every texture used by a state of the block.
+    pub fn textures(&self) -> Vec<&str> {
+        let mut textures = Vec::new();
+        for state in std::iter::once(self).chain(self.states.values()) {
+            textures.extend(state.textures.values().map(String::as_str));
+            for element in state.elements.iter().flatten() {
+                textures.extend(
+                    element
+                        .faces
+                        .values()
+                        .filter(|face| !face.texture().starts_with('#'))
+                        .map(FaceDef::texture),
+                );
+            }
+        }
+        textures
+    }
+
+    /// Build the block state of a state returned by `BlockDef::expand`.
+    /// Texture paths are turned into indices of the texture array with `texture`.
+    pub fn build_state(&self, mut texture: impl FnMut(&str) -> u16) -> BlockState {
+        let transparency = self.transparency.unwrap_or_default();
+        let turns = self.turns();
+        let mut state = match self.model.unwrap_or_default() {
+            ModelKind::Empty => BlockState::empty(),
+            ModelKind::Full => BlockState::full(
+                AxisArray::from_fn(|axis| {
+                    // the side that is turned to face this axis.
+                    let side = rotate_axis(axis, 4 - turns);
+                    self.side_texture(side).map_or(0, &mut texture)
+                }),
+                transparency,
+            ),
+            ModelKind::Cross => {
+                BlockState::cross(self.cross_texture().map_or(0, texture), transparency)
+            }
+            ModelKind::Elements => BlockState::elements(
+                self.elements
+                    .iter()
+                    .flatten()
+                    .map(|element| {
+                        let mut faces = AxisArray::new([None; 6]);
+                        for (side, face) in &element.faces {
+                            if let Some(axis) = Axis::from_str(side) {
+                                faces[axis] = Some(ElementFace {
+                                    texture: self.face_texture(face).map_or(0, &mut texture),
+                                    uv: face.uv(),
+                                });
+                            }
+                        }
+
+                        rotate_element(
+                            Element {
+                                from: element.from,
+                                to: element.to,
+                                faces,
+                            },
+                            turns,
+                        )
+                    })
+                    .collect(),
+                transparency,
+            ),
+        };
+
+        if self.coverage == Some(CoverageDef::None) {
+            state.coverages = Coverages::empty();
+        }
+        state.liquid = self.liquid.unwrap_or(false);
+        state.hardness = self.hardness.unwrap_or(DEFAULT_HARDNESS);
+        state
+    }
+
+    /// Check the definition for anything that can't be turned into a block state.
+    fn validate(&self) -> Result<(), BlockDefErrorKind> {
+        let invalid = |msg: String| Err(BlockDefErrorKind::Invalid(msg));
+
+        if self.name == AIR {
+            return invalid(format!("the name '{AIR}' is reserved for the empty block"));
+        }
+
+        if self.name.is_empty()
+            || !self
+                .name
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+        {
+            return invalid(format!(
+                "block names may only have lowercase letters, digits and '_', found '{}'",
+                self.name
+            ));
+        }
+
+        for (name, values) in &self.properties {
+            if name.is_empty() || values.is_empty() {
+                return invalid(format!(
+                    "property '{name}' needs a name and at least one value"
+                ));
+            }
+
+            if let Some(value) = values
+                .iter()
+                .enumerate()
+                .find_map(|(i, value)| values[..i].contains(value).then_some(value))
+            {
+                return invalid(format!("property '{name}' has the value '{value}' twice"));
+            }
+        }
+
+        let variants = self.variants();
+        if variants.num_states() > MAX_STATES {
+            return invalid(format!(
+                "the properties make {} states, at most {MAX_STATES} are allowed",
+                variants.num_states()
+            ));
+        }
+
+        for (selector, state) in &self.states {
+            if let Err(msg) = variants.parse_selector(selector) {
+                return invalid(format!("state '{selector}': {msg}"));
+            }
+
+            if !state.states.is_empty() || !state.properties.is_empty() || state.growth.is_some() {
+                return invalid(format!(
+                    "state '{selector}' can't have states, properties or growth of its own"
+                ));
+            }
+        }
+
+        if let Some(growth) = &self.growth {
+            growth
+                .validate(&self.properties)
+                .map_err(BlockDefErrorKind::Invalid)?;
+        }
+
+        for (name, _, state) in self.expand() {
+            state
+                .validate_state()
+                .map_err(|msg| BlockDefErrorKind::Invalid(format!("{name}: {msg}")))?;
+        }
+
+        Ok(())
+    }
+
+    /// Check a state returned by `BlockDef::expand`.
+    fn validate_state(&self) -> Result<(), String> {
+        if let Some(rotation) = self.rotation
+            && rotation % 90 != 0
+        {
+            return Err(format!(
+                "rotation must be a multiple of 90, found {rotation}"
+            ));
+        }
+
+        if let Some(hardness) = self.hardness
+            && !(hardness.is_finite() && hardness >= 0.0)
+        {
+            return Err(format!(
+                "hardness must be a positive number, found {hardness}"
+            ));
+        }
+
+        match self.model.unwrap_or_default() {
+            ModelKind::Empty => {}
+            ModelKind::Full => {
+                for axis in Axis::ALL {
+                    if self.side_texture(axis).is_none() {
+                        return Err(format!(
+                            "no texture for side '{}', set it, \"side\" or \"all\"",
+                            axis.as_dir_str()
+                        ));
+                    }
+                }
+            }
+            ModelKind::Cross => {
+                if self.cross_texture().is_none() {
+                    return Err("no texture for the cross, set \"cross\" or \"all\"".into());
+                }
+            }
+            ModelKind::Elements => {
+                let elements = self.elements.as_deref().unwrap_or_default();
+                if elements.is_empty() {
+                    return Err("the \"elements\" model needs at least one element".into());
+                }
+
+                for (i, element) in elements.iter().enumerate() {
+                    if (0..3).any(|d| {
+                        element.from[d] < 0
+                            || element.to[d] > 16
+                            || element.from[d] >= element.to[d]
+                    }) {
+                        return Err(format!(
+                            "element {i} must have 0 <= from < to <= 16 on every axis"
+                        ));
+                    }
+
+                    for (side, face) in &element.faces {
+                        if Axis::from_str(side).is_none() {
+                            return Err(format!("element {i} has a face on unknown side '{side}'"));
+                        }
+
+                        if self.face_texture(face).is_none() {
+                            return Err(format!(
+                                "element {i} uses texture '{}', which isn't in \"textures\"",
+                                face.texture()
+                            ));
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The side that a side of a model faces after it's turned by quarter turns.
+fn rotate_axis(axis: Axis, turns: u16) -> Axis {
+    (0..turns % 4).fold(axis, |axis, _| match axis {
+        Axis::PosX => Axis::PosZ,
+        Axis::PosZ => Axis::NegX,
+        Axis::NegX => Axis::NegZ,
+        Axis::NegZ => Axis::PosX,
+        vertical => vertical,
+    })
+}
+
+/// Turn an element around the center of the block, by quarter turns.
+fn rotate_element(mut element: Element, turns: u16) -> Element {
+    for _ in 0..turns % 4 {
+        let (from, to) = (element.from, element.to);
+        element.from = [16 - to[2], from[1], from[0]];
+        element.to = [16 - from[2], to[1], to[0]];
+
+        let faces = element.faces;
+        for axis in Axis::ALL {
+            element.faces[rotate_axis(axis, 1)] = faces[axis];
+        }
+    }
+    element
+}
+
+/// Read every block definition in the `blocks` folder of these directories.
+///
+/// Directories earlier in the list replace the blocks of later directories with
+/// the same name. The definitions are sorted by name, and files that failed to
+/// load are returned as errors instead.
+pub fn read_block_defs<P: AsRef<Path>>(dirs: &[P]) -> (Vec<BlockDef>, Vec<BlockDefError>) {
+    let mut defs = BTreeMap::<String, BlockDef>::new();
+    let mut errors = Vec::new();
+
+    for dir in dirs.iter().rev() {
+        // most packs only replace textures, and don't have any blocks.
+        let folder = dir.as_ref().join("blocks");
+        if !folder.is_dir() {
+            continue;
+        }
+
+        for path in iter_files_in_dir(&folder) {
+            if FileExt::from(&path) != FileExt::Json {
+                continue;
+            }
+
+            match read_block_def(&path) {
+                Ok(def) => {
+                    defs.insert(def.name.clone(), def);
+                }
+                Err(kind) => errors.push(BlockDefError { path, kind }),
+            }
+        }
+    }
+
+    (defs.into_values().collect(), errors)
+}
+
+fn read_block_def(path: &Path) -> Result<BlockDef, BlockDefErrorKind> {
+    let name = path
+        .file_stem()
+        .and_then(|name| name.to_str())
+        .unwrap_or_default();
+    let data = fs::read(path).map_err(BlockDefErrorKind::Read)?;
+    BlockDef::parse(name, &data)
+}
+
+/// A block definition file that couldn't be loaded.
+#[derive(thiserror::Error, Debug)]
+#[error("{} (in '{}')", .kind, .path.display())]
+pub struct BlockDefError {
+    pub path: PathBuf,
+    pub kind: BlockDefErrorKind,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum BlockDefErrorKind {
+    #[error("[D210] Failed to read block definition: {0}")]
+    Read(io::Error),
+
+    #[error("[D211] Block definition is not valid JSON: {0}")]
+    Parse(serde_json::Error),
+
+    #[error("[D212] Invalid block definition: {0}")]
+    Invalid(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{blockstates::ModelData, registry::Registry, util::TempDir};
+
+    #[test]
+    fn full_block_textures_fall_back_to_side_and_all() {
+        let def = BlockDef::parse(
+            "log",
+            br#"{
+                "model": "full",
+                "hardness": 2.0,
+                "textures": { "up": "top.png", "side": "bark.png", "all": "rings.png" }
+            }"#,
+        )
+        .unwrap();
+
+        let names = ["top.png", "bark.png", "rings.png"];
+        let state = def.build_state(|path| names.iter().position(|n| *n == path).unwrap() as u16);
+        let ModelData::Full { textures } = state.model else {
+            panic!("expected a full model");
+        };
+        assert_eq!(textures[Axis::PosY], 0);
+        assert_eq!(textures[Axis::PosX], 1);
+        assert_eq!(textures[Axis::NegZ], 1);
+        assert_eq!(textures[Axis::NegY], 2);
+        assert_eq!(state.hardness, 2.0);
+        assert_eq!(state.transparency, Transparency::Opaque);
+    }
+
+    #[test]
+    fn states_replace_fields_of_the_block() {
+        let def = BlockDef::parse(
+            "slab",
+            br##"{
+                "model": "elements",
+                "textures": { "planks": "planks.png" },
+                "elements": [{ "from": [0, 0, 0], "to": [16, 8, 16], "faces": { "up": "#planks" } }],
+                "properties": { "half": ["bottom", "top"] },
+                "states": {
+                    "half=top": {
+                        "elements": [{ "from": [0, 8, 0], "to": [16, 16, 16], "faces": { "down": "#planks" } }]
+                    }
+                }
+            }"##,
+        )
+        .unwrap();
+
+        let states = def.expand();
+        let names = states
+            .iter()
+            .map(|(name, _, _)| name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["slab[half=bottom]", "slab[half=top]"]);
+        assert_eq!(states[0].2.elements.as_ref().unwrap()[0].to[1], 8);
+        assert_eq!(states[1].2.elements.as_ref().unwrap()[0].from[1], 8);
+        assert_eq!(def.variants().get(states[1].1, "half"), Some("top"));
+        assert_eq!(def.textures(), ["planks.png"]);
+    }
+
+    #[test]
+    fn states_are_rotated_by_facing() {
+        let def = BlockDef::parse(
+            "step",
+            br#"{
+                "model": "elements",
+                "elements": [{ "from": [8, 0, 0], "to": [16, 8, 16], "faces": { "east": "front.png", "up": "top.png" } }],
+                "properties": { "facing": ["east", "north", "west"] },
+                "states": {
+                    "facing=north": { "rotation": 90 },
+                    "facing=west": { "rotation": 180 }
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let elements = def
+            .expand()
+            .into_iter()
+            .map(|(_, _, state)| {
+                let ModelData::Elements(elements) = state.build_state(|_| 1).model else {
+                    panic!("expected an elements model");
+                };
+                elements[0].clone()
+            })
+            .collect::<Vec<_>>();
+
+        // east half, north half and west half of the block.
+        assert_eq!((elements[0].from, elements[0].to), ([8, 0, 0], [16, 8, 16]));
+        assert_eq!((elements[1].from, elements[1].to), ([0, 0, 8], [16, 8, 16]));
+        assert_eq!((elements[2].from, elements[2].to), ([0, 0, 0], [8, 8, 16]));
+        assert!(elements[1].faces[Axis::PosZ].is_some());
+        assert!(elements[1].faces[Axis::PosX].is_none());
+        assert!(elements[2].faces[Axis::NegX].is_some());
+        assert!(
+            elements
+                .iter()
+                .all(|element| element.faces[Axis::PosY].is_some())
+        );
+    }
+
+    #[test]
+    fn invalid_definitions_are_rejected() {
+        let invalid = |name: &str, json: &str| {
+            BlockDef::parse(name, json.as_bytes())
+                .unwrap_err()
+                .to_string()
+        };
+
+        assert!(invalid("stone", r#"{ "modle": "full" }"#).starts_with("[D211]"));
+        assert!(invalid("stone", r#"{ "model": "full" }"#).contains("side 'east'"));
+        assert!(invalid("air", "{}").contains("reserved"));
+        assert!(invalid("Stone", "{}").contains("lowercase"));
+        assert!(invalid("grass", r#"{ "model": "cross", "hardness": -1 }"#).contains("hardness"));
+        assert!(
+            invalid(
+                "slab",
+                r#"{ "model": "elements", "elements": [{ "from": [0, 0, 0], "to": [16, 17, 16] }] }"#
+            )
+            .contains("element 0")
+        );
+        assert!(
+            invalid(
+                "slab",
+                r##"{ "model": "elements", "elements": [{ "from": [0, 0, 0], "to": [16, 8, 16], "faces": { "up": "#top" } }] }"##
+            )
+            .contains("'#top'")
+        );
+        assert!(
+            invalid(
+                "lever",
+                r#"{ "properties": { "on": ["false", "true"] }, "states": { "on=yes": {} } }"#
+            )
+            .contains("can't be 'yes'")
+        );
+        assert!(
+            invalid("lever", r#"{ "properties": { "on": ["false", "false"] } }"#).contains("twice")
+        );
+        assert!(invalid("lever", r#"{ "rotation": 45 }"#).contains("multiple of 90"));
+    }
+
+    #[test]
+    fn growth_is_validated() {
+        let parse = |json: &str| BlockDef::parse("wheat", json.as_bytes());
+
+        let def = parse(
+            r##"{ "properties": { "age": ["0", "1"] }, "growth": { "property": "age", "soil": ["#dirt"] } }"##,
+        )
+        .unwrap();
+        let growth = def.growth.as_ref().unwrap();
+        assert_eq!(growth.chance, 1.0);
+        assert_eq!(growth.min_light, 0);
+
+        let (mut states, mut blocks) = (Registry::new(), Registry::new());
+        crate::blocks::register_blocks(&[def.clone()], &mut states, &mut blocks, |_| 0);
+        let block = blocks.get_by_name("wheat").unwrap();
+        assert!(block.growth.is_some());
+        let young = states.get(block.default_state()).unwrap();
+        let grown = states.get(block.with_next(young, "age").unwrap()).unwrap();
+        assert_eq!(block.get(grown, "age"), Some("1"));
+        assert_eq!(block.with_next(grown, "age"), None);
+        assert!(
+            def.expand()
+                .iter()
+                .all(|(_, _, state)| state.growth.is_none())
+        );
+
+        let invalid = |json: &str| parse(json).unwrap_err().to_string();
+        assert!(invalid(r#"{ "growth": {} }"#).contains("or both"));
+        assert!(invalid(r#"{ "growth": { "property": "age" } }"#).contains("'age'"));
+        assert!(invalid(r#"{ "growth": { "structure": "oak", "chance": 2 } }"#).contains("chance"));
+        assert!(invalid(r#"{ "growth": { "structure": "oak", "min_light": 16 } }"#).contains("15"));
+        assert!(
+            invalid(
+                r#"{ "properties": { "age": ["0"] }, "states": { "age=0": { "growth": { "structure": "oak" } } } }"#
+            )
+            .contains("growth of its own")
+        );
+    }
+
+    #[test]
+    fn earlier_directories_replace_blocks_of_later_ones() {
+        let dir = TempDir::new("blocks");
+        let (pack, base) = (dir.join("pack"), dir.join("base"));
+        fs::create_dir_all(pack.join("blocks")).unwrap();
+        fs::create_dir_all(base.join("blocks")).unwrap();
+        fs::write(base.join("blocks/stone.json"), r#"{ "hardness": 1.0 }"#).unwrap();
+        fs::write(base.join("blocks/dirt.json"), r#"{ "hardness": 0.5 }"#).unwrap();
+        fs::write(base.join("blocks/notes.txt"), "not a block").unwrap();
+        fs::write(pack.join("blocks/stone.json"), r#"{ "hardness": 3.0 }"#).unwrap();
+        fs::write(pack.join("blocks/broken.json"), "{").unwrap();
+
+        let (defs, errors) = read_block_defs(&[&pack, &base]);
+        let names = defs.iter().map(|def| def.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, ["dirt", "stone"]);
+        assert_eq!(defs[1].hardness, Some(3.0));
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].path, pack.join("blocks/broken.json"));
+    }
+}
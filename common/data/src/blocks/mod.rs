@@ -1,8 +1,139 @@
 use std::ops::Range;
 
+use variant::Variants;
+
+use crate::{
+    blocks::def::{AIR, BlockDef, GrowthDef},
+    blockstates::BlockState,
+    registry::{Registry, RegistryId},
+};
+
+pub mod def;
 pub mod variant;
 
 pub struct Block {
     /// Range of BlockState indices.
     pub states: Range<usize>,
+
+    /// Properties of the block, which pick one of its states.
+    pub variants: Variants,
+
+    /// How the block grows on random ticks, if it does.
+    pub growth: Option<GrowthDef>,
+}
+
+impl Block {
+    /// The state with every property set to its first value.
+    pub fn default_state(&self) -> RegistryId {
+        RegistryId(self.states.start)
+    }
+
+    /// The state of this block with these property bits.
+    pub fn state(&self, bits: u32) -> RegistryId {
+        RegistryId(self.states.start + self.variants.bits_to_index(bits))
+    }
+
+    /// Value of a property of one of this block's states.
+    pub fn get(&self, state: &BlockState, property: &str) -> Option<&str> {
+        self.variants.get(state.bits, property)
+    }
+
+    /// The state of this block that is like `state`, but with a property set to a value.
+    /// Returns None if the block doesn't have the property, or it can't have the value.
+    pub fn with(&self, state: &BlockState, property: &str, value: &str) -> Option<RegistryId> {
+        let bits = self.variants.with(state.bits, property, value)?;
+        Some(self.state(bits))
+    }
+
+    /// The state of this block that is like `state`, but with a property set to the value
+    /// after its current one. Returns None if the property already has its last value.
+    pub fn with_next(&self, state: &BlockState, property: &str) -> Option<RegistryId> {
+        let def = self
+            .variants
+            .properties()
+            .iter()
+            .find(|p| p.name == property)?;
+        let next = def.index_of(self.get(state, property)?)? + 1;
+        self.with(state, property, def.values.get(next)?)
+    }
+}
+
+impl Registry<Block> {
+    /// Value of a property of a block state, like "facing" of a stair.
+    /// Returns None if the block of the state doesn't have the property.
+    pub fn get_property(&self, state: &BlockState, property: &str) -> Option<&str> {
+        self.get(state.block)?.get(state, property)
+    }
+
+    /// The state that is like `state`, but with a property set to a value.
+    /// The returned id can be turned into the `Voxel` to place.
+    pub fn with_property(
+        &self,
+        state: &BlockState,
+        property: &str,
+        value: &str,
+    ) -> Option<RegistryId> {
+        self.get(state.block)?.with(state, property, value)
+    }
+}
+
+/// Register the states of air and then of every definition, in order, and the blocks
+/// they belong to. `texture` turns a texture path into its index in the texture array.
+///
+/// States that are already registered keep their ids. Returns the names of blocks whose
+/// states didn't get consecutive ids because of that, which happens when the properties of
+/// a block changed since its states were first registered. Their properties may pick the
+/// wrong state.
+pub fn register_blocks(
+    defs: &[BlockDef],
+    states: &mut Registry<BlockState>,
+    blocks: &mut Registry<Block>,
+    mut texture: impl FnMut(&str) -> u16,
+) -> Vec<String> {
+    let mut scattered = Vec::new();
+
+    let air = states.insert(AIR, BlockState::empty()).0;
+    blocks.insert(
+        AIR,
+        Block {
+            states: air..air + 1,
+            variants: Variants::default(),
+            growth: None,
+        },
+    );
+
+    for def in defs {
+        let ids = def
+            .expand()
+            .into_iter()
+            .map(|(name, bits, state)| {
+                let mut state = state.build_state(&mut texture);
+                state.bits = bits;
+                states.insert(name, state)
+            })
+            .collect::<Vec<_>>();
+
+        // states are looked up by their offset from the first state of the block.
+        let start = ids.first().map_or(0, |id| id.0);
+        if ids.iter().enumerate().any(|(i, id)| id.0 != start + i) {
+            scattered.push(def.name.clone());
+        }
+
+        let block = blocks.insert(
+            def.name.clone(),
+            Block {
+                states: start..start + ids.len(),
+                variants: def.variants(),
+                growth: def.growth.clone(),
+            },
+        );
+
+        for id in ids {
+            if let Some(state) = states.get_mut(id) {
+                state.block = block;
+            }
+        }
+    }
+
+    scattered
 }
@@ -1 +1,238 @@
-pub struct Variant {}
+/// Most states a block can have, the product of the number of values of its properties.
+pub const MAX_STATES: usize = 1024;
+
+/// A property of a block, like "facing" or "waterlogged", and the values it can have.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Property {
+    pub name: String,
+    pub values: Vec<String>,
+}
+
+impl Property {
+    /// Number of bits needed to store the index of a value.
+    pub fn width(&self) -> u32 {
+        (self.values.len().max(1) as u32)
+            .next_power_of_two()
+            .ilog2()
+    }
+
+    /// Index of a value of the property.
+    pub fn index_of(&self, value: &str) -> Option<usize> {
+        self.values.iter().position(|v| v == value)
+    }
+}
+
+/// The properties of a block, and how their values are packed into the `bits` of its states.
+///
+/// Each property takes as many bits as the index of its last value needs, in order,
+/// starting from the lowest bit. The states of a block are ordered like numbers with a
+/// digit per property, where the last property changes fastest, and the first state
+/// has the first value of every property.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Variants {
+    properties: Vec<Property>,
+}
+
+/// Value of one property, as indices into `Variants::properties` and `Property::values`.
+pub type Selector = Vec<(usize, usize)>;
+
+impl Variants {
+    pub fn new(properties: Vec<Property>) -> Self {
+        Self { properties }
+    }
+
+    pub fn properties(&self) -> &[Property] {
+        &self.properties
+    }
+
+    /// Number of states, 1 if there are no properties.
+    pub fn num_states(&self) -> usize {
+        self.properties
+            .iter()
+            .map(|property| property.values.len())
+            .product()
+    }
+
+    /// Number of bits used by every property.
+    pub fn width(&self) -> u32 {
+        self.properties.iter().map(Property::width).sum()
+    }
+
+    /// Bit offset and mask of a property.
+    fn field(&self, property: usize) -> (u32, u32) {
+        let offset = self.properties[..property]
+            .iter()
+            .map(Property::width)
+            .sum::<u32>();
+        let width = self.properties[property].width();
+        (offset, (1u32 << width) - 1)
+    }
+
+    /// Index of the value a property has in these bits.
+    pub fn value_index(&self, bits: u32, property: usize) -> usize {
+        let (offset, mask) = self.field(property);
+        ((bits >> offset) & mask) as usize
+    }
+
+    /// Bits with the value of a property replaced by the value at this index.
+    fn with_index(&self, bits: u32, property: usize, value: usize) -> u32 {
+        let (offset, mask) = self.field(property);
+        (bits & !(mask << offset)) | ((value as u32) << offset)
+    }
+
+    /// Value of a property in these bits.
+    /// Returns None if the block doesn't have the property.
+    pub fn get(&self, bits: u32, name: &str) -> Option<&str> {
+        let i = self.properties.iter().position(|p| p.name == name)?;
+        let value = self.value_index(bits, i);
+        self.properties[i].values.get(value).map(String::as_str)
+    }
+
+    /// These bits with a property set to a value.
+    /// Returns None if the block doesn't have the property, or the property can't have the value.
+    pub fn with(&self, bits: u32, name: &str, value: &str) -> Option<u32> {
+        let i = self.properties.iter().position(|p| p.name == name)?;
+        let value = self.properties[i].index_of(value)?;
+        Some(self.with_index(bits, i, value))
+    }
+
+    /// Bits of the state at this index in the states of the block.
+    pub fn index_to_bits(&self, mut index: usize) -> u32 {
+        let mut bits = 0;
+        for (i, property) in self.properties.iter().enumerate().rev() {
+            let len = property.values.len();
+            bits = self.with_index(bits, i, index % len);
+            index /= len;
+        }
+        bits
+    }
+
+    /// Index in the states of the block of the state with these bits.
+    pub fn bits_to_index(&self, bits: u32) -> usize {
+        self.properties
+            .iter()
+            .enumerate()
+            .fold(0, |index, (i, property)| {
+                index * property.values.len() + self.value_index(bits, i)
+            })
+    }
+
+    /// Name of the state with these bits, like "facing=north,half=top".
+    /// Empty if there are no properties.
+    pub fn state_name(&self, bits: u32) -> String {
+        self.properties
+            .iter()
+            .enumerate()
+            .map(|(i, property)| {
+                format!(
+                    "{}={}",
+                    property.name,
+                    property.values[self.value_index(bits, i)]
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    /// Parse a list of property values like "facing=north,half=top".
+    pub fn parse_selector(&self, selector: &str) -> Result<Selector, String> {
+        selector
+            .split(',')
+            .map(|pair| {
+                let Some((name, value)) = pair.split_once('=') else {
+                    return Err(format!("expected 'property=value', found '{pair}'"));
+                };
+
+                let (name, value) = (name.trim(), value.trim());
+                let Some(i) = self.properties.iter().position(|p| p.name == name) else {
+                    return Err(format!("unknown property '{name}'"));
+                };
+
+                let Some(j) = self.properties[i].index_of(value) else {
+                    return Err(format!("property '{name}' can't be '{value}'"));
+                };
+
+                Ok((i, j))
+            })
+            .collect()
+    }
+
+    /// Whether every property of the selector has its value in these bits.
+    pub fn matches(&self, bits: u32, selector: &Selector) -> bool {
+        selector
+            .iter()
+            .all(|(property, value)| self.value_index(bits, *property) == *value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stair() -> Variants {
+        let property = |name: &str, values: &[&str]| Property {
+            name: name.into(),
+            values: values.iter().map(|v| v.to_string()).collect(),
+        };
+
+        Variants::new(vec![
+            property("facing", &["east", "north", "west"]),
+            property("half", &["bottom", "top"]),
+            property("waterlogged", &["false", "true"]),
+        ])
+    }
+
+    #[test]
+    fn bits_round_trip_through_index() {
+        let variants = stair();
+        assert_eq!(variants.num_states(), 12);
+        assert_eq!(variants.width(), 4);
+
+        let mut seen = Vec::new();
+        for index in 0..variants.num_states() {
+            let bits = variants.index_to_bits(index);
+            assert_eq!(variants.bits_to_index(bits), index);
+            assert!(!seen.contains(&bits));
+            seen.push(bits);
+        }
+
+        // the last property changes fastest.
+        assert_eq!(
+            variants.state_name(variants.index_to_bits(1)),
+            "facing=east,half=bottom,waterlogged=true"
+        );
+    }
+
+    #[test]
+    fn get_and_with_properties() {
+        let variants = stair();
+        let bits = variants.index_to_bits(0);
+        assert_eq!(variants.get(bits, "facing"), Some("east"));
+
+        let bits = variants.with(bits, "facing", "west").unwrap();
+        let bits = variants.with(bits, "half", "top").unwrap();
+        assert_eq!(variants.get(bits, "facing"), Some("west"));
+        assert_eq!(variants.get(bits, "half"), Some("top"));
+        assert_eq!(variants.get(bits, "waterlogged"), Some("false"));
+        assert_eq!(variants.get(bits, "color"), None);
+        assert_eq!(variants.with(bits, "facing", "up"), None);
+        assert_eq!(
+            variants.state_name(bits),
+            "facing=west,half=top,waterlogged=false"
+        );
+
+        let selector = variants.parse_selector("half=top, facing=west").unwrap();
+        assert!(variants.matches(bits, &selector));
+        assert!(!variants.matches(variants.index_to_bits(0), &selector));
+        assert!(variants.parse_selector("half=middle").is_err());
+        assert!(variants.parse_selector("shape").is_err());
+    }
+
+    #[test]
+    fn no_properties_has_one_state() {
+        let variants = Variants::default();
+        assert_eq!(variants.num_states(), 1);
+        assert_eq!(variants.index_to_bits(0), 0);
+        assert_eq!(variants.state_name(0), "");
+    }
+}
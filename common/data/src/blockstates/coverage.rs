@@ -1,6 +1,8 @@
 use bevy::ecs::intern::{Internable, Interned, Interner};
 use math::axis::{Axis, AxisArray};
 
+use super::{Transparency, element::Element};
+
 static COVERAGES_INTERNER: Interner<AxisArray<Mask>> = Interner::new();
 
 #[derive(Copy, Clone)]
@@ -21,32 +23,96 @@ impl Coverages {
         }
     }
 
+    /// Coverage of a block that doesn't touch its neighbours, like air or a flower.
+    pub fn empty() -> Self {
+        Self::new(AxisArray::new([0; 6]), AxisArray::new([Mask::EMPTY; 6]))
+    }
+
+    /// Coverage of a full cube.
+    /// Opaque cubes cover every neighbour, transparent cubes only
+    /// cover the faces of neighbours that have the same texture.
+    pub fn full(textures: AxisArray<u16>, transparency: Transparency) -> Self {
+        let textures = match transparency {
+            Transparency::Opaque => AxisArray::new([0; 6]),
+            _ => textures,
+        };
+        Self::new(textures, AxisArray::new([Mask::FULL; 6]))
+    }
+
+    /// Coverage of a block made of elements, like a slab or stair.
+    /// The mask on each side is the union of the faces of the elements
+    /// that lie on the boundary of the block.
+    ///
+    /// Transparent blocks use the texture of the first element face on each side,
+    /// so they only cover neighbours with that texture.
+    pub fn from_elements(elements: &[Element], transparency: Transparency) -> Self {
+        let masks = AxisArray::from_fn(|axis| {
+            elements
+                .iter()
+                .fold(Mask::EMPTY, |mask, element| mask.union(&element.mask(axis)))
+        });
+
+        let textures = AxisArray::from_fn(|axis| match transparency {
+            Transparency::Opaque => 0,
+            _ => elements
+                .iter()
+                .find_map(|element| element.faces[axis].filter(|_| element.is_on_boundary(axis)))
+                .map_or(0, |face| face.texture),
+        });
+
+        Self::new(textures, masks)
+    }
+
+    /// Whether every face of self on this side is hidden by the neighbour on that side.
     #[inline]
     pub fn is_covered_by(&self, other: &Self, axis: Axis) -> bool {
-        let rhs_axis = axis.invert();
         let (lhs_num_bits, lhs_texture) = self.data[axis];
-        let (rhs_num_bits, rhs_texture) = other.data[rhs_axis];
+        lhs_num_bits != 0 && other.occludes_face(axis.invert(), &self.masks[axis], lhs_texture)
+    }
+
+    /// Whether self hides every face of the neighbour on this side of it that faces self.
+    #[inline]
+    pub fn occludes(&self, axis: Axis, neighbor: &Self) -> bool {
+        neighbor.is_covered_by(self, axis.invert())
+    }
+
+    /// Whether self hides a face of the neighbour on this side of it, that covers
+    /// the "mask" of the boundary between them. The texture of the face is 0 if
+    /// the neighbour is opaque, and transparent faces are only hidden by self
+    /// if self is opaque or has the same texture.
+    ///
+    /// Used to cull the faces of elements one by one, like the bottom half of the side
+    /// of a stair next to a slab, when not every face of the side is hidden.
+    pub fn occludes_face(&self, axis: Axis, mask: &Mask, texture: u16) -> bool {
+        let lhs_num_bits = mask.num_covered();
+        let (rhs_num_bits, rhs_texture) = self.data[axis];
         if lhs_num_bits > rhs_num_bits || lhs_num_bits == 0 {
-            // self has more bits than other, and therefore
-            // cannot be covered by other. Or self is empty and
+            // the face has more bits than self, and therefore
+            // cannot be covered by self. Or the face is empty and
             // doesn't interact with the boundary at all.
             false
         } else if rhs_num_bits == 256 {
-            // self is partial or full and other is full.
-            // Covered if other is opaque or self is transparent and they have same texture.
-            lhs_texture == rhs_texture
-        } else if lhs_texture == rhs_texture {
-            // self is partial and other is partial, neither are empty.
-            // if self is opaque and other is transparent, it can't cover.
-            // if self is opaque and other is opaque, check for mask coverage.
-            self.masks[axis].is_covered_by(&other.masks[rhs_axis])
+            // the face is partial or full and self is full.
+            // Covered if self is opaque or the face is transparent and they have same texture.
+            rhs_texture == 0 || texture == rhs_texture
+        } else if texture == rhs_texture {
+            // the face is partial and self is partial, neither are empty.
+            // if the face is opaque and self is transparent, it can't cover.
+            // if the face is opaque and self is opaque, check for mask coverage.
+            mask.is_covered_by(&self.masks[axis])
         } else {
-            // Self and other are known to be partial, neither are empty.
-            // Self and/or other are transparent, but they are not both solid.
-            lhs_texture == 0 && self.masks[axis].is_covered_by(&other.masks[rhs_axis])
+            // The face and self are known to be partial, neither are empty.
+            // The face and/or self are transparent, but they are not both solid.
+            texture == 0 && mask.is_covered_by(&self.masks[axis])
         }
     }
 
+    /// Whether self doesn't cover any part of the boundary on this side.
+    #[inline]
+    pub fn is_empty(&self, axis: Axis) -> bool {
+        self.data[axis].0 == 0
+    }
+
     /// Whether the coverage on this axis is full and equal to this texture.
     pub fn is_full_and_texture(&self, axis: Axis, texture: u16) -> bool {
         let (rhs_num_bits, rhs_texture) = self.data[axis];
@@ -87,7 +153,7 @@ impl Coverage {
         } else if other.num_bits == 256 {
             // self is partial or full and other is full.
             // Covered if other is opaque or self is transparent and they have same texture.
-            self.texture == other.texture
+            other.is_opaque() || self.texture == other.texture
         } else if self.texture == other.texture {
             // self is partial and other is partial, neither are empty.
             // if self is opaque and other is transparent, it can't cover.
@@ -129,6 +195,31 @@ impl Mask {
     /// Quadrant from 8,8 to 16,16
     pub const Q11: Self = Self([0x0, 0x0, 0x00FF_00FF_00FF_00FF, 0x00FF_00FF_00FF_00FF]);
 
+    /// Mask of the rectangle from (u0, v0) to (u1, v1), exclusive, in 1/16ths of a face.
+    pub const fn from_rect(u0: u8, v0: u8, u1: u8, v1: u8) -> Self {
+        let mut mask = [0u64; 4];
+        let mut v = v0;
+        while v < v1 && v < 16 {
+            let mut u = u0;
+            while u < u1 && u < 16 {
+                mask[v as usize / 4] |= 1u64 << ((v % 4) * 16 + (15 - u));
+                u += 1;
+            }
+            v += 1;
+        }
+        Self(mask)
+    }
+
+    /// Bits that are set in either mask.
+    pub const fn union(&self, other: &Self) -> Self {
+        Self([
+            self.0[0] | other.0[0],
+            self.0[1] | other.0[1],
+            self.0[2] | other.0[2],
+            self.0[3] | other.0[3],
+        ])
+    }
+
     pub const fn is_full(&self) -> bool {
         self.0[0] == u64::MAX
             && self.0[1] == u64::MAX
@@ -174,3 +265,90 @@ impl Internable for Mask {
         state.write_usize(refr)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mask_from_rect_matches_quadrants() {
+        assert_eq!(Mask::from_rect(0, 0, 8, 8), Mask::Q00);
+        assert_eq!(Mask::from_rect(8, 0, 16, 8), Mask::Q10);
+        assert_eq!(Mask::from_rect(0, 8, 8, 16), Mask::Q01);
+        assert_eq!(Mask::from_rect(8, 8, 16, 16), Mask::Q11);
+        assert_eq!(Mask::from_rect(0, 0, 16, 16), Mask::FULL);
+        assert_eq!(Mask::from_rect(4, 4, 4, 12), Mask::EMPTY);
+    }
+
+    #[test]
+    fn mask_union() {
+        let bottom = Mask::Q00.union(&Mask::Q10);
+        assert_eq!(bottom, Mask::from_rect(0, 0, 16, 8));
+        assert_eq!(bottom.num_covered(), 128);
+        assert!(Mask::Q00.is_covered_by(&bottom));
+        assert!(!Mask::Q01.is_covered_by(&bottom));
+    }
+
+    #[test]
+    fn opaque_full_covers_transparent() {
+        let full = AxisArray::new([Mask::FULL; 6]);
+        let stone = Coverages::new(AxisArray::new([0; 6]), full);
+        let glass = Coverages::new(AxisArray::new([3; 6]), full);
+        let water = Coverages::new(AxisArray::new([4; 6]), full);
+        assert!(glass.is_covered_by(&stone, Axis::PosX));
+        assert!(glass.is_covered_by(&glass, Axis::PosX));
+        assert!(!glass.is_covered_by(&water, Axis::PosX));
+        assert!(!stone.is_covered_by(&glass, Axis::PosX));
+        assert!(stone.is_covered_by(&stone, Axis::PosX));
+        assert!(stone.occludes(Axis::NegX, &glass));
+        assert!(!glass.occludes(Axis::NegX, &stone));
+    }
+
+    #[test]
+    fn element_coverage() {
+        let slab = [Element::cube(
+            [0, 0, 0],
+            [16, 8, 16],
+            AxisArray::new([1; 6]),
+        )];
+        let top_slab = [Element::cube(
+            [0, 8, 0],
+            [16, 16, 16],
+            AxisArray::new([1; 6]),
+        )];
+        let stair = [
+            slab[0].clone(),
+            Element::cube([0, 8, 8], [16, 16, 16], AxisArray::new([1; 6])),
+        ];
+        let slab = Coverages::from_elements(&slab, Transparency::Opaque);
+        let top_slab = Coverages::from_elements(&top_slab, Transparency::Opaque);
+        let stair = Coverages::from_elements(&stair, Transparency::Opaque);
+        let stone = Coverages::full(AxisArray::new([1; 6]), Transparency::Opaque);
+
+        // the sides of slabs next to each other are hidden, unless they are offset.
+        assert!(slab.is_covered_by(&slab, Axis::PosX));
+        assert!(!slab.is_covered_by(&top_slab, Axis::PosX));
+        assert!(slab.is_covered_by(&stone, Axis::PosX));
+        assert!(!stone.is_covered_by(&slab, Axis::PosX));
+
+        // the bottom of a slab is full, the top is open.
+        assert!(stone.is_covered_by(&slab, Axis::PosY));
+        assert!(!stone.is_covered_by(&slab, Axis::NegY));
+
+        // the side of a stair is only partly hidden by a slab,
+        // but the face of its bottom element is.
+        assert!(!stair.is_covered_by(&slab, Axis::PosX));
+        assert!(slab.occludes_face(Axis::NegX, &Mask::from_rect(0, 0, 16, 8), 0));
+        assert!(!slab.occludes_face(Axis::NegX, &Mask::from_rect(8, 8, 16, 16), 0));
+        assert!(stair.occludes(Axis::PosX, &slab));
+    }
+
+    #[test]
+    fn empty_coverage_hides_nothing() {
+        let air = Coverages::empty();
+        let stone = Coverages::full(AxisArray::new([1; 6]), Transparency::Opaque);
+        assert!(air.is_empty(Axis::PosY));
+        assert!(!stone.is_covered_by(&air, Axis::PosY));
+        assert!(!air.is_covered_by(&stone, Axis::PosY));
+    }
+}
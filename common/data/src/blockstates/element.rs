@@ -0,0 +1,148 @@
+use math::axis::{Axis, AxisArray};
+
+use super::{
+    coverage::Mask,
+    quad::{FULL_BLOCK, Quad, QuadUv},
+};
+
+/// An axis-aligned box inside of a block, like the base of a slab or the step of a stair.
+#[derive(Clone, Debug)]
+pub struct Element {
+    /// Minimum corner of the box, in 1/16ths of a voxel.
+    pub from: [i16; 3],
+
+    /// Maximum corner of the box, in 1/16ths of a voxel.
+    pub to: [i16; 3],
+
+    /// Faces of the box. Faces that are "None" are not drawn.
+    pub faces: AxisArray<Option<ElementFace>>,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct ElementFace {
+    pub texture: u16,
+
+    /// Region of the texture that is mapped onto the face, as `[u0, v0, u1, v1]`
+    /// in 1/16ths of the texture. If "None", the texture is locked to the voxel grid,
+    /// so the face of a slab shows the half of the texture it covers.
+    pub uv: Option<[u8; 4]>,
+}
+
+impl Element {
+    /// A box with a grid-locked texture on every face.
+    pub fn cube(from: [i16; 3], to: [i16; 3], textures: AxisArray<u16>) -> Self {
+        Self {
+            from,
+            to,
+            faces: textures.map(|_, texture| Some(ElementFace { texture, uv: None })),
+        }
+    }
+
+    /// Whether the face on this axis lies on the boundary of the block,
+    /// which means it can be hidden by the neighbour on that side.
+    pub fn is_on_boundary(&self, axis: Axis) -> bool {
+        let (d, _, _) = plane_dims(axis);
+        if axis == axis.abs() {
+            self.to[d] == 16
+        } else {
+            self.from[d] == 0
+        }
+    }
+
+    /// The quad of the face on this axis, relative to the block origin.
+    pub fn quad(&self, axis: Axis, texture: i16) -> Quad {
+        let mut quad = FULL_BLOCK[axis];
+        for vert in &mut quad.0 {
+            for i in 0..3 {
+                vert.pos[i] = if vert.pos[i] == 0 {
+                    self.from[i]
+                } else {
+                    self.to[i]
+                };
+            }
+            vert.texture = texture;
+        }
+        quad
+    }
+
+    /// The texture coordinates of the face on this axis.
+    pub fn uv(&self, axis: Axis) -> QuadUv {
+        let Some([u0, v0, u1, v1]) = self.faces[axis].and_then(|face| face.uv) else {
+            return QuadUv::WORLD;
+        };
+
+        let (_, u, v) = plane_dims(axis);
+        let quad = FULL_BLOCK[axis];
+        QuadUv::new(std::array::from_fn(|i| {
+            let pos = quad.0[i].pos;
+            [
+                if pos[u] == 0 { u0 } else { u1 },
+                if pos[v] == 0 { v0 } else { v1 },
+            ]
+        }))
+    }
+
+    /// The part of the block boundary on this axis covered by the face.
+    pub fn mask(&self, axis: Axis) -> Mask {
+        if self.faces[axis].is_none() || !self.is_on_boundary(axis) {
+            return Mask::EMPTY;
+        }
+
+        let (_, u, v) = plane_dims(axis);
+        let clamp = |x: i16| x.clamp(0, 16) as u8;
+        Mask::from_rect(
+            clamp(self.from[u]),
+            clamp(self.from[v]),
+            clamp(self.to[u]),
+            clamp(self.to[v]),
+        )
+    }
+}
+
+/// Index of the (depth, u, v) dimensions of a face on this axis.
+pub const fn plane_dims(axis: Axis) -> (usize, usize, usize) {
+    match axis {
+        Axis::PosX | Axis::NegX => (0, 2, 1),
+        Axis::PosY | Axis::NegY => (1, 0, 2),
+        Axis::PosZ | Axis::NegZ => (2, 0, 1),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slab_masks() {
+        let slab = Element::cube([0, 0, 0], [16, 8, 16], AxisArray::new([1; 6]));
+        assert!(slab.is_on_boundary(Axis::NegY));
+        assert!(!slab.is_on_boundary(Axis::PosY));
+        assert_eq!(slab.mask(Axis::NegY), Mask::FULL);
+        assert_eq!(slab.mask(Axis::PosY), Mask::EMPTY);
+        assert_eq!(slab.mask(Axis::PosX), Mask::from_rect(0, 0, 16, 8));
+        assert_eq!(slab.mask(Axis::NegZ), Mask::from_rect(0, 0, 16, 8));
+    }
+
+    #[test]
+    fn quad_is_resized_to_element() {
+        let slab = Element::cube([0, 0, 0], [16, 8, 16], AxisArray::new([1; 6]));
+        let top = slab.quad(Axis::PosY, 1);
+        assert!(top.0.iter().all(|vert| vert.pos[1] == 8));
+        let side = slab.quad(Axis::PosX, 1);
+        assert!(side.0.iter().all(|vert| vert.pos[0] == 16 && vert.pos[1] <= 8));
+    }
+
+    #[test]
+    fn explicit_uv() {
+        let mut slab = Element::cube([0, 0, 0], [16, 8, 16], AxisArray::new([1; 6]));
+        assert!(slab.uv(Axis::PosX).is_world());
+
+        slab.faces[Axis::PosX] = Some(ElementFace {
+            texture: 1,
+            uv: Some([0, 0, 16, 8]),
+        });
+        let uv = slab.uv(Axis::PosX);
+        assert!(!uv.is_world());
+        assert!(uv.0.iter().all(|[u, v, _, _]| *u <= 16 && *v <= 8));
+    }
+}
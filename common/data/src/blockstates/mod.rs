@@ -1,8 +1,13 @@
 use coverage::{Coverage, Coverages};
-use math::axis::AxisArray;
+use element::Element;
+use math::axis::{Axis, AxisArray};
 use quad::{Normal, Quad};
+use serde::Deserialize;
+
+use crate::registry::RegistryId;
 
 pub mod coverage;
+pub mod element;
 pub mod quad;
 
 pub struct BlockState {
@@ -10,8 +15,102 @@ pub struct BlockState {
     pub transparency: Transparency,
     pub model: ModelData,
 
-    /// Identifies the variant.
+    /// Liquids can be swum through, and don't block movement.
+    pub liquid: bool,
+
+    /// How long the block takes to break.
+    pub hardness: f32,
+
+    /// Identifies the variant, see `Variants` for how properties are packed.
     pub bits: u32,
+
+    /// The block this is a state of.
+    pub block: RegistryId,
+}
+
+impl BlockState {
+    /// A block with no model that doesn't cover its neighbours, like air.
+    pub fn empty() -> Self {
+        Self {
+            coverages: Coverages::empty(),
+            transparency: Transparency::Opaque,
+            model: ModelData::Empty,
+            liquid: false,
+            hardness: 0.0,
+            bits: 0,
+            block: RegistryId(0),
+        }
+    }
+
+    /// A full cube with a texture on each side.
+    ///
+    /// Opaque cubes cover every neighbour, transparent cubes only
+    /// cover the faces of neighbours that have the same texture.
+    pub fn full(textures: AxisArray<u16>, transparency: Transparency) -> Self {
+        Self {
+            coverages: Coverages::full(textures, transparency),
+            transparency,
+            model: ModelData::Full { textures },
+            liquid: false,
+            hardness: 1.0,
+            bits: 0,
+            block: RegistryId(0),
+        }
+    }
+
+    /// A block made of boxes, like a slab or stair.
+    ///
+    /// The coverage on each side is the union of the faces of
+    /// the elements that lie on the boundary of the block.
+    pub fn elements(elements: Vec<Element>, transparency: Transparency) -> Self {
+        Self {
+            coverages: Coverages::from_elements(&elements, transparency),
+            transparency,
+            model: ModelData::Elements(elements),
+            liquid: false,
+            hardness: 1.0,
+            bits: 0,
+            block: RegistryId(0),
+        }
+    }
+
+    /// A plant-like block made of two quads crossing diagonally through it.
+    /// Crosses don't cover their neighbours.
+    pub fn cross(texture: u16, transparency: Transparency) -> Self {
+        Self {
+            transparency,
+            model: ModelData::Cross { texture },
+            hardness: 1.0,
+            ..Self::empty()
+        }
+    }
+
+    /// Whether the block stops entities from moving through it.
+    /// Liquids and blocks without a collision box, like plants, don't.
+    pub fn blocks_movement(&self) -> bool {
+        !self.liquid && !matches!(self.model, ModelData::Empty | ModelData::Cross { .. })
+    }
+
+    /// Whether the block is a full opaque cube, which blocks light
+    /// and occludes the faces of its neighbours.
+    pub fn is_opaque_cube(&self) -> bool {
+        matches!(self.model, ModelData::Full { .. }) && self.transparency == Transparency::Opaque
+    }
+
+    /// The texture shown where a block is drawn with a single texture,
+    /// like on its particles, or None if it has no model.
+    pub fn icon_texture(&self) -> Option<u16> {
+        match &self.model {
+            ModelData::Empty => None,
+            // the sides are more recognizable than the top, like grass.
+            ModelData::Full { textures } => Some(textures[Axis::PosX]),
+            ModelData::Elements(elements) => elements
+                .iter()
+                .flat_map(|element| element.faces.values())
+                .find_map(|face| face.map(|face| face.texture)),
+            ModelData::Cross { texture } => Some(*texture),
+        }
+    }
 }
 
 pub enum ModelData {
@@ -23,11 +122,22 @@ pub enum ModelData {
         /// Texture used on each side.
         textures: AxisArray<u16>,
     },
+
+    /// The block is made of arbitrary axis-aligned boxes.
+    Elements(Vec<Element>),
+
+    /// Two quads crossing diagonally through the block, like flowers and grass.
+    Cross {
+        /// Texture used on both quads.
+        texture: u16,
+    },
 }
 
-#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Transparency {
     /// Alpha values are all 1.0.
+    #[default]
     Opaque = 0,
 
     /// Has alpha values in the range (0.0,1.0)
@@ -37,4 +147,3 @@ pub enum Transparency {
     Mask = 2,
 }
 
-pub struct Element {}
@@ -46,6 +46,81 @@ impl Vertex {
     }
 }
 
+/// Per-vertex lighting of a quad, in the same order as the quad's vertices.
+/// Each vertex is `[sky, block, ao, 0]`, where sky and block light are
+/// in the range 0..=15 and ambient occlusion is in the range 0..=3. (3 is unoccluded)
+#[derive(Copy, Clone, Eq, PartialEq, Pod, Zeroable, Debug, Hash, Default)]
+#[repr(C)]
+pub struct QuadLight(pub [[u8; 4]; 4]);
+
+impl QuadLight {
+    /// Full sky light, no block light, no occlusion.
+    pub const FULL: Self = Self([[15, 0, 3, 0]; 4]);
+
+    /// Whether all four vertices have the same lighting.
+    /// Only uniformly lit quads can be merged without changing how they look.
+    pub const fn is_uniform(&self) -> bool {
+        let v = self.0[0];
+        let mut i = 1;
+        while i < 4 {
+            let w = self.0[i];
+            if v[0] != w[0] || v[1] != w[1] || v[2] != w[2] {
+                return false;
+            }
+            i += 1;
+        }
+        true
+    }
+
+    /// Rotate the vertices by one, to match `Quad::rotate`.
+    pub const fn rotate(self) -> Self {
+        Self([self.0[1], self.0[2], self.0[3], self.0[0]])
+    }
+
+    /// Sum of the ambient occlusion values of vertices 0 and 2,
+    /// and vertices 1 and 3. Used to pick the triangulation of the quad.
+    pub const fn ao_diagonals(&self) -> (u8, u8) {
+        (
+            self.0[0][2] + self.0[2][2],
+            self.0[1][2] + self.0[3][2],
+        )
+    }
+}
+
+/// Per-vertex texture coordinates of a quad, in the same order as the quad's vertices.
+/// Each vertex is `[u, v, explicit, 0]`, where u and v are in 1/16ths of the texture.
+///
+/// If `explicit` is 0, the texture coordinates are derived from the position of the
+/// vertex instead, which tiles the texture once per voxel and lets quads be merged.
+#[derive(Copy, Clone, Eq, PartialEq, Pod, Zeroable, Debug, Hash, Default)]
+#[repr(C)]
+pub struct QuadUv(pub [[u8; 4]; 4]);
+
+impl QuadUv {
+    /// Texture coordinates derived from the position of each vertex.
+    pub const WORLD: Self = Self([[0; 4]; 4]);
+
+    /// Explicit texture coordinates of each vertex.
+    pub const fn new(uv: [[u8; 2]; 4]) -> Self {
+        Self([
+            [uv[0][0], uv[0][1], 1, 0],
+            [uv[1][0], uv[1][1], 1, 0],
+            [uv[2][0], uv[2][1], 1, 0],
+            [uv[3][0], uv[3][1], 1, 0],
+        ])
+    }
+
+    /// Whether the texture coordinates are derived from vertex positions.
+    pub const fn is_world(&self) -> bool {
+        self.0[0][2] == 0 && self.0[1][2] == 0 && self.0[2][2] == 0 && self.0[3][2] == 0
+    }
+
+    /// Rotate the vertices by one, to match `Quad::rotate`.
+    pub const fn rotate(self) -> Self {
+        Self([self.0[1], self.0[2], self.0[3], self.0[0]])
+    }
+}
+
 #[derive(Copy, Clone, Eq, PartialEq, Pod, Zeroable)]
 #[repr(C, align(32))]
 pub struct Quad(pub [Vertex; 4]);
@@ -71,6 +146,12 @@ impl Quad {
         }
     }
 
+    /// Rotate the vertices by one, which keeps the winding but
+    /// flips the diagonal the quad is split along.
+    pub const fn rotate(self) -> Self {
+        Self([self.0[1], self.0[2], self.0[3], self.0[0]])
+    }
+
     pub const fn offset(self, offs: [i16; 3]) -> Self {
         Self([
             self.0[0].offset(offs),
@@ -90,6 +171,27 @@ impl Quad {
     }
 }
 
+/// Two double-sided quads crossing diagonally through a voxel, for plants like flowers and grass.
+pub const CROSS: [(Quad, Normal); 4] = [
+    (Quad::new(CROSS_A, 0), Normal::Unaligned([89, 0, -89])),
+    (Quad::new(CROSS_A_BACK, 0), Normal::Unaligned([-89, 0, 89])),
+    (Quad::new(CROSS_B, 0), Normal::Unaligned([89, 0, 89])),
+    (Quad::new(CROSS_B_BACK, 0), Normal::Unaligned([-89, 0, -89])),
+];
+
+/// Texture coordinates of each quad in `CROSS`.
+pub const CROSS_UV: [QuadUv; 4] = [
+    QuadUv::new([[0, 16], [16, 16], [16, 0], [0, 0]]),
+    QuadUv::new([[0, 0], [16, 0], [16, 16], [0, 16]]),
+    QuadUv::new([[0, 16], [16, 16], [16, 0], [0, 0]]),
+    QuadUv::new([[0, 0], [16, 0], [16, 16], [0, 16]]),
+];
+
+const CROSS_A: [[i16; 3]; 4] = [[0, 16, 0], [16, 16, 16], [16, 0, 16], [0, 0, 0]];
+const CROSS_A_BACK: [[i16; 3]; 4] = [[0, 0, 0], [16, 0, 16], [16, 16, 16], [0, 16, 0]];
+const CROSS_B: [[i16; 3]; 4] = [[16, 16, 0], [0, 16, 16], [0, 0, 16], [16, 0, 0]];
+const CROSS_B_BACK: [[i16; 3]; 4] = [[16, 0, 0], [0, 0, 16], [0, 16, 16], [16, 16, 0]];
+
 const POS_X: [[i16; 3]; 4] = [[16, 16, 0], [16, 16, 16], [16, 0, 16], [16, 0, 0]];
 const NEG_X: [[i16; 3]; 4] = [[0, 16, 16], [0, 16, 0], [0, 0, 0], [0, 0, 16]];
 const POS_Y: [[i16; 3]; 4] = [[0, 16, 16], [16, 16, 16], [16, 16, 0], [0, 16, 0]];
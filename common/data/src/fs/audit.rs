@@ -0,0 +1,216 @@
+//! The audit log of a world, recording who ran commands and who tried to build in
+//! protected regions.
+//!
+//! Entries are appended to `audit.log` next to the region files as JSON lines. The
+//! server never changes or removes them, so admins sharing a server can see what the
+//! others did. The log is read back with `read_recent`.
+
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::fs::save::{civil_from_days, unix_now};
+
+/// Name of the audit log, in the region directory of a world.
+pub const AUDIT_FILE: &str = "audit.log";
+
+/// Issuer of commands run in the server console.
+pub const CONSOLE_ISSUER: &str = "console";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditKind {
+    /// A command was run.
+    Command,
+
+    /// A block edit was rejected because it was in a protected region.
+    ProtectionViolation,
+}
+
+/// Something recorded in the audit log.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    /// Seconds since the unix epoch.
+    pub time: u64,
+
+    pub kind: AuditKind,
+
+    /// Name of the player, or `console`.
+    pub issuer: String,
+
+    /// Name of the command, or the edit that was rejected, like `break` or `place`.
+    pub action: String,
+
+    /// Arguments of the command, or the position of the rejected edit.
+    pub args: Vec<String>,
+}
+
+impl AuditEntry {
+    /// An entry recorded now.
+    pub fn now(
+        kind: AuditKind,
+        issuer: impl Into<String>,
+        action: impl Into<String>,
+        args: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        Self {
+            time: unix_now(),
+            kind,
+            issuer: issuer.into(),
+            action: action.into(),
+            args: args.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    /// The time in UTC, like `2025-01-31 14:05:09`.
+    pub fn time_string(&self) -> String {
+        let (year, month, day) = civil_from_days((self.time / 86400) as i64);
+        let time = self.time % 86400;
+        format!(
+            "{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02}",
+            time / 3600,
+            time / 60 % 60,
+            time % 60
+        )
+    }
+}
+
+/// The audit log file, opened for appending.
+pub struct AuditFile {
+    file: File,
+}
+
+impl AuditFile {
+    /// Open the audit log in the region directory, creating it if it doesn't exist.
+    pub fn open(region_dir: &Path) -> io::Result<Self> {
+        fs::create_dir_all(region_dir)?;
+        let path = region_dir.join(AUDIT_FILE);
+        let mut file = OpenOptions::new().append(true).create(true).open(&path)?;
+
+        // a line cut off by a crash is ended, so the next entry starts on its own line.
+        if !ends_with_newline(&path)? {
+            file.write_all(b"\n")?;
+        }
+        Ok(Self { file })
+    }
+
+    /// Append an entry, written right away so it isn't lost if the server crashes.
+    pub fn append(&mut self, entry: &AuditEntry) -> io::Result<()> {
+        let mut line = serde_json::to_vec(entry).map_err(io::Error::other)?;
+        line.push(b'\n');
+        self.file.write_all(&line)?;
+        self.file.flush()
+    }
+}
+
+/// Whether the file is empty or its last byte is a newline.
+fn ends_with_newline(path: &Path) -> io::Result<bool> {
+    let mut file = File::open(path)?;
+    if file.metadata()?.len() == 0 {
+        return Ok(true);
+    }
+
+    let mut last = [0];
+    file.seek(SeekFrom::End(-1))?;
+    file.read_exact(&mut last)?;
+    Ok(last[0] == b'\n')
+}
+
+/// The most recent entries of the audit log in the region directory, oldest first,
+/// only of one issuer if there is one. A world without an audit log has no entries.
+///
+/// Lines that can't be read, like one cut off by a crash, are skipped.
+pub fn read_recent(
+    region_dir: &Path,
+    issuer: Option<&str>,
+    count: usize,
+) -> io::Result<Vec<AuditEntry>> {
+    let file = match File::open(region_dir.join(AUDIT_FILE)) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+
+    let mut entries = Vec::new();
+    for line in BufReader::new(file).lines() {
+        let Ok(entry) = serde_json::from_str::<AuditEntry>(&line?) else {
+            continue;
+        };
+        if issuer.is_none_or(|issuer| entry.issuer == issuer) {
+            entries.push(entry);
+        }
+    }
+
+    let skip = entries.len().saturating_sub(count);
+    entries.drain(..skip);
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::TempDir;
+
+    #[test]
+    fn time_string_is_utc() {
+        let mut entry = AuditEntry::now(
+            AuditKind::Command,
+            CONSOLE_ISSUER,
+            "seed",
+            Vec::<String>::new(),
+        );
+        entry.time = 1738332309;
+        assert_eq!(entry.time_string(), "2025-01-31 14:05:09");
+    }
+
+    #[test]
+    fn entries_are_appended_and_read_back() {
+        let dir = TempDir::new("audit");
+        assert!(read_recent(&dir, None, 10).unwrap().is_empty());
+
+        let entries = [
+            AuditEntry::now(AuditKind::Command, "Player 1", "protect", ["add", "spawn"]),
+            AuditEntry::now(
+                AuditKind::ProtectionViolation,
+                "Player 2",
+                "break",
+                ["1", "2", "3"],
+            ),
+            AuditEntry::now(AuditKind::Command, "Player 1", "seed", Vec::<String>::new()),
+        ];
+        for entry in &entries {
+            // opened again for every entry, like across restarts of the server.
+            AuditFile::open(&dir).unwrap().append(entry).unwrap();
+        }
+
+        // a line cut off by a crash doesn't hide the entries after it.
+        let mut file = OpenOptions::new()
+            .append(true)
+            .open(dir.join(AUDIT_FILE))
+            .unwrap();
+        file.write_all(b"{\"time\":1,\"ki").unwrap();
+        AuditFile::open(&dir).unwrap().append(&entries[0]).unwrap();
+
+        assert_eq!(
+            read_recent(&dir, None, 10).unwrap(),
+            [
+                entries[0].clone(),
+                entries[1].clone(),
+                entries[2].clone(),
+                entries[0].clone()
+            ]
+        );
+        assert_eq!(
+            read_recent(&dir, None, 2).unwrap(),
+            [entries[2].clone(), entries[0].clone()]
+        );
+        assert_eq!(
+            read_recent(&dir, Some("Player 2"), 10).unwrap(),
+            [entries[1].clone()]
+        );
+    }
+}
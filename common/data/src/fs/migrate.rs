@@ -0,0 +1,160 @@
+//! Upgrades of saved data written by older versions of the game.
+//!
+//! Saved data has a kind, like "world_info", and is saved with the version of its format.
+//! A migration upgrades data of one kind from one version to the next, and loading data
+//! with an older version runs every migration from its version to the latest, in order.
+//! Data saved before it had a version is version 0.
+
+use std::collections::BTreeMap;
+
+/// Upgrade data from the version it is registered with to the next.
+pub type Migration<V> = fn(&mut V) -> Result<(), String>;
+
+/// Migrations of every kind of data saved as a `V`, like a toml table or bytes.
+pub struct Migrations<V> {
+    kinds: BTreeMap<&'static str, BTreeMap<u32, Migration<V>>>,
+}
+
+impl<V> Migrations<V> {
+    pub fn new() -> Self {
+        Self {
+            kinds: BTreeMap::new(),
+        }
+    }
+
+    /// Add the upgrade of data of this kind from version `from` to `from + 1`.
+    /// Panics if the kind already has an upgrade from that version.
+    pub fn add(&mut self, kind: &'static str, from: u32, migration: Migration<V>) -> &mut Self {
+        let steps = self.kinds.entry(kind).or_default();
+        if steps.insert(from, migration).is_some() {
+            panic!("[D420] Data of kind '{kind}' has two migrations from version {from}.");
+        }
+        self
+    }
+
+    /// Version data of this kind is saved with, one past its last migration.
+    pub fn latest(&self, kind: &str) -> u32 {
+        self.kinds
+            .get(kind)
+            .and_then(|steps| steps.last_key_value())
+            .map_or(0, |(from, _)| from + 1)
+    }
+
+    /// Upgrade data of this kind from its version to the latest.
+    /// Returns the version the data has afterwards, which is always the latest.
+    pub fn migrate(&self, kind: &str, version: u32, data: &mut V) -> Result<u32, MigrationError> {
+        let latest = self.latest(kind);
+        if version > latest {
+            return Err(MigrationError::Newer {
+                kind: kind.to_string(),
+                version,
+                latest,
+            });
+        }
+
+        for from in version..latest {
+            let Some(migration) = self.kinds.get(kind).and_then(|steps| steps.get(&from)) else {
+                return Err(MigrationError::Missing {
+                    kind: kind.to_string(),
+                    from,
+                });
+            };
+
+            migration(data).map_err(|msg| MigrationError::Failed {
+                kind: kind.to_string(),
+                from,
+                msg,
+            })?;
+        }
+
+        Ok(latest)
+    }
+}
+
+impl<V> Default for Migrations<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum MigrationError {
+    #[error(
+        "[D421] Data of kind '{kind}' has version {version}, newer than the latest known version {latest}."
+    )]
+    Newer {
+        kind: String,
+        version: u32,
+        latest: u32,
+    },
+
+    #[error("[D422] Data of kind '{kind}' has no migration from version {from}.")]
+    Missing { kind: String, from: u32 },
+
+    #[error("[D423] Failed to migrate data of kind '{kind}' from version {from}: {msg}")]
+    Failed {
+        kind: String,
+        from: u32,
+        msg: String,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn migrations() -> Migrations<Vec<String>> {
+        let mut migrations = Migrations::<Vec<String>>::new();
+        migrations
+            .add("log", 0, |lines| {
+                lines.push("v1".into());
+                Ok(())
+            })
+            .add("log", 1, |lines| {
+                lines.push("v2".into());
+                Ok(())
+            })
+            .add("broken", 0, |_| Err("field is missing".into()));
+        migrations
+    }
+
+    #[test]
+    fn migrate_runs_steps_in_order() {
+        let migrations = migrations();
+        assert_eq!(migrations.latest("log"), 2);
+        assert_eq!(migrations.latest("unknown"), 0);
+
+        let mut lines = Vec::new();
+        assert_eq!(migrations.migrate("log", 0, &mut lines).unwrap(), 2);
+        assert_eq!(lines, ["v1", "v2"]);
+
+        let mut lines = Vec::new();
+        assert_eq!(migrations.migrate("log", 1, &mut lines).unwrap(), 2);
+        assert_eq!(lines, ["v2"]);
+
+        // data that is up to date isn't touched.
+        let mut lines = Vec::new();
+        assert_eq!(migrations.migrate("log", 2, &mut lines).unwrap(), 2);
+        assert!(lines.is_empty());
+    }
+
+    #[test]
+    fn migrate_errors() {
+        let mut migrations = migrations();
+        let mut lines = Vec::new();
+        assert!(matches!(
+            migrations.migrate("log", 3, &mut lines),
+            Err(MigrationError::Newer { latest: 2, .. })
+        ));
+        assert!(matches!(
+            migrations.migrate("broken", 0, &mut lines),
+            Err(MigrationError::Failed { from: 0, .. })
+        ));
+
+        migrations.add("gap", 1, |_| Ok(()));
+        assert!(matches!(
+            migrations.migrate("gap", 0, &mut lines),
+            Err(MigrationError::Missing { from: 0, .. })
+        ));
+    }
+}
@@ -1,5 +1,7 @@
 use bevy::prelude::*;
 
+pub mod audit;
+pub mod migrate;
 pub mod packs;
 pub mod path;
 pub mod save;
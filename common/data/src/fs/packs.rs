@@ -1,36 +1,52 @@
-use std::{fs, io, path::PathBuf};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 use bevy::{
     prelude::*,
     tasks::{IoTaskPool, Task, futures_lite},
 };
 use fxhash::FxHashSet;
+use parking_lot::RwLock;
 use ron::de::SpannedError;
 use serde::de::DeserializeOwned;
 
 use crate::fs::path::{FileExt, get_relative_path_without_ext_as_string, iter_files_in_dir};
 
-#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
 pub struct AssetPackId(pub u32);
 
-#[derive(Resource, Default)]
+/// Asset packs layered over each other, searched in order of priority.
+///
+/// Packs with a lower priority value are searched first, so their files replace the
+/// files of packs after them. The mounts are shared between clones of the reader, so a
+/// clone can be given to an asset source and see packs mounted after it was made.
+#[derive(Resource, Default, Clone)]
 pub struct AssetPackReader {
+    mounts: Arc<RwLock<Mounts>>,
+}
+
+#[derive(Default)]
+struct Mounts {
     next_mount_id: u32,
-    mounts: Vec<Mount>,
+    list: Vec<Mount>,
 }
 
 impl AssetPackReader {
     /// Add an asset pack to the reader.
+    /// Packs mounted with the same priority as an existing pack are searched first.
     pub fn mount(&mut self, path: PathBuf, priority: u32) -> AssetPackId {
-        let id = AssetPackId(self.next_mount_id);
-        self.next_mount_id += 1;
+        let mut mounts = self.mounts.write();
+        let id = AssetPackId(mounts.next_mount_id);
+        mounts.next_mount_id += 1;
 
-        let mount = Mount { path, priority, id };
-
-        if let Some(i) = self.mounts.iter().position(|mt| mt.priority >= priority) {
-            self.mounts.insert(i, mount);
+        let mount = Mount::new(path, priority, id);
+        if let Some(i) = mounts.list.iter().position(|mt| mt.priority >= priority) {
+            mounts.list.insert(i, mount);
         } else {
-            self.mounts.push(mount);
+            mounts.list.push(mount);
         }
 
         id
@@ -38,13 +54,59 @@ impl AssetPackReader {
 
     /// Add an asset pack to the end of the reader.
     pub fn mount_to_end(&mut self, path: PathBuf) -> AssetPackId {
-        let id = AssetPackId(self.next_mount_id);
-        self.next_mount_id += 1;
-        let priority = self.mounts.last().map(|mt| mt.priority).unwrap_or(0);
-        self.mounts.push(Mount { path, priority, id });
+        let mut mounts = self.mounts.write();
+        let id = AssetPackId(mounts.next_mount_id);
+        mounts.next_mount_id += 1;
+        let priority = mounts.list.last().map(|mt| mt.priority).unwrap_or(0);
+        mounts.list.push(Mount::new(path, priority, id));
         id
     }
 
+    /// Remove an asset pack from the reader.
+    /// Returns "false" if the pack was not mounted.
+    pub fn unmount(&mut self, id: AssetPackId) -> bool {
+        let mut mounts = self.mounts.write();
+        let len = mounts.list.len();
+        mounts.list.retain(|mt| mt.id != id);
+        mounts.list.len() != len
+    }
+
+    /// Remove every asset pack from the reader.
+    pub fn unmount_all(&mut self) {
+        self.mounts.write().list.clear();
+    }
+
+    /// Info of every mounted pack, in the order they are searched.
+    pub fn packs(&self) -> Vec<AssetPackInfo> {
+        self.mounts
+            .read()
+            .list
+            .iter()
+            .map(|mt| AssetPackInfo {
+                id: mt.id,
+                name: mt.name.clone(),
+                path: mt.path.clone(),
+                priority: mt.priority,
+            })
+            .collect()
+    }
+
+    /// Get the path of a file in the first pack that contains it.
+    /// Returns None if no mounted pack has a file at this relative path.
+    pub fn resolve(&self, rel: impl AsRef<Path>) -> Option<PathBuf> {
+        let rel = rel.as_ref();
+        if rel.is_absolute() {
+            return None;
+        }
+
+        self.mounts
+            .read()
+            .list
+            .iter()
+            .map(|mt| mt.path.join(rel))
+            .find(|path| path.is_file())
+    }
+
     /// Load all files in the folder with the provided extensions.
     /// Higher priority packs will load their files first, and any other files
     /// with the same relative path as an already loaded file won't be loaded again.
@@ -60,7 +122,7 @@ impl AssetPackReader {
         let mut in_progress = FxHashSet::<String>::default();
         let mut tasks = Vec::new();
 
-        for mount in &self.mounts {
+        for mount in &self.mounts.read().list {
             let pack_id = mount.id;
             let folder_path = mount.path.join(&rel);
             for path in iter_files_in_dir(&folder_path) {
@@ -88,10 +150,56 @@ impl AssetPackReader {
 
 struct Mount {
     path: PathBuf,
+    name: String,
     priority: u32,
     id: AssetPackId,
 }
 
+impl Mount {
+    fn new(path: PathBuf, priority: u32, id: AssetPackId) -> Self {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        Self {
+            path,
+            name,
+            priority,
+            id,
+        }
+    }
+}
+
+/// Description of a mounted asset pack.
+#[derive(Clone, Debug)]
+pub struct AssetPackInfo {
+    pub id: AssetPackId,
+
+    /// Name of the pack's directory.
+    pub name: String,
+
+    /// Absolute path of the pack's directory.
+    pub path: PathBuf,
+
+    pub priority: u32,
+}
+
+/// Find the asset packs in a directory, which are its sub-directories.
+/// Returns their names, sorted alphabetically.
+pub fn discover_packs(dir: impl AsRef<Path>) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(dir.as_ref()) else {
+        return Vec::new();
+    };
+
+    let mut names = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_ok_and(|ty| ty.is_dir()))
+        .filter_map(|entry| entry.file_name().to_str().map(str::to_owned))
+        .collect::<Vec<_>>();
+    names.sort_unstable();
+    names
+}
+
 #[derive(Default)]
 pub struct PackFolder {
     /// Relative path of folder.
@@ -108,10 +216,10 @@ impl PackFolder {
     /// Number of completed files divided by total number of files.
     /// In the range [0.0,1.0]
     pub fn progress(&self) -> f32 {
-        if self.tasks.len() == 0 {
+        if self.tasks.is_empty() {
             1.0
         } else {
-            self.tasks.len() as f32 / self.total as f32
+            self.amount_finished() as f32 / self.total as f32
         }
     }
 
@@ -218,7 +326,7 @@ impl PackFile {
             }
         };
 
-        let meta = if has_meta {
+        let meta = if !has_meta {
             // not all files loaded this way need metadata.
             None
         } else {
@@ -263,3 +371,55 @@ impl PackFile {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::TempDir;
+
+    fn write_file(path: &Path, data: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, data).unwrap();
+    }
+
+    #[test]
+    fn resolve_searches_packs_in_priority_order() {
+        let dir = TempDir::new("packs");
+        let (low, high) = (dir.join("low"), dir.join("high"));
+        write_file(&low.join("textures/stone.png"), "low");
+        write_file(&low.join("textures/dirt.png"), "low");
+        write_file(&high.join("textures/stone.png"), "high");
+
+        let mut reader = AssetPackReader::default();
+        let low_id = reader.mount(low.clone(), 1);
+        let high_id = reader.mount(high.clone(), 0);
+
+        assert_eq!(
+            reader.resolve("textures/stone.png"),
+            Some(high.join("textures/stone.png"))
+        );
+        assert_eq!(
+            reader.resolve("textures/dirt.png"),
+            Some(low.join("textures/dirt.png"))
+        );
+        assert_eq!(reader.resolve("textures/sand.png"), None);
+
+        // clones share their mounts.
+        let clone = reader.clone();
+        assert!(reader.unmount(high_id));
+        assert!(!reader.unmount(high_id));
+        assert_eq!(
+            clone.resolve("textures/stone.png"),
+            Some(low.join("textures/stone.png"))
+        );
+
+        let packs = clone.packs();
+        assert_eq!(packs.len(), 1);
+        assert_eq!(packs[0].id, low_id);
+        assert_eq!(packs[0].name, "low");
+        assert_eq!(
+            discover_packs(&dir),
+            vec!["high".to_string(), "low".to_string()]
+        );
+    }
+}
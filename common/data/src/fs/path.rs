@@ -13,6 +13,7 @@ use bevy::{
 use crossbeam_channel::Receiver;
 use protocol::bytes::Bytes;
 use serde::Deserialize;
+use walkdir::WalkDir;
 
 /// Get the path relative to the mount given a subpath and convert to String.
 /// Example:
@@ -57,6 +58,25 @@ pub fn iter_files_in_dir(path: impl AsRef<Path>) -> impl Iterator<Item = PathBuf
         .flatten()
 }
 
+/// Iterate the JSON files in a directory and its sub-directories, sorted by name.
+/// Yields the path relative to the directory without the extension, always separated
+/// by '/', like "mineable/pickaxe", and the absolute path of the file.
+pub fn iter_json_files_recursive(dir: &Path) -> impl Iterator<Item = (String, PathBuf)> {
+    WalkDir::new(dir)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let path = entry.path();
+            if !entry.file_type().is_file() || FileExt::from(path) != FileExt::Json {
+                return None;
+            }
+
+            let name = get_relative_path_without_ext_as_string(dir, path)?;
+            Some((name.replace('\\', "/"), path.to_path_buf()))
+        })
+}
+
 #[derive(Debug)]
 pub enum FileLoadError {
     NotFound,
@@ -1,2 +1,613 @@
+//! Metadata of world saves.
+//!
+//! Every world is a directory in the saves folder, containing a `world.toml`
+//! with the settings it was created with and the region files of the world.
+//! The region files are next to a `registries.toml`, with the ids they were written with,
+//! a `protections.toml`, with the regions only some players may build in, and a
+//! `players.toml`, with the keys of the players that joined.
+//! Data packs of the world, which change its recipes, loot and tags, are in `datapacks`.
+
 use bevy::prelude::*;
-use std::{fs, io};
+use math::space::area::IArea;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::fs::migrate::Migrations;
+
+/// Name of the metadata file in a world directory.
+pub const WORLD_INFO_FILE: &str = "world.toml";
+
+/// Name of the directory with the region files, in a world directory.
+pub const REGIONS_DIR: &str = "regions";
+
+/// Name of the directory with the data packs, in a world directory.
+pub const DATA_PACKS_DIR: &str = "datapacks";
+
+/// Name of the file with the registry ids of the region files, in the region directory.
+pub const REGISTRY_IDS_FILE: &str = "registries.toml";
+
+/// Name of the file with the protected regions, in the region directory.
+pub const PROTECTIONS_FILE: &str = "protections.toml";
+
+/// Name of the file with the keys of the players that joined, in the region directory.
+pub const PLAYERS_FILE: &str = "players.toml";
+
+/// Kind of the data in `world.toml`, for `Migrations`.
+pub const WORLD_INFO_KIND: &str = "world_info";
+
+/// Heights a world can be created with.
+/// The bottom of the world is always at `WorldInfo::MIN_Y`.
+pub const WORLD_HEIGHTS: [i32; 3] = [256, 384, 512];
+
+/// Settings a world was created with, and when it was last played.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WorldInfo {
+    /// Version of the format the info was saved with, see `toml_migrations`.
+    pub version: u32,
+
+    /// Display name of the world, which can differ from the directory name.
+    pub name: String,
+
+    /// Seed of the world generator.
+    /// Stored signed because TOML integers are 64-bit signed.
+    pub seed: i64,
+
+    /// Top of the world, exclusive.
+    pub max_y: i32,
+
+    /// Bottom of the world, inclusive.
+    pub min_y: i32,
+
+    /// Seconds since the unix epoch the world was last launched.
+    pub last_played: u64,
+}
+
+impl Default for WorldInfo {
+    fn default() -> Self {
+        Self {
+            version: toml_migrations().latest(WORLD_INFO_KIND),
+            name: String::new(),
+            seed: 0,
+            max_y: 256,
+            min_y: Self::MIN_Y,
+            last_played: 0,
+        }
+    }
+}
+
+impl WorldInfo {
+    pub const MIN_Y: i32 = -128;
+
+    /// Info for a new world of this height.
+    pub fn new(name: impl Into<String>, seed: i64, height: i32) -> Self {
+        Self {
+            name: name.into(),
+            seed,
+            version: toml_migrations().latest(WORLD_INFO_KIND),
+            max_y: Self::MIN_Y + height,
+            min_y: Self::MIN_Y,
+            last_played: unix_now(),
+        }
+    }
+
+    pub fn height(&self) -> i32 {
+        self.max_y - self.min_y
+    }
+
+    /// Read the info of the world in this directory.
+    /// Info saved by older versions is migrated to the latest version.
+    pub fn load(world_dir: &Path) -> io::Result<Self> {
+        let text = fs::read_to_string(world_dir.join(WORLD_INFO_FILE))?;
+        Self::parse(&text)
+    }
+
+    fn parse(text: &str) -> io::Result<Self> {
+        let mut table = toml::from_str::<toml::Table>(text).map_err(io::Error::other)?;
+        let version = table
+            .get("version")
+            .and_then(|version| version.as_integer())
+            .and_then(|version| u32::try_from(version).ok())
+            .unwrap_or(0);
+
+        let version = toml_migrations()
+            .migrate(WORLD_INFO_KIND, version, &mut table)
+            .map_err(io::Error::other)?;
+
+        let mut info = toml::Value::Table(table)
+            .try_into::<Self>()
+            .map_err(io::Error::other)?;
+        info.version = version;
+        Ok(info)
+    }
+
+    /// Read the info of the world in this directory. Worlds saved before
+    /// world.toml existed get default info named after their directory.
+    pub fn load_or_default(world_dir: &Path) -> io::Result<Self> {
+        match Self::load(world_dir) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self {
+                name: dir_name(world_dir),
+                ..default()
+            }),
+            result => result,
+        }
+    }
+
+    /// Write the info to the world directory, creating it if it doesn't exist.
+    pub fn save(&self, world_dir: &Path) -> io::Result<()> {
+        fs::create_dir_all(world_dir)?;
+        let text = toml::to_string_pretty(self).map_err(io::Error::other)?;
+        write_replacing(&world_dir.join(WORLD_INFO_FILE), &text)
+    }
+
+    /// Mark the world as played right now.
+    pub fn touch(&mut self) {
+        self.last_played = unix_now();
+    }
+}
+
+/// Names of the entries of registries whose ids are saved in region files, like
+/// the block states of voxels, in the order of their ids.
+///
+/// The order is restored with `Registry::reserve` before anything is registered,
+/// so saved ids stay valid when blocks are added or removed between runs.
+#[derive(Clone, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct RegistryIds(BTreeMap<String, Vec<String>>);
+
+impl RegistryIds {
+    /// Read the ids saved in this region directory.
+    /// A directory without saved ids, like one of a new world, has none.
+    pub fn load(region_dir: &Path) -> io::Result<Self> {
+        match fs::read_to_string(region_dir.join(REGISTRY_IDS_FILE)) {
+            Ok(text) => toml::from_str(&text).map_err(io::Error::other),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Write the ids to the region directory, creating it if it doesn't exist.
+    pub fn save(&self, region_dir: &Path) -> io::Result<()> {
+        fs::create_dir_all(region_dir)?;
+        let text = toml::to_string_pretty(self).map_err(io::Error::other)?;
+        write_replacing(&region_dir.join(REGISTRY_IDS_FILE), &text)
+    }
+
+    /// Names of the entries of a registry, empty if none were saved.
+    pub fn get(&self, registry: &str) -> &[String] {
+        self.0.get(registry).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn set<S: Into<String>>(&mut self, registry: &str, names: impl IntoIterator<Item = S>) {
+        self.0.insert(
+            registry.to_string(),
+            names.into_iter().map(Into::into).collect(),
+        );
+    }
+}
+
+/// The players that joined a world, by the key their client sent with `PlayerLogin`.
+/// Players keep the number they were given when they first joined, so their name in
+/// chat, like "Player 3", is the same in every session and after restarts.
+/// The keys are secret, since whoever sends one is that player.
+#[derive(Clone, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct KnownPlayers(BTreeMap<String, u32>);
+
+impl KnownPlayers {
+    /// Read the players saved in this region directory.
+    /// A directory without saved players, like one of a new world, has none.
+    pub fn load(region_dir: &Path) -> io::Result<Self> {
+        match fs::read_to_string(region_dir.join(PLAYERS_FILE)) {
+            Ok(text) => toml::from_str(&text).map_err(io::Error::other),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Write the players to the region directory, creating it if it doesn't exist.
+    pub fn save(&self, region_dir: &Path) -> io::Result<()> {
+        fs::create_dir_all(region_dir)?;
+        let text = toml::to_string_pretty(self).map_err(io::Error::other)?;
+        write_replacing(&region_dir.join(PLAYERS_FILE), &text)
+    }
+
+    /// Number of the player with this key, or None if they haven't joined before.
+    pub fn get(&self, key: &str) -> Option<u32> {
+        self.0.get(key).copied()
+    }
+
+    /// Number of the player with this key, giving them the next free number if they
+    /// haven't joined before. Also returns whether the player is new.
+    pub fn get_or_insert(&mut self, key: &str) -> (u32, bool) {
+        if let Some(id) = self.get(key) {
+            return (id, false);
+        }
+
+        // numbers start at 1 and are never reused.
+        let id = self.0.values().max().map_or(1, |max| max + 1);
+        self.0.insert(key.to_string(), id);
+        (id, true)
+    }
+}
+
+/// An area of the world, over its full height, where only some players may place and
+/// break blocks, like the spawn. Players are named by their name in chat, which is
+/// the same in every session, see `KnownPlayers`.
+#[derive(Clone, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProtectedRegion {
+    /// Smallest x and z in the region, inclusive.
+    pub min: [i32; 2],
+
+    /// Largest x and z in the region, exclusive.
+    pub max: [i32; 2],
+
+    /// Players that may build in the region, "*" allows every player.
+    pub allow: Vec<String>,
+
+    /// Players that may not build in the region, even if `allow` has "*".
+    pub deny: Vec<String>,
+}
+
+impl ProtectedRegion {
+    /// A region of this area, that no player may build in yet.
+    pub fn new(area: IArea) -> Self {
+        Self {
+            min: area.min.to_array(),
+            max: area.max.to_array(),
+            ..default()
+        }
+    }
+
+    pub fn area(&self) -> IArea {
+        IArea::new(IVec2::from_array(self.min), IVec2::from_array(self.max))
+    }
+
+    /// Whether the player with this name may build in the region.
+    pub fn allows(&self, player: &str) -> bool {
+        !self.deny.iter().any(|name| name == player)
+            && self.allow.iter().any(|name| name == "*" || name == player)
+    }
+}
+
+/// The protected regions of a world by name, saved next to its region files.
+#[derive(Clone, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Protections(BTreeMap<String, ProtectedRegion>);
+
+impl Protections {
+    /// Read the protected regions saved in this region directory.
+    /// A directory without saved regions, like one of a new world, has none.
+    pub fn load(region_dir: &Path) -> io::Result<Self> {
+        match fs::read_to_string(region_dir.join(PROTECTIONS_FILE)) {
+            Ok(text) => toml::from_str(&text).map_err(io::Error::other),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Write the regions to the region directory, creating it if it doesn't exist.
+    pub fn save(&self, region_dir: &Path) -> io::Result<()> {
+        fs::create_dir_all(region_dir)?;
+        let text = toml::to_string_pretty(self).map_err(io::Error::other)?;
+        write_replacing(&region_dir.join(PROTECTIONS_FILE), &text)
+    }
+
+    /// Whether the player with this name may build at a position, which
+    /// needs every region the position is in to allow them.
+    pub fn allows(&self, player: &str, pos: IVec2) -> bool {
+        self.0
+            .values()
+            .filter(|region| region.area().contains(pos))
+            .all(|region| region.allows(player))
+    }
+
+    pub fn get(&self, name: &str) -> Option<&ProtectedRegion> {
+        self.0.get(name)
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut ProtectedRegion> {
+        self.0.get_mut(name)
+    }
+
+    /// Add a region, replacing the region with the same name.
+    pub fn insert(&mut self, name: impl Into<String>, region: ProtectedRegion) {
+        self.0.insert(name.into(), region);
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<ProtectedRegion> {
+        self.0.remove(name)
+    }
+
+    /// The regions and their names, in name order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &ProtectedRegion)> {
+        self.0.iter().map(|(name, region)| (name.as_str(), region))
+    }
+}
+
+/// Migrations of the toml files of a world save.
+pub fn toml_migrations() -> Migrations<toml::Table> {
+    let mut migrations = Migrations::new();
+    // version 0 was saved before world.toml had a version, and is otherwise the same.
+    migrations.add(WORLD_INFO_KIND, 0, |_| Ok(()));
+    migrations
+}
+
+/// Parse the text of a seed field the same way for every world.
+/// Numbers are used as-is, other text is hashed, and empty text is random.
+pub fn parse_seed(text: &str) -> i64 {
+    let text = text.trim();
+    if text.is_empty() {
+        getrandom::u64().unwrap_or_else(|_| unix_now()) as i64
+    } else if let Ok(seed) = text.parse::<i64>() {
+        seed
+    } else {
+        fxhash::hash64(text) as i64
+    }
+}
+
+/// A world directory found in the saves folder.
+pub struct SaveEntry {
+    pub dir: PathBuf,
+    pub info: WorldInfo,
+
+    /// Total size of the files in the world directory, in bytes.
+    pub size: u64,
+}
+
+/// Find every world in the saves folder, most recently played first.
+/// Directories with a world.toml that can't be read are skipped.
+pub fn list_saves(saves: &Path) -> Vec<SaveEntry> {
+    let Ok(iter) = fs::read_dir(saves) else {
+        return Vec::new();
+    };
+
+    let mut entries = iter
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            if !entry.file_type().is_ok_and(|ty| ty.is_dir()) {
+                return None;
+            }
+
+            let dir = entry.path();
+            let info = WorldInfo::load_or_default(&dir)
+                .inspect_err(|e| {
+                    warn!(
+                        "[D410] Failed to read world info in: '{}' with error: '{e}'",
+                        dir.display()
+                    )
+                })
+                .ok()?;
+            let size = dir_size(&dir);
+            Some(SaveEntry { dir, info, size })
+        })
+        .collect::<Vec<_>>();
+
+    entries.sort_by(|a, b| {
+        b.info
+            .last_played
+            .cmp(&a.info.last_played)
+            .then_with(|| a.info.name.cmp(&b.info.name))
+    });
+    entries
+}
+
+/// Get a directory in the saves folder for a world with this name that doesn't
+/// exist yet. Characters that aren't allowed in file names are replaced, and a
+/// number is appended if the name is taken.
+pub fn new_world_dir(saves: &Path, name: &str) -> PathBuf {
+    let mut base = name
+        .trim()
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect::<String>();
+    if base.is_empty() || base.chars().all(|c| c == '.') {
+        base = "world".into();
+    }
+
+    let mut path = saves.join(&base);
+    let mut i = 1;
+    while path.exists() {
+        path = saves.join(format!("{base} {i}"));
+        i += 1;
+    }
+    path
+}
+
+/// Directories of the data packs in a data packs folder, highest priority first.
+/// Packs are applied in name order, so a pack overrides the packs whose names sort before it.
+pub fn data_pack_dirs(packs_dir: &Path) -> Vec<PathBuf> {
+    let Ok(iter) = fs::read_dir(packs_dir) else {
+        return Vec::new();
+    };
+
+    let mut dirs = iter
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_ok_and(|ty| ty.is_dir()))
+        .map(|entry| entry.path())
+        .collect::<Vec<_>>();
+    dirs.sort_by(|a, b| b.file_name().cmp(&a.file_name()));
+    dirs
+}
+
+/// Total size of all files in a directory, recursively.
+pub fn dir_size(dir: &Path) -> u64 {
+    walkdir::WalkDir::new(dir)
+        .into_iter()
+        .filter_map(|entry| entry.ok()?.metadata().ok())
+        .filter(|meta| meta.is_file())
+        .map(|meta| meta.len())
+        .sum()
+}
+
+/// Seconds since the unix epoch.
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+/// Year, month and day of a number of days since the unix epoch.
+/// See http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+pub fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Write the file under another name first and rename it over the file, so a crash
+/// while writing never leaves a half-written file in place of the old one.
+fn write_replacing(path: &Path, text: &str) -> io::Result<()> {
+    let temp = path.with_extension("tmp");
+    fs::write(&temp, text)?;
+    fs::rename(&temp, path)
+}
+
+fn dir_name(dir: &Path) -> String {
+    dir.file_name()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::TempDir;
+
+    #[test]
+    fn world_info_roundtrip() {
+        let info = WorldInfo::new("My World", -42, 384);
+        let text = toml::to_string_pretty(&info).unwrap();
+        assert_eq!(toml::from_str::<WorldInfo>(&text).unwrap(), info);
+        assert_eq!(info.height(), 384);
+        assert_eq!(info.max_y, 256);
+    }
+
+    #[test]
+    fn world_info_without_version_is_migrated() {
+        let info = WorldInfo::parse("name = \"Old\"\nseed = 7\n").unwrap();
+        assert_eq!(info.version, toml_migrations().latest(WORLD_INFO_KIND));
+        assert_eq!(info.name, "Old");
+        assert_eq!(info.seed, 7);
+
+        assert!(WorldInfo::parse("version = 999\n").is_err());
+    }
+
+    #[test]
+    fn registry_ids_roundtrip() {
+        let mut ids = RegistryIds::default();
+        ids.set("block_states", ["air", "stone", "stair[facing=east]"]);
+
+        let text = toml::to_string_pretty(&ids).unwrap();
+        let loaded = toml::from_str::<RegistryIds>(&text).unwrap();
+        assert_eq!(loaded, ids);
+        assert_eq!(loaded.get("block_states")[2], "stair[facing=east]");
+        assert!(loaded.get("items").is_empty());
+    }
+
+    #[test]
+    fn registry_ids_replace_the_saved_file() {
+        let dir = TempDir::new("registry-ids");
+        let mut ids = RegistryIds::default();
+        ids.set("block_states", ["air", "stone"]);
+        ids.save(&dir).unwrap();
+        ids.set("block_states", ["air", "stone", "dirt"]);
+        ids.save(&dir).unwrap();
+
+        assert_eq!(RegistryIds::load(&dir).unwrap(), ids);
+        assert!(!dir.join(REGISTRY_IDS_FILE).with_extension("tmp").exists());
+
+        fs::write(dir.join(REGISTRY_IDS_FILE), "block_states = [").unwrap();
+        assert!(RegistryIds::load(&dir).is_err());
+    }
+
+    #[test]
+    fn known_players_keep_their_numbers() {
+        let mut players = KnownPlayers::default();
+        assert_eq!(players.get_or_insert("aa"), (1, true));
+        assert_eq!(players.get_or_insert("bb"), (2, true));
+        assert_eq!(players.get_or_insert("aa"), (1, false));
+
+        let text = toml::to_string_pretty(&players).unwrap();
+        let mut loaded = toml::from_str::<KnownPlayers>(&text).unwrap();
+        assert_eq!(loaded, players);
+        assert_eq!(loaded.get("bb"), Some(2));
+        assert_eq!(loaded.get_or_insert("cc"), (3, true));
+    }
+
+    #[test]
+    fn protected_regions_allow_listed_players() {
+        let mut spawn = ProtectedRegion::new(IArea::new(IVec2::splat(-16), IVec2::splat(16)));
+        spawn.allow.push("Player 1".into());
+        let mut plot = ProtectedRegion::new(IArea::new(IVec2::ZERO, IVec2::splat(32)));
+        plot.allow.push("*".into());
+        plot.deny.push("Player 2".into());
+
+        let mut protections = Protections::default();
+        protections.insert("spawn", spawn);
+        protections.insert("plot", plot);
+
+        // both regions have to allow a player where they overlap.
+        assert!(protections.allows("Player 1", IVec2::new(5, 5)));
+        assert!(!protections.allows("Player 3", IVec2::new(5, 5)));
+        assert!(protections.allows("Player 3", IVec2::new(20, 20)));
+        assert!(!protections.allows("Player 2", IVec2::new(20, 20)));
+        assert!(protections.allows("Player 2", IVec2::new(32, 0)));
+
+        let text = toml::to_string_pretty(&protections).unwrap();
+        assert_eq!(toml::from_str::<Protections>(&text).unwrap(), protections);
+    }
+
+    #[test]
+    fn parse_seed_numbers_and_text() {
+        assert_eq!(parse_seed("12345"), 12345);
+        assert_eq!(parse_seed(" -7 "), -7);
+        assert_eq!(parse_seed("hello"), parse_seed("hello"));
+        assert_ne!(parse_seed("hello"), parse_seed("world"));
+    }
+
+    #[test]
+    fn new_world_dir_sanitizes() {
+        let saves = Path::new("/nonexistent/saves");
+        assert_eq!(new_world_dir(saves, "a/b:c"), saves.join("a_b_c"));
+        assert_eq!(new_world_dir(saves, "  "), saves.join("world"));
+        assert_eq!(new_world_dir(saves, ".."), saves.join("world"));
+    }
+
+    #[test]
+    fn data_packs_later_names_first() {
+        let dir = TempDir::new("datapacks");
+        for name in ["10-trees", "00-base", "20-ores"] {
+            fs::create_dir_all(dir.join(name)).unwrap();
+        }
+        fs::write(dir.join("readme.txt"), "not a pack").unwrap();
+
+        let dirs = data_pack_dirs(&dir);
+        assert_eq!(
+            dirs,
+            [
+                dir.join("20-ores"),
+                dir.join("10-trees"),
+                dir.join("00-base")
+            ]
+        );
+        assert!(data_pack_dirs(&dir.join("missing")).is_empty());
+    }
+}
@@ -1,5 +1,13 @@
 use bevy::prelude::*;
-use std::path::{Path, PathBuf};
+use protocol::types::PlayerLogin;
+use std::{
+    fs, io,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+/// Name of the file with the key servers know this player by, in the root directory.
+pub const PLAYER_KEY_FILE: &str = "player.key";
 
 #[derive(Resource, Deref)]
 pub struct Version(pub String);
@@ -29,3 +37,35 @@ impl Default for RootPath {
         }
     }
 }
+
+/// Read the key servers know this player by, see `PlayerLogin`, or generate
+/// and save one the first time. A key that can't be read isn't replaced,
+/// since the player would lose their name on every server they joined.
+pub fn load_player_key(root: &RootPath) -> io::Result<PlayerLogin> {
+    let path = root.join(PLAYER_KEY_FILE);
+    match fs::read_to_string(&path) {
+        Ok(text) => {
+            let text = text.trim();
+            let half = |range: Range<usize>| {
+                text.get(range)
+                    .and_then(|hex| u64::from_str_radix(hex, 16).ok())
+            };
+            match (text.len(), half(0..16), half(16..32)) {
+                (32, Some(a), Some(b)) => Ok(PlayerLogin { key: [a, b] }),
+                _ => Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "the key is not 32 hex digits",
+                )),
+            }
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            let random = || getrandom::u64().map_err(io::Error::other);
+            let login = PlayerLogin {
+                key: [random()?, random()?],
+            };
+            fs::write(&path, login.key_hex())?;
+            Ok(login)
+        }
+        Err(e) => Err(e),
+    }
+}
@@ -0,0 +1,148 @@
+//! Items, the things players hold and carry in their inventory.
+//!
+//! The registry is synchronized with the server on join, so the ids in an `ItemStack`
+//! mean the same item on both sides once the client has joined.
+
+use serde::{Deserialize, Serialize};
+
+use crate::registry::{Registry, RegistryId};
+
+/// Stack size of items that don't set one.
+pub const DEFAULT_MAX_STACK: u16 = 64;
+
+pub struct Item {
+    /// Most items that fit in one stack.
+    pub max_stack: u16,
+
+    /// Name of the block the item places, if it places one.
+    pub block: Option<String>,
+
+    /// Path of the texture drawn for the item in the inventory.
+    pub texture: Option<String>,
+}
+
+impl Item {
+    /// An item that places a block, drawn with one of the block's textures.
+    pub fn block(block: impl Into<String>, texture: impl Into<String>) -> Self {
+        Self {
+            max_stack: DEFAULT_MAX_STACK,
+            block: Some(block.into()),
+            texture: Some(texture.into()),
+        }
+    }
+}
+
+/// Items known to both the client and the server.
+pub fn builtin_items() -> Registry<Item> {
+    let mut items = Registry::new();
+    items.insert("stone", Item::block("stone", "textures/blocks/stone.png"));
+    items
+}
+
+/// A number of one item, like the contents of an inventory slot.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct ItemStack {
+    pub item: RegistryId,
+    pub count: u16,
+}
+
+impl ItemStack {
+    pub const fn new(item: RegistryId, count: u16) -> Self {
+        Self { item, count }
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Whether two stacks are of the same item, and could be merged.
+    pub fn stacks_with(&self, other: &ItemStack) -> bool {
+        self.item == other.item
+    }
+
+    /// Number of items that can be added before the stack is full.
+    pub fn space(&self, items: &Registry<Item>) -> u16 {
+        let max = items
+            .get(self.item)
+            .map_or(DEFAULT_MAX_STACK, |item| item.max_stack);
+        max.saturating_sub(self.count)
+    }
+
+    /// Move as many items of `other` into this stack as fit.
+    /// Returns true if `other` is empty afterwards.
+    pub fn merge(&mut self, other: &mut ItemStack, items: &Registry<Item>) -> bool {
+        if self.is_empty() {
+            self.item = other.item;
+        } else if !self.stacks_with(other) {
+            return other.is_empty();
+        }
+
+        let moved = other.count.min(self.space(items));
+        self.count += moved;
+        other.count -= moved;
+        other.is_empty()
+    }
+
+    /// Take up to `count` items out of this stack.
+    pub fn take(&mut self, count: u16) -> ItemStack {
+        let count = count.min(self.count);
+        self.count -= count;
+        ItemStack::new(self.item, count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn items() -> Registry<Item> {
+        let mut items = builtin_items();
+        items.insert(
+            "stick",
+            Item {
+                max_stack: 16,
+                block: None,
+                texture: None,
+            },
+        );
+        items
+    }
+
+    #[test]
+    fn merge_stops_at_max_stack() {
+        let items = items();
+        let stick = items.resolve("stick").unwrap();
+
+        let mut slot = ItemStack::new(stick, 10);
+        let mut held = ItemStack::new(stick, 10);
+        assert!(!slot.merge(&mut held, &items));
+        assert_eq!(slot.count, 16);
+        assert_eq!(held.count, 4);
+
+        let stone = items.resolve("stone").unwrap();
+        let mut other = ItemStack::new(stone, 1);
+        assert!(!slot.merge(&mut other, &items));
+        assert_eq!(other.count, 1);
+
+        let mut empty = ItemStack::new(stick, 0);
+        assert!(empty.merge(&mut other, &items));
+        assert_eq!(empty, ItemStack::new(stone, 1));
+    }
+
+    #[test]
+    fn take_splits_the_stack() {
+        let stone = RegistryId(0);
+        let mut stack = ItemStack::new(stone, 5);
+        assert_eq!(stack.take(3), ItemStack::new(stone, 3));
+        assert_eq!(stack.take(3), ItemStack::new(stone, 2));
+        assert!(stack.is_empty());
+    }
+
+    #[test]
+    fn stacks_are_serialized_by_id() {
+        let stack = ItemStack::new(RegistryId(3), 12);
+        let json = serde_json::to_string(&stack).unwrap();
+        assert_eq!(json, r#"{"item":3,"count":12}"#);
+        assert_eq!(serde_json::from_str::<ItemStack>(&json).unwrap(), stack);
+    }
+}
@@ -10,15 +10,21 @@ pub mod blocks;
 pub mod blockstates;
 pub mod fs;
 pub mod info;
+pub mod items;
 pub mod locale;
+pub mod logging;
+pub mod loot;
 pub mod queue;
+pub mod recipes;
 pub mod registry;
 pub mod sequence;
 pub mod states;
+pub mod structures;
 pub mod table;
 pub mod tags;
 pub mod text;
 pub mod util;
+pub mod worldgen;
 
 pub struct OpenvoxelDataPlugin;
 
@@ -28,11 +34,7 @@ impl Plugin for OpenvoxelDataPlugin {
         let root_path = info::RootPath::default();
         app
             // initialize resources
-            // .insert_resource({
-            //     let mut rdr = fs::packs::AssetPackReader::default();
-            //     rdr.mount_to_end(root_path.join("assets/vanilla"));
-            //     rdr
-            // })
+            .init_resource::<fs::packs::AssetPackReader>()
             .insert_resource(root_path)
             .init_resource::<info::Version>()
             .init_resource::<Locale>()
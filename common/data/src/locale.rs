@@ -1,40 +1,285 @@
-use std::fs;
-use std::{io, path::Path};
+//! Translations of the text shown to players.
+//!
+//! Each language is a RON map of keys to text in the `locale` folder of the assets
+//! directory or a pack, named after the language, like `locale/en-us.ron`.
+//! Keys a language doesn't have are looked up in the languages it falls back to,
+//! its base language ("en" for "en-gb") and then `DEFAULT_LANGUAGE`.
+//!
+//! Text can take arguments, `{}` is replaced by the next argument and `{0}` by the
+//! argument at that index. `{{` and `}}` are a literal brace.
+
+use std::{
+    fmt::{Display, Write},
+    fs, io,
+    path::{Path, PathBuf},
+};
 
 use bevy::prelude::*;
 use fxhash::FxHashMap;
 
+/// Language used when the selected language doesn't have a key.
+pub const DEFAULT_LANGUAGE: &str = "en-us";
+
+/// Translate a key with a `Locale`, replacing the placeholders of the text with the arguments.
+///
+/// ```ignore
+/// let text = tr!(locale, "ui.time.minutes-ago", minutes);
+/// ```
+#[macro_export]
+macro_rules! tr {
+    ($locale:expr, $key:expr $(,)?) => {
+        $locale.get($key)
+    };
+    ($locale:expr, $key:expr, $($arg:expr),+ $(,)?) => {
+        $locale.format($key, &[$(&$arg as &dyn ::std::fmt::Display),+])
+    };
+}
+
 #[derive(Resource)]
 pub struct Locale {
-    map: FxHashMap<String, String>,
+    language: String,
+
+    /// Text of the language and the languages it falls back to, in lookup order.
+    tables: Vec<FxHashMap<String, String>>,
+
+    /// Every language with a file in the directories it was loaded from, sorted.
+    languages: Vec<String>,
 }
 
 impl Locale {
+    /// Read a language and its fallbacks from the `locale` folder of each directory.
+    /// Earlier directories take priority, their keys replace the keys of later ones.
+    /// Files that fail to load are skipped.
+    pub fn load<P: AsRef<Path>>(dirs: &[P], language: &str) -> (Self, Vec<LocaleError>) {
+        let mut errors = Vec::new();
+        let mut tables = Vec::new();
+        for lang in fallback_chain(language) {
+            let mut table = FxHashMap::default();
+            for dir in dirs.iter().rev() {
+                let path = dir.as_ref().join("locale").join(format!("{lang}.ron"));
+                match read_table(&path) {
+                    Ok(Some(map)) => table.extend(map),
+                    Ok(None) => {}
+                    Err(kind) => errors.push(LocaleError { path, kind }),
+                }
+            }
+            tables.push(table);
+        }
+
+        let mut languages = Vec::new();
+        for dir in dirs {
+            let Ok(entries) = fs::read_dir(dir.as_ref().join("locale")) else {
+                continue;
+            };
+
+            for path in entries.flatten().map(|entry| entry.path()) {
+                if path.extension().is_some_and(|ext| ext == "ron")
+                    && let Some(name) = path.file_stem().and_then(|stem| stem.to_str())
+                    && !languages.iter().any(|lang| lang == name)
+                {
+                    languages.push(name.to_string());
+                }
+            }
+        }
+        languages.sort();
+
+        let locale = Self {
+            language: language.to_string(),
+            tables,
+            languages,
+        };
+        (locale, errors)
+    }
+
+    /// The language selected when the locale was loaded.
+    pub fn language(&self) -> &str {
+        &self.language
+    }
+
+    /// Every language that can be loaded, sorted.
+    pub fn languages(&self) -> &[String] {
+        &self.languages
+    }
+
+    /// Text of a key, from the first language in the fallback chain that has it.
+    pub fn lookup(&self, key: &str) -> Option<&str> {
+        self.tables
+            .iter()
+            .find_map(|table| table.get(key))
+            .map(String::as_str)
+    }
+
+    /// Text of a key, or the key itself if no language has it.
     pub fn get(&self, label: impl AsRef<str>) -> String {
         let label = label.as_ref();
-        self.map.get(label).cloned().unwrap_or_else(|| label.into())
+        self.lookup(label).unwrap_or(label).to_string()
     }
 
-    pub fn load_file(&mut self, path: impl AsRef<Path>) -> io::Result<()> {
-        let data = fs::read(path)?;
-        match ron::de::from_bytes::<FxHashMap<String, String>>(&data) {
-            Err(e) => {
-                error!("[D110] Failed to deserialize Localization file with error: '{e}'");
-            }
-            Ok(map) => {
-                for (key, val) in map {
-                    self.map.insert(key, val);
-                }
-            }
+    /// Text of a key with its placeholders replaced by the arguments.
+    /// Prefer the `tr!` macro, which doesn't need the arguments as a slice.
+    pub fn format(&self, label: impl AsRef<str>, args: &[&dyn Display]) -> String {
+        let label = label.as_ref();
+        format_text(self.lookup(label).unwrap_or(label), args)
+    }
+
+    /// Set the text of a key in the selected language.
+    pub fn insert(&mut self, key: impl Into<String>, text: impl Into<String>) {
+        if self.tables.is_empty() {
+            self.tables.push(FxHashMap::default());
         }
-        Ok(())
+        self.tables[0].insert(key.into(), text.into());
     }
 }
 
 impl Default for Locale {
     fn default() -> Self {
         Self {
-            map: FxHashMap::default(),
+            language: DEFAULT_LANGUAGE.to_string(),
+            tables: Vec::new(),
+            languages: Vec::new(),
+        }
+    }
+}
+
+/// Languages a key is looked up in, in order, starting with the language itself.
+/// A language like "en-gb" falls back to "en", and every language to `DEFAULT_LANGUAGE`.
+pub fn fallback_chain(language: &str) -> Vec<String> {
+    let mut chain = vec![language.to_string()];
+    if let Some((base, _)) = language.split_once('-') {
+        chain.push(base.to_string());
+    }
+    if !chain.iter().any(|lang| lang == DEFAULT_LANGUAGE) {
+        chain.push(DEFAULT_LANGUAGE.to_string());
+    }
+    chain
+}
+
+/// Replace the placeholders of a text with the arguments.
+/// Placeholders without an argument, or that aren't a number, are kept as they are.
+pub fn format_text(text: &str, args: &[&dyn Display]) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut next = 0;
+    let mut rest = text;
+    while let Some(i) = rest.find(['{', '}']) {
+        out.push_str(&rest[..i]);
+        rest = &rest[i..];
+
+        if rest.starts_with("{{") || rest.starts_with("}}") {
+            out.push_str(&rest[..1]);
+            rest = &rest[2..];
+            continue;
+        }
+
+        let placeholder = rest.starts_with('{').then(|| rest.find('}')).flatten();
+        let Some(end) = placeholder else {
+            out.push_str(&rest[..1]);
+            rest = &rest[1..];
+            continue;
+        };
+
+        let inner = &rest[1..end];
+        let index = match inner {
+            "" => {
+                next += 1;
+                Some(next - 1)
+            }
+            _ => inner.parse::<usize>().ok(),
+        };
+
+        match index.and_then(|i| args.get(i)) {
+            Some(arg) => write!(out, "{arg}").unwrap(),
+            None => out.push_str(&rest[..=end]),
         }
+        rest = &rest[end + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Read a language file, None if it doesn't exist.
+fn read_table(path: &Path) -> Result<Option<FxHashMap<String, String>>, LocaleErrorKind> {
+    let data = match fs::read(path) {
+        Ok(data) => data,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(LocaleErrorKind::Read(e)),
+    };
+
+    ron::de::from_bytes(&data)
+        .map(Some)
+        .map_err(LocaleErrorKind::Parse)
+}
+
+/// A language file that couldn't be loaded.
+#[derive(thiserror::Error, Debug)]
+#[error("{} (in '{}')", .kind, .path.display())]
+pub struct LocaleError {
+    pub path: PathBuf,
+    pub kind: LocaleErrorKind,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum LocaleErrorKind {
+    #[error("[D110] Failed to deserialize Localization file with error: '{0}'")]
+    Parse(ron::error::SpannedError),
+
+    #[error("[D111] Failed to read Localization file: {0}")]
+    Read(io::Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::TempDir;
+
+    #[test]
+    fn format_replaces_placeholders() {
+        let args: [&dyn Display; 2] = [&5, &"stone"];
+        assert_eq!(format_text("{} minutes ago", &args), "5 minutes ago");
+        assert_eq!(format_text("{1} x{0}", &args), "stone x5");
+        assert_eq!(format_text("{} {} {}", &args), "5 stone {}");
+        assert_eq!(format_text("{{}} {name} }", &args), "{} {name} }");
+        assert_eq!(format_text("unclosed {", &args), "unclosed {");
+    }
+
+    #[test]
+    fn languages_fall_back_to_base_and_default() {
+        assert_eq!(fallback_chain("en-gb"), ["en-gb", "en", "en-us"]);
+        assert_eq!(fallback_chain("en-us"), ["en-us", "en"]);
+        assert_eq!(fallback_chain("fr"), ["fr", "en-us"]);
+    }
+
+    #[test]
+    fn load_layers_packs_and_fallbacks() {
+        let dir = TempDir::new("locale");
+        let (pack, assets) = (dir.join("pack"), dir.join("assets"));
+        fs::create_dir_all(pack.join("locale")).unwrap();
+        fs::create_dir_all(assets.join("locale")).unwrap();
+
+        fs::write(
+            assets.join("locale/en-us.ron"),
+            r#"{ "color": "Color", "quit": "Quit", "ago": "{} minutes ago" }"#,
+        )
+        .unwrap();
+        fs::write(assets.join("locale/en-gb.ron"), r#"{ "color": "Colour" }"#).unwrap();
+        fs::write(pack.join("locale/en-gb.ron"), r#"{ "quit": "Leave" }"#).unwrap();
+        fs::write(pack.join("locale/broken.ron"), "{ oops").unwrap();
+
+        let (locale, errors) = Locale::load(&[&pack, &assets], "en-gb");
+        assert!(errors.is_empty());
+        assert_eq!(locale.language(), "en-gb");
+        assert_eq!(locale.languages(), ["broken", "en-gb", "en-us"]);
+        assert_eq!(locale.get("color"), "Colour");
+        assert_eq!(locale.get("quit"), "Leave");
+        assert_eq!(locale.get("missing"), "missing");
+        assert_eq!(tr!(locale, "ago", 3), "3 minutes ago");
+
+        let (_, errors) = Locale::load(&[&pack, &assets], "broken");
+        assert!(matches!(
+            errors[..],
+            [LocaleError {
+                kind: LocaleErrorKind::Parse(_),
+                ..
+            }]
+        ));
     }
 }
@@ -0,0 +1,508 @@
+//! Logging shared by the client and server.
+//!
+//! The level of each module is set by a `LogConfig`, or by the `RUST_LOG` environment
+//! variable when it is set, with directives like `info,server::net=debug`. Everything
+//! logged is also written to `latest.log` in a logs directory. When it grows past the
+//! size limit, or the next session starts, it is moved to `old-1.log` and the older
+//! files are shifted up, keeping `max_files` of them.
+//!
+//! The levels can be changed while running through the `LogLevels` resource.
+
+use std::{
+    collections::BTreeMap,
+    env,
+    fs::{self, File},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Mutex,
+};
+
+use bevy::{
+    log::{
+        BoxedLayer, Level, LogPlugin,
+        tracing_subscriber::{EnvFilter, Layer, Registry, filter::LevelFilter, fmt, reload},
+    },
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+
+/// Name of the logs directory, in the root directory of the client or the working
+/// directory of a dedicated server.
+pub const LOGS_DIR: &str = "logs";
+
+/// Environment variable that overrides the levels of the config.
+pub const LOG_ENV: &str = "RUST_LOG";
+
+/// Name of the file logs of the current session are written to.
+pub const LATEST_LOG: &str = "latest.log";
+
+/// Levels of modules and where logs are kept.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LogConfig {
+    /// Level of modules without a filter, one of "off", "error", "warn", "info", "debug"
+    /// or "trace".
+    pub level: String,
+
+    /// Levels of modules by their path, like `server::net` or `wgpu`.
+    /// A filter applies to the submodules of the module too.
+    pub filters: BTreeMap<String, String>,
+
+    /// Most old log files kept, the oldest are deleted.
+    pub max_files: usize,
+
+    /// Size in bytes `latest.log` can grow to before it is moved.
+    pub max_file_size: u64,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            level: "info".into(),
+            filters: BTreeMap::from([
+                ("wgpu".into(), "error".into()),
+                ("naga".into(), "warn".into()),
+            ]),
+            max_files: 5,
+            max_file_size: 16 * 1024 * 1024,
+        }
+    }
+}
+
+impl LogConfig {
+    /// The levels as filter directives, like `info,wgpu=error`.
+    pub fn directives(&self) -> String {
+        directives(&self.level, &self.filters)
+    }
+
+    /// Replace the levels with the ones of filter directives, like `info,wgpu=error`.
+    /// The level of modules without a filter is kept if the directives don't set it.
+    pub fn set_directives(&mut self, directives: &str) -> Result<(), LogLevelError> {
+        let mut level = self.level.clone();
+        let mut filters = BTreeMap::new();
+        for directive in directives.split(',').map(str::trim) {
+            match directive.split_once('=') {
+                _ if directive.is_empty() => {}
+                Some((target, target_level)) => {
+                    validate_target(target)?;
+                    filters.insert(target.to_string(), parse_level(target_level)?);
+                }
+                None => level = parse_level(directive)?,
+            }
+        }
+
+        self.level = level;
+        self.filters = filters;
+        Ok(())
+    }
+}
+
+fn directives(level: &str, filters: &BTreeMap<String, String>) -> String {
+    let mut directives = level.to_string();
+    for (target, level) in filters {
+        directives.push_str(&format!(",{target}={level}"));
+    }
+    directives
+}
+
+/// The level in lowercase, if it is one.
+fn parse_level(level: &str) -> Result<String, LogLevelError> {
+    LevelFilter::from_str(level.trim())
+        .map(|filter| filter.to_string().to_lowercase())
+        .map_err(|_| LogLevelError::InvalidLevel(level.to_string()))
+}
+
+fn validate_target(target: &str) -> Result<(), LogLevelError> {
+    let valid = !target.is_empty()
+        && target
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == ':');
+
+    if valid {
+        Ok(())
+    } else {
+        Err(LogLevelError::InvalidTarget(target.to_string()))
+    }
+}
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum LogLevelError {
+    #[error("[D270] '{0}' is not one of off, error, warn, info, debug or trace")]
+    InvalidLevel(String),
+
+    #[error("[D271] '{0}' is not a module path")]
+    InvalidTarget(String),
+
+    #[error("[D272] Failed to change the log filter: {0}")]
+    Reload(String),
+}
+
+/// The levels logs are filtered with, which can be changed while running.
+///
+/// When `RUST_LOG` is set, bevy filters logs with it as well, so modules
+/// can't log more than it allows.
+#[derive(Resource)]
+pub struct LogLevels {
+    level: String,
+    filters: BTreeMap<String, String>,
+    handle: reload::Handle<EnvFilter, Registry>,
+}
+
+impl LogLevels {
+    pub fn new(config: &LogConfig, handle: reload::Handle<EnvFilter, Registry>) -> Self {
+        Self {
+            level: config.level.clone(),
+            filters: config.filters.clone(),
+            handle,
+        }
+    }
+
+    /// Level of modules without a filter.
+    pub fn level(&self) -> &str {
+        &self.level
+    }
+
+    /// Levels of modules by their path.
+    pub fn filters(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.filters
+            .iter()
+            .map(|(target, level)| (target.as_str(), level.as_str()))
+    }
+
+    /// The levels as filter directives, like `info,wgpu=error`.
+    pub fn directives(&self) -> String {
+        directives(&self.level, &self.filters)
+    }
+
+    /// Set the level of a module, or of modules without a filter if there is no target.
+    pub fn set(&mut self, target: Option<&str>, level: &str) -> Result<(), LogLevelError> {
+        let level = parse_level(level)?;
+        match target {
+            Some(target) => {
+                validate_target(target)?;
+                self.filters.insert(target.to_string(), level);
+            }
+            None => self.level = level,
+        }
+        self.reload()
+    }
+
+    /// Remove the filter of a module, so it logs at the level of modules without one.
+    /// Returns false if the module had no filter.
+    pub fn reset(&mut self, target: &str) -> Result<bool, LogLevelError> {
+        if self.filters.remove(target).is_none() {
+            return Ok(false);
+        }
+        self.reload().map(|_| true)
+    }
+
+    /// Replace every level with the ones of a config.
+    pub fn apply(&mut self, config: &LogConfig) -> Result<(), LogLevelError> {
+        parse_level(&config.level)?;
+        for (target, level) in &config.filters {
+            validate_target(target)?;
+            parse_level(level)?;
+        }
+
+        self.level = config.level.clone();
+        self.filters = config.filters.clone();
+        self.reload()
+    }
+
+    fn reload(&self) -> Result<(), LogLevelError> {
+        let filter = EnvFilter::try_new(self.directives())
+            .map_err(|e| LogLevelError::Reload(e.to_string()))?;
+        self.handle
+            .reload(filter)
+            .map_err(|e| LogLevelError::Reload(e.to_string()))
+    }
+}
+
+/// Run the arguments of a `log` command, returning the reply.
+///
+/// `log` shows the levels, `log <level>` sets the level of modules without a filter,
+/// `log <module> <level>` sets the level of a module, and `log <module> reset` removes
+/// the filter of a module.
+pub fn run_log_command(levels: &mut LogLevels, args: &[&str]) -> String {
+    let result = match *args {
+        [] => return format!("Log levels: {}", levels.directives()),
+        [level] => levels
+            .set(None, level)
+            .map(|_| format!("Set the log level to {level}.")),
+        [target, "reset"] => levels.reset(target).map(|reset| match reset {
+            true => format!("Removed the log filter of {target}."),
+            false => format!("{target} has no log filter."),
+        }),
+        [target, level] => levels
+            .set(Some(target), level)
+            .map(|_| format!("Set the log level of {target} to {level}.")),
+        _ => return "Usage: log [module] <level|reset>".into(),
+    };
+    result.unwrap_or_else(|e| e.to_string())
+}
+
+/// The file logs are written to, moved aside when it grows too large.
+pub struct LogFile {
+    dir: PathBuf,
+    max_files: usize,
+    max_size: u64,
+
+    /// None only while the file is being moved.
+    file: Option<File>,
+
+    /// Bytes written to the file.
+    size: u64,
+}
+
+impl LogFile {
+    /// Start a new `latest.log` in the directory, moving the one of the last session.
+    pub fn open(dir: impl Into<PathBuf>, max_files: usize, max_size: u64) -> io::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        rotate(&dir, max_files)?;
+
+        Ok(Self {
+            file: Some(File::create(dir.join(LATEST_LOG))?),
+            dir,
+            max_files,
+            max_size,
+            size: 0,
+        })
+    }
+
+    /// Path of the file logs are written to.
+    pub fn path(&self) -> PathBuf {
+        self.dir.join(LATEST_LOG)
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        // closed first, open files can't be renamed on every platform.
+        self.file = None;
+        rotate(&self.dir, self.max_files)?;
+        self.file = Some(File::create(self.path())?);
+        self.size = 0;
+        Ok(())
+    }
+}
+
+impl Write for LogFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.size > 0 && self.size + buf.len() as u64 > self.max_size {
+            self.rotate()?;
+        }
+
+        let Some(file) = &mut self.file else {
+            return Err(io::Error::other("log file is closed"));
+        };
+        let written = file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.file {
+            Some(file) => file.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Path of an old log file, `old-1.log` is the most recent.
+pub fn old_log_path(dir: &Path, n: usize) -> PathBuf {
+    dir.join(format!("old-{n}.log"))
+}
+
+/// Move `latest.log` to `old-1.log`, shifting the older files up and deleting
+/// the ones past `max_files`.
+fn rotate(dir: &Path, max_files: usize) -> io::Result<()> {
+    let latest = dir.join(LATEST_LOG);
+    if !latest.exists() {
+        return Ok(());
+    }
+    if max_files == 0 {
+        return fs::remove_file(latest);
+    }
+
+    let oldest = old_log_path(dir, max_files);
+    if oldest.exists() {
+        fs::remove_file(oldest)?;
+    }
+    for n in (1..max_files).rev() {
+        let from = old_log_path(dir, n);
+        if from.exists() {
+            fs::rename(from, old_log_path(dir, n + 1))?;
+        }
+    }
+    fs::rename(latest, old_log_path(dir, 1))
+}
+
+/// Sets up bevy's `LogPlugin` with levels that can be changed while running
+/// and a log file, and inserts the `LogLevels` resource.
+pub struct LoggingPlugin {
+    pub config: LogConfig,
+
+    /// Directory log files are written to, or None to not write any.
+    pub dir: Option<PathBuf>,
+
+    /// Another layer logs are sent to, like the log view of the server TUI.
+    pub custom_layer: fn(&mut App) -> Option<BoxedLayer>,
+}
+
+impl Default for LoggingPlugin {
+    fn default() -> Self {
+        Self {
+            config: LogConfig::default(),
+            dir: None,
+            custom_layer: |_| None,
+        }
+    }
+}
+
+/// What `LoggingPlugin` passes to the layers it builds, which can't capture it.
+#[derive(Resource)]
+struct LogSetup {
+    config: LogConfig,
+    dir: Option<PathBuf>,
+    custom_layer: fn(&mut App) -> Option<BoxedLayer>,
+}
+
+impl Plugin for LoggingPlugin {
+    fn build(&self, app: &mut App) {
+        let mut config = self.config.clone();
+        if let Ok(env) = env::var(LOG_ENV)
+            && let Err(e) = config.set_directives(&env)
+        {
+            // nothing is logged until the plugin is built.
+            eprintln!("[D273] Invalid {LOG_ENV}: '{env}', using the configured levels: {e}");
+        }
+
+        app.insert_resource(LogSetup {
+            config,
+            dir: self.dir.clone(),
+            custom_layer: self.custom_layer,
+        });
+
+        // everything passes bevy's filter, unless RUST_LOG is set, and the levels are
+        // applied by the reloadable filter of the custom layer instead.
+        app.add_plugins(LogPlugin {
+            level: Level::TRACE,
+            filter: String::new(),
+            custom_layer: log_layers,
+            ..default()
+        });
+    }
+}
+
+/// The reloadable filter, the log file, and the custom layer of the `LoggingPlugin`.
+fn log_layers(app: &mut App) -> Option<BoxedLayer> {
+    let setup = app.world_mut().remove_resource::<LogSetup>()?;
+    let filter = EnvFilter::try_new(setup.config.directives()).unwrap_or_else(|e| {
+        eprintln!("[D274] Invalid log levels, using 'info': {e}");
+        EnvFilter::new("info")
+    });
+    let (filter, handle) = reload::Layer::new(filter);
+
+    let file = setup.dir.and_then(|dir| {
+        match LogFile::open(&dir, setup.config.max_files, setup.config.max_file_size) {
+            Ok(file) => Some(file),
+            Err(e) => {
+                eprintln!(
+                    "[D275] Failed to open a log file in '{}': {e}",
+                    dir.display()
+                );
+                None
+            }
+        }
+    });
+    let file_layer = file.map(|file| fmt::layer().with_ansi(false).with_writer(Mutex::new(file)));
+
+    app.insert_resource(LogLevels::new(&setup.config, handle));
+    let custom_layer = (setup.custom_layer)(app);
+    Some(filter.and_then(file_layer).and_then(custom_layer).boxed())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::TempDir;
+
+    #[test]
+    fn directives_round_trip() {
+        let mut config = LogConfig::default();
+        assert_eq!(config.directives(), "info,naga=warn,wgpu=error");
+
+        config
+            .set_directives("debug, server::net=TRACE,wgpu=off")
+            .unwrap();
+        assert_eq!(config.level, "debug");
+        assert_eq!(config.directives(), "debug,server::net=trace,wgpu=off");
+
+        // the level is kept if it isn't set.
+        config.set_directives("client=warn").unwrap();
+        assert_eq!(config.directives(), "debug,client=warn");
+    }
+
+    #[test]
+    fn invalid_directives_are_rejected() {
+        let mut config = LogConfig::default();
+        assert_eq!(
+            config.set_directives("info,server=loud"),
+            Err(LogLevelError::InvalidLevel("loud".into()))
+        );
+        assert_eq!(
+            config.set_directives("server net=info"),
+            Err(LogLevelError::InvalidTarget("server net".into()))
+        );
+        assert_eq!(config, LogConfig::default());
+    }
+
+    #[test]
+    fn levels_change_while_running() {
+        let (_filter, handle) = reload::Layer::<_, Registry>::new(EnvFilter::new("info"));
+        let mut levels = LogLevels::new(&LogConfig::default(), handle);
+
+        levels.set(Some("server::net"), "debug").unwrap();
+        levels.set(None, "warn").unwrap();
+        assert_eq!(
+            levels.directives(),
+            "warn,naga=warn,server::net=debug,wgpu=error"
+        );
+
+        assert_eq!(levels.reset("server::net"), Ok(true));
+        assert_eq!(levels.reset("server::net"), Ok(false));
+        assert!(levels.set(None, "everything").is_err());
+        assert_eq!(levels.directives(), "warn,naga=warn,wgpu=error");
+
+        run_log_command(&mut levels, &["wgpu", "reset"]);
+        run_log_command(&mut levels, &["debug"]);
+        assert_eq!(levels.directives(), "debug,naga=warn");
+        assert_eq!(
+            run_log_command(&mut levels, &[]),
+            "Log levels: debug,naga=warn"
+        );
+    }
+
+    #[test]
+    fn log_files_rotate() {
+        let dir = TempDir::new("log-rotate");
+        for session in 0..4 {
+            let mut file = LogFile::open(&dir, 2, 1024).unwrap();
+            write!(file, "session {session}").unwrap();
+        }
+
+        let read = |path: PathBuf| fs::read_to_string(path).unwrap();
+        assert_eq!(read(dir.join(LATEST_LOG)), "session 3");
+        assert_eq!(read(old_log_path(&dir, 1)), "session 2");
+        assert_eq!(read(old_log_path(&dir, 2)), "session 1");
+        assert!(!old_log_path(&dir, 3).exists());
+
+        // a write that doesn't fit starts a new file.
+        let mut file = LogFile::open(&dir, 2, 8).unwrap();
+        file.write_all(b"12345").unwrap();
+        file.write_all(b"6789").unwrap();
+        assert_eq!(read(dir.join(LATEST_LOG)), "6789");
+        assert_eq!(read(old_log_path(&dir, 1)), "12345");
+        assert_eq!(read(old_log_path(&dir, 2)), "session 3");
+    }
+}
@@ -0,0 +1,466 @@
+//! Loot tables, read from the `loot_tables` folder of data packs, pick the items that drop
+//! when a block is broken or an entity dies.
+//!
+//! Each table is a JSON file named by its path in the folder, like `loot_tables/blocks/stone.json`
+//! for the stone block, or `loot_tables/entities/zombie.json` for a zombie:
+//!
+//! ```json
+//! {
+//!     "pools": [{
+//!         "rolls": { "min": 1, "max": 2 },
+//!         "conditions": [{ "condition": "random_chance", "chance": 0.5 }],
+//!         "entries": [
+//!             { "item": "stone", "weight": 3, "count": { "min": 1, "max": 4 } },
+//!             { "weight": 1 }
+//!         ]
+//!     }]
+//! }
+//! ```
+//!
+//! Every pool whose conditions pass is rolled a number of times, and each roll picks one
+//! of the entries whose conditions pass, weighted by their `weight`. Entries without an
+//! item drop nothing, which makes the other entries less likely.
+
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use math::rng::Rng;
+use serde::Deserialize;
+
+use crate::{
+    fs::path::iter_json_files_recursive,
+    items::{Item, ItemStack},
+    registry::{Registry, RegistryId},
+};
+
+#[derive(Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct LootTable {
+    #[serde(default)]
+    pub pools: Vec<LootPool>,
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct LootPool {
+    /// Number of entries picked from the pool.
+    #[serde(default = "NumberRange::one")]
+    pub rolls: NumberRange,
+
+    #[serde(default)]
+    pub conditions: Vec<LootCondition>,
+
+    pub entries: Vec<LootEntry>,
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct LootEntry {
+    /// Name of the item that drops, or None if the entry drops nothing.
+    pub item: Option<String>,
+
+    /// Chance of picking the entry, relative to the weight of the other entries of the pool.
+    #[serde(default = "default_weight")]
+    pub weight: u32,
+
+    #[serde(default = "NumberRange::one")]
+    pub count: NumberRange,
+
+    #[serde(default)]
+    pub conditions: Vec<LootCondition>,
+}
+
+fn default_weight() -> u32 {
+    1
+}
+
+/// A number, or a random number between `min` and `max`, inclusive.
+#[derive(Deserialize, Copy, Clone, Debug, Eq, PartialEq)]
+#[serde(untagged)]
+pub enum NumberRange {
+    Exact(u32),
+    Range { min: u32, max: u32 },
+}
+
+impl NumberRange {
+    pub const fn one() -> Self {
+        Self::Exact(1)
+    }
+
+    pub fn sample(&self, rng: &mut impl Rng) -> u32 {
+        match *self {
+            Self::Exact(n) => n,
+            Self::Range { min, max } => rng.random_range(min..=max),
+        }
+    }
+}
+
+/// A condition that must pass for a pool to be rolled, or an entry to be picked.
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "condition", rename_all = "snake_case", deny_unknown_fields)]
+pub enum LootCondition {
+    /// Passes with a chance between 0 and 1.
+    RandomChance { chance: f32 },
+
+    /// Passes if the tool is one of these items.
+    MatchTool { items: Vec<String> },
+
+    /// Passes if a player broke the block or killed the entity.
+    ByPlayer,
+}
+
+/// What caused the loot to drop.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct LootContext {
+    /// Item used to break the block or kill the entity.
+    pub tool: Option<RegistryId>,
+
+    /// Whether a player broke the block or killed the entity.
+    pub by_player: bool,
+}
+
+impl LootCondition {
+    pub fn test(&self, ctx: &LootContext, items: &Registry<Item>, rng: &mut impl Rng) -> bool {
+        match self {
+            Self::RandomChance { chance } => rng.random::<f32>() < *chance,
+            Self::MatchTool { items: names } => ctx
+                .tool
+                .and_then(|tool| items.get(tool))
+                .is_some_and(|tool| names.iter().any(|name| *name == tool.name)),
+            Self::ByPlayer => ctx.by_player,
+        }
+    }
+}
+
+fn test_all(
+    conditions: &[LootCondition],
+    ctx: &LootContext,
+    items: &Registry<Item>,
+    rng: &mut impl Rng,
+) -> bool {
+    conditions
+        .iter()
+        .all(|condition| condition.test(ctx, items, rng))
+}
+
+impl LootTable {
+    /// Parse and validate a loot table file.
+    /// Every item the table names must be in `items`.
+    pub fn parse(data: &[u8], items: &Registry<Item>) -> Result<Self, LootTableErrorKind> {
+        let table = serde_json::from_slice::<Self>(data).map_err(LootTableErrorKind::Parse)?;
+        table.validate(items)?;
+        Ok(table)
+    }
+
+    fn validate(&self, items: &Registry<Item>) -> Result<(), LootTableErrorKind> {
+        let invalid = |msg: String| Err(LootTableErrorKind::Invalid(msg));
+
+        let check_range = |range: &NumberRange, what: &str| match *range {
+            NumberRange::Range { min, max } if min > max => invalid(format!(
+                "{what} has a min of {min}, which is more than its max of {max}"
+            )),
+            _ => Ok(()),
+        };
+
+        let check_conditions = |conditions: &[LootCondition]| {
+            for condition in conditions {
+                match condition {
+                    LootCondition::RandomChance { chance } if !(0.0..=1.0).contains(chance) => {
+                        return invalid(format!("chance must be between 0 and 1, found {chance}"));
+                    }
+                    LootCondition::MatchTool { items: names } => {
+                        if let Some(name) = names.iter().find(|name| items.resolve(name).is_none())
+                        {
+                            return invalid(format!("unknown item '{name}'"));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Ok(())
+        };
+
+        for pool in &self.pools {
+            check_range(&pool.rolls, "rolls")?;
+            check_conditions(&pool.conditions)?;
+
+            if pool.entries.iter().all(|entry| entry.weight == 0) {
+                return invalid("a pool has no entries with a weight".into());
+            }
+
+            for entry in &pool.entries {
+                check_range(&entry.count, "count")?;
+                check_conditions(&entry.conditions)?;
+
+                if let Some(item) = &entry.item
+                    && items.resolve(item).is_none()
+                {
+                    return invalid(format!("unknown item '{item}'"));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Roll the pools of the table, and return the items that drop.
+    ///
+    /// Drops of the same item are merged into as few stacks as fit. The result only depends
+    /// on the state of `rng`, so a seeded rng always drops the same items.
+    pub fn roll(
+        &self,
+        ctx: &LootContext,
+        items: &Registry<Item>,
+        rng: &mut impl Rng,
+    ) -> Vec<ItemStack> {
+        // counts of each item, in the order they first dropped.
+        let mut drops = Vec::<(RegistryId, u32)>::new();
+
+        for pool in &self.pools {
+            if !test_all(&pool.conditions, ctx, items, rng) {
+                continue;
+            }
+
+            for _ in 0..pool.rolls.sample(rng) {
+                let entries = pool
+                    .entries
+                    .iter()
+                    .filter(|entry| {
+                        entry.weight > 0 && test_all(&entry.conditions, ctx, items, rng)
+                    })
+                    .collect::<Vec<_>>();
+
+                let total = entries.iter().map(|entry| entry.weight).sum::<u32>();
+                if total == 0 {
+                    break;
+                }
+
+                let mut pick = rng.random_range(0..total);
+                let Some(entry) = entries.into_iter().find(|entry| {
+                    let found = pick < entry.weight;
+                    pick = pick.saturating_sub(entry.weight);
+                    found
+                }) else {
+                    continue;
+                };
+
+                let count = entry.count.sample(rng);
+                let Some(item) = entry.item.as_ref().and_then(|name| items.resolve(name)) else {
+                    continue;
+                };
+
+                match drops.iter_mut().find(|(id, _)| *id == item) {
+                    Some((_, total)) => *total += count,
+                    None => drops.push((item, count)),
+                }
+            }
+        }
+
+        let mut stacks = Vec::new();
+        for (item, mut count) in drops {
+            let max = items.get(item).map_or(1, |item| item.max_stack.max(1));
+            while count > 0 {
+                let n = count.min(u32::from(max));
+                stacks.push(ItemStack::new(item, n as u16));
+                count -= n;
+            }
+        }
+
+        stacks
+    }
+}
+
+/// Read the loot tables in the `loot_tables` folder of these directories, and its sub-folders.
+/// Tables in directories earlier in the list replace the ones with the same name after them.
+pub fn read_loot_tables<P: AsRef<Path>>(
+    dirs: &[P],
+    items: &Registry<Item>,
+) -> (Registry<LootTable>, Vec<LootTableError>) {
+    let mut tables = BTreeMap::<String, LootTable>::new();
+    let mut errors = Vec::new();
+
+    for dir in dirs.iter().rev() {
+        let folder = dir.as_ref().join("loot_tables");
+        if !folder.is_dir() {
+            continue;
+        }
+
+        for (name, path) in iter_json_files_recursive(&folder) {
+            let table = fs::read(&path)
+                .map_err(LootTableErrorKind::Read)
+                .and_then(|data| LootTable::parse(&data, items));
+
+            match table {
+                Ok(table) => {
+                    tables.insert(name, table);
+                }
+                Err(kind) => errors.push(LootTableError { path, kind }),
+            }
+        }
+    }
+
+    let mut registry = Registry::new();
+    for (name, table) in tables {
+        registry.insert(name, table);
+    }
+
+    (registry, errors)
+}
+
+/// A loot table file that couldn't be loaded.
+#[derive(thiserror::Error, Debug)]
+#[error("{} (in '{}')", .kind, .path.display())]
+pub struct LootTableError {
+    pub path: PathBuf,
+    pub kind: LootTableErrorKind,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum LootTableErrorKind {
+    #[error("[D240] Failed to read loot table: {0}")]
+    Read(io::Error),
+
+    #[error("[D241] Loot table is not valid JSON: {0}")]
+    Parse(serde_json::Error),
+
+    #[error("[D242] Invalid loot table: {0}")]
+    Invalid(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use math::rng::BitRng;
+
+    use super::*;
+
+    fn items() -> Registry<Item> {
+        let mut items = Registry::new();
+        for (name, max_stack) in [("stone", 64), ("flint", 16), ("pickaxe", 1)] {
+            items.insert(
+                name,
+                Item {
+                    max_stack,
+                    block: None,
+                    texture: None,
+                },
+            );
+        }
+        items
+    }
+
+    #[test]
+    fn seeded_rolls_are_repeatable() {
+        let items = items();
+        let table = LootTable::parse(
+            br#"{
+                "pools": [{
+                    "rolls": { "min": 1, "max": 3 },
+                    "entries": [
+                        { "item": "stone", "weight": 2, "count": { "min": 1, "max": 4 } },
+                        { "item": "flint" },
+                        { "weight": 1 }
+                    ]
+                }]
+            }"#,
+            &items,
+        )
+        .unwrap();
+
+        let ctx = LootContext::default();
+        for seed in 0..32 {
+            let a = table.roll(&ctx, &items, &mut BitRng::new(seed));
+            let b = table.roll(&ctx, &items, &mut BitRng::new(seed));
+            assert_eq!(a, b);
+
+            // at most 3 rolls of 4 items.
+            let total = a.iter().map(|stack| stack.count as u32).sum::<u32>();
+            assert!(total <= 12);
+        }
+    }
+
+    #[test]
+    fn drops_are_split_into_full_stacks() {
+        let items = items();
+        let table = LootTable::parse(
+            br#"{ "pools": [{ "rolls": 2, "entries": [{ "item": "flint", "count": 10 }] }] }"#,
+            &items,
+        )
+        .unwrap();
+
+        let flint = items.resolve("flint").unwrap();
+        let drops = table.roll(&LootContext::default(), &items, &mut BitRng::new(0));
+        assert_eq!(
+            drops,
+            vec![ItemStack::new(flint, 16), ItemStack::new(flint, 4)]
+        );
+    }
+
+    #[test]
+    fn conditions_filter_pools_and_entries() {
+        let items = items();
+        let table = LootTable::parse(
+            br#"{
+                "pools": [
+                    {
+                        "conditions": [{ "condition": "by_player" }],
+                        "entries": [
+                            { "item": "flint", "conditions": [{ "condition": "match_tool", "items": ["pickaxe"] }] },
+                            { "item": "stone" }
+                        ]
+                    },
+                    {
+                        "conditions": [{ "condition": "random_chance", "chance": 0 }],
+                        "entries": [{ "item": "flint" }]
+                    }
+                ]
+            }"#,
+            &items,
+        )
+        .unwrap();
+
+        let mut rng = BitRng::new(7);
+        let stone = ItemStack::new(items.resolve("stone").unwrap(), 1);
+
+        let nobody = LootContext::default();
+        assert!(table.roll(&nobody, &items, &mut rng).is_empty());
+
+        let hand = LootContext {
+            tool: None,
+            by_player: true,
+        };
+        assert_eq!(table.roll(&hand, &items, &mut rng), vec![stone]);
+
+        let pickaxe = LootContext {
+            tool: items.resolve("pickaxe"),
+            by_player: true,
+        };
+        for _ in 0..8 {
+            assert_eq!(table.roll(&pickaxe, &items, &mut rng).len(), 1);
+        }
+    }
+
+    #[test]
+    fn invalid_tables_are_rejected() {
+        let items = items();
+        let invalid = [
+            r#"{ "pools": [{ "entries": [{ "item": "diamond" }] }] }"#,
+            r#"{ "pools": [{ "entries": [{ "item": "stone", "weight": 0 }] }] }"#,
+            r#"{ "pools": [{ "rolls": { "min": 3, "max": 1 }, "entries": [{ "item": "stone" }] }] }"#,
+            r#"{ "pools": [{ "conditions": [{ "condition": "random_chance", "chance": 2 }], "entries": [{}] }] }"#,
+            r#"{ "pools": [{ "entries": [{ "conditions": [{ "condition": "match_tool", "items": ["axe"] }] }] }] }"#,
+        ];
+
+        for json in invalid {
+            assert!(
+                matches!(
+                    LootTable::parse(json.as_bytes(), &items),
+                    Err(LootTableErrorKind::Invalid(_))
+                ),
+                "{json}"
+            );
+        }
+    }
+}
@@ -1,6 +1,14 @@
 use bevy::prelude::*;
+use parking_lot::Mutex;
 pub use priority_queue::PriorityQueue;
-use std::{collections::VecDeque, hash::Hash};
+use std::{
+    collections::VecDeque,
+    hash::Hash,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+};
 
 /// A "Queue" resource that can have multiple different internal implementations.
 #[derive(Resource, Deref, DerefMut)]
@@ -114,3 +122,169 @@ where
         }
     }
 }
+
+/// What a `BoundedQueue` does with an item pushed while it is full.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum OverflowPolicy {
+    /// Hand the pushed item back to the caller.
+    #[default]
+    Reject,
+
+    /// Drop the item at the front of the queue to make room.
+    DropOldest,
+
+    /// Drop the pushed item.
+    DropNewest,
+}
+
+/// A queue with a max number of items, shared by any number of producers
+/// and consumers. Clones are handles to the same queue, so it can be handed
+/// to tasks on other threads that push results for a system to pop.
+pub struct BoundedQueue<T> {
+    shared: Arc<Shared<T>>,
+}
+
+struct Shared<T> {
+    buffer: Mutex<VecDeque<T>>,
+    capacity: usize,
+    policy: OverflowPolicy,
+    dropped: AtomicU64,
+}
+
+impl<T> BoundedQueue<T> {
+    /// Create a queue that holds at most "capacity" items.
+    /// A capacity of 0 is treated as 1.
+    pub fn new(capacity: usize, policy: OverflowPolicy) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            shared: Arc::new(Shared {
+                buffer: Mutex::new(VecDeque::with_capacity(capacity)),
+                capacity,
+                policy,
+                dropped: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    /// Push to the back of the queue.
+    /// If the queue is full, the item is handled according to the overflow policy,
+    /// and only returned as the error with `OverflowPolicy::Reject`.
+    pub fn push(&self, item: T) -> Result<(), T> {
+        let mut buffer = self.shared.buffer.lock();
+        if buffer.len() >= self.shared.capacity {
+            match self.shared.policy {
+                OverflowPolicy::Reject => return Err(item),
+                OverflowPolicy::DropOldest => {
+                    buffer.pop_front();
+                }
+                OverflowPolicy::DropNewest => {
+                    self.shared.dropped.fetch_add(1, Ordering::Relaxed);
+                    return Ok(());
+                }
+            }
+            self.shared.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+        buffer.push_back(item);
+        Ok(())
+    }
+
+    /// Pop off the front of the queue.
+    #[inline]
+    pub fn pop(&self) -> Option<T> {
+        self.shared.buffer.lock().pop_front()
+    }
+
+    /// Take every queued item, oldest first.
+    pub fn drain(&self) -> Vec<T> {
+        self.shared.buffer.lock().drain(..).collect()
+    }
+
+    /// Number of items queued.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.shared.buffer.lock().len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Max number of items the queue holds.
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.shared.capacity
+    }
+
+    #[inline]
+    pub fn policy(&self) -> OverflowPolicy {
+        self.shared.policy
+    }
+
+    /// Number of items dropped by the overflow policy since the queue was created.
+    /// Rejected items are not counted, since the caller gets them back.
+    #[inline]
+    pub fn dropped(&self) -> u64 {
+        self.shared.dropped.load(Ordering::Relaxed)
+    }
+}
+
+impl<T> Clone for BoundedQueue<T> {
+    fn clone(&self) -> Self {
+        Self {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bounded_queue_overflow_policies() {
+        let queue = BoundedQueue::new(2, OverflowPolicy::Reject);
+        assert_eq!(queue.push(1), Ok(()));
+        assert_eq!(queue.push(2), Ok(()));
+        assert_eq!(queue.push(3), Err(3));
+        assert_eq!(queue.drain(), [1, 2]);
+        assert_eq!(queue.dropped(), 0);
+
+        let queue = BoundedQueue::new(2, OverflowPolicy::DropOldest);
+        for i in 1..=4 {
+            queue.push(i).unwrap();
+        }
+        assert_eq!(queue.drain(), [3, 4]);
+        assert_eq!(queue.dropped(), 2);
+
+        let queue = BoundedQueue::new(2, OverflowPolicy::DropNewest);
+        for i in 1..=4 {
+            queue.push(i).unwrap();
+        }
+        assert_eq!(queue.drain(), [1, 2]);
+        assert_eq!(queue.dropped(), 2);
+    }
+
+    #[test]
+    fn bounded_queue_is_shared_between_threads() {
+        let queue = BoundedQueue::new(1024, OverflowPolicy::Reject);
+        let producers: Vec<_> = (0..4)
+            .map(|t| {
+                let queue = queue.clone();
+                std::thread::spawn(move || {
+                    for i in 0..100 {
+                        queue.push(t * 100 + i).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for producer in producers {
+            producer.join().unwrap();
+        }
+
+        let mut items = queue.drain();
+        items.sort();
+        assert_eq!(items, (0..400).collect::<Vec<_>>());
+        assert!(queue.is_empty());
+    }
+}
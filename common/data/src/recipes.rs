@@ -0,0 +1,646 @@
+//! Crafting and smelting recipes, read from the `recipes` folder of asset packs.
+//!
+//! Each recipe is a JSON file, named by its path in the folder without the extension,
+//! like `recipes/smelting/stone.json`. The "type" field picks the kind of recipe:
+//!
+//! ```json
+//! {
+//!     "type": "shaped",
+//!     "pattern": ["##", "##"],
+//!     "key": { "#": "stone" },
+//!     "result": { "item": "stone_bricks", "count": 4 }
+//! }
+//! ```
+//!
+//! Shaped recipes match a pattern of up to 3x3 items anywhere in the grid, or its mirror
+//! image. Spaces in the pattern are empty slots. Shapeless recipes match their ingredients
+//! in any order, and smelting recipes turn one item into another in a furnace. An
+//! ingredient is an item name, or a list of item names any of which is accepted.
+//!
+//! Recipes name items instead of storing their ids, so they stay valid when the item
+//! registry is remapped to the order of the server.
+
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+
+use crate::{
+    fs::path::iter_json_files_recursive,
+    items::{Item, ItemStack},
+    registry::{Registry, RegistryId},
+};
+
+/// Width and height of the largest crafting grid.
+pub const MAX_GRID_SIZE: usize = 3;
+
+/// Seconds an item takes to smelt, for recipes that don't set it.
+pub const DEFAULT_SMELT_TIME: f32 = 10.0;
+
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "lowercase", deny_unknown_fields)]
+pub enum Recipe {
+    Shaped {
+        /// Rows of the pattern, where each character is a key of `key`, or a space.
+        pattern: Vec<String>,
+        key: BTreeMap<char, Ingredient>,
+        result: RecipeResult,
+    },
+    Shapeless {
+        ingredients: Vec<Ingredient>,
+        result: RecipeResult,
+    },
+    Smelting {
+        input: Ingredient,
+        result: RecipeResult,
+
+        /// Seconds the input takes to smelt.
+        #[serde(default = "default_smelt_time")]
+        time: f32,
+    },
+}
+
+fn default_smelt_time() -> f32 {
+    DEFAULT_SMELT_TIME
+}
+
+/// Item accepted in one slot of a recipe.
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum Ingredient {
+    Item(String),
+    AnyOf(Vec<String>),
+}
+
+impl Ingredient {
+    /// Names of the items that are accepted.
+    pub fn names(&self) -> &[String] {
+        match self {
+            Self::Item(name) => std::slice::from_ref(name),
+            Self::AnyOf(names) => names,
+        }
+    }
+
+    /// Whether the item is accepted.
+    pub fn matches(&self, item: RegistryId, items: &Registry<Item>) -> bool {
+        items
+            .get(item)
+            .is_some_and(|entry| self.names().iter().any(|name| *name == entry.name))
+    }
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct RecipeResult {
+    pub item: String,
+
+    #[serde(default = "default_count")]
+    pub count: u16,
+}
+
+fn default_count() -> u16 {
+    1
+}
+
+impl RecipeResult {
+    /// The result as a stack, if the item exists.
+    pub fn stack(&self, items: &Registry<Item>) -> Option<ItemStack> {
+        Some(ItemStack::new(items.resolve(&self.item)?, self.count))
+    }
+}
+
+impl Recipe {
+    /// Parse and validate a recipe file.
+    /// Every item the recipe names must be in `items`.
+    pub fn parse(data: &[u8], items: &Registry<Item>) -> Result<Self, RecipeErrorKind> {
+        let recipe = serde_json::from_slice::<Self>(data).map_err(RecipeErrorKind::Parse)?;
+        recipe.validate(items)?;
+        Ok(recipe)
+    }
+
+    pub fn result(&self) -> &RecipeResult {
+        match self {
+            Self::Shaped { result, .. } => result,
+            Self::Shapeless { result, .. } => result,
+            Self::Smelting { result, .. } => result,
+        }
+    }
+
+    fn validate(&self, items: &Registry<Item>) -> Result<(), RecipeErrorKind> {
+        let invalid = |msg: String| Err(RecipeErrorKind::Invalid(msg));
+
+        let ingredients = match self {
+            Self::Shaped { pattern, key, .. } => {
+                if pattern.is_empty() || pattern.len() > MAX_GRID_SIZE {
+                    return invalid(format!(
+                        "pattern must have 1 to {MAX_GRID_SIZE} rows, found {}",
+                        pattern.len()
+                    ));
+                }
+
+                for row in pattern {
+                    if row.chars().count() > MAX_GRID_SIZE {
+                        return invalid(format!(
+                            "pattern row '{row}' is longer than {MAX_GRID_SIZE}"
+                        ));
+                    }
+
+                    if let Some(c) = row.chars().find(|c| *c != ' ' && !key.contains_key(c)) {
+                        return invalid(format!("'{c}' in the pattern is not in the key"));
+                    }
+                }
+
+                if pattern.iter().all(|row| row.trim().is_empty()) {
+                    return invalid("pattern is empty".into());
+                }
+
+                if key.contains_key(&' ') {
+                    return invalid("' ' can't be a key, it's an empty slot".into());
+                }
+
+                key.values().collect::<Vec<_>>()
+            }
+            Self::Shapeless { ingredients, .. } => {
+                let max = MAX_GRID_SIZE * MAX_GRID_SIZE;
+                if ingredients.is_empty() || ingredients.len() > max {
+                    return invalid(format!(
+                        "must have 1 to {max} ingredients, found {}",
+                        ingredients.len()
+                    ));
+                }
+
+                ingredients.iter().collect()
+            }
+            Self::Smelting { input, time, .. } => {
+                if time.is_nan() || *time <= 0.0 {
+                    return invalid(format!("time must be positive, found {time}"));
+                }
+
+                vec![input]
+            }
+        };
+
+        for ingredient in ingredients {
+            if ingredient.names().is_empty() {
+                return invalid("an ingredient has no items".into());
+            }
+
+            if let Some(name) = ingredient
+                .names()
+                .iter()
+                .find(|name| items.resolve(name).is_none())
+            {
+                return invalid(format!("unknown item '{name}'"));
+            }
+        }
+
+        let result = self.result();
+        if result.count == 0 {
+            return invalid("result count must be at least 1".into());
+        }
+
+        if items.resolve(&result.item).is_none() {
+            return invalid(format!("unknown item '{}'", result.item));
+        }
+
+        Ok(())
+    }
+
+    /// Whether the items in the grid craft this recipe.
+    /// Smelting recipes never match a crafting grid.
+    pub fn matches(&self, grid: &CraftingGrid, items: &Registry<Item>) -> bool {
+        match self {
+            Self::Shaped { pattern, key, .. } => {
+                let rows = pattern
+                    .iter()
+                    .map(|row| {
+                        let mut cells = row.chars().map(|c| key.get(&c)).collect::<Vec<_>>();
+                        cells.resize(MAX_GRID_SIZE, None);
+                        cells
+                    })
+                    .collect::<Vec<_>>();
+
+                let shape = Bounds::of(rows.len(), MAX_GRID_SIZE, |x, y| rows[y][x].is_some());
+                let Some(shape) = shape else {
+                    return false;
+                };
+
+                let Some(bounds) = grid.bounds() else {
+                    return false;
+                };
+
+                if (shape.width, shape.height) != (bounds.width, bounds.height) {
+                    return false;
+                }
+
+                let matches = |mirror: bool| {
+                    (0..shape.height).all(|y| {
+                        (0..shape.width).all(|x| {
+                            let px = match mirror {
+                                true => shape.width - 1 - x,
+                                false => x,
+                            };
+                            let slot = grid.get(bounds.x + x, bounds.y + y);
+                            match (rows[shape.y + y][shape.x + px], slot) {
+                                (None, None) => true,
+                                (Some(ingredient), Some(item)) => ingredient.matches(item, items),
+                                _ => false,
+                            }
+                        })
+                    })
+                };
+
+                matches(false) || matches(true)
+            }
+            Self::Shapeless { ingredients, .. } => {
+                let slots = grid.slots.iter().flatten().copied().collect::<Vec<_>>();
+                if slots.len() != ingredients.len() {
+                    return false;
+                }
+
+                let mut used = vec![false; slots.len()];
+                assign_shapeless(ingredients, &slots, &mut used, items)
+            }
+            Self::Smelting { .. } => false,
+        }
+    }
+}
+
+/// Whether every ingredient can be given a different slot that it accepts.
+/// Tries every assignment, which is cheap for the 9 slots of a crafting grid.
+fn assign_shapeless(
+    ingredients: &[Ingredient],
+    slots: &[RegistryId],
+    used: &mut [bool],
+    items: &Registry<Item>,
+) -> bool {
+    let Some((first, rest)) = ingredients.split_first() else {
+        return true;
+    };
+
+    for (i, item) in slots.iter().enumerate() {
+        if used[i] || !first.matches(*item, items) {
+            continue;
+        }
+
+        used[i] = true;
+        if assign_shapeless(rest, slots, used, items) {
+            return true;
+        }
+        used[i] = false;
+    }
+
+    false
+}
+
+impl Registry<Recipe> {
+    /// The first recipe, in registry order, that the items in the grid craft,
+    /// and the stack it crafts.
+    pub fn craft(
+        &self,
+        grid: &CraftingGrid,
+        items: &Registry<Item>,
+    ) -> Option<(RegistryId, ItemStack)> {
+        self.entries()
+            .filter(|entry| entry.matches(grid, items))
+            .find_map(|entry| Some((entry.id, entry.result().stack(items)?)))
+    }
+
+    /// The first smelting recipe that accepts the item, the stack it smelts into,
+    /// and the seconds it takes.
+    pub fn smelt(
+        &self,
+        item: RegistryId,
+        items: &Registry<Item>,
+    ) -> Option<(RegistryId, ItemStack, f32)> {
+        self.entries().find_map(|entry| match &entry.item {
+            Recipe::Smelting {
+                input,
+                result,
+                time,
+            } if input.matches(item, items) => Some((entry.id, result.stack(items)?, *time)),
+            _ => None,
+        })
+    }
+}
+
+/// Smallest rectangle around the occupied slots of a grid.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+struct Bounds {
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+}
+
+impl Bounds {
+    /// Bounds of the slots that are occupied, or None if every slot is empty.
+    fn of(height: usize, width: usize, occupied: impl Fn(usize, usize) -> bool) -> Option<Self> {
+        let (mut min, mut max) = ((usize::MAX, usize::MAX), (0, 0));
+        for y in 0..height {
+            for x in 0..width {
+                if occupied(x, y) {
+                    min = (min.0.min(x), min.1.min(y));
+                    max = (max.0.max(x), max.1.max(y));
+                }
+            }
+        }
+
+        (min.0 != usize::MAX).then(|| Self {
+            x: min.0,
+            y: min.1,
+            width: max.0 - min.0 + 1,
+            height: max.1 - min.1 + 1,
+        })
+    }
+}
+
+/// Items in the slots of a crafting grid, row by row from the top left.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct CraftingGrid {
+    width: usize,
+    height: usize,
+    slots: Vec<Option<RegistryId>>,
+}
+
+impl CraftingGrid {
+    /// An empty grid, at most `MAX_GRID_SIZE` slots wide and tall.
+    pub fn new(width: usize, height: usize) -> Self {
+        assert!(width <= MAX_GRID_SIZE && height <= MAX_GRID_SIZE);
+        Self {
+            width,
+            height,
+            slots: vec![None; width * height],
+        }
+    }
+
+    /// A grid with the items of these stacks, ignoring empty stacks.
+    pub fn from_stacks(width: usize, height: usize, stacks: &[Option<ItemStack>]) -> Self {
+        let mut grid = Self::new(width, height);
+        for (slot, stack) in grid.slots.iter_mut().zip(stacks) {
+            *slot = stack
+                .filter(|stack| !stack.is_empty())
+                .map(|stack| stack.item);
+        }
+        grid
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Item in a slot, None if the slot is empty or outside the grid.
+    pub fn get(&self, x: usize, y: usize) -> Option<RegistryId> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        self.slots[y * self.width + x]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, item: Option<RegistryId>) {
+        assert!(x < self.width && y < self.height);
+        self.slots[y * self.width + x] = item;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.iter().all(Option::is_none)
+    }
+
+    fn bounds(&self) -> Option<Bounds> {
+        Bounds::of(self.height, self.width, |x, y| self.get(x, y).is_some())
+    }
+}
+
+/// Read the recipes in the `recipes` folder of these directories, and its sub-folders.
+/// Recipes in directories earlier in the list replace the ones with the same name after them.
+///
+/// The registry is sorted by name, so it's the same on every machine with the same packs.
+pub fn read_recipes<P: AsRef<Path>>(
+    dirs: &[P],
+    items: &Registry<Item>,
+) -> (Registry<Recipe>, Vec<RecipeError>) {
+    let mut recipes = BTreeMap::<String, Recipe>::new();
+    let mut errors = Vec::new();
+
+    for dir in dirs.iter().rev() {
+        let folder = dir.as_ref().join("recipes");
+        if !folder.is_dir() {
+            continue;
+        }
+
+        for (name, path) in iter_json_files_recursive(&folder) {
+            let recipe = fs::read(&path)
+                .map_err(RecipeErrorKind::Read)
+                .and_then(|data| Recipe::parse(&data, items));
+
+            match recipe {
+                Ok(recipe) => {
+                    recipes.insert(name, recipe);
+                }
+                Err(kind) => errors.push(RecipeError { path, kind }),
+            }
+        }
+    }
+
+    let mut registry = Registry::new();
+    for (name, recipe) in recipes {
+        registry.insert(name, recipe);
+    }
+
+    (registry, errors)
+}
+
+/// A recipe file that couldn't be loaded.
+#[derive(thiserror::Error, Debug)]
+#[error("{} (in '{}')", .kind, .path.display())]
+pub struct RecipeError {
+    pub path: PathBuf,
+    pub kind: RecipeErrorKind,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum RecipeErrorKind {
+    #[error("[D230] Failed to read recipe: {0}")]
+    Read(io::Error),
+
+    #[error("[D231] Recipe is not valid JSON: {0}")]
+    Parse(serde_json::Error),
+
+    #[error("[D232] Invalid recipe: {0}")]
+    Invalid(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn items() -> Registry<Item> {
+        let mut items = Registry::new();
+        for name in ["stone", "stick", "planks", "birch_planks", "torch", "glass"] {
+            items.insert(
+                name,
+                Item {
+                    max_stack: 64,
+                    block: None,
+                    texture: None,
+                },
+            );
+        }
+        items
+    }
+
+    fn grid(items: &Registry<Item>, rows: &[&[&str]]) -> CraftingGrid {
+        let mut grid = CraftingGrid::new(3, 3);
+        for (y, row) in rows.iter().enumerate() {
+            for (x, name) in row.iter().enumerate() {
+                grid.set(x, y, items.resolve(name));
+            }
+        }
+        grid
+    }
+
+    fn recipes(items: &Registry<Item>, files: &[(&str, &str)]) -> Registry<Recipe> {
+        let mut recipes = Registry::new();
+        for (name, json) in files {
+            recipes.insert(*name, Recipe::parse(json.as_bytes(), items).unwrap());
+        }
+        recipes
+    }
+
+    #[test]
+    fn shaped_recipes_match_anywhere_and_mirrored() {
+        let items = items();
+        let recipes = recipes(
+            &items,
+            &[(
+                "torch",
+                r##"{
+                    "type": "shaped",
+                    "pattern": ["s ", "#s"],
+                    "key": { "#": ["planks", "birch_planks"], "s": "stick" },
+                    "result": { "item": "torch", "count": 4 }
+                }"##,
+            )],
+        );
+        let torch = ItemStack::new(items.resolve("torch").unwrap(), 4);
+
+        let placed = grid(
+            &items,
+            &[&["", "", ""], &["", "stick", ""], &["", "planks", "stick"]],
+        );
+        assert_eq!(recipes.craft(&placed, &items).map(|(_, s)| s), Some(torch));
+
+        let mirrored = grid(
+            &items,
+            &[&["", "stick", ""], &["stick", "birch_planks", ""]],
+        );
+        assert_eq!(
+            recipes.craft(&mirrored, &items).map(|(_, s)| s),
+            Some(torch)
+        );
+
+        let extra = grid(&items, &[&["stone", "stick", ""], &["", "planks", "stick"]]);
+        assert_eq!(recipes.craft(&extra, &items), None);
+
+        let wrong = grid(&items, &[&["stick", ""], &["stick", "planks"]]);
+        assert_eq!(recipes.craft(&wrong, &items), None);
+        assert_eq!(recipes.craft(&CraftingGrid::new(3, 3), &items), None);
+    }
+
+    #[test]
+    fn shapeless_recipes_match_in_any_order() {
+        let items = items();
+        let recipes = recipes(
+            &items,
+            &[(
+                "torch",
+                r#"{
+                    "type": "shapeless",
+                    "ingredients": [["planks", "stick"], "stick"],
+                    "result": { "item": "torch" }
+                }"#,
+            )],
+        );
+
+        let sticks = grid(&items, &[&["", "stick"], &["", ""], &["stick", ""]]);
+        let (_, stack) = recipes.craft(&sticks, &items).unwrap();
+        assert_eq!(stack.count, 1);
+
+        // the first ingredient has to take the planks, or the stick would be left over.
+        let mixed = grid(&items, &[&["stick", "planks"]]);
+        assert!(recipes.craft(&mixed, &items).is_some());
+
+        let planks = grid(&items, &[&["planks", "planks"]]);
+        assert!(recipes.craft(&planks, &items).is_none());
+
+        let three = grid(&items, &[&["stick", "stick", "stick"]]);
+        assert!(recipes.craft(&three, &items).is_none());
+    }
+
+    #[test]
+    fn smelting_recipes_only_match_furnaces() {
+        let items = items();
+        let recipes = recipes(
+            &items,
+            &[(
+                "glass",
+                r#"{ "type": "smelting", "input": "stone", "result": { "item": "glass" } }"#,
+            )],
+        );
+
+        let stone = items.resolve("stone").unwrap();
+        let (_, stack, time) = recipes.smelt(stone, &items).unwrap();
+        assert_eq!(stack, ItemStack::new(items.resolve("glass").unwrap(), 1));
+        assert_eq!(time, DEFAULT_SMELT_TIME);
+
+        assert!(
+            recipes
+                .smelt(items.resolve("stick").unwrap(), &items)
+                .is_none()
+        );
+        assert!(
+            recipes
+                .craft(&grid(&items, &[&["stone"]]), &items)
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn invalid_recipes_are_rejected() {
+        let items = items();
+        let invalid = [
+            r#"{ "type": "shaped", "pattern": ["ssss"], "key": { "s": "stone" }, "result": { "item": "glass" } }"#,
+            r#"{ "type": "shaped", "pattern": ["sx"], "key": { "s": "stone" }, "result": { "item": "glass" } }"#,
+            r#"{ "type": "shaped", "pattern": ["  "], "key": {}, "result": { "item": "glass" } }"#,
+            r#"{ "type": "shapeless", "ingredients": [], "result": { "item": "glass" } }"#,
+            r#"{ "type": "shapeless", "ingredients": ["dirt"], "result": { "item": "glass" } }"#,
+            r#"{ "type": "shapeless", "ingredients": [[]], "result": { "item": "glass" } }"#,
+            r#"{ "type": "smelting", "input": "stone", "result": { "item": "glass", "count": 0 } }"#,
+            r#"{ "type": "smelting", "input": "stone", "result": { "item": "diamond" } }"#,
+            r#"{ "type": "smelting", "input": "stone", "result": { "item": "glass" }, "time": 0 }"#,
+        ];
+
+        for json in invalid {
+            assert!(
+                matches!(
+                    Recipe::parse(json.as_bytes(), &items),
+                    Err(RecipeErrorKind::Invalid(_))
+                ),
+                "{json}"
+            );
+        }
+
+        let json = r#"{ "type": "brewing", "result": { "item": "glass" } }"#;
+        assert!(matches!(
+            Recipe::parse(json.as_bytes(), &items),
+            Err(RecipeErrorKind::Parse(_))
+        ));
+    }
+}
@@ -1,4 +1,4 @@
-use std::{hash::Hash, mem::MaybeUninit};
+use std::{fmt, hash::Hash, mem::MaybeUninit};
 
 use bevy::{
     ecs::intern::Interner,
@@ -6,9 +6,11 @@ use bevy::{
 };
 use fxhash::{FxHashMap, FxHashSet};
 use protocol::ChannelId;
+use serde::{Deserialize, Serialize};
 
 /// An index of an entry in a Registry.
-#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
 pub struct RegistryId(pub usize);
 
 impl From<usize> for RegistryId {
@@ -118,11 +120,29 @@ where
             .collect::<Vec<_>>()
     }
 
+    /// Insert an entry for every name that isn't in the registry yet, in order.
+    /// Returns the number of entries that were inserted.
+    ///
+    /// Used to keep the ids of entries that are gone, like blocks of a pack that was removed,
+    /// so ids saved while they existed still point to them. On an empty registry, every
+    /// name gets the index it has in `names` as its id, and entries inserted later with
+    /// the same names replace the placeholders without changing their ids.
+    pub fn reserve<A: AsRef<str>>(&mut self, names: &[A], placeholder: impl Fn() -> T) -> usize {
+        names
+            .iter()
+            .filter(|name| {
+                self.insert_nonoverwriting(name.as_ref(), placeholder())
+                    .is_ok()
+            })
+            .count()
+    }
+
     /// Attempt to make the registry compliant to another.
+    /// Returns a table of the IDs entries had before to the IDs they have now.
     pub fn make_compliant<A: AsRef<str> + Clone + Eq + PartialEq + Hash>(
         &mut self,
         to: &Vec<A>,
-    ) -> Result<(), RegistryComplianceErrors<A>> {
+    ) -> Result<RegistryRemap, RegistryComplianceErrors<A>> {
         // Buffer of entry sources to entry destinations.
         let mut swap = Vec::<(usize, usize)>::with_capacity(self.entries.len());
 
@@ -169,14 +189,16 @@ where
         // move old entries into new buffer.
         let old = vec_to_uninit(std::mem::take(&mut self.entries));
         let mut new = Vec::<Entry<T>>::with_capacity(swap.len());
+        let mut remap = vec![RegistryId(0); swap.len()];
         for (src, dst) in swap {
             new.push(unsafe { old[src].assume_init_read() });
             new[dst].id = RegistryId(dst);
             *self.resolver.get_mut(&new[dst].name).unwrap() = dst;
+            remap[src] = RegistryId(dst);
         }
         self.entries = new;
 
-        Ok(())
+        Ok(RegistryRemap(remap))
     }
 
     pub fn entries(&self) -> impl DoubleEndedIterator<Item = &Entry<T>> {
@@ -226,6 +248,34 @@ where
 
 static REGISTRY_NAME_INTERNER: Interner<str> = Interner::new();
 
+/// The IDs entries of a registry had before `Registry::make_compliant`,
+/// mapped to the IDs they have after. Data that stores IDs of the
+/// registry (like cached lookups) can use it to update them.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RegistryRemap(Vec<RegistryId>);
+
+impl RegistryRemap {
+    /// The new ID of the entry that had this ID.
+    /// Returns None if there was no entry with this ID.
+    pub fn get(&self, old: impl Into<RegistryId>) -> Option<RegistryId> {
+        self.0.get(old.into().0).copied()
+    }
+
+    /// Whether no entry changed its ID.
+    pub fn is_identity(&self) -> bool {
+        self.0.iter().enumerate().all(|(i, id)| id.0 == i)
+    }
+
+    /// Pairs of old and new IDs of entries whose ID changed.
+    pub fn changed(&self) -> impl Iterator<Item = (RegistryId, RegistryId)> {
+        self.0
+            .iter()
+            .enumerate()
+            .filter(|(i, id)| id.0 != *i)
+            .map(|(i, id)| (RegistryId(i), *id))
+    }
+}
+
 #[derive(Debug)]
 pub struct RegistryComplianceErrors<A: AsRef<str> = String> {
     /// Registry entries that exist on the server but not on the client.
@@ -235,6 +285,29 @@ pub struct RegistryComplianceErrors<A: AsRef<str> = String> {
     pub duplicates: FxHashSet<A>,
 }
 
+impl<A: AsRef<str>> fmt::Display for RegistryComplianceErrors<A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let join = |set: &FxHashSet<A>| {
+            let mut names = set.iter().map(|a| a.as_ref()).collect::<Vec<_>>();
+            names.sort_unstable();
+            names.join(", ")
+        };
+
+        if !self.mismatches.is_empty() {
+            write!(f, "missing entries: [{}]", join(&self.mismatches))?;
+        }
+
+        if !self.duplicates.is_empty() {
+            if !self.mismatches.is_empty() {
+                write!(f, ", ")?;
+            }
+            write!(f, "duplicate entries: [{}]", join(&self.duplicates))?;
+        }
+
+        Ok(())
+    }
+}
+
 fn vec_to_uninit<T>(vec: Vec<T>) -> Vec<MaybeUninit<T>> {
     let (ptr, len, cap) = vec.into_raw_parts();
     unsafe { Vec::from_raw_parts(ptr.cast::<MaybeUninit<T>>(), len, cap) }
@@ -242,7 +315,7 @@ fn vec_to_uninit<T>(vec: Vec<T>) -> Vec<MaybeUninit<T>> {
 
 #[cfg(test)]
 mod tests {
-    use super::Registry;
+    use super::{Registry, RegistryId};
 
     #[derive(Copy, Clone, Eq, PartialEq, Debug)]
     struct Thing(u64);
@@ -293,4 +366,61 @@ mod tests {
         let names1 = reg1.get_names();
         reg2.make_compliant(&names1).unwrap();
     }
+
+    #[test]
+    fn make_compliant_remap() {
+        let mut reg = Registry::<Thing>::new();
+        reg.insert("thing0", Thing(0));
+        reg.insert("thing1", Thing(1));
+        reg.insert("thing2", Thing(2));
+
+        let remap = reg.make_compliant(&vec!["thing2", "thing0", "thing1"]).unwrap();
+        assert_eq!(remap.get(0usize), Some(RegistryId(1)));
+        assert_eq!(remap.get(1usize), Some(RegistryId(2)));
+        assert_eq!(remap.get(2usize), Some(RegistryId(0)));
+        assert_eq!(remap.get(3usize), None);
+        assert!(!remap.is_identity());
+        assert_eq!(remap.changed().count(), 3);
+
+        for (old, new) in remap.changed() {
+            assert_eq!(reg.get(new).unwrap().item, Thing(old.0 as u64));
+        }
+
+        let remap = reg.make_compliant(&vec!["thing2", "thing0", "thing1"]).unwrap();
+        assert!(remap.is_identity());
+    }
+
+    #[test]
+    fn reserve_keeps_saved_ids() {
+        let saved = ["thing0", "removed", "thing1"];
+
+        let mut reg = Registry::<Thing>::new();
+        assert_eq!(reg.reserve(&saved, || Thing(u64::MAX)), 3);
+        reg.insert("thing2", Thing(2));
+        reg.insert("thing1", Thing(1));
+        reg.insert("thing0", Thing(0));
+
+        assert_eq!(reg.resolve("thing0"), Some(RegistryId(0)));
+        assert_eq!(reg.resolve("thing1"), Some(RegistryId(2)));
+        assert_eq!(reg.resolve("thing2"), Some(RegistryId(3)));
+        assert_eq!(reg.get_by_name("removed").unwrap().item, Thing(u64::MAX));
+
+        // reserving again only inserts names that are missing.
+        assert_eq!(reg.reserve(&["thing2", "thing3"], || Thing(3)), 1);
+        assert_eq!(reg.resolve("thing3"), Some(RegistryId(4)));
+    }
+
+    #[test]
+    fn compliance_errors_display() {
+        let mut reg = Registry::<Thing>::new();
+        reg.insert("thing0", Thing(0));
+
+        let err = reg
+            .make_compliant(&vec!["thing0", "thing1", "thing0"])
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "missing entries: [thing1], duplicate entries: [thing0]"
+        );
+    }
 }
@@ -1,11 +1,47 @@
-use std::{io, marker::PhantomData};
+use std::{marker::PhantomData, sync::Arc, time::Duration};
 
 use bevy::{prelude::*, state::state::FreelyMutableState};
 use fxhash::FxHashMap;
 use parking_lot::RwLock;
 
-#[derive(Default)]
-pub struct SequencesPlugin<S: Sequences>(pub S);
+/// Error code of a stage or rivulet that took longer than its timeout,
+/// unless the sequence overrides `Sequences::timeout_error`.
+pub const TIMEOUT_ERR_CODE: &str = "[D440]";
+
+pub struct SequencesPlugin<S: Sequences> {
+    /// The inactive state the sequence starts in and returns to.
+    pub default: S,
+
+    /// Adds the systems that make the transitions when the sequence fails.
+    on_failure: Vec<Arc<dyn Fn(&mut App) + Send + Sync>>,
+}
+
+impl<S: Sequences> SequencesPlugin<S> {
+    pub fn new(default: S) -> Self {
+        Self {
+            default,
+            on_failure: Vec::new(),
+        }
+    }
+
+    /// Transition to a state outside of the sequence when it fails, like a menu
+    /// that shows the error. The sequence itself always returns to its default state.
+    pub fn fail_to<T: FreelyMutableState + Clone>(mut self, state: T) -> Self {
+        self.on_failure.push(Arc::new(move |app: &mut App| {
+            app.add_systems(
+                Last,
+                crate::util::transition(state.clone()).run_if(on_message::<SequenceFailed<S>>),
+            );
+        }));
+        self
+    }
+}
+
+impl<S: Sequences + Default> Default for SequencesPlugin<S> {
+    fn default() -> Self {
+        Self::new(S::default())
+    }
+}
 
 impl<S> Plugin for SequencesPlugin<S>
 where
@@ -14,25 +50,59 @@ where
     #[rustfmt::skip]
     fn build(&self, app: &mut App) {
         app
-            .insert_state(self.0.clone())
+            .insert_state(self.default.clone())
             .insert_resource(
                 Sequence {
                     rivulets: RwLock::new(FxHashMap::default()),
-                    default: self.0.clone(),
+                    default: self.default.clone(),
                     error: RwLock::default(),
                 }
             )
             .add_message::<SequenceStarted<S>>()
             .add_message::<SequenceEnded<S>>()
             .add_message::<SequenceFailed<S>>()
-            .add_systems(OnExit(self.0.clone()), (
+            .add_systems(OnExit(self.default.clone()), (
                 write_sequence_start_ev::<S>,
             ))
             .add_systems(PostUpdate, (
-                advance_sequence_state::<S>
-                    .run_if(not(in_state(self.0.clone()))),
+                (
+                    check_sequence_timeouts::<S>,
+                    advance_sequence_state::<S>,
+                ).chain().run_if(not(in_state(self.default.clone()))),
             ))
         ;
+
+        for add_systems in &self.on_failure {
+            add_systems(app);
+        }
+    }
+}
+
+/// Fail the sequence if its stage, or a rivulet in it, takes longer than its timeout.
+fn check_sequence_timeouts<S: Sequences>(
+    seq: Res<Sequence<S>>,
+    curr: Res<State<S>>,
+    time: Res<Time>,
+    mut in_stage: Local<Duration>,
+) {
+    if curr.is_changed() {
+        *in_stage = Duration::ZERO;
+    } else {
+        *in_stage += time.delta();
+    }
+
+    let expired = seq.tick_rivulets(time.delta());
+    if seq.get_err().is_some() {
+        return;
+    }
+
+    let stage = curr.get();
+    if let Some(timeout) = stage.timeout()
+        && *in_stage > timeout
+    {
+        seq.set_error(stage.timeout_error(timeout, None));
+    } else if let Some((name, timeout)) = expired {
+        seq.set_error(stage.timeout_error(timeout, Some(&name)));
     }
 }
 
@@ -51,9 +121,13 @@ fn advance_sequence_state<S: Sequences>(
                 stage: curr.get().clone(),
             });
             next.set(seq.default.clone());
+
+            // reset so the sequence can be started again.
+            seq.reset();
+            return;
         }
 
-        if seq.is_empty_or_all_finished() {
+        if seq.all_finished(curr.get().steps()) && seq.is_empty_or_all_finished() {
             seq.clear();
             let new = if let Some(state) = curr.get().next() {
                 state
@@ -152,6 +226,7 @@ impl<S> Sequence<S> {
                     name: rivulet,
                     progress: v.progress,
                     state: v.state,
+                    timeout: v.timeout,
                 };
             }
         }
@@ -164,6 +239,8 @@ impl<S> Sequence<S> {
                     progress: 0.0,
                     hint_text: String::default(),
                     state: RivuletState::Uninit,
+                    timeout: None,
+                    elapsed: Duration::ZERO,
                 });
         }
 
@@ -173,6 +250,7 @@ impl<S> Sequence<S> {
             name: rivulet,
             progress: 0.0,
             state: RivuletState::Uninit,
+            timeout: None,
         }
     }
 
@@ -184,10 +262,36 @@ impl<S> Sequence<S> {
         self.error.read().clone()
     }
 
+    /// Forget the state of every rivulet and any error, so the
+    /// sequence can be started again after it was interrupted.
+    pub fn reset(&self) {
+        self.clear();
+        *self.error.write() = None;
+    }
+
     fn clear(&self) {
         self.rivulets.write().clear();
     }
 
+    /// Add the time since the last frame to every rivulet in progress,
+    /// returning the name and timeout of one that took longer than its timeout.
+    fn tick_rivulets(&self, delta: Duration) -> Option<(String, Duration)> {
+        let mut expired = None;
+        for (name, rivulet) in self.rivulets.write().iter_mut() {
+            if rivulet.state != RivuletState::InProgress {
+                continue;
+            }
+
+            rivulet.elapsed += delta;
+            if let Some(timeout) = rivulet.timeout
+                && rivulet.elapsed > timeout
+            {
+                expired = Some((name.clone(), timeout));
+            }
+        }
+        expired
+    }
+
     fn is_empty_or_all_finished(&self) -> bool {
         let guard = self.rivulets.read();
         for entry in guard.values() {
@@ -206,6 +310,9 @@ pub struct RivuletGuard<'a, S> {
     pub name: &'a str,
     pub progress: f32,
     pub state: RivuletState,
+
+    /// How long the rivulet may be in progress before the sequence fails.
+    pub timeout: Option<Duration>,
 }
 
 impl<'a, S> RivuletGuard<'a, S> {
@@ -221,6 +328,7 @@ impl<'a, S> Drop for RivuletGuard<'a, S> {
         if let Some(inner) = self.sequence.rivulets.write().get_mut(self.name) {
             inner.progress = self.progress.min(1.0);
             inner.state = self.state;
+            inner.timeout = self.timeout;
         }
     }
 }
@@ -240,6 +348,12 @@ struct Rivulet {
     ///  - InProgress
     ///  - Finished
     state: RivuletState,
+
+    /// How long the rivulet may be in progress before the sequence fails.
+    timeout: Option<Duration>,
+
+    /// How long the rivulet has been in progress.
+    elapsed: Duration,
 }
 
 /// The state of the Rivulet.
@@ -265,4 +379,152 @@ pub trait Sequences: Clone + FreelyMutableState {
     /// Get the next variant, returning None if the
     /// sequence is complete.
     fn next(&self) -> Option<Self>;
+
+    /// Names of the rivulets of this stage, which run at the same time.
+    /// The stage waits for every one of them to finish, even ones that haven't
+    /// started yet, as well as any other rivulet that started in the stage.
+    fn steps(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    /// How long the stage may take before the sequence fails.
+    fn timeout(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Error the sequence fails with when the stage, or the rivulet
+    /// with this name, takes longer than its timeout.
+    fn timeout_error(&self, timeout: Duration, rivulet: Option<&str>) -> RivuletError {
+        let err_text = match rivulet {
+            Some(name) => format!(
+                "Rivulet '{name}' did not finish within {}s.",
+                timeout.as_secs_f32()
+            ),
+            None => format!(
+                "Stage '{self:?}' did not finish within {}s.",
+                timeout.as_secs_f32()
+            ),
+        };
+
+        RivuletError {
+            err_code: TIMEOUT_ERR_CODE,
+            err_text,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::state::app::StatesPlugin;
+
+    use super::*;
+
+    #[derive(States, Default, Clone, PartialEq, Eq, Hash, Debug)]
+    enum TestSeq {
+        #[default]
+        Inactive,
+        Load,
+        Build,
+    }
+
+    impl Sequences for TestSeq {
+        fn is_active(&self) -> bool {
+            *self != Self::Inactive
+        }
+
+        fn first() -> Self {
+            Self::Load
+        }
+
+        fn next(&self) -> Option<Self> {
+            match *self {
+                Self::Inactive => Some(Self::Load),
+                Self::Load => Some(Self::Build),
+                Self::Build => None,
+            }
+        }
+
+        fn steps(&self) -> &'static [&'static str] {
+            match *self {
+                Self::Load => &["textures", "sounds"],
+                Self::Build => &["atlas"],
+                Self::Inactive => &[],
+            }
+        }
+
+        fn timeout(&self) -> Option<Duration> {
+            match *self {
+                Self::Build => Some(Duration::from_secs(1)),
+                _ => None,
+            }
+        }
+    }
+
+    #[derive(States, Default, Clone, PartialEq, Eq, Hash, Debug)]
+    enum TestMenu {
+        #[default]
+        Loading,
+        Failed,
+    }
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.add_plugins((
+            StatesPlugin,
+            SequencesPlugin::<TestSeq>::default().fail_to(TestMenu::Failed),
+        ))
+        .init_state::<TestMenu>()
+        .init_resource::<Time>();
+        app.world_mut()
+            .resource_mut::<NextState<TestSeq>>()
+            .set(TestSeq::first());
+        app.update();
+        app
+    }
+
+    fn finish(app: &App, rivulet: &str) {
+        let seq = app.world().resource::<Sequence<TestSeq>>();
+        seq.get(rivulet).state = RivuletState::Finished;
+    }
+
+    fn stage(app: &App) -> TestSeq {
+        app.world().resource::<State<TestSeq>>().get().clone()
+    }
+
+    #[test]
+    fn stage_waits_for_every_step() {
+        let mut app = app();
+        assert_eq!(stage(&app), TestSeq::Load);
+
+        finish(&app, "textures");
+        app.update();
+        app.update();
+        assert_eq!(stage(&app), TestSeq::Load);
+
+        finish(&app, "sounds");
+        app.update();
+        app.update();
+        assert_eq!(stage(&app), TestSeq::Build);
+    }
+
+    #[test]
+    fn timeout_fails_to_state() {
+        let mut app = app();
+        finish(&app, "textures");
+        finish(&app, "sounds");
+        app.update();
+        app.update();
+        assert_eq!(stage(&app), TestSeq::Build);
+
+        app.world_mut()
+            .resource_mut::<Time>()
+            .advance_by(Duration::from_secs(2));
+        app.update();
+        app.update();
+        assert_eq!(stage(&app), TestSeq::Inactive);
+        assert_eq!(
+            *app.world().resource::<State<TestMenu>>().get(),
+            TestMenu::Failed
+        );
+    }
 }
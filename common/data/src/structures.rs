@@ -0,0 +1,215 @@
+//! Structure templates, read from the `structures` folder of data packs, are small
+//! arrangements of blocks placed into the world at once, like the tree a sapling grows into.
+//!
+//! Each template is a JSON file named by its path in the folder, like
+//! `structures/trees/oak.json`. Layers go from the bottom up, each layer is a list of
+//! rows along z, and each character of a row is a block along x, looked up in `palette`.
+//! Spaces leave the world as it is. `origin` is the position in the layers that is placed
+//! at the position the structure grows from.
+//!
+//! ```json
+//! {
+//!     "origin": [1, 0, 1],
+//!     "palette": { "L": "oak_log", "#": "oak_leaves" },
+//!     "layers": [
+//!         ["   ", " L ", "   "],
+//!         ["###", "#L#", "###"],
+//!         [" # ", "###", " # "]
+//!     ]
+//! }
+//! ```
+
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use bevy::math::IVec3;
+use serde::Deserialize;
+
+use crate::{fs::path::iter_json_files_recursive, registry::Registry};
+
+/// Most blocks a template may have on any axis.
+pub const MAX_STRUCTURE_SIZE: usize = 64;
+
+#[derive(Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+struct StructureDef {
+    #[serde(default)]
+    origin: [i32; 3],
+
+    palette: BTreeMap<char, String>,
+    layers: Vec<Vec<String>>,
+}
+
+/// The blocks of a structure template, relative to its origin.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StructureTemplate {
+    /// Offset from the origin, and the name of the block state placed there.
+    pub blocks: Vec<(IVec3, String)>,
+}
+
+impl StructureTemplate {
+    /// Parse and validate a structure template file.
+    /// Block state names aren't checked, since states are registered by the game.
+    pub fn parse(data: &[u8]) -> Result<Self, StructureErrorKind> {
+        let def =
+            serde_json::from_slice::<StructureDef>(data).map_err(StructureErrorKind::Parse)?;
+        let invalid = |msg: String| Err(StructureErrorKind::Invalid(msg));
+
+        if def.layers.len() > MAX_STRUCTURE_SIZE {
+            return invalid(format!(
+                "{} layers, at most {MAX_STRUCTURE_SIZE} are allowed",
+                def.layers.len()
+            ));
+        }
+
+        if let Some(c) = def.palette.keys().find(|c| **c == ' ') {
+            return invalid(format!(
+                "'{c}' can't be in the palette, it leaves blocks as they are"
+            ));
+        }
+
+        let origin = IVec3::from_array(def.origin);
+        let mut blocks = Vec::new();
+        for (y, layer) in def.layers.iter().enumerate() {
+            if layer.len() > MAX_STRUCTURE_SIZE {
+                return invalid(format!(
+                    "layer {y} has {} rows, at most {MAX_STRUCTURE_SIZE} are allowed",
+                    layer.len()
+                ));
+            }
+
+            for (z, row) in layer.iter().enumerate() {
+                if row.chars().count() > MAX_STRUCTURE_SIZE {
+                    return invalid(format!(
+                        "row {z} of layer {y} is longer than {MAX_STRUCTURE_SIZE} blocks"
+                    ));
+                }
+
+                for (x, c) in row.chars().enumerate() {
+                    if c == ' ' {
+                        continue;
+                    }
+
+                    let Some(name) = def.palette.get(&c) else {
+                        return invalid(format!(
+                            "row {z} of layer {y} has '{c}', which isn't in the palette"
+                        ));
+                    };
+
+                    let pos = IVec3::new(x as i32, y as i32, z as i32);
+                    blocks.push((pos - origin, name.clone()));
+                }
+            }
+        }
+
+        Ok(Self { blocks })
+    }
+}
+
+/// Read the structure templates in the `structures` folder of these directories, and its
+/// sub-folders. Templates in directories earlier in the list replace the ones with the same
+/// name after them.
+pub fn read_structures<P: AsRef<Path>>(
+    dirs: &[P],
+) -> (Registry<StructureTemplate>, Vec<StructureError>) {
+    let mut templates = BTreeMap::<String, StructureTemplate>::new();
+    let mut errors = Vec::new();
+
+    for dir in dirs.iter().rev() {
+        let folder = dir.as_ref().join("structures");
+        if !folder.is_dir() {
+            continue;
+        }
+
+        for (name, path) in iter_json_files_recursive(&folder) {
+            let template = fs::read(&path)
+                .map_err(StructureErrorKind::Read)
+                .and_then(|data| StructureTemplate::parse(&data));
+
+            match template {
+                Ok(template) => {
+                    templates.insert(name, template);
+                }
+                Err(kind) => errors.push(StructureError { path, kind }),
+            }
+        }
+    }
+
+    let mut registry = Registry::new();
+    for (name, template) in templates {
+        registry.insert(name, template);
+    }
+
+    (registry, errors)
+}
+
+/// A structure template file that couldn't be loaded.
+#[derive(thiserror::Error, Debug)]
+#[error("{} (in '{}')", .kind, .path.display())]
+pub struct StructureError {
+    pub path: PathBuf,
+    pub kind: StructureErrorKind,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum StructureErrorKind {
+    #[error("[D250] Failed to read structure template: {0}")]
+    Read(io::Error),
+
+    #[error("[D251] Structure template is not valid JSON: {0}")]
+    Parse(serde_json::Error),
+
+    #[error("[D252] Invalid structure template: {0}")]
+    Invalid(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layers_are_placed_around_the_origin() {
+        let template = StructureTemplate::parse(
+            br##"{
+                "origin": [1, 0, 1],
+                "palette": { "L": "log", "#": "leaves" },
+                "layers": [
+                    ["   ", " L "],
+                    ["#", "", "  #"]
+                ]
+            }"##,
+        )
+        .unwrap();
+
+        assert_eq!(
+            template.blocks,
+            [
+                (IVec3::new(0, 0, 0), "log".to_string()),
+                (IVec3::new(-1, 1, -1), "leaves".to_string()),
+                (IVec3::new(1, 1, 1), "leaves".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_templates_are_rejected() {
+        let invalid = |json: &str| {
+            StructureTemplate::parse(json.as_bytes())
+                .unwrap_err()
+                .to_string()
+        };
+
+        assert!(invalid(r#"{ "layers": [] }"#).starts_with("[D251]"));
+        assert!(invalid(r#"{ "palette": {}, "layers": [["x"]] }"#).contains("'x'"));
+        assert!(invalid(r#"{ "palette": { " ": "log" }, "layers": [] }"#).contains("' '"));
+
+        let tall = format!(
+            r#"{{ "palette": {{}}, "layers": [{}] }}"#,
+            ["[]"; 65].join(",")
+        );
+        assert!(invalid(&tall).contains("65 layers"));
+    }
+}
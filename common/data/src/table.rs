@@ -75,3 +75,228 @@ pub trait IndexType: Sized + Copy + PartialEq + Eq {
 
     fn as_usize(self) -> usize;
 }
+
+/// Handle to a value in a `Table`.
+///
+/// Keys stay valid until their value is removed. Slots of removed values are
+/// reused, but with a new generation, so old keys never point to a new value.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TableKey {
+    index: u32,
+    generation: u32,
+}
+
+impl TableKey {
+    /// Index of the slot the value is stored in.
+    #[inline]
+    pub fn index(self) -> u32 {
+        self.index
+    }
+
+    /// Number of times the slot was reused before the value was inserted.
+    #[inline]
+    pub fn generation(self) -> u32 {
+        self.generation
+    }
+}
+
+/// Values stored in reusable slots, addressed by generational `TableKey`s.
+/// Use it instead of a map with a counter for ids, like for pending tasks.
+pub struct Table<T> {
+    slots: Vec<Slot<T>>,
+    /// Indices of slots without a value, reused last-in first-out.
+    free: Vec<u32>,
+    len: usize,
+}
+
+struct Slot<T> {
+    generation: u32,
+    value: Option<T>,
+}
+
+impl<T> Table<T> {
+    pub fn new() -> Self {
+        Self {
+            slots: Vec::new(),
+            free: Vec::new(),
+            len: 0,
+        }
+    }
+
+    /// Number of values in the table.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Add a value, reusing the slot of a removed value if there is one.
+    pub fn insert(&mut self, value: T) -> TableKey {
+        self.len += 1;
+        if let Some(index) = self.free.pop() {
+            let slot = &mut self.slots[index as usize];
+            slot.value = Some(value);
+            return TableKey {
+                index,
+                generation: slot.generation,
+            };
+        }
+
+        let index =
+            u32::try_from(self.slots.len()).expect("[D430] Table has more than u32::MAX slots.");
+        self.slots.push(Slot {
+            generation: 0,
+            value: Some(value),
+        });
+        TableKey {
+            index,
+            generation: 0,
+        }
+    }
+
+    /// Remove the value of a key, None if it was already removed.
+    pub fn remove(&mut self, key: TableKey) -> Option<T> {
+        let slot = self.slots.get_mut(key.index as usize)?;
+        if slot.generation != key.generation {
+            return None;
+        }
+
+        let value = slot.value.take()?;
+        slot.generation = slot.generation.wrapping_add(1);
+        self.free.push(key.index);
+        self.len -= 1;
+        Some(value)
+    }
+
+    #[inline]
+    pub fn get(&self, key: TableKey) -> Option<&T> {
+        self.slots
+            .get(key.index as usize)
+            .filter(|slot| slot.generation == key.generation)
+            .and_then(|slot| slot.value.as_ref())
+    }
+
+    #[inline]
+    pub fn get_mut(&mut self, key: TableKey) -> Option<&mut T> {
+        self.slots
+            .get_mut(key.index as usize)
+            .filter(|slot| slot.generation == key.generation)
+            .and_then(|slot| slot.value.as_mut())
+    }
+
+    #[inline]
+    pub fn contains(&self, key: TableKey) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Remove every value. Keys of removed values stay invalid.
+    pub fn clear(&mut self) {
+        for index in 0..self.slots.len() {
+            let key = TableKey {
+                index: index as u32,
+                generation: self.slots[index].generation,
+            };
+            self.remove(key);
+        }
+    }
+
+    /// Remove every value the predicate returns false for.
+    pub fn retain(&mut self, mut predicate: impl FnMut(TableKey, &mut T) -> bool) {
+        for index in 0..self.slots.len() {
+            let slot = &mut self.slots[index];
+            let key = TableKey {
+                index: index as u32,
+                generation: slot.generation,
+            };
+            if let Some(value) = &mut slot.value
+                && !predicate(key, value)
+            {
+                self.remove(key);
+            }
+        }
+    }
+
+    /// Every key and value, in order of slot index.
+    pub fn iter(&self) -> impl Iterator<Item = (TableKey, &T)> {
+        self.slots.iter().enumerate().filter_map(|(index, slot)| {
+            let key = TableKey {
+                index: index as u32,
+                generation: slot.generation,
+            };
+            slot.value.as_ref().map(|value| (key, value))
+        })
+    }
+
+    /// Every key and value, in order of slot index.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (TableKey, &mut T)> {
+        self.slots
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(index, slot)| {
+                let key = TableKey {
+                    index: index as u32,
+                    generation: slot.generation,
+                };
+                slot.value.as_mut().map(|value| (key, value))
+            })
+    }
+}
+
+impl<T> Default for Table<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn table_reuses_slots_with_new_generation() {
+        let mut table = Table::new();
+        let a = table.insert("a");
+        let b = table.insert("b");
+        assert_eq!(table.len(), 2);
+        assert_eq!(table.get(a), Some(&"a"));
+
+        assert_eq!(table.remove(a), Some("a"));
+        assert_eq!(table.remove(a), None);
+        assert!(!table.contains(a));
+
+        let c = table.insert("c");
+        assert_eq!(c.index(), a.index());
+        assert_ne!(c.generation(), a.generation());
+        assert_eq!(table.get(a), None);
+        assert_eq!(table.get(c), Some(&"c"));
+
+        *table.get_mut(b).unwrap() = "d";
+        let values: Vec<_> = table.iter().map(|(_, value)| *value).collect();
+        assert_eq!(values, ["c", "d"]);
+    }
+
+    #[test]
+    fn table_retain_and_clear() {
+        let mut table = Table::new();
+        let keys: Vec<_> = (0..6).map(|i| table.insert(i)).collect();
+        table.retain(|_, value| *value % 2 == 0);
+        assert_eq!(table.len(), 3);
+        assert!(table.contains(keys[0]));
+        assert!(!table.contains(keys[1]));
+
+        for (_, value) in table.iter_mut() {
+            *value *= 10;
+        }
+        assert_eq!(table.get(keys[4]), Some(&40));
+
+        table.clear();
+        assert!(table.is_empty());
+        assert!(keys.iter().all(|key| !table.contains(*key)));
+        let key = table.insert(7);
+        assert!(!keys.contains(&key));
+    }
+}
@@ -1,13 +1,23 @@
 use std::{
     alloc::{Allocator, Global},
     cmp::Ordering,
+    collections::BTreeMap,
+    fs,
     hash::Hash,
+    marker::PhantomData,
     ops::Index,
+    path::{Path, PathBuf},
 };
 
-use bevy::ecs::intern::{Internable, Interned, Interner};
+use bevy::{
+    ecs::intern::{Internable, Interned, Interner},
+    prelude::Resource,
+};
+use bitvec::vec::BitVec;
+use fxhash::FxHashMap;
+use serde::Deserialize;
 
-use crate::registry::RegistryId;
+use crate::{fs::path::iter_json_files_recursive, registry::RegistryId};
 
 /// u16 key for a Tag.
 #[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Hash)]
@@ -481,11 +491,290 @@ impl<'a> Iterator for Iter<'a> {
     }
 }
 
+/// A tag file, like `tags/blocks/logs.json` for the "logs" block tag.
+///
+/// Values are names of registry entries, or other tags with a leading '#'.
+/// The values of a tag in every pack are merged, unless a pack sets "replace",
+/// which drops the values of the packs it's layered over.
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct TagFile {
+    #[serde(default)]
+    pub replace: bool,
+    pub values: Vec<String>,
+}
+
+/// Tags of one kind, read from every pack, before their values are resolved.
+#[derive(Clone, Debug, Default)]
+pub struct TagDefs {
+    defs: BTreeMap<String, Vec<String>>,
+}
+
+impl TagDefs {
+    /// Read the tags in the `tags/<kind>` folder of these directories, and its sub-folders.
+    /// Directories earlier in the list are layered over the ones after them.
+    pub fn read<P: AsRef<Path>>(dirs: &[P], kind: &str) -> (Self, Vec<TagError>) {
+        let mut tags = Self::default();
+        let mut errors = Vec::new();
+
+        for dir in dirs.iter().rev() {
+            let folder = dir.as_ref().join("tags").join(kind);
+            if !folder.is_dir() {
+                continue;
+            }
+
+            for (name, path) in iter_json_files_recursive(&folder) {
+                let file = fs::read(&path).map_err(|e| e.to_string()).and_then(|data| {
+                    serde_json::from_slice::<TagFile>(&data).map_err(|e| e.to_string())
+                });
+
+                match file {
+                    Ok(file) => tags.insert(name, file),
+                    Err(msg) => errors.push(TagError::File { path, msg }),
+                }
+            }
+        }
+
+        (tags, errors)
+    }
+
+    /// Layer a tag file over the values of the tag that are already known.
+    pub fn insert(&mut self, name: impl Into<String>, file: TagFile) {
+        let values = self.defs.entry(name.into()).or_default();
+        if file.replace {
+            values.clear();
+        }
+
+        for value in file.values {
+            if !values.contains(&value) {
+                values.push(value);
+            }
+        }
+    }
+
+    /// Resolve the values of every tag into registry entries.
+    ///
+    /// `entries` turns the name of an entry into the ids it stands for, which
+    /// can be more than one, like every state of a block. References to other tags
+    /// are followed, and values that can't be resolved are returned as errors.
+    pub fn resolve<T, I>(&self, entries: impl Fn(&str) -> Option<I>) -> (Tags<T>, Vec<TagError>)
+    where
+        I: IntoIterator<Item = RegistryId>,
+    {
+        let mut errors = Vec::new();
+        let names = self
+            .defs
+            .keys()
+            .enumerate()
+            .map(|(i, name)| (name.clone(), Tag(i as u16)))
+            .collect::<FxHashMap<_, _>>();
+
+        // direct members of each tag, and the tags it references.
+        let mut members = Vec::with_capacity(self.defs.len());
+        let mut references = Vec::with_capacity(self.defs.len());
+        for (name, values) in &self.defs {
+            let mut ids = BitVec::new();
+            let mut refs = Vec::new();
+            for value in values {
+                if let Some(tag) = value.strip_prefix('#') {
+                    match names.get(tag) {
+                        Some(tag) => refs.push(*tag),
+                        None => errors.push(TagError::UnknownTag {
+                            tag: name.clone(),
+                            reference: tag.to_string(),
+                        }),
+                    }
+                    continue;
+                }
+
+                let Some(resolved) = entries(value) else {
+                    errors.push(TagError::UnknownEntry {
+                        tag: name.clone(),
+                        entry: value.clone(),
+                    });
+                    continue;
+                };
+
+                for id in resolved {
+                    if ids.len() <= id.0 {
+                        ids.resize(id.0 + 1, false);
+                    }
+                    ids.set(id.0, true);
+                }
+            }
+            members.push(ids);
+            references.push(refs);
+        }
+
+        // add the members of referenced tags, in an order where references come first.
+        let mut state = vec![Visit::New; members.len()];
+        for tag in 0..members.len() {
+            let mut path = Vec::new();
+            if let Err(cycle) = visit(tag, &references, &mut members, &mut state, &mut path) {
+                let names = self.defs.keys().collect::<Vec<_>>();
+                errors.push(TagError::Cycle {
+                    tag: names[cycle].clone(),
+                    path: path.iter().map(|i| names[*i].clone()).collect(),
+                });
+
+                // tags in the cycle keep the members they had so far.
+                for visit in &mut state {
+                    if *visit == Visit::InProgress {
+                        *visit = Visit::Done;
+                    }
+                }
+            }
+        }
+
+        let len = members.iter().map(|ids| ids.len()).max().unwrap_or(0);
+        let mut of_entry = vec![TagSet::new(); len];
+        for (tag, ids) in members.iter().enumerate() {
+            for id in ids.iter_ones() {
+                of_entry[id].insert(Tag(tag as u16));
+            }
+        }
+
+        let tags = Tags {
+            names,
+            members,
+            of_entry,
+            empty: TagSet::new(),
+            _marker: PhantomData,
+        };
+        (tags, errors)
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq)]
+enum Visit {
+    New,
+    InProgress,
+    Done,
+}
+
+/// Add the members of every tag referenced by `tag` to it, depth first.
+/// Returns the tag where a cycle was found, with the tags leading to it in `path`.
+fn visit(
+    tag: usize,
+    references: &[Vec<Tag>],
+    members: &mut [BitVec],
+    state: &mut [Visit],
+    path: &mut Vec<usize>,
+) -> Result<(), usize> {
+    match state[tag] {
+        Visit::Done => return Ok(()),
+        Visit::InProgress => return Err(tag),
+        Visit::New => {}
+    }
+
+    state[tag] = Visit::InProgress;
+    path.push(tag);
+    for reference in &references[tag] {
+        let reference = reference.0 as usize;
+        visit(reference, references, members, state, path)?;
+
+        let ids = members[reference].iter_ones().collect::<Vec<_>>();
+        let own = &mut members[tag];
+        for id in ids {
+            if own.len() <= id {
+                own.resize(id + 1, false);
+            }
+            own.set(id, true);
+        }
+    }
+    path.pop();
+    state[tag] = Visit::Done;
+    Ok(())
+}
+
+/// Resolved tags of the entries of `Registry<T>`, with fast membership tests.
+#[derive(Resource)]
+pub struct Tags<T: 'static> {
+    names: FxHashMap<String, Tag>,
+
+    /// Entries of each tag, indexed by the tag.
+    members: Vec<BitVec>,
+
+    /// Tags of each entry, indexed by the entry.
+    of_entry: Vec<TagSet>,
+    empty: TagSet,
+
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T: 'static> Default for Tags<T> {
+    fn default() -> Self {
+        TagDefs::default().resolve(|_| None::<Vec<RegistryId>>).0
+    }
+}
+
+impl<T: 'static> Tags<T> {
+    /// Get a tag by name, with or without the leading '#'.
+    pub fn get(&self, name: &str) -> Option<Tag> {
+        let name = name.strip_prefix('#').unwrap_or(name);
+        self.names.get(name).copied()
+    }
+
+    /// Whether the entry has the tag with this name.
+    /// Returns false if there is no tag with the name.
+    pub fn contains(&self, name: &str, id: impl Into<RegistryId>) -> bool {
+        self.get(name).is_some_and(|tag| self.has_tag(tag, id))
+    }
+
+    /// Whether the entry has the tag.
+    pub fn has_tag(&self, tag: Tag, id: impl Into<RegistryId>) -> bool {
+        self.members
+            .get(tag.0 as usize)
+            .and_then(|ids| ids.get(id.into().0).map(|bit| *bit))
+            .unwrap_or(false)
+    }
+
+    /// Every tag the entry has.
+    pub fn tags_of(&self, id: impl Into<RegistryId>) -> &TagSlice {
+        self.of_entry
+            .get(id.into().0)
+            .unwrap_or(&self.empty)
+            .as_slice()
+    }
+
+    /// Entries that have the tag.
+    pub fn members(&self, tag: Tag) -> impl Iterator<Item = RegistryId> {
+        self.members
+            .get(tag.0 as usize)
+            .into_iter()
+            .flat_map(|ids| ids.iter_ones().map(RegistryId))
+    }
+
+    /// Number of tags.
+    pub fn len(&self) -> usize {
+        self.members.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.members.is_empty()
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum TagError {
+    #[error("[D220] Failed to load tag file '{}': {msg}", .path.display())]
+    File { path: PathBuf, msg: String },
+
+    #[error("[D221] Tag '{tag}' references the unknown tag '#{reference}'.")]
+    UnknownTag { tag: String, reference: String },
+
+    #[error("[D222] Tag '{tag}' has the unknown entry '{entry}'.")]
+    UnknownEntry { tag: String, entry: String },
+
+    #[error("[D223] Tag '{tag}' includes itself through: [{}].", .path.join(" -> "))]
+    Cycle { tag: String, path: Vec<String> },
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::tags::Tag;
+    use crate::{registry::RegistryId, tags::Tag, util::TempDir};
 
-    use super::FixedTagSet;
+    use super::{FixedTagSet, TagDefs, TagError, TagFile, Tags};
 
     const TAGS: [Tag; 7] = [Tag(6), Tag(4), Tag(5), Tag(2), Tag(3), Tag(1), Tag(0)];
 
@@ -568,4 +857,103 @@ mod tests {
         assert!(set1.intersects(set2.as_slice()));
         assert!(set2.intersects(set1.as_slice()));
     }
+
+    fn file(values: &[&str]) -> TagFile {
+        TagFile {
+            replace: false,
+            values: values.iter().map(|v| v.to_string()).collect(),
+        }
+    }
+
+    /// Entries "a0".."a9" with ids 0..10, and "pair" standing for 20 and 21.
+    fn entry(name: &str) -> Option<Vec<RegistryId>> {
+        match name {
+            "pair" => Some(vec![RegistryId(20), RegistryId(21)]),
+            _ => name
+                .strip_prefix('a')
+                .and_then(|i| i.parse().ok())
+                .map(|i| vec![RegistryId(i)]),
+        }
+    }
+
+    #[test]
+    fn nested_tags_are_resolved() {
+        let mut defs = TagDefs::default();
+        defs.insert("logs", file(&["a1", "a2"]));
+        defs.insert("mineable/axe", file(&["#logs", "a3", "pair"]));
+        defs.insert("flammable", file(&["#mineable/axe", "a4"]));
+
+        let (tags, errors): (Tags<()>, _) = defs.resolve(entry);
+        assert!(errors.is_empty());
+        assert_eq!(tags.len(), 3);
+
+        assert!(tags.contains("logs", 1usize));
+        assert!(!tags.contains("logs", 3usize));
+        assert!(tags.contains("#mineable/axe", 2usize));
+        assert!(tags.contains("mineable/axe", 21usize));
+        assert!(tags.contains("flammable", 1usize));
+        assert!(tags.contains("flammable", 4usize));
+        assert!(!tags.contains("flammable", 5usize));
+        assert!(!tags.contains("unknown", 1usize));
+
+        let flammable = tags.get("flammable").unwrap();
+        let members = tags.members(flammable).map(|id| id.0).collect::<Vec<_>>();
+        assert_eq!(members, [1, 2, 3, 4, 20, 21]);
+        assert_eq!(tags.tags_of(1usize).iter().count(), 3);
+        assert_eq!(tags.tags_of(4usize).iter().count(), 1);
+        assert_eq!(tags.tags_of(100usize).iter().count(), 0);
+    }
+
+    #[test]
+    fn unknown_values_and_cycles_are_errors() {
+        let mut defs = TagDefs::default();
+        defs.insert("a", file(&["#b", "a1"]));
+        defs.insert("b", file(&["#a", "a2", "missing"]));
+        defs.insert("c", file(&["#nothing", "a3"]));
+
+        let (tags, errors): (Tags<()>, _) = defs.resolve(entry);
+        assert!(tags.contains("c", 3usize));
+        assert_eq!(errors.len(), 3);
+        assert!(errors.iter().any(|e| matches!(e, TagError::Cycle { .. })));
+        assert!(
+            errors
+                .iter()
+                .any(|e| matches!(e, TagError::UnknownTag { .. }))
+        );
+        assert!(
+            errors
+                .iter()
+                .any(|e| matches!(e, TagError::UnknownEntry { entry, .. } if entry == "missing"))
+        );
+    }
+
+    #[test]
+    fn packs_merge_or_replace_tags() {
+        let dir = TempDir::new("tags");
+        let write = |path: &str, data: &str| {
+            let path = dir.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, data).unwrap();
+        };
+        write("base/tags/blocks/logs.json", r#"{ "values": ["a1"] }"#);
+        write(
+            "base/tags/blocks/mineable/pickaxe.json",
+            r#"{ "values": ["a5"] }"#,
+        );
+        write("pack/tags/blocks/logs.json", r#"{ "values": ["a2"] }"#);
+        write(
+            "pack/tags/blocks/mineable/pickaxe.json",
+            r#"{ "replace": true, "values": ["a6"] }"#,
+        );
+        write("pack/tags/blocks/broken.json", "[");
+
+        let (defs, errors) = TagDefs::read(&[dir.join("pack"), dir.join("base")], "blocks");
+        assert_eq!(errors.len(), 1);
+
+        let (tags, errors): (Tags<()>, _) = defs.resolve(entry);
+        assert!(errors.is_empty());
+        assert!(tags.contains("logs", 1usize) && tags.contains("logs", 2usize));
+        assert!(!tags.contains("mineable/pickaxe", 5usize));
+        assert!(tags.contains("mineable/pickaxe", 6usize));
+    }
 }
@@ -1,4 +1,4 @@
-//! Utilities for recording text input.
+//! Utilities for recording text input, and the rich text shown to players in `rich`.
 
 use std::collections::VecDeque;
 
@@ -10,6 +10,8 @@ use bevy::{
     prelude::*,
 };
 
+pub mod rich;
+
 /// Helper struct for working with single-line text input.
 #[derive(Default)]
 pub struct TextRecorder {
@@ -184,10 +186,11 @@ pub enum SpecialKey {
 /// Helper struct for keeping track of recorded text input.
 #[derive(Default)]
 pub struct TextHistory {
-    /// Back of the buffer (low index) is newer messages.
+    /// Front of the buffer (low index) is newer messages.
     history: VecDeque<String>,
 
-    /// Currently selected item in history.
+    /// Number of steps back into the history.
+    /// Zero means no item is selected.
     cursor: usize,
 
     /// Max number of items to keep in the history.
@@ -210,57 +213,71 @@ impl TextHistory {
         self.cursor = 0;
     }
 
-    /// Move the cursor to the newest item in the history.
+    /// Deselect the current item, so the next call to `prev` returns the newest.
     pub fn go_to_start(&mut self) {
         self.cursor = 0;
     }
 
-    /// Move the cursor to the end of the history.
+    /// Select the oldest item in the history.
     pub fn go_to_end(&mut self) {
         self.cursor = self.history.len();
     }
 
-    /// Push a new item onto the back of the stack, and
-    /// reset the cursor to the newest item.
+    /// Push a new item onto the front of the history, and
+    /// deselect the current item.
     pub fn push(&mut self, item: String) {
         self.cursor = 0;
-        self.history.push_back(item);
+        self.history.push_front(item);
         if let Some(limit) = self.limit {
-            if self.history.len() > limit {
-                self.history.pop_front();
-            }
+            self.history.truncate(limit);
         }
     }
 
-    /// Get the item at the cursor.
-    /// Returns nothing if the history is empty.
-    /// If the cursor is `history.len()`, then the last element is returned.
+    /// Get the selected item, or nothing if no item is selected.
     pub fn curr(&self) -> Option<&str> {
-        self.history
-            .get(self.cursor)
-            .or_else(|| self.history.front())
+        self.cursor
+            .checked_sub(1)
+            .and_then(|i| self.history.get(i))
             .map(|s| s.as_str())
     }
 
-    /// Get the next oldest item, if not already at the end of the stack.
+    /// Select the next oldest item, if not already at the oldest.
     pub fn prev(&mut self) -> Option<&str> {
         if self.cursor < self.history.len() {
-            let item = &self.history[self.cursor];
             self.cursor += 1;
-            Some(item)
-        } else {
-            None
         }
+        self.curr()
     }
 
-    /// Get the next newest item, if not already at the newest.
+    /// Select the next newest item. Returns nothing once it
+    /// steps past the newest item, which deselects it.
     pub fn next(&mut self) -> Option<&str> {
-        if self.cursor != 0 {
-            let item = &self.history[self.cursor];
-            self.cursor -= 1;
-            Some(item)
-        } else {
-            None
-        }
+        self.cursor = self.cursor.saturating_sub(1);
+        self.curr()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn history_navigation() {
+        let mut history = TextHistory::with_limit(2);
+        assert_eq!(history.prev(), None);
+
+        history.push("a".into());
+        history.push("b".into());
+        history.push("c".into());
+
+        assert_eq!(history.curr(), None);
+        assert_eq!(history.prev(), Some("c"));
+        assert_eq!(history.prev(), Some("b"));
+
+        // "a" was dropped by the limit.
+        assert_eq!(history.prev(), Some("b"));
+        assert_eq!(history.next(), Some("c"));
+        assert_eq!(history.next(), None);
+        assert_eq!(history.next(), None);
     }
 }
@@ -0,0 +1,391 @@
+//! Rich text, text with colors, styles and click actions, sent by the server for
+//! chat lines and other text shown to players.
+//!
+//! A `RichText` is a tree. Each node has text and a style, and its `extra` children
+//! are drawn after it, taking any style they don't set themselves from their parent.
+//! It is written as JSON, like:
+//! `{ "text": "Hello ", "color": "gold", "extra": [{ "text": "world", "bold": true }] }`
+
+use std::fmt::{self, Display};
+
+use bevy::color::Color;
+use serde::{Deserialize, Serialize};
+
+use crate::locale::Locale;
+
+/// Colors that can be written by name instead of as `#rrggbb`.
+pub const NAMED_COLORS: [(&str, Rgb); 10] = [
+    ("white", Rgb([255, 255, 255])),
+    ("gray", Rgb([170, 170, 170])),
+    ("dark_gray", Rgb([85, 85, 85])),
+    ("black", Rgb([0, 0, 0])),
+    ("red", Rgb([255, 85, 85])),
+    ("gold", Rgb([255, 170, 0])),
+    ("yellow", Rgb([255, 255, 85])),
+    ("green", Rgb([85, 255, 85])),
+    ("aqua", Rgb([85, 255, 255])),
+    ("blue", Rgb([85, 85, 255])),
+];
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct RichText {
+    /// Text of the node, ignored if it is translated.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub text: String,
+
+    /// Locale key the client translates instead of showing `text`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub translate: Option<String>,
+
+    /// Arguments of the translated text, drawn without their style.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub with: Vec<RichText>,
+
+    #[serde(flatten)]
+    pub style: Style,
+
+    /// What happens when the text is clicked, for the node and its children.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub click: Option<ClickAction>,
+
+    /// Text shown while the node or its children are hovered.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hover: Option<Box<RichText>>,
+
+    /// Nodes drawn after this one.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extra: Vec<RichText>,
+}
+
+impl RichText {
+    /// Text without a style.
+    pub fn plain(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            ..Default::default()
+        }
+    }
+
+    /// A locale key translated by the client, with its arguments.
+    pub fn translate(key: impl Into<String>, with: impl IntoIterator<Item = RichText>) -> Self {
+        Self {
+            translate: Some(key.into()),
+            with: with.into_iter().collect(),
+            ..Default::default()
+        }
+    }
+
+    pub fn color(mut self, color: Rgb) -> Self {
+        self.style.color = Some(color);
+        self
+    }
+
+    pub fn bold(mut self) -> Self {
+        self.style.bold = Some(true);
+        self
+    }
+
+    pub fn italic(mut self) -> Self {
+        self.style.italic = Some(true);
+        self
+    }
+
+    pub fn underlined(mut self) -> Self {
+        self.style.underlined = Some(true);
+        self
+    }
+
+    pub fn on_click(mut self, action: ClickAction) -> Self {
+        self.click = Some(action);
+        self
+    }
+
+    pub fn on_hover(mut self, text: impl Into<RichText>) -> Self {
+        self.hover = Some(Box::new(text.into()));
+        self
+    }
+
+    /// Add a node drawn after this one.
+    pub fn push(mut self, extra: impl Into<RichText>) -> Self {
+        self.extra.push(extra.into());
+        self
+    }
+
+    /// Text of this node alone, translated if it has a key.
+    fn own_text(&self, locale: &Locale) -> String {
+        let Some(key) = &self.translate else {
+            return self.text.clone();
+        };
+
+        let args = self
+            .with
+            .iter()
+            .map(|arg| arg.to_plain(locale))
+            .collect::<Vec<_>>();
+        let args = args
+            .iter()
+            .map(|arg| arg as &dyn Display)
+            .collect::<Vec<_>>();
+        locale.format(key, &args)
+    }
+
+    /// The whole text without styles, for places that can't draw them like logs.
+    pub fn to_plain(&self, locale: &Locale) -> String {
+        let mut text = self.own_text(locale);
+        for extra in &self.extra {
+            text.push_str(&extra.to_plain(locale));
+        }
+        text
+    }
+
+    /// Every node with text, in order, with the style it inherited from its parents.
+    pub fn spans(&self, locale: &Locale) -> Vec<Span<'_>> {
+        let mut spans = Vec::new();
+        self.collect_spans(locale, Style::default(), None, None, &mut spans);
+        spans
+    }
+
+    fn collect_spans<'a>(
+        &'a self,
+        locale: &Locale,
+        style: Style,
+        click: Option<&'a ClickAction>,
+        hover: Option<&'a RichText>,
+        spans: &mut Vec<Span<'a>>,
+    ) {
+        let style = self.style.inherit(style);
+        let click = self.click.as_ref().or(click);
+        let hover = self.hover.as_deref().or(hover);
+
+        let text = self.own_text(locale);
+        if !text.is_empty() {
+            spans.push(Span {
+                text,
+                style,
+                click,
+                hover,
+            });
+        }
+
+        for extra in &self.extra {
+            extra.collect_spans(locale, style, click, hover, spans);
+        }
+    }
+}
+
+impl From<&str> for RichText {
+    fn from(text: &str) -> Self {
+        Self::plain(text)
+    }
+}
+
+impl From<String> for RichText {
+    fn from(text: String) -> Self {
+        Self::plain(text)
+    }
+}
+
+/// How a node is drawn. Unset fields are taken from the parent node.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Style {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<Rgb>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bold: Option<bool>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub italic: Option<bool>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub underlined: Option<bool>,
+}
+
+impl Style {
+    /// This style, with unset fields taken from the parent.
+    pub fn inherit(self, parent: Style) -> Style {
+        Style {
+            color: self.color.or(parent.color),
+            bold: self.bold.or(parent.bold),
+            italic: self.italic.or(parent.italic),
+            underlined: self.underlined.or(parent.underlined),
+        }
+    }
+
+    pub fn is_bold(&self) -> bool {
+        self.bold == Some(true)
+    }
+
+    pub fn is_italic(&self) -> bool {
+        self.italic == Some(true)
+    }
+
+    pub fn is_underlined(&self) -> bool {
+        self.underlined == Some(true)
+    }
+}
+
+/// A color written as a name from `NAMED_COLORS` or as `#rrggbb`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Rgb(pub [u8; 3]);
+
+impl Rgb {
+    pub const WHITE: Self = NAMED_COLORS[0].1;
+    pub const GRAY: Self = NAMED_COLORS[1].1;
+    pub const RED: Self = NAMED_COLORS[4].1;
+    pub const GOLD: Self = NAMED_COLORS[5].1;
+    pub const YELLOW: Self = NAMED_COLORS[6].1;
+    pub const GREEN: Self = NAMED_COLORS[7].1;
+    pub const AQUA: Self = NAMED_COLORS[8].1;
+}
+
+impl TryFrom<String> for Rgb {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        if let Some((_, color)) = NAMED_COLORS.iter().find(|(name, _)| *name == value) {
+            return Ok(*color);
+        }
+
+        let hex = value
+            .strip_prefix('#')
+            .filter(|hex| hex.len() == 6)
+            .and_then(|hex| u32::from_str_radix(hex, 16).ok());
+        match hex {
+            Some(hex) => Ok(Rgb([(hex >> 16) as u8, (hex >> 8) as u8, hex as u8])),
+            None => Err(format!(
+                "expected a color name or '#rrggbb', found '{value}'"
+            )),
+        }
+    }
+}
+
+impl From<Rgb> for String {
+    fn from(color: Rgb) -> Self {
+        color.to_string()
+    }
+}
+
+impl Display for Rgb {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match NAMED_COLORS.iter().find(|(_, named)| named == self) {
+            Some((name, _)) => f.write_str(name),
+            None => {
+                let [r, g, b] = self.0;
+                write!(f, "#{r:02x}{g:02x}{b:02x}")
+            }
+        }
+    }
+}
+
+impl From<Rgb> for Color {
+    fn from(color: Rgb) -> Self {
+        let [r, g, b] = color.0;
+        Color::srgb_u8(r, g, b)
+    }
+}
+
+/// What clicking a node does, written like `{ "action": "run_command", "value": "/help" }`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "action", content = "value", rename_all = "snake_case")]
+pub enum ClickAction {
+    /// Send a line to the chat as if it was typed, usually a command.
+    RunCommand(String),
+
+    /// Replace the chat input with this text.
+    SuggestCommand(String),
+
+    /// Insert this text into the chat input at the cursor.
+    Insert(String),
+}
+
+/// Text of one node of a `RichText`, with its inherited style and actions.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Span<'a> {
+    pub text: String,
+    pub style: Style,
+    pub click: Option<&'a ClickAction>,
+    pub hover: Option<&'a RichText>,
+}
+
+/// Sent from the server to clients with a line to show in the chat.
+/// Kept here rather than with the other packets, since its text is a `RichText`.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ChatMessage {
+    /// The `Session` of the player that sent the message,
+    /// or `None` if it came from the server.
+    pub sender: Option<u64>,
+
+    /// Display name of the sender, if any.
+    pub sender_name: Option<String>,
+
+    pub text: RichText,
+
+    /// Seconds since the unix epoch the server received the message.
+    pub timestamp: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn children_inherit_style_and_actions() {
+        let help = ClickAction::SuggestCommand("/help".into());
+        let text = RichText::plain("Type ")
+            .color(Rgb::GRAY)
+            .push(
+                RichText::plain("/help")
+                    .color(Rgb::GOLD)
+                    .underlined()
+                    .on_click(help.clone())
+                    .push(RichText::plain("!").bold()),
+            )
+            .push(" for commands.");
+
+        let locale = Locale::default();
+        assert_eq!(text.to_plain(&locale), "Type /help! for commands.");
+
+        let spans = text.spans(&locale);
+        assert_eq!(spans.len(), 4);
+        assert_eq!(spans[0].style.color, Some(Rgb::GRAY));
+        assert_eq!(spans[1].style.color, Some(Rgb::GOLD));
+        assert_eq!(spans[1].click, Some(&help));
+        assert!(spans[2].style.is_bold() && spans[2].style.is_underlined());
+        assert_eq!(spans[2].style.color, Some(Rgb::GOLD));
+        assert_eq!(spans[2].click, Some(&help));
+        assert_eq!(spans[3].style.color, Some(Rgb::GRAY));
+        assert!(!spans[3].style.is_underlined());
+        assert_eq!(spans[3].click, None);
+    }
+
+    #[test]
+    fn translated_text_uses_the_locale() {
+        let mut locale = Locale::default();
+        locale.insert("chat.online", "{} online: {}");
+
+        let text = RichText::translate(
+            "chat.online",
+            [RichText::plain("2"), RichText::plain("a, ").push("b")],
+        );
+        assert_eq!(text.to_plain(&locale), "2 online: a, b");
+
+        // keys missing from the locale are shown as they are.
+        let missing = RichText::translate("chat.missing", []);
+        assert_eq!(missing.to_plain(&locale), "chat.missing");
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let json = r##"{"text":"Hi","color":"#102030","bold":true,"click":{"action":"run_command","value":"/list"},"extra":[{"text":"!","color":"red"}]}"##;
+        let text = serde_json::from_str::<RichText>(json).unwrap();
+        assert_eq!(text.style.color, Some(Rgb([0x10, 0x20, 0x30])));
+        assert_eq!(text.extra[0].style.color, Some(Rgb::RED));
+        assert_eq!(text.click, Some(ClickAction::RunCommand("/list".into())));
+        assert_eq!(serde_json::to_string(&text).unwrap(), json);
+
+        assert!(serde_json::from_str::<RichText>(r#"{"color":"pink"}"#).is_err());
+        assert!(serde_json::from_str::<RichText>(r##"{"color":"#12345"}"##).is_err());
+    }
+}
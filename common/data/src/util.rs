@@ -28,3 +28,32 @@ pub fn load_assets_from_folder<P: AsRef<Path>, A: Asset>(
 
     Ok(entries)
 }
+
+/// A directory for the files of a test, in the temporary directory of the OS.
+/// It is removed when dropped, so it is cleaned up even when an assert of the test fails.
+/// Shared with the tests of the other crates, which can't see `cfg(test)` items of this one.
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    /// Path of the directory, named after the test and the process. It doesn't exist
+    /// until the test creates it, files left behind by a killed run are removed.
+    pub fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("openvoxel-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        Self(dir)
+    }
+}
+
+impl std::ops::Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
@@ -0,0 +1,194 @@
+//! Biomes, read from `worldgen/biomes`, are picked for each column of the world by its
+//! climate and the height of its surface, and decide the blocks its surface is made of.
+//!
+//! ```json
+//! {
+//!     "color": [96, 160, 64],
+//!     "climate": { "temperature": 0.2, "humidity": 0.3 },
+//!     "height": [-4, 40],
+//!     "surface": [
+//!         { "block": "grass", "depth": 1 },
+//!         { "block": "dirt", "depth": 3 }
+//!     ]
+//! }
+//! ```
+//!
+//! Temperature and humidity go from -1 to 1, and each column gets the biome whose `climate`
+//! is closest to its own, out of the biomes whose `height` range has the surface of the
+//! column. If none of them do, the height is ignored. The `surface` layers replace the top
+//! blocks of the column from the top down, and `color` is the color of the biome on maps.
+
+use serde::Deserialize;
+
+use crate::worldgen::WorldgenErrorKind;
+
+/// Most blocks a single surface layer may replace.
+pub const MAX_LAYER_DEPTH: u32 = 16;
+
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct BiomeFeature {
+    pub color: [u8; 3],
+    pub climate: Climate,
+
+    /// Lowest and highest surface the biome is picked for, or any if None.
+    #[serde(default)]
+    pub height: Option<[i32; 2]>,
+
+    #[serde(default)]
+    pub surface: Vec<SurfaceLayer>,
+}
+
+/// Temperature and humidity of a column, from -1 to 1.
+#[derive(Deserialize, Copy, Clone, Debug, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Climate {
+    pub temperature: f32,
+    pub humidity: f32,
+}
+
+/// Blocks that replace the top of a column.
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct SurfaceLayer {
+    /// Name of the block state of the layer.
+    pub block: String,
+
+    /// Number of blocks in the layer.
+    pub depth: u32,
+}
+
+impl Climate {
+    pub fn distance_squared(&self, other: &Climate) -> f32 {
+        (self.temperature - other.temperature).powi(2) + (self.humidity - other.humidity).powi(2)
+    }
+}
+
+impl BiomeFeature {
+    /// Parse and validate a biome file.
+    /// Block state names aren't checked, since states are registered by the game.
+    pub fn parse(data: &[u8]) -> Result<Self, WorldgenErrorKind> {
+        let biome = serde_json::from_slice::<Self>(data).map_err(WorldgenErrorKind::Parse)?;
+        let invalid = |msg: String| Err(WorldgenErrorKind::Invalid(msg));
+
+        let Climate {
+            temperature,
+            humidity,
+        } = biome.climate;
+        if !(-1.0..=1.0).contains(&temperature) || !(-1.0..=1.0).contains(&humidity) {
+            return invalid(format!(
+                "the climate has a temperature of {temperature} and humidity of {humidity}, both must be between -1 and 1"
+            ));
+        }
+
+        if let Some([min_y, max_y]) = biome.height
+            && min_y > max_y
+        {
+            return invalid(format!(
+                "the height goes from {min_y} down to {max_y}, it must go up"
+            ));
+        }
+
+        if let Some(layer) = biome
+            .surface
+            .iter()
+            .find(|layer| !(1..=MAX_LAYER_DEPTH).contains(&layer.depth))
+        {
+            return invalid(format!(
+                "the '{}' layer is {} blocks deep, it must be between 1 and {MAX_LAYER_DEPTH}",
+                layer.block, layer.depth
+            ));
+        }
+
+        Ok(biome)
+    }
+
+    /// Whether the biome may be picked for a column with its surface at this Y.
+    pub fn allows_height(&self, y: i32) -> bool {
+        self.height
+            .is_none_or(|[min_y, max_y]| (min_y..=max_y).contains(&y))
+    }
+}
+
+/// Index of the biome picked for a column with this climate and surface height,
+/// or None if there are no biomes.
+pub fn pick_biome<'a>(
+    biomes: impl IntoIterator<Item = &'a BiomeFeature>,
+    climate: Climate,
+    height: i32,
+) -> Option<usize> {
+    biomes
+        .into_iter()
+        .enumerate()
+        .map(|(i, biome)| {
+            let key = (
+                !biome.allows_height(height),
+                biome.climate.distance_squared(&climate),
+            );
+            (i, key)
+        })
+        .min_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap())
+        .map(|(i, _)| i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn biome(temperature: f32, height: Option<[i32; 2]>) -> BiomeFeature {
+        BiomeFeature {
+            color: [0; 3],
+            climate: Climate {
+                temperature,
+                humidity: 0.0,
+            },
+            height,
+            surface: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn closest_biome_with_the_height_is_picked() {
+        let biomes = [
+            biome(-0.5, None),
+            biome(0.5, None),
+            biome(0.0, Some([40, 100])),
+        ];
+        let climate = |temperature| Climate {
+            temperature,
+            humidity: 0.0,
+        };
+
+        assert_eq!(pick_biome(&biomes, climate(-0.1), 0), Some(0));
+        assert_eq!(pick_biome(&biomes, climate(0.4), 0), Some(1));
+        assert_eq!(pick_biome(&biomes, climate(0.4), 60), Some(2));
+        assert_eq!(pick_biome(&biomes[2..], climate(0.4), 0), Some(0));
+        assert_eq!(pick_biome(&[] as &[BiomeFeature], climate(0.0), 0), None);
+    }
+
+    #[test]
+    fn invalid_biomes_are_rejected() {
+        let parse = |json: &str| BiomeFeature::parse(json.as_bytes());
+        let biome = parse(
+            r#"{
+                "color": [1, 2, 3],
+                "climate": { "temperature": 0.5, "humidity": -0.5 },
+                "surface": [{ "block": "sand", "depth": 4 }]
+            }"#,
+        )
+        .unwrap();
+        assert!(biome.allows_height(i32::MIN));
+        assert_eq!(biome.surface[0].depth, 4);
+
+        let invalid = |json: &str| matches!(parse(json), Err(WorldgenErrorKind::Invalid(_)));
+        assert!(invalid(
+            r#"{ "color": [0, 0, 0], "climate": { "temperature": 2, "humidity": 0 } }"#
+        ));
+        assert!(invalid(
+            r#"{ "color": [0, 0, 0], "climate": { "temperature": 0, "humidity": 0 }, "height": [5, 0] }"#
+        ));
+        assert!(invalid(
+            r#"{ "color": [0, 0, 0], "climate": { "temperature": 0, "humidity": 0 }, "surface": [{ "block": "a", "depth": 0 }] }"#
+        ));
+    }
+}
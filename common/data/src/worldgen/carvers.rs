@@ -0,0 +1,164 @@
+//! Carvers, read from `worldgen/carvers`, hollow caves out of the terrain of each chunk
+//! in the terrain stage of generation, before the surface and features are placed.
+//!
+//! ```json
+//! {
+//!     "height": [-120, 40],
+//!     "cheese": { "scale": 0.02, "squash": 2.0, "threshold": [[-128, 0.45], [32, 0.9]] },
+//!     "tunnels": { "scale": 0.015, "squash": 1.5, "width": [[-128, 0.06], [32, 0.03]] },
+//!     "aquifer": { "level": -48, "fluid": "water" }
+//! }
+//! ```
+//!
+//! Voxels between the two Y values of `height` are carved if they are in a cave of either kind.
+//!  - `cheese` caves are large open caves, carved where 3D simplex noise is above the
+//!    `threshold` curve, which maps the Y of a voxel to a noise value between -1 and 1.
+//!  - `tunnels` are long winding worm tunnels, carved along the edges of 3D Worley cells,
+//!    where the nearest three cell points are about as far from a voxel as each other. The
+//!    `width` curve maps the Y of a voxel to how much their distances may differ, in cells.
+//!
+//! `scale` is the size of the noise in cells per voxel, and `squash` makes the caves
+//! that many times flatter than they are wide. Voxels carved below the `level` of the
+//! `aquifer` are filled with its `fluid` instead of air, once that block exists.
+
+use math::spline::Spline;
+use serde::Deserialize;
+
+use crate::worldgen::WorldgenErrorKind;
+
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct CarverFeature {
+    /// Lowest and highest Y the carver carves.
+    pub height: [i32; 2],
+
+    #[serde(default)]
+    pub cheese: Option<CheeseCaves>,
+
+    #[serde(default)]
+    pub tunnels: Option<Tunnels>,
+
+    #[serde(default)]
+    pub aquifer: Option<Aquifer>,
+}
+
+/// Large caves where 3D noise is above a threshold.
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct CheeseCaves {
+    pub scale: f32,
+
+    #[serde(default = "default_squash")]
+    pub squash: f32,
+
+    /// Maps the Y of a voxel to the noise value it is carved above.
+    pub threshold: Spline,
+}
+
+/// Winding tunnels along the edges of Worley cells.
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Tunnels {
+    pub scale: f32,
+
+    #[serde(default = "default_squash")]
+    pub squash: f32,
+
+    /// Maps the Y of a voxel to the width of the tunnels there, in cells.
+    pub width: Spline,
+}
+
+/// Fluid that fills caves carved below a level.
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Aquifer {
+    /// Voxels carved below this Y are filled with the fluid.
+    pub level: i32,
+
+    /// Name of the block state of the fluid.
+    pub fluid: String,
+}
+
+fn default_squash() -> f32 {
+    1.0
+}
+
+impl CarverFeature {
+    /// Parse and validate a carver file.
+    /// The fluid of the aquifer isn't checked, since blocks are registered by the game.
+    pub fn parse(data: &[u8]) -> Result<Self, WorldgenErrorKind> {
+        let carver = serde_json::from_slice::<Self>(data).map_err(WorldgenErrorKind::Parse)?;
+        let invalid = |msg: String| Err(WorldgenErrorKind::Invalid(msg));
+
+        let [min_y, max_y] = carver.height;
+        if min_y > max_y {
+            return invalid(format!(
+                "the height goes from {min_y} down to {max_y}, it must go up"
+            ));
+        }
+
+        if carver.cheese.is_none() && carver.tunnels.is_none() {
+            return invalid("it has neither cheese caves nor tunnels".into());
+        }
+
+        let noise = [
+            carver
+                .cheese
+                .as_ref()
+                .map(|c| ("cheese", c.scale, c.squash)),
+            carver
+                .tunnels
+                .as_ref()
+                .map(|t| ("tunnels", t.scale, t.squash)),
+        ];
+        for (kind, scale, squash) in noise.into_iter().flatten() {
+            if !(scale.is_finite() && scale > 0.0) || !(squash.is_finite() && squash > 0.0) {
+                return invalid(format!(
+                    "the {kind} have a scale of {scale} and squash of {squash}, both must be above 0"
+                ));
+            }
+        }
+
+        Ok(carver)
+    }
+
+    /// Whether a voxel at this Y may be carved.
+    pub fn carves_y(&self, y: i32) -> bool {
+        (self.height[0]..=self.height[1]).contains(&y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn carvers_are_parsed_and_validated() {
+        let carver = CarverFeature::parse(
+            br##"{
+                "height": [-64, 32],
+                "tunnels": { "scale": 0.02, "width": [[0, 0.05]] },
+                "aquifer": { "level": -40, "fluid": "water" }
+            }"##,
+        )
+        .unwrap();
+        assert_eq!(carver.tunnels.as_ref().unwrap().squash, 1.0);
+        assert!(carver.carves_y(-64) && carver.carves_y(32));
+        assert!(!carver.carves_y(33));
+        assert_eq!(carver.aquifer.unwrap().level, -40);
+
+        let invalid = |json: &str| {
+            matches!(
+                CarverFeature::parse(json.as_bytes()),
+                Err(WorldgenErrorKind::Invalid(_))
+            )
+        };
+        assert!(invalid(r#"{ "height": [0, 10] }"#));
+        assert!(invalid(
+            r#"{ "height": [10, 0], "tunnels": { "scale": 0.02, "width": [[0, 0.05]] } }"#
+        ));
+        assert!(invalid(
+            r#"{ "height": [0, 10], "cheese": { "scale": 0, "threshold": [[0, 0.5]] } }"#
+        ));
+    }
+}
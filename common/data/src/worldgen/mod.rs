@@ -0,0 +1,78 @@
+//! Worldgen settings, read from the `worldgen` folder of data packs, configure what the
+//! server generates into new chunks.
+//!
+//! Each kind of setting has a sub-folder, like `worldgen/ores`, with a JSON file for each
+//! entry named by its path in the sub-folder, so `worldgen/ores/deep/diamond.json` is the
+//! ore named `deep/diamond`. Files in data packs replace the files with the same name
+//! in the data directory, so packs can change or turn off the default features.
+
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{fs::path::iter_json_files_recursive, registry::Registry};
+
+pub mod biomes;
+pub mod carvers;
+pub mod ores;
+
+/// Read and parse the JSON files in the `worldgen/<folder>` folder of these directories, and
+/// its sub-folders. Files in directories earlier in the list replace the ones with the same
+/// name after them.
+pub fn read_worldgen<T, P: AsRef<Path>>(
+    dirs: &[P],
+    folder: &str,
+    parse: impl Fn(&[u8]) -> Result<T, WorldgenErrorKind>,
+) -> (Registry<T>, Vec<WorldgenError>) {
+    let mut entries = BTreeMap::<String, T>::new();
+    let mut errors = Vec::new();
+
+    for dir in dirs.iter().rev() {
+        let folder = dir.as_ref().join("worldgen").join(folder);
+        if !folder.is_dir() {
+            continue;
+        }
+
+        for (name, path) in iter_json_files_recursive(&folder) {
+            let entry = fs::read(&path)
+                .map_err(WorldgenErrorKind::Read)
+                .and_then(|data| parse(&data));
+
+            match entry {
+                Ok(entry) => {
+                    entries.insert(name, entry);
+                }
+                Err(kind) => errors.push(WorldgenError { path, kind }),
+            }
+        }
+    }
+
+    let mut registry = Registry::new();
+    for (name, entry) in entries {
+        registry.insert(name, entry);
+    }
+
+    (registry, errors)
+}
+
+/// A worldgen file that couldn't be loaded.
+#[derive(thiserror::Error, Debug)]
+#[error("{} (in '{}')", .kind, .path.display())]
+pub struct WorldgenError {
+    pub path: PathBuf,
+    pub kind: WorldgenErrorKind,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum WorldgenErrorKind {
+    #[error("[D260] Failed to read worldgen file: {0}")]
+    Read(io::Error),
+
+    #[error("[D261] Worldgen file is not valid JSON: {0}")]
+    Parse(serde_json::Error),
+
+    #[error("[D262] Invalid worldgen file: {0}")]
+    Invalid(String),
+}
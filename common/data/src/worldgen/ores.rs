@@ -0,0 +1,127 @@
+//! Ores, read from `worldgen/ores`, are veins of a block scattered through the stone of each
+//! chunk in the features stage of generation.
+//!
+//! ```json
+//! {
+//!     "block": "coal_ore",
+//!     "replace": "#stone_ore_replaceables",
+//!     "vein_size": 12,
+//!     "per_chunk": 20,
+//!     "height": [[-64, 0.0], [0, 1.0], [128, 0.2]]
+//! }
+//! ```
+//!
+//! `block` is the block state of the ore, and `replace` is the block, or the tag with a
+//! leading '#', of the voxels a vein may replace. Up to `per_chunk` veins of `vein_size`
+//! voxels are placed in each chunk, spread apart so they don't clump together, and
+//! `height` maps the Y of a vein to how likely it is to be placed there, from 0 to 1.
+
+use math::spline::Spline;
+use serde::Deserialize;
+
+use crate::worldgen::WorldgenErrorKind;
+
+/// Most voxels a single vein may have.
+pub const MAX_VEIN_SIZE: u32 = 64;
+
+/// Most veins of an ore a chunk may have.
+pub const MAX_VEINS_PER_CHUNK: u32 = 256;
+
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct OreFeature {
+    /// Name of the block state placed for the ore.
+    pub block: String,
+
+    /// Name of the block, or tag with a leading '#', of the voxels the ore may replace.
+    pub replace: String,
+
+    /// Number of voxels in each vein.
+    pub vein_size: u32,
+
+    /// Max number of veins in each chunk.
+    pub per_chunk: u32,
+
+    /// Maps the Y of a vein to the chance of it being placed at that height.
+    pub height: Spline,
+}
+
+impl OreFeature {
+    /// Parse and validate an ore file.
+    /// Block and tag names aren't checked, since they are registered by the game.
+    pub fn parse(data: &[u8]) -> Result<Self, WorldgenErrorKind> {
+        let ore = serde_json::from_slice::<Self>(data).map_err(WorldgenErrorKind::Parse)?;
+        let invalid = |msg: String| Err(WorldgenErrorKind::Invalid(msg));
+
+        if !(1..=MAX_VEIN_SIZE).contains(&ore.vein_size) {
+            return invalid(format!(
+                "vein_size is {}, it must be between 1 and {MAX_VEIN_SIZE}",
+                ore.vein_size
+            ));
+        }
+
+        if ore.per_chunk > MAX_VEINS_PER_CHUNK {
+            return invalid(format!(
+                "per_chunk is {}, at most {MAX_VEINS_PER_CHUNK} are allowed",
+                ore.per_chunk
+            ));
+        }
+
+        if let Some([y, chance]) = ore
+            .height
+            .points()
+            .iter()
+            .find(|[_, chance]| !(0.0..=1.0).contains(chance))
+        {
+            return invalid(format!(
+                "the height curve has a chance of {chance} at y {y}, it must be between 0 and 1"
+            ));
+        }
+
+        Ok(ore)
+    }
+
+    /// Chance of a vein being placed at a height.
+    pub fn chance_at(&self, y: i32) -> f32 {
+        self.height.sample(y as f32).clamp(0.0, 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ores_are_parsed_and_validated() {
+        let ore = OreFeature::parse(
+            br##"{
+                "block": "coal_ore",
+                "replace": "#stone",
+                "vein_size": 8,
+                "per_chunk": 16,
+                "height": [[0, 1.0], [64, 0.0]]
+            }"##,
+        )
+        .unwrap();
+        assert_eq!(ore.block, "coal_ore");
+        assert_eq!(ore.chance_at(-10), 1.0);
+        assert_eq!(ore.chance_at(32), 0.5);
+        assert_eq!(ore.chance_at(100), 0.0);
+
+        let invalid = |json: &str| {
+            matches!(
+                OreFeature::parse(json.as_bytes()),
+                Err(WorldgenErrorKind::Invalid(_))
+            )
+        };
+        assert!(invalid(
+            r#"{ "block": "a", "replace": "b", "vein_size": 0, "per_chunk": 1, "height": [[0, 1]] }"#
+        ));
+        assert!(invalid(
+            r#"{ "block": "a", "replace": "b", "vein_size": 4, "per_chunk": 1000, "height": [[0, 1]] }"#
+        ));
+        assert!(invalid(
+            r#"{ "block": "a", "replace": "b", "vein_size": 4, "per_chunk": 1, "height": [[0, 2]] }"#
+        ));
+    }
+}
@@ -35,10 +35,9 @@ impl Activity {
     //
     // A negative alpha will shift the value toward zero.
     // A positive alpha will shift the value toward one.
-    pub fn update(&mut self, mut alpha: f32) {
+    pub fn update(&mut self, alpha: f32) {
         let target = (alpha >= 0.0) as u32 as f32;
-        alpha = alpha.abs();
-        self.value = self.value * (1.0 - alpha) + target * alpha;
+        self.value = approach(self.value, target, alpha.abs());
     }
 }
 
@@ -47,3 +46,187 @@ impl Default for Activity {
         Self::DEFAULT
     }
 }
+
+/// Exponential moving average, with separate rates for rising and falling.
+///
+/// A fast rise and slow fall reacts quickly to spikes and keeps them in
+/// mind for a while, like when estimating the worst case of a noisy value.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Ema {
+    value: f32,
+
+    /// Fraction of the difference moved by a sample above the value, in the range 0.0..=1.0.
+    rise: f32,
+
+    /// Fraction of the difference moved by a sample below the value, in the range 0.0..=1.0.
+    fall: f32,
+}
+
+impl Ema {
+    /// An average that starts at zero, with a rise and fall alpha.
+    pub const fn new(rise: f32, fall: f32) -> Self {
+        Self {
+            value: 0.0,
+            rise: rise.clamp(0.0, 1.0),
+            fall: fall.clamp(0.0, 1.0),
+        }
+    }
+
+    /// An average that moves at the same rate in both directions.
+    pub const fn symmetric(alpha: f32) -> Self {
+        Self::new(alpha, alpha)
+    }
+
+    /// Start the average at a value instead of zero.
+    pub const fn with_value(mut self, value: f32) -> Self {
+        self.value = value;
+        self
+    }
+
+    pub const fn value(&self) -> f32 {
+        self.value
+    }
+
+    /// Move the average toward a sample, returning the new average.
+    pub fn update(&mut self, sample: f32) -> f32 {
+        let alpha = if sample > self.value {
+            self.rise
+        } else {
+            self.fall
+        };
+        self.value = approach(self.value, sample, alpha);
+        self.value
+    }
+
+    /// Jump straight to a value, forgetting the previous samples.
+    pub fn reset(&mut self, value: f32) {
+        self.value = value;
+    }
+}
+
+/// A switch that turns on when a value rises above one threshold, and off
+/// when it falls below a lower one, so a value near a threshold doesn't
+/// make it flicker. With debounce, the value must stay past the threshold
+/// for a number of updates in a row before the switch changes.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Hysteresis {
+    /// Turns on when the value is above this.
+    on: f32,
+
+    /// Turns off when the value is below this.
+    off: f32,
+
+    /// Number of updates in a row the value must be past a threshold.
+    debounce: u32,
+
+    /// Number of updates in a row the value has been past the threshold.
+    pending: u32,
+
+    state: bool,
+}
+
+impl Hysteresis {
+    /// A switch that starts off. If "off" is greater than "on",
+    /// both thresholds are "on".
+    pub const fn new(on: f32, off: f32) -> Self {
+        Self {
+            on,
+            off: off.min(on),
+            debounce: 1,
+            pending: 0,
+            state: false,
+        }
+    }
+
+    /// Require the value to be past a threshold for a number of updates
+    /// in a row before switching. A debounce of 0 is treated as 1.
+    pub const fn with_debounce(mut self, updates: u32) -> Self {
+        self.debounce = if updates == 0 { 1 } else { updates };
+        self
+    }
+
+    pub const fn is_on(&self) -> bool {
+        self.state
+    }
+
+    /// Update the switch with a value. Returns "true" if the switch changed.
+    pub fn update(&mut self, value: f32) -> bool {
+        let past = if self.state {
+            value < self.off
+        } else {
+            value > self.on
+        };
+
+        if !past {
+            self.pending = 0;
+            return false;
+        }
+
+        self.pending += 1;
+        if self.pending < self.debounce {
+            return false;
+        }
+
+        self.pending = 0;
+        self.state = !self.state;
+        true
+    }
+}
+
+/// Move "value" toward "target" by a fraction of the difference.
+#[inline]
+fn approach(value: f32, target: f32, alpha: f32) -> f32 {
+    value * (1.0 - alpha) + target * alpha
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ema_rise_and_fall() {
+        let mut ema = Ema::new(1.0, 0.5);
+        assert_eq!(ema.update(8.0), 8.0);
+        assert_eq!(ema.update(0.0), 4.0);
+        assert_eq!(ema.update(0.0), 2.0);
+        assert_eq!(ema.update(10.0), 10.0);
+
+        let mut ema = Ema::symmetric(0.25).with_value(4.0);
+        assert_eq!(ema.update(0.0), 3.0);
+        ema.reset(1.0);
+        assert_eq!(ema.value(), 1.0);
+    }
+
+    #[test]
+    fn hysteresis_thresholds_and_debounce() {
+        let mut switch = Hysteresis::new(0.8, 0.2);
+        assert!(!switch.update(0.5));
+        assert!(switch.update(0.9));
+        assert!(switch.is_on());
+        // between the thresholds, stays on.
+        assert!(!switch.update(0.5));
+        assert!(switch.update(0.1));
+        assert!(!switch.is_on());
+
+        let mut switch = Hysteresis::new(0.8, 0.2).with_debounce(3);
+        assert!(!switch.update(0.9));
+        assert!(!switch.update(0.9));
+        // dipping below resets the count.
+        assert!(!switch.update(0.5));
+        assert!(!switch.update(0.9));
+        assert!(!switch.update(0.9));
+        assert!(switch.update(0.9));
+        assert!(switch.is_on());
+    }
+
+    #[test]
+    fn activity_matches_ema() {
+        let mut activity = Activity::new();
+        let mut ema = Ema::new(0.5, 0.1);
+        for rising in [true, true, false, true, false, false] {
+            activity.update(if rising { 0.5 } else { -0.1 });
+            ema.update(rising as u32 as f32);
+            assert!((*activity - ema.value()).abs() < 1e-6);
+        }
+    }
+}
@@ -0,0 +1,262 @@
+//! Collision of axis-aligned boxes with the voxel grid.
+//!
+//! Boxes are swept along their velocity instead of being moved and then checked,
+//! so they can't pass through thin walls when moving fast. Voxels are tested with
+//! a function that returns whether the voxel at a position blocks movement,
+//! so the same routines can be used with any world representation.
+
+use bevy::math::{IVec3, Vec3, ivec3};
+
+use crate::axis::Axis;
+
+/// Gap kept between a box and the voxels it collides with,
+/// so the box is never considered to be inside them.
+pub const SKIN: f32 = 0.001;
+
+/// An axis-aligned box, in voxels.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    pub const fn new(min: Vec3, max: Vec3) -> Self {
+        Self { min, max }
+    }
+
+    /// A box standing on "feet", like the collision box of a player.
+    pub fn from_feet(feet: Vec3, half_width: f32, height: f32) -> Self {
+        Self {
+            min: feet - Vec3::new(half_width, 0.0, half_width),
+            max: feet + Vec3::new(half_width, height, half_width),
+        }
+    }
+
+    /// The box moved by an offset.
+    pub fn translate(self, offset: Vec3) -> Self {
+        Self {
+            min: self.min + offset,
+            max: self.max + offset,
+        }
+    }
+
+    /// Smallest box that contains both boxes.
+    pub fn union(self, other: Self) -> Self {
+        Self {
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+        }
+    }
+
+    /// Every voxel the box overlaps, with edges exactly on a voxel boundary
+    /// not overlapping the voxel on the other side.
+    pub fn voxels(&self) -> impl Iterator<Item = IVec3> {
+        let min = self.min.floor().as_ivec3();
+        let max = (self.max.ceil().as_ivec3() - 1).max(min);
+        (min.y..=max.y).flat_map(move |y| {
+            (min.x..=max.x).flat_map(move |x| (min.z..=max.z).map(move |z| ivec3(x, y, z)))
+        })
+    }
+
+    /// Whether the box overlaps a voxel that blocks movement.
+    pub fn collides(&self, solid: impl Fn(IVec3) -> bool) -> bool {
+        self.voxels().any(solid)
+    }
+}
+
+/// Result of sweeping a box along its velocity.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Sweep {
+    /// Fraction of the velocity the box moved before it hit a voxel, 1.0 if it didn't.
+    pub time: f32,
+
+    /// Direction of the face of the voxel that was hit, pointing back at the box.
+    pub normal: Option<Axis>,
+
+    /// The voxel that was hit.
+    pub voxel: Option<IVec3>,
+
+    /// Offset the box can move by, stopping `SKIN` away from the voxel that was hit.
+    pub offset: Vec3,
+}
+
+impl Sweep {
+    pub fn is_hit(&self) -> bool {
+        self.normal.is_some()
+    }
+
+    /// The box at the resolved position.
+    pub fn resolve(&self, aabb: Aabb) -> Aabb {
+        aabb.translate(self.offset)
+    }
+}
+
+/// Sweep a box along its velocity, stopping at the first voxel that blocks movement.
+///
+/// Voxels the box already overlaps are ignored, so a box that ends up inside
+/// a voxel can still move out of it. Every voxel in the bounds of the sweep is
+/// tested, so the velocity should be about the movement of a single step.
+pub fn sweep_aabb(solid: impl Fn(IVec3) -> bool, aabb: Aabb, velocity: Vec3) -> Sweep {
+    let mut sweep = Sweep {
+        time: 1.0,
+        normal: None,
+        voxel: None,
+        offset: velocity,
+    };
+
+    if velocity == Vec3::ZERO {
+        return sweep;
+    }
+
+    for voxel in aabb.union(aabb.translate(velocity)).voxels() {
+        if !solid(voxel) {
+            continue;
+        }
+
+        let target = Aabb::new(voxel.as_vec3(), voxel.as_vec3() + 1.0);
+        if let Some((time, normal)) = sweep_against(aabb, target, velocity)
+            && time < sweep.time
+        {
+            sweep.time = time;
+            sweep.normal = Some(normal);
+            sweep.voxel = Some(voxel);
+        }
+    }
+
+    if let Some(normal) = sweep.normal {
+        // back off along the normal, without moving backwards.
+        let axis = normal.abs() as usize / 2;
+        let mut offset = velocity * sweep.time;
+        let backoff = SKIN * velocity[axis].signum();
+        offset[axis] = if offset[axis].abs() > SKIN {
+            offset[axis] - backoff
+        } else {
+            0.0
+        };
+        sweep.offset = offset;
+    }
+
+    sweep
+}
+
+/// Sweep a box along each axis in turn, Y first, sliding along the voxels it hits
+/// instead of stopping. Returns the offset the box moved by, and which of the
+/// X, Y and Z axes were blocked.
+pub fn slide_aabb(solid: impl Fn(IVec3) -> bool, aabb: Aabb, velocity: Vec3) -> (Vec3, [bool; 3]) {
+    let mut offset = Vec3::ZERO;
+    let mut blocked = [false; 3];
+    for axis in [1, 0, 2] {
+        let mut step = Vec3::ZERO;
+        step[axis] = velocity[axis];
+        let sweep = sweep_aabb(&solid, aabb.translate(offset), step);
+        offset += sweep.offset;
+        blocked[axis] = sweep.is_hit();
+    }
+    (offset, blocked)
+}
+
+/// Time the moving box first touches the target, and the face of the target it touches.
+/// None if they don't touch during the movement, or already overlap.
+fn sweep_against(aabb: Aabb, target: Aabb, velocity: Vec3) -> Option<(f32, Axis)> {
+    let mut entry = f32::NEG_INFINITY;
+    let mut exit = f32::INFINITY;
+    let mut normal = None;
+
+    for axis in 0..3 {
+        let v = velocity[axis];
+        if v == 0.0 {
+            // the box can only touch the target if they already overlap on this axis.
+            if aabb.max[axis] <= target.min[axis] || aabb.min[axis] >= target.max[axis] {
+                return None;
+            }
+            continue;
+        }
+
+        let (near, far) = if v > 0.0 {
+            (
+                target.min[axis] - aabb.max[axis],
+                target.max[axis] - aabb.min[axis],
+            )
+        } else {
+            (
+                target.max[axis] - aabb.min[axis],
+                target.min[axis] - aabb.max[axis],
+            )
+        };
+
+        let (t0, t1) = (near / v, far / v);
+        if t0 > entry {
+            entry = t0;
+            normal = Some(AXES[axis][(v > 0.0) as usize]);
+        }
+        exit = exit.min(t1);
+    }
+
+    // already overlapping, or missed, or hit after the movement.
+    let normal = normal?;
+    (entry >= 0.0 && entry < exit && entry <= 1.0).then_some((entry, normal))
+}
+
+/// Normal of the face hit when moving along each axis, negative then positive.
+const AXES: [[Axis; 2]; 3] = [
+    [Axis::PosX, Axis::NegX],
+    [Axis::PosY, Axis::NegY],
+    [Axis::PosZ, Axis::NegZ],
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A floor at y=0 and a wall at x=2.
+    fn solid(pos: IVec3) -> bool {
+        pos.y < 0 || pos.x == 2
+    }
+
+    #[test]
+    fn sweep_stops_at_wall() {
+        let aabb = Aabb::from_feet(Vec3::new(0.5, 0.0, 0.5), 0.3, 1.8);
+        let sweep = sweep_aabb(solid, aabb, Vec3::new(3.0, 0.0, 0.0));
+        assert!(sweep.is_hit());
+        assert_eq!(sweep.normal, Some(Axis::NegX));
+        assert_eq!(sweep.voxel.map(|v| v.x), Some(2));
+        assert!((sweep.time - 1.2 / 3.0).abs() < 1e-5);
+
+        let resolved = sweep.resolve(aabb);
+        assert!(resolved.max.x < 2.0 && resolved.max.x > 2.0 - 2.0 * SKIN);
+        assert!(!resolved.collides(solid));
+    }
+
+    #[test]
+    fn sweep_does_not_tunnel() {
+        // a thin wall is hit even if the box would move past it in one step.
+        let aabb = Aabb::new(Vec3::new(0.0, 0.5, 0.0), Vec3::new(0.5, 1.0, 0.5));
+        let sweep = sweep_aabb(solid, aabb, Vec3::new(20.0, 0.0, 0.0));
+        assert_eq!(sweep.voxel, Some(ivec3(2, 0, 0)));
+    }
+
+    #[test]
+    fn sweep_misses_and_grazes() {
+        let aabb = Aabb::from_feet(Vec3::new(0.5, SKIN, 0.5), 0.3, 1.8);
+        // sliding along the floor doesn't hit it.
+        let sweep = sweep_aabb(solid, aabb, Vec3::new(0.0, 0.0, 5.0));
+        assert!(!sweep.is_hit());
+        assert_eq!(sweep.offset, Vec3::new(0.0, 0.0, 5.0));
+
+        // falling onto it does.
+        let sweep = sweep_aabb(solid, aabb.translate(Vec3::Y), Vec3::new(0.0, -3.0, 0.0));
+        assert_eq!(sweep.normal, Some(Axis::PosY));
+        assert!(!sweep.resolve(aabb.translate(Vec3::Y)).collides(solid));
+    }
+
+    #[test]
+    fn slide_along_wall() {
+        let aabb = Aabb::from_feet(Vec3::new(1.0, SKIN, 0.5), 0.3, 1.8);
+        let (offset, blocked) = slide_aabb(solid, aabb, Vec3::new(1.0, -1.0, 2.0));
+        assert_eq!(blocked, [true, true, false]);
+        assert!((offset.z - 2.0).abs() < 1e-5);
+        assert!(offset.y.abs() < 1e-5);
+        assert!(!aabb.translate(offset).collides(solid));
+    }
+}
@@ -2,7 +2,11 @@
 
 pub mod activity;
 pub mod axis;
+pub mod collide;
+pub mod line;
+pub mod morton;
 pub mod noise;
 pub mod rng;
 pub mod space;
+pub mod spline;
 pub mod util;
@@ -0,0 +1,136 @@
+//! Lines through the voxel grid, with integer math only.
+//!
+//! Unlike the raycaster in `world`, lines go between the centers of two voxels,
+//! which is enough for checks like whether one voxel can be seen from another.
+
+use bevy::math::IVec3;
+
+/// Iterates the voxels on the line between the centers of two voxels,
+/// including both ends.
+///
+/// Each voxel shares a face with the one before it, so a line can't slip
+/// through the gap between two voxels that only touch at an edge. When the
+/// line passes exactly through an edge or corner, X is stepped first, then Y, then Z.
+#[derive(Clone, Debug)]
+pub struct VoxelLine {
+    /// The next voxel to return.
+    pos: IVec3,
+
+    /// Direction to step on each axis.
+    step: IVec3,
+
+    /// Number of voxels to cross on each axis, in total.
+    delta: [i64; 3],
+
+    /// Number of voxels crossed on each axis so far.
+    crossed: [i64; 3],
+
+    /// Number of voxels left to return.
+    remaining: usize,
+}
+
+impl VoxelLine {
+    pub fn new(from: IVec3, to: IVec3) -> Self {
+        let diff = to - from;
+        let delta = diff.abs().to_array().map(i64::from);
+        Self {
+            pos: from,
+            step: diff.signum(),
+            delta,
+            crossed: [0; 3],
+            remaining: (delta[0] + delta[1] + delta[2]) as usize + 1,
+        }
+    }
+}
+
+impl Iterator for VoxelLine {
+    type Item = IVec3;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        let result = self.pos;
+
+        // The line crosses its n-th boundary on an axis at t = (2n + 1) / 2d,
+        // so the axis with the smallest t is found by cross-multiplying.
+        let mut best: Option<usize> = None;
+        for axis in 0..3 {
+            if self.crossed[axis] >= self.delta[axis] {
+                continue;
+            }
+            let closer = best.is_none_or(|b| {
+                (2 * self.crossed[axis] + 1) * self.delta[b]
+                    < (2 * self.crossed[b] + 1) * self.delta[axis]
+            });
+            if closer {
+                best = Some(axis);
+            }
+        }
+
+        if let Some(axis) = best {
+            self.crossed[axis] += 1;
+            self.pos[axis] += self.step[axis];
+        }
+
+        Some(result)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl ExactSizeIterator for VoxelLine {}
+
+/// Whether the line between the centers of two voxels is clear,
+/// ignoring the voxels at both ends.
+pub fn line_of_sight(from: IVec3, to: IVec3, opaque: impl Fn(IVec3) -> bool) -> bool {
+    let line = VoxelLine::new(from, to);
+    let len = line.len();
+    line.skip(1)
+        .take(len.saturating_sub(2))
+        .all(|pos| !opaque(pos))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::math::ivec3;
+
+    #[test]
+    fn lines_are_face_connected() {
+        let ends = [
+            (ivec3(0, 0, 0), ivec3(5, 0, 0)),
+            (ivec3(0, 0, 0), ivec3(3, 3, 3)),
+            (ivec3(-4, 7, 2), ivec3(9, -3, -5)),
+            (ivec3(1, 1, 1), ivec3(1, 1, 1)),
+        ];
+        for (from, to) in ends {
+            let line: Vec<_> = VoxelLine::new(from, to).collect();
+            let diff = (to - from).abs();
+            assert_eq!(line.len() as i32, diff.x + diff.y + diff.z + 1);
+            assert_eq!(line.first(), Some(&from));
+            assert_eq!(line.last(), Some(&to));
+            for w in line.windows(2) {
+                let step = (w[1] - w[0]).abs();
+                assert_eq!(step.x + step.y + step.z, 1);
+            }
+        }
+
+        // a shallow line stays close to the real line.
+        let line: Vec<_> = VoxelLine::new(ivec3(0, 0, 0), ivec3(8, 2, 0)).collect();
+        assert_eq!(line[1], ivec3(1, 0, 0));
+        assert_eq!(line[3], ivec3(2, 1, 0));
+    }
+
+    #[test]
+    fn sight_ignores_ends() {
+        let wall = |pos: IVec3| pos.x == 3;
+        assert!(!line_of_sight(ivec3(0, 0, 0), ivec3(6, 2, 1), wall));
+        assert!(line_of_sight(ivec3(0, 2, 0), ivec3(3, 2, 0), wall));
+        assert!(line_of_sight(ivec3(3, 1, 1), ivec3(-2, 1, 1), wall));
+        assert!(line_of_sight(ivec3(0, 4, 0), ivec3(2, 0, 0), wall));
+    }
+}
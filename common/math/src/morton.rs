@@ -0,0 +1,279 @@
+//! Morton codes, also known as Z-order.
+//!
+//! A morton code interleaves the bits of the coordinates of a point, so points
+//! that are close together usually have codes that are close together. Sorting
+//! by morton code keeps nearby points nearby in memory or on disk, and a box
+//! of points can be found with a few ranges of codes instead of a full scan.
+//!
+//! The first coordinate is stored in the lowest bit, so the codes of 2D points
+//! are `x0 y0 x1 y1 ...` from least significant bit up.
+
+use std::ops::Range;
+
+use bevy::math::{IVec2, IVec3, ivec2, ivec3};
+
+use crate::space::{area::IArea, volume::IVolume};
+
+/// Number of bits of each coordinate in a 3D morton code.
+pub const BITS3: u32 = 21;
+
+/// Mask of the bits of each coordinate in a 3D morton code.
+const MASK3: u32 = (1 << BITS3) - 1;
+
+/// Added to signed 3D coordinates so the smallest one is 0.
+const BIAS3: i32 = 1 << (BITS3 - 1);
+
+/// Interleave the bits of two coordinates.
+pub const fn encode2([x, y]: [u32; 2]) -> u64 {
+    spread2(x) | (spread2(y) << 1)
+}
+
+/// Recover the coordinates of a 2D morton code.
+pub const fn decode2(code: u64) -> [u32; 2] {
+    [compact2(code), compact2(code >> 1)]
+}
+
+/// Interleave the lower 21 bits of three coordinates.
+/// The upper bits are ignored.
+pub const fn encode3([x, y, z]: [u32; 3]) -> u64 {
+    spread3(x) | (spread3(y) << 1) | (spread3(z) << 2)
+}
+
+/// Recover the coordinates of a 3D morton code.
+pub const fn decode3(code: u64) -> [u32; 3] {
+    [compact3(code), compact3(code >> 1), compact3(code >> 2)]
+}
+
+/// Morton code of a signed point. Coordinates are offset so the codes are ordered
+/// the same way as the coordinates, and points on either side of 0 stay close.
+pub const fn encode_ivec2(v: IVec2) -> u64 {
+    encode2([flip_sign(v.x), flip_sign(v.y)])
+}
+
+pub const fn decode_ivec2(code: u64) -> IVec2 {
+    let [x, y] = decode2(code);
+    ivec2(flip_sign(x as i32) as i32, flip_sign(y as i32) as i32)
+}
+
+/// Morton code of a signed point, which must be within 2^20 of the origin.
+/// Coordinates are offset so the codes are ordered the same way as the coordinates.
+pub const fn encode_ivec3(v: IVec3) -> u64 {
+    encode3([bias3(v.x), bias3(v.y), bias3(v.z)])
+}
+
+pub const fn decode_ivec3(code: u64) -> IVec3 {
+    let [x, y, z] = decode3(code);
+    ivec3(x as i32 - BIAS3, y as i32 - BIAS3, z as i32 - BIAS3)
+}
+
+/// Split a box of points into the ranges of morton codes it covers, in order.
+/// Min is inclusive and max is exclusive.
+pub fn ranges2(min: [u32; 2], max: [u32; 2]) -> Vec<Range<u64>> {
+    split2(min.map(u64::from), max.map(u64::from))
+}
+
+/// Split a box of points into the ranges of morton codes it covers, in order.
+/// Min is inclusive and max is exclusive, and both must fit in 21 bits.
+pub fn ranges3(min: [u32; 3], max: [u32; 3]) -> Vec<Range<u64>> {
+    let mut out = Vec::new();
+    split(
+        min.map(u64::from),
+        max.map(|v| u64::from(v).min(1 << BITS3)),
+        [0; 3],
+        BITS3,
+        &|[x, y, z]| encode3([x as u32, y as u32, z as u32]),
+        &mut out,
+    );
+    out
+}
+
+/// The ranges of morton codes of the points in an area, as encoded by `encode_ivec2`.
+pub fn area_ranges(area: &IArea) -> Vec<Range<u64>> {
+    if area.min.cmpge(area.max).any() {
+        return Vec::new();
+    }
+
+    let min = [flip_sign(area.min.x), flip_sign(area.min.y)];
+    let max = [flip_sign(area.max.x - 1), flip_sign(area.max.y - 1)];
+    split2(min.map(u64::from), max.map(|v| u64::from(v) + 1))
+}
+
+/// The ranges of morton codes of the points in a volume, as encoded by `encode_ivec3`.
+pub fn volume_ranges(volume: &IVolume) -> Vec<Range<u64>> {
+    if volume.min.cmpge(volume.max).any() {
+        return Vec::new();
+    }
+
+    let min = volume.min.to_array().map(bias3);
+    let max = volume.max.to_array().map(|v| bias3(v - 1) + 1);
+    ranges3(min, max)
+}
+
+fn split2(min: [u64; 2], max: [u64; 2]) -> Vec<Range<u64>> {
+    let mut out = Vec::new();
+    split(
+        min,
+        max,
+        [0; 2],
+        u32::BITS,
+        &|[x, y]| encode2([x as u32, y as u32]),
+        &mut out,
+    );
+    out
+}
+
+/// Recursively split the cell of size 2^level at "cell" into its children in
+/// morton order, adding the ranges of cells entirely inside the box.
+fn split<const N: usize>(
+    min: [u64; N],
+    max: [u64; N],
+    cell: [u64; N],
+    level: u32,
+    encode: &impl Fn([u64; N]) -> u64,
+    out: &mut Vec<Range<u64>>,
+) {
+    let size = 1u64 << level;
+    if (0..N).any(|d| cell[d] >= max[d] || cell[d] + size <= min[d]) {
+        return;
+    }
+
+    // the range of the cell of every 2D point wouldn't fit in a u64.
+    if level * (N as u32) < u64::BITS
+        && (0..N).all(|d| cell[d] >= min[d] && cell[d] + size <= max[d])
+    {
+        // the codes of an aligned cell are contiguous.
+        let start = encode(cell);
+        let end = start + (1u64 << (level * N as u32));
+        match out.last_mut() {
+            Some(last) if last.end == start => last.end = end,
+            _ => out.push(start..end),
+        }
+        return;
+    }
+
+    let half = size >> 1;
+    for child in 0..1 << N {
+        let mut pos = cell;
+        for d in 0..N {
+            if child >> d & 1 == 1 {
+                pos[d] += half;
+            }
+        }
+        split(min, max, pos, level - 1, encode, out);
+    }
+}
+
+/// Map a signed coordinate to unsigned, keeping the order.
+const fn flip_sign(v: i32) -> u32 {
+    (v as u32) ^ (1 << 31)
+}
+
+const fn bias3(v: i32) -> u32 {
+    (v + BIAS3) as u32 & MASK3
+}
+
+/// Spread the bits of a u32 so there is a 0 between each of them.
+const fn spread2(v: u32) -> u64 {
+    let mut v = v as u64;
+    v = (v | (v << 16)) & 0x0000_FFFF_0000_FFFF;
+    v = (v | (v << 8)) & 0x00FF_00FF_00FF_00FF;
+    v = (v | (v << 4)) & 0x0F0F_0F0F_0F0F_0F0F;
+    v = (v | (v << 2)) & 0x3333_3333_3333_3333;
+    v = (v | (v << 1)) & 0x5555_5555_5555_5555;
+    v
+}
+
+/// Inverse of `spread2`, ignoring the odd bits.
+const fn compact2(v: u64) -> u32 {
+    let mut v = v & 0x5555_5555_5555_5555;
+    v = (v | (v >> 1)) & 0x3333_3333_3333_3333;
+    v = (v | (v >> 2)) & 0x0F0F_0F0F_0F0F_0F0F;
+    v = (v | (v >> 4)) & 0x00FF_00FF_00FF_00FF;
+    v = (v | (v >> 8)) & 0x0000_FFFF_0000_FFFF;
+    v = (v | (v >> 16)) & 0x0000_0000_FFFF_FFFF;
+    v as u32
+}
+
+/// Spread the lower 21 bits of a u32 so there are two 0s between each of them.
+const fn spread3(v: u32) -> u64 {
+    let mut v = (v & MASK3) as u64;
+    v = (v | (v << 32)) & 0x001F_0000_0000_FFFF;
+    v = (v | (v << 16)) & 0x001F_0000_FF00_00FF;
+    v = (v | (v << 8)) & 0x100F_00F0_0F00_F00F;
+    v = (v | (v << 4)) & 0x10C3_0C30_C30C_30C3;
+    v = (v | (v << 2)) & 0x1249_2492_4924_9249;
+    v
+}
+
+/// Inverse of `spread3`, ignoring the other bits.
+const fn compact3(v: u64) -> u32 {
+    let mut v = v & 0x1249_2492_4924_9249;
+    v = (v | (v >> 2)) & 0x10C3_0C30_C30C_30C3;
+    v = (v | (v >> 4)) & 0x100F_00F0_0F00_F00F;
+    v = (v | (v >> 8)) & 0x001F_0000_FF00_00FF;
+    v = (v | (v >> 16)) & 0x001F_0000_0000_FFFF;
+    v = (v | (v >> 32)) & MASK3 as u64;
+    v as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_decode() {
+        assert_eq!(encode2([1, 0]), 0b01);
+        assert_eq!(encode2([0, 1]), 0b10);
+        assert_eq!(encode2([3, 5]), 0b100111);
+        assert_eq!(encode3([1, 1, 1]), 0b111);
+        assert_eq!(encode3([0, 0, 2]), 0b100000);
+
+        for v in [0, 1, 7, 12345, 0xDEAD_BEEF, u32::MAX] {
+            assert_eq!(decode2(encode2([v, !v])), [v, !v]);
+            let v = v & MASK3;
+            assert_eq!(
+                decode3(encode3([v, v / 3, MASK3 - v])),
+                [v, v / 3, MASK3 - v]
+            );
+        }
+
+        for v in [
+            ivec3(0, 0, 0),
+            ivec3(-1, 5, -1000),
+            ivec3(-BIAS3, BIAS3 - 1, 3),
+        ] {
+            let xz = ivec2(v.x, v.z);
+            assert_eq!(decode_ivec2(encode_ivec2(xz)), xz);
+            assert_eq!(decode_ivec3(encode_ivec3(v)), v);
+        }
+
+        // signed codes keep the order of the coordinates.
+        assert!(encode_ivec2(ivec2(-1, -1)) < encode_ivec2(ivec2(0, 0)));
+        assert!(encode_ivec3(ivec3(-1, -1, -1)) < encode_ivec3(ivec3(0, 0, 0)));
+    }
+
+    #[test]
+    fn ranges_cover_box() {
+        let area = IArea::new(ivec2(-3, -2), ivec2(4, 6));
+        let ranges = area_ranges(&area);
+        let mut codes: Vec<u64> = ranges.iter().cloned().flatten().collect();
+        let mut expected: Vec<u64> = area.iter(1).map(encode_ivec2).collect();
+        expected.sort_unstable();
+        assert_eq!(codes, expected);
+        // ranges are sorted and merged.
+        assert!(ranges.windows(2).all(|w| w[0].end < w[1].start));
+
+        let volume = IVolume::new(ivec3(-2, 0, -5), ivec3(3, 4, 1));
+        codes = volume_ranges(&volume).into_iter().flatten().collect();
+        expected = (volume.min.y..volume.max.y)
+            .flat_map(|y| (volume.min.x..volume.max.x).map(move |x| (x, y)))
+            .flat_map(|(x, y)| (volume.min.z..volume.max.z).map(move |z| ivec3(x, y, z)))
+            .map(encode_ivec3)
+            .collect();
+        expected.sort_unstable();
+        assert_eq!(codes, expected);
+
+        assert_eq!(ranges2([0, 0], [4, 4]), vec![0..16]);
+        assert!(area_ranges(&IArea::new(ivec2(1, 1), ivec2(1, 5))).is_empty());
+    }
+}
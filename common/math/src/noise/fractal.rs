@@ -1,10 +1,10 @@
-use std::{ops::Mul, sync::Arc};
+use std::{ops::Mul, simd::f32x4, sync::Arc};
 
-use bevy::math::{Vec2, Vec3};
+use bevy::math::{Vec2, Vec3, vec2, vec3};
 
-use crate::rng::Permutation;
+use crate::{noise::opensimplex::WARP_OFFSETS, rng::Permutation};
 
-/// Parameters for Fractal Brownian Motion with Perlin or Simplex noise.
+/// Parameters for Fractal Brownian Motion with Perlin, Simplex or OpenSimplex-style noise.
 #[derive(Clone)]
 pub struct Fractal {
     /// The number of iterations.
@@ -51,6 +51,50 @@ impl Fractal {
         self.compute(&self.perm, pt, super::simplex::simplex3)
     }
 
+    /// Fractal Brownian Motion with 2D OpenSimplex-style noise.
+    pub fn opensimplex2(&self, pt: Vec2) -> f32 {
+        self.compute(&self.perm, pt, super::opensimplex::noise2)
+    }
+
+    /// Fractal Brownian Motion with 3D OpenSimplex-style noise.
+    pub fn opensimplex3(&self, pt: Vec3) -> f32 {
+        self.compute(&self.perm, pt, super::opensimplex::noise3)
+    }
+
+    /// Fractal Brownian Motion with 2D OpenSimplex-style noise of four points at once.
+    pub fn opensimplex2x4(&self, x: f32x4, y: f32x4) -> f32x4 {
+        let mut frequency = self.frequency;
+        let mut amplitude = self.amplitude;
+        let mut output = f32x4::splat(0.0);
+
+        for _ in 0..self.octaves {
+            let f = f32x4::splat(frequency);
+            output +=
+                f32x4::splat(amplitude) * super::opensimplex::noise2x4(&self.perm, x * f, y * f);
+            frequency *= self.lacunarity;
+            amplitude *= self.gain;
+        }
+
+        output
+    }
+
+    /// Move a point by 2D OpenSimplex-style fBM, so noise sampled at the result is warped.
+    /// Each axis is moved by the fBM of the point at a different offset, scaled by "amount".
+    pub fn warp2(&self, pt: Vec2, amount: f32) -> Vec2 {
+        let [_, ox, oy] = WARP_OFFSETS.map(|offset| vec2(offset.x, offset.y));
+        pt + vec2(self.opensimplex2(pt + ox), self.opensimplex2(pt + oy)) * amount
+    }
+
+    /// Move a point by 3D OpenSimplex-style fBM, so noise sampled at the result is warped.
+    /// Each axis is moved by the fBM of the point at a different offset, scaled by "amount".
+    pub fn warp3(&self, pt: Vec3, amount: f32) -> Vec3 {
+        let [ox, oy, oz] = WARP_OFFSETS;
+        let dx = self.opensimplex3(pt + ox);
+        let dy = self.opensimplex3(pt + oy);
+        let dz = self.opensimplex3(pt + oz);
+        pt + vec3(dx, dy, dz) * amount
+    }
+
     #[inline]
     fn compute<T, F>(&self, perm: &Permutation, point: T, noise_fn: F) -> f32
     where
@@ -1,5 +1,6 @@
 pub mod fractal;
 pub mod noisemap;
+pub mod opensimplex;
 pub mod perlin;
 pub mod simplex;
 pub mod worley;
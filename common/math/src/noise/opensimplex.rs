@@ -0,0 +1,304 @@
+//! OpenSimplex-style gradient noise.
+//!
+//! Noise is sampled on the simplex lattice like `simplex`, but gradients are picked from
+//! a larger set of unit vectors that aren't aligned to the axes, and each corner only
+//! contributes within a radius that keeps the output continuous. This removes the
+//! diagonal and grid-aligned artifacts simplex noise has, which show up in terrain.
+//!
+//! The output is in the range [-1,1], and is 0.0 at the corners of the lattice.
+
+use std::simd::{StdFloat, prelude::*};
+
+use bevy::math::{Vec2, Vec3, vec2, vec3};
+
+use crate::rng::Permutation;
+
+// factors for skewing to simplex space.
+const SQRT_3: f32 = 1.73205080757;
+const F2: f32 = 0.5 * (SQRT_3 - 1.0);
+const G2: f32 = (3.0 - SQRT_3) / 6.0;
+const F3: f32 = 1.0 / 3.0;
+const G3: f32 = 1.0 / 6.0;
+
+/// Squared radius each corner contributes within.
+const R2: f32 = 0.5;
+
+/// Scales the sum of the contributions to the range [-1,1].
+const NORM2: f32 = 99.2;
+const NORM3: f32 = 106.6;
+
+/// Offsets of the samples used to warp each axis of a point,
+/// so the axes are moved by unrelated noise.
+pub(crate) const WARP_OFFSETS: [Vec3; 3] = [
+    vec3(0.0, 0.0, 0.0),
+    vec3(5.2, 1.3, 7.9),
+    vec3(9.7, 4.1, 2.8),
+];
+
+/// 24 unit vectors, 15 degrees apart, rotated so none lie on an axis.
+const GRAD2: [[f32; 2]; 24] = {
+    let mut grads = [[0.0; 2]; 24];
+    // cos and sin of 7.5 + 15n degrees for n in 0..6, the first quadrant.
+    const QUADRANT: [[f32; 2]; 6] = [
+        [0.99144486, 0.13052619],
+        [0.92387953, 0.38268343],
+        [0.79335334, 0.60876143],
+        [0.60876143, 0.79335334],
+        [0.38268343, 0.92387953],
+        [0.13052619, 0.99144486],
+    ];
+    let mut i = 0;
+    while i < 6 {
+        let [c, s] = QUADRANT[i];
+        grads[i] = [c, s];
+        grads[i + 6] = [-s, c];
+        grads[i + 12] = [-c, -s];
+        grads[i + 18] = [s, -c];
+        i += 1;
+    }
+    grads
+};
+
+/// Unit vectors towards the 12 edges and 8 corners of a cube,
+/// with the edges repeated so the directions are picked about evenly.
+const GRAD3: [[f32; 3]; 32] = {
+    const E: f32 = 0.70710678;
+    const C: f32 = 0.57735027;
+    const EDGES: [[f32; 3]; 12] = [
+        [E, E, 0.0],
+        [-E, E, 0.0],
+        [E, -E, 0.0],
+        [-E, -E, 0.0],
+        [E, 0.0, E],
+        [-E, 0.0, E],
+        [E, 0.0, -E],
+        [-E, 0.0, -E],
+        [0.0, E, E],
+        [0.0, -E, E],
+        [0.0, E, -E],
+        [0.0, -E, -E],
+    ];
+    const CORNERS: [[f32; 3]; 8] = [
+        [C, C, C],
+        [-C, C, C],
+        [C, -C, C],
+        [-C, -C, C],
+        [C, C, -C],
+        [-C, C, -C],
+        [C, -C, -C],
+        [-C, -C, -C],
+    ];
+
+    let mut grads = [[0.0; 3]; 32];
+    let mut i = 0;
+    while i < 32 {
+        grads[i] = if i < 12 {
+            EDGES[i]
+        } else if i < 20 {
+            CORNERS[i - 12]
+        } else {
+            EDGES[i - 20]
+        };
+        i += 1;
+    }
+    grads
+};
+
+/// 2-Dimensional OpenSimplex-style Noise.
+#[inline]
+pub fn noise2(perm: &Permutation, point: Vec2) -> f32 {
+    // skew input space to determine which simplex cell we're in
+    let s = (point.x + point.y) * F2;
+    let i = (point.x + s).floor();
+    let j = (point.y + s).floor();
+    // Unskew back to (x, y) space
+    let t = (i + j) * G2;
+    let x0 = point.x - (i - t);
+    let y0 = point.y - (j - t);
+    // Offsets for second corner of the simplex cell.
+    let (i1, j1) = if x0 > y0 { (1.0, 0.0) } else { (0.0, 1.0) };
+    let corners = [
+        (0.0, 0.0, x0, y0),
+        (i1, j1, x0 - i1 + G2, y0 - j1 + G2),
+        (1.0, 1.0, x0 - 1.0 + 2.0 * G2, y0 - 1.0 + 2.0 * G2),
+    ];
+
+    let mut value = 0.0;
+    for (di, dj, x, y) in corners {
+        let att = R2 - x * x - y * y;
+        if att > 0.0 {
+            let [gx, gy] = GRAD2[hash2(perm, (i + di) as i32, (j + dj) as i32) % 24];
+            let att = att * att;
+            value += att * att * (gx * x + gy * y);
+        }
+    }
+
+    value * NORM2
+}
+
+/// 2-Dimensional OpenSimplex-style Noise of four points at once.
+/// Each lane is the same as `noise2` of the point in that lane.
+#[inline]
+pub fn noise2x4(perm: &Permutation, x: f32x4, y: f32x4) -> f32x4 {
+    let zero = f32x4::splat(0.0);
+    let one = f32x4::splat(1.0);
+    let g2 = f32x4::splat(G2);
+
+    // skew input space to determine which simplex cell we're in
+    let s = (x + y) * f32x4::splat(F2);
+    let i = (x + s).floor();
+    let j = (y + s).floor();
+    // Unskew back to (x, y) space
+    let t = (i + j) * g2;
+    let x0 = x - (i - t);
+    let y0 = y - (j - t);
+    // Offsets for second corner of the simplex cell.
+    let lower = x0.simd_gt(y0);
+    let i1 = lower.select(one, zero);
+    let j1 = lower.select(zero, one);
+    let corners = [
+        (zero, zero, x0, y0),
+        (i1, j1, x0 - i1 + g2, y0 - j1 + g2),
+        (
+            one,
+            one,
+            x0 - one + f32x4::splat(2.0 * G2),
+            y0 - one + f32x4::splat(2.0 * G2),
+        ),
+    ];
+
+    let mut value = zero;
+    for (di, dj, cx, cy) in corners {
+        let ci: i32x4 = (i + di).cast();
+        let cj: i32x4 = (j + dj).cast();
+        let mut gx = [0.0; 4];
+        let mut gy = [0.0; 4];
+        for lane in 0..4 {
+            [gx[lane], gy[lane]] = GRAD2[hash2(perm, ci[lane], cj[lane]) % 24];
+        }
+
+        let att = (f32x4::splat(R2) - cx * cx - cy * cy).simd_max(zero);
+        let att = att * att;
+        value += att * att * (f32x4::from_array(gx) * cx + f32x4::from_array(gy) * cy);
+    }
+
+    value * f32x4::splat(NORM2)
+}
+
+/// 3-Dimensional OpenSimplex-style Noise.
+#[inline]
+pub fn noise3(perm: &Permutation, point: Vec3) -> f32 {
+    // skew input space to determine which simplex cell we're in
+    let s = (point.x + point.y + point.z) * F3;
+    let i = (point.x + s).floor();
+    let j = (point.y + s).floor();
+    let k = (point.z + s).floor();
+    let t = (i + j + k) * G3;
+    let x0 = point.x - (i - t);
+    let y0 = point.y - (j - t);
+    let z0 = point.z - (k - t);
+    // Offsets for second and third corner of the simplex cell.
+    let (o1, o2) = if x0 >= y0 {
+        if y0 >= z0 {
+            ([1.0, 0.0, 0.0], [1.0, 1.0, 0.0])
+        } else if x0 >= z0 {
+            ([1.0, 0.0, 0.0], [1.0, 0.0, 1.0])
+        } else {
+            ([0.0, 0.0, 1.0], [1.0, 0.0, 1.0])
+        }
+    } else if y0 < z0 {
+        ([0.0, 0.0, 1.0], [0.0, 1.0, 1.0])
+    } else if x0 < z0 {
+        ([0.0, 1.0, 0.0], [0.0, 1.0, 1.0])
+    } else {
+        ([0.0, 1.0, 0.0], [1.0, 1.0, 0.0])
+    };
+
+    let mut value = 0.0;
+    for (n, [di, dj, dk]) in [[0.0; 3], o1, o2, [1.0; 3]].into_iter().enumerate() {
+        let g = n as f32 * G3;
+        let x = x0 - di + g;
+        let y = y0 - dj + g;
+        let z = z0 - dk + g;
+        let att = R2 - x * x - y * y - z * z;
+        if att > 0.0 {
+            let hash = hash3(perm, (i + di) as i32, (j + dj) as i32, (k + dk) as i32);
+            let [gx, gy, gz] = GRAD3[hash % 32];
+            let att = att * att;
+            value += att * att * (gx * x + gy * y + gz * z);
+        }
+    }
+
+    value * NORM3
+}
+
+/// Move a point by 2D noise, so noise sampled at the result is warped.
+/// Each axis is moved by up to "amount", with noise sampled at a different offset.
+#[inline]
+pub fn warp2(perm: &Permutation, point: Vec2, amount: f32) -> Vec2 {
+    let [_, ox, oy] = WARP_OFFSETS.map(|offset| vec2(offset.x, offset.y));
+    point + vec2(noise2(perm, point + ox), noise2(perm, point + oy)) * amount
+}
+
+/// Move a point by 3D noise, so noise sampled at the result is warped.
+/// Each axis is moved by up to "amount", with noise sampled at a different offset.
+#[inline]
+pub fn warp3(perm: &Permutation, point: Vec3, amount: f32) -> Vec3 {
+    let [ox, oy, oz] = WARP_OFFSETS;
+    point
+        + vec3(
+            noise3(perm, point + ox),
+            noise3(perm, point + oy),
+            noise3(perm, point + oz),
+        ) * amount
+}
+
+/// Hash a corner of the 2D lattice.
+#[inline]
+fn hash2(perm: &Permutation, i: i32, j: i32) -> usize {
+    perm[perm[(i & 255) as usize] as usize + (j & 255) as usize] as usize
+}
+
+/// Hash a corner of the 3D lattice.
+#[inline]
+fn hash3(perm: &Permutation, i: i32, j: i32, k: i32) -> usize {
+    let ij = perm[perm[(i & 255) as usize] as usize + (j & 255) as usize];
+    perm[ij as usize + (k & 255) as usize] as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn batch_matches_single() {
+        let perm = Permutation::DEFAULT;
+        for i in 0..64 {
+            let x = f32x4::from_array(std::array::from_fn(|lane| i as f32 * 0.37 + lane as f32));
+            let y = f32x4::from_array(std::array::from_fn(|lane| lane as f32 * 1.91 - i as f32));
+            let batch = noise2x4(&perm, x, y);
+            for lane in 0..4 {
+                let single = noise2(&perm, vec2(x[lane], y[lane]));
+                assert!((batch[lane] - single).abs() < 1e-6);
+            }
+        }
+    }
+
+    #[test]
+    fn output_in_range() {
+        let perm = Permutation::DEFAULT;
+        for i in 0..4096 {
+            let pt = vec3(
+                (i % 16) as f32 * 0.13,
+                (i / 16 % 16) as f32 * 0.29,
+                (i / 256) as f32 * 0.11,
+            );
+            assert!(noise2(&perm, pt.truncate()).abs() <= 1.0);
+            assert!(noise3(&perm, pt).abs() <= 1.0);
+        }
+
+        // corners of the lattice
+        assert_eq!(noise2(&perm, Vec2::ZERO), 0.0);
+        assert_eq!(noise3(&perm, Vec3::ZERO), 0.0);
+    }
+}
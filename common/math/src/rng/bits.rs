@@ -1,4 +1,4 @@
-use rand::{RngCore, SeedableRng};
+use rand::{RngCore, SeedableRng, rand_core::impls};
 
 /// An RNG for generating a few bits at a time.
 ///
@@ -65,6 +65,24 @@ impl BitRng {
     }
 }
 
+/// Lets a `BitRng` drive anything that takes an `Rng`, like ranges or weighted picks.
+/// A seeded `BitRng` makes those repeatable, which tests rely on.
+impl RngCore for BitRng {
+    #[inline]
+    fn next_u32(&mut self) -> u32 {
+        self.take(32) as u32
+    }
+
+    #[inline]
+    fn next_u64(&mut self) -> u64 {
+        self.take(32) | (self.take(32) << 32)
+    }
+
+    fn fill_bytes(&mut self, dst: &mut [u8]) {
+        impls::fill_bytes_via_next(self, dst)
+    }
+}
+
 impl SeedableRng for BitRng {
     type Seed = [u8; core::mem::size_of::<u64>()];
 
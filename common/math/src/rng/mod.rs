@@ -5,3 +5,6 @@ pub use bits::BitRng;
 
 pub mod permutation;
 pub use permutation::Permutation;
+
+pub mod world;
+pub use world::WorldRng;
@@ -49,6 +49,54 @@ impl IArea {
         self.intersection(other).is_some()
     }
 
+    /// Whether the area contains no points.
+    pub const fn is_empty(&self) -> bool {
+        self.min.x >= self.max.x || self.min.y >= self.max.y
+    }
+
+    /// Smallest area that contains both areas.
+    pub fn bounds(&self, other: &Self) -> Self {
+        Self {
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+        }
+    }
+
+    /// Split the points of self that are not in other into up to 4 disjoint areas.
+    ///
+    /// Useful for finding the cells that entered or left a moving area,
+    /// like the chunks around a player, without visiting the whole area.
+    pub fn difference(&self, other: &Self) -> impl Iterator<Item = Self> + use<> {
+        let mut parts = [None; 4];
+        match self.intersection(other) {
+            None if self.is_empty() => {}
+            None => parts[0] = Some(*self),
+            Some(i) => {
+                // full-width strips below and above, then the sides between them.
+                parts = [
+                    Self::new(self.min, ivec2(self.max.x, i.min.y)),
+                    Self::new(ivec2(self.min.x, i.max.y), self.max),
+                    Self::new(ivec2(self.min.x, i.min.y), ivec2(i.min.x, i.max.y)),
+                    Self::new(ivec2(i.max.x, i.min.y), ivec2(self.max.x, i.max.y)),
+                ]
+                .map(|part| (!part.is_empty()).then_some(part));
+            }
+        }
+        parts.into_iter().flatten()
+    }
+
+    /// Split the points in either area into up to 5 disjoint areas.
+    pub fn union(&self, other: &Self) -> impl Iterator<Item = Self> + use<> {
+        let first = (!self.is_empty()).then_some(*self);
+        first.into_iter().chain(other.difference(self))
+    }
+
+    /// Iterate the points on the edge of the area, each only once.
+    pub fn border(&self) -> impl Iterator<Item = IVec2> + use<> {
+        let inner = Self::new(self.min + 1, self.max - 1);
+        self.difference(&inner).flat_map(|part| part.iter(1))
+    }
+
     /// Returns the X and Y extent (size).
     pub const fn extents(&self) -> IVec2 {
         ivec2(self.max.x - self.min.x, self.max.y - self.min.y)
@@ -676,4 +724,24 @@ mod tests {
             "Multiple iterations should produce identical results"
         );
     }
+
+    #[test]
+    fn area_difference_and_border() {
+        let a = IArea::new(ivec2(0, 0), ivec2(6, 5));
+        let b = IArea::new(ivec2(2, -3), ivec2(4, 2));
+
+        let mut diff: Vec<IVec2> = a.difference(&b).flatten().collect();
+        diff.sort_by_key(|p| (p.x, p.y));
+        let mut expected: Vec<IVec2> = a.into_iter().filter(|p| !b.contains(*p)).collect();
+        expected.sort_by_key(|p| (p.x, p.y));
+        assert_eq!(diff, expected);
+
+        let union: Vec<IVec2> = a.union(&b).flatten().collect();
+        assert_eq!(union.len(), 30 + 6);
+
+        let border: Vec<IVec2> = a.border().collect();
+        assert_eq!(border.len(), 2 * 6 + 2 * 3);
+        assert!(border.iter().all(|p| p.x % 5 == 0 || p.y % 4 == 0));
+        assert_eq!(IArea::new(ivec2(0, 0), ivec2(1, 1)).border().count(), 1);
+    }
 }
@@ -0,0 +1,149 @@
+//! View frustums, for finding what a camera can see.
+//!
+//! Planes are extracted from the view-projection matrix of the camera, so any
+//! projection works, including the infinite reverse-z perspective Bevy uses.
+
+use bevy::math::{Mat4, Vec3, Vec4};
+
+use super::volume::IVolume;
+use crate::collide::Aabb;
+
+/// A plane, with the points in front of it on the side the normal faces.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Plane {
+    /// Unit normal, or zero if the plane contains every point.
+    pub normal: Vec3,
+
+    /// Signed distance of the origin from the plane.
+    pub d: f32,
+}
+
+impl Plane {
+    /// Create a plane from the coefficients of its equation `ax + by + cz + d = 0`.
+    /// The normal is normalized, unless it is zero.
+    pub fn from_vec4(v: Vec4) -> Self {
+        let normal = v.truncate();
+        let len = normal.length();
+        if len > f32::EPSILON {
+            Self {
+                normal: normal / len,
+                d: v.w / len,
+            }
+        } else {
+            Self { normal, d: v.w }
+        }
+    }
+
+    /// A plane through a point, facing the direction of the normal.
+    pub fn from_point_normal(point: Vec3, normal: Vec3) -> Self {
+        let normal = normal.normalize();
+        Self {
+            normal,
+            d: -normal.dot(point),
+        }
+    }
+
+    /// Signed distance of the point from the plane, positive in front of it.
+    pub fn distance(&self, point: Vec3) -> f32 {
+        self.normal.dot(point) + self.d
+    }
+}
+
+/// The volume a camera can see, bounded by six planes facing inwards.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Frustum {
+    /// Left, right, bottom, top, near and far planes.
+    pub planes: [Plane; 6],
+}
+
+impl Frustum {
+    /// Extract the planes from a view-projection matrix, which maps world space
+    /// to clip space with depth in [0,1]. With an infinite projection, the far
+    /// plane has no normal and contains every point.
+    pub fn from_view_projection(view_proj: Mat4) -> Self {
+        let [r0, r1, r2, r3] = [0, 1, 2, 3].map(|i| view_proj.row(i));
+        Self {
+            planes: [r3 + r0, r3 - r0, r3 + r1, r3 - r1, r2, r3 - r2].map(Plane::from_vec4),
+        }
+    }
+
+    /// Whether the point is inside the frustum.
+    pub fn contains_point(&self, point: Vec3) -> bool {
+        self.planes.iter().all(|plane| plane.distance(point) >= 0.0)
+    }
+
+    /// Whether any part of the sphere may be inside the frustum.
+    pub fn intersects_sphere(&self, center: Vec3, radius: f32) -> bool {
+        self.planes
+            .iter()
+            .all(|plane| plane.distance(center) >= -radius)
+    }
+
+    /// Whether any part of the box may be inside the frustum.
+    ///
+    /// This is conservative, a box near the corner of the frustum may be
+    /// reported as intersecting it when it is actually outside.
+    pub fn intersects_aabb(&self, aabb: &Aabb) -> bool {
+        self.planes.iter().all(|plane| {
+            // the corner furthest in front of the plane.
+            let corner = Vec3::select(plane.normal.cmpge(Vec3::ZERO), aabb.max, aabb.min);
+            plane.distance(corner) >= 0.0
+        })
+    }
+
+    /// Whether any part of the voxels in the volume may be inside the frustum.
+    pub fn intersects_volume(&self, volume: &IVolume) -> bool {
+        self.intersects_aabb(&Aabb::new(volume.min.as_vec3(), volume.max.as_vec3()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::math::ivec3;
+
+    /// A camera at the origin looking down -Z, with a 90 degree field of view.
+    fn frustum(infinite: bool) -> Frustum {
+        let fov = std::f32::consts::FRAC_PI_2;
+        let proj = if infinite {
+            Mat4::perspective_infinite_reverse_rh(fov, 1.0, 0.1)
+        } else {
+            Mat4::perspective_rh(fov, 1.0, 0.1, 100.0)
+        };
+        Frustum::from_view_projection(proj * Mat4::IDENTITY)
+    }
+
+    #[test]
+    fn points_and_spheres() {
+        for f in [frustum(false), frustum(true)] {
+            assert!(f.contains_point(Vec3::new(0.0, 0.0, -10.0)));
+            assert!(f.contains_point(Vec3::new(9.0, -9.0, -10.0)));
+            assert!(!f.contains_point(Vec3::new(11.0, 0.0, -10.0)));
+            assert!(!f.contains_point(Vec3::new(0.0, 0.0, 10.0)));
+            assert!(!f.contains_point(Vec3::new(0.0, 0.0, -0.05)));
+
+            assert!(f.intersects_sphere(Vec3::new(0.0, 0.0, 1.0), 1.5));
+            assert!(!f.intersects_sphere(Vec3::new(0.0, 0.0, 5.0), 1.0));
+        }
+
+        assert!(!frustum(false).contains_point(Vec3::new(0.0, 0.0, -200.0)));
+        assert!(frustum(true).contains_point(Vec3::new(0.0, 0.0, -1e6)));
+    }
+
+    #[test]
+    fn boxes() {
+        let f = frustum(false);
+        let inside = IVolume::new(ivec3(-1, -1, -20), ivec3(1, 1, -18));
+        let behind = IVolume::new(ivec3(-1, -1, 2), ivec3(1, 1, 4));
+        let beside = IVolume::new(ivec3(30, -1, -20), ivec3(32, 1, -18));
+        // only a corner pokes into the frustum.
+        let corner = IVolume::new(ivec3(19, 19, -20), ivec3(25, 25, -18));
+        assert!(f.intersects_volume(&inside));
+        assert!(!f.intersects_volume(&behind));
+        assert!(!f.intersects_volume(&beside));
+        assert!(f.intersects_volume(&corner));
+
+        let plane = Plane::from_point_normal(Vec3::Y, Vec3::Y * 2.0);
+        assert_eq!(plane.distance(Vec3::new(3.0, 4.0, 0.0)), 3.0);
+    }
+}
@@ -1,6 +1,9 @@
 use bevy::math::{IVec2, IVec3, ivec2, ivec3};
 
 pub mod area;
+pub mod frustum;
+pub mod scatter;
+pub mod spiral;
 pub mod volume;
 
 #[cfg(test)]
@@ -0,0 +1,86 @@
+//! Blue-noise scattering of points, for placing things like trees, ores and
+//! structures so they are spread out evenly instead of clumping together.
+
+use bevy::math::{IVec2, ivec2};
+
+use super::area::IArea;
+use crate::rng::WorldRng;
+
+/// Scatter points in an area so no two points are closer than "spacing".
+///
+/// The world is split into cells of size "spacing", and each cell has one candidate
+/// point with a random priority. A candidate is kept unless a neighbouring candidate
+/// with a higher priority is too close to it. Candidates only depend on the RNG and
+/// the cell, so the points are the same no matter how the world is split into areas,
+/// and points in neighbouring chunks keep their spacing too.
+///
+/// Points are returned in the order of their cells, Y-major.
+pub fn poisson_disk(rng: WorldRng, area: &IArea, spacing: i32) -> Vec<IVec2> {
+    let spacing = spacing.max(1);
+    let min_dist2 = spacing * spacing;
+    let mut points = Vec::new();
+
+    for cell in area.cells(spacing) {
+        let cell = cell.min / spacing;
+        let (point, priority) = candidate(rng, cell, spacing);
+        if !area.contains(point) {
+            continue;
+        }
+
+        // a point in another cell closer than "spacing" must be in a neighbouring cell.
+        let kept = IArea::new(cell - 1, cell + 2).into_iter().all(|other| {
+            if other == cell {
+                return true;
+            }
+            let (other_point, other_priority) = candidate(rng, other, spacing);
+            other_point.distance_squared(point) >= min_dist2
+                || (other_priority, other.to_array()) < (priority, cell.to_array())
+        });
+
+        if kept {
+            points.push(point);
+        }
+    }
+
+    points
+}
+
+/// The candidate point of a cell, and its priority.
+fn candidate(rng: WorldRng, cell: IVec2, spacing: i32) -> (IVec2, u32) {
+    let mut rng = rng.column(cell).rng();
+    let mut offset = || ((rng.take(32) * spacing as u64) >> 32) as i32;
+    let jitter = ivec2(offset(), offset());
+    (cell * spacing + jitter, rng.take(32) as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn points_are_spaced_and_stable() {
+        let rng = WorldRng::new(42).feature("trees");
+        let area = IArea::new(ivec2(-64, -32), ivec2(64, 96));
+        let points = poisson_disk(rng, &area, 7);
+
+        assert!(points.iter().all(|p| area.contains(*p)));
+        for (i, a) in points.iter().enumerate() {
+            for b in &points[i + 1..] {
+                assert!(a.distance_squared(*b) >= 49);
+            }
+        }
+
+        // at least a third of the cells keep their point.
+        assert!(points.len() > 128 * 128 / 49 / 3);
+
+        // splitting the area doesn't change the points.
+        let left = IArea::new(ivec2(-64, -32), ivec2(3, 96));
+        let right = IArea::new(ivec2(3, -32), ivec2(64, 96));
+        let mut split = poisson_disk(rng, &left, 7);
+        split.extend(poisson_disk(rng, &right, 7));
+        let mut points = points;
+        points.sort_by_key(|p| (p.x, p.y));
+        split.sort_by_key(|p| (p.x, p.y));
+        assert_eq!(points, split);
+    }
+}
@@ -0,0 +1,110 @@
+use bevy::math::IVec2;
+
+/// Iterator over the points in a square around the origin, from the origin outwards.
+///
+/// Points are yielded ring by ring, where each ring is the points at the same
+/// Chebyshev distance from the origin. Each ring is walked counter-clockwise, starting
+/// next to its bottom-right corner. The radius is inclusive.
+#[derive(Clone)]
+pub struct Spiral {
+    radius: u32,
+    ring: u32,
+    index: u32,
+}
+
+impl Spiral {
+    pub fn new(radius: u32) -> Self {
+        Self {
+            radius,
+            ring: 0,
+            index: 0,
+        }
+    }
+
+    /// Number of points in a spiral with this radius.
+    pub const fn len_for(radius: u32) -> usize {
+        let side = 2 * radius as usize + 1;
+        side * side
+    }
+}
+
+impl Iterator for Spiral {
+    type Item = IVec2;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.ring > self.radius {
+            return None;
+        }
+
+        if self.ring == 0 {
+            self.ring = 1;
+            return Some(IVec2::ZERO);
+        }
+
+        let r = self.ring as i32;
+        let side = (self.index / (2 * self.ring)) as i32;
+        let k = (self.index % (2 * self.ring)) as i32;
+        let point = match side {
+            0 => IVec2::new(r, -r + 1 + k),
+            1 => IVec2::new(r - 1 - k, r),
+            2 => IVec2::new(-r, r - 1 - k),
+            _ => IVec2::new(-r + 1 + k, -r),
+        };
+
+        self.index += 1;
+        if self.index == 8 * self.ring {
+            self.index = 0;
+            self.ring += 1;
+        }
+
+        Some(point)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::{IVec2, ivec2};
+
+    use super::Spiral;
+
+    #[test]
+    fn spiral_order() {
+        let points = Spiral::new(1).collect::<Vec<_>>();
+        assert_eq!(
+            points,
+            vec![
+                ivec2(0, 0),
+                ivec2(1, 0),
+                ivec2(1, 1),
+                ivec2(0, 1),
+                ivec2(-1, 1),
+                ivec2(-1, 0),
+                ivec2(-1, -1),
+                ivec2(0, -1),
+                ivec2(1, -1),
+            ]
+        );
+    }
+
+    #[test]
+    fn spiral_covers_square() {
+        for radius in 0..6 {
+            let points = Spiral::new(radius).collect::<Vec<_>>();
+            assert_eq!(points.len(), Spiral::len_for(radius));
+
+            // every point is unique and within the radius.
+            let mut sorted = points.clone();
+            sorted.sort_by_key(|p| (p.x, p.y));
+            sorted.dedup();
+            assert_eq!(sorted.len(), points.len());
+            assert!(points.iter().all(|p| p.abs().max_element() <= radius as i32));
+
+            // distance never decreases.
+            let dists = points
+                .iter()
+                .map(|p| p.chebyshev_distance(IVec2::ZERO))
+                .collect::<Vec<_>>();
+            assert!(dists.windows(2).all(|w| w[0] <= w[1]));
+        }
+    }
+}
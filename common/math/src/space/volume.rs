@@ -1,3 +1,5 @@
+use crate::util::{IsPow2, Pow2};
+
 use super::area::*;
 use bevy::prelude::*;
 
@@ -16,6 +18,143 @@ impl IVolume {
         Self { min, max }
     }
 
+    /// Whether the volume contains no points.
+    pub const fn is_empty(&self) -> bool {
+        self.min.x >= self.max.x || self.min.y >= self.max.y || self.min.z >= self.max.z
+    }
+
+    /// Returns the X, Y and Z extent (size).
+    pub const fn extents(&self) -> IVec3 {
+        ivec3(
+            self.max.x - self.min.x,
+            self.max.y - self.min.y,
+            self.max.z - self.min.z,
+        )
+    }
+
+    pub const fn contains(&self, pt: IVec3) -> bool {
+        pt.x >= self.min.x
+            && pt.x < self.max.x
+            && pt.y >= self.min.y
+            && pt.y < self.max.y
+            && pt.z >= self.min.z
+            && pt.z < self.max.z
+    }
+
+    /// Get the portion of self and is also in other.
+    pub fn intersection(&self, other: &Self) -> Option<Self> {
+        let result = Self::new(self.min.max(other.min), self.max.min(other.max));
+        (!result.is_empty()).then_some(result)
+    }
+
+    pub fn intersects(&self, other: &Self) -> bool {
+        self.intersection(other).is_some()
+    }
+
+    /// Smallest volume that contains both volumes.
+    pub fn bounds(&self, other: &Self) -> Self {
+        Self::new(self.min.min(other.min), self.max.max(other.max))
+    }
+
+    /// Split the points of self that are not in other into up to 6 disjoint volumes.
+    pub fn difference(&self, other: &Self) -> impl Iterator<Item = Self> + use<> {
+        let mut parts = [None; 6];
+        match self.intersection(other) {
+            None if self.is_empty() => {}
+            None => parts[0] = Some(*self),
+            Some(i) => {
+                let (min, max) = (self.min, self.max);
+                // full slabs below and above, then full rows on Z, then the sides on X.
+                parts = [
+                    Self::new(min, max.with_y(i.min.y)),
+                    Self::new(min.with_y(i.max.y), max),
+                    Self::new(min.with_y(i.min.y), ivec3(max.x, i.max.y, i.min.z)),
+                    Self::new(ivec3(min.x, i.min.y, i.max.z), max.with_y(i.max.y)),
+                    Self::new(
+                        ivec3(min.x, i.min.y, i.min.z),
+                        ivec3(i.min.x, i.max.y, i.max.z),
+                    ),
+                    Self::new(
+                        ivec3(i.max.x, i.min.y, i.min.z),
+                        ivec3(max.x, i.max.y, i.max.z),
+                    ),
+                ]
+                .map(|part| (!part.is_empty()).then_some(part));
+            }
+        }
+        parts.into_iter().flatten()
+    }
+
+    /// Split the points in either volume into up to 7 disjoint volumes.
+    pub fn union(&self, other: &Self) -> impl Iterator<Item = Self> + use<> {
+        let first = (!self.is_empty()).then_some(*self);
+        first.into_iter().chain(other.difference(self))
+    }
+
+    /// Iterate the points on the surface of the volume, each only once.
+    pub fn border(&self) -> impl Iterator<Item = IVec3> + use<> {
+        let inner = Self::new(self.min + 1, self.max - 1);
+        self.difference(&inner).flat_map(|part| part.iter(1))
+    }
+
+    /// Get all cubic cells of size `SIZE` that this volume overlaps.
+    ///
+    /// The origins of the cells are relative to the world origin,
+    /// so they are always multiples of `SIZE`.
+    pub const fn cells_pow2<const SIZE: i32>(&self) -> ICells3d
+    where
+        Pow2<SIZE>: IsPow2,
+    {
+        let rounded = self.rounded_up_to_pow2::<SIZE>();
+        ICells3d {
+            volume: rounded,
+            next: if rounded.is_empty() {
+                rounded.max
+            } else {
+                rounded.min
+            },
+            stride: SIZE,
+        }
+    }
+
+    /// Round the min down to the previous multiple of `SIZE`,
+    /// and the max up to the next multiple.
+    pub const fn round_up_to_pow2<const SIZE: i32>(&mut self)
+    where
+        Pow2<SIZE>: IsPow2,
+    {
+        let f = SIZE - 1;
+        self.min.x &= !f;
+        self.min.y &= !f;
+        self.min.z &= !f;
+        self.max.x = (self.max.x + f) & !f;
+        self.max.y = (self.max.y + f) & !f;
+        self.max.z = (self.max.z + f) & !f;
+    }
+
+    pub const fn rounded_up_to_pow2<const SIZE: i32>(mut self) -> Self
+    where
+        Pow2<SIZE>: IsPow2,
+    {
+        self.round_up_to_pow2::<SIZE>();
+        self
+    }
+
+    /// Get an iterator over the points in this volume.
+    ///
+    /// Panics if stride is less than 1.
+    pub fn iter(&self, stride: i32) -> IVolumeIter {
+        assert!(
+            stride > 0,
+            "Expected stride of IVolumeIter to be greater than 0, found: '{stride}'"
+        );
+        IVolumeIter {
+            volume: *self,
+            curr: if self.is_empty() { self.max } else { self.min },
+            stride,
+        }
+    }
+
     pub const fn xz(&self) -> IArea {
         IArea {
             min: ivec2(self.min.x, self.min.z),
@@ -62,3 +201,99 @@ impl Iterator for IVolumeIter {
         Some(result)
     }
 }
+
+impl IntoIterator for IVolume {
+    type IntoIter = IVolumeIter;
+    type Item = IVec3;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter(1)
+    }
+}
+
+/// The cells of a volume, Y-major. Unlike IVolumeIter, this is EXCLUSIVE on the max.
+#[derive(Clone)]
+pub struct ICells3d {
+    volume: IVolume,
+    next: IVec3,
+    stride: i32,
+}
+
+impl Iterator for ICells3d {
+    type Item = IVolume;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next.y >= self.volume.max.y {
+            return None;
+        }
+
+        let result = self.next;
+
+        self.next.x += self.stride;
+        if self.next.x >= self.volume.max.x {
+            self.next.x = self.volume.min.x;
+            self.next.z += self.stride;
+            if self.next.z >= self.volume.max.z {
+                self.next.z = self.volume.min.z;
+                self.next.y += self.stride;
+            }
+        }
+
+        Some(IVolume {
+            min: result,
+            max: result + self.stride,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(points: impl Iterator<Item = IVec3>) -> Vec<IVec3> {
+        let mut points: Vec<IVec3> = points.collect();
+        points.sort_by_key(|p| (p.x, p.y, p.z));
+        points
+    }
+
+    #[test]
+    fn volume_difference_and_union() {
+        let a = IVolume::new(ivec3(0, 0, 0), ivec3(4, 5, 6));
+        let b = IVolume::new(ivec3(2, -1, 1), ivec3(7, 3, 3));
+
+        let diff = sorted(a.difference(&b).flatten());
+        let expected = sorted(a.iter(1).filter(|p| !b.contains(*p)));
+        assert_eq!(diff, expected);
+
+        // parts of the union are disjoint, so no point is repeated.
+        let union = sorted(a.union(&b).flatten());
+        let expected = sorted(
+            a.bounds(&b)
+                .iter(1)
+                .filter(|p| a.contains(*p) || b.contains(*p)),
+        );
+        assert_eq!(union, expected);
+
+        assert_eq!(a.difference(&a).count(), 0);
+        let far = IVolume::new(ivec3(9, 9, 9), ivec3(10, 10, 10));
+        assert_eq!(a.difference(&far).collect::<Vec<_>>(), vec![a]);
+    }
+
+    #[test]
+    fn volume_border_and_cells() {
+        let a = IVolume::new(ivec3(-2, -2, -2), ivec3(2, 2, 2));
+        let inner = IVolume::new(ivec3(-1, -1, -1), ivec3(1, 1, 1));
+        let border: Vec<_> = a.border().collect();
+        assert_eq!(border.len(), 64 - 8);
+        assert!(border.iter().all(|p| a.contains(*p) && !inner.contains(*p)));
+
+        let volume = IVolume::new(ivec3(-1, 0, 5), ivec3(1, 3, 9));
+        let cells: Vec<_> = volume.cells_pow2::<4>().collect();
+        assert_eq!(cells.len(), 4);
+        assert_eq!(cells[0], IVolume::new(ivec3(-4, 0, 4), ivec3(0, 4, 8)));
+
+        let empty = IVolume::new(ivec3(0, 0, 0), ivec3(0, 4, 4));
+        assert!(empty.cells_pow2::<4>().next().is_none());
+        assert!(empty.iter(1).next().is_none());
+    }
+}
@@ -0,0 +1,218 @@
+//! Curves through control points, used to shape terrain by mapping
+//! noise values to things like height or squash factors.
+//!
+//! Splines are read from worldgen files as a list of `[x, y]` points sorted by x:
+//!
+//! ```json
+//! [[-1.0, -24.0], [-0.2, 0.0], [0.4, 12.0], [1.0, 64.0]]
+//! ```
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// A monotone cubic spline.
+///
+/// The curve passes through every control point and never overshoots them, so it only
+/// rises or falls between two points if they do, unlike a plain cubic spline.
+/// Before the first and after the last point, the curve is flat.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "Vec<[f32; 2]>", into = "Vec<[f32; 2]>")]
+pub struct Spline {
+    /// Control points, sorted by x.
+    points: Vec<[f32; 2]>,
+
+    /// Slope of the curve at each control point.
+    tangents: Vec<f32>,
+}
+
+impl Spline {
+    /// Create a spline through the points, which must be sorted by x without duplicates.
+    pub fn new(points: Vec<[f32; 2]>) -> Result<Self, SplineError> {
+        if points.is_empty() {
+            return Err(SplineError::Empty);
+        }
+
+        if let Some(index) = points
+            .iter()
+            .position(|[x, y]| !x.is_finite() || !y.is_finite())
+        {
+            return Err(SplineError::NotFinite { index });
+        }
+
+        if let Some(index) = points.windows(2).position(|w| w[0][0] >= w[1][0]) {
+            return Err(SplineError::Unsorted { index: index + 1 });
+        }
+
+        let tangents = tangents(&points);
+        Ok(Self { points, tangents })
+    }
+
+    /// A straight line through two points.
+    pub fn linear(from: [f32; 2], to: [f32; 2]) -> Result<Self, SplineError> {
+        Self::new(vec![from, to])
+    }
+
+    /// Control points of the spline, sorted by x.
+    pub fn points(&self) -> &[[f32; 2]] {
+        &self.points
+    }
+
+    /// The value of the curve at x.
+    pub fn sample(&self, x: f32) -> f32 {
+        let last = self.points.len() - 1;
+        if x <= self.points[0][0] {
+            return self.points[0][1];
+        }
+        if x >= self.points[last][0] {
+            return self.points[last][1];
+        }
+
+        // index of the first point right of x, never 0 or past the end.
+        let i = self.points.partition_point(|[px, _]| *px <= x);
+        let [x0, y0] = self.points[i - 1];
+        let [x1, y1] = self.points[i];
+        let (m0, m1) = (self.tangents[i - 1], self.tangents[i]);
+
+        // cubic hermite basis.
+        let h = x1 - x0;
+        let t = (x - x0) / h;
+        let t2 = t * t;
+        let t3 = t2 * t;
+        let h00 = 2.0 * t3 - 3.0 * t2 + 1.0;
+        let h10 = t3 - 2.0 * t2 + t;
+        let h01 = -2.0 * t3 + 3.0 * t2;
+        let h11 = t3 - t2;
+        h00 * y0 + h10 * h * m0 + h01 * y1 + h11 * h * m1
+    }
+}
+
+impl TryFrom<Vec<[f32; 2]>> for Spline {
+    type Error = SplineError;
+
+    fn try_from(points: Vec<[f32; 2]>) -> Result<Self, Self::Error> {
+        Self::new(points)
+    }
+}
+
+impl From<Spline> for Vec<[f32; 2]> {
+    fn from(spline: Spline) -> Self {
+        spline.points
+    }
+}
+
+/// Slopes at each point that keep the curve monotone between points (Fritsch-Carlson).
+fn tangents(points: &[[f32; 2]]) -> Vec<f32> {
+    let n = points.len();
+    if n == 1 {
+        return vec![0.0];
+    }
+
+    // slopes of the lines between each pair of points.
+    let secants: Vec<f32> = points
+        .windows(2)
+        .map(|w| (w[1][1] - w[0][1]) / (w[1][0] - w[0][0]))
+        .collect();
+
+    let mut tangents = vec![0.0; n];
+    tangents[0] = secants[0];
+    tangents[n - 1] = secants[n - 2];
+    for i in 1..n - 1 {
+        let (d0, d1) = (secants[i - 1], secants[i]);
+        // flat at local extrema, so the curve doesn't overshoot them.
+        if d0 * d1 > 0.0 {
+            tangents[i] = (d0 + d1) / 2.0;
+        }
+    }
+
+    for (i, d) in secants.into_iter().enumerate() {
+        if d == 0.0 {
+            tangents[i] = 0.0;
+            tangents[i + 1] = 0.0;
+            continue;
+        }
+
+        // limit the tangents so the segment can't overshoot.
+        let a = tangents[i] / d;
+        let b = tangents[i + 1] / d;
+        let len = a * a + b * b;
+        if len > 9.0 {
+            let scale = 3.0 / len.sqrt();
+            tangents[i] = scale * a * d;
+            tangents[i + 1] = scale * b * d;
+        }
+    }
+
+    tangents
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SplineError {
+    Empty,
+    NotFinite { index: usize },
+    Unsorted { index: usize },
+}
+
+impl fmt::Display for SplineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "spline has no control points"),
+            Self::NotFinite { index } => {
+                write!(f, "control point {index} of spline is not a finite number")
+            }
+            Self::Unsorted { index } => write!(
+                f,
+                "control point {index} of spline is not right of the one before it"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SplineError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn passes_through_points_and_clamps() {
+        let spline = Spline::new(vec![[-1.0, -24.0], [0.0, 0.0], [0.5, 4.0], [1.0, 64.0]]).unwrap();
+        for [x, y] in spline.points().to_vec() {
+            assert!((spline.sample(x) - y).abs() < 1e-4);
+        }
+        assert_eq!(spline.sample(-5.0), -24.0);
+        assert_eq!(spline.sample(5.0), 64.0);
+
+        let line = Spline::linear([-1.0, -32.0], [1.0, 32.0]).unwrap();
+        assert!((line.sample(0.25) - 8.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn is_monotone_between_points() {
+        // a plain cubic spline would dip below 0 and rise above 10 around the plateau.
+        let spline = Spline::new(vec![[0.0, 0.0], [1.0, 0.0], [1.5, 10.0], [3.0, 10.0]]).unwrap();
+        let mut prev = spline.sample(0.0);
+        for i in 1..=300 {
+            let y = spline.sample(i as f32 * 0.01);
+            assert!(y >= prev - 1e-4 && (-1e-4..=10.0 + 1e-4).contains(&y));
+            prev = y;
+        }
+    }
+
+    #[test]
+    fn invalid_points() {
+        assert_eq!(Spline::new(vec![]), Err(SplineError::Empty));
+        assert_eq!(
+            Spline::new(vec![[0.0, 0.0], [0.0, 1.0]]),
+            Err(SplineError::Unsorted { index: 1 })
+        );
+        assert_eq!(
+            Spline::new(vec![[0.0, f32::NAN]]),
+            Err(SplineError::NotFinite { index: 0 })
+        );
+
+        let single = Spline::new(vec![[0.0, 3.0]]).unwrap();
+        assert_eq!(single.sample(-1.0), 3.0);
+        assert_eq!(single.sample(1.0), 3.0);
+    }
+}
@@ -124,16 +124,40 @@ impl UdpDecoder {
     }
 
     pub fn decode(&mut self) -> Option<(ChannelId, Bytes)> {
-        if self.buffer.len() >= 4 {
-            let len = self.buffer.get_u16_le() as usize;
-            let channel = ChannelId(self.buffer.get_u16_le() as usize);
-            if self.buffer.len() >= len {
-                return Some((channel, self.buffer.split_to(len).freeze()));
-            }
+        decode_udp_packet(&mut self.buffer)
+    }
+}
+
+/// Decode the next packet of a datagram, after its session was read.
+fn decode_udp_packet(buffer: &mut BytesMut) -> Option<(ChannelId, Bytes)> {
+    if buffer.len() >= 4 {
+        let len = buffer.get_u16_le() as usize;
+        let channel = ChannelId(buffer.get_u16_le() as usize);
+        if buffer.len() >= len {
+            return Some((channel, buffer.split_to(len).freeze()));
         }
+    }
 
-        None
+    None
+}
+
+/// Decode the session and packets of a UDP datagram, the same as `UdpDecoder` does.
+/// Returns None if the datagram is too short to have a session.
+///
+/// Entry point for fuzzing, since datagrams are read from untrusted peers.
+pub fn decode_udp_datagram(bytes: &[u8]) -> Option<(Session, Vec<(ChannelId, Bytes)>)> {
+    if bytes.len() < 8 {
+        return None;
     }
+
+    let mut buffer = BytesMut::from(bytes);
+    let session = Session(buffer.get_u64_le());
+    let mut packets = Vec::new();
+    while let Some(packet) = decode_udp_packet(&mut buffer) {
+        packets.push(packet);
+    }
+
+    Some((session, packets))
 }
 
 pub struct TcpEncoder {
@@ -284,6 +308,22 @@ impl TcpDecoder {
     }
 }
 
+/// Decode the packets in bytes read from a TCP stream, the same as `TcpDecoder` does.
+/// A packet cut off at the end is ignored, like one that hasn't fully arrived yet.
+///
+/// Entry point for fuzzing, since streams are read from untrusted peers.
+pub fn decode_tcp_frame(bytes: &[u8]) -> Result<Vec<(ChannelId, Bytes)>, ExitCode> {
+    let mut decoder = TcpDecoder::new();
+    decoder.buffer.extend_from_slice(bytes);
+
+    let mut packets = Vec::new();
+    while let Some((payload, channel)) = decoder.decode()? {
+        packets.push((channel, payload));
+    }
+
+    Ok(packets)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -323,6 +363,39 @@ mod tests {
         assert_eq!(p2.get_u64_ne(), TEST_DATA_2);
     }
 
+    #[test]
+    fn decode_frames() {
+        let mut encoder = TcpEncoder::new();
+        encoder.encode(ChannelId(3), &(TEST_DATA_1).to_ne_bytes());
+        encoder.encode(ChannelId(4), &(TEST_DATA_2).to_ne_bytes());
+        let mut flush = Vec::new();
+        encoder.flush(&mut flush).unwrap();
+
+        // the second packet is cut off, so only the first is decoded.
+        let packets = decode_tcp_frame(&flush[..flush.len() - 1]).unwrap();
+        assert_eq!(packets.len(), 1);
+        assert_eq!(packets[0].0, ChannelId(3));
+        assert_eq!(packets[0].1.as_ref(), &(TEST_DATA_1).to_ne_bytes());
+
+        // a length past the limit is a protocol violation.
+        let mut frame = Vec::new();
+        frame.put_u32_le(u32::MAX);
+        frame.put_u16_le(0);
+        assert!(decode_tcp_frame(&frame).is_err());
+
+        let mut datagram = Vec::new();
+        datagram.put_u64_le(77);
+        datagram.put_u16_le(8);
+        datagram.put_u16_le(5);
+        datagram.put_u64_ne(TEST_DATA_2);
+        datagram.put_u16_le(200);
+        let (session, packets) = decode_udp_datagram(&datagram).unwrap();
+        assert_eq!(session, Session(77));
+        assert_eq!(packets.len(), 1);
+        assert_eq!(packets[0].0, ChannelId(5));
+        assert!(decode_udp_datagram(&datagram[..7]).is_none());
+    }
+
     // #[test]
     // fn tcp_collect() {
     //     let mut encoder = TcpEncoder::new();
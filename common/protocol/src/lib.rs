@@ -8,6 +8,7 @@ pub mod netsync;
 pub mod packet;
 pub mod session;
 pub mod streams;
+pub mod trace;
 pub mod types;
 
 pub use crate::{
@@ -1,7 +1,7 @@
 use crate::{exit::ExitCode, session::Session};
 use bytemuck::{Pod, Zeroable};
 use bytes::Bytes;
-use serde::Serialize;
+use serde::{Serialize, de::DeserializeOwned};
 
 #[derive(Clone)]
 pub struct Packet {
@@ -15,6 +15,20 @@ impl Packet {
         bytemuck::try_pod_read_unaligned::<T>(&self.payload).ok()
     }
 
+    /// Read a payload made of several `T`s, as written by `bytemuck::cast_slice`.
+    /// Trailing bytes that don't make up a whole `T` are ignored.
+    pub fn cast_slice<T: Pod>(&self) -> Vec<T> {
+        self.payload
+            .chunks_exact(size_of::<T>())
+            .map(bytemuck::pod_read_unaligned)
+            .collect()
+    }
+
+    /// Decode a payload encoded with `Packet::from_json`.
+    pub fn json<T: DeserializeOwned>(&self) -> Option<T> {
+        serde_json::from_slice(&self.payload).ok()
+    }
+
     pub fn from_json<T: Serialize>(channel: ChannelId, session: Session, item: &T) -> Self {
         let buf = serde_json::to_vec(item).unwrap();
         Self {
@@ -55,6 +69,16 @@ impl ChannelId {
     pub fn is_special(self) -> bool {
         self.0 >= 32768
     }
+
+    /// Name of a special channel, which isn't in the channel registry.
+    pub fn special_name(self) -> Option<&'static str> {
+        match self {
+            Self::EXIT_CODE => Some("exit-code"),
+            Self::AUTH_REQ => Some("auth"),
+            Self::SYNC_DATA => Some("sync-data"),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
@@ -0,0 +1,176 @@
+//! Tracing of the packets sent and received, for debugging channel routing.
+//!
+//! Tracing is off by default and is turned on at runtime. While on, every packet is
+//! recorded with its channel, session and size, and the recorded packets are taken
+//! once per tick to be logged with the names of their channels. At most
+//! `MAX_TRACES_PER_SECOND` packets are recorded each second, the rest are counted
+//! so the log isn't flooded by chunk data.
+
+use std::time::{Duration, Instant};
+
+use crate::{ChannelId, Packet, session::Session};
+
+/// Most packets recorded each second while tracing.
+pub const MAX_TRACES_PER_SECOND: usize = 200;
+
+/// Whether a packet was sent or received.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Direction {
+    Sent,
+    Received,
+}
+
+/// A packet that was recorded while tracing.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct TracedPacket {
+    pub direction: Direction,
+    pub channel: ChannelId,
+    pub session: Session,
+
+    /// Size of the payload, in bytes.
+    pub size: usize,
+}
+
+impl TracedPacket {
+    /// Describe the packet for the log, with the name of its channel.
+    pub fn describe(&self, channel_name: &str) -> String {
+        let (verb, preposition) = match self.direction {
+            Direction::Sent => ("Sent", "to"),
+            Direction::Received => ("Received", "from"),
+        };
+        format!(
+            "{verb} '{channel_name}' ({} bytes) {preposition} session {}",
+            self.size,
+            self.session.index()
+        )
+    }
+}
+
+#[derive(Default)]
+pub struct PacketTrace {
+    enabled: bool,
+
+    /// Packets recorded since they were last taken.
+    records: Vec<TracedPacket>,
+
+    /// Start of the current second, and the number of packets recorded in it.
+    window: Option<(Instant, usize)>,
+
+    /// Packets that weren't recorded because of the rate limit, since they were last counted.
+    dropped: usize,
+}
+
+impl PacketTrace {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Turn tracing on or off. Packets recorded before it was turned off are kept.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        self.window = None;
+    }
+
+    /// Record a packet, if tracing is on.
+    #[inline]
+    pub fn record(&mut self, direction: Direction, packet: &Packet) {
+        if self.enabled {
+            self.record_at(
+                Instant::now(),
+                direction,
+                packet.channel,
+                packet.session,
+                packet.payload.len(),
+            );
+        }
+    }
+
+    /// Record a packet that isn't in a `Packet`, if tracing is on.
+    #[inline]
+    pub fn record_raw(
+        &mut self,
+        direction: Direction,
+        channel: ChannelId,
+        session: Session,
+        size: usize,
+    ) {
+        if self.enabled {
+            self.record_at(Instant::now(), direction, channel, session, size);
+        }
+    }
+
+    fn record_at(
+        &mut self,
+        now: Instant,
+        direction: Direction,
+        channel: ChannelId,
+        session: Session,
+        size: usize,
+    ) {
+        let (start, count) = self.window.get_or_insert((now, 0));
+        if now.duration_since(*start) >= Duration::from_secs(1) {
+            *start = now;
+            *count = 0;
+        }
+
+        if *count >= MAX_TRACES_PER_SECOND {
+            self.dropped += 1;
+            return;
+        }
+
+        *count += 1;
+        self.records.push(TracedPacket {
+            direction,
+            channel,
+            session,
+            size,
+        });
+    }
+
+    /// Take the packets recorded since this was last called.
+    pub fn drain(&mut self) -> std::vec::Drain<'_, TracedPacket> {
+        self.records.drain(..)
+    }
+
+    /// Take the number of packets that weren't recorded because of the rate limit.
+    pub fn take_dropped(&mut self) -> usize {
+        std::mem::take(&mut self.dropped)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rate_limited() {
+        let mut trace = PacketTrace::default();
+        let start = Instant::now();
+        let record = |trace: &mut PacketTrace, at: Instant| {
+            if trace.is_enabled() {
+                trace.record_at(at, Direction::Sent, ChannelId(3), Session::ZERO, 16);
+            }
+        };
+
+        // nothing is recorded while off.
+        record(&mut trace, start);
+        assert_eq!(trace.drain().count(), 0);
+
+        trace.set_enabled(true);
+        for _ in 0..MAX_TRACES_PER_SECOND + 10 {
+            record(&mut trace, start);
+        }
+        assert_eq!(trace.drain().count(), MAX_TRACES_PER_SECOND);
+        assert_eq!(trace.take_dropped(), 10);
+        assert_eq!(trace.take_dropped(), 0);
+
+        // the limit resets the next second.
+        record(&mut trace, start + Duration::from_millis(1500));
+        let traced = trace.drain().collect::<Vec<_>>();
+        assert_eq!(traced.len(), 1);
+        assert_eq!(
+            traced[0].describe("chunk-data"),
+            "Sent 'chunk-data' (16 bytes) to session 0"
+        );
+    }
+}
@@ -36,6 +36,374 @@ pub struct PlayerInputUpdate {
     pub version: Version,
     pub translation: Vec3,
     pub look_dir: Quat,
+
+    /// The `MovementState` of the player, as a `u32`.
+    pub movement: u32,
+
+    /// The last `PlayerPositionCorrection::correction` the client applied.
+    /// Positions sent before the client moved back to a correction are ignored.
+    pub correction: u32,
+
+    pub _pad: [u32; 2],
+}
+
+/// Sent from the server to a client when a movement of its player was rejected,
+/// with the position the server kept, so the client moves back to it.
+#[derive(Copy, Clone, Pod, Zeroable)]
+#[repr(C)]
+pub struct PlayerPositionCorrection {
+    /// Number of corrections sent to the player, counting this one.
+    pub correction: u32,
+
+    /// Position of the feet of the player.
+    pub translation: Vec3,
+}
+
+/// Sent from the server to clients with the state of another player.
+#[derive(Copy, Clone, Pod, Zeroable)]
+#[repr(C)]
+pub struct PlayerSnapshot {
+    /// The `Session` of the player this snapshot describes.
+    pub session: u64,
+
+    /// The version of the input update this snapshot was taken from.
+    pub version: Version,
+
+    /// The `MovementState` of the player, as a `u32`.
+    pub movement: u32,
+
+    /// Position of the feet of the player.
+    pub translation: Vec3,
+
+    /// Rotation of the body around the Y axis, in radians.
+    pub yaw: f32,
+
+    /// Rotation of the head around the X axis, in radians.
+    pub pitch: f32,
+
+    pub _pad: u32,
+}
+
+/// Sent from the server to clients when another player leaves.
+#[derive(Copy, Clone, Pod, Zeroable)]
+#[repr(C)]
+pub struct PlayerRemoved {
+    /// The `Session` of the player that left.
+    pub session: u64,
+}
+
+/// How a player looks to other players.
+/// Sent by a client when it joins, then by the server to every other client.
+#[derive(Copy, Clone, Pod, Zeroable, Debug, PartialEq, Eq)]
+#[repr(C)]
+pub struct PlayerAppearance {
+    /// The `Session` of the player, the server overwrites it with the sender's.
+    pub session: u64,
+
+    /// The `skin_hash` of the name of the player's skin, 0 for none.
+    /// Clients that don't have the skin draw the player with its colors.
+    pub skin: u64,
+
+    /// sRGB colors of the body and head, as RGBA.
+    pub colors: [[u8; 4]; 2],
+}
+
+impl PlayerAppearance {
+    /// Colors of players without a skin, or whose skin isn't found.
+    pub const DEFAULT_COLORS: [[u8; 4]; 2] = [[178, 115, 76, 255]; 2];
+
+    /// Hash of the name of a skin, which clients resolve to the skin with that name.
+    /// Names are compared without case, and the empty name is no skin.
+    pub fn skin_hash(name: &str) -> u64 {
+        if name.is_empty() {
+            return 0;
+        }
+
+        // 0 is reserved for players without a skin.
+        fxhash::hash64(name.to_lowercase().as_bytes()).max(1)
+    }
+}
+
+impl Default for PlayerAppearance {
+    fn default() -> Self {
+        Self {
+            session: 0,
+            skin: 0,
+            colors: Self::DEFAULT_COLORS,
+        }
+    }
+}
+
+/// Sent by a client when it joins, so the server knows the player by the same identity
+/// as in earlier sessions. The key is generated once by the client and kept secret,
+/// since whoever sends it is that player.
+#[derive(Copy, Clone, Pod, Zeroable, Debug, PartialEq, Eq)]
+#[repr(C)]
+pub struct PlayerLogin {
+    pub key: [u64; 2],
+}
+
+impl PlayerLogin {
+    /// The key as hex, the way servers save it.
+    pub fn key_hex(&self) -> String {
+        format!("{:016x}{:016x}", self.key[0], self.key[1])
+    }
+}
+
+/// How the player is currently moving.
+#[derive(Copy, Clone, Default, Eq, PartialEq, Debug)]
+#[repr(u8)]
+pub enum MovementState {
+    #[default]
+    Walking,
+    Sprinting,
+
+    /// Moving slowly, without walking off of ledges.
+    Sneaking,
+
+    /// Inside a liquid.
+    Swimming,
+
+    /// Gravity and collision are disabled.
+    Flying,
+}
+
+impl MovementState {
+    pub const fn from_u8(v: u8) -> Option<Self> {
+        Some(match v {
+            0 => Self::Walking,
+            1 => Self::Sprinting,
+            2 => Self::Sneaking,
+            3 => Self::Swimming,
+            4 => Self::Flying,
+            _ => return None,
+        })
+    }
+}
+
+/// Sent from the client to the server to request that a block be broken or placed.
+#[derive(Copy, Clone, Pod, Zeroable)]
+#[repr(C)]
+pub struct BlockEditRequest {
+    /// Client-assigned sequence number, echoed back in the `BlockUpdate`
+    /// so the client can reconcile its predicted edit.
+    pub sequence: u32,
+
+    /// Position of the targeted voxel.
+    pub pos: IVec3,
+
+    /// Face of the targeted voxel (`Axis as u8`), or `u8::MAX`
+    /// if the player is inside the voxel.
+    pub face: u8,
+
+    /// One of `BlockEditRequest::BREAK` or `BlockEditRequest::PLACE`.
+    pub action: u8,
+
+    /// The voxel held by the player, placed when the action is `PLACE`.
+    pub item: u16,
+}
+
+impl BlockEditRequest {
+    pub const BREAK: u8 = 0;
+    pub const PLACE: u8 = 1;
+}
+
+/// Sent from the server to the client with the authoritative state of a voxel.
+#[derive(Copy, Clone, Pod, Zeroable)]
+#[repr(C)]
+pub struct BlockUpdate {
+    /// Position of the voxel that changed.
+    pub pos: IVec3,
+
+    /// The new voxel at that position.
+    pub voxel: u16,
+
+    pub _pad: u16,
+
+    /// Sequence of the `BlockEditRequest` this update answers,
+    /// or zero if it was caused by something else.
+    pub sequence: u32,
+}
+
+/// Sent from the server to the client when many voxels near each other change at
+/// once, like in an explosion. The payload is this header, followed by `count`
+/// `MultiBlockEntry`s.
+#[derive(Copy, Clone, Pod, Zeroable, Debug, PartialEq, Eq)]
+#[repr(C)]
+pub struct MultiBlockUpdate {
+    /// Position the offsets of the entries are relative to.
+    pub origin: IVec3,
+
+    /// Number of entries after the header.
+    pub count: u32,
+}
+
+/// A voxel that changed in a `MultiBlockUpdate`.
+#[derive(Copy, Clone, Pod, Zeroable, Debug, PartialEq, Eq)]
+#[repr(C)]
+pub struct MultiBlockEntry {
+    /// Position of the voxel, relative to the origin of the update.
+    pub offset: [i8; 3],
+
+    pub _pad: u8,
+
+    /// The new voxel at that position.
+    pub voxel: u16,
+}
+
+impl MultiBlockUpdate {
+    /// Furthest a voxel can be from the origin on any axis.
+    pub const MAX_OFFSET: i32 = i8::MAX as i32;
+
+    /// Write the header and an entry for each voxel.
+    /// Voxels further than `MAX_OFFSET` from the origin are skipped.
+    pub fn encode(origin: IVec3, voxels: impl IntoIterator<Item = (IVec3, u16)>) -> Bytes {
+        let mut entries = Vec::new();
+        for (pos, voxel) in voxels {
+            let offset = pos - origin;
+            if offset.abs().max_element() > Self::MAX_OFFSET {
+                continue;
+            }
+
+            entries.push(MultiBlockEntry {
+                offset: [offset.x as i8, offset.y as i8, offset.z as i8],
+                _pad: 0,
+                voxel,
+            });
+        }
+
+        let header = Self {
+            origin,
+            count: entries.len() as u32,
+        };
+        let mut buffer = BytesMut::with_capacity(
+            size_of::<Self>() + entries.len() * size_of::<MultiBlockEntry>(),
+        );
+        buffer.put_slice(bytemuck::bytes_of(&header));
+        buffer.put_slice(bytemuck::cast_slice(&entries));
+        buffer.freeze()
+    }
+
+    /// Read the positions and voxels of an update,
+    /// or None if the payload doesn't hold as many entries as its header says.
+    pub fn decode(payload: &[u8]) -> Option<Vec<(IVec3, u16)>> {
+        let (header, entries) = payload.split_at_checked(size_of::<Self>())?;
+        let header = bytemuck::pod_read_unaligned::<Self>(header);
+        if entries.len() != header.count as usize * size_of::<MultiBlockEntry>() {
+            return None;
+        }
+
+        let voxels = entries
+            .chunks_exact(size_of::<MultiBlockEntry>())
+            .map(|entry| {
+                let entry = bytemuck::pod_read_unaligned::<MultiBlockEntry>(entry);
+                let [x, y, z] = entry.offset;
+                let offset = IVec3::new(x as i32, y as i32, z as i32);
+                (header.origin + offset, entry.voxel)
+            })
+            .collect();
+        Some(voxels)
+    }
+}
+
+/// Sent from the server to the client when a chunk it was sent leaves the player's
+/// draw distance. The client drops its copy, and the chunk is sent again if it comes
+/// back into range.
+#[derive(Copy, Clone, Pod, Zeroable)]
+#[repr(C)]
+pub struct ChunkUnload {
+    /// Origin of the chunk on the XZ plane.
+    pub origin: IVec2,
+}
+
+/// Sent from the client to the server for a chunk near the player it doesn't have.
+/// The server sends the chunk ahead of the others it has queued, if it is in the
+/// player's draw distance. Requests for other chunks are ignored.
+#[derive(Copy, Clone, Pod, Zeroable)]
+#[repr(C)]
+pub struct ChunkRequest {
+    /// Origin of the chunk on the XZ plane.
+    pub origin: IVec2,
+}
+
+/// A chunk and the revision of it the sender has.
+///
+/// Sent from the client to the server, several per packet, for chunks it kept a copy
+/// of after unloading them. When one of those chunks would be sent again and its
+/// revision hasn't changed, the server sends this back instead of the chunk data,
+/// and the client loads its copy.
+#[derive(Copy, Clone, Pod, Zeroable, Debug, PartialEq, Eq)]
+#[repr(C)]
+pub struct ChunkRevision {
+    /// Origin of the chunk on the XZ plane.
+    pub origin: IVec2,
+
+    /// The `Chunk::revision` of the copy.
+    pub revision: u64,
+}
+
+/// Sent from the server to clients when they join, and then periodically,
+/// so their time of day doesn't drift from the server's.
+#[derive(Copy, Clone, Pod, Zeroable)]
+#[repr(C)]
+pub struct WorldTimeSync {
+    /// Ticks since the world was created.
+    pub tick: u64,
+
+    /// Fraction of the sky light taken away, see `WorldTime::sky_darkness`.
+    pub sky_darkness: f32,
+
+    pub _pad: u32,
+}
+
+/// Sent from the server to a client when its player's stats change,
+/// and once when it joins.
+#[derive(Copy, Clone, Pod, Zeroable, Debug, Default, PartialEq, Eq)]
+#[repr(C)]
+pub struct PlayerStatsUpdate {
+    pub health: u16,
+    pub max_health: u16,
+    pub hunger: u16,
+    pub max_hunger: u16,
+}
+
+/// Sent from the client to the server with a line typed into the chat.
+/// Lines starting with '/' are commands.
+/// Lines are shown to players with a `data::text::rich::ChatMessage`.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ChatSend {
+    pub text: String,
+}
+
+/// Sent from the server to a client after it joins,
+/// with the commands the client can autocomplete.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct CommandCompletions {
+    pub commands: Vec<CommandCompletion>,
+}
+
+/// Completion metadata of a single command.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CommandCompletion {
+    /// Name of the command, without the leading '/'.
+    pub name: String,
+
+    /// Names of the arguments of the command, in order, for usage hints.
+    pub args: Vec<String>,
+
+    /// Short description of what the command does.
+    pub description: String,
+}
+
+impl CommandCompletion {
+    /// Usage hint of the command, like `/tp <x> <y> <z>`.
+    pub fn usage(&self) -> String {
+        let mut usage = format!("/{}", self.name);
+        for arg in &self.args {
+            usage.push_str(&format!(" <{arg}>"));
+        }
+        usage
+    }
 }
 
 #[derive(Default, Serialize, Deserialize)]
@@ -70,3 +438,44 @@ pub struct AuthAccepted {
     pub session: Session,
     pub udp_addr: SocketAddr,
 }
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::IVec3;
+
+    use super::{MultiBlockUpdate, PlayerAppearance};
+
+    #[test]
+    fn multi_block_update() {
+        let origin = IVec3::new(100, -20, -300);
+        let voxels = [
+            (origin, 0),
+            (origin + IVec3::new(-127, 5, 127), 7),
+            (origin + IVec3::new(3, -1, 0), u16::MAX),
+        ];
+
+        // the last voxel is too far from the origin to be written.
+        let far = (origin + IVec3::new(0, 128, 0), 1);
+        let payload = MultiBlockUpdate::encode(origin, voxels.into_iter().chain([far]));
+        assert_eq!(MultiBlockUpdate::decode(&payload).unwrap(), voxels);
+
+        assert!(MultiBlockUpdate::decode(&payload[..payload.len() - 1]).is_none());
+        assert!(MultiBlockUpdate::decode(&payload[..4]).is_none());
+        let empty = MultiBlockUpdate::encode(origin, []);
+        assert!(MultiBlockUpdate::decode(&empty).unwrap().is_empty());
+    }
+
+    #[test]
+    fn skin_hash() {
+        assert_eq!(PlayerAppearance::skin_hash(""), 0);
+        assert_ne!(PlayerAppearance::skin_hash("steve"), 0);
+        assert_eq!(
+            PlayerAppearance::skin_hash("Steve"),
+            PlayerAppearance::skin_hash("steve")
+        );
+        assert_ne!(
+            PlayerAppearance::skin_hash("steve"),
+            PlayerAppearance::skin_hash("alex")
+        );
+    }
+}
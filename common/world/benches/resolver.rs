@@ -0,0 +1,68 @@
+//! Looking up regions and voxels by position, in a world with many regions loaded.
+
+use std::hint::black_box;
+
+use bevy::math::{IVec2, IVec3, ivec2, ivec3};
+use criterion::{Criterion, criterion_group, criterion_main};
+use math::rng::BitRng;
+use world::World;
+
+/// Regions loaded on each side of the origin, on both axes.
+const RADIUS: i32 = 4;
+
+/// Number of positions looked up per iteration.
+const LOOKUPS: usize = 4096;
+
+fn world() -> World {
+    let mut world = World::new(256, -128);
+    for x in -RADIUS..RADIUS {
+        for z in -RADIUS..RADIUS {
+            world.get_or_insert_region(ivec2(x * 512, z * 512));
+        }
+    }
+    world
+}
+
+/// Random positions in the loaded regions, and some just outside of them.
+fn positions() -> Vec<IVec3> {
+    let mut rng = BitRng::new(0x5EED);
+    let extent = (RADIUS + 1) * 512;
+    (0..LOOKUPS)
+        .map(|_| {
+            let x = (rng.take(13) as i32 % (extent * 2)) - extent;
+            let z = (rng.take(13) as i32 % (extent * 2)) - extent;
+            let y = rng.take(8) as i32 - 128;
+            ivec3(x, y, z)
+        })
+        .collect()
+}
+
+fn get_region(c: &mut Criterion) {
+    let world = world();
+    let positions = positions()
+        .into_iter()
+        .map(|pos| IVec2::new(pos.x, pos.z))
+        .collect::<Vec<_>>();
+    c.bench_function("get_region", |b| {
+        b.iter(|| {
+            for pos in &positions {
+                black_box(world.get_region(*pos));
+            }
+        })
+    });
+}
+
+fn get_voxel(c: &mut Criterion) {
+    let world = world();
+    let positions = positions();
+    c.bench_function("get_voxel", |b| {
+        b.iter(|| {
+            for pos in &positions {
+                black_box(world.get_voxel(*pos));
+            }
+        })
+    });
+}
+
+criterion_group!(benches, get_region, get_voxel);
+criterion_main!(benches);
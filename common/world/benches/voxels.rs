@@ -0,0 +1,73 @@
+//! Reads and writes of voxels, with palettes of 4, 8 and 16 bits per index.
+//!
+//! Writes start from an empty subchunk, so they include growing the palette
+//! and repacking the words every time it needs more bits per index.
+
+use std::hint::black_box;
+
+use bevy::math::{IVec3, ivec3};
+use criterion::{BatchSize, BenchmarkId, Criterion, criterion_group, criterion_main};
+use math::rng::BitRng;
+use world::{Region, Voxel};
+
+/// Number of different voxels in the subchunk, and the bits per index it needs.
+const PALETTES: [(u16, u8); 3] = [(16, 4), (256, 8), (4096, 16)];
+
+/// The voxels of one subchunk, in a random order with `distinct` different voxels.
+fn voxels(distinct: u16) -> Vec<(IVec3, Voxel)> {
+    let mut rng = BitRng::new(0x5EED);
+    let mut voxels = Vec::with_capacity(32768);
+    for y in 0..32 {
+        for x in 0..32 {
+            for z in 0..32 {
+                let voxel = Voxel(rng.take(16) as u16 % distinct);
+                voxels.push((ivec3(x, y, z), voxel));
+            }
+        }
+    }
+    voxels
+}
+
+fn write(region: &mut Region, voxels: &[(IVec3, Voxel)]) {
+    for (pos, voxel) in voxels {
+        region.set_voxel(*pos, *voxel);
+    }
+}
+
+fn set_voxels(c: &mut Criterion) {
+    let mut group = c.benchmark_group("set_voxels");
+    for (distinct, bpi) in PALETTES {
+        let voxels = voxels(distinct);
+        group.bench_with_input(BenchmarkId::from_parameter(bpi), &voxels, |b, voxels| {
+            b.iter_batched(
+                || Region::new(IVec3::ZERO, 32),
+                |mut region| {
+                    write(&mut region, voxels);
+                    region
+                },
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+fn get_voxels(c: &mut Criterion) {
+    let mut group = c.benchmark_group("get_voxels");
+    for (distinct, bpi) in PALETTES {
+        let voxels = voxels(distinct);
+        let mut region = Region::new(IVec3::ZERO, 32);
+        write(&mut region, &voxels);
+        group.bench_function(BenchmarkId::from_parameter(bpi), |b| {
+            b.iter(|| {
+                for (pos, _) in &voxels {
+                    black_box(region.get_voxel(*pos));
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, set_voxels, get_voxels);
+criterion_main!(benches);
@@ -0,0 +1,74 @@
+//! Zipping and unzipping a chunk of terrain with each algorithm.
+
+use std::hint::black_box;
+
+use bevy::math::{IVec2, ivec3};
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use world::{Voxel, World, region::format::UnzippedChunk};
+use zip::{Algorithm, ZipLevel};
+
+const STONE: Voxel = Voxel(1);
+const DIRT: Voxel = Voxel(2);
+const GRASS: Voxel = Voxel(3);
+const WATER: Voxel = Voxel(4);
+
+/// Surface at this height, on average.
+const SEA_LEVEL: i32 = 64;
+
+/// A world with one chunk of rolling hills at the origin, with lakes below sea level.
+fn terrain() -> World {
+    let mut world = World::new(256, -128);
+    world.get_or_insert_region(IVec2::ZERO);
+    for x in 0..32 {
+        for z in 0..32 {
+            let hills = (x as f32 * 0.2).sin() * 6.0 + (z as f32 * 0.15).cos() * 6.0;
+            let surface = SEA_LEVEL + hills as i32;
+            for y in -128..surface.max(SEA_LEVEL) {
+                let voxel = match y {
+                    y if y >= surface => WATER,
+                    y if y == surface - 1 => GRASS,
+                    y if y >= surface - 4 => DIRT,
+                    _ => STONE,
+                };
+                world.set_voxel(ivec3(x, y, z), voxel);
+            }
+        }
+    }
+    world
+}
+
+fn algorithms() -> [(&'static str, Algorithm); 2] {
+    [("zstd", Algorithm::Zstd), ("lz4", Algorithm::Lz4)]
+}
+
+fn zip_chunk(c: &mut Criterion) {
+    let world = terrain();
+    let chunk = world.get_chunk(IVec2::ZERO).unwrap();
+    let mut group = c.benchmark_group("zip_chunk");
+    for (name, alg) in algorithms() {
+        group.bench_function(BenchmarkId::from_parameter(name), |b| {
+            b.iter(|| black_box(chunk.zip(alg, ZipLevel::default())))
+        });
+    }
+    group.finish();
+}
+
+fn unzip_chunk(c: &mut Criterion) {
+    let world = terrain();
+    let chunk = world.get_chunk(IVec2::ZERO).unwrap();
+    let mut group = c.benchmark_group("unzip_chunk");
+    for (name, alg) in algorithms() {
+        let data = chunk.zip(alg, ZipLevel::default());
+        let mut into = World::new(256, -128);
+        group.bench_function(BenchmarkId::from_parameter(name), |b| {
+            b.iter(|| {
+                let unzip = UnzippedChunk::unzip(&data).unwrap();
+                black_box(into.read_unzipped_chunk(unzip, true).unwrap().origin)
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, zip_chunk, unzip_chunk);
+criterion_main!(benches);
@@ -0,0 +1,131 @@
+use std::hash::Hash;
+
+use crate::region::chunk::ChunkId;
+use bevy::prelude::*;
+use fxhash::FxHashMap;
+
+/// Items, like entities, listed by the chunk they are in.
+///
+/// Unlike the `SpatialQuery`, which is rebuilt from every item at once, items are moved
+/// between chunks one at a time as they move, so systems that run per chunk can iterate
+/// the items of the chunk without scanning every item.
+pub struct ChunkIndex<T> {
+    chunks: FxHashMap<ChunkId, Vec<T>>,
+    items: FxHashMap<T, ChunkId>,
+}
+
+impl<T> ChunkIndex<T>
+where
+    T: Copy + Eq + Hash,
+{
+    pub fn new() -> Self {
+        Self {
+            chunks: FxHashMap::default(),
+            items: FxHashMap::default(),
+        }
+    }
+
+    /// Set the position of an item, adding it to the index if it isn't in it yet.
+    /// Returns the chunk the item left, if it moved to a different chunk.
+    pub fn update(&mut self, item: T, pos: IVec3) -> Option<ChunkId> {
+        let chunk = ChunkId::new(pos.xz());
+        let previous = self.items.insert(item, chunk);
+        if previous == Some(chunk) {
+            return None;
+        }
+
+        if let Some(previous) = previous {
+            self.remove_from(previous, item);
+        }
+        self.chunks.entry(chunk).or_default().push(item);
+        previous
+    }
+
+    /// Remove an item from the index, returning the chunk it was in.
+    pub fn remove(&mut self, item: T) -> Option<ChunkId> {
+        let chunk = self.items.remove(&item)?;
+        self.remove_from(chunk, item);
+        Some(chunk)
+    }
+
+    fn remove_from(&mut self, chunk: ChunkId, item: T) {
+        let Some(list) = self.chunks.get_mut(&chunk) else {
+            return;
+        };
+
+        if let Some(i) = list.iter().position(|other| *other == item) {
+            list.swap_remove(i);
+        }
+
+        if list.is_empty() {
+            self.chunks.remove(&chunk);
+        }
+    }
+
+    /// The items in a chunk, in no particular order.
+    pub fn in_chunk(&self, chunk: impl Into<ChunkId>) -> &[T] {
+        self.chunks
+            .get(&chunk.into())
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// The chunk an item is in, or None if it isn't in the index.
+    pub fn chunk_of(&self, item: T) -> Option<ChunkId> {
+        self.items.get(&item).copied()
+    }
+
+    /// The chunks with at least one item, and their items.
+    pub fn chunks(&self) -> impl Iterator<Item = (ChunkId, &[T])> {
+        self.chunks
+            .iter()
+            .map(|(chunk, items)| (*chunk, items.as_slice()))
+    }
+
+    /// Number of items in the index.
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+}
+
+impl<T> Default for ChunkIndex<T>
+where
+    T: Copy + Eq + Hash,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn items_follow_their_position() {
+        let mut index = ChunkIndex::new();
+        let (a, b) = (ChunkId::new(IVec2::ZERO), ChunkId::new(IVec2::new(-32, 64)));
+
+        assert_eq!(index.update(1, IVec3::new(5, 100, 31)), None);
+        assert_eq!(index.update(2, IVec3::new(0, -20, 0)), None);
+        assert_eq!(index.update(1, IVec3::new(6, 0, 0)), None);
+        assert_eq!(index.in_chunk(a).len(), 2);
+        assert_eq!(index.len(), 2);
+
+        // crossing into a negative chunk.
+        assert_eq!(index.update(1, IVec3::new(-1, 0, 70)), Some(a));
+        assert_eq!(index.in_chunk(a), [2]);
+        assert_eq!(index.in_chunk(b), [1]);
+        assert_eq!(index.chunk_of(1), Some(b));
+
+        assert_eq!(index.remove(2), Some(a));
+        assert_eq!(index.remove(2), None);
+        assert!(index.in_chunk(a).is_empty());
+        assert_eq!(index.chunks().count(), 1);
+        assert_eq!(index.len(), 1);
+    }
+}
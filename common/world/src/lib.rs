@@ -17,13 +17,17 @@ use crate::region::{
     format::{ChunkReadError, ChunkReadSuccess, UnzippedChunk},
 };
 pub use crate::{
+    raycast::RayHit,
     region::{BiomeId, Chunk, ColumnData, Region, Subchunk},
     voxel::{Light, Voxel, VoxelState},
 };
 
+pub mod chunk_index;
 pub mod knn;
+pub mod raycast;
 pub mod region;
 pub mod resolver;
+pub mod time;
 pub mod voxel;
 
 #[derive(Resource)]
@@ -121,6 +125,36 @@ impl World {
         }
     }
 
+    /// Remove and drop every Region.
+    /// This will invalidate any borrowed region maps.
+    pub fn clear(&mut self) {
+        for ptr in self.regions.drain(..) {
+            drop(unsafe { Box::from_non_null(ptr) });
+        }
+        self.resolver = resolver::OwnedResolver::new();
+    }
+
+    /// Number of Regions in the World.
+    #[inline]
+    pub fn num_regions(&self) -> usize {
+        self.regions.len()
+    }
+
+    /// Approximate number of bytes allocated for every Region in the World.
+    pub fn heap_size(&self) -> usize {
+        self.regions().map(Region::heap_size).sum()
+    }
+
+    /// Iterate every Region in the World, in no particular order.
+    pub fn regions(&self) -> impl Iterator<Item = &Region> {
+        self.regions.iter().map(|ptr| unsafe { ptr.as_ref() })
+    }
+
+    /// Iterate every Region in the World mutably, in no particular order.
+    pub fn regions_mut(&mut self) -> impl Iterator<Item = &mut Region> {
+        self.regions.iter_mut().map(|ptr| unsafe { ptr.as_mut() })
+    }
+
     /// Check whether the region exists in the map.
     #[inline]
     pub fn has_region(&self, id: impl Into<RegionId>) -> bool {
@@ -309,14 +343,23 @@ impl World {
     }
 }
 
+impl Drop for World {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
 unsafe impl Send for World {}
 unsafe impl Sync for World {}
 
 #[cfg(test)]
 mod tests {
-    use bevy::math::{IVec2, ivec2, ivec3};
+    use bevy::math::{IVec2, Vec3Swizzles, ivec2, ivec3};
 
-    use crate::World;
+    use crate::{
+        Voxel, World,
+        region::{RegionId, chunk::flags::ChunkState},
+    };
 
     #[test]
     fn get_region() {
@@ -345,4 +388,43 @@ mod tests {
             ivec3(64, -128, 64)
         );
     }
+
+    #[test]
+    fn remove_and_iterate_regions() {
+        let mut world = World::new(256, -128);
+        world.get_or_insert_region(ivec2(0, 0));
+        world.get_or_insert_region(ivec2(512, 0));
+        world.get_or_insert_region(ivec2(0, 512));
+        assert_eq!(world.num_regions(), 3);
+
+        assert!(world.remove(ivec2(0, 0)).is_some());
+        assert!(world.get_region(ivec2(512, 0)).is_some());
+        assert!(world.get_region(ivec2(0, 512)).is_some());
+
+        let mut ids = world.regions().map(|r| r.id()).collect::<Vec<_>>();
+        ids.sort_by_key(|id| (id.x(), id.z()));
+        assert_eq!(
+            ids,
+            vec![RegionId::new(ivec2(0, 512)), RegionId::new(ivec2(512, 0))]
+        );
+
+        world.clear();
+        assert_eq!(world.num_regions(), 0);
+        assert!(world.get_region(ivec2(512, 0)).is_none());
+    }
+
+    #[test]
+    fn unload_chunk() {
+        let mut world = World::new(256, -128);
+        world.get_or_insert_region(ivec2(0, 0));
+        let pos = ivec3(40, 10, 40);
+        *world.get_chunk_mut(pos.xz()).unwrap().load_state_mut() = ChunkState::Loaded;
+        world.set_voxel(pos, Voxel(3));
+        assert_eq!(world.get_voxel(pos), Some(Voxel(3)));
+
+        world.get_chunk_mut(pos.xz()).unwrap().unload();
+        let chunk = world.get_chunk(pos.xz()).unwrap();
+        assert_eq!(chunk.load_state(), ChunkState::Unloaded);
+        assert_eq!(world.get_voxel(pos), Some(Voxel::AIR));
+    }
 }
@@ -0,0 +1,165 @@
+use bevy::math::{IVec3, Vec3};
+use math::axis::Axis;
+
+use crate::{VoxelState, World};
+
+/// The voxel hit by a ray.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RayHit {
+    /// Position of the voxel that was hit.
+    pub pos: IVec3,
+
+    /// The face of the voxel the ray entered through.
+    /// "None" if the ray started inside the voxel.
+    pub face: Option<Axis>,
+
+    /// Distance along the ray to the point it entered the voxel.
+    pub distance: f32,
+
+    /// State of the voxel that was hit.
+    pub state: VoxelState,
+}
+
+impl RayHit {
+    /// Position of the voxel adjacent to the face that was hit,
+    /// which is where a block would be placed.
+    pub fn adjacent(&self) -> IVec3 {
+        match self.face {
+            Some(face) => self.pos + face.as_ivec3(),
+            None => self.pos,
+        }
+    }
+}
+
+impl World {
+    /// Walk the voxels along a ray, returning the first voxel for which `hit` returns true.
+    /// Traversal stops at `max_distance`, or when the ray leaves loaded regions.
+    ///
+    /// `dir` does not need to be normalized, but must not be zero.
+    pub fn raycast(
+        &self,
+        origin: Vec3,
+        dir: Vec3,
+        max_distance: f32,
+        mut hit: impl FnMut(IVec3, VoxelState) -> bool,
+    ) -> Option<RayHit> {
+        let dir = dir.try_normalize()?;
+        let mut pos = origin.floor().as_ivec3();
+        let step = dir.signum().as_ivec3();
+
+        // distance along the ray to cross one voxel on each axis.
+        let delta = (1.0 / dir).abs();
+
+        // distance along the ray to the first boundary on each axis.
+        let next_boundary = pos.as_vec3() + step.max(IVec3::ZERO).as_vec3();
+        let mut t_max = Vec3::select(
+            dir.cmpeq(Vec3::ZERO),
+            Vec3::INFINITY,
+            (next_boundary - origin) / dir,
+        );
+
+        let mut face = None;
+        let mut distance = 0.0;
+        while distance <= max_distance {
+            let state = self.get_state(pos)?;
+            if hit(pos, state) {
+                return Some(RayHit {
+                    pos,
+                    face,
+                    distance,
+                    state,
+                });
+            }
+
+            // step along the axis with the nearest boundary.
+            let axis = if t_max.x < t_max.y && t_max.x < t_max.z {
+                0
+            } else if t_max.y < t_max.z {
+                1
+            } else {
+                2
+            };
+
+            distance = t_max[axis];
+            t_max[axis] += delta[axis];
+            pos[axis] += step[axis];
+            face = Some(entered_face(axis, step[axis]));
+        }
+
+        None
+    }
+}
+
+/// The face a voxel is entered through when stepping along `axis` in direction `step`.
+fn entered_face(axis: usize, step: i32) -> Axis {
+    match (axis, step > 0) {
+        (0, true) => Axis::NegX,
+        (0, false) => Axis::PosX,
+        (1, true) => Axis::NegY,
+        (1, false) => Axis::PosY,
+        (2, true) => Axis::NegZ,
+        _ => Axis::PosZ,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::{Vec3, ivec2, ivec3, vec3};
+    use math::axis::Axis;
+
+    use crate::{Voxel, World};
+
+    fn world_with_wall() -> World {
+        let mut world = World::new(256, -128);
+        world.get_or_insert_region(ivec2(0, 0));
+        for y in 0..4 {
+            for z in 0..4 {
+                world.set_voxel(ivec3(5, y, z), Voxel(1));
+            }
+        }
+        world
+    }
+
+    #[test]
+    fn hits_wall_face() {
+        let world = world_with_wall();
+        let hit = world
+            .raycast(vec3(0.5, 1.5, 1.5), Vec3::X, 16.0, |_, s| s.voxel != Voxel::AIR)
+            .unwrap();
+        assert_eq!(hit.pos, ivec3(5, 1, 1));
+        assert_eq!(hit.face, Some(Axis::NegX));
+        assert_eq!(hit.adjacent(), ivec3(4, 1, 1));
+        assert!((hit.distance - 4.5).abs() < 1e-4);
+    }
+
+    #[test]
+    fn respects_max_distance() {
+        let world = world_with_wall();
+        let hit = world.raycast(vec3(0.5, 1.5, 1.5), Vec3::X, 3.0, |_, s| {
+            s.voxel != Voxel::AIR
+        });
+        assert!(hit.is_none());
+    }
+
+    #[test]
+    fn hits_from_negative_direction() {
+        let world = world_with_wall();
+        let hit = world
+            .raycast(vec3(9.5, 2.5, 2.5), vec3(-1.0, 0.0, 0.0), 16.0, |_, s| {
+                s.voxel != Voxel::AIR
+            })
+            .unwrap();
+        assert_eq!(hit.pos, ivec3(5, 2, 2));
+        assert_eq!(hit.face, Some(Axis::PosX));
+    }
+
+    #[test]
+    fn starts_inside_voxel() {
+        let world = world_with_wall();
+        let hit = world
+            .raycast(vec3(5.5, 0.5, 0.5), Vec3::Y, 16.0, |_, s| s.voxel != Voxel::AIR)
+            .unwrap();
+        assert_eq!(hit.pos, ivec3(5, 0, 0));
+        assert_eq!(hit.face, None);
+    }
+}
@@ -4,6 +4,7 @@ use bitvec::{
     ptr::{BitRef, Mut},
 };
 use math::space::area::IArea;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 const CHUNK_FLAGS_LEN: usize = 256 / usize::BITS as usize;
 
@@ -32,10 +33,16 @@ impl ChunkMask {
     /// are with the area of its containing region.
     pub fn from_area(area: &IArea) -> Self {
         let mut mask = ChunkMask::new();
+        mask.set_area(area, true);
+        mask
+    }
+
+    /// Set or clear every chunk the area overlaps.
+    /// Like `from_area`, the area is expected to be within a single region.
+    pub fn set_area(&mut self, area: &IArea, v: bool) {
         for cell in area.cells_pow2::<32>() {
-            mask.set(cell.min, true);
+            self.set(cell.min, v);
         }
-        mask
     }
 
     /// This operation is wrapping, and therefore infallible.
@@ -89,12 +96,89 @@ impl ChunkMask {
         Self(self.0 & rhs.0)
     }
 
+    /// Get the chunks in self or rhs.
+    pub fn union(&self, rhs: &Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+
+    /// Get the chunks in self that are not in rhs.
+    pub fn difference(&self, rhs: &Self) -> Self {
+        Self(self.0 & !rhs.0)
+    }
+
+    /// Get the chunks in either self or rhs, but not both.
+    pub fn symmetric_difference(&self, rhs: &Self) -> Self {
+        Self(self.0 ^ rhs.0)
+    }
+
+    /// Number of chunks that are set.
+    pub fn count_ones(&self) -> usize {
+        self.0.count_ones()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.not_any()
+    }
+
+    /// Offsets of the chunks that are set, from the origin of the region, X-major.
     pub fn iter_ones(&self) -> impl Iterator<Item = IVec2> {
         self.0.iter_ones().map(|i| IVec2 {
             x: ((i & 0xF) as i32) << 5,
             y: ((i >> 4) as i32) << 5,
         })
     }
+
+    /// Origins of the chunks that are set, in a region with an origin.
+    pub fn iter_positions(&self, region_origin: IVec2) -> impl Iterator<Item = IVec2> {
+        self.iter_ones().map(move |offs| region_origin + offs)
+    }
+
+    /// Origins of at most "limit" of the chunks that are set, in index order.
+    /// The chunks that were returned are cleared, so the rest can be taken later.
+    pub fn take_positions(&mut self, region_origin: IVec2, limit: usize) -> Vec<IVec2> {
+        let taken: Vec<usize> = self.0.iter_ones().take(limit).collect();
+        taken
+            .into_iter()
+            .map(|i| {
+                self.0.set(i, false);
+                region_origin + ivec2(((i & 0xF) as i32) << 5, ((i >> 4) as i32) << 5)
+            })
+            .collect()
+    }
+
+    /// The mask as four 64-bit words, with chunk 0 in the lowest bit of the first word.
+    /// This doesn't depend on the size of usize, unlike the bits themselves.
+    pub fn to_words(&self) -> [u64; 4] {
+        let mut words = [0u64; 4];
+        for i in self.0.iter_ones() {
+            words[i / 64] |= 1 << (i % 64);
+        }
+        words
+    }
+
+    pub fn from_words(words: [u64; 4]) -> Self {
+        let mut mask = Self::new();
+        for (w, word) in words.into_iter().enumerate() {
+            for bit in 0..64 {
+                if word >> bit & 1 == 1 {
+                    mask.0.set(w * 64 + bit, true);
+                }
+            }
+        }
+        mask
+    }
+}
+
+impl Serialize for ChunkMask {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.to_words().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for ChunkMask {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        <[u64; 4]>::deserialize(deserializer).map(Self::from_words)
+    }
 }
 
 impl std::ops::Not for ChunkMask {
@@ -126,6 +210,20 @@ impl std::ops::BitOrAssign for ChunkMask {
     }
 }
 
+impl std::ops::BitAndAssign for ChunkMask {
+    fn bitand_assign(&mut self, rhs: Self) {
+        self.0 &= rhs.0
+    }
+}
+
+impl std::ops::BitXor for ChunkMask {
+    type Output = Self;
+
+    fn bitxor(self, rhs: Self) -> Self::Output {
+        Self(self.0 ^ rhs.0)
+    }
+}
+
 #[cfg(test)]
 mod chunk_mask_tests {
     use super::*;
@@ -494,4 +592,55 @@ mod chunk_mask_tests {
         assert!(chunks.contains(&ivec2(0, 32)));
         assert!(chunks.contains(&ivec2(32, 32)));
     }
+
+    #[test]
+    fn test_chunk_mask_positions_and_set_area() {
+        let origin = ivec2(-512, 1024);
+        let mut mask = ChunkMask::new();
+        mask.set_area(&IArea::new(origin, origin + ivec2(96, 64)), true);
+        assert_eq!(mask.count_ones(), 6);
+
+        // clearing part of the area.
+        mask.set_area(
+            &IArea::new(origin + ivec2(32, 0), origin + ivec2(64, 64)),
+            false,
+        );
+        let positions: Vec<_> = mask.iter_positions(origin).collect();
+        assert_eq!(
+            positions,
+            vec![
+                origin,
+                origin + ivec2(64, 0),
+                origin + ivec2(0, 32),
+                origin + ivec2(64, 32),
+            ]
+        );
+
+        // taking a few at a time.
+        let first = mask.take_positions(origin, 3);
+        assert_eq!(first, positions[..3]);
+        assert_eq!(mask.take_positions(origin, 3), positions[3..]);
+        assert!(mask.is_empty());
+    }
+
+    #[test]
+    fn test_chunk_mask_set_operations_and_words() {
+        let a = ChunkMask::from_area(&region_area(ivec2(0, 0), ivec2(2, 2)));
+        let b = ChunkMask::from_area(&region_area(ivec2(1, 1), ivec2(3, 3)));
+        assert_eq!(a.union(&b).count_ones(), 7);
+        assert_eq!(a.difference(&b).count_ones(), 3);
+        assert_eq!(a.symmetric_difference(&b), a ^ b);
+        assert_eq!(a.symmetric_difference(&b).count_ones(), 6);
+
+        let mut c = a;
+        c &= b;
+        assert_eq!(c, a.intersection(&b));
+
+        let mut mask = a;
+        mask.set(ivec2(480, 480), true);
+        let words = mask.to_words();
+        assert_eq!(words[0] & 0b11, 0b11);
+        assert_eq!(words[3] >> 63, 1);
+        assert_eq!(ChunkMask::from_words(words), mask);
+    }
 }
@@ -16,9 +16,11 @@ use bytes::Bytes;
 use column::{ColumnData, ColumnMap};
 use math::space::area::IArea;
 use zip::{Algorithm, UnzippedSpan, ZipLevel, Zipper};
+use zip_cache::ZipCache;
 
 pub mod column;
 pub mod flags;
+pub mod zip_cache;
 
 /// A vertical column of subchunks in a Region.
 pub struct Chunk<A: Allocator + Clone = RegionAlloc> {
@@ -129,6 +131,18 @@ impl<A: Allocator + Clone> Chunk<A> {
         }
     }
 
+    /// Version number of the chunk, bumped every time it is modified.
+    pub const fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// Assign the revision of the chunk, for newly generated chunks. Starting them at a
+    /// random revision keeps copies of a chunk from a deleted world from being mistaken
+    /// for the chunk generated in its place.
+    pub fn set_revision(&mut self, revision: u64) {
+        self.revision = revision;
+    }
+
     pub const fn load_state(&self) -> ChunkState {
         self.state
     }
@@ -137,6 +151,21 @@ impl<A: Allocator + Clone> Chunk<A> {
         &mut self.state
     }
 
+    /// Drop the voxel data of the chunk and mark it as unloaded.
+    /// The memory of the chunk is freed, but not the chunk itself,
+    /// which is owned by the containing Region.
+    pub fn unload(&mut self) {
+        // release pointers into the span before it is dropped.
+        for subchunk in self.iter_mut() {
+            subchunk.fill_air();
+        }
+
+        self.span = None;
+        self.zip = None;
+        self.needs_save = false;
+        self.state = ChunkState::Unloaded;
+    }
+
     pub fn get_cached_zip(&self) -> Option<ZippedChunk> {
         self.zip.clone()
     }
@@ -326,6 +355,14 @@ impl<A: Allocator + Clone> Chunk<A> {
         mask
     }
 
+    /// Call `f` with each voxel value in each subchunk of the chunk and the
+    /// number of voxels with that value. Values may be reported once per subchunk.
+    pub fn count_voxels(&self, mut f: impl FnMut(Voxel, u32)) {
+        for subchunk in self {
+            subchunk.count_voxels(&mut f);
+        }
+    }
+
     pub fn fill_air(&mut self) {
         for subchunk in self {
             subchunk.fill_air();
@@ -336,6 +373,26 @@ impl<A: Allocator + Clone> Chunk<A> {
         self.zip = Some(zip);
     }
 
+    /// Discard the cached zip, so it is rebuilt the next time the chunk is sent.
+    /// Must be called whenever the voxels of a loaded chunk are modified, since
+    /// it also bumps the revision clients compare their cached copies against,
+    /// and marks the chunk as needing a save.
+    pub fn clear_cached_zip(&mut self) {
+        self.zip = None;
+        self.revision += 1;
+        self.needs_save = true;
+    }
+
+    /// Whether the chunk changed since it was last saved, see `mark_saved`.
+    pub fn needs_save(&self) -> bool {
+        self.needs_save
+    }
+
+    /// Mark the chunk as saved, once its zip was taken to be written to disk.
+    pub fn mark_saved(&mut self) {
+        self.needs_save = false;
+    }
+
     pub fn zip(&self, alg: Algorithm, level: ZipLevel) -> ZippedChunk {
         let result = match alg {
             Algorithm::Zstd => self.init_and_zip::<zip::ZstdZipper>(level),
@@ -353,21 +410,28 @@ impl<A: Allocator + Clone> Chunk<A> {
     }
 
     pub fn zip_into<Z: Zipper>(&self, zipper: &mut Z) {
-        // get header data
-        let mask = self.non_empty_mask();
-        let header = ChunkHeader {
+        // write headers
+        zipper.put_as(&self.zip_header());
+        // write subchunks
+        self.zip_subchunks_into(zipper);
+    }
+
+    /// The header written in front of the subchunks of the chunk when it is zipped.
+    pub(crate) fn zip_header(&self) -> ChunkHeader {
+        ChunkHeader {
             origin: self.origin,
             height: self.height,
             format: ChunkFormat::LATEST as u16,
-            length: mask.0.count_ones() as u16,
+            length: self.non_empty_mask().0.count_ones() as u16,
             state: self.state as u16,
             _unused: 0,
             revision: self.revision,
-        };
-        // write headers
-        zipper.put_as(&header);
-        // write subchunks
-        for y in mask {
+        }
+    }
+
+    /// Write the non-empty subchunks of the chunk, without the header.
+    pub(crate) fn zip_subchunks_into<Z: Zipper>(&self, zipper: &mut Z) {
+        for y in self.non_empty_mask() {
             self.get_subchunk(y).unwrap().zip(zipper);
         }
     }
@@ -388,6 +452,24 @@ impl<A: Allocator + Clone> Chunk<A> {
         }
     }
 
+    /// Get the cached zip, or re-zip it with the compressed subchunks
+    /// of an identical chunk in the `ZipCache`, if there is one.
+    pub fn get_cached_or_zip_with(
+        &mut self,
+        cache: &mut ZipCache,
+        alg: Algorithm,
+        level: ZipLevel,
+    ) -> ZippedChunk {
+        if let Some(cached) = &self.zip {
+            cached.clone()
+        } else {
+            self.needs_save = true;
+            let ret = cache.zip(self, alg, level);
+            self.zip = Some(ret.clone());
+            ret
+        }
+    }
+
     #[cfg(test)]
     pub(crate) fn assert_voxels_eq(&self, other: &Self) {
         // check for equivalent y ranges
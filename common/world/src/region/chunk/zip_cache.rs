@@ -0,0 +1,139 @@
+//! Many newly generated chunks, like those of oceans and flat plains, have the exact same
+//! voxels, only at different origins. The `ZipCache` compresses the subchunks of such chunks
+//! once, keyed by the hash of their content, and shares the compressed frame between them.
+//!
+//! The chunk header, which has the origin and revision of each chunk, is zipped in a frame of
+//! its own in front of the shared frame. Zstd unzips frames in a row as their data in a row,
+//! so chunks zipped this way are read like any other. Lz4 can't do this, so chunks zipped
+//! with it don't go through the cache.
+
+use std::{
+    alloc::Allocator,
+    collections::VecDeque,
+    hash::{DefaultHasher, Hash, Hasher},
+};
+
+use bevy::prelude::*;
+use bytes::Bytes;
+use fxhash::FxHashMap;
+use zip::{Algorithm, ZipLevel, Zipper, ZstdFrame, ZstdFrameZipper};
+
+use super::Chunk;
+use crate::region::format::ZippedChunk;
+
+/// Number of frames kept by default, the oldest are dropped first.
+pub const DEFAULT_CAPACITY: usize = 1024;
+
+/// Compressed subchunks of chunks, keyed by the hash of their content.
+#[derive(Resource)]
+pub struct ZipCache {
+    frames: FxHashMap<(u64, ZipLevel), ZstdFrame>,
+
+    /// Keys of the frames, from oldest to newest.
+    order: VecDeque<(u64, ZipLevel)>,
+    capacity: usize,
+
+    /// Reused for the unzipped subchunks of each chunk.
+    scratch: Vec<u8>,
+}
+
+impl Default for ZipCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY)
+    }
+}
+
+impl ZipCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            frames: FxHashMap::default(),
+            order: VecDeque::new(),
+            capacity: capacity.max(1),
+            scratch: Vec::new(),
+        }
+    }
+
+    /// Number of frames in the cache.
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.frames.clear();
+        self.order.clear();
+    }
+
+    /// Zip a chunk, reusing the compressed subchunks of an identical chunk zipped before.
+    pub fn zip<A: Allocator + Clone>(
+        &mut self,
+        chunk: &Chunk<A>,
+        alg: Algorithm,
+        level: ZipLevel,
+    ) -> ZippedChunk {
+        if alg != Algorithm::Zstd {
+            return chunk.zip(alg, level);
+        }
+
+        let mut collector = Collector::init(std::mem::take(&mut self.scratch), level);
+        chunk.zip_subchunks_into(&mut collector);
+        let body = collector.0;
+
+        let mut hasher = DefaultHasher::new();
+        body.hash(&mut hasher);
+        let key = (hasher.finish(), level);
+
+        let frame = match self.frames.get(&key) {
+            // the size is compared too, to make a collision of hashes even less likely.
+            Some(frame) if frame.unzipped_size() == body.len() => frame.clone(),
+            _ => {
+                let frame = ZstdFrame::zip(&body, level);
+                self.insert(key, frame.clone());
+                frame
+            }
+        };
+
+        let mut zipper = ZstdFrameZipper::new(Vec::new());
+        zipper.put_frame(&ZstdFrame::zip(
+            bytemuck::bytes_of(&chunk.zip_header()),
+            ZipLevel::Low,
+        ));
+        zipper.put_frame(&frame);
+
+        self.scratch = body;
+        ZippedChunk(Bytes::from(zipper.finish()))
+    }
+
+    fn insert(&mut self, key: (u64, ZipLevel), frame: ZstdFrame) {
+        if self.frames.insert(key, frame).is_none() {
+            self.order.push_back(key);
+        }
+        while self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.frames.remove(&oldest);
+            }
+        }
+    }
+}
+
+/// Collects the data put into it as is.
+struct Collector(Vec<u8>);
+
+impl Zipper for Collector {
+    fn init(mut buf: Vec<u8>, _: ZipLevel) -> Self {
+        buf.clear();
+        Self(buf)
+    }
+
+    #[inline]
+    fn put(&mut self, data: &[u8]) {
+        self.0.extend_from_slice(data);
+    }
+
+    fn finish(self) -> Vec<u8> {
+        self.0
+    }
+}
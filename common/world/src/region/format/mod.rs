@@ -171,3 +171,27 @@ pub struct ChunkReadSuccess {
     pub origin: IVec3,
     pub format: ChunkFormat,
 }
+
+#[derive(thiserror::Error, Debug)]
+pub enum ChunkDecodeError {
+    #[error(transparent)]
+    Unzip(#[from] UnzipError),
+
+    #[error(transparent)]
+    Read(#[from] ChunkReadError),
+}
+
+/// Unzip chunk data and read it into an empty World of this height,
+/// the same as the client reads the chunk data it receives.
+///
+/// Entry point for fuzzing, since chunk data is received from untrusted servers.
+pub fn decode_chunk_bytes(
+    data: &[u8],
+    max_y: i32,
+    min_y: i32,
+) -> Result<(World, ChunkReadSuccess), ChunkDecodeError> {
+    let mut world = World::new(max_y, min_y);
+    let unzip = UnzippedChunk::unzip(data)?;
+    let success = world.read_unzipped_chunk(unzip, true)?;
+    Ok((world, success))
+}
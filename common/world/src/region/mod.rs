@@ -388,6 +388,18 @@ impl<A: Allocator + Clone> Region<A> {
     pub const fn subchunks_mut(&mut self) -> &mut [Subchunk<A>] {
         unsafe { std::slice::from_raw_parts_mut(self.subchunks.as_ptr(), self.num_subchunks) }
     }
+
+    /// Approximate number of bytes allocated for the Region,
+    /// including the data of every subchunk.
+    pub fn heap_size(&self) -> usize {
+        let data = self
+            .subchunks()
+            .iter()
+            .map(Subchunk::heap_size)
+            .sum::<usize>();
+        data + std::mem::size_of::<Subchunk<A>>() * self.num_subchunks
+            + std::mem::size_of::<Chunk<A>>() * 256
+    }
 }
 
 impl<A: Allocator + Clone> Drop for Region<A> {
@@ -508,18 +520,19 @@ impl From<Vec3> for RegionId {
 
 #[cfg(test)]
 mod tests {
-    use bevy::math::{IVec3, Vec3Swizzles, ivec3};
+    use bevy::math::{IVec2, IVec3, Vec3Swizzles, ivec3};
     use zip::{Algorithm, UnzippedSpan, ZipLevel, Zipper, ZstdZipper};
 
     use crate::{
         World,
         region::{
             alloc::init_region_alloc,
-            format::{ChunkFormat, UnzippedChunk},
+            chunk::zip_cache::ZipCache,
+            format::{ChunkFormat, UnzippedChunk, decode_chunk_bytes},
         },
     };
 
-    use super::{Region, Voxel};
+    use super::{Light, Region, Voxel};
 
     #[test]
     fn region() {
@@ -531,6 +544,21 @@ mod tests {
         assert_eq!(region.replace_voxel(pos, Voxel(99)), Some(Voxel(88)));
     }
 
+    #[test]
+    fn heap_size_grows_with_data() {
+        let mut region = Region::new(ivec3(0, 0, 0), 128);
+        let empty = region.heap_size();
+
+        let pos = ivec3(40, 70, 300);
+        assert!(region.set_voxel(pos, Voxel(5)));
+        let with_voxel = region.heap_size();
+        assert!(with_voxel > empty);
+
+        // writing a non-uniform light value allocates the lightmap.
+        assert!(region.set_light(pos, Light::AMBIENT_NONE));
+        assert!(region.heap_size() >= with_voxel + 32768 * std::mem::size_of::<Light>());
+    }
+
     #[test]
     fn iter_subchunks_in_chunk() {
         let mut origin = ivec3(416, -32, 384);
@@ -603,4 +631,76 @@ mod tests {
             .unwrap()
             .assert_voxels_eq(&w2.get_chunk(origin.xz()).unwrap())
     }
+
+    #[test]
+    fn decode_chunk_bytes_rejects_garbage() {
+        let mut world = World::new(96, -32);
+        let origin = IVec3::new(-32, -32, 96);
+        world.get_or_insert_region(origin.xz());
+        world.set_voxel(origin + 5, Voxel(3));
+        let data = world
+            .get_chunk(origin.xz())
+            .unwrap()
+            .zip(Algorithm::Zstd, ZipLevel::default());
+
+        let (decoded, success) = decode_chunk_bytes(&data, 96, -32).unwrap();
+        assert_eq!(success.origin, origin);
+        assert_eq!(decoded.get_voxel(origin + 5), Some(Voxel(3)));
+
+        // truncated or corrupted data is an error, not a panic.
+        assert!(decode_chunk_bytes(&data[..data.len() / 2], 96, -32).is_err());
+        assert!(decode_chunk_bytes(&[0xFF; 64], 96, -32).is_err());
+        assert!(decode_chunk_bytes(&[], 96, -32).is_err());
+    }
+
+    #[test]
+    fn modified_chunks_need_a_save() {
+        let mut world = World::new(96, -32);
+        world.get_or_insert_region(IVec2::ZERO);
+        let chunk = world.get_chunk_mut(IVec2::ZERO).unwrap();
+        assert!(!chunk.needs_save());
+
+        chunk.clear_cached_zip();
+        assert!(chunk.needs_save());
+        chunk.mark_saved();
+        assert!(!chunk.needs_save());
+
+        // a zip cached from a region file is already saved.
+        let data = chunk.zip(Algorithm::Zstd, ZipLevel::default());
+        chunk.set_cached_zip(data);
+        assert!(!chunk.needs_save());
+    }
+
+    #[test]
+    fn identical_chunks_share_zipped_subchunks() {
+        let mut world = World::new(96, -32);
+        let origins = [ivec3(0, -32, 0), ivec3(32, -32, 0), ivec3(-512, -32, 64)];
+        for origin in origins {
+            world.get_or_insert_region(origin.xz() & !511);
+            for y in 0..20 {
+                world.set_voxel(origin + ivec3(y % 32, y, 7), Voxel(y as u16 + 1));
+            }
+        }
+
+        let mut cache = ZipCache::new(16);
+        for origin in origins {
+            let chunk = world.get_chunk_mut(origin.xz()).unwrap();
+            let data = chunk.get_cached_or_zip_with(&mut cache, Algorithm::Zstd, ZipLevel::High);
+            let (decoded, success) = decode_chunk_bytes(&data.0, 96, -32).unwrap();
+            assert_eq!(success.origin, origin);
+            decoded
+                .get_chunk(origin.xz())
+                .unwrap()
+                .assert_voxels_eq(chunk);
+        }
+        assert_eq!(cache.len(), 1);
+
+        // other content or levels get frames of their own.
+        world.set_voxel(ivec3(1, 1, 1), Voxel(40));
+        let chunk = world.get_chunk_mut(IVec2::ZERO).unwrap();
+        chunk.clear_cached_zip();
+        chunk.get_cached_or_zip_with(&mut cache, Algorithm::Zstd, ZipLevel::High);
+        cache.zip(chunk, Algorithm::Zstd, ZipLevel::Low);
+        assert_eq!(cache.len(), 3);
+    }
 }
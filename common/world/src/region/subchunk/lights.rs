@@ -59,6 +59,16 @@ impl<A: Allocator> Lights<A> {
         }
     }
 
+    /// Number of bytes allocated for the lightmap.
+    /// Uniform lightmaps point to a static buffer, and don't allocate.
+    pub const fn heap_size(&self) -> usize {
+        if self.0.is_owned() {
+            std::mem::size_of::<[Light; 32768]>()
+        } else {
+            0
+        }
+    }
+
     /// Get the light value of the voxel at this index.
     /// i must be less than 32768.
     #[inline(always)]
@@ -145,6 +145,19 @@ impl<A: Allocator + Clone> Subchunk<A> {
         self.voxels.is_empty()
     }
 
+    /// Call `f` with each voxel value in the subchunk and the number of
+    /// voxels with that value, counted from the palette indices.
+    pub fn count_voxels(&self, mut f: impl FnMut(Voxel, u32)) {
+        self.voxels.count(|v, n| f(Voxel(v), n));
+    }
+
+    /// Approximate number of bytes allocated for the voxels and lights of the subchunk.
+    pub fn heap_size(&self) -> usize {
+        self.voxels.palette_as_bytes().len()
+            + self.voxels.words_as_bytes().len()
+            + self.lights.heap_size()
+    }
+
     /// Assign a value of 0 to all voxels in the subchunk.
     pub fn fill_air(&mut self) {
         self.voxels.set_empty();
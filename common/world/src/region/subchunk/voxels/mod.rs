@@ -123,6 +123,25 @@ impl<A: Allocator> Voxels<A> {
         }
     }
 
+    /// Call `f` with each value present in the array and the number of voxels
+    /// with that value. Palette indices are counted first, so each value in the
+    /// palette is only looked up once. Values that were in the palette but no
+    /// longer occur are skipped.
+    pub fn count(&self, mut f: impl FnMut(u16, u32)) {
+        if self.is_empty() {
+            f(0, 32768);
+            return;
+        }
+
+        let mut counts = vec![0u32; self.palette.len()];
+        self.words.count_indices(&mut counts);
+        for (pidx, count) in counts.into_iter().enumerate() {
+            if count != 0 {
+                f(unsafe { self.palette.get(pidx) }, count);
+            }
+        }
+    }
+
     #[inline(always)]
     unsafe fn find_or_insert(&mut self, v: u16) -> usize {
         unsafe {
@@ -311,4 +330,40 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn count() {
+        let palette = Voxels::empty(&Global);
+        let mut counts = Vec::new();
+        palette.count(|v, n| counts.push((v, n)));
+        assert_eq!(counts, [(0, 32768)]);
+
+        for (len, bpi) in [(15, 4), (255, 8), (4095, 16)] {
+            let mut palette = Voxels::empty(&Global);
+            let mut expected = vec![0; len + 1];
+            unsafe {
+                for i in 0..32768 {
+                    palette.set(i, (i % len) as u16 + 1);
+                }
+                // 1 is overwritten, so it stays in the palette but isn't counted.
+                for i in (0..32768).step_by(len) {
+                    palette.set(i, 0);
+                }
+                for i in 0..32768 {
+                    expected[palette.get(i) as usize] += 1;
+                }
+            }
+            assert_eq!(palette.bpi(), bpi);
+            assert_eq!(expected[1], 0);
+
+            let mut counts = vec![0; len + 1];
+            let mut calls = 0;
+            palette.count(|v, n| {
+                counts[v as usize] += n;
+                calls += 1;
+            });
+            assert_eq!(counts, expected);
+            assert_eq!(calls, len);
+        }
+    }
 }
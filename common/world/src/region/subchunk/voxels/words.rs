@@ -130,6 +130,27 @@ impl Words {
         }
     }
 
+    /// Add the number of times each palette index occurs to `counts`.
+    /// `counts` must be at least as long as the palette.
+    /// In the case of BPI=0, every voxel has the palette index 0.
+    pub fn count_indices(&self, counts: &mut [u32]) {
+        if self.is_bpi0() {
+            counts[0] += 32768;
+            return;
+        }
+
+        let bpi = 1 << self.bpi_mul;
+        let words = unsafe { std::slice::from_raw_parts(self.words.as_ptr(), self.num_words()) };
+        for &word in words {
+            // walk the indices of the word from the lowest bits up.
+            let mut offs = 0;
+            while offs < usize::BITS {
+                counts[(word >> offs) & self.bpi_mask] += 1;
+                offs += bpi;
+            }
+        }
+    }
+
     pub unsafe fn grow_bpi0_to_bpi4<A: Allocator>(&mut self, alloc: &A) {
         debug_assert_eq!(
             self.bpi_mask, 0x0,
@@ -0,0 +1,119 @@
+//! Time of day, and the positions of the sun and moon.
+//!
+//! The server advances the time every tick and sends it to clients,
+//! which advance it between syncs at the same rate.
+
+use std::f32::consts::TAU;
+
+use bevy::{
+    ecs::resource::Resource,
+    math::{Quat, Vec3},
+};
+
+/// Rate the world time advances, the same as the default server tick rate.
+pub const TICKS_PER_SECOND: u64 = 30;
+
+/// Length of a full day and night, 20 minutes.
+pub const DAY_LENGTH: u64 = 20 * 60 * TICKS_PER_SECOND;
+
+/// How far the path of the sun leans away from straight overhead, in radians.
+const SUN_TILT: f32 = 0.35;
+
+/// Fraction of the sky light taken away at midnight.
+/// Less than 1, so surfaces under the open sky are still dimly lit by the moon.
+const MAX_SKY_DARKNESS: f32 = 0.8;
+
+/// Ticks since the world was created.
+#[derive(Resource, Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub struct WorldTime {
+    pub tick: u64,
+}
+
+impl WorldTime {
+    /// Number of full days that have passed.
+    pub const fn day(&self) -> u64 {
+        self.tick / DAY_LENGTH
+    }
+
+    /// Fraction of the current day that has passed, from 0 to 1.
+    /// 0 is sunrise, 0.25 is noon, 0.5 is sunset and 0.75 is midnight.
+    pub fn time_of_day(&self) -> f32 {
+        (self.tick % DAY_LENGTH) as f32 / DAY_LENGTH as f32
+    }
+
+    /// Direction from the world to the sun.
+    /// Rises in +X, and sets in -X.
+    pub fn sun_direction(&self) -> Vec3 {
+        self.sky_rotation() * Vec3::X
+    }
+
+    /// Direction from the world to the moon, which is always opposite the sun.
+    pub fn moon_direction(&self) -> Vec3 {
+        -self.sun_direction()
+    }
+
+    /// Rotation of the sky around the axis the sun travels around.
+    /// Rotates +X to the direction of the sun, and can be used to turn the stars.
+    pub fn sky_rotation(&self) -> Quat {
+        let axis = Vec3::new(0.0, -SUN_TILT.sin(), SUN_TILT.cos());
+        Quat::from_axis_angle(axis, self.time_of_day() * TAU)
+    }
+
+    /// How bright the sky is, from 0 at night to 1 during the day.
+    /// Fades while the sun is near the horizon.
+    pub fn daylight(&self) -> f32 {
+        let t = ((self.sun_direction().y + 0.2) / 0.4).clamp(0.0, 1.0);
+        t * t * (3.0 - 2.0 * t)
+    }
+
+    /// Fraction of the sky light taken away for the time of day, from 0 during the day
+    /// to `MAX_SKY_DARKNESS` at night. Block light, like that of torches, isn't darkened.
+    pub fn sky_darkness(&self) -> f32 {
+        (1.0 - self.daylight()) * MAX_SKY_DARKNESS
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(time_of_day: f32) -> WorldTime {
+        WorldTime {
+            tick: (time_of_day * DAY_LENGTH as f32) as u64,
+        }
+    }
+
+    #[test]
+    fn sun_path() {
+        assert!(at(0.0).sun_direction().abs_diff_eq(Vec3::X, 1e-5));
+        assert!(at(0.25).sun_direction().y > 0.9);
+        assert!(at(0.5).sun_direction().x < -0.99);
+        assert!(at(0.75).sun_direction().y < -0.9);
+        assert!(at(0.75).moon_direction().y > 0.9);
+    }
+
+    #[test]
+    fn daylight() {
+        assert_eq!(at(0.25).daylight(), 1.0);
+        assert_eq!(at(0.75).daylight(), 0.0);
+        let sunrise = at(0.0).daylight();
+        assert!(sunrise > 0.0 && sunrise < 1.0);
+    }
+
+    #[test]
+    fn sky_darkness() {
+        assert_eq!(at(0.25).sky_darkness(), 0.0);
+        assert_eq!(at(0.75).sky_darkness(), MAX_SKY_DARKNESS);
+        let dusk = at(0.5).sky_darkness();
+        assert!(dusk > 0.0 && dusk < MAX_SKY_DARKNESS);
+    }
+
+    #[test]
+    fn days_wrap() {
+        let time = WorldTime {
+            tick: DAY_LENGTH * 3 + DAY_LENGTH / 4,
+        };
+        assert_eq!(time.day(), 3);
+        assert_eq!(time.time_of_day(), 0.25);
+    }
+}
@@ -8,7 +8,7 @@ use std::{
 };
 
 use bytemuck::Pod;
-use bytes::{Buf, TryGetError};
+use bytes::{Buf, Bytes, TryGetError};
 
 #[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
 #[repr(u32)]
@@ -28,7 +28,7 @@ impl Algorithm {
     }
 }
 
-#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default)]
 pub enum ZipLevel {
     Low,
     #[default]
@@ -37,6 +37,18 @@ pub enum ZipLevel {
     Ultra,
 }
 
+impl ZipLevel {
+    /// Compression level of Zstd at this level.
+    const fn zstd_level(self) -> i32 {
+        match self {
+            ZipLevel::Low => 1,
+            ZipLevel::Medium => 3,
+            ZipLevel::High => 5,
+            ZipLevel::Ultra => 7,
+        }
+    }
+}
+
 /// A span that was created by decompressing a region of memory,
 /// which can now be shared in multiple places.
 /// Spans are always aligned to a multiple of 8.
@@ -220,16 +232,7 @@ impl Zipper for ZstdZipper {
         buf.extend(&[0, 0, 0, 0]);
         buf.extend(&(Algorithm::Zstd as u32).to_le_bytes());
         Self {
-            encoder: zstd::Encoder::new(
-                buf,
-                match level {
-                    ZipLevel::Low => 1,
-                    ZipLevel::Medium => 3,
-                    ZipLevel::High => 5,
-                    ZipLevel::Ultra => 7,
-                },
-            )
-            .unwrap(),
+            encoder: zstd::Encoder::new(buf, level.zstd_level()).unwrap(),
             unzipped_size: 0,
         }
     }
@@ -254,11 +257,68 @@ impl ZstdZipper {
     }
 }
 
-pub struct Lz4Zipper {
-    encoder: lz4_flex::frame::FrameEncoder<Vec<u8>>,
+/// Data compressed with Zstd on its own, which can be shared by every
+/// stream that contains the same data, without compressing it again.
+#[derive(Clone)]
+pub struct ZstdFrame {
+    data: Bytes,
+    unzipped_size: usize,
+}
+
+impl ZstdFrame {
+    pub fn zip(data: &[u8], level: ZipLevel) -> Self {
+        Self {
+            data: Bytes::from(zstd::bulk::compress(data, level.zstd_level()).unwrap()),
+            unzipped_size: data.len(),
+        }
+    }
+
+    /// Size of the data before it was zipped.
+    pub fn unzipped_size(&self) -> usize {
+        self.unzipped_size
+    }
+}
+
+/// Builds a Zstd stream out of frames that were zipped separately.
+/// Zstd unzips frames in a row as their data in a row, so the stream
+/// is unzipped like any other.
+pub struct ZstdFrameZipper {
+    buf: Vec<u8>,
     unzipped_size: usize,
 }
 
+impl ZstdFrameZipper {
+    pub fn new(mut buf: Vec<u8>) -> Self {
+        buf.clear();
+        // reserve 8 bytes for unwrapped_size (u32) and algorithm (u32)
+        buf.extend(&[0, 0, 0, 0]);
+        buf.extend(&(Algorithm::Zstd as u32).to_le_bytes());
+        Self {
+            buf,
+            unzipped_size: 0,
+        }
+    }
+
+    pub fn put_frame(&mut self, frame: &ZstdFrame) {
+        self.buf.extend_from_slice(&frame.data);
+        self.unzipped_size += frame.unzipped_size;
+    }
+
+    pub fn finish(self) -> Vec<u8> {
+        let mut buf = self.buf;
+        let unzipped_size = (self.unzipped_size as u32).to_le_bytes();
+        buf[0..4].copy_from_slice(&unzipped_size);
+        buf
+    }
+}
+
+/// Lz4 is unzipped as a single block, so the data is collected
+/// and compressed all at once when the zipper is finished.
+pub struct Lz4Zipper {
+    buf: Vec<u8>,
+    data: Vec<u8>,
+}
+
 impl Zipper for Lz4Zipper {
     fn init(mut buf: Vec<u8>, _: ZipLevel) -> Self {
         buf.clear();
@@ -266,20 +326,20 @@ impl Zipper for Lz4Zipper {
         buf.extend(&[0, 0, 0, 0]);
         buf.extend(&(Algorithm::Lz4 as u32).to_le_bytes());
         Self {
-            encoder: lz4_flex::frame::FrameEncoder::new(buf),
-            unzipped_size: 0,
+            buf,
+            data: Vec::new(),
         }
     }
 
     #[inline]
     fn put(&mut self, data: &[u8]) {
-        self.unzipped_size += data.len();
-        self.encoder.write_all(data).unwrap()
+        self.data.extend_from_slice(data);
     }
 
     fn finish(self) -> Vec<u8> {
-        let mut buf = self.encoder.finish().unwrap();
-        let unzipped_size = (self.unzipped_size as u32).to_le_bytes();
+        let mut buf = self.buf;
+        buf.extend(lz4_flex::block::compress(&self.data));
+        let unzipped_size = (self.data.len() as u32).to_le_bytes();
         buf[0..4].copy_from_slice(&unzipped_size);
         buf
     }
@@ -289,7 +349,9 @@ impl Zipper for Lz4Zipper {
 mod tests {
     use bytemuck::{Pod, Zeroable};
 
-    use crate::{UnzippedSpan, ZipLevel, Zipper, ZstdZipper};
+    use crate::{
+        Lz4Zipper, UnzippedSpan, ZipLevel, Zipper, ZstdFrame, ZstdFrameZipper, ZstdZipper,
+    };
 
     #[test]
     fn zstd() {
@@ -341,4 +403,36 @@ mod tests {
         // check thing 2
         assert_eq!(reader.take_as::<Thing>().unwrap(), thing2);
     }
+
+    #[test]
+    fn lz4() {
+        let data = (0..1000_u32).map(|i| (i % 7) as u8).collect::<Vec<_>>();
+
+        let mut zipper = Lz4Zipper::init(Vec::new(), ZipLevel::default());
+        zipper.put(&data[..300]);
+        zipper.put(&data[300..]);
+        let buf = zipper.finish();
+
+        let span = UnzippedSpan::unzip(&buf, &std::alloc::Global).unwrap();
+        assert_eq!(span.reader().as_slice(), data);
+    }
+
+    #[test]
+    fn zstd_frames() {
+        let header = [9_u8; 32];
+        let body = (0..4096_u32).map(|i| (i % 13) as u8).collect::<Vec<_>>();
+        let shared = ZstdFrame::zip(&body, ZipLevel::High);
+
+        for _ in 0..2 {
+            let mut zipper = ZstdFrameZipper::new(Vec::new());
+            zipper.put_frame(&ZstdFrame::zip(&header, ZipLevel::Low));
+            zipper.put_frame(&shared);
+            let buf = zipper.finish();
+
+            let span = UnzippedSpan::unzip(&buf, &std::alloc::Global).unwrap();
+            let mut reader = span.reader();
+            assert_eq!(reader.take_slice(32).unwrap(), header);
+            assert_eq!(reader.as_slice(), body);
+        }
+    }
 }
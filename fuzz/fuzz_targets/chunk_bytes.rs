@@ -0,0 +1,9 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use world::region::format::decode_chunk_bytes;
+
+// chunk data received from a server, read into a world of the default height.
+fuzz_target!(|data: &[u8]| {
+    let _ = decode_chunk_bytes(data, 256, -128);
+});
@@ -0,0 +1,8 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use protocol::codec::decode_tcp_frame;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = decode_tcp_frame(data);
+});
@@ -0,0 +1,8 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use protocol::codec::decode_udp_datagram;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = decode_udp_datagram(data);
+});
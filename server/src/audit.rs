@@ -0,0 +1,167 @@
+//! The audit log of the world, see `data::fs::audit`.
+//!
+//! Every command run in the chat or the console, and every block edit rejected in a
+//! protected region, is recorded with its issuer and arguments. `/auditlog` shows the
+//! most recent entries, and only operators may run it.
+
+use std::{
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use bevy::prelude::*;
+use data::{
+    fs::audit::{self, AuditEntry, AuditFile, AuditKind},
+    text::rich::{Rgb, RichText},
+};
+use fxhash::FxHashMap;
+
+use crate::world::loader::WorldLoader;
+
+/// Entries shown by /auditlog without a count.
+const DEFAULT_SHOWN: usize = 10;
+
+/// Most entries shown by /auditlog.
+const MAX_SHOWN: usize = 50;
+
+/// Time a violation of a player at one position isn't recorded again.
+/// Players holding the mouse button send an edit every few ticks.
+const VIOLATION_COOLDOWN: Duration = Duration::from_secs(30);
+
+/// Most violations recorded for one player in `VIOLATION_COOLDOWN`, at any positions.
+const MAX_VIOLATIONS: usize = 16;
+
+/// The audit log of the world, opened on startup.
+#[derive(Resource, Default)]
+pub struct AuditLog {
+    /// Region directory the log is in.
+    dir: PathBuf,
+
+    /// None if the log couldn't be opened, then nothing is recorded.
+    file: Option<AuditFile>,
+
+    /// When each player last had a violation recorded at a position.
+    violations: FxHashMap<(String, IVec3), Instant>,
+}
+
+impl AuditLog {
+    /// Record an entry. Failures are logged, they don't stop the command or edit.
+    pub fn record(&mut self, entry: AuditEntry) {
+        let Some(file) = &mut self.file else {
+            return;
+        };
+
+        if let Err(e) = file.append(&entry) {
+            error!(
+                "[S170] Failed to record '{} {}' in the audit log with error: '{e}'",
+                entry.issuer, entry.action
+            );
+        }
+    }
+
+    /// Record a command run by a player or the console.
+    pub fn record_command(&mut self, issuer: impl Into<String>, command: &str) {
+        let mut args = command.split_whitespace();
+        let name = args.next().unwrap_or_default();
+        self.record(AuditEntry::now(AuditKind::Command, issuer, name, args));
+    }
+
+    /// Record a block edit rejected in a protected region, unless the player had one
+    /// recorded at the position recently, or too many at other positions.
+    pub fn record_violation(&mut self, issuer: String, action: &str, pos: IVec3) {
+        let now = Instant::now();
+        self.violations
+            .retain(|_, time| now.duration_since(*time) < VIOLATION_COOLDOWN);
+
+        let key = (issuer, pos);
+        let recent = self
+            .violations
+            .keys()
+            .filter(|(other, _)| *other == key.0)
+            .count();
+        if recent >= MAX_VIOLATIONS || self.violations.contains_key(&key) {
+            return;
+        }
+
+        let coords = [pos.x, pos.y, pos.z].map(|c| c.to_string());
+        self.record(AuditEntry::now(
+            AuditKind::ProtectionViolation,
+            key.0.clone(),
+            action,
+            coords,
+        ));
+        self.violations.insert(key, now);
+    }
+}
+
+/// Open the audit log in the region directory of the world.
+pub fn open_audit_log(loader: Res<WorldLoader>, mut log: ResMut<AuditLog>) {
+    let dir = loader.region_dir().to_path_buf();
+    match AuditFile::open(&dir) {
+        Ok(file) => log.file = Some(file),
+        Err(e) => error!(
+            "[S171] Failed to open the audit log in '{}', nothing will be recorded: {e}",
+            dir.display()
+        ),
+    }
+    log.dir = dir;
+}
+
+/// Reply to /auditlog, which shows the most recent entries of the audit log, of every
+/// player or only one. Takes an optional count first, then an optional player name.
+pub fn auditlog_reply<'a>(log: &AuditLog, mut args: impl Iterator<Item = &'a str>) -> RichText {
+    let mut first = args.next();
+    let count = match first.map(str::parse::<usize>) {
+        Some(Ok(count)) => {
+            first = None;
+            count.clamp(1, MAX_SHOWN)
+        }
+        _ => DEFAULT_SHOWN,
+    };
+
+    // names in chat have spaces, like "Player 1".
+    let issuer = first.into_iter().chain(args).collect::<Vec<_>>().join(" ");
+    let issuer = Some(issuer.as_str()).filter(|issuer| !issuer.is_empty());
+
+    let entries = match audit::read_recent(&log.dir, issuer, count) {
+        Ok(entries) => entries,
+        Err(e) => {
+            error!(
+                "[S172] Failed to read the audit log in '{}': {e}",
+                log.dir.display()
+            );
+            return RichText::translate("chat.command.auditlog.error", []).color(Rgb::RED);
+        }
+    };
+
+    if entries.is_empty() {
+        return RichText::translate("chat.command.auditlog.none", []);
+    }
+
+    let mut reply =
+        RichText::translate("chat.command.auditlog", [entries.len().to_string().into()]);
+    for entry in entries {
+        let time = entry.time_string().into();
+        let issuer = RichText::plain(entry.issuer).color(Rgb::GOLD);
+        let line = match entry.kind {
+            AuditKind::Command => {
+                let command = format!("/{} {}", entry.action, entry.args.join(" "));
+                RichText::translate(
+                    "chat.command.auditlog.command",
+                    [time, issuer, command.trim_end().into()],
+                )
+            }
+            AuditKind::ProtectionViolation => RichText::translate(
+                "chat.command.auditlog.violation",
+                [
+                    time,
+                    issuer,
+                    entry.action.into(),
+                    entry.args.join(", ").into(),
+                ],
+            ),
+        };
+        reply = reply.push("\n").push(line);
+    }
+    reply
+}
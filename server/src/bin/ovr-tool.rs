@@ -0,0 +1,383 @@
+//! Inspect and repair region files (`.ovr`) while the server isn't running.
+//!
+//! ```text
+//! ovr-tool info <file>                  print the header, segments and free list
+//! ovr-tool verify <file>                check the header and that every chunk decodes
+//! ovr-tool extract <file> <chunk> <out> write the zipped data of a chunk to a file
+//! ovr-tool insert <file> <chunk> <in>   replace the zipped data of a chunk
+//! ovr-tool repair <file>                drop broken chunks and rebuild the free list
+//! ```
+//!
+//! A chunk is either its index in the region (0..256), or its "x,z" position in the world.
+//! Files are backed up to "<file>.bak" before they're modified.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use bevy::math::{Vec3Swizzles, ivec2};
+use server::world::loader::{Header, RegionProblem, chunk_origin};
+use world::region::{chunk::ChunkId, format::decode_chunk_bytes};
+
+const USAGE: &str = "usage:
+  ovr-tool info <file>
+  ovr-tool verify <file>
+  ovr-tool extract <file> <chunk> <out>
+  ovr-tool insert <file> <chunk> <in>
+  ovr-tool repair <file>
+
+<chunk> is an index in 0..256, or a position 'x,z' in the world.";
+
+fn main() -> ExitCode {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let args = args.iter().map(|arg| arg.as_str()).collect::<Vec<_>>();
+    let result = match args.as_slice() {
+        ["info", file] => info(Path::new(file)),
+        ["verify", file] => verify(Path::new(file)),
+        ["extract", file, chunk, out] => extract(Path::new(file), chunk, Path::new(out)),
+        ["insert", file, chunk, input] => insert(Path::new(file), chunk, Path::new(input)),
+        ["repair", file] => repair(Path::new(file)),
+        _ => {
+            eprintln!("{USAGE}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match result {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(e) => {
+            eprintln!("{e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// A region file, read into memory.
+struct RegionFile {
+    path: PathBuf,
+    data: Vec<u8>,
+    header: Header,
+}
+
+impl RegionFile {
+    fn open(path: &Path) -> Result<Self, String> {
+        let data = fs::read(path)
+            .map_err(|e| format!("Failed to read '{}' with error: '{e}'", path.display()))?;
+        let Some(header) = Header::read(&data) else {
+            return Err(format!(
+                "'{}' is {} bytes, too small to be a region file.",
+                path.display(),
+                data.len()
+            ));
+        };
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            data,
+            header,
+        })
+    }
+
+    /// The zipped data of the chunk at this index, if its segment is in the file.
+    fn chunk(&self, index: usize) -> Option<&[u8]> {
+        let segment = self.header.segment(index);
+        if segment.padding() as usize > (segment.count() as usize) << 12 {
+            return None;
+        }
+        self.data.get(segment.byte_range())
+    }
+
+    /// Check that the zipped data of a chunk decodes to the chunk at this index.
+    fn check_chunk(&self, index: usize, data: &[u8]) -> Result<(), String> {
+        let min_y = self.header.origin().y;
+        let height = self.header.height();
+        if height <= 0 || height & 31 != 0 || min_y & 31 != 0 {
+            return Err(format!(
+                "Region has an invalid height of {height} at y {min_y}, chunks can't be decoded."
+            ));
+        }
+
+        let expected = chunk_origin(self.header.origin().xz(), index);
+        match decode_chunk_bytes(data, min_y + height, min_y) {
+            Err(e) => Err(format!("Chunk {index} at {expected} failed to decode: {e}")),
+            Ok((_, success)) if success.origin.xz() != expected => Err(format!(
+                "Chunk {index} has the origin {}, expected {expected}.",
+                success.origin.xz()
+            )),
+            Ok(_) => Ok(()),
+        }
+    }
+
+    /// Copy the file to "<file>.bak", then write the header and data back to the file.
+    fn save(&mut self) -> Result<(), String> {
+        let backup = self.path.with_extension("ovr.bak");
+        fs::copy(&self.path, &backup).map_err(|e| {
+            format!(
+                "Failed to back up to '{}' with error: '{e}'",
+                backup.display()
+            )
+        })?;
+
+        self.data.resize(self.header.size() as usize, 0);
+        self.data[..Header::SIZE].copy_from_slice(self.header.as_bytes());
+
+        // written under another name first, so the file is never half-written.
+        let temp = self.path.with_extension("ovr.tmp");
+        fs::write(&temp, &self.data)
+            .and_then(|_| fs::rename(&temp, &self.path))
+            .map_err(|e| {
+                format!(
+                    "Failed to write '{}' with error: '{e}'",
+                    self.path.display()
+                )
+            })?;
+
+        println!(
+            "Saved '{}', backup at '{}'.",
+            self.path.display(),
+            backup.display()
+        );
+        Ok(())
+    }
+}
+
+/// Parse a chunk index, or the position of a chunk in the region.
+fn parse_chunk(header: &Header, chunk: &str) -> Result<usize, String> {
+    if let Some((x, z)) = chunk.split_once(',') {
+        let (Ok(x), Ok(z)) = (x.trim().parse::<i32>(), z.trim().parse::<i32>()) else {
+            return Err(format!("'{chunk}' is not a position 'x,z'."));
+        };
+
+        let id = ChunkId::new(ivec2(x, z));
+        if id.to_region_id().as_ivec2() != header.origin().xz() {
+            return Err(format!(
+                "Chunk at {} is not in the region at {}.",
+                id.as_ivec2(),
+                header.origin().xz()
+            ));
+        }
+        Ok(id.to_chunk_idx())
+    } else {
+        match chunk.parse::<usize>() {
+            Ok(index) if index < 256 => Ok(index),
+            _ => Err(format!("'{chunk}' is not a chunk index in 0..256.")),
+        }
+    }
+}
+
+fn info(path: &Path) -> Result<bool, String> {
+    let file = RegionFile::open(path)?;
+    let header = &file.header;
+    let origin = header.origin();
+
+    println!(
+        "file:          {} ({} bytes)",
+        path.display(),
+        file.data.len()
+    );
+    println!(
+        "magic:         {:#x}{}",
+        header.magic(),
+        if header.is_magic_valid() {
+            ""
+        } else {
+            " (invalid)"
+        }
+    );
+    println!("version:       {}", header.version());
+    println!("origin:        {origin}");
+    println!("height:        {}", header.height());
+    println!("created at:    {}", header.created_at());
+    println!("modified at:   {}", header.last_modified_at());
+    println!(
+        "total pages:   {} ({} bytes)",
+        header.total_pages(),
+        header.size()
+    );
+    println!("free head:     {}", header.free_list_head());
+    println!("empty cursor:  {}", header.empty_cursor());
+
+    println!();
+    println!("segments:");
+    println!("  index  origin            start  count  padding  bytes");
+    let mut used = 0;
+    for index in 0..256 {
+        let segment = header.segment(index);
+        if segment.is_empty() {
+            continue;
+        }
+
+        used += 1;
+        let chunk = chunk_origin(origin.xz(), index);
+        println!(
+            "  {index:>5}  {:<16}  {:>5}  {:>5}  {:>7}  {:>5}",
+            format!("{},{}", chunk.x, chunk.y),
+            segment.start(),
+            segment.count(),
+            segment.padding(),
+            segment.len(),
+        );
+    }
+    println!("  {used} of 256 chunks have data.");
+
+    println!();
+    println!("free list:");
+    match header.free_blocks() {
+        Ok(blocks) => {
+            println!("  index  start  count   next");
+            for (index, block) in blocks {
+                println!(
+                    "  {index:>5}  {:>5}  {:>5}  {:>5}",
+                    block.start(),
+                    block.count(),
+                    block.next()
+                );
+            }
+        }
+        Err(problem) => println!("  {problem}"),
+    }
+
+    Ok(true)
+}
+
+fn verify(path: &Path) -> Result<bool, String> {
+    let file = RegionFile::open(path)?;
+    let mut errors = file
+        .header
+        .problems(file.data.len() as u64)
+        .iter()
+        .map(RegionProblem::to_string)
+        .collect::<Vec<_>>();
+
+    let mut chunks = 0;
+    for index in 0..256 {
+        if file.header.segment(index).is_empty() {
+            continue;
+        }
+
+        chunks += 1;
+        match file.chunk(index) {
+            None => errors.push(format!("Chunk {index} is not in the file.")),
+            Some(data) => {
+                if let Err(e) = file.check_chunk(index, data) {
+                    errors.push(e);
+                }
+            }
+        }
+    }
+
+    for error in &errors {
+        println!("{error}");
+    }
+    println!(
+        "Checked {chunks} chunks of '{}', found {} problems.",
+        path.display(),
+        errors.len()
+    );
+    Ok(errors.is_empty())
+}
+
+fn extract(path: &Path, chunk: &str, out: &Path) -> Result<bool, String> {
+    let file = RegionFile::open(path)?;
+    let index = parse_chunk(&file.header, chunk)?;
+    if file.header.segment(index).is_empty() {
+        return Err(format!("Chunk {index} has no data."));
+    }
+
+    let data = file
+        .chunk(index)
+        .ok_or_else(|| format!("Chunk {index} is not in the file."))?;
+    fs::write(out, data)
+        .map_err(|e| format!("Failed to write '{}' with error: '{e}'", out.display()))?;
+
+    println!(
+        "Wrote {} bytes of chunk {index} to '{}'.",
+        data.len(),
+        out.display()
+    );
+    Ok(true)
+}
+
+fn insert(path: &Path, chunk: &str, input: &Path) -> Result<bool, String> {
+    let mut file = RegionFile::open(path)?;
+    let index = parse_chunk(&file.header, chunk)?;
+    let data = fs::read(input)
+        .map_err(|e| format!("Failed to read '{}' with error: '{e}'", input.display()))?;
+
+    // inserting data that can't be loaded would only make things worse.
+    file.check_chunk(index, &data)?;
+
+    let segment = file
+        .header
+        .append_segment(index, data.len())
+        .map_err(|problem| problem.to_string())?;
+    file.data.resize(file.header.size() as usize, 0);
+    file.data[segment.byte_range()].copy_from_slice(&data);
+    file.save()?;
+
+    println!("Inserted {} bytes as chunk {index}.", data.len());
+    Ok(true)
+}
+
+fn repair(path: &Path) -> Result<bool, String> {
+    let mut file = RegionFile::open(path)?;
+    if !file.header.is_magic_valid() {
+        return Err(format!(
+            "{}, refusing to repair something that may not be a region file.",
+            RegionProblem::BadMagic(file.header.magic())
+        ));
+    }
+
+    let problems = file.header.problems(file.data.len() as u64).len();
+
+    // drop the chunks whose data is missing or broken.
+    let mut kept: Vec<(u16, u16, usize)> = Vec::new();
+    let mut dropped = 0;
+    for index in 0..256 {
+        let segment = file.header.segment(index);
+        if segment.is_empty() {
+            continue;
+        }
+
+        let check = match file.chunk(index) {
+            None => Err(format!("Chunk {index} is not in the file.")),
+            Some(data) => file.check_chunk(index, data),
+        };
+
+        // segments that overlap a kept chunk can't both be right, keep the first.
+        let (start, end) = (
+            segment.start(),
+            segment.start().saturating_add(segment.count()),
+        );
+        let check = check.and_then(|_| match kept.iter().find(|k| k.0 < end && start < k.1) {
+            Some(other) => Err(format!("Chunk {index} overlaps chunk {}.", other.2)),
+            None => Ok(()),
+        });
+
+        match check {
+            Ok(()) => kept.push((start, end, index)),
+            Err(e) => {
+                println!("Dropped chunk {index}: {e}");
+                file.header.clear_segment(index);
+                dropped += 1;
+            }
+        }
+    }
+
+    file.header
+        .rebuild_free_list()
+        .map_err(|problem| problem.to_string())?;
+
+    // the file only needs to be as large as its last segment.
+    file.data.truncate(file.header.size() as usize);
+    file.save()?;
+
+    println!(
+        "Kept {} chunks, dropped {dropped}, the header had {problems} problems.",
+        kept.len()
+    );
+    Ok(true)
+}
@@ -0,0 +1,286 @@
+//! Render top-down maps of the world a seed generates, to tune the worldgen settings of the
+//! data directory and data packs without starting a server and flying around in the client.
+//!
+//! ```text
+//! worldgen-preview [--seed <seed>] [--data <dir>]... [--center <x,z>] [--size <blocks>]
+//!                  [--height <min_y,max_y>] [--out <dir>] [--fast]
+//! ```
+//!
+//! Chunks are generated just like the server generates them, and their biomes and the
+//! height of their surface are written to `biomes.png` and `height.png` in the output folder,
+//! one pixel per column, with north at the top. `--fast` only picks the biome and height of
+//! each column without generating chunks, so caves that open at the surface aren't shown.
+
+use std::{
+    path::{Path, PathBuf},
+    process::ExitCode,
+    time::Instant,
+};
+
+use bevy::math::{IVec2, ivec2};
+use data::{
+    blocks::{Block, def::read_block_defs, register_blocks},
+    blockstates::BlockState,
+    fs::save::WorldInfo,
+    registry::{Registry, RegistryId},
+    tags::{TagDefs, Tags},
+    worldgen::{
+        WorldgenError, biomes::BiomeFeature, carvers::CarverFeature, ores::OreFeature,
+        read_worldgen,
+    },
+};
+use image::{GrayImage, Luma, Rgb, RgbImage};
+use server::world::generator::{WorldGenerator, biomes::Biomes, carvers::Carvers, ores::Ores};
+use world::{BiomeId, World};
+
+const USAGE: &str = "usage:
+  worldgen-preview [options]
+
+options:
+  --seed <seed>           seed of the world (default: 0)
+  --data <dir>            data directory or data pack to read, repeat to layer data packs
+                          over it, first one wins (default: the assets of the client)
+  --center <x,z>          column at the center of the maps (default: 0,0)
+  --size <blocks>         width and height of the maps, rounded up to chunks (default: 1024)
+  --height <min_y,max_y>  bottom and top of the world (default: -128,256)
+  --out <dir>             folder the maps are written to (default: .)
+  --fast                  only pick biomes and heights, without generating chunks";
+
+/// Settings of a preview, from the command line.
+struct Options {
+    seed: u64,
+    dirs: Vec<PathBuf>,
+    center: IVec2,
+    size: i32,
+    min_y: i32,
+    max_y: i32,
+    out: PathBuf,
+    fast: bool,
+}
+
+fn main() -> ExitCode {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let options = match Options::parse(&args) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{e}\n\n{USAGE}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match preview(&options) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+impl Options {
+    fn parse(args: &[String]) -> Result<Self, String> {
+        let defaults = WorldInfo::default();
+        let mut options = Self {
+            seed: 0,
+            dirs: Vec::new(),
+            center: IVec2::ZERO,
+            size: 1024,
+            min_y: defaults.min_y,
+            max_y: defaults.max_y,
+            out: PathBuf::from("."),
+            fast: false,
+        };
+
+        let mut args = args.iter().map(|arg| arg.as_str());
+        while let Some(arg) = args.next() {
+            if arg == "--fast" {
+                options.fast = true;
+                continue;
+            }
+
+            let Some(value) = args.next() else {
+                return Err(format!("'{arg}' is missing its value."));
+            };
+
+            match arg {
+                "--seed" => {
+                    // seeds are shown signed by "/seed", but saved unsigned by the generator.
+                    options.seed = match value.parse::<i64>() {
+                        Ok(seed) => seed as u64,
+                        Err(_) => parse(arg, value)?,
+                    }
+                }
+                "--data" => options.dirs.push(PathBuf::from(value)),
+                "--center" => options.center = parse_pair(arg, value)?,
+                "--size" => options.size = parse::<i32>(arg, value)?.max(1),
+                "--height" => {
+                    let pair = parse_pair(arg, value)?;
+                    if pair.x & 31 != 0 || pair.y & 31 != 0 || pair.x >= pair.y {
+                        return Err(format!(
+                            "'{arg}' must be two multiples of 32, the bottom first, not '{value}'."
+                        ));
+                    }
+                    (options.min_y, options.max_y) = (pair.x, pair.y);
+                }
+                "--out" => options.out = PathBuf::from(value),
+                _ => return Err(format!("Unknown option '{arg}'.")),
+            }
+        }
+
+        if options.dirs.is_empty() {
+            let assets = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../client/assets");
+            options.dirs.push(assets);
+        }
+
+        Ok(options)
+    }
+
+    /// Chunks covered by the maps, from the north-west corner.
+    fn area(&self) -> (IVec2, IVec2) {
+        let chunks = (self.size + 31) / 32;
+        let min = (self.center - chunks * 16) & !31;
+        (min, IVec2::splat(chunks))
+    }
+}
+
+fn parse<T: std::str::FromStr>(arg: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("'{value}' is not a valid value for '{arg}'."))
+}
+
+fn parse_pair(arg: &str, value: &str) -> Result<IVec2, String> {
+    let Some((x, z)) = value.split_once(',') else {
+        return Err(format!(
+            "'{arg}' must be two numbers like '0,0', not '{value}'."
+        ));
+    };
+    Ok(ivec2(parse(arg, x.trim())?, parse(arg, z.trim())?))
+}
+
+/// The worldgen settings of the data directories, resolved like the server resolves them.
+struct WorldgenData {
+    biomes: Biomes,
+    carvers: Carvers,
+    ores: Ores,
+}
+
+impl WorldgenData {
+    /// Read the blocks, block tags and worldgen settings of the directories,
+    /// printing the files that couldn't be read.
+    fn read(dirs: &[PathBuf]) -> Self {
+        let (defs, errors) = read_block_defs(dirs);
+        print_errors(errors);
+
+        let mut states = Registry::<BlockState>::new();
+        let mut blocks = Registry::<Block>::new();
+        register_blocks(&defs, &mut states, &mut blocks, |_| 0);
+
+        let (tag_defs, errors) = TagDefs::read(dirs, "blocks");
+        print_errors(errors);
+        let (tags, errors): (Tags<BlockState>, _) = tag_defs.resolve(|name| {
+            blocks
+                .get_by_name(name)
+                .map(|block| block.states.clone().map(RegistryId))
+        });
+        print_errors(errors);
+
+        let biomes = read_worldgen(dirs, "biomes", BiomeFeature::parse);
+        let carvers = read_worldgen(dirs, "carvers", CarverFeature::parse);
+        let ores = read_worldgen(dirs, "ores", OreFeature::parse);
+        print_errors::<WorldgenError>(biomes.1.into_iter().chain(carvers.1).chain(ores.1));
+
+        Self {
+            biomes: Biomes::resolve(&biomes.0, &states),
+            carvers: Carvers::resolve(&carvers.0, &states),
+            ores: Ores::resolve(&ores.0, &states, &blocks, &tags),
+        }
+    }
+}
+
+fn print_errors<E: std::fmt::Display>(errors: impl IntoIterator<Item = E>) {
+    for err in errors {
+        eprintln!("{err}");
+    }
+}
+
+fn preview(options: &Options) -> Result<(), String> {
+    let data = WorldgenData::read(&options.dirs);
+    let generator = WorldGenerator::new(options.seed);
+    let (min, chunks) = options.area();
+    let size = (chunks * 32).as_uvec2();
+
+    println!(
+        "Generating {} chunks around {} with the seed {}, {} biomes, {} carvers and {} ores.",
+        chunks.x * chunks.y,
+        options.center,
+        options.seed as i64,
+        data.biomes.0.len(),
+        data.carvers.0.len(),
+        data.ores.0.len(),
+    );
+
+    let started = Instant::now();
+    let mut biome_map = RgbImage::new(size.x, size.y);
+    let mut height_map = GrayImage::new(size.x, size.y);
+    let mut counts = vec![0_u64; data.biomes.0.len()];
+    let mut world = World::new(options.max_y, options.min_y);
+    let range = (options.max_y - options.min_y) as f32;
+
+    let mut plot = |xz: IVec2, biome: BiomeId, height: i32| {
+        let px = (xz - min).as_uvec2();
+        let color = data
+            .biomes
+            .get(biome)
+            .map_or([0; 3], |biome| biome.feature.color);
+        if let Some(count) = counts.get_mut(biome.0 as usize) {
+            *count += 1;
+        }
+        let shade = ((height - options.min_y) as f32 / range * 255.0).clamp(0.0, 255.0);
+        biome_map.put_pixel(px.x, px.y, Rgb(color));
+        height_map.put_pixel(px.x, px.y, Luma([shade as u8]));
+    };
+
+    for cz in 0..chunks.y {
+        for cx in 0..chunks.x {
+            let origin = min + ivec2(cx, cz) * 32;
+            if options.fast {
+                for xz in (0..32 * 32).map(|i| origin + ivec2(i % 32, i / 32)) {
+                    let height = generator.surface_height(xz);
+                    plot(xz, data.biomes.pick(generator.climate(xz), height), height);
+                }
+                continue;
+            }
+
+            world.get_or_insert_region(origin & !511);
+            let chunk = world.get_chunk_mut(origin).unwrap();
+            generator.generate(chunk, &data.biomes, &data.carvers, &data.ores);
+            for xz in chunk.area() {
+                let column = chunk.get_column(xz);
+                plot(xz, column.land_biome, column.height as i32);
+            }
+            // voxels aren't needed once the columns are plotted.
+            chunk.unload();
+        }
+    }
+
+    println!("Generated in {:.1?}.", started.elapsed());
+    for (biome, count) in data.biomes.0.iter().zip(&counts) {
+        let share = *count as f64 / (size.x * size.y) as f64 * 100.0;
+        println!("  {:>5.1}% {}", share, biome.name);
+    }
+
+    save(&options.out, "biomes.png", |path| biome_map.save(path))?;
+    save(&options.out, "height.png", |path| height_map.save(path))
+}
+
+fn save(
+    dir: &Path,
+    name: &str,
+    write: impl FnOnce(&Path) -> image::ImageResult<()>,
+) -> Result<(), String> {
+    let path = dir.join(name);
+    write(&path).map_err(|e| format!("Failed to write '{}' with error: '{e}'", path.display()))?;
+    println!("Wrote '{}'.", path.display());
+    Ok(())
+}
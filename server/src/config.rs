@@ -1,23 +1,435 @@
+//! Settings of the server that can be changed while it runs, read from `server.toml`.
+//!
+//! The file is checked for changes every second. When its settings change, the `Config`
+//! resource is replaced and a `ConfigChanged` message is sent, which the systems that use
+//! a setting react to. Settings that can't change while the server runs, like its address
+//! and seed, are set on the `ServerPlugin` instead.
+//!
+//! ```toml
+//! draw_distance = 64
+//! sim_distance = 32
+//! chunk_sends_per_tick = 5
+//! compression_level = "medium"
+//! adaptive_compression = true
+//! motd = { text = "Welcome!", color = "gold" }
+//! tick_rate = 30
+//! max_catch_up_ticks = 10
+//! operators = ["Player 1"]
+//! autosave_interval = 30
+//! max_concurrent_region_loads = 4
+//! generation_quota = 64
+//! generation_aging_interval = 8
+//!
+//! [watchdog]
+//! threshold = 5
+//! dump_dir = "crash-reports"
+//! abort = false
+//! save = true
+//!
+//! [logging]
+//! level = "info"
+//! filters = { "server::net" = "debug" }
+//! max_files = 5
+//! max_file_size = 16777216
+//! ```
+
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+
 use bevy::prelude::*;
+use data::{
+    logging::{LOG_ENV, LOGS_DIR, LogConfig, LogLevels, LoggingPlugin},
+    text::rich::RichText,
+};
+use serde::{Deserialize, Serialize};
+use zip::ZipLevel;
 
-#[derive(Resource)]
+use crate::{events::ConfigChanged, watchdog::WatchdogConfig};
+
+/// Name of the config file, in the working directory of a dedicated server.
+pub const CONFIG_FILE: &str = "server.toml";
+
+/// Highest tick rate the server can be set to.
+const MAX_TICK_RATE: u32 = 240;
+
+/// Time between checks of the config file for changes.
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Resource, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Config {
-    /// A radius describing how close a player needs to
-    /// be to a chunk for them to receive voxel updates
-    /// from that chunk.
-    pub draw_distance: i32,
+    /// A radius in blocks describing how close a player needs
+    /// to be to a chunk for them to be sent that chunk.
+    pub draw_distance: u32,
+
+    /// A radius in blocks describing how close a player needs to
+    /// be to a chunk for entity updates from that chunk to be sent.
+    /// Can't be larger than the draw distance.
+    pub sim_distance: u32,
+
+    /// Most chunks sent to each player per tick.
+    pub chunk_sends_per_tick: u32,
+
+    /// Compression level of chunks sent to players and written to region
+    /// files, one of "low", "medium", "high" or "ultra".
+    pub compression_level: String,
+
+    /// Whether chunks sent to players are compressed less while the server is
+    /// busy, up to the `compression_level`, see `world::compression`.
+    pub adaptive_compression: bool,
+
+    /// Message of the day, sent in the chat to players when they join.
+    /// If it isn't set, the message of the `ServerPlugin` is used.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub motd: Option<RichText>,
+
+    /// Ticks the server runs per second. The time of day advances at the same
+    /// speed at any tick rate.
+    pub tick_rate: u32,
+
+    /// Most ticks run right after each other to catch up after a stall, see `tick`.
+    /// Ticks missed beyond that are skipped.
+    pub max_catch_up_ticks: u32,
 
-    /// A radius describing how close a player needs to be
-    /// to a chunk for entity updates from that chunk to
-    /// be sent.
-    pub sim_distance: i32,
+    /// Players that may run admin commands like /protect, named like in chat.
+    pub operators: Vec<String>,
+
+    /// Seconds between writes of the chunks that changed to the region files, see
+    /// `world::save`. They are also written when the server stops.
+    pub autosave_interval: u32,
+
+    /// Most region files read at once, see `world::loader`.
+    pub max_concurrent_region_loads: u32,
+
+    /// Most chunks queued for generation for one player at once, see `world::generator`.
+    /// Chunks requested beyond it are requested again once the queue drains.
+    pub generation_quota: u32,
+
+    /// Ticks between priority boosts of the chunks queued for generation, so chunks
+    /// behind a moving player are generated eventually.
+    pub generation_aging_interval: u32,
+
+    /// What the watchdog does when a tick hangs, see `watchdog`.
+    pub watchdog: WatchdogConfig,
+
+    /// Log levels and files, see `data::logging`. The levels are applied when the file
+    /// changes, the files only when the server starts. `RUST_LOG` overrides the levels
+    /// when it is set.
+    pub logging: LogConfig,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
-            draw_distance: 8,
-            sim_distance: 4,
+            draw_distance: 64,
+            sim_distance: 32,
+            chunk_sends_per_tick: 5,
+            compression_level: "medium".into(),
+            adaptive_compression: true,
+            motd: None,
+            tick_rate: 30,
+            max_catch_up_ticks: 10,
+            operators: Vec::new(),
+            autosave_interval: 30,
+            max_concurrent_region_loads: 4,
+            generation_quota: 64,
+            generation_aging_interval: 8,
+            watchdog: WatchdogConfig::default(),
+            logging: LogConfig::default(),
+        }
+    }
+}
+
+impl Config {
+    /// Read the config from a toml file.
+    pub fn read(path: &Path) -> io::Result<Self> {
+        let text = fs::read_to_string(path)?;
+        toml::from_str(&text).map_err(io::Error::other)
+    }
+
+    /// Write the config to a toml file.
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        let text = toml::to_string_pretty(self).map_err(io::Error::other)?;
+        fs::write(path, text)
+    }
+
+    /// The compression level, or None if it isn't one of the levels.
+    pub fn zip_level(&self) -> Option<ZipLevel> {
+        match self.compression_level.as_str() {
+            "low" => Some(ZipLevel::Low),
+            "medium" => Some(ZipLevel::Medium),
+            "high" => Some(ZipLevel::High),
+            "ultra" => Some(ZipLevel::Ultra),
+            _ => None,
+        }
+    }
+
+    /// Length of a tick at the tick rate.
+    pub fn tick_duration(&self) -> Duration {
+        Duration::from_secs(1) / self.tick_rate.max(1)
+    }
+
+    /// Replace settings that can't be used with ones that can, returning what was wrong.
+    fn validate(&mut self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if self.zip_level().is_none() {
+            problems.push(format!(
+                "compression_level '{}' is not one of 'low', 'medium', 'high' or 'ultra', using 'medium'.",
+                self.compression_level
+            ));
+            self.compression_level = "medium".into();
+        }
+
+        if self.sim_distance > self.draw_distance {
+            problems.push(format!(
+                "sim_distance {} is larger than draw_distance {}, using {}.",
+                self.sim_distance, self.draw_distance, self.draw_distance
+            ));
+            self.sim_distance = self.draw_distance;
+        }
+
+        if self.chunk_sends_per_tick == 0 {
+            problems.push("chunk_sends_per_tick can't be 0, using 1.".into());
+            self.chunk_sends_per_tick = 1;
+        }
+
+        if !(1..=MAX_TICK_RATE).contains(&self.tick_rate) {
+            let clamped = self.tick_rate.clamp(1, MAX_TICK_RATE);
+            problems.push(format!(
+                "tick_rate {} is not between 1 and {MAX_TICK_RATE}, using {clamped}.",
+                self.tick_rate
+            ));
+            self.tick_rate = clamped;
+        }
+
+        if self.max_concurrent_region_loads == 0 {
+            problems.push("max_concurrent_region_loads can't be 0, using 1.".into());
+            self.max_concurrent_region_loads = 1;
+        }
+
+        if self.generation_aging_interval == 0 {
+            problems.push("generation_aging_interval can't be 0, using 1.".into());
+            self.generation_aging_interval = 1;
+        }
+
+        if self.watchdog.threshold == 0 {
+            problems.push("watchdog.threshold can't be 0, using 1.".into());
+            self.watchdog.threshold = 1;
+        }
+
+        problems
+    }
+
+    /// Names of the settings that are different in `other`.
+    pub fn changes(&self, other: &Self) -> Vec<&'static str> {
+        let mut changes = Vec::new();
+        if self.draw_distance != other.draw_distance {
+            changes.push("draw_distance");
+        }
+        if self.sim_distance != other.sim_distance {
+            changes.push("sim_distance");
+        }
+        if self.chunk_sends_per_tick != other.chunk_sends_per_tick {
+            changes.push("chunk_sends_per_tick");
+        }
+        if self.compression_level != other.compression_level {
+            changes.push("compression_level");
+        }
+        if self.adaptive_compression != other.adaptive_compression {
+            changes.push("adaptive_compression");
+        }
+        if self.motd != other.motd {
+            changes.push("motd");
+        }
+        if self.tick_rate != other.tick_rate {
+            changes.push("tick_rate");
+        }
+        if self.max_catch_up_ticks != other.max_catch_up_ticks {
+            changes.push("max_catch_up_ticks");
+        }
+        if self.operators != other.operators {
+            changes.push("operators");
+        }
+        if self.autosave_interval != other.autosave_interval {
+            changes.push("autosave_interval");
+        }
+        if self.max_concurrent_region_loads != other.max_concurrent_region_loads {
+            changes.push("max_concurrent_region_loads");
+        }
+        if self.generation_quota != other.generation_quota {
+            changes.push("generation_quota");
+        }
+        if self.generation_aging_interval != other.generation_aging_interval {
+            changes.push("generation_aging_interval");
+        }
+        if self.watchdog != other.watchdog {
+            changes.push("watchdog");
+        }
+        if self.logging != other.logging {
+            changes.push("logging");
+        }
+        changes
+    }
+}
+
+/// The file the `Config` is read from, and when it was last read.
+#[derive(Resource)]
+pub struct ConfigFile {
+    /// Path of the file, or None if the config isn't read from a file.
+    path: Option<PathBuf>,
+
+    /// Modification time of the file when it was last read.
+    modified: Option<SystemTime>,
+
+    /// When the file is checked for changes next.
+    next_check: Instant,
+
+    /// Message of the day used when the file doesn't set one.
+    default_motd: Option<RichText>,
+}
+
+impl ConfigFile {
+    pub fn new(path: Option<PathBuf>, default_motd: Option<RichText>) -> Self {
+        Self {
+            path,
+            modified: None,
+            next_check: Instant::now(),
+            default_motd,
         }
     }
+
+    /// Path of the file the config is read from, if there is one.
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    fn read(&self, path: &Path) -> io::Result<Config> {
+        let mut config = Config::read(path)?;
+        for problem in config.validate() {
+            warn!("[S155] In '{}': {problem}", path.display());
+        }
+
+        if config.motd.is_none() {
+            config.motd = self.default_motd.clone();
+        }
+        Ok(config)
+    }
+}
+
+/// Read the config file on startup, or write the default config if it doesn't exist yet.
+/// A `ConfigChanged` is always sent, so every setting is applied once.
+pub fn load_config(
+    mut file: ResMut<ConfigFile>,
+    mut config: ResMut<Config>,
+    mut changed_evs: MessageWriter<ConfigChanged>,
+) {
+    let previous = config.clone();
+    if let Some(path) = file.path.clone() {
+        if path.exists() {
+            match file.read(&path) {
+                Ok(read) => *config = read,
+                Err(e) => error!(
+                    "[S154] Failed to read the config '{}', using the defaults: {e}",
+                    path.display()
+                ),
+            }
+        } else {
+            let mut defaults = config.clone();
+            defaults.motd = None;
+            match defaults.write(&path) {
+                Ok(()) => info!("Wrote the default config to '{}'.", path.display()),
+                Err(e) => warn!(
+                    "[S156] Failed to write the default config to '{}': {e}",
+                    path.display()
+                ),
+            }
+        }
+
+        file.modified = fs::metadata(&path).and_then(|m| m.modified()).ok();
+    }
+
+    changed_evs.write(ConfigChanged { previous });
+}
+
+/// Read the config file again when it was modified, and send a
+/// `ConfigChanged` if any of its settings changed.
+pub fn watch_config(
+    mut file: ResMut<ConfigFile>,
+    mut config: ResMut<Config>,
+    mut changed_evs: MessageWriter<ConfigChanged>,
+) {
+    let now = Instant::now();
+    if now < file.next_check {
+        return;
+    }
+    file.next_check = now + CHECK_INTERVAL;
+
+    let Some(path) = file.path.clone() else {
+        return;
+    };
+
+    // a file that was removed keeps the settings it had.
+    let Ok(modified) = fs::metadata(&path).and_then(|m| m.modified()) else {
+        return;
+    };
+    if file.modified == Some(modified) {
+        return;
+    }
+    file.modified = Some(modified);
+
+    let read = match file.read(&path) {
+        Ok(read) => read,
+        Err(e) => {
+            // editors may save in more than one write, so it's read again on the next change.
+            error!(
+                "[S154] Failed to read the config '{}', keeping the current settings: {e}",
+                path.display()
+            );
+            return;
+        }
+    };
+
+    let changes = config.changes(&read);
+    if changes.is_empty() {
+        return;
+    }
+
+    info!(
+        "Config '{}' changed: [{}]",
+        path.display(),
+        changes.join(", ")
+    );
+    let previous = std::mem::replace(&mut *config, read);
+    changed_evs.write(ConfigChanged { previous });
+}
+
+/// Logging configured by the config file, written to the logs directory in the
+/// working directory. The file is read here too, since logging starts before it is loaded.
+pub fn logging_plugin(path: &Path) -> LoggingPlugin {
+    LoggingPlugin {
+        config: Config::read(path)
+            .map(|config| config.logging)
+            .unwrap_or_default(),
+        dir: Some(LOGS_DIR.into()),
+        ..default()
+    }
+}
+
+/// Apply the log levels of the config when they change.
+pub fn apply_log_levels(config: Res<Config>, levels: Option<ResMut<LogLevels>>) {
+    // RUST_LOG overrides the config, and the integrated server logs through the client.
+    let Some(mut levels) = levels.filter(|_| env::var(LOG_ENV).is_err()) else {
+        return;
+    };
+    if levels.directives() == config.logging.directives() {
+        return;
+    }
+
+    match levels.apply(&config.logging) {
+        Ok(()) => info!("Log levels changed to '{}'.", levels.directives()),
+        Err(e) => warn!("[S169] Failed to apply the log levels of the config: {e}"),
+    }
 }
@@ -1,6 +1,9 @@
 use bevy::prelude::*;
+use data::items::ItemStack;
 use protocol::{ExitCode, session::Session};
-use world::region::RegionId;
+use world::{region::RegionId, voxel::Voxel};
+
+use crate::config::Config;
 
 #[derive(Message)]
 pub struct PlayerJoined {
@@ -67,3 +70,63 @@ pub enum SubscInterest {
     /// visually.
     Simulation,
 }
+
+/// A block was broken, and replaced with air.
+#[derive(Message)]
+pub struct BlockBroken {
+    pub pos: IVec3,
+
+    /// The voxel that was broken.
+    pub voxel: Voxel,
+
+    /// The player that broke the block, if a player did.
+    pub session: Option<Session>,
+
+    /// Chance that the block drops its loot, 1 for blocks broken by players.
+    pub drop_chance: f32,
+}
+
+/// An explosion at a position, carried out by `world::explosion::process_explosions`.
+#[derive(Message, Copy, Clone, Debug)]
+pub struct Explosion {
+    pub center: Vec3,
+
+    /// Distance from the center at which the explosion stops breaking blocks.
+    /// Entities are damaged up to twice as far.
+    pub radius: f32,
+
+    /// Strength of the explosion. Higher power breaks harder blocks, and does more damage.
+    pub power: f32,
+}
+
+/// An entity died, and should drop the loot of its kind.
+/// Nothing sends this yet, there are no entities that can die.
+#[derive(Message)]
+pub struct EntityDied {
+    /// Name of the kind of entity, like "zombie".
+    pub kind: String,
+    pub pos: Vec3,
+
+    /// Whether a player killed the entity.
+    pub by_player: bool,
+}
+
+/// Items dropped by a loot table, to be spawned at a position.
+#[derive(Message)]
+pub struct LootDropped {
+    pub pos: Vec3,
+    pub stacks: Vec<ItemStack>,
+}
+
+/// Read the data packs again, replacing the recipes, loot tables and tags.
+/// Sent by the `/reload` command.
+#[derive(Message)]
+pub struct ReloadData;
+
+/// The settings of the `Config` resource changed, because `server.toml` was modified.
+/// Also sent once on startup, after the config was first read.
+#[derive(Message)]
+pub struct ConfigChanged {
+    /// The settings before the change.
+    pub previous: Config,
+}
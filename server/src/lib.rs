@@ -0,0 +1,364 @@
+#![feature(allocator_api)]
+
+//! The server as a library, so it can be run as a dedicated server (see main.rs)
+//! or embedded in the client as an integrated singleplayer server.
+
+use std::{
+    io,
+    net::SocketAddr,
+    path::PathBuf,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread::{self, JoinHandle},
+};
+
+use bevy::{
+    app::{App, TaskPoolPlugin},
+    asset::AssetPlugin,
+    diagnostic::DiagnosticsPlugin,
+    prelude::*,
+    state::app::StatesPlugin,
+    time::TimePlugin,
+    transform::TransformPlugin,
+};
+
+use ::world::World;
+use data::{
+    fs::save::{DATA_PACKS_DIR, REGIONS_DIR, WorldInfo},
+    loot::LootTable,
+    registry::Registry,
+    text::rich::RichText,
+};
+use protocol::packet::SentBy;
+
+use crate::{
+    config::{Config, ConfigFile},
+    events::{
+        BlockBroken, ConfigChanged, EntityDied, Explosion, LootDropped, PlayerJoined, PlayerLeft,
+        RegionLoaded, ReloadData, SubscChanged,
+    },
+    loot::LootRng,
+    net::{InitialMessageContent, Server, channel::Channel},
+    packs::DataDirs,
+    tick::{TickClock, TickStats},
+    world::{generator::WorldGenerator, loader::WorldLoader},
+};
+
+pub mod audit;
+pub mod config;
+pub mod events;
+pub mod loot;
+pub mod net;
+pub mod packs;
+pub mod player;
+pub mod queues;
+pub mod recipes;
+pub mod startup;
+pub mod states;
+pub mod tick;
+pub mod watchdog;
+pub mod world;
+
+#[cfg(feature = "tui")]
+pub mod tui;
+
+/// All server logic, without any of the bevy plugins that
+/// are needed to run it. (those are added by the caller)
+pub struct ServerPlugin {
+    /// Address to bind to on startup.
+    /// A port of 0 will bind to an ephemeral port.
+    pub addr: SocketAddr,
+
+    /// Directory where region files are stored.
+    pub region_dir: Option<PathBuf>,
+
+    /// Seed of the world generator.
+    /// If none is provided, the seed saved in the `world.toml` next to the region
+    /// directory is used, or a random seed is saved there.
+    pub seed: Option<u64>,
+
+    /// Top of the world, exclusive.
+    pub max_y: i32,
+
+    /// Bottom of the world, inclusive.
+    pub min_y: i32,
+
+    /// Seed of the rng that rolls loot tables, for tests that need the same drops every run.
+    /// A random seed is used if none is provided.
+    pub loot_seed: Option<u64>,
+
+    /// Directory blocks, recipes and loot tables are read from,
+    /// laid out like the assets directory of the client.
+    /// Defaults to the assets of the client in this repository.
+    pub data_dir: PathBuf,
+
+    /// Directory of the data packs of the world, each folder in it is laid out like
+    /// `data_dir` and replaces the files of `data_dir` it has.
+    pub data_packs_dir: Option<PathBuf>,
+
+    /// Message of the day, sent in the chat to players when they join.
+    /// Replaced by the message of the config file, if it sets one.
+    pub motd: Option<RichText>,
+
+    /// File of the settings that can change while the server runs, see `config`.
+    /// It is watched for changes, and written with the defaults if it doesn't exist.
+    /// The defaults are used if none is provided.
+    pub config_file: Option<PathBuf>,
+
+    /// Whether every player is an operator, like the only player of a singleplayer world.
+    /// Otherwise only the `operators` of the config are.
+    pub everyone_operator: bool,
+}
+
+impl Default for ServerPlugin {
+    fn default() -> Self {
+        Self {
+            addr: "127.0.0.1:51423".parse().unwrap(),
+            region_dir: None,
+            seed: None,
+            max_y: 256,
+            min_y: -128,
+            loot_seed: None,
+            data_dir: PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../client/assets"),
+            data_packs_dir: None,
+            motd: None,
+            config_file: None,
+            everyone_operator: false,
+        }
+    }
+}
+
+impl Plugin for ServerPlugin {
+    #[rustfmt::skip]
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(net::BindAddr(self.addr))
+            .add_plugins((
+                net::ServerNetPlugin,
+                world::ServerWorldPlugin,
+                player::ServerPlayerPlugin,
+            ))
+            // initialize resources
+            .insert_resource(World::new(self.max_y, self.min_y))
+            .insert_resource(DataDirs {
+                base: self.data_dir.clone(),
+                packs: self.data_packs_dir.clone(),
+            })
+            .insert_resource(player::chat::Motd(self.motd.clone()))
+            .insert_resource(player::identity::Operators::new(self.everyone_operator))
+            .insert_resource(Config {
+                motd: self.motd.clone(),
+                ..Default::default()
+            })
+            .insert_resource(ConfigFile::new(self.config_file.clone(), self.motd.clone()))
+            .init_resource::<TickStats>()
+            .init_resource::<audit::AuditLog>()
+            .init_resource::<Registry<LootTable>>()
+            .insert_resource(match self.loot_seed {
+                Some(seed) => LootRng::seeded(seed),
+                None => LootRng::default(),
+            })
+            // load data before registries are snapshotted for the initial message in PostStartup.
+            .add_systems(Startup, (
+                config::load_config,
+                audit::open_audit_log,
+                recipes::load_recipes,
+                loot::load_loot_tables,
+            ))
+            .add_systems(PreUpdate, (
+                config::watch_config,
+                config::apply_log_levels
+                    .after(config::watch_config)
+                    .run_if(on_message::<ConfigChanged>),
+            ))
+            .add_systems(Update, (
+                (
+                    recipes::load_recipes,
+                    loot::load_loot_tables,
+                ).run_if(on_message::<ReloadData>),
+                loot::drop_block_loot
+                    .after(world::edits::apply_block_edits)
+                    .after(world::explosion::process_explosions),
+                loot::drop_entity_loot,
+            ))
+            // initialize messages
+            .add_message::<PlayerJoined>()
+            .add_message::<PlayerLeft>()
+            .add_message::<SubscChanged>()
+            .add_message::<RegionLoaded>()
+            .add_message::<BlockBroken>()
+            .add_message::<Explosion>()
+            .add_message::<EntityDied>()
+            .add_message::<LootDropped>()
+            .add_message::<ReloadData>()
+            .add_message::<ConfigChanged>()
+        ;
+
+        if let Some(dir) = &self.region_dir {
+            app.world_mut()
+                .resource_mut::<WorldLoader>()
+                .set_region_dir(dir.clone());
+        }
+
+        match self.seed {
+            Some(seed) => {
+                app.insert_resource(WorldGenerator::new(seed));
+            }
+            None => {
+                app.add_systems(Startup, world::generator::load_saved_seed);
+            }
+        }
+    }
+}
+
+/// Handle to a server running in a background thread of this process.
+/// Used by the client for singleplayer. The server is stopped on drop.
+pub struct IntegratedServer {
+    /// Address the server is listening on.
+    addr: SocketAddr,
+
+    /// Set to request the server thread to exit.
+    stop: Arc<AtomicBool>,
+
+    thread: Option<JoinHandle<()>>,
+}
+
+impl IntegratedServer {
+    /// Start a server on a background thread, bound to an ephemeral localhost port.
+    /// Blocks until the server has bound its socket.
+    ///
+    /// The world is generated with the seed and height in `info`, and its region
+    /// files and data packs are read from the world directory.
+    ///
+    /// The caller is expected to have initialized logging and the task pools.
+    pub fn spawn(world_dir: impl Into<PathBuf>, info: &WorldInfo) -> io::Result<Self> {
+        let world_dir = world_dir.into();
+        let region_dir = world_dir.join(REGIONS_DIR);
+        let (seed, max_y, min_y) = (info.seed as u64, info.max_y, info.min_y);
+        std::fs::create_dir_all(&region_dir)?;
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let (tx, rx) = crossbeam_channel::bounded(1);
+        let thread = {
+            let stop = stop.clone();
+            thread::Builder::new()
+                .name("integrated-server".into())
+                .spawn(move || {
+                    let mut app = App::new();
+                    app.add_plugins((
+                        TaskPoolPlugin::default(),
+                        TimePlugin,
+                        TransformPlugin,
+                        DiagnosticsPlugin,
+                        AssetPlugin::default(),
+                        StatesPlugin,
+                        ServerPlugin {
+                            addr: "127.0.0.1:0".parse().unwrap(),
+                            region_dir: Some(region_dir),
+                            data_packs_dir: Some(world_dir.join(DATA_PACKS_DIR)),
+                            seed: Some(seed),
+                            max_y,
+                            min_y,
+                            everyone_operator: true,
+                            ..Default::default()
+                        },
+                    ));
+                    app.finish();
+                    app.cleanup();
+
+                    // the first update runs the startup schedules, which binds the server.
+                    app.update();
+                    let addr = app.world().resource::<Server>().local_addr();
+                    let _ = tx.send(addr);
+
+                    let mut clock = TickClock::new();
+                    while !stop.load(Ordering::Relaxed) {
+                        clock.run_tick(&mut app);
+                    }
+
+                    // one more update with the exit, so the chunks that changed since the
+                    // last autosave are written by `save_world_on_exit`.
+                    app.world_mut().write_message(AppExit::Success);
+                    app.update();
+                    info!("Integrated server stopped.");
+                })?
+        };
+
+        match rx.recv() {
+            Ok(Some(addr)) => {
+                info!("Integrated server listening on {addr}.");
+                Ok(Self {
+                    addr,
+                    stop,
+                    thread: Some(thread),
+                })
+            }
+            _ => {
+                stop.store(true, Ordering::Relaxed);
+                let _ = thread.join();
+                Err(io::Error::other("[S120] Integrated server failed to bind."))
+            }
+        }
+    }
+
+    /// Address the server is listening on.
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Whether the server thread is still running.
+    pub fn is_running(&self) -> bool {
+        self.thread.as_ref().is_some_and(|t| !t.is_finished())
+    }
+}
+
+impl Drop for IntegratedServer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+pub trait AppExt {
+    /// Add a channel on which data can be sent and/or received.
+    fn add_channel(&mut self, name: impl Into<String>, sent_by: SentBy) -> &mut Self;
+
+    /// Initialize a Registry that is sent to the client on join.
+    /// Clients that join after the registry changes are sent the new entries.
+    fn init_sync_registry<T>(&mut self, name: impl Into<String>) -> &mut Self
+    where
+        T: Send + Sync + 'static;
+}
+
+impl AppExt for App {
+    fn add_channel(&mut self, name: impl Into<String>, sent_by: SentBy) -> &mut Self {
+        let name = name.into();
+        self.main_mut()
+            .world_mut()
+            .get_resource_mut::<Registry<Channel>>()
+            .unwrap_or_else(|| {
+                panic!("[S381] Attempted to create channel with name: '{name}', but the Channels registry has not been added.")
+            })
+            .insert(name, Channel::new(sent_by));
+        self
+    }
+
+    fn init_sync_registry<T>(&mut self, name: impl Into<String>) -> &mut Self
+    where
+        T: Send + Sync + 'static,
+    {
+        let name = name.into();
+        let snapshot = move |registry: Res<Registry<T>>,
+                             mut initial: ResMut<InitialMessageContent>| {
+            initial.add_registry(name.clone(), registry.get_names());
+        };
+
+        self.init_resource::<Registry<T>>();
+        self.add_systems(PostStartup, snapshot.clone())
+            .add_systems(PostUpdate, snapshot.run_if(resource_changed::<Registry<T>>))
+    }
+}
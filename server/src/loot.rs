@@ -0,0 +1,116 @@
+use bevy::prelude::*;
+use data::{
+    blocks::Block,
+    blockstates::BlockState,
+    items::Item,
+    loot::{LootContext, LootTable, read_loot_tables},
+    registry::Registry,
+};
+use math::rng::{BitRng, Rng};
+
+use crate::{
+    events::{BlockBroken, EntityDied, LootDropped},
+    packs::DataDirs,
+};
+
+/// Rng used to roll loot tables.
+#[derive(Resource)]
+pub struct LootRng(pub BitRng);
+
+impl Default for LootRng {
+    fn default() -> Self {
+        Self(BitRng::from_entropy())
+    }
+}
+
+impl LootRng {
+    /// An rng that rolls the same drops every run, for tests.
+    pub fn seeded(seed: u64) -> Self {
+        Self(BitRng::new(seed))
+    }
+}
+
+/// Read the loot tables of the data directory and data packs.
+pub fn load_loot_tables(
+    dirs: Res<DataDirs>,
+    items: Res<Registry<Item>>,
+    mut tables: ResMut<Registry<LootTable>>,
+) {
+    let (loaded, errors) = read_loot_tables(&dirs.dirs(), &items);
+    for err in errors {
+        error!("{err}");
+    }
+
+    info!("Loaded {} loot tables.", loaded.iter().count());
+    *tables = loaded;
+}
+
+/// Roll the loot table of each broken block, named like "blocks/stone".
+/// Blocks without a table don't drop anything, and some blocks broken by explosions don't either.
+pub fn drop_block_loot(
+    states: Res<Registry<BlockState>>,
+    blocks: Res<Registry<Block>>,
+    tables: Res<Registry<LootTable>>,
+    items: Res<Registry<Item>>,
+    mut rng: ResMut<LootRng>,
+    mut broken: MessageReader<BlockBroken>,
+    mut dropped: MessageWriter<LootDropped>,
+) {
+    for msg in broken.read() {
+        if msg.drop_chance < 1.0 && rng.0.random::<f32>() >= msg.drop_chance {
+            continue;
+        }
+
+        let Some(block) = states
+            .get(msg.voxel)
+            .and_then(|state| blocks.get(state.block))
+        else {
+            continue;
+        };
+
+        let Some(table) = tables.get_by_name(format!("blocks/{}", block.name)) else {
+            continue;
+        };
+
+        let ctx = LootContext {
+            tool: None,
+            by_player: msg.session.is_some(),
+        };
+
+        let stacks = table.roll(&ctx, &items, &mut rng.0);
+        if !stacks.is_empty() {
+            dropped.write(LootDropped {
+                pos: msg.pos.as_vec3() + 0.5,
+                stacks,
+            });
+        }
+    }
+}
+
+/// Roll the loot table of each entity that died, named like "entities/zombie".
+pub fn drop_entity_loot(
+    tables: Res<Registry<LootTable>>,
+    items: Res<Registry<Item>>,
+    mut rng: ResMut<LootRng>,
+    mut died: MessageReader<EntityDied>,
+    mut dropped: MessageWriter<LootDropped>,
+) {
+    for msg in died.read() {
+        let Some(table) = tables.get_by_name(format!("entities/{}", msg.kind)) else {
+            continue;
+        };
+
+        let ctx = LootContext {
+            tool: None,
+            by_player: msg.by_player,
+        };
+
+        let stacks = table.roll(&ctx, &items, &mut rng.0);
+        if !stacks.is_empty() {
+            dropped.write(LootDropped {
+                pos: msg.pos,
+                stacks,
+            });
+        }
+    }
+}
@@ -1,42 +1,22 @@
 #![feature(allocator_api)]
 
-use std::time::Duration;
+use std::path::Path;
 
 use bevy::{
-    app::{
-        App, AppExit, PanicHandlerPlugin, ScheduleRunnerPlugin, TaskPoolPlugin,
-        TerminalCtrlCHandlerPlugin,
-    },
+    app::{App, AppExit, PanicHandlerPlugin, TaskPoolPlugin, TerminalCtrlCHandlerPlugin},
     asset::AssetPlugin,
     diagnostic::DiagnosticsPlugin,
-    log::LogPlugin,
-    prelude::*,
     state::app::StatesPlugin,
     time::TimePlugin,
     transform::TransformPlugin,
 };
 
-use ::world::World;
-use data::{queue::Queue, registry::Registry};
-use protocol::{Packet, packet::SentBy};
-
-use crate::{
-    events::{PlayerJoined, PlayerLeft, RegionLoaded, SubscChanged},
-    net::{InitialMessageContent, Server, channel::Channel},
+use server::{
+    ServerPlugin,
+    config::{self, CONFIG_FILE},
+    tick, watchdog,
 };
 
-pub mod config;
-pub mod events;
-pub mod net;
-pub mod player;
-pub mod queues;
-pub mod startup;
-pub mod states;
-pub mod world;
-
-#[cfg(feature = "tui")]
-pub mod tui;
-
 #[rustfmt::skip]
 fn main() -> AppExit {
     App::new()
@@ -44,69 +24,27 @@ fn main() -> AppExit {
         .add_plugins((
             PanicHandlerPlugin,
             #[cfg(not(feature = "tui"))]
-            LogPlugin::default(),
+            config::logging_plugin(Path::new(CONFIG_FILE)),
             TaskPoolPlugin::default(),
             TimePlugin,
             TransformPlugin,
             DiagnosticsPlugin,
-            ScheduleRunnerPlugin::run_loop(Duration::from_secs_f32(1.0 / 30.0)),
             TerminalCtrlCHandlerPlugin,
             AssetPlugin::default(),
             StatesPlugin,
-            net::ServerNetPlugin,
-            world::ServerWorldPlugin,
-            player::ServerPlayerPlugin,
+            ServerPlugin {
+                config_file: Some(CONFIG_FILE.into()),
+                ..Default::default()
+            },
+            watchdog::WatchdogPlugin,
             #[cfg(feature = "tui")]
-            tui::TuiPlugin,
+            server::tui::TuiPlugin,
         ))
-        // initialize resources
-        .insert_resource(World::new(256, -128))
-        // initialize messages
-        .add_message::<PlayerJoined>()
-        .add_message::<PlayerLeft>()
-        .add_message::<SubscChanged>()
-        .add_message::<RegionLoaded>()
+        // runs ticks at the tick rate of the config, catching up after stalls.
+        .set_runner(tick::run)
         .run()
 }
 
-pub trait AppExt {
-    /// Add a channel on which data can be sent and/or received.
-    fn add_channel(&mut self, name: impl Into<String>, sent_by: SentBy) -> &mut Self;
-
-    /// Initialize a Registry that is sent to the client on join.
-    fn init_sync_registry<T>(&mut self, name: impl Into<String>) -> &mut Self
-    where
-        T: Send + Sync + 'static;
-}
-
-impl AppExt for App {
-    fn add_channel(&mut self, name: impl Into<String>, sent_by: SentBy) -> &mut Self {
-        let name = name.into();
-        self.main_mut()
-            .world_mut()
-            .get_resource_mut::<Registry<Channel>>()
-            .unwrap_or_else(|| {
-                panic!("[S381] Attempted to create channel with name: '{name}', but the Channels registry has not been added.")
-            })
-            .insert(name, Channel::new(sent_by));
-        self
-    }
-
-    fn init_sync_registry<T>(&mut self, name: impl Into<String>) -> &mut Self
-    where
-        T: Send + Sync + 'static,
-    {
-        let name = name.into();
-        self.init_resource::<Registry<T>>();
-        self.add_systems(
-            PostStartup,
-            move |registry: Res<Registry<T>>, mut initial: ResMut<InitialMessageContent>| {
-                initial.add_registry(name.clone(), registry.get_names());
-            },
-        )
-    }
-}
-
 // fn send_chunk_data_to_player_on_join(
 //     mut evs: MessageReader<PlayerJoined>,
 //     world: Res<World>,
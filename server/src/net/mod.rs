@@ -6,13 +6,19 @@ use std::{
 };
 
 use bevy::{log::error, prelude::*};
-use data::registry::Registry;
+use data::{
+    blockstates::BlockState,
+    items::{self, Item},
+    recipes::Recipe,
+    registry::Registry,
+};
 use protocol::{
     bytes::Bytes,
     codec::{TcpDecoder, TcpEncoder, UdpDecoder, UdpEncoder},
     exit::ExitCode,
     packet::{ChannelId, Packet, Protocol, SentBy},
     session::Session,
+    trace::{Direction, MAX_TRACES_PER_SECOND, PacketTrace},
     types::{AuthAccepted, RegistrySyncPacket},
 };
 
@@ -28,6 +34,7 @@ use crate::{
     AppExt,
     events::{PlayerJoined, PlayerLeft},
     net::channel::Channel,
+    world::blocks::BLOCK_STATES,
 };
 
 pub struct ServerNetPlugin;
@@ -39,10 +46,31 @@ impl Plugin for ServerNetPlugin {
             .init_resource::<InitialMessageContent>()
             .init_resource::<Server>()
             .init_sync_registry::<Channel>("channels")
+            .insert_resource(items::builtin_items())
+            .init_sync_registry::<Item>("items")
+            .init_sync_registry::<Recipe>("recipes")
+            .init_sync_registry::<BlockState>(BLOCK_STATES)
             .add_channel("player-input", SentBy::Client)
             .add_channel("chunk-data", SentBy::Server)
+            .add_channel("block-edit", SentBy::Client)
+            .add_channel("block-update", SentBy::Server)
+            .add_channel("player-snapshot", SentBy::Server)
+            .add_channel("player-removed", SentBy::Server)
+            .add_channel("player-appearance", SentBy::Both)
+            .add_channel("player-login", SentBy::Client)
+            .add_channel("chat-send", SentBy::Client)
+            .add_channel("chat-message", SentBy::Server)
+            .add_channel("command-completions", SentBy::Server)
+            .add_channel("chunk-unload", SentBy::Server)
+            .add_channel("world-time", SentBy::Server)
+            .add_channel("player-stats", SentBy::Server)
+            .add_channel("chunk-request", SentBy::Client)
+            .add_channel("chunk-revisions", SentBy::Client)
+            .add_channel("chunk-cached", SentBy::Server)
+            .add_channel("multi-block-update", SentBy::Server)
+            .add_channel("player-position", SentBy::Server)
             .add_systems(PreStartup, (
-                bind_server_to_localhost,
+                bind_server_to_addr,
             ))
             .add_systems(PreUpdate, (
                 process_server_events,
@@ -53,6 +81,8 @@ impl Plugin for ServerNetPlugin {
             ))
             .add_systems(PostUpdate, (
                 clear_channels,
+                log_packet_trace
+                    .before(flush_server_buffers),
                 flush_server_buffers,
             ))
         ;
@@ -83,6 +113,9 @@ pub struct Server {
 
     /// Handle to dedicated runtime for TCP IO
     runtime: Runtime,
+
+    /// Packets sent and received, while tracing is on.
+    trace: PacketTrace,
 }
 
 impl Server {
@@ -101,10 +134,19 @@ impl Server {
         Ok(())
     }
 
+    /// Address of the first bound socket, if any.
+    /// Useful when bound to an ephemeral port.
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.sockets
+            .first()
+            .and_then(|socket| socket.listener.local_addr().ok())
+    }
+
     /// Send a Packet on this Protocol/Channel to the user with the Packets' Session.
     /// Returns "false" if no users exist with the session.
     pub fn send(&mut self, protocol: Protocol, packet: Packet) -> bool {
         if let Some(conn) = self.connections.get_mut(packet.session) {
+            self.trace.record(Direction::Sent, &packet);
             match protocol {
                 Protocol::Udp => conn.udp_send(packet.channel, &packet.payload),
                 Protocol::Tcp => self.outgoing_tcp.push(packet),
@@ -119,6 +161,8 @@ impl Server {
     /// Returns "false" if no users exist with the session.
     pub fn udp_send(&mut self, session: Session, channel: ChannelId, data: &[u8]) -> bool {
         if let Some(conn) = self.connections.get_mut(session) {
+            self.trace
+                .record_raw(Direction::Sent, channel, session, data.len());
             conn.udp_send(channel, data);
             true
         } else {
@@ -130,6 +174,7 @@ impl Server {
     /// Returns "false" if no users exist with the session.
     pub fn tcp_send(&mut self, packet: Packet) -> bool {
         if let Some(_) = self.connections.get_mut(packet.session) {
+            self.trace.record(Direction::Sent, &packet);
             self.outgoing_tcp.push(packet);
             true
         } else {
@@ -166,6 +211,9 @@ impl Server {
                     }
                 }
                 RecvPackets { packets } => {
+                    for packet in &packets {
+                        self.trace.record(Direction::Received, packet);
+                    }
                     self.incoming.push(packets);
                 }
             }
@@ -196,11 +244,13 @@ impl Server {
                 if let Some(conn) = self.connections.get_mut(session) {
                     conn.udp_encoder.set_address(addr);
                     while let Some((channel, data)) = socket.decoder.decode() {
-                        packets.push(Packet {
+                        let packet = Packet {
                             payload: data,
                             channel,
                             session,
-                        });
+                        };
+                        self.trace.record(Direction::Received, &packet);
+                        packets.push(packet);
                     }
                 }
             }
@@ -225,6 +275,11 @@ impl Server {
         session
     }
 
+    /// Logging of the packets sent and received, see `protocol::trace`.
+    pub fn trace_mut(&mut self) -> &mut PacketTrace {
+        &mut self.trace
+    }
+
     /// Reject a pending connection.
     pub fn reject(&mut self, _: Pending) {
         // at some point we will need to send a rejection payload
@@ -239,6 +294,7 @@ impl Default for Server {
             outgoing_tcp: Vec::new(),
             incoming: Vec::new(),
             runtime: Runtime::start(Duration::from_secs_f32(1.0 / 20.0)).unwrap(),
+            trace: PacketTrace::default(),
         }
     }
 }
@@ -324,11 +380,36 @@ fn process_server_events(
     }
 }
 
-/// Binds Server to LocalHost on startup.
-fn bind_server_to_localhost(mut server: ResMut<Server>) {
-    server
-        .bind("127.0.0.1:51423".parse::<SocketAddr>().unwrap())
-        .unwrap();
+/// Address the server binds to on startup.
+#[derive(Resource, Copy, Clone, Debug)]
+pub struct BindAddr(pub SocketAddr);
+
+/// Binds Server to the BindAddr on startup.
+fn bind_server_to_addr(mut server: ResMut<Server>, addr: Res<BindAddr>) {
+    if let Err(e) = server.bind(addr.0) {
+        panic!("[N101] Failed to bind server to '{}' with error: '{e}'", addr.0);
+    }
+}
+
+/// Log the packets traced this tick, with the names of their channels.
+fn log_packet_trace(mut server: ResMut<Server>, channels: Res<Registry<Channel>>) {
+    let trace = server.trace_mut();
+    for traced in trace.drain() {
+        let name = traced
+            .channel
+            .special_name()
+            .or_else(|| channels.get(traced.channel).map(|entry| entry.name))
+            .unwrap_or("unknown");
+        info!(target: "packets", "{}", traced.describe(name));
+    }
+
+    let dropped = trace.take_dropped();
+    if dropped != 0 {
+        info!(
+            target: "packets",
+            "{dropped} packets weren't traced, over the limit of {MAX_TRACES_PER_SECOND} per second."
+        );
+    }
 }
 
 fn flush_server_buffers(mut server: ResMut<Server>) {
@@ -0,0 +1,44 @@
+//! Data packs, folders of recipes, loot tables, tags, structures and worldgen settings in
+//! the `datapacks` folder of a world, layered over the data directory. They are read on
+//! startup, and again when `ReloadData` is sent, like by the `/reload` command.
+//!
+//! Blocks are only read on startup, since their states are the ids of saved voxels.
+
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+use data::fs::save::data_pack_dirs;
+
+/// Directories the server reads data like recipes from.
+#[derive(Resource)]
+pub struct DataDirs {
+    /// Data every world has, laid out like the assets directory of the client.
+    pub base: PathBuf,
+
+    /// Folder with the data packs of the world, if it has one.
+    pub packs: Option<PathBuf>,
+}
+
+impl DataDirs {
+    /// Directories to read data from, highest priority first.
+    /// Data packs come first, so their files replace the files of the data directory.
+    pub fn dirs(&self) -> Vec<PathBuf> {
+        let mut dirs = self
+            .packs
+            .as_deref()
+            .map(data_pack_dirs)
+            .unwrap_or_default();
+        dirs.push(self.base.clone());
+        dirs
+    }
+
+    /// Names of the data packs, highest priority first.
+    pub fn pack_names(&self) -> Vec<String> {
+        let mut dirs = self.dirs();
+        dirs.pop();
+        dirs.iter()
+            .filter_map(|dir| dir.file_name())
+            .map(|name| name.to_string_lossy().into_owned())
+            .collect()
+    }
+}
@@ -0,0 +1,77 @@
+//! How players look to each other.
+//!
+//! Clients send their appearance when they join, which the server keeps on the player
+//! and relays to every other player. Players that join later are sent the appearance
+//! of everyone already connected.
+
+use bevy::prelude::*;
+use data::registry::Registry;
+use protocol::{ChannelId, Packet, bytes::Bytes, types::PlayerAppearance};
+
+use crate::{
+    events::PlayerJoined,
+    net::{Server, channel::Channel},
+    player::{Player, owner::Owners},
+};
+
+/// The appearance the client of a player last sent.
+#[derive(Component, Copy, Clone, Deref)]
+pub struct Appearance(pub PlayerAppearance);
+
+/// Keep the appearances sent by clients, and relay those that changed to the other players.
+pub fn recv_player_appearances(
+    mut commands: Commands,
+    channels: Res<Registry<Channel>>,
+    owners: Owners,
+    q: Query<(&Player, Option<&Appearance>)>,
+    mut server: ResMut<Server>,
+) {
+    let channel: ChannelId = channels.resolve("player-appearance").unwrap().into();
+    for packet in channels.get_by_name("player-appearance").unwrap() {
+        if let Some(update) = packet.cast::<PlayerAppearance>()
+            && let Some(entity) = owners.player(packet.session)
+            && owners.authorize(packet.session, entity)
+            && let Ok((player, current)) = q.get(entity)
+        {
+            // clients can't claim the appearance of another player.
+            let appearance = PlayerAppearance {
+                session: player.session.0,
+                ..update
+            };
+            if current.is_some_and(|current| current.0 == appearance) {
+                continue;
+            }
+
+            commands.entity(entity).insert(Appearance(appearance));
+            let payload = Bytes::copy_from_slice(bytemuck::bytes_of(&appearance));
+            for (other, _) in &q {
+                if other.session != player.session {
+                    server.tcp_send(Packet {
+                        payload: payload.clone(),
+                        session: other.session,
+                        channel,
+                    });
+                }
+            }
+        }
+    }
+}
+
+/// Send players that joined the appearance of every other player.
+pub fn send_appearances_on_join(
+    channels: Res<Registry<Channel>>,
+    mut joined_evs: MessageReader<PlayerJoined>,
+    q: Query<&Appearance>,
+    mut server: ResMut<Server>,
+) {
+    let channel: ChannelId = channels.resolve("player-appearance").unwrap().into();
+    for ev in joined_evs.read() {
+        for appearance in &q {
+            server.tcp_send(Packet {
+                payload: Bytes::copy_from_slice(bytemuck::bytes_of(&appearance.0)),
+                session: ev.session,
+                channel,
+            });
+        }
+    }
+}
@@ -0,0 +1,545 @@
+//! Chat messages and commands sent by players.
+
+use std::{path::Path, str::SplitWhitespace};
+
+use bevy::prelude::*;
+use data::{
+    blockstates::BlockState,
+    fs::save::{ProtectedRegion, unix_now},
+    registry::Registry,
+    text::rich::{ChatMessage, ClickAction, Rgb, RichText},
+};
+use math::space::area::IArea;
+use protocol::{
+    ChannelId, Packet,
+    types::{ChatSend, CommandCompletion, CommandCompletions},
+};
+use world::World;
+
+use crate::{
+    audit::{AuditLog, auditlog_reply},
+    config::Config,
+    events::{PlayerJoined, ReloadData},
+    net::{Server, channel::Channel},
+    packs::DataDirs,
+    player::{
+        Player,
+        identity::{Operators, PlayerId},
+        table::Players,
+    },
+    world::{
+        generator::WorldGenerator,
+        loader::WorldLoader,
+        protect::ProtectedRegions,
+        stats::{StatsScans, WorldStats},
+    },
+};
+
+/// Longest chat line that will be relayed, in characters.
+const MAX_CHAT_LEN: usize = 256;
+
+/// Number of voxel types and chunks listed by /stats.
+const STATS_TOP: usize = 8;
+
+/// Commands only operators may run, see `Operators`.
+const OPERATOR_COMMANDS: &[&str] = &["auditlog", "protect", "reload", "seed", "trace"];
+
+/// Commands players can run, and the completion metadata sent to clients.
+#[derive(Resource)]
+pub struct ChatCommands(Vec<CommandCompletion>);
+
+impl Default for ChatCommands {
+    fn default() -> Self {
+        Self(vec![
+            CommandCompletion {
+                name: "auditlog".into(),
+                args: vec!["count".into(), "player".into()],
+                description: "Show the most recent commands and builds in protected regions."
+                    .into(),
+            },
+            CommandCompletion {
+                name: "help".into(),
+                args: Vec::new(),
+                description: "List the available commands.".into(),
+            },
+            CommandCompletion {
+                name: "list".into(),
+                args: Vec::new(),
+                description: "List the players that are online.".into(),
+            },
+            CommandCompletion {
+                name: "protect".into(),
+                args: vec![
+                    "add|remove|allow|deny|list".into(),
+                    "region".into(),
+                    "x1 z1 x2 z2|player".into(),
+                ],
+                description: "Define the regions only some players may build in.".into(),
+            },
+            CommandCompletion {
+                name: "reload".into(),
+                args: Vec::new(),
+                description: "Read the data packs of the world again.".into(),
+            },
+            CommandCompletion {
+                name: "seed".into(),
+                args: Vec::new(),
+                description: "Show the seed the world is generated with.".into(),
+            },
+            CommandCompletion {
+                name: "stats".into(),
+                args: vec!["all".into()],
+                description:
+                    "Count the voxels and chunks of the loaded regions, or of all regions.".into(),
+            },
+            CommandCompletion {
+                name: "trace".into(),
+                args: vec!["on|off".into()],
+                description: "Log every packet sent and received, on the server and your client."
+                    .into(),
+            },
+        ])
+    }
+}
+
+/// Message of the day, sent in the chat to players when they join.
+#[derive(Resource, Default)]
+pub struct Motd(pub Option<RichText>);
+
+/// Apply the message of the day of the config when it changes.
+pub fn apply_config_motd(config: Res<Config>, mut motd: ResMut<Motd>) {
+    motd.0 = config.motd.clone();
+}
+
+/// Send the completion metadata of every command to players that joined.
+pub fn send_command_completions(
+    channels: Res<Registry<Channel>>,
+    commands: Res<ChatCommands>,
+    mut joined_evs: MessageReader<PlayerJoined>,
+    mut server: ResMut<Server>,
+) {
+    let channel: ChannelId = channels.resolve("command-completions").unwrap().into();
+    let completions = CommandCompletions {
+        commands: commands.0.clone(),
+    };
+
+    for ev in joined_evs.read() {
+        server.tcp_send(Packet::from_json(channel, ev.session, &completions));
+    }
+}
+
+/// Send the message of the day to players that joined, if there is one.
+pub fn send_motd(
+    channels: Res<Registry<Channel>>,
+    motd: Res<Motd>,
+    mut joined_evs: MessageReader<PlayerJoined>,
+    mut server: ResMut<Server>,
+) {
+    let Some(text) = &motd.0 else {
+        joined_evs.clear();
+        return;
+    };
+
+    let channel: ChannelId = channels.resolve("chat-message").unwrap().into();
+    for ev in joined_evs.read() {
+        let msg = ChatMessage {
+            sender: None,
+            sender_name: None,
+            text: text.clone(),
+            timestamp: unix_now(),
+        };
+        server.tcp_send(Packet::from_json(channel, ev.session, &msg));
+    }
+}
+
+/// Relay chat lines to every player, and run commands for their sender.
+pub fn relay_chat_messages(
+    channels: Res<Registry<Channel>>,
+    commands: Res<ChatCommands>,
+    dirs: Res<DataDirs>,
+    states: Res<Registry<BlockState>>,
+    world: Res<World>,
+    loader: Res<WorldLoader>,
+    generator: Res<WorldGenerator>,
+    players: Res<Players>,
+    operators: Res<Operators>,
+    q: Query<(&Player, Option<&PlayerId>)>,
+    mut server: ResMut<Server>,
+    mut reload: MessageWriter<ReloadData>,
+    mut protections: ResMut<ProtectedRegions>,
+    mut audit: ResMut<AuditLog>,
+    mut scans: ResMut<StatsScans>,
+) {
+    let channel: ChannelId = channels.resolve("chat-message").unwrap().into();
+    for packet in channels.get_by_name("chat-send").unwrap() {
+        let Some(send) = packet.json::<ChatSend>() else {
+            continue;
+        };
+
+        let text = send.text.trim();
+        if text.is_empty() || text.chars().count() > MAX_CHAT_LEN {
+            continue;
+        }
+
+        // players are named in chat once they logged in.
+        let Some(id) = players
+            .entity(packet.session)
+            .and_then(|entity| q.get(entity).ok())
+            .and_then(|(_, id)| id.copied())
+        else {
+            continue;
+        };
+        let name = id.to_string();
+
+        if let Some(command) = text.strip_prefix('/') {
+            audit.record_command(name, command);
+            let reply = run_command(
+                command,
+                id,
+                operators.contains(Some(id)),
+                &commands,
+                &dirs,
+                &states,
+                &world,
+                &loader,
+                &generator,
+                &q,
+                &mut server,
+                &mut reload,
+                &mut protections,
+                &audit,
+                &mut scans,
+            );
+            let msg = ChatMessage {
+                sender: None,
+                sender_name: None,
+                text: reply,
+                timestamp: unix_now(),
+            };
+            server.tcp_send(Packet::from_json(channel, packet.session, &msg));
+            continue;
+        }
+
+        info!("<{name}> {text}");
+        let msg = ChatMessage {
+            sender: Some(packet.session.0),
+            sender_name: Some(name),
+            text: RichText::plain(text),
+            timestamp: unix_now(),
+        };
+
+        for (player, _) in &q {
+            server.tcp_send(Packet::from_json(channel, player.session, &msg));
+        }
+    }
+}
+
+/// Run a command, returning the reply for its sender.
+fn run_command(
+    command: &str,
+    sender: PlayerId,
+    operator: bool,
+    commands: &ChatCommands,
+    dirs: &DataDirs,
+    states: &Registry<BlockState>,
+    world: &World,
+    loader: &WorldLoader,
+    generator: &WorldGenerator,
+    q: &Query<(&Player, Option<&PlayerId>)>,
+    server: &mut Server,
+    reload: &mut MessageWriter<ReloadData>,
+    protections: &mut ProtectedRegions,
+    audit: &AuditLog,
+    scans: &mut StatsScans,
+) -> RichText {
+    let mut args = command.split_whitespace();
+    let name = args.next().unwrap_or_default();
+    if !operator && OPERATOR_COMMANDS.contains(&name) {
+        return RichText::translate("chat.command.operator", [format!("/{name}").into()])
+            .color(Rgb::RED);
+    }
+
+    match name {
+        "auditlog" => auditlog_reply(audit, args),
+        "help" => {
+            // only the commands the player may run are listed.
+            let allowed = commands
+                .0
+                .iter()
+                .filter(|cmd| operator || !OPERATOR_COMMANDS.contains(&cmd.name.as_str()));
+
+            let mut reply = RichText::default();
+            for (i, cmd) in allowed.enumerate() {
+                if i > 0 {
+                    reply = reply.push("\n");
+                }
+
+                // clicking a command puts it in the input, ready for its arguments.
+                let usage = RichText::plain(cmd.usage())
+                    .color(Rgb::GOLD)
+                    .on_click(ClickAction::SuggestCommand(format!("/{} ", cmd.name)))
+                    .on_hover(cmd.description.as_str());
+                reply = reply.push(usage).push(format!(" - {}", cmd.description));
+            }
+            reply
+        }
+        "list" => {
+            let names = q
+                .iter()
+                .filter_map(|(_, id)| id.map(PlayerId::to_string))
+                .collect::<Vec<_>>();
+            RichText::translate(
+                "chat.command.list",
+                [names.len().to_string().into(), names.join(", ").into()],
+            )
+        }
+        "protect" => protect_reply(args, protections, loader.region_dir()),
+        "reload" => {
+            reload.write(ReloadData);
+            let packs = dirs.pack_names();
+            info!("Reloading data with packs: [{}]", packs.join(", "));
+            RichText::translate("chat.command.reload", [packs.join(", ").into()])
+        }
+        "seed" => {
+            // shown signed, like the seed field of a new world and world.toml.
+            let seed = (generator.seed() as i64).to_string();
+            let text = RichText::plain(seed.clone())
+                .color(Rgb::GOLD)
+                .on_click(ClickAction::Insert(seed));
+            RichText::translate("chat.command.seed", [text])
+        }
+        "stats" => {
+            if args.next() != Some("all") {
+                let stats = WorldStats::loaded(world, loader);
+                info!(
+                    "Counted {} voxels in {} loaded chunks of {} regions.",
+                    stats.total_voxels(),
+                    stats.chunks,
+                    stats.regions
+                );
+                return stats_reply(&stats, states, false);
+            }
+
+            // reads every region file, so only operators may start it, one at a time.
+            if !operator {
+                return RichText::translate("chat.command.operator", ["/stats all".into()])
+                    .color(Rgb::RED);
+            }
+            if scans.is_running() {
+                return RichText::translate("chat.command.stats.running", []).color(Rgb::RED);
+            }
+            scans.start(sender, world, loader);
+            RichText::translate("chat.command.stats.started", [])
+        }
+        "trace" => {
+            // the client turns its own tracing on or off when it sends this.
+            let trace = server.trace_mut();
+            match args.next() {
+                Some("on") => trace.set_enabled(true),
+                Some("off") => trace.set_enabled(false),
+                _ => {
+                    let state = if trace.is_enabled() { "on" } else { "off" };
+                    return RichText::translate("chat.command.trace.status", [state.into()]);
+                }
+            }
+            let state = if trace.is_enabled() { "on" } else { "off" };
+            info!("Packet tracing turned {state}.");
+            RichText::translate("chat.command.trace", [state.into()])
+        }
+        _ => RichText::translate("chat.command.unknown", [format!("/{name}").into()])
+            .color(Rgb::RED)
+            .on_click(ClickAction::SuggestCommand("/help".into())),
+    }
+}
+
+/// Reply to /protect, which adds and removes protected regions, changes the players
+/// that may build in them, and lists them. Changes are saved right away.
+fn protect_reply(
+    mut args: SplitWhitespace,
+    protections: &mut ProtectedRegions,
+    region_dir: &Path,
+) -> RichText {
+    let usage = || RichText::translate("chat.command.protect.usage", []).color(Rgb::RED);
+    let action = args.next().unwrap_or_default();
+    if action == "list" {
+        return protect_list_reply(protections);
+    }
+
+    let Some(name) = args.next() else {
+        return usage();
+    };
+
+    let reply = match action {
+        "add" => {
+            let corners = args
+                .map(str::parse::<i32>)
+                .collect::<Result<Vec<_>, _>>()
+                .ok()
+                .and_then(|corners| <[i32; 4]>::try_from(corners).ok());
+            let Some([x1, z1, x2, z2]) = corners else {
+                return usage();
+            };
+
+            // both corners are in the region, so the end is one past the larger corner.
+            let (a, b) = (IVec2::new(x1, z1), IVec2::new(x2, z2));
+            let max = a.max(b);
+            let (Some(max_x), Some(max_z)) = (max.x.checked_add(1), max.y.checked_add(1)) else {
+                return usage();
+            };
+            protections.insert(
+                name,
+                ProtectedRegion::new(IArea::new(a.min(b), IVec2::new(max_x, max_z))),
+            );
+            RichText::translate("chat.command.protect.add", [name.into()])
+        }
+        "remove" => {
+            if protections.remove(name).is_none() {
+                return RichText::translate("chat.command.protect.unknown", [name.into()])
+                    .color(Rgb::RED);
+            }
+            RichText::translate("chat.command.protect.remove", [name.into()])
+        }
+        "allow" | "deny" => {
+            // names in chat have spaces, like "Player 1".
+            let player = args.collect::<Vec<_>>().join(" ");
+            if player.is_empty() {
+                return usage();
+            }
+
+            let Some(region) = protections.get_mut(name) else {
+                return RichText::translate("chat.command.protect.unknown", [name.into()])
+                    .color(Rgb::RED);
+            };
+
+            let (add, remove) = match action {
+                "allow" => (&mut region.allow, &mut region.deny),
+                _ => (&mut region.deny, &mut region.allow),
+            };
+            remove.retain(|other| *other != player);
+            if !add.contains(&player) {
+                add.push(player.clone());
+            }
+
+            let key = format!("chat.command.protect.{action}");
+            RichText::translate(key, [player.into(), name.into()])
+        }
+        _ => return usage(),
+    };
+
+    info!("Protected regions changed with /protect {action} {name}.");
+    protections.save(region_dir);
+    reply
+}
+
+/// Reply to /protect list, with the corners and players of every protected region.
+fn protect_list_reply(protections: &ProtectedRegions) -> RichText {
+    if protections.iter().next().is_none() {
+        return RichText::translate("chat.command.protect.none", []);
+    }
+
+    let mut reply = RichText::translate("chat.command.protect.list", []);
+    for (name, region) in protections.iter() {
+        let area = region.area();
+        reply = reply.push("\n").push(RichText::translate(
+            "chat.command.protect.region",
+            [
+                RichText::plain(name).color(Rgb::GOLD),
+                format!("{}, {}", area.min.x, area.min.y).into(),
+                format!("{}, {}", area.max.x - 1, area.max.y - 1).into(),
+                region.allow.join(", ").into(),
+                region.deny.join(", ").into(),
+            ],
+        ));
+    }
+    reply
+}
+
+/// Reply to the players that ran `/stats all` once the region files were counted.
+pub fn reply_stats_scans(
+    channels: Res<Registry<Channel>>,
+    states: Res<Registry<BlockState>>,
+    q: Query<(&Player, &PlayerId)>,
+    mut scans: ResMut<StatsScans>,
+    mut server: ResMut<Server>,
+) {
+    let channel: ChannelId = channels.resolve("chat-message").unwrap().into();
+    for (sender, stats) in scans.poll() {
+        info!(
+            "Counted {} voxels in {} chunks of {} regions, loaded and saved.",
+            stats.total_voxels(),
+            stats.chunks,
+            stats.regions
+        );
+        let msg = ChatMessage {
+            sender: None,
+            sender_name: None,
+            text: stats_reply(&stats, &states, true),
+            timestamp: unix_now(),
+        };
+
+        // the sender may have left while the files were read.
+        if let Some((player, _)) = q.iter().find(|(_, id)| **id == sender) {
+            server.tcp_send(Packet::from_json(channel, player.session, &msg));
+        }
+    }
+}
+
+/// Reply to /stats, listing the most common voxels and the largest chunks.
+fn stats_reply(stats: &WorldStats, states: &Registry<BlockState>, all: bool) -> RichText {
+    let key = if all {
+        "chat.command.stats.all"
+    } else {
+        "chat.command.stats"
+    };
+    let mut reply = RichText::translate(
+        key,
+        [
+            stats.regions.to_string().into(),
+            stats.chunks.to_string().into(),
+        ],
+    );
+
+    let total = stats.total_voxels().max(1);
+    for (voxel, count) in stats.most_common(STATS_TOP) {
+        let name = states
+            .get(voxel.0 as usize)
+            .map(|entry| entry.name.to_string())
+            .unwrap_or_else(|| format!("#{}", voxel.0));
+        let percent = count as f64 * 100.0 / total as f64;
+        reply = reply.push("\n").push(RichText::translate(
+            "chat.command.stats.voxel",
+            [
+                RichText::plain(name).color(Rgb::GOLD),
+                count.to_string().into(),
+                format!("{percent:.1}").into(),
+            ],
+        ));
+    }
+
+    let largest = stats.largest_chunks(STATS_TOP);
+    if !largest.is_empty() {
+        reply = reply
+            .push("\n")
+            .push(RichText::translate("chat.command.stats.largest", []));
+    }
+    for (origin, size) in largest {
+        reply = reply.push("\n").push(RichText::translate(
+            "chat.command.stats.chunk",
+            [
+                format!("{}, {}", origin.x, origin.y).into(),
+                size.to_string().into(),
+            ],
+        ));
+    }
+
+    if stats.errors > 0 {
+        reply = reply.push("\n").push(
+            RichText::translate(
+                "chat.command.stats.errors",
+                [stats.errors.to_string().into()],
+            )
+            .color(Rgb::RED),
+        );
+    }
+    reply
+}
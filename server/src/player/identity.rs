@@ -0,0 +1,133 @@
+//! Who players are across sessions, and which of them are operators.
+//!
+//! Sessions change every time a player joins and their index is reused, so players are
+//! known by the key their client sends with `PlayerLogin` right after joining. The first
+//! time a key is seen, the player is given the next number, which is saved with the key
+//! in `players.toml` next to the region files, see `KnownPlayers`. Their name in chat,
+//! in protected regions and in the operators of the config uses that number.
+
+use std::{fmt, path::Path};
+
+use bevy::prelude::*;
+use data::{fs::save::KnownPlayers, registry::Registry};
+use protocol::types::PlayerLogin;
+
+use crate::{
+    config::Config,
+    net::channel::Channel,
+    player::{Player, table::Players},
+    world::loader::WorldLoader,
+};
+
+/// The number a player was given when they first joined the world.
+#[derive(Component, Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct PlayerId(pub u32);
+
+impl fmt::Display for PlayerId {
+    /// Name of the player in chat, like "Player 3".
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Player {}", self.0)
+    }
+}
+
+/// The players that joined the world before.
+#[derive(Resource, Default, Deref)]
+pub struct PlayerKeys(KnownPlayers);
+
+impl PlayerKeys {
+    /// Save the players to the region directory, after a new player joined.
+    fn save(&self, region_dir: &Path) {
+        if let Err(e) = self.0.save(region_dir) {
+            error!(
+                "[S174] Failed to save the known players in '{}': {e}",
+                region_dir.display()
+            );
+        }
+    }
+}
+
+/// The players that may run admin commands, like /protect, and fly through blocks.
+#[derive(Resource, Default)]
+pub struct Operators {
+    /// Whether every player is an operator, like the only player of a singleplayer world.
+    pub everyone: bool,
+
+    /// Names of the operators in chat, from the config.
+    names: Vec<String>,
+}
+
+impl Operators {
+    pub fn new(everyone: bool) -> Self {
+        Self {
+            everyone,
+            names: Vec::new(),
+        }
+    }
+
+    /// Whether the player is an operator. Players that haven't logged in yet aren't.
+    pub fn contains(&self, id: Option<PlayerId>) -> bool {
+        id.is_some_and(|id| self.everyone || self.names.contains(&id.to_string()))
+    }
+}
+
+/// Apply the operators of the config when they change.
+pub fn apply_config_operators(config: Res<Config>, mut operators: ResMut<Operators>) {
+    operators.names = config.operators.clone();
+}
+
+/// Read the players that joined the world before on startup.
+pub fn load_player_keys(loader: Res<WorldLoader>, mut keys: ResMut<PlayerKeys>) {
+    let region_dir = loader.region_dir();
+    match KnownPlayers::load(region_dir) {
+        Ok(loaded) => keys.0 = loaded,
+        Err(e) => error!(
+            "[S175] Failed to read the known players in '{}', players that joined before get new names: {e}",
+            region_dir.display()
+        ),
+    }
+}
+
+/// Give players that logged in the number of their key.
+pub fn recv_player_logins(
+    mut commands: Commands,
+    channels: Res<Registry<Channel>>,
+    loader: Res<WorldLoader>,
+    players: Res<Players>,
+    q: Query<Option<&PlayerId>, With<Player>>,
+    mut keys: ResMut<PlayerKeys>,
+    mut logged_in: Local<Vec<PlayerId>>,
+) {
+    // players that logged in this tick don't have their id inserted yet.
+    logged_in.clear();
+    for packet in channels.get_by_name("player-login").unwrap() {
+        let Some(login) = packet.cast::<PlayerLogin>() else {
+            continue;
+        };
+
+        // players can't change who they are during a session.
+        let Some(entity) = players.entity(packet.session) else {
+            continue;
+        };
+        if !q.get(entity).is_ok_and(|id| id.is_none()) {
+            continue;
+        }
+
+        let (id, new) = keys.0.get_or_insert(&login.key_hex());
+        let id = PlayerId(id);
+        if logged_in.contains(&id) || q.iter().flatten().any(|other| *other == id) {
+            warn!(
+                "[S173] Session {} logged in as {id}, who is already online, the login was ignored.",
+                packet.session
+            );
+            continue;
+        }
+
+        if new {
+            keys.save(loader.region_dir());
+        }
+
+        info!("Session {} logged in as {id}.", packet.session);
+        commands.entity(entity).insert(id);
+        logged_in.push(id);
+    }
+}
@@ -1,13 +1,23 @@
 use bevy::{ecs::entity::EntityHashMap, prelude::*};
 use data::registry::Registry;
-use protocol::{packet::Version, session::Session, types::EntityUpdate};
+use protocol::{
+    packet::Version,
+    session::Session,
+    types::{EntityUpdate, MovementState},
+};
 
 use crate::{
-    events::{PlayerJoined, PlayerLeft},
+    events::{ConfigChanged, PlayerJoined, PlayerLeft},
     net::channel::Channel,
 };
 use table::Players;
 
+pub mod appearance;
+pub mod chat;
+pub mod identity;
+pub mod owner;
+pub mod replicate;
+pub mod stats;
 pub mod table;
 pub mod update;
 
@@ -18,15 +28,40 @@ impl Plugin for ServerPlayerPlugin {
     fn build(&self, app: &mut App) {
         app
             .init_resource::<table::Players>()
+            .init_resource::<chat::ChatCommands>()
+            .init_resource::<chat::Motd>()
+            .init_resource::<crate::world::stats::StatsScans>()
+            .init_resource::<identity::PlayerKeys>()
+            .add_systems(Startup, identity::load_player_keys)
             .add_systems(Update, (
                 update::apply_input_updates,
                 spawn_player_on_join,
                 despawn_player_on_leave,
+                replicate::broadcast_player_snapshots
+                    .after(update::apply_input_updates),
+                replicate::broadcast_player_removals,
+                appearance::recv_player_appearances,
+                appearance::send_appearances_on_join,
+                chat::send_command_completions,
+                chat::apply_config_motd
+                    .run_if(on_message::<ConfigChanged>),
+                identity::apply_config_operators
+                    .run_if(on_message::<ConfigChanged>),
+                chat::send_motd
+                    .after(chat::apply_config_motd),
+                identity::recv_player_logins,
+                chat::relay_chat_messages,
+                chat::reply_stats_scans
+                    .after(chat::relay_chat_messages),
+                stats::send_player_stats,
             ))
         ;
     }
 }
 
+/// Where players spawn, same as on the client.
+pub const SPAWN_POINT: Vec3 = Vec3::new(0.0, 64.0, 0.0);
+
 #[derive(Component, Copy, Clone)]
 pub struct Player {
     /// The Session assigned to the Player.
@@ -36,6 +71,16 @@ pub struct Player {
     /// Used to determine if updates from the client
     /// should be discarded or applied.
     pub version: Version,
+
+    /// How the player is moving, as reported by the client.
+    /// Flying is only kept for operators.
+    pub movement: MovementState,
+
+    /// Distance the player may still move, see `update::apply_input_updates`.
+    pub move_budget: f32,
+
+    /// Number of position corrections sent to the client after rejected movements.
+    pub corrections: u32,
 }
 
 pub fn spawn_player_on_join(
@@ -48,12 +93,17 @@ pub fn spawn_player_on_join(
         let id = commands
             .spawn((
                 // TODO: load from memory if player has joined before, otherwise choose spawn location.
-                Transform::from_xyz(0.0, 0.0, 0.0),
+                Transform::from_translation(SPAWN_POINT),
                 //
                 Player {
                     session: ev.session,
                     version: Version::ZERO,
+                    movement: MovementState::Walking,
+                    move_budget: 0.0,
+                    corrections: 0,
                 },
+                stats::PlayerStats::default(),
+                owner::Owner(ev.session),
             ))
             .id();
         players.insert(ev.session, id);
@@ -0,0 +1,64 @@
+//! Which session controls an entity, so systems that apply packets from clients can check
+//! that the session that sent a packet is allowed to change the entity it is for.
+//!
+//! Players own their own body, and will own the vehicles and mounts they ride.
+//! Entities without an `Owner` are only changed by the server.
+
+use bevy::{ecs::system::SystemParam, prelude::*};
+use protocol::session::Session;
+
+use crate::player::table::Players;
+
+/// The session whose packets may change this entity.
+#[derive(Component, Copy, Clone, Eq, PartialEq, Debug, Deref)]
+pub struct Owner(pub Session);
+
+impl Owner {
+    /// Whether packets from `session` may change the entity.
+    pub fn allows(&self, session: Session) -> bool {
+        self.0 == session
+    }
+}
+
+/// The owners of entities, for checking packets before applying them.
+#[derive(SystemParam)]
+pub struct Owners<'w, 's> {
+    players: Res<'w, Players>,
+    q: Query<'w, 's, (Entity, &'static Owner)>,
+}
+
+impl Owners<'_, '_> {
+    /// The owner of an entity, or None if only the server may change it.
+    pub fn get(&self, entity: Entity) -> Option<Session> {
+        self.q.get(entity).ok().map(|(_, owner)| owner.0)
+    }
+
+    /// Whether packets from `session` may change `entity`.
+    /// Packets for entities the session doesn't own are reported, since clients
+    /// only send them when they are out of sync or modified.
+    pub fn authorize(&self, session: Session, entity: Entity) -> bool {
+        match self.get(entity) {
+            Some(owner) if owner == session => true,
+            owner => {
+                warn!(
+                    "[S160] Session {session} sent a packet for entity {entity}, which is owned by {}, the packet was ignored.",
+                    owner.map_or("the server".into(), |owner| format!("session {owner}"))
+                );
+                false
+            }
+        }
+    }
+
+    /// The body of the player of `session`.
+    pub fn player(&self, session: Session) -> Option<Entity> {
+        self.players.entity(session)
+    }
+
+    /// Every entity `session` owns.
+    pub fn owned_by(&self, session: Session) -> impl Iterator<Item = Entity> {
+        self.q
+            .iter()
+            .filter(move |(_, owner)| owner.allows(session))
+            .map(|(entity, _)| entity)
+    }
+}
@@ -0,0 +1,74 @@
+use bevy::prelude::*;
+use data::registry::Registry;
+use protocol::{
+    ChannelId, Packet,
+    bytes::Bytes,
+    types::{PlayerRemoved, PlayerSnapshot},
+};
+
+use crate::{
+    events::PlayerLeft,
+    net::{Server, channel::Channel},
+    player::Player,
+    world::subscriber::Subscriber,
+};
+
+/// Send the state of every player to the other players that can see them.
+pub fn broadcast_player_snapshots(
+    channels: Res<Registry<Channel>>,
+    subscriber: Res<Subscriber>,
+    q: Query<(&Transform, &Player)>,
+    mut server: ResMut<Server>,
+) {
+    let channel: ChannelId = channels.resolve("player-snapshot").unwrap().into();
+    for (transform, player) in &q {
+        let (yaw, pitch, _) = transform.rotation.to_euler(EulerRot::YXZ);
+        let snapshot = PlayerSnapshot {
+            session: player.session.0,
+            version: player.version,
+            movement: player.movement as u32,
+            translation: transform.translation,
+            yaw,
+            pitch,
+            _pad: 0,
+        };
+
+        let payload = Bytes::copy_from_slice(bytemuck::bytes_of(&snapshot));
+        let xz = transform.translation.as_ivec3().xz();
+        for (session, _) in subscriber.in_draw_range(xz) {
+            if session != player.session {
+                server.tcp_send(Packet {
+                    payload: payload.clone(),
+                    session,
+                    channel,
+                });
+            }
+        }
+    }
+}
+
+/// Tell the remaining players to despawn players that left.
+pub fn broadcast_player_removals(
+    channels: Res<Registry<Channel>>,
+    mut left_evs: MessageReader<PlayerLeft>,
+    q: Query<&Player>,
+    mut server: ResMut<Server>,
+) {
+    let channel: ChannelId = channels.resolve("player-removed").unwrap().into();
+    for ev in left_evs.read() {
+        let removed = PlayerRemoved {
+            session: ev.session.0,
+        };
+
+        let payload = Bytes::copy_from_slice(bytemuck::bytes_of(&removed));
+        for session in q.iter().map(|player| player.session) {
+            if session != ev.session {
+                server.tcp_send(Packet {
+                    payload: payload.clone(),
+                    session,
+                    channel,
+                });
+            }
+        }
+    }
+}
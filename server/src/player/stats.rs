@@ -0,0 +1,58 @@
+//! Health, hunger and other stats of players, replicated to their own client.
+
+use bevy::prelude::*;
+use data::registry::Registry;
+use protocol::{ChannelId, Packet, bytes::Bytes, types::PlayerStatsUpdate};
+
+use crate::{
+    net::{Server, channel::Channel},
+    player::Player,
+};
+
+/// Stats of a player that are shown in its HUD.
+/// Only explosions change these yet, and health doesn't come back.
+#[derive(Component, Copy, Clone, Debug)]
+pub struct PlayerStats {
+    pub health: u16,
+    pub max_health: u16,
+    pub hunger: u16,
+    pub max_hunger: u16,
+}
+
+impl Default for PlayerStats {
+    fn default() -> Self {
+        Self {
+            health: 20,
+            max_health: 20,
+            hunger: 20,
+            max_hunger: 20,
+        }
+    }
+}
+
+impl PlayerStats {
+    pub fn to_update(&self) -> PlayerStatsUpdate {
+        PlayerStatsUpdate {
+            health: self.health,
+            max_health: self.max_health,
+            hunger: self.hunger,
+            max_hunger: self.max_hunger,
+        }
+    }
+}
+
+/// Send the stats of players that changed, or just joined, to their client.
+pub fn send_player_stats(
+    channels: Res<Registry<Channel>>,
+    q: Query<(&Player, &PlayerStats), Changed<PlayerStats>>,
+    mut server: ResMut<Server>,
+) {
+    let channel: ChannelId = channels.resolve("player-stats").unwrap().into();
+    for (player, stats) in &q {
+        server.tcp_send(Packet {
+            payload: Bytes::copy_from_slice(bytemuck::bytes_of(&stats.to_update())),
+            session: player.session,
+            channel,
+        });
+    }
+}
@@ -1,26 +1,161 @@
 use bevy::prelude::*;
-use data::registry::Registry;
-use protocol::types::PlayerInputUpdate;
+use data::{blockstates::BlockState, registry::Registry};
+use math::collide::{Aabb, slide_aabb};
+use protocol::{
+    ChannelId, Packet,
+    bytes::Bytes,
+    types::{MovementState, PlayerInputUpdate, PlayerPositionCorrection},
+};
+use world::World;
 
 use crate::{
-    net::channel::Channel,
-    player::{Player, table::Players},
+    net::{Server, channel::Channel},
+    player::{
+        Player,
+        identity::{Operators, PlayerId},
+        owner::Owners,
+    },
 };
 
+/// Half of the width of the collision box of a player, same as on the client.
+const HALF_WIDTH: f32 = 0.3;
+
+/// Height of the collision box of a player, from the feet.
+const HEIGHT: f32 = 1.8;
+
+/// Height of the ledges a player can walk onto without jumping.
+const STEP_HEIGHT: f32 = 0.6;
+
+/// How far the reported position can be from where the player
+/// could have moved to, before the movement is rejected.
+const TOLERANCE: f32 = 0.1;
+
+/// Fastest a player can move, in voxels per second, which is falling at terminal velocity.
+const MAX_SPEED: f32 = 60.0;
+
+/// Most distance a player can save up while standing still, in voxels. Allows for updates
+/// that arrive late and all at once, and limits how far the collision check has to look.
+const MAX_MOVE_BUDGET: f32 = MAX_SPEED;
+
+/// Apply the position and rotation clients report for their player. Movements are checked
+/// against the distance the player could have moved since the last, and against solid blocks,
+/// unless they are flying, which only operators can.
+///
+/// When a movement is rejected, the client is sent the position the server kept, and the
+/// positions it sent before it moved back there are ignored.
 pub fn apply_input_updates(
     channels: Res<Registry<Channel>>,
-    players: Res<Players>,
-    mut q: Query<(&mut Transform, &mut Player)>,
+    owners: Owners,
+    operators: Res<Operators>,
+    world: Res<World>,
+    blocks: Res<Registry<BlockState>>,
+    time: Res<Time>,
+    mut server: ResMut<Server>,
+    mut q: Query<(&mut Transform, &mut Player, Option<&PlayerId>)>,
 ) {
+    let channel: ChannelId = channels.resolve("player-position").unwrap().into();
+
+    // players can move at most at `MAX_SPEED`, over any number of updates.
+    for (_, mut player, _) in &mut q {
+        player.move_budget =
+            (player.move_budget + MAX_SPEED * time.delta_secs()).min(MAX_MOVE_BUDGET);
+    }
+
     for packet in channels.get_by_name("player-input").unwrap() {
-        if let Some(update) = packet.cast::<PlayerInputUpdate>() {
-            if let Some(entity) = players.entity(packet.session) {
-                if let Ok((mut transform, mut player)) = q.get_mut(entity) {
-                    if player.version.update(update.version) {
-                        transform.translation = update.translation;
+        if let Some(update) = packet.cast::<PlayerInputUpdate>()
+            && let Some(entity) = owners.player(packet.session)
+            && owners.authorize(packet.session, entity)
+            && let Ok((mut transform, mut player, id)) = q.get_mut(entity)
+        {
+            if !update.translation.is_finite() || !update.look_dir.is_finite() {
+                warn!(
+                    "[S176] Player with session {} sent a position or rotation that isn't finite, the update was ignored.",
+                    packet.session
+                );
+                continue;
+            }
+
+            if player.version.update(update.version) {
+                let movement = match u8::try_from(update.movement)
+                    .ok()
+                    .and_then(MovementState::from_u8)
+                    .unwrap_or_default()
+                {
+                    MovementState::Flying if !operators.contains(id.copied()) => {
+                        MovementState::Walking
                     }
+                    movement => movement,
+                };
+
+                let from = transform.translation;
+                let to = update.translation;
+                let distance = from.distance(to);
+                let rejected = if update.correction != player.corrections {
+                    // sent before the client moved back to the last correction.
+                    false
+                } else if distance > player.move_budget + TOLERANCE {
+                    warn!(
+                        "[S177] Player with session {} moved {distance:.1} voxels from {from} to {to}, faster than they can, the movement was rejected.",
+                        packet.session
+                    );
+                    true
+                } else if movement == MovementState::Flying || can_move(&world, &blocks, from, to) {
+                    transform.translation = to;
+                    player.move_budget = (player.move_budget - distance).max(0.0);
+                    false
+                } else {
+                    warn!(
+                        "[S140] Player with session {} moved through solid blocks from {from} to {to}, the movement was rejected.",
+                        packet.session
+                    );
+                    true
+                };
+
+                if rejected {
+                    player.corrections = player.corrections.wrapping_add(1);
+                    let correction = PlayerPositionCorrection {
+                        correction: player.corrections,
+                        translation: from,
+                    };
+                    server.tcp_send(Packet {
+                        payload: Bytes::copy_from_slice(bytemuck::bytes_of(&correction)),
+                        session: packet.session,
+                        channel,
+                    });
                 }
+
+                transform.rotation = update.look_dir;
+                player.movement = movement;
             }
         }
     }
 }
+
+/// Whether a player could have moved between two positions without passing
+/// through solid blocks. The movement is replayed one axis at a time like the
+/// client does it, and again from the step height to allow walking up ledges.
+fn can_move(world: &World, blocks: &Registry<BlockState>, from: Vec3, to: Vec3) -> bool {
+    // voxels in unloaded chunks aren't solid, the client may have moved into them
+    // while the server unloaded them.
+    let solid = |pos: IVec3| {
+        world
+            .get_state(pos)
+            .and_then(|state| blocks.get(state.voxel))
+            .is_some_and(BlockState::blocks_movement)
+    };
+
+    let aabb = Aabb::from_feet(from, HALF_WIDTH, HEIGHT);
+    let (offset, _) = slide_aabb(&solid, aabb, to - from);
+    if (from + offset).distance(to) <= TOLERANCE {
+        return true;
+    }
+
+    // the client settles back down after stepping over the ledge.
+    let (raise, _) = slide_aabb(&solid, aabb, Vec3::Y * STEP_HEIGHT);
+    let aabb = aabb.translate(raise);
+    let (across, _) = slide_aabb(&solid, aabb, (to - from - raise).with_y(0.0));
+    let aabb = aabb.translate(across);
+    let moved = raise + across;
+    let (down, _) = slide_aabb(&solid, aabb, Vec3::Y * (to.y - from.y - moved.y));
+    (from + moved + down).distance(to) <= TOLERANCE
+}
@@ -0,0 +1,26 @@
+use bevy::prelude::*;
+use data::{
+    items::Item,
+    recipes::{Recipe, read_recipes},
+    registry::Registry,
+};
+
+use crate::packs::DataDirs;
+
+/// Read the recipes of the data directory and data packs.
+/// Runs on startup before the registries are snapshotted for the initial message,
+/// so every client is sent the names of the recipes that loaded, and on reload.
+/// Players that joined before a reload keep the recipe ids they were sent.
+pub fn load_recipes(
+    dirs: Res<DataDirs>,
+    items: Res<Registry<Item>>,
+    mut recipes: ResMut<Registry<Recipe>>,
+) {
+    let (loaded, errors) = read_recipes(&dirs.dirs(), &items);
+    for err in errors {
+        error!("{err}");
+    }
+
+    info!("Loaded {} recipes.", loaded.iter().count());
+    *recipes = loaded;
+}
@@ -0,0 +1,126 @@
+//! The tick loop of the server, which runs the app at the tick rate of the `Config`.
+//!
+//! Ticks are due at fixed times, rather than a fixed time after the last tick ended, so a
+//! tick that ran long is made up for by running the ticks after it sooner. After a stall,
+//! like loading a large region, up to `max_catch_up_ticks` missed ticks are run right after
+//! each other. Ticks missed beyond that are skipped, and the world time they would have
+//! advanced is made up gradually by `advance_world_time`, so the time of day doesn't fall
+//! behind the clock and clients see it speed up for a while instead of jumping.
+
+use std::{thread, time::Instant};
+
+use math::activity::Ema;
+
+use bevy::{
+    app::{App, AppExit, PluginsState},
+    prelude::*,
+};
+
+use crate::config::Config;
+
+/// Counts of the ticks the server ran, caught up on and skipped.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct TickStats {
+    /// Ticks run since the server started.
+    pub ticks: u64,
+
+    /// Ticks that started a tick or more after they were due, to catch up after a stall.
+    pub caught_up: u64,
+
+    /// Ticks that weren't run, because the server fell too far behind.
+    pub skipped: u64,
+
+    /// Skipped ticks whose world time hasn't been made up yet.
+    pub time_debt: u64,
+
+    /// Smoothed time ticks take, as a fraction of the length of a tick at the tick rate.
+    /// Above 1 when ticks take longer than the tick rate allows.
+    pub load: f32,
+}
+
+/// Runs the ticks of an app when they are due.
+pub struct TickClock {
+    /// When the next tick is due.
+    next: Instant,
+
+    /// Average of the time ticks take, rising quickly when they get slower,
+    /// so the server reacts to load before it falls behind.
+    load: Ema,
+}
+
+impl Default for TickClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TickClock {
+    /// A clock whose first tick is due now.
+    pub fn new() -> Self {
+        Self {
+            next: Instant::now(),
+            load: Ema::new(0.2, 0.05),
+        }
+    }
+
+    /// Wait until the next tick is due, then run it.
+    ///
+    /// If the server is more ticks behind than the config allows it to catch up on,
+    /// the ticks past that are skipped and counted in the `TickStats`.
+    pub fn run_tick(&mut self, app: &mut App) {
+        let config = app.world().resource::<Config>();
+        let (duration, max_catch_up) = (config.tick_duration(), config.max_catch_up_ticks as u64);
+
+        let now = Instant::now();
+        let behind = match now.checked_duration_since(self.next) {
+            Some(late) => (late.as_nanos() / duration.as_nanos()) as u64,
+            None => {
+                thread::sleep(self.next - now);
+                0
+            }
+        };
+
+        let skipped = behind.saturating_sub(max_catch_up);
+        if skipped > 0 {
+            warn!(
+                "[S159] Server is {behind} ticks behind, skipping {skipped} ticks. The time of day will catch up gradually."
+            );
+            self.next += duration * skipped as u32;
+        }
+        self.next += duration;
+
+        let mut stats = app.world_mut().resource_mut::<TickStats>();
+        stats.ticks += 1;
+        stats.skipped += skipped;
+        stats.time_debt += skipped;
+        if behind > 0 {
+            stats.caught_up += 1;
+        }
+
+        let started = Instant::now();
+        app.update();
+        let load = self
+            .load
+            .update(started.elapsed().as_secs_f32() / duration.as_secs_f32());
+        app.world_mut().resource_mut::<TickStats>().load = load;
+    }
+}
+
+/// Runner of a dedicated server, which runs ticks until the app exits.
+pub fn run(mut app: App) -> AppExit {
+    if app.plugins_state() != PluginsState::Cleaned {
+        while app.plugins_state() == PluginsState::Adding {
+            bevy::tasks::tick_global_task_pools_on_main_thread();
+        }
+        app.finish();
+        app.cleanup();
+    }
+
+    let mut clock = TickClock::new();
+    loop {
+        clock.run_tick(&mut app);
+        if let Some(exit) = app.should_exit() {
+            return exit;
+        }
+    }
+}
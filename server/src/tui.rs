@@ -1,15 +1,18 @@
-use std::{collections::VecDeque, io};
+use std::{collections::VecDeque, io, path::Path};
 
 use bevy::{
     ecs::system::Local,
-    log::{LogPlugin, info, tracing},
+    log::{info, tracing},
     prelude::{
         App, AppExit, Commands, IntoScheduleConfigs, Last, MessageWriter, Plugin, PreStartup,
         ResMut, Resource, Update, on_message,
     },
-    utils::default,
 };
 use crossbeam_channel::{Receiver, Sender, TrySendError};
+use data::{
+    fs::audit::CONSOLE_ISSUER,
+    logging::{LogLevels, LoggingPlugin, run_log_command},
+};
 use ratatui::{
     DefaultTerminal,
     crossterm::event::{self, Event, KeyCode, KeyModifiers},
@@ -18,6 +21,11 @@ use ratatui::{
     widgets::{Block, Paragraph},
 };
 
+use crate::{
+    audit::AuditLog,
+    config::{self, CONFIG_FILE},
+};
+
 pub struct TuiPlugin;
 
 impl Plugin for TuiPlugin {
@@ -26,7 +34,7 @@ impl Plugin for TuiPlugin {
         app
             .insert_resource(Terminal::default())
             .add_plugins(
-                LogPlugin {
+                LoggingPlugin {
                     custom_layer: move |app| {
                         Some(Box::new(LogCapture {
                             tx: app.main()
@@ -36,8 +44,7 @@ impl Plugin for TuiPlugin {
                                 .logs.tx.clone()
                         }))
                     },
-                    fmt_layer: move |_| None,
-                    ..default()
+                    ..config::logging_plugin(Path::new(CONFIG_FILE))
                 }
             )
             .add_systems(Update, render_tui)
@@ -48,6 +55,8 @@ impl Plugin for TuiPlugin {
 
 fn render_tui(
     mut terminal: ResMut<Terminal>,
+    mut log_levels: ResMut<LogLevels>,
+    mut audit: ResMut<AuditLog>,
     mut exit: MessageWriter<AppExit>,
     mut not_first: Local<bool>,
 ) {
@@ -73,7 +82,13 @@ fn render_tui(
                 }
                 KeyCode::Enter => {
                     if let Some(text) = terminal.prompt.submit() {
-                        info!("Submitted: {text}");
+                        audit.record_command(CONSOLE_ISSUER, &text);
+                        match text.split_whitespace().collect::<Vec<_>>()[..] {
+                            ["log", ref args @ ..] => {
+                                info!("{}", run_log_command(&mut log_levels, args));
+                            }
+                            _ => info!("Submitted: {text}"),
+                        }
                     }
                 }
                 KeyCode::Backspace => terminal.prompt.backspace(),
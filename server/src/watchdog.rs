@@ -0,0 +1,397 @@
+//! Tick watchdog for catching hung servers.
+//!
+//! A background thread watches a heartbeat that is updated at the start and end of
+//! every tick. If a tick runs longer than the threshold, a crash dump is written to
+//! the dump directory containing the phase of the tick that hung, recent tick times,
+//! the stack of every thread in the process (including the task pools) and their state.
+//!
+//! Stacks are read with `eu-stack` or `gdb`, if one of them is installed. Without them,
+//! only the kernel stacks in `/proc` are written, which are only readable by root.
+//!
+//! If `abort` is set, the process is aborted after the dump is written so the OS
+//! can produce a core dump. With `save`, the chunks that changed since the last
+//! autosave are written to their region files first, see `world::save`.
+//!
+//! The settings are the `[watchdog]` table of the config, and apply when it changes.
+
+use std::{
+    fmt::Write as _,
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        Arc, Mutex, PoisonError,
+        atomic::{AtomicBool, AtomicU8, AtomicU64, Ordering},
+    },
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use bevy::{app::MainScheduleOrder, ecs::schedule::ScheduleLabel, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{config::Config, events::ConfigChanged, world::save::UnsavedChunks};
+
+/// Settings of the watchdog, the `[watchdog]` table of the config.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WatchdogConfig {
+    /// Seconds a tick can run before it is considered hung.
+    pub threshold: u64,
+
+    /// Where crash dumps are written, relative to the working directory.
+    pub dump_dir: PathBuf,
+
+    /// Whether to abort the process after dumping.
+    pub abort: bool,
+
+    /// Whether to save the chunks that changed before aborting.
+    /// Only used with `abort`, since a tick that is only slow may still write them.
+    pub save: bool,
+}
+
+impl Default for WatchdogConfig {
+    fn default() -> Self {
+        Self {
+            threshold: 5,
+            dump_dir: PathBuf::from("crash-reports"),
+            abort: false,
+            save: true,
+        }
+    }
+}
+
+pub struct WatchdogPlugin;
+
+impl Plugin for WatchdogPlugin {
+    #[rustfmt::skip]
+    fn build(&self, app: &mut App) {
+        let heartbeat = Heartbeat::new();
+        let unsaved = app.world_mut().get_resource_or_init::<UnsavedChunks>().clone();
+        spawn_watchdog_thread(heartbeat.clone(), unsaved);
+
+        app
+            .insert_resource(heartbeat)
+            .add_systems(First, mark_tick_start)
+            .add_systems(PreUpdate, mark_phase::<{ Phase::PreUpdate as u8 }>)
+            .add_systems(Update, (
+                mark_phase::<{ Phase::Update as u8 }>,
+                apply_config_watchdog
+                    .run_if(on_message::<ConfigChanged>),
+            ))
+            .add_systems(PostUpdate, mark_phase::<{ Phase::PostUpdate as u8 }>)
+            .add_systems(Last, mark_phase::<{ Phase::Last as u8 }>)
+            .add_schedule(Schedule::new(WatchdogTickEnd))
+            .add_systems(WatchdogTickEnd, mark_tick_end)
+        ;
+
+        // Last runs user systems, so the end of the tick is marked in
+        // a schedule that runs after it.
+        app.world_mut()
+            .resource_mut::<MainScheduleOrder>()
+            .insert_after(Last, WatchdogTickEnd);
+    }
+}
+
+#[derive(ScheduleLabel, Clone, Debug, PartialEq, Eq, Hash)]
+struct WatchdogTickEnd;
+
+/// The part of the tick that was last entered.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[repr(u8)]
+enum Phase {
+    Idle = 0,
+    First = 1,
+    PreUpdate = 2,
+    Update = 3,
+    PostUpdate = 4,
+    Last = 5,
+}
+
+impl Phase {
+    fn from_u8(v: u8) -> Self {
+        match v {
+            1 => Self::First,
+            2 => Self::PreUpdate,
+            3 => Self::Update,
+            4 => Self::PostUpdate,
+            5 => Self::Last,
+            _ => Self::Idle,
+        }
+    }
+}
+
+/// Shared state between the main thread and the watchdog.
+#[derive(Resource, Clone)]
+struct Heartbeat(Arc<HeartbeatInner>);
+
+struct HeartbeatInner {
+    /// Reference point for all timestamps.
+    epoch: Instant,
+
+    /// Microseconds since epoch that the current tick started.
+    tick_start: AtomicU64,
+
+    /// Number of ticks completed.
+    tick: AtomicU64,
+
+    /// Whether a tick is currently in-progress.
+    running: AtomicBool,
+
+    /// Current Phase as a u8.
+    phase: AtomicU8,
+
+    /// Durations (micros) of the most recent ticks, as a ring buffer.
+    recent: [AtomicU64; 32],
+
+    /// Settings of the watchdog, from the config.
+    config: Mutex<WatchdogConfig>,
+}
+
+impl Heartbeat {
+    fn new() -> Self {
+        Self(Arc::new(HeartbeatInner {
+            epoch: Instant::now(),
+            tick_start: AtomicU64::new(0),
+            tick: AtomicU64::new(0),
+            running: AtomicBool::new(false),
+            phase: AtomicU8::new(Phase::Idle as u8),
+            recent: std::array::from_fn(|_| AtomicU64::new(0)),
+            config: Mutex::new(WatchdogConfig::default()),
+        }))
+    }
+
+    fn now_micros(&self) -> u64 {
+        self.0.epoch.elapsed().as_micros() as u64
+    }
+
+    fn config(&self) -> WatchdogConfig {
+        self.0
+            .config
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+}
+
+/// Apply the watchdog settings of the config when they change.
+fn apply_config_watchdog(config: Res<Config>, heartbeat: Res<Heartbeat>) {
+    *heartbeat
+        .0
+        .config
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner) = config.watchdog.clone();
+}
+
+fn mark_tick_start(heartbeat: Res<Heartbeat>) {
+    heartbeat
+        .0
+        .tick_start
+        .store(heartbeat.now_micros(), Ordering::Relaxed);
+    heartbeat
+        .0
+        .phase
+        .store(Phase::First as u8, Ordering::Relaxed);
+    heartbeat.0.running.store(true, Ordering::Release);
+}
+
+fn mark_phase<const P: u8>(heartbeat: Res<Heartbeat>) {
+    heartbeat.0.phase.store(P, Ordering::Relaxed);
+}
+
+fn mark_tick_end(heartbeat: Res<Heartbeat>) {
+    let elapsed = heartbeat.now_micros() - heartbeat.0.tick_start.load(Ordering::Relaxed);
+    let tick = heartbeat.0.tick.fetch_add(1, Ordering::Relaxed);
+    heartbeat.0.recent[tick as usize % 32].store(elapsed, Ordering::Relaxed);
+    heartbeat
+        .0
+        .phase
+        .store(Phase::Idle as u8, Ordering::Relaxed);
+    heartbeat.0.running.store(false, Ordering::Release);
+}
+
+fn spawn_watchdog_thread(heartbeat: Heartbeat, unsaved: UnsavedChunks) {
+    thread::Builder::new()
+        .name("watchdog".into())
+        .spawn(move || {
+            // tick that was last reported, so a single hang is only dumped once.
+            let mut reported = None;
+            loop {
+                let config = heartbeat.config();
+                let threshold = Duration::from_secs(config.threshold.max(1));
+                thread::sleep(threshold / 4);
+
+                if !heartbeat.0.running.load(Ordering::Acquire) {
+                    continue;
+                }
+
+                let tick = heartbeat.0.tick.load(Ordering::Relaxed);
+                let start = heartbeat.0.tick_start.load(Ordering::Relaxed);
+                let elapsed = Duration::from_micros(heartbeat.now_micros().saturating_sub(start));
+                if elapsed < threshold || reported == Some(tick) {
+                    continue;
+                }
+
+                reported = Some(tick);
+                let dump = build_crash_dump(&heartbeat, tick, elapsed);
+                error!(
+                    "[S901] Tick {tick} has been running for {elapsed:?}, the server may be hung."
+                );
+                match write_crash_dump(&config.dump_dir, &dump) {
+                    Ok(path) => error!("[S902] Crash dump written to '{}'.", path.display()),
+                    Err(e) => {
+                        error!("[S903] Failed to write crash dump with error: '{e}'.\n{dump}")
+                    }
+                }
+
+                if config.abort {
+                    if config.save {
+                        match unsaved.write_to_files() {
+                            Ok(saved) => warn!("Saved {saved} chunks of the hung server."),
+                            Err(e) => {
+                                error!("[S905] Failed to save the hung server with error: '{e}'.")
+                            }
+                        }
+                    }
+
+                    error!("[S904] Aborting hung server.");
+                    std::process::abort();
+                }
+            }
+        })
+        .expect("[S900] Failed to spawn watchdog thread.");
+}
+
+fn build_crash_dump(heartbeat: &Heartbeat, tick: u64, elapsed: Duration) -> String {
+    let mut out = String::new();
+    let phase = Phase::from_u8(heartbeat.0.phase.load(Ordering::Relaxed));
+    let _ = writeln!(out, "=== openvoxel server crash dump ===");
+    let _ = writeln!(out, "tick: {tick}");
+    let _ = writeln!(out, "elapsed: {elapsed:?}");
+    let _ = writeln!(out, "phase: {phase:?}");
+
+    let _ = writeln!(out, "\n--- recent tick times ---");
+    let n = tick.min(32);
+    for i in (tick - n)..tick {
+        let micros = heartbeat.0.recent[i as usize % 32].load(Ordering::Relaxed);
+        let _ = writeln!(out, "tick {i}: {:?}", Duration::from_micros(micros));
+    }
+
+    let _ = writeln!(out, "\n--- stacks ---");
+    write_stacks(&mut out);
+
+    let _ = writeln!(out, "\n--- threads ---");
+    write_thread_states(&mut out);
+    out
+}
+
+/// Dump the stack of every thread with the first debugger that can attach to the process.
+#[cfg(target_os = "linux")]
+fn write_stacks(out: &mut String) {
+    let pid = std::process::id().to_string();
+    let debuggers: [&[&str]; 2] = [
+        &["eu-stack", "-p", &pid],
+        &["gdb", "-p", &pid, "-batch", "-ex", "thread apply all bt"],
+    ];
+
+    for debugger in debuggers {
+        if let Some(stacks) = run_debugger(debugger) {
+            let _ = writeln!(out, "{}", stacks.trim_end());
+            return;
+        }
+    }
+    let _ = writeln!(out, "unavailable, install 'eu-stack' or 'gdb'");
+}
+
+/// Run a debugger on this process, returning what it printed if it succeeded.
+///
+/// Debuggers may only attach to processes that allow it, see `PR_SET_PTRACER`. The
+/// debugger waits in a shell until it is allowed, and keeps the pid of the shell.
+#[cfg(target_os = "linux")]
+fn run_debugger(args: &[&str]) -> Option<String> {
+    use std::{
+        io::Write as _,
+        process::{Command, Stdio},
+    };
+
+    let mut child = Command::new("sh")
+        .args(["-c", "read _ && exec \"$0\" \"$@\""])
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+
+    unsafe { libc::prctl(libc::PR_SET_PTRACER, child.id() as libc::c_ulong, 0, 0, 0) };
+    let started = child
+        .stdin
+        .take()
+        .is_some_and(|mut stdin| stdin.write_all(b"\n").is_ok());
+    let output = child.wait_with_output();
+    unsafe { libc::prctl(libc::PR_SET_PTRACER, 0, 0, 0, 0) };
+
+    let output = output
+        .ok()
+        .filter(|output| started && output.status.success())?;
+    let stacks = String::from_utf8_lossy(&output.stdout).into_owned();
+    (!stacks.trim().is_empty()).then_some(stacks)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn write_stacks(out: &mut String) {
+    let _ = writeln!(out, "unavailable on this platform");
+}
+
+/// Dump the name, state, kernel wait channel and kernel stack of every thread in the process.
+#[cfg(target_os = "linux")]
+fn write_thread_states(out: &mut String) {
+    let Ok(tasks) = fs::read_dir("/proc/self/task") else {
+        let _ = writeln!(out, "unavailable");
+        return;
+    };
+
+    for task in tasks.flatten() {
+        let path = task.path();
+        let read = |name: &str| {
+            fs::read_to_string(path.join(name))
+                .map(|s| s.trim().to_owned())
+                .unwrap_or_default()
+        };
+
+        let name = read("comm");
+        let state = read("status")
+            .lines()
+            .find_map(|line| line.strip_prefix("State:").map(|s| s.trim().to_owned()))
+            .unwrap_or_default();
+        let wchan = read("wchan");
+        let stack = read("stack");
+        let _ = writeln!(
+            out,
+            "[{}] '{name}' state={state} wchan={wchan}",
+            task.file_name().display()
+        );
+
+        // only readable with elevated privileges.
+        if !stack.is_empty() {
+            for line in stack.lines() {
+                let _ = writeln!(out, "    {line}");
+            }
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn write_thread_states(out: &mut String) {
+    let _ = writeln!(out, "unavailable on this platform");
+}
+
+fn write_crash_dump(dir: &Path, dump: &str) -> std::io::Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let path = dir.join(format!("hang-{secs}.txt"));
+    fs::write(&path, dump)?;
+    Ok(path)
+}
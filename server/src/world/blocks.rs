@@ -0,0 +1,84 @@
+use bevy::prelude::*;
+use data::{
+    blocks::{Block, def::read_block_defs, register_blocks},
+    blockstates::BlockState,
+    fs::save::RegistryIds,
+    registry::{Registry, RegistryId},
+    tags::{TagDefs, Tags},
+};
+
+use crate::{packs::DataDirs, world::loader::WorldLoader};
+
+/// Name of the registry of block states, whose ids are the `Voxel`s in region files.
+pub const BLOCK_STATES: &str = "block_states";
+
+/// Register the block states of the data directory and data packs,
+/// to know which block a `Voxel` is.
+/// The server doesn't draw blocks, so their textures are left at 0.
+///
+/// States keep the ids they had when the region files were written, and the ids of
+/// states that are gone are reserved, so voxels saved before still mean the same block.
+/// Panics if the saved ids can't be read, instead of saving new ones over them.
+pub fn load_blocks(
+    dirs: Res<DataDirs>,
+    loader: Res<WorldLoader>,
+    mut states: ResMut<Registry<BlockState>>,
+    mut blocks: ResMut<Registry<Block>>,
+) {
+    let (defs, errors) = read_block_defs(&dirs.dirs());
+    for err in errors {
+        error!("{err}");
+    }
+
+    let region_dir = loader.region_dir();
+    // they are the only record of what the saved voxels mean, so they must not be replaced.
+    let mut ids = RegistryIds::load(region_dir).unwrap_or_else(|e| {
+        panic!(
+            "[S130] Failed to read the registry ids in '{}', the world can't be opened without them: {e}",
+            region_dir.display()
+        )
+    });
+
+    states.reserve(ids.get(BLOCK_STATES), BlockState::empty);
+    for name in register_blocks(&defs, &mut states, &mut blocks, |_| 0) {
+        warn!(
+            "[S132] States of block '{name}' changed since the world was saved, its properties may pick the wrong state."
+        );
+    }
+
+    info!(
+        "Loaded {} block definitions with {} states.",
+        defs.len(),
+        states.iter().count()
+    );
+
+    ids.set(BLOCK_STATES, states.get_names());
+    if let Err(e) = ids.save(region_dir) {
+        error!(
+            "[S131] Failed to save the registry ids in '{}': {e}",
+            region_dir.display()
+        );
+    }
+}
+
+/// Read the block tags of the data directory and data packs, on startup and on reload.
+/// Tags list block names, and every state of a block has the tags of the block.
+pub fn load_block_tags(
+    dirs: Res<DataDirs>,
+    blocks: Res<Registry<Block>>,
+    mut tags: ResMut<Tags<BlockState>>,
+) {
+    let (defs, read_errors) = TagDefs::read(&dirs.dirs(), "blocks");
+    let (resolved, errors) = defs.resolve(|name| {
+        blocks
+            .get_by_name(name)
+            .map(|block| block.states.clone().map(RegistryId))
+    });
+
+    for err in read_errors.into_iter().chain(errors) {
+        error!("{err}");
+    }
+
+    info!("Loaded {} block tags.", resolved.len());
+    *tags = resolved;
+}
@@ -0,0 +1,100 @@
+//! Compression of the chunks sent to players, adapted to the load of the server.
+//!
+//! Zipping chunks at a high level is slow, so when ticks take most of their budget or many
+//! chunks are waiting to be sent, the level is lowered a step at a time, down to Lz4, which
+//! is much faster but makes larger packets. Once the server is idle again the level is
+//! raised back up to the `compression_level` of the config, which is never exceeded.
+//!
+//! Chunks keep the compression they were zipped with until they change, since their zip is
+//! cached, and chunks zipped under load are written to region files as they are.
+
+use bevy::prelude::*;
+use zip::{Algorithm, ZipLevel};
+
+use crate::{
+    config::Config,
+    tick::TickStats,
+    world::{loader::WorldLoader, subscriber::Subscriber},
+};
+
+/// Compressions from fastest to smallest.
+const STEPS: [(Algorithm, ZipLevel); 5] = [
+    (Algorithm::Lz4, ZipLevel::Medium),
+    (Algorithm::Zstd, ZipLevel::Low),
+    (Algorithm::Zstd, ZipLevel::Medium),
+    (Algorithm::Zstd, ZipLevel::High),
+    (Algorithm::Zstd, ZipLevel::Ultra),
+];
+
+/// The server is busy when ticks take more than this fraction of their budget...
+const BUSY_LOAD: f32 = 0.8;
+
+/// ...or more than this many chunks are waiting to be sent.
+const BUSY_QUEUED_CHUNKS: usize = 512;
+
+/// The server is idle when ticks take less than this fraction of their budget,
+/// and fewer than this many chunks are waiting to be sent.
+const IDLE_LOAD: f32 = 0.4;
+const IDLE_QUEUED_CHUNKS: usize = 64;
+
+/// The compression step currently used for chunks sent to players.
+#[derive(Resource, Default)]
+pub struct AdaptiveCompression {
+    /// Index in `STEPS`, or None before the first tick.
+    step: Option<usize>,
+
+    /// Tick the step last changed on.
+    changed: u64,
+}
+
+impl AdaptiveCompression {
+    /// The step of the compression level of the config.
+    fn ceiling(config: &Config) -> usize {
+        let level = config.zip_level().unwrap_or_default();
+        STEPS
+            .iter()
+            .rposition(|step| *step == (Algorithm::Zstd, level))
+            .unwrap_or(STEPS.len() - 1)
+    }
+}
+
+/// Lower the compression of chunks a step when the server is busy, and raise it a step when
+/// it is idle, at most once a second so a single slow tick doesn't change it.
+pub fn adapt_compression(
+    config: Res<Config>,
+    stats: Res<TickStats>,
+    subscriber: Res<Subscriber>,
+    mut adaptive: ResMut<AdaptiveCompression>,
+    mut loader: ResMut<WorldLoader>,
+) {
+    let ceiling = AdaptiveCompression::ceiling(&config);
+    let current = adaptive.step.unwrap_or(ceiling).min(ceiling);
+    let queued = subscriber.queued_chunks();
+
+    let step = if !config.adaptive_compression {
+        ceiling
+    } else if stats.ticks < adaptive.changed + config.tick_rate as u64 {
+        current
+    } else if stats.load > BUSY_LOAD || queued > BUSY_QUEUED_CHUNKS {
+        current.saturating_sub(1)
+    } else if stats.load < IDLE_LOAD && queued < IDLE_QUEUED_CHUNKS {
+        (current + 1).min(ceiling)
+    } else {
+        current
+    };
+
+    let (algorithm, level) = STEPS[step];
+    if adaptive.step != Some(step) {
+        if adaptive.step.is_some() {
+            debug!(
+                "Compressing chunks with {algorithm:?} at {level:?}, ticks take {:.0}% of their budget and {queued} chunks are queued.",
+                stats.load * 100.0
+            );
+        }
+        adaptive.step = Some(step);
+        adaptive.changed = stats.ticks;
+    }
+
+    loader.set_algorithm(algorithm);
+    loader.set_zip_level(level);
+}
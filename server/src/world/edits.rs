@@ -0,0 +1,193 @@
+use bevy::prelude::*;
+use data::{
+    blockstates::{BlockState, ModelData},
+    registry::Registry,
+};
+use math::{axis::Axis, line::VoxelLine};
+use protocol::{
+    ChannelId, Packet,
+    bytes::Bytes,
+    types::{BlockEditRequest, BlockUpdate},
+};
+use world::{World, region::chunk::flags::ChunkState, voxel::Voxel};
+
+use crate::{
+    audit::AuditLog,
+    events::BlockBroken,
+    net::{Server, channel::Channel},
+    player::{Player, identity::PlayerId, table::Players},
+    world::{protect::ProtectedRegions, subscriber::Subscriber},
+};
+
+/// How far (in voxels) a player may be from a block they edit.
+/// Larger than the client reach, to allow for latency in player movement.
+const MAX_EDIT_DISTANCE: f32 = 10.0;
+
+/// Height of the eyes of a player above their feet, same as on the client.
+const EYE_HEIGHT: f32 = 1.62;
+
+/// Validate and apply block edits requested by players, then send the
+/// authoritative state of the voxel to every player that can see it.
+pub fn apply_block_edits(
+    channels: Res<Registry<Channel>>,
+    players: Res<Players>,
+    subscriber: Res<Subscriber>,
+    blocks: Res<Registry<BlockState>>,
+    protections: Res<ProtectedRegions>,
+    q: Query<(&Transform, &PlayerId), With<Player>>,
+    mut audit: ResMut<AuditLog>,
+    mut world: ResMut<World>,
+    mut server: ResMut<Server>,
+    mut broken: MessageWriter<BlockBroken>,
+) {
+    let channel: ChannelId = channels.resolve("block-update").unwrap().into();
+    for packet in channels.get_by_name("block-edit").unwrap() {
+        let Some(request) = packet.cast::<BlockEditRequest>() else {
+            continue;
+        };
+
+        // players that haven't logged in yet can't be checked against protected regions.
+        let Some((transform, id)) = players
+            .entity(packet.session)
+            .and_then(|entity| q.get(entity).ok())
+        else {
+            continue;
+        };
+
+        // the target of a place is the voxel next to the face that was clicked.
+        let pos = match request.action {
+            BlockEditRequest::BREAK => request.pos,
+            BlockEditRequest::PLACE => match Axis::ALL.get(request.face as usize) {
+                Some(face) => request.pos + face.as_ivec3(),
+                None => request.pos,
+            },
+            _ => continue,
+        };
+
+        let eye = transform.translation + Vec3::Y * EYE_HEIGHT;
+        let in_reach = eye.distance(pos.as_vec3() + 0.5) <= MAX_EDIT_DISTANCE
+            && world
+                .get_chunk(pos.xz())
+                .is_some_and(|chunk| chunk.load_state() == ChunkState::Loaded)
+            && can_reach(&world, &blocks, eye, &request);
+
+        // edits out of reach are more likely lag than intent, so they aren't recorded.
+        let name = id.to_string();
+        let protected = !protections.allows(&name, pos.xz());
+        if in_reach && protected {
+            let action = match request.action {
+                BlockEditRequest::BREAK => "break",
+                _ => "place",
+            };
+            audit.record_violation(name, action, pos);
+        }
+        let allowed = in_reach && !protected;
+
+        let replaced = match allowed {
+            true => try_edit(&mut world, &blocks, pos, &request),
+            false => None,
+        };
+
+        if let Some(voxel) = replaced
+            && request.action == BlockEditRequest::BREAK
+        {
+            broken.write(BlockBroken {
+                pos,
+                voxel,
+                session: Some(packet.session),
+                drop_chance: 1.0,
+            });
+        }
+
+        // always answer the requester, so rejected edits are rolled back.
+        let Some(state) = world.get_state(pos) else {
+            continue;
+        };
+        let mut update = BlockUpdate {
+            pos,
+            voxel: state.voxel.0,
+            _pad: 0,
+            sequence: request.sequence,
+        };
+        server.tcp_send(Packet {
+            payload: Bytes::copy_from_slice(bytemuck::bytes_of(&update)),
+            session: packet.session,
+            channel,
+        });
+
+        if replaced.is_some() {
+            update.sequence = 0;
+            let payload = Bytes::copy_from_slice(bytemuck::bytes_of(&update));
+            for (session, _) in subscriber.in_draw_range(pos.xz()) {
+                if session != packet.session {
+                    server.tcp_send(Packet {
+                        payload: payload.clone(),
+                        session,
+                        channel,
+                    });
+                }
+            }
+        }
+    }
+}
+
+/// Whether the face the player clicked can be reached from their eyes without
+/// going through solid blocks. The line goes to the voxel in front of the face,
+/// and may pass through one solid voxel, since a line between voxel centers can
+/// clip the corner of a voxel the view of the player passes by.
+fn can_reach(
+    world: &World,
+    blocks: &Registry<BlockState>,
+    eye: Vec3,
+    request: &BlockEditRequest,
+) -> bool {
+    let Some(face) = Axis::ALL.get(request.face as usize) else {
+        // the player is inside the voxel.
+        return true;
+    };
+
+    let solid = |pos: IVec3| {
+        world
+            .get_state(pos)
+            .and_then(|state| blocks.get(state.voxel))
+            .is_some_and(BlockState::blocks_movement)
+    };
+
+    let front = request.pos + face.as_ivec3();
+    let line = VoxelLine::new(eye.floor().as_ivec3(), front);
+    line.skip(1).filter(|pos| solid(*pos)).count() <= 1
+}
+
+/// Apply the edit to the world, returning the voxel it replaced,
+/// or None if it isn't allowed.
+fn try_edit(
+    world: &mut World,
+    blocks: &Registry<BlockState>,
+    pos: IVec3,
+    request: &BlockEditRequest,
+) -> Option<Voxel> {
+    let current = world.get_state(pos)?.voxel;
+
+    let voxel = match request.action {
+        BlockEditRequest::BREAK if current != Voxel::AIR => Voxel::AIR,
+        BlockEditRequest::PLACE if current == Voxel::AIR => placeable(blocks, request.item)?,
+        _ => return None,
+    };
+
+    world.set_voxel(pos, voxel);
+    if let Some(chunk) = world.get_chunk_mut(pos.xz()) {
+        chunk.clear_cached_zip();
+    }
+
+    Some(current)
+}
+
+/// The voxel of a block state players can place, or None if there is no state with this
+/// id, or it has no model, like air. Ids come from the client, so they may be anything.
+fn placeable(blocks: &Registry<BlockState>, item: u16) -> Option<Voxel> {
+    let voxel = Voxel(item);
+    blocks
+        .get(voxel)
+        .filter(|state| !matches!(state.model, ModelData::Empty))
+        .map(|_| voxel)
+}
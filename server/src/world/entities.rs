@@ -0,0 +1,68 @@
+//! Indices of the positions of entities, for finding the ones near a point or in a chunk
+//! without checking every entity in the world.
+
+use bevy::prelude::*;
+use world::{chunk_index::ChunkIndex, knn::SpatialQuery, region::RegionId};
+
+use crate::player::stats::PlayerStats;
+
+/// Entities that can take damage, bucketed by region.
+/// Rebuilt at the start of every tick, so positions may be a tick old.
+#[derive(Resource, Default)]
+pub struct EntityIndex(SpatialQuery<Entity>);
+
+impl EntityIndex {
+    /// Entities whose indexed position is within `radius` of `center`.
+    pub fn within(&self, center: Vec3, radius: f32) -> Vec<Entity> {
+        let min = (center.xz() - radius).floor().as_ivec2() & !511;
+        let max = (center.xz() + radius).floor().as_ivec2() & !511;
+
+        let mut found = Vec::new();
+        for x in (min.x..=max.x).step_by(512) {
+            for z in (min.y..=max.y).step_by(512) {
+                for (entity, pos) in self.0.in_region(RegionId::new(IVec2::new(x, z))) {
+                    if pos.as_vec3().distance(center) <= radius {
+                        found.push(entity);
+                    }
+                }
+            }
+        }
+        found
+    }
+}
+
+/// Index the position of every entity with stats.
+pub fn rebuild_entity_index(
+    q: Query<(Entity, &Transform), With<PlayerStats>>,
+    mut index: ResMut<EntityIndex>,
+) {
+    for (entity, transform) in &q {
+        index
+            .0
+            .push(entity, transform.translation.floor().as_ivec3());
+    }
+    index.0.rebuild();
+}
+
+/// Every entity with a transform, listed by the chunk it is in, for systems that run per
+/// chunk. Entities are moved between chunks at the end of each tick in which they moved.
+#[derive(Resource, Default, Deref)]
+pub struct ChunkEntities(ChunkIndex<Entity>);
+
+/// Move the entities that moved to the chunk they are in now,
+/// and remove the entities that were despawned.
+pub fn update_chunk_entities(
+    q: Query<(Entity, &Transform), Changed<Transform>>,
+    mut removed: RemovedComponents<Transform>,
+    mut chunks: ResMut<ChunkEntities>,
+) {
+    for entity in removed.read() {
+        chunks.0.remove(entity);
+    }
+
+    for (entity, transform) in &q {
+        chunks
+            .0
+            .update(entity, transform.translation.floor().as_ivec3());
+    }
+}
@@ -0,0 +1,224 @@
+//! Explosions, which carve a rough sphere out of the world, drop the loot of
+//! some of the blocks they break, and damage the entities around them.
+//!
+//! Rays are cast from the center towards points on the surface of a cube. Each ray
+//! starts with about the power of the explosion, loses some of it with every step,
+//! and more for every block it passes through, depending on the hardness of the block.
+//! Blocks are broken while the ray still has power left, so hard blocks shield the
+//! blocks behind them, and the sphere has a ragged edge.
+
+use bevy::prelude::*;
+use data::{blockstates::BlockState, registry::Registry};
+use fxhash::FxHashSet;
+use math::{
+    line::VoxelLine,
+    rng::{BitRng, Rng},
+};
+use protocol::{ChannelId, Packet, types::MultiBlockUpdate};
+use world::{World, region::chunk::flags::ChunkState, voxel::Voxel};
+
+use crate::{
+    events::{BlockBroken, Explosion},
+    loot::LootRng,
+    net::{Server, channel::Channel},
+    player::stats::PlayerStats,
+    world::{entities::EntityIndex, subscriber::Subscriber},
+};
+
+/// Largest radius of an explosion, so every broken block fits in one `MultiBlockUpdate`.
+pub const MAX_RADIUS: f32 = 16.0;
+
+/// Rays are cast towards the points on the surface of a cube with this many points per side.
+const RAYS_PER_SIDE: i32 = 16;
+
+/// Distance a ray moves per step, in blocks.
+const STEP: f32 = 0.3;
+
+/// Power a ray loses per step in a block, in addition to the hardness of the block.
+const BLOCK_ABSORPTION: f32 = 0.3;
+
+/// Damage to an entity at the center of an explosion, per power.
+const DAMAGE_PER_POWER: f32 = 4.0;
+
+/// Heights above the feet of an entity that are checked for a clear line to the explosion.
+const EXPOSURE_SAMPLES: [f32; 3] = [0.1, 0.9, 1.6];
+
+/// Break the blocks around `center` reached by the rays of the explosion, returning each
+/// broken block and the voxel it was. Blocks in chunks that aren't loaded are left alone,
+/// and so are liquids, which stop rays.
+pub fn explode(
+    world: &mut World,
+    blocks: &Registry<BlockState>,
+    rng: &mut BitRng,
+    center: Vec3,
+    radius: f32,
+    power: f32,
+) -> Vec<(IVec3, Voxel)> {
+    let radius = radius.clamp(STEP, MAX_RADIUS);
+
+    // power a ray loses per step, so it runs out at the radius even in air.
+    let falloff = power * STEP / radius;
+
+    let mut seen = FxHashSet::default();
+    let mut carved = Vec::new();
+    for dir in ray_directions() {
+        let mut strength = power * rng.random_range(0.7..1.3);
+        let mut pos = center;
+        while strength > 0.0 {
+            let voxel_pos = pos.floor().as_ivec3();
+            let Some(state) = world.get_state(voxel_pos) else {
+                break;
+            };
+
+            if state.voxel != Voxel::AIR {
+                let Some(block) = blocks.get(state.voxel) else {
+                    break;
+                };
+                if block.liquid {
+                    break;
+                }
+
+                strength -= (block.hardness + BLOCK_ABSORPTION) * STEP;
+                if strength > 0.0 && seen.insert(voxel_pos) {
+                    carved.push((voxel_pos, state.voxel));
+                }
+            }
+
+            strength -= falloff;
+            pos += dir * STEP;
+        }
+    }
+
+    carved.retain(|(pos, _)| {
+        world
+            .get_chunk(pos.xz())
+            .is_some_and(|chunk| chunk.load_state() == ChunkState::Loaded)
+    });
+
+    let mut chunks = FxHashSet::default();
+    for (pos, _) in &carved {
+        world.set_voxel(*pos, Voxel::AIR);
+        chunks.insert(pos.xz() & !31);
+    }
+
+    for xz in chunks {
+        if let Some(chunk) = world.get_chunk_mut(xz) {
+            chunk.clear_cached_zip();
+        }
+    }
+
+    carved
+}
+
+/// Directions from the center to the points on the surface of a cube.
+fn ray_directions() -> impl Iterator<Item = Vec3> {
+    let last = RAYS_PER_SIDE - 1;
+    let points = (0..RAYS_PER_SIDE).flat_map(move |x| {
+        (0..RAYS_PER_SIDE).flat_map(move |y| (0..RAYS_PER_SIDE).map(move |z| IVec3::new(x, y, z)))
+    });
+
+    points
+        .filter(move |p| p.min_element() == 0 || p.max_element() == last)
+        .map(move |p| (p.as_vec3() / last as f32 * 2.0 - 1.0).normalize())
+}
+
+/// Damage to an entity `distance` from the center, before it's shielded by blocks.
+fn damage_at(distance: f32, radius: f32, power: f32) -> f32 {
+    let reach = radius * 2.0;
+    if distance >= reach {
+        return 0.0;
+    }
+
+    (1.0 - distance / reach) * power * DAMAGE_PER_POWER
+}
+
+/// Fraction of the heights of an entity with feet at `feet`
+/// that can be seen from the center without solid blocks in the way.
+fn exposure(world: &World, blocks: &Registry<BlockState>, center: Vec3, feet: Vec3) -> f32 {
+    let solid = |pos: IVec3| {
+        world
+            .get_state(pos)
+            .and_then(|state| blocks.get(state.voxel))
+            .is_some_and(BlockState::blocks_movement)
+    };
+
+    let from = center.floor().as_ivec3();
+    let visible = EXPOSURE_SAMPLES
+        .iter()
+        .filter(|height| {
+            let to = (feet + Vec3::Y * **height).floor().as_ivec3();
+            !VoxelLine::new(from, to).any(solid)
+        })
+        .count();
+
+    visible as f32 / EXPOSURE_SAMPLES.len() as f32
+}
+
+/// Carry out explosions. Broken blocks have a chance of `1 / power` to drop their loot,
+/// players that can see them are sent the blocks in one packet, and entities nearby
+/// lose health depending on their distance and how much of them is exposed.
+pub fn process_explosions(
+    channels: Res<Registry<Channel>>,
+    blocks: Res<Registry<BlockState>>,
+    subscriber: Res<Subscriber>,
+    index: Res<EntityIndex>,
+    mut world: ResMut<World>,
+    mut rng: ResMut<LootRng>,
+    mut server: ResMut<Server>,
+    mut explosions: MessageReader<Explosion>,
+    mut broken: MessageWriter<BlockBroken>,
+    mut q: Query<(&Transform, &mut PlayerStats)>,
+) {
+    let channel: ChannelId = channels.resolve("multi-block-update").unwrap().into();
+    for explosion in explosions.read() {
+        let radius = explosion.radius.min(MAX_RADIUS);
+        let carved = explode(
+            &mut world,
+            &blocks,
+            &mut rng.0,
+            explosion.center,
+            radius,
+            explosion.power,
+        );
+
+        let drop_chance = 1.0 / explosion.power.max(1.0);
+        for (pos, voxel) in &carved {
+            broken.write(BlockBroken {
+                pos: *pos,
+                voxel: *voxel,
+                session: None,
+                drop_chance,
+            });
+        }
+
+        if !carved.is_empty() {
+            let origin = explosion.center.floor().as_ivec3();
+            let payload = MultiBlockUpdate::encode(
+                origin,
+                carved.iter().map(|(pos, _)| (*pos, Voxel::AIR.0)),
+            );
+            for (session, _) in subscriber.in_draw_range(origin.xz()) {
+                server.tcp_send(Packet {
+                    payload: payload.clone(),
+                    session,
+                    channel,
+                });
+            }
+        }
+
+        for entity in index.within(explosion.center, radius * 2.0) {
+            let Ok((transform, mut stats)) = q.get_mut(entity) else {
+                continue;
+            };
+
+            let feet = transform.translation;
+            let distance = (feet + Vec3::Y * 0.9).distance(explosion.center);
+            let damage = damage_at(distance, radius, explosion.power)
+                * exposure(&world, &blocks, explosion.center, feet);
+            let damage = damage.round() as u16;
+            if damage != 0 {
+                stats.health = stats.health.saturating_sub(damage);
+            }
+        }
+    }
+}
@@ -13,52 +13,243 @@
 //! isn't what we want. This adds some complexity because chunks with structures
 //! must be generated first.
 
-use std::sync::Arc;
+use std::{io, sync::Arc};
 
 use bevy::prelude::*;
-use data::queue::PriorityQueue;
+use data::{fs::save::WorldInfo, queue::PriorityQueue, worldgen::biomes::Climate};
+use fxhash::FxHashMap;
 use math::{
     noise::simplex::{simplex2, simplex2_derivative},
-    rng::Permutation,
+    rng::{BitRng, Permutation, WorldRng},
+    spline::Spline,
 };
+use protocol::session::{Session, SessionMap};
 use world::{
     Voxel, World,
-    region::chunk::{ChunkId, flags::ChunkState},
+    region::chunk::{Chunk, ChunkId, flags::ChunkState},
 };
 
+use crate::{
+    config::Config,
+    world::{
+        generator::{biomes::Biomes, carvers::Carvers, ores::Ores},
+        loader::WorldLoader,
+    },
+};
+
+pub mod biomes;
+pub mod carvers;
+pub mod ores;
 pub mod structures;
 pub mod terrain;
 
+/// Scale of the climate noise, so climates change over thousands of blocks.
+const CLIMATE_SCALE: f32 = 0.002;
+
 #[derive(Resource)]
 pub struct WorldGenerator {
     queue: PriorityQueue<ChunkId, u32>,
+
+    /// Seeds the RNG of everything that is generated.
+    rng: WorldRng,
+
     perm1: Arc<Permutation>,
     perm2: Arc<Permutation>,
+
+    /// Seed the climate noise of attribution.
+    temperature: Arc<Permutation>,
+    humidity: Arc<Permutation>,
+
+    /// Maps the terrain noise, in the range [-1,1], to the height of the surface.
+    height: Spline,
+
+    /// The player that first requested each queued chunk.
+    owners: FxHashMap<ChunkId, Session>,
+
+    /// Number of chunks each player currently has queued.
+    counts: SessionMap<u32>,
+
+    /// Max number of chunks a single player can have queued at once.
+    /// Requests beyond this are dropped, but they'll be re-requested
+    /// by the subscriber once the player's queue drains.
+    per_player_quota: u32,
+
+    /// Every queued chunk has its priority bumped by one every
+    /// `aging_interval` ticks, so chunks behind a moving player
+    /// will eventually beat newly enqueued chunks and get generated.
+    aging_interval: u32,
+
+    /// Ticks since the last aging pass.
+    ticks_since_aging: u32,
 }
 
 impl WorldGenerator {
     pub fn from_entropy() -> Self {
-        Self {
-            queue: PriorityQueue::new(),
-            perm1: Permutation::from_entropy(),
-            perm2: Permutation::from_entropy(),
-        }
+        Self::with_rng(WorldRng::from_entropy())
     }
 
-    pub fn new(seed: u128) -> Self {
+    pub fn new(seed: u64) -> Self {
+        Self::with_rng(WorldRng::new(seed))
+    }
+
+    fn with_rng(rng: WorldRng) -> Self {
         Self {
             queue: PriorityQueue::default(),
-            perm1: Permutation::new(seed),
-            perm2: Permutation::new(seed ^ u128::from(0x8375897581235738_u64)),
+            rng,
+            perm1: rng.feature("terrain").permutation(),
+            perm2: rng.feature("elevation").permutation(),
+            temperature: rng.feature("temperature").permutation(),
+            humidity: rng.feature("humidity").permutation(),
+            height: Spline::linear([-1.0, -32.0], [1.0, 32.0]).unwrap(),
+            owners: FxHashMap::default(),
+            counts: SessionMap::new(),
+            per_player_quota: 64,
+            aging_interval: 8,
+            ticks_since_aging: 0,
+        }
+    }
+
+    /// The RNG streams of the world, which give the same
+    /// results no matter what order chunks are generated in.
+    pub fn rng(&self) -> WorldRng {
+        self.rng
+    }
+
+    /// The seed of the world.
+    pub fn seed(&self) -> u64 {
+        self.rng.seed()
+    }
+
+    /// An RNG for a feature of a chunk, like the ores in it, which
+    /// is the same every time the chunk is generated with this seed.
+    pub fn chunk_rng(&self, feature: &str, id: ChunkId) -> BitRng {
+        self.rng.feature(feature).column(id.as_ivec2()).rng()
+    }
+
+    /// Height of the surface of the terrain at a column, before caves are carved.
+    pub fn surface_height(&self, xz: IVec2) -> i32 {
+        let pt_scaled = xz.as_vec2() * 0.01;
+        self.height.sample(simplex2(&self.perm2, pt_scaled)) as i32
+    }
+
+    /// Temperature and humidity of a column, which pick its biome.
+    pub fn climate(&self, xz: IVec2) -> Climate {
+        let pt = xz.as_vec2() * CLIMATE_SCALE;
+        Climate {
+            temperature: simplex2(&self.temperature, pt).clamp(-1.0, 1.0),
+            humidity: simplex2(&self.humidity, pt).clamp(-1.0, 1.0),
+        }
+    }
+
+    /// Generate the terrain of a chunk and carve its caves, then build its surface
+    /// and place its features.
+    pub fn generate(&self, chunk: &mut Chunk, biomes: &Biomes, carvers: &Carvers, ores: &Ores) {
+        self.attribute(chunk, biomes);
+        self.generate_terrain(chunk);
+        carvers.carve(self.rng, chunk);
+        biomes.build_surface(chunk);
+        ores.place(self.rng, chunk);
+    }
+
+    /// Save the biome and the height of the surface of each column in the chunk.
+    fn attribute(&self, chunk: &mut Chunk, biomes: &Biomes) {
+        let (min_y, max_y) = (chunk.min_y(), chunk.max_y());
+        for xz in chunk.area() {
+            let height = self.surface_height(xz);
+            let column = chunk.get_column_mut(xz);
+            column.land_biome = biomes.pick(self.climate(xz), height);
+            column.height = height.clamp(min_y - 1, max_y - 1) as i16;
+        }
+    }
+
+    /// Fill the chunk with stone up to the height of the surface.
+    fn generate_terrain(&self, chunk: &mut Chunk) {
+        for pt in chunk.area() {
+            let y = chunk.get_column(pt).height as i32;
+            let mut top = ivec3(pt.x, y, pt.y);
+            while top.y >= chunk.min_y() {
+                chunk.set_voxel(top, Voxel(1));
+                top.y -= 1;
+            }
         }
     }
 
-    /// Enqueue a chunk for generation.
+    /// Set the curve that maps terrain noise to the height of the surface.
+    pub fn set_height_spline(&mut self, spline: Spline) {
+        self.height = spline;
+    }
+
+    /// Set the max number of chunks a single player can have queued.
+    pub fn set_per_player_quota(&mut self, quota: u32) {
+        self.per_player_quota = quota;
+    }
+
+    /// Set the number of ticks between priority boosts of queued chunks.
+    /// An interval of 0 is treated as 1.
+    pub fn set_aging_interval(&mut self, ticks: u32) {
+        self.aging_interval = ticks.max(1);
+    }
+
+    /// Number of chunks waiting to be generated.
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Enqueue a chunk for generation on behalf of a player.
     /// The "distance" is used to compute priority, and should be
     /// the chebyshev distance from the player to the chunk's origin.
-    pub fn enqueue(&mut self, id: impl Into<ChunkId>, distance: u32) {
+    ///
+    /// If the chunk is already queued, its priority is raised (never lowered),
+    /// so any boost it has received from aging is kept.
+    ///
+    /// Returns "false" if the chunk was not queued because the
+    /// player has exceeded their quota.
+    pub fn enqueue(&mut self, id: impl Into<ChunkId>, distance: u32, session: Session) -> bool {
+        let id = id.into();
         let prio = 16 - u32::min(distance >> 5, 15);
-        self.queue.push_increase(id.into(), prio);
+        if self.queue.get(&id).is_some() {
+            self.queue.push_increase(id, prio);
+            return true;
+        }
+
+        let count = self.counts.get_or_insert(session, || 0);
+        if *count >= self.per_player_quota {
+            return false;
+        }
+        *count += 1;
+
+        self.owners.insert(id, session);
+        self.queue.push(id, prio);
+        true
+    }
+
+    /// Pop the highest priority chunk, releasing it from its owner's quota.
+    fn pop(&mut self) -> Option<ChunkId> {
+        let (id, _) = self.queue.pop()?;
+        if let Some(session) = self.owners.remove(&id)
+            && let Some(count) = self.counts.get_mut(session)
+        {
+            *count = count.saturating_sub(1);
+            if *count == 0 {
+                self.counts.remove(session);
+            }
+        }
+
+        Some(id)
+    }
+
+    /// Advance the aging timer, bumping the priority of all queued
+    /// chunks if the interval has elapsed.
+    fn age(&mut self) {
+        self.ticks_since_aging += 1;
+        if self.ticks_since_aging < self.aging_interval {
+            return;
+        }
+
+        self.ticks_since_aging = 0;
+        for (_, prio) in self.queue.iter_mut() {
+            *prio = prio.saturating_add(1);
+        }
     }
 }
 
@@ -68,21 +259,65 @@ impl Default for WorldGenerator {
     }
 }
 
+/// Use the seed in the `world.toml` of the world the region files are in, or save the
+/// seed of the generator there if there isn't one yet, so a dedicated server generates
+/// the same terrain after it restarts. Only runs if the `ServerPlugin` has no seed.
+pub fn load_saved_seed(
+    loader: Res<WorldLoader>,
+    world: Res<World>,
+    mut generator: ResMut<WorldGenerator>,
+) {
+    let Some(world_dir) = loader.region_dir().parent() else {
+        return;
+    };
+
+    match WorldInfo::load(world_dir) {
+        Ok(info) => {
+            info!("Generating the world with its saved seed {}.", info.seed);
+            *generator = WorldGenerator::new(info.seed as u64);
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            let name = world_dir
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let seed = generator.seed() as i64;
+            let info = WorldInfo::new(name, seed, world.max_y() - world.min_y());
+            match info.save(world_dir) {
+                Ok(()) => info!("Saved the new world seed {seed}."),
+                Err(e) => error!(
+                    "[S164] Failed to save the world seed to '{}', the world will have another seed after a restart: {e}",
+                    world_dir.display()
+                ),
+            }
+        }
+        Err(e) => error!(
+            "[S163] Failed to read the world info in '{}', generating with the seed {}: {e}",
+            world_dir.display(),
+            generator.seed() as i64
+        ),
+    }
+}
+
+/// Apply the generation quota and aging interval of the config when they change.
+pub fn apply_config(config: Res<Config>, mut generator: ResMut<WorldGenerator>) {
+    generator.set_per_player_quota(config.generation_quota);
+    generator.set_aging_interval(config.generation_aging_interval);
+}
+
 pub fn process_world_generator_queue(
     mut generator: ResMut<WorldGenerator>,
+    biomes: Res<Biomes>,
+    carvers: Res<Carvers>,
+    ores: Res<Ores>,
     mut world: ResMut<World>,
 ) {
-    if let Some((id, _)) = generator.queue.pop() {
+    generator.age();
+    if let Some(id) = generator.pop() {
         if let Some(chunk) = world.get_chunk_mut(id.as_ivec2()) {
-            for pt in chunk.area() {
-                let pt_scaled = pt.as_vec2() * 0.01;
-                let y = (32.0 * (simplex2(&generator.perm2, pt_scaled))) as i32;
-                let mut top = ivec3(pt.x, y, pt.y);
-                while top.y >= chunk.min_y() {
-                    chunk.set_voxel(top, Voxel(1));
-                    top.y -= 1;
-                }
-            }
+            generator.generate(chunk, &biomes, &carvers, &ores);
+            // leaves room for the revision to be bumped by edits.
+            chunk.set_revision(WorldRng::from_entropy().seed() >> 1);
             *chunk.load_state_mut() = ChunkState::Loaded;
         }
     }
@@ -0,0 +1,121 @@
+//! Biomes of the attribution and surface stages, configured by the `worldgen/biomes` of the
+//! data packs.
+//!
+//! Attribution picks the biome of each column before its terrain is generated, and saves it
+//! in the column data of the chunk along with the height of its surface. After the caves are
+//! carved, the surface stage replaces the top blocks of each column with the surface layers
+//! of its biome, like grass on dirt, or sand on sandstone.
+
+use bevy::prelude::*;
+use data::{
+    blockstates::BlockState,
+    registry::Registry,
+    worldgen::{
+        biomes::{BiomeFeature, Climate, pick_biome},
+        read_worldgen,
+    },
+};
+use world::{BiomeId, Voxel, region::chunk::Chunk};
+
+use crate::packs::DataDirs;
+
+/// A biome with the block states of its surface layers resolved.
+pub struct Biome {
+    pub name: &'static str,
+    pub feature: BiomeFeature,
+
+    /// Voxel of each surface layer, and how many blocks deep it is.
+    pub surface: Vec<(Voxel, u32)>,
+}
+
+/// The biomes of the world, indexed by their `BiomeId`.
+#[derive(Resource, Default)]
+pub struct Biomes(pub Vec<Biome>);
+
+impl Biomes {
+    /// Resolve the block states of the surface layers of the biomes.
+    /// Layers of block states that don't exist are left out.
+    pub fn resolve(biomes: &Registry<BiomeFeature>, states: &Registry<BlockState>) -> Self {
+        let resolved = biomes.entries().map(|biome| {
+            let surface = biome
+                .surface
+                .iter()
+                .filter_map(|layer| match states.resolve(&layer.block) {
+                    Some(id) => Some((Voxel::from(id), layer.depth)),
+                    None => {
+                        // the default biomes name surface blocks that haven't been added yet.
+                        info!(
+                            "[S168] Biome '{}' has a surface layer of the unknown block state '{}', which was left out.",
+                            biome.name, layer.block
+                        );
+                        None
+                    }
+                })
+                .collect();
+
+            Biome {
+                name: biome.name,
+                feature: biome.item.clone(),
+                surface,
+            }
+        });
+
+        Self(resolved.collect())
+    }
+
+    pub fn get(&self, id: BiomeId) -> Option<&Biome> {
+        self.0.get(id.0 as usize)
+    }
+
+    /// The biome of a column with this climate and surface height.
+    /// Returns `BiomeId(0)` if there are no biomes.
+    pub fn pick(&self, climate: Climate, height: i32) -> BiomeId {
+        let picked = pick_biome(self.0.iter().map(|biome| &biome.feature), climate, height);
+        BiomeId(picked.unwrap_or(0) as u16)
+    }
+
+    /// Replace the top blocks of each column of a chunk with the surface layers of its biome,
+    /// and move the height of columns whose top was carved away down to their new surface.
+    ///
+    /// Layers stop at the first air below the surface, so the ceilings of caves
+    /// just below the surface keep the blocks they were carved out of.
+    pub fn build_surface(&self, chunk: &mut Chunk) {
+        for xz in chunk.area() {
+            let column = chunk.get_column(xz);
+            let mut pos = IVec3::new(xz.x, column.height as i32, xz.y);
+            while pos.y >= chunk.min_y() && chunk.get_voxel(pos) == Some(Voxel::AIR) {
+                pos.y -= 1;
+            }
+            chunk.get_column_mut(xz).height = pos.y.max(i16::MIN as i32) as i16;
+
+            let Some(biome) = self.get(column.land_biome) else {
+                continue;
+            };
+
+            'layers: for (voxel, depth) in &biome.surface {
+                for _ in 0..*depth {
+                    if chunk.get_voxel(pos).is_none_or(|v| v == Voxel::AIR) {
+                        break 'layers;
+                    }
+                    chunk.set_voxel(pos, *voxel);
+                    pos.y -= 1;
+                }
+            }
+        }
+    }
+}
+
+/// Read the biomes of the data directory and data packs, on startup and on reload.
+pub fn load_biomes(
+    dirs: Res<DataDirs>,
+    states: Res<Registry<BlockState>>,
+    mut biomes: ResMut<Biomes>,
+) {
+    let (loaded, errors) = read_worldgen(&dirs.dirs(), "biomes", BiomeFeature::parse);
+    for err in errors {
+        error!("{err}");
+    }
+
+    *biomes = Biomes::resolve(&loaded, &states);
+    info!("Loaded {} biomes.", biomes.0.len());
+}
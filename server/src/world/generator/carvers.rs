@@ -0,0 +1,134 @@
+//! Caves of the terrain stage, carved by the `worldgen/carvers` of the data packs.
+//!
+//! Carvers only sample noise at the position of each voxel, so a cave that crosses the edge
+//! of a chunk lines up with the part of it in the next chunk, no matter which is generated
+//! first. The noise of each carver is seeded by its name, so carvers don't carve the same caves.
+
+use bevy::prelude::*;
+use data::{
+    blockstates::BlockState,
+    registry::Registry,
+    worldgen::{carvers::CarverFeature, read_worldgen},
+};
+use math::{
+    noise::{simplex::simplex3, worley::Worley3},
+    rng::WorldRng,
+};
+use world::{Voxel, region::chunk::Chunk};
+
+use crate::packs::DataDirs;
+
+/// A carver with the fluid of its aquifer resolved.
+pub struct Carver {
+    pub name: &'static str,
+    pub feature: CarverFeature,
+
+    /// The Y below which carved voxels are filled with a fluid, and the voxel of the fluid.
+    /// None if the carver has no aquifer, or its fluid isn't a block yet.
+    pub aquifer: Option<(i32, Voxel)>,
+}
+
+/// The carvers that hollow caves out of new chunks.
+#[derive(Resource, Default)]
+pub struct Carvers(pub Vec<Carver>);
+
+impl Carvers {
+    /// Resolve the fluids of the carvers.
+    pub fn resolve(carvers: &Registry<CarverFeature>, states: &Registry<BlockState>) -> Self {
+        let resolved = carvers.entries().map(|carver| {
+            let aquifer = carver.aquifer.as_ref().and_then(|aquifer| {
+                let Some(id) = states.resolve(&aquifer.fluid) else {
+                    // not a warning, since the default carvers use water before it exists.
+                    info!(
+                        "[S167] Carver '{}' fills caves with the unknown block state '{}', they will be left empty.",
+                        carver.name, aquifer.fluid
+                    );
+                    return None;
+                };
+                Some((aquifer.level, Voxel::from(id)))
+            });
+
+            Carver {
+                name: carver.name,
+                feature: carver.item.clone(),
+                aquifer,
+            }
+        });
+
+        Self(resolved.collect())
+    }
+
+    /// Carve the caves of every carver out of a chunk.
+    pub fn carve(&self, rng: WorldRng, chunk: &mut Chunk) {
+        for carver in &self.0 {
+            carver.carve(rng.feature("carvers").feature(carver.name), chunk);
+        }
+    }
+}
+
+impl Carver {
+    /// Carve the caves of this carver out of a chunk.
+    pub fn carve(&self, rng: WorldRng, chunk: &mut Chunk) {
+        let min_y = self.feature.height[0].max(chunk.min_y());
+        let max_y = self.feature.height[1].min(chunk.max_y() - 1);
+        if min_y > max_y {
+            return;
+        }
+
+        let cheese = self
+            .feature
+            .cheese
+            .as_ref()
+            .map(|cheese| (cheese, rng.feature("cheese").permutation()));
+        let mut tunnels = self.feature.tunnels.as_ref().map(|tunnels| {
+            let scale = Vec3::new(1.0, tunnels.squash, 1.0) * tunnels.scale;
+            (
+                tunnels,
+                Worley3::new(rng.feature("tunnels").permutation(), scale),
+            )
+        });
+
+        for xz in chunk.area() {
+            for y in min_y..=max_y {
+                let pos = IVec3::new(xz.x, y, xz.y);
+                if chunk.get_voxel(pos).is_none_or(|voxel| voxel == Voxel::AIR) {
+                    continue;
+                }
+
+                let in_cheese = cheese.as_ref().is_some_and(|(cheese, perm)| {
+                    let pt = pos.as_vec3() * Vec3::new(1.0, cheese.squash, 1.0) * cheese.scale;
+                    simplex3(perm, pt) > cheese.threshold.sample(y as f32)
+                });
+                let in_tunnel = !in_cheese
+                    && tunnels.as_mut().is_some_and(|(tunnels, worley)| {
+                        // lines where three cells meet, rather than the faces where two do.
+                        let (_, l1, _, l3) = worley.l3(pos.as_vec3());
+                        l3 - l1 < tunnels.width.sample(y as f32)
+                    });
+
+                if in_cheese || in_tunnel {
+                    let voxel = match self.aquifer {
+                        Some((level, fluid)) if y < level => fluid,
+                        _ => Voxel::AIR,
+                    };
+                    chunk.set_voxel(pos, voxel);
+                }
+            }
+        }
+    }
+}
+
+/// Read the carvers of the data directory and data packs, on startup and on reload.
+pub fn load_carvers(
+    dirs: Res<DataDirs>,
+    states: Res<Registry<BlockState>>,
+    mut carvers: ResMut<Carvers>,
+) {
+    let (loaded, errors) = read_worldgen(&dirs.dirs(), "carvers", CarverFeature::parse);
+    for err in errors {
+        error!("{err}");
+    }
+
+    *carvers = Carvers::resolve(&loaded, &states);
+    info!("Loaded {} carvers.", carvers.0.len());
+}
@@ -0,0 +1,173 @@
+//! Ore veins of the features stage, configured by the `worldgen/ores` of the data packs.
+//!
+//! The centers of the veins of an ore are scattered over the world with blue noise, so they
+//! are spread evenly instead of clumping together, then each center gets a height picked by
+//! the height curve of the ore, and the vein grows from it with a random walk. Everything
+//! is rolled from the world seed, so a chunk gets the same ores every time it is generated.
+
+use bevy::prelude::*;
+use data::{
+    blocks::Block,
+    blockstates::BlockState,
+    registry::Registry,
+    tags::Tags,
+    worldgen::{ores::OreFeature, read_worldgen},
+};
+use fxhash::FxHashSet;
+use math::{
+    rng::{BitRng, Rng, WorldRng},
+    space::scatter::poisson_disk,
+};
+use world::{Voxel, region::chunk::Chunk};
+
+use crate::packs::DataDirs;
+
+/// Most times a height is rolled for a vein before it is skipped.
+const HEIGHT_ATTEMPTS: u32 = 8;
+
+/// An ore with its block states resolved.
+pub struct Ore {
+    pub name: &'static str,
+    pub feature: OreFeature,
+
+    /// The voxel placed for the ore.
+    pub voxel: Voxel,
+
+    /// The voxels the ore may replace.
+    pub replace: FxHashSet<Voxel>,
+}
+
+/// The ores placed into new chunks.
+#[derive(Resource, Default)]
+pub struct Ores(pub Vec<Ore>);
+
+impl Ores {
+    /// Resolve the block states of the ores, reporting the ones that can't be placed.
+    pub fn resolve(
+        ores: &Registry<OreFeature>,
+        states: &Registry<BlockState>,
+        blocks: &Registry<Block>,
+        tags: &Tags<BlockState>,
+    ) -> Self {
+        let mut resolved = Vec::new();
+        for ore in ores.entries() {
+            let Some(id) = states.resolve(&ore.block) else {
+                warn!(
+                    "[S165] Ore '{}' has the unknown block state '{}', so it won't be placed.",
+                    ore.name, ore.block
+                );
+                continue;
+            };
+
+            let replace = states
+                .entries()
+                .filter(|state| {
+                    if ore.replace.starts_with('#') {
+                        tags.contains(&ore.replace, state.id)
+                    } else {
+                        blocks
+                            .get(state.block)
+                            .is_some_and(|block| block.name == ore.replace)
+                    }
+                })
+                .map(|state| Voxel::from(state.id))
+                .collect::<FxHashSet<_>>();
+
+            if replace.is_empty() {
+                warn!(
+                    "[S166] Ore '{}' replaces '{}', which has no block states, so it won't be placed.",
+                    ore.name, ore.replace
+                );
+                continue;
+            }
+
+            resolved.push(Ore {
+                name: ore.name,
+                feature: ore.item.clone(),
+                voxel: Voxel::from(id),
+                replace,
+            });
+        }
+
+        Self(resolved)
+    }
+
+    /// Place the veins of every ore into a chunk.
+    pub fn place(&self, rng: WorldRng, chunk: &mut Chunk) {
+        for ore in &self.0 {
+            ore.place(rng.feature("ores").feature(ore.name), chunk);
+        }
+    }
+}
+
+impl Ore {
+    /// Place the veins of the ore that start in a chunk.
+    /// Veins are cut off at the edge of the chunk, so they never change generated chunks.
+    pub fn place(&self, rng: WorldRng, chunk: &mut Chunk) {
+        let per_chunk = self.feature.per_chunk;
+        if per_chunk == 0 {
+            return;
+        }
+
+        // cells of this size hold about one point each, about half of which are kept.
+        let spacing = (32.0 / (per_chunk as f32 * 2.0).sqrt()) as i32;
+        let area = chunk.area();
+        let centers = poisson_disk(rng, &area, spacing);
+        let height = chunk.max_y() - chunk.min_y();
+
+        for center in centers.into_iter().take(per_chunk as usize) {
+            let mut rng = rng.column(center).rng();
+            let Some(y) = self.roll_height(&mut rng, chunk.min_y(), height) else {
+                continue;
+            };
+
+            let mut pos = IVec3::new(center.x, y, center.y);
+            for _ in 0..self.feature.vein_size {
+                if area.contains(pos.xz())
+                    && chunk
+                        .get_voxel(pos)
+                        .is_some_and(|voxel| self.replace.contains(&voxel))
+                {
+                    chunk.set_voxel(pos, self.voxel);
+                }
+
+                let step = if rng.random::<bool>() { 1 } else { -1 };
+                match rng.random_range(0..3) {
+                    0 => pos.x += step,
+                    1 => pos.y += step,
+                    _ => pos.z += step,
+                }
+            }
+        }
+    }
+
+    /// Roll heights between `min_y` and `min_y + height` until one passes the height curve,
+    /// or None if none of the attempts did.
+    fn roll_height(&self, rng: &mut BitRng, min_y: i32, height: i32) -> Option<i32> {
+        for _ in 0..HEIGHT_ATTEMPTS {
+            let y = min_y + rng.random_range(0..height);
+            if rng.random::<f32>() < self.feature.chance_at(y) {
+                return Some(y);
+            }
+        }
+
+        None
+    }
+}
+
+/// Read the ores of the data directory and data packs, on startup and on reload.
+pub fn load_ores(
+    dirs: Res<DataDirs>,
+    states: Res<Registry<BlockState>>,
+    blocks: Res<Registry<Block>>,
+    tags: Res<Tags<BlockState>>,
+    mut ores: ResMut<Ores>,
+) {
+    let (loaded, errors) = read_worldgen(&dirs.dirs(), "ores", OreFeature::parse);
+    for err in errors {
+        error!("{err}");
+    }
+
+    *ores = Ores::resolve(&loaded, &states, &blocks, &tags);
+    info!("Loaded {} ores.", ores.0.len());
+}
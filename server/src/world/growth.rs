@@ -0,0 +1,189 @@
+//! Random ticks, and the growth of crops and saplings they drive.
+//!
+//! Every tick, a few random voxels of each subchunk with blocks are picked, in the loaded
+//! chunks in simulation range of a player. Picked voxels of blocks with `growth` in their
+//! definition may grow, by stepping a property like the age of a crop, or by being
+//! replaced by a structure template, like a sapling growing into a tree.
+
+use bevy::prelude::*;
+use data::{
+    blocks::Block, blockstates::BlockState, registry::Registry, structures::StructureTemplate,
+    tags::Tags,
+};
+use math::rng::{BitRng, Rng};
+use protocol::{ChannelId, Packet, types::MultiBlockUpdate};
+use world::{World, region::chunk::flags::ChunkState, voxel::Voxel};
+
+use crate::{
+    net::{Server, channel::Channel},
+    world::{structures::place_structure, subscriber::Subscriber},
+};
+
+/// Voxels picked in each subchunk with blocks per tick.
+const RANDOM_TICKS_PER_SUBCHUNK: usize = 3;
+
+/// Light level of voxels with nothing opaque above them.
+const SKY_LIGHT: u8 = 15;
+
+/// Rng used to pick voxels for random ticks, and roll their growth.
+#[derive(Resource)]
+pub struct RandomTickRng(pub BitRng);
+
+impl Default for RandomTickRng {
+    fn default() -> Self {
+        Self(BitRng::from_entropy())
+    }
+}
+
+/// Give random ticks to voxels near players, growing the ones that can grow,
+/// and send the voxels that changed to the players that can see them.
+pub fn random_tick_growth(
+    states: Res<Registry<BlockState>>,
+    blocks: Res<Registry<Block>>,
+    tags: Res<Tags<BlockState>>,
+    structures: Res<Registry<StructureTemplate>>,
+    channels: Res<Registry<Channel>>,
+    subscriber: Res<Subscriber>,
+    mut world: ResMut<World>,
+    mut rng: ResMut<RandomTickRng>,
+    mut server: ResMut<Server>,
+) {
+    let rng = &mut rng.0;
+    let mut ticked = Vec::new();
+    for region in world.regions() {
+        for chunk in region.chunks() {
+            let center = chunk.origin().xz() + 16;
+            if chunk.load_state() != ChunkState::Loaded
+                || subscriber.in_simulation_range(center).next().is_none()
+            {
+                continue;
+            }
+
+            for subchunk in chunk {
+                if subchunk.is_empty() {
+                    continue;
+                }
+
+                for _ in 0..RANDOM_TICKS_PER_SUBCHUNK {
+                    let offset =
+                        IVec3::new(rng.take(5) as i32, rng.take(5) as i32, rng.take(5) as i32);
+                    let pos = subchunk.origin() + offset;
+                    let voxel = subchunk.get_voxel(pos);
+                    if can_grow(&states, &blocks, voxel) {
+                        ticked.push((pos, voxel));
+                    }
+                }
+            }
+        }
+    }
+
+    let data = GrowthData {
+        states: &states,
+        blocks: &blocks,
+        tags: &tags,
+        structures: &structures,
+    };
+
+    let channel: ChannelId = channels.resolve("multi-block-update").unwrap().into();
+    for (pos, voxel) in ticked {
+        let Some(changed) = data.grow(&mut world, rng, pos, voxel) else {
+            continue;
+        };
+
+        let payload =
+            MultiBlockUpdate::encode(pos, changed.iter().map(|(pos, voxel)| (*pos, voxel.0)));
+        for (session, _) in subscriber.in_draw_range(pos.xz()) {
+            server.tcp_send(Packet {
+                payload: payload.clone(),
+                session,
+                channel,
+            });
+        }
+    }
+}
+
+fn can_grow(states: &Registry<BlockState>, blocks: &Registry<Block>, voxel: Voxel) -> bool {
+    voxel != Voxel::AIR
+        && states
+            .get(voxel)
+            .and_then(|state| blocks.get(state.block))
+            .is_some_and(|block| block.growth.is_some())
+}
+
+/// The registries a block needs to grow.
+struct GrowthData<'a> {
+    states: &'a Registry<BlockState>,
+    blocks: &'a Registry<Block>,
+    tags: &'a Tags<BlockState>,
+    structures: &'a Registry<StructureTemplate>,
+}
+
+impl GrowthData<'_> {
+    /// Roll the growth of a voxel, and grow it if its conditions pass,
+    /// returning the voxels that changed.
+    fn grow(
+        &self,
+        world: &mut World,
+        rng: &mut BitRng,
+        pos: IVec3,
+        voxel: Voxel,
+    ) -> Option<Vec<(IVec3, Voxel)>> {
+        let state = self.states.get(voxel)?;
+        let block = self.blocks.get(state.block)?;
+        let growth = block.growth.as_ref()?;
+
+        if rng.random::<f32>() >= growth.chance {
+            return None;
+        }
+
+        let below = world.get_state(pos - IVec3::Y)?.voxel;
+        let on_soil =
+            growth.soil.is_empty() || growth.soil.iter().any(|soil| self.is_soil(soil, below));
+        if !on_soil || sky_light(world, self.states, pos) < growth.min_light {
+            return None;
+        }
+
+        if let Some(property) = &growth.property
+            && let Some(next) = block.with_next(state, property)
+        {
+            let next = Voxel::from(next);
+            world.set_voxel(pos, next);
+            if let Some(chunk) = world.get_chunk_mut(pos.xz()) {
+                chunk.clear_cached_zip();
+            }
+            return Some(vec![(pos, next)]);
+        }
+
+        let template = self.structures.get_by_name(growth.structure.as_ref()?)?;
+        place_structure(world, self.states, template, pos)
+    }
+
+    /// Whether a voxel is the block, or has the tag with a leading '#', named by `soil`.
+    fn is_soil(&self, soil: &str, voxel: Voxel) -> bool {
+        if soil.starts_with('#') {
+            return self.tags.contains(soil, voxel);
+        }
+
+        self.states
+            .get(voxel)
+            .and_then(|state| self.blocks.get(state.block))
+            .is_some_and(|block| block.name == soil)
+    }
+}
+
+/// Light level of a voxel, for the light a block needs to grow. The server doesn't
+/// spread light, so this is the sky light when nothing opaque is above the voxel,
+/// and 0 otherwise.
+fn sky_light(world: &World, states: &Registry<BlockState>, pos: IVec3) -> u8 {
+    let covered = (pos.y + 1..world.max_y()).any(|y| {
+        world
+            .get_state(IVec3::new(pos.x, y, pos.z))
+            .and_then(|state| states.get(state.voxel))
+            .is_some_and(BlockState::is_opaque_cube)
+    });
+
+    match covered {
+        true => 0,
+        false => SKY_LIGHT,
+    }
+}
@@ -16,8 +16,18 @@
 //! grow, or shrink spans of pages for chunks.
 //!
 //! Each region will use the file extension `.ovr`, short for 'openvoxel region'
-
-use std::{cmp::Ordering::*, fs, io, ops::Range, path::PathBuf, sync::Arc};
+//!
+//! The `Header` of a file can be checked and repaired without loading it, which
+//! is what the `ovr-tool` binary does.
+
+use std::{
+    cmp::Ordering::*,
+    collections::hash_map::Entry,
+    fs, io,
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 use bevy::{
     prelude::*,
@@ -25,6 +35,7 @@ use bevy::{
 };
 use bytemuck::{Pod, Zeroable};
 use fxhash::FxHashMap;
+use math::morton;
 use memmap2::{MmapMut, MmapOptions};
 use protocol::bytes::Bytes;
 use world::{
@@ -33,7 +44,7 @@ use world::{
 };
 use zip::{Algorithm, ZipLevel};
 
-use crate::events::RegionLoaded;
+use crate::{config::Config, events::RegionLoaded};
 
 /// Resource for loading and saving regions.
 #[derive(Resource)]
@@ -57,11 +68,25 @@ pub struct WorldLoader {
     loaded: FxHashMap<RegionId, RegionFile>,
 
     /// Priority Queue of regions to be loaded into the World.
-    /// This is dequeued just by iterating and taking the max prio.
+    /// This is dequeued by iterating and taking the highest prios.
     queue: FxHashMap<RegionId, LoadTask>,
+
+    /// Max number of region loads that can be running at once.
+    max_concurrent_loads: usize,
 }
 
 impl WorldLoader {
+    /// Set the directory regions are loaded from and saved to.
+    /// Regions that are already loaded are not moved.
+    pub fn set_region_dir(&mut self, dir: impl Into<PathBuf>) {
+        self.region_dir = Arc::new(dir.into());
+    }
+
+    /// Directory regions are loaded from and saved to.
+    pub fn region_dir(&self) -> &Path {
+        &self.region_dir
+    }
+
     pub fn algorithm(&self) -> Algorithm {
         self.algorithm
     }
@@ -70,6 +95,18 @@ impl WorldLoader {
         self.zip_level
     }
 
+    /// Change the compression level of chunks zipped from now on.
+    /// Chunks that were already zipped keep their level.
+    pub fn set_zip_level(&mut self, level: ZipLevel) {
+        self.zip_level = level;
+    }
+
+    /// Change the compression algorithm of chunks zipped from now on.
+    /// Chunks that were already zipped keep their algorithm, which is in their header.
+    pub fn set_algorithm(&mut self, algorithm: Algorithm) {
+        self.algorithm = algorithm;
+    }
+
     /// Request a region to be loaded, with a distance to determine priority.
     ///
     /// The distance should be the chebyshev distance from the requesting player
@@ -163,39 +200,60 @@ impl WorldLoader {
         self.write_chunk_raw(id, &data.0)
     }
 
+    /// Set the max number of region loads that can be in-flight at once.
+    /// A limit of 0 is treated as 1, otherwise nothing would ever load.
+    pub fn set_max_concurrent_loads(&mut self, limit: usize) {
+        self.max_concurrent_loads = limit.max(1);
+    }
+
+    /// The max number of region loads that can be in-flight at once.
+    pub fn max_concurrent_loads(&self) -> usize {
+        self.max_concurrent_loads
+    }
+
     fn process_queues(&mut self, evs: &mut MessageWriter<RegionLoaded>, world: &mut World) {
-        let mut max_prio: Option<(RegionId, u32)> = None;
+        let mut finished = Vec::new();
+        let mut pending = Vec::new();
+        let mut running = 0;
 
-        // dequeue completed region load tasks and find highest priority pending task.
-        for (id, task) in self.queue.extract_if(|id, task| match task {
-            LoadTask::Pending(prio) => {
-                if max_prio.is_none_or(|(_, curr_max)| *prio > curr_max) {
-                    max_prio = Some((*id, *prio));
-                }
-                false
-            }
-            LoadTask::Running(task) => task.is_finished(),
-        }) {
+        // poll running tasks without blocking and collect pending tasks by priority.
+        for (id, task) in self.queue.iter_mut() {
             match task {
-                LoadTask::Pending(_) => unreachable!(),
-                LoadTask::Running(task) => match futures_lite::future::block_on(task) {
-                    Err(e) => panic!("[S777] Failed to load region: '{e:?}'"),
-                    Ok(file) => {
-                        info!("FINISHED LOADING REGION: {}", id.as_ivec2());
-                        let header = file.header();
-                        let region = Box::new(Region::new(header.origin, header.height as i32));
-                        evs.write(RegionLoaded(id));
-                        world.insert(region);
-                        self.loaded.insert(id, file);
+                LoadTask::Pending(prio) => pending.push((*id, *prio)),
+                LoadTask::Running(task) => {
+                    match futures_lite::future::block_on(futures_lite::future::poll_once(task)) {
+                        Some(result) => finished.push((*id, result)),
+                        None => running += 1,
                     }
-                },
+                }
             }
         }
 
-        // start the region load task for the highest priority pending task
-        // that was found in the previous step.
-        if let Some((id, _)) = max_prio {
-            let task_pool = IoTaskPool::get();
+        // insert completed regions into the world.
+        for (id, result) in finished {
+            self.queue.remove(&id);
+            match result {
+                Err(e) => panic!("[S777] Failed to load region: '{e:?}'"),
+                Ok(file) => {
+                    info!("FINISHED LOADING REGION: {}", id.as_ivec2());
+                    let header = file.header();
+                    let region = Box::new(Region::new(header.origin, header.height as i32));
+                    evs.write(RegionLoaded(id));
+                    world.insert(region);
+                    self.loaded.insert(id, file);
+                }
+            }
+        }
+
+        // start as many of the highest priority pending tasks as the concurrency limit allows.
+        let available = self.max_concurrent_loads.saturating_sub(running);
+        if available == 0 || pending.is_empty() {
+            return;
+        }
+
+        pending.sort_unstable_by(|(_, a), (_, b)| b.cmp(a));
+        let task_pool = IoTaskPool::get();
+        for (id, _) in pending.into_iter().take(available) {
             if let Some(task) = self.queue.get_mut(&id) {
                 info!("STARTED LOADING REGION: {}", id.as_ivec2());
                 *task = LoadTask::Running(task_pool.spawn(RegionFile::load_async(
@@ -217,10 +275,17 @@ impl Default for WorldLoader {
             zip_level: ZipLevel::default(),
             loaded: FxHashMap::default(),
             queue: FxHashMap::default(),
+            max_concurrent_loads: 4,
         }
     }
 }
 
+/// Apply the region load limit of the config when it changes.
+pub fn apply_config(config: Res<Config>, mut loader: ResMut<WorldLoader>) {
+    loader.set_max_concurrent_loads(config.max_concurrent_region_loads as usize);
+}
+
+/// Start loading queued regions, and insert the regions that finished loading into the world.
 pub fn process_loader_queues(
     mut loader: ResMut<WorldLoader>,
     mut world: ResMut<World>,
@@ -229,6 +294,36 @@ pub fn process_loader_queues(
     loader.process_queues(&mut evs, &mut world);
 }
 
+/// Write zipped chunks straight to their region files, opening the files again instead
+/// of using the `WorldLoader`, and return how many were written. Only for the watchdog,
+/// which saves the world while a hung tick may still hold the loader.
+pub(crate) fn write_chunks_to_files<'a>(
+    dir: &Path,
+    min_y: i32,
+    height: i32,
+    chunks: impl IntoIterator<Item = (&'a ChunkId, &'a ZippedChunk)>,
+) -> io::Result<usize> {
+    let dir = Arc::new(dir.to_path_buf());
+    let mut files: FxHashMap<RegionId, RegionFile> = FxHashMap::default();
+    let mut written = 0;
+    for (id, zip) in chunks {
+        let region = id.to_region_id();
+        let file = match files.entry(region) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(RegionFile::load(
+                dir.clone(),
+                region.as_ivec3(min_y),
+                height,
+            )?),
+        };
+        file.write_segment(id.to_chunk_idx(), &zip.0);
+        written += 1;
+    }
+
+    // the files are flushed when they are dropped.
+    Ok(written)
+}
+
 #[derive(Debug, Clone)]
 pub enum ChunkReadError {
     /// The containing region of the chunk did not exist.
@@ -366,9 +461,14 @@ impl RegionFile {
     fn check_size(&mut self) {
         if self.page_count < self.header().total_pages {
             self.page_count = self.header().total_pages;
+            let len = (self.page_count as u64 + 1) << 12;
             self.file
-                .set_len((self.page_count as u64 + 1) << 12)
+                .set_len(len)
                 .expect("[S198] Failed to set length of file.");
+
+            // the map only covers the length the file had when it was mapped.
+            self.map = unsafe { MmapOptions::new().len(len as usize).map_mut(&self.file) }
+                .expect("[S199] Failed to map the grown region file.");
         }
     }
 
@@ -407,7 +507,7 @@ impl Drop for RegionFile {
 /// Size is 4096 bytes.
 #[derive(Copy, Clone, Pod, Zeroable)]
 #[repr(C)]
-struct Header {
+pub struct Header {
     /// Magic factor for corruption prevention.
     magic: u32,
 
@@ -455,6 +555,217 @@ struct Header {
 impl Header {
     const MAGIC: u32 = 0xabcddcba;
 
+    /// Size of the header, which is also the size of a page.
+    pub const SIZE: usize = 4096;
+
+    /// Read the header at the start of a region file.
+    /// Returns None if the file is shorter than a header.
+    pub fn read(file: &[u8]) -> Option<Self> {
+        file.get(..Self::SIZE).map(bytemuck::pod_read_unaligned)
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        bytemuck::bytes_of(self)
+    }
+
+    pub fn is_magic_valid(&self) -> bool {
+        self.magic == Self::MAGIC
+    }
+
+    pub fn magic(&self) -> u32 {
+        self.magic
+    }
+
+    pub fn version(&self) -> u16 {
+        self.version
+    }
+
+    pub fn height(&self) -> i32 {
+        self.height as i32
+    }
+
+    pub fn origin(&self) -> IVec3 {
+        self.origin
+    }
+
+    pub fn created_at(&self) -> u64 {
+        self.created_at
+    }
+
+    pub fn last_modified_at(&self) -> u64 {
+        self.last_modified_at
+    }
+
+    pub fn total_pages(&self) -> u16 {
+        self.total_pages
+    }
+
+    pub fn free_list_head(&self) -> u16 {
+        self.free_list_head
+    }
+
+    pub fn empty_cursor(&self) -> u16 {
+        self.empty_cursor
+    }
+
+    /// The segment of the chunk at this index, see `ChunkId::to_chunk_idx`.
+    pub fn segment(&self, index: usize) -> Segment {
+        self.segments[index]
+    }
+
+    /// Forget the data of the chunk at this index, without freeing its pages.
+    /// The free list needs to be rebuilt after.
+    pub fn clear_segment(&mut self, index: usize) {
+        self.segments[index] = Segment::zeroed();
+    }
+
+    /// The blocks of the free list, with their index in it, in link order.
+    pub fn free_blocks(&self) -> Result<Vec<(usize, Block)>, RegionProblem> {
+        let mut blocks = Vec::new();
+        let mut visited = [false; 256];
+        let mut curr = self.free_list_head as usize;
+        loop {
+            let Some(block) = self.free_list.get(curr) else {
+                return Err(RegionProblem::FreeListIndexOutOfBounds(curr));
+            };
+            if std::mem::replace(&mut visited[curr], true) {
+                return Err(RegionProblem::FreeListCycle(curr));
+            }
+
+            blocks.push((curr, *block));
+            if block.next == u16::MAX {
+                return Ok(blocks);
+            }
+            curr = block.next as usize;
+        }
+    }
+
+    /// Check the header for corruption, and that the file is `file_len` bytes large enough.
+    pub fn problems(&self, file_len: u64) -> Vec<RegionProblem> {
+        let mut problems = Vec::new();
+        if !self.is_magic_valid() {
+            problems.push(RegionProblem::BadMagic(self.magic));
+        }
+
+        if file_len < self.size() {
+            problems.push(RegionProblem::FileTooSmall {
+                expected: self.size(),
+                found: file_len,
+            });
+        }
+
+        // segments must be in the file, and not share pages.
+        let mut used = Vec::new();
+        for (index, segment) in self.segments.iter().enumerate() {
+            if segment.padding as u32 > (segment.count as u32) << 12 {
+                problems.push(RegionProblem::BadPadding(index));
+            } else if segment.count != 0 {
+                if segment.end_u32() > self.total_pages as u32 {
+                    problems.push(RegionProblem::SegmentOutOfBounds(index));
+                } else {
+                    used.push((segment.start as u32, segment.end_u32(), index));
+                }
+            }
+        }
+
+        used.sort_unstable();
+        for pair in used.windows(2) {
+            if pair[1].0 < pair[0].1 {
+                problems.push(RegionProblem::SegmentsOverlap(pair[0].2, pair[1].2));
+            }
+        }
+
+        // free blocks must be sorted, and not share pages with each other or segments.
+        match self.free_blocks() {
+            Err(problem) => problems.push(problem),
+            Ok(blocks) => {
+                let mut prev_end = 0;
+                for (index, block) in &blocks {
+                    let (start, end) =
+                        (block.start as u32, block.start as u32 + block.count as u32);
+                    if start < prev_end {
+                        problems.push(RegionProblem::FreeListUnsorted(*index));
+                    }
+                    prev_end = end;
+
+                    if let Some((_, _, segment)) =
+                        used.iter().find(|(s, e, _)| *s < end && start < *e)
+                    {
+                        problems.push(RegionProblem::FreeBlockOverlapsSegment {
+                            block: *index,
+                            segment: *segment,
+                        });
+                    }
+                }
+
+                // the last block must reach the end of the address space, or nothing can be allocated past it.
+                if let Some((index, last)) = blocks.last()
+                    && last.start as u32 + last.count as u32 != u16::MAX as u32
+                {
+                    problems.push(RegionProblem::FreeListNotTerminated(*index));
+                }
+            }
+        }
+
+        problems
+    }
+
+    /// Rebuild the free list from the segments, so every page that isn't in a segment is free.
+    /// Segments that are out of bounds or overlap another need to be cleared first.
+    pub fn rebuild_free_list(&mut self) -> Result<(), RegionProblem> {
+        let mut used = self
+            .segments
+            .iter()
+            .enumerate()
+            .filter(|(_, segment)| segment.count != 0)
+            .map(|(index, segment)| (segment.start as u32, segment.end_u32(), index))
+            .collect::<Vec<_>>();
+        used.sort_unstable();
+
+        let mut blocks = Vec::new();
+        let mut cursor = 0;
+        let mut prev = None;
+        for (start, end, index) in used {
+            if end > u16::MAX as u32 {
+                return Err(RegionProblem::SegmentOutOfBounds(index));
+            }
+            if let Some(prev) = prev
+                && start < cursor
+            {
+                return Err(RegionProblem::SegmentsOverlap(prev, index));
+            }
+            if start > cursor {
+                blocks.push((cursor, start - cursor));
+            }
+            cursor = end;
+            prev = Some(index);
+        }
+        blocks.push((cursor, u16::MAX as u32 - cursor));
+
+        if blocks.len() > self.free_list.len() {
+            return Err(RegionProblem::TooManyFreeBlocks(blocks.len()));
+        }
+
+        self.free_list = [Block::EMPTY; 256];
+        for (i, (start, count)) in blocks.iter().enumerate() {
+            let next = if i + 1 < blocks.len() {
+                i as u16 + 1
+            } else {
+                u16::MAX
+            };
+            self.free_list[i] = Block {
+                start: *start as u16,
+                count: *count as u16,
+                next,
+            };
+        }
+
+        self.free_list_head = 0;
+        self.empty_cursor = (blocks.len() & 255) as u16;
+        self.total_pages = cursor as u16;
+        Ok(())
+    }
+
     /// Initialize header fields, to be used after zero-filling a new region file.
     fn init(&mut self, origin: IVec3, height: i32) {
         self.magic = Self::MAGIC;
@@ -471,8 +782,29 @@ impl Header {
         }
     }
 
+    /// Move the chunk at this index to new pages after every other segment, sized for
+    /// `len` bytes, and rebuild the free list. Unlike `realloc`, this works when the
+    /// free list is corrupted, as long as the segments are not.
+    pub fn append_segment(&mut self, index: usize, len: usize) -> Result<Segment, RegionProblem> {
+        self.clear_segment(index);
+        self.rebuild_free_list()?;
+
+        let start = self.total_pages;
+        let count = (len >> 12) + 1;
+        if start as usize + count >= u16::MAX as usize {
+            return Err(RegionProblem::SegmentOutOfBounds(index));
+        }
+
+        let segment = &mut self.segments[index];
+        segment.start = start;
+        segment.count = count as u16;
+        segment.update_padding(len);
+        self.rebuild_free_list()?;
+        Ok(self.segments[index])
+    }
+
     /// The number of bytes needed to store the file.
-    fn size(&self) -> u64 {
+    pub fn size(&self) -> u64 {
         // page count + 1 (for header) times 4096
         (self.total_pages as u64 + 1) << 12
     }
@@ -896,7 +1228,7 @@ impl Header {
 /// A segment describing the memory location of a chunk's data in the file.
 #[derive(Copy, Clone, Pod, Zeroable)]
 #[repr(C)]
-struct Segment {
+pub struct Segment {
     /// The first page in the segment.
     start: u16,
 
@@ -910,10 +1242,36 @@ struct Segment {
 }
 
 impl Segment {
+    pub const fn start(&self) -> u16 {
+        self.start
+    }
+
+    pub const fn count(&self) -> u16 {
+        self.count
+    }
+
+    pub const fn padding(&self) -> u16 {
+        self.padding
+    }
+
+    /// Number of bytes of chunk data in the segment.
+    pub const fn len(&self) -> usize {
+        ((self.count as usize) << 12).saturating_sub(self.padding as usize)
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
     const fn end(&self) -> u16 {
         self.start + self.count
     }
 
+    /// Same as `end`, but can't overflow if the segment is corrupted.
+    const fn end_u32(&self) -> u32 {
+        self.start as u32 + self.count as u32
+    }
+
     const fn block(&self, next: u16) -> Block {
         Block {
             start: self.start,
@@ -930,7 +1288,8 @@ impl Segment {
         (self.start as usize)..(self.start + self.count) as usize
     }
 
-    const fn byte_range(&self) -> Range<usize> {
+    /// Range of the chunk data in the file.
+    pub const fn byte_range(&self) -> Range<usize> {
         let start = (self.start as usize + 1) << 12;
         start..(start + (((self.count as usize) << 12) - self.padding as usize))
     }
@@ -943,7 +1302,7 @@ impl Segment {
 /// A free block of memory.
 #[derive(Copy, Clone, Pod, Zeroable)]
 #[repr(C)]
-struct Block {
+pub struct Block {
     /// The first page in the free block.
     start: u16,
 
@@ -961,6 +1320,18 @@ impl Block {
         next: u16::MAX,
     };
 
+    pub const fn start(&self) -> u16 {
+        self.start
+    }
+
+    pub const fn count(&self) -> u16 {
+        self.count
+    }
+
+    pub const fn next(&self) -> u16 {
+        self.next
+    }
+
     const fn is_empty(&self) -> bool {
         self.start == u16::MAX
     }
@@ -1004,17 +1375,107 @@ impl Block {
     }
 }
 
+/// Corruption found in a region file by `Header::problems`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RegionProblem {
+    /// The magic number is wrong, the file is probably not a region file.
+    BadMagic(u32),
+
+    /// The file is shorter than the pages the header says it has.
+    FileTooSmall { expected: u64, found: u64 },
+
+    /// The segment of the chunk at this index ends past the last page.
+    SegmentOutOfBounds(usize),
+
+    /// The segment of the chunk at this index has more padding than pages.
+    BadPadding(usize),
+
+    /// The segments of the chunks at these indices share pages.
+    SegmentsOverlap(usize, usize),
+
+    /// The free list links to a block past its end.
+    FreeListIndexOutOfBounds(usize),
+
+    /// The free list links back to the block at this index.
+    FreeListCycle(usize),
+
+    /// The block at this index starts before the end of the block linked before it.
+    FreeListUnsorted(usize),
+
+    /// The last block of the free list doesn't reach the end of the address space.
+    FreeListNotTerminated(usize),
+
+    /// A free block shares pages with the segment of a chunk.
+    FreeBlockOverlapsSegment { block: usize, segment: usize },
+
+    /// Rebuilding the free list needs more blocks than it can hold.
+    TooManyFreeBlocks(usize),
+}
+
+impl std::fmt::Display for RegionProblem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::BadMagic(magic) => write!(f, "[S141] Bad magic number: {magic:#x}"),
+            Self::FileTooSmall { expected, found } => write!(
+                f,
+                "[S142] File is {found} bytes, but the header needs {expected} bytes."
+            ),
+            Self::SegmentOutOfBounds(i) => {
+                write!(f, "[S143] Segment of chunk {i} ends past the last page.")
+            }
+            Self::BadPadding(i) => write!(f, "[S144] Segment of chunk {i} has invalid padding."),
+            Self::SegmentsOverlap(a, b) => {
+                write!(f, "[S145] Segments of chunks {a} and {b} overlap.")
+            }
+            Self::FreeListIndexOutOfBounds(i) => {
+                write!(
+                    f,
+                    "[S146] Free list links to block {i}, which doesn't exist."
+                )
+            }
+            Self::FreeListCycle(i) => write!(f, "[S147] Free list links back to block {i}."),
+            Self::FreeListUnsorted(i) => {
+                write!(
+                    f,
+                    "[S148] Free block {i} starts before the end of the block before it."
+                )
+            }
+            Self::FreeListNotTerminated(i) => {
+                write!(
+                    f,
+                    "[S149] Last free block {i} doesn't reach the end of the file."
+                )
+            }
+            Self::FreeBlockOverlapsSegment { block, segment } => write!(
+                f,
+                "[S150] Free block {block} overlaps the segment of chunk {segment}."
+            ),
+            Self::TooManyFreeBlocks(n) => {
+                write!(
+                    f,
+                    "[S151] Free list would need {n} blocks, but holds at most 256."
+                )
+            }
+        }
+    }
+}
+
+/// Origin of the chunk at this index of a region, see `ChunkId::to_chunk_idx`.
+pub fn chunk_origin(region_origin: IVec2, index: usize) -> IVec2 {
+    region_origin + ivec2((index & 15) as i32 * 32, (index >> 4) as i32 * 32)
+}
+
 /// Region Filename as an array of UTF-8 bytes.
-/// Hex digits are the zorder index of the Regions XZ origin.
+/// Hex digits are the morton code of the Regions XZ origin.
 /// "x" + "<12 hex digits>" + ".ovr"
-fn filename(origin: IVec2) -> [u8; 17] {
+pub fn filename(origin: IVec2) -> [u8; 17] {
     const HEX_DIGITS: [u8; 16] = [
         b'0', b'1', b'2', b'3', b'4', b'5', b'6', b'7', b'8', b'9', b'a', b'b', b'c', b'd', b'e',
         b'f',
     ];
 
     // the upper 18 bits of this number are always 0, leaving us with 46 relevant bits.
-    let mut zorder = zorder::index_of([(origin.x as u32) >> 9, (origin.y as u32) >> 9]);
+    let mut code = morton::encode2([(origin.x as u32) >> 9, (origin.y as u32) >> 9]);
     let mut ret = [0u8; 17];
 
     // I'm including 'x' at the start of the filename, just because idk what
@@ -1022,10 +1483,10 @@ fn filename(origin: IVec2) -> [u8; 17] {
     ret[0] = b'x';
     ret[13..17].copy_from_slice(&[b'.', b'o', b'v', b'r']);
 
-    // visit zorder 4 bits at a time.
+    // visit the code 4 bits at a time.
     for i in 1..13 {
-        ret[i] = HEX_DIGITS[(zorder & 0xF) as usize];
-        zorder >>= 4;
+        ret[i] = HEX_DIGITS[(code & 0xF) as usize];
+        code >>= 4;
     }
 
     ret
@@ -1,8 +1,27 @@
-use bevy::prelude::*;
+use bevy::{app::AppExit, prelude::*};
+use data::{
+    blocks::Block, blockstates::BlockState, registry::Registry, structures::StructureTemplate,
+    tags::Tags,
+};
 
+use crate::events::{ConfigChanged, ReloadData};
+
+pub mod blocks;
+pub mod compression;
+pub mod edits;
+pub mod entities;
+pub mod explosion;
 pub mod generator;
+pub mod growth;
 pub mod loader;
+pub mod protect;
+pub mod save;
+pub mod stats;
+pub mod structures;
 pub mod subscriber;
+pub mod time;
+
+pub use explosion::explode;
 
 pub struct ServerWorldPlugin;
 
@@ -13,11 +32,73 @@ impl Plugin for ServerWorldPlugin {
             .init_resource::<subscriber::Subscriber>()
             .init_resource::<loader::WorldLoader>()
             .init_resource::<generator::WorldGenerator>()
+            .init_resource::<::world::time::WorldTime>()
+            .init_resource::<Registry<Block>>()
+            .init_resource::<Tags<BlockState>>()
+            .init_resource::<entities::EntityIndex>()
+            .init_resource::<entities::ChunkEntities>()
+            .init_resource::<Registry<StructureTemplate>>()
+            .init_resource::<growth::RandomTickRng>()
+            .init_resource::<protect::ProtectedRegions>()
+            .init_resource::<compression::AdaptiveCompression>()
+            .init_resource::<::world::region::chunk::zip_cache::ZipCache>()
+            .init_resource::<save::UnsavedChunks>()
+            .init_resource::<generator::biomes::Biomes>()
+            .init_resource::<generator::carvers::Carvers>()
+            .init_resource::<generator::ores::Ores>()
+            .add_systems(Startup, (
+                blocks::load_blocks,
+                blocks::load_block_tags,
+                structures::load_structures,
+                generator::biomes::load_biomes,
+                generator::carvers::load_carvers,
+                generator::ores::load_ores,
+            ).chain())
+            .add_systems(Startup, protect::load_protections)
+            .add_systems(First, entities::rebuild_entity_index)
+            .add_systems(PostUpdate, entities::update_chunk_entities)
+            .add_systems(Last, (
+                save::collect_unsaved_chunks,
+                save::autosave_world,
+                save::save_world_on_exit
+                    .run_if(on_message::<AppExit>),
+            ).chain())
             .add_systems(Update, (
+                (
+                    blocks::load_block_tags,
+                    structures::load_structures,
+                    generator::biomes::load_biomes,
+                    generator::carvers::load_carvers,
+                    generator::ores::load_ores,
+                ).chain().run_if(on_message::<ReloadData>),
+                subscriber::apply_config
+                    .run_if(on_message::<ConfigChanged>),
+                subscriber::recv_chunk_requests
+                    .before(subscriber::process_chunk_send_queues),
+                subscriber::recv_chunk_revisions
+                    .before(subscriber::process_chunk_send_queues),
                 subscriber::process_chunk_send_queues,
-                subscriber::recompute_subscriptions,
-                generator::process_world_generator_queue,
-                loader::process_loader_queues,
+                compression::adapt_compression
+                    .before(subscriber::process_chunk_send_queues),
+                subscriber::recompute_subscriptions
+                    .after(subscriber::apply_config),
+                generator::apply_config
+                    .run_if(on_message::<ConfigChanged>),
+                generator::process_world_generator_queue
+                    .after(generator::apply_config),
+                loader::apply_config
+                    .run_if(on_message::<ConfigChanged>),
+                loader::process_loader_queues
+                    .after(loader::apply_config),
+                edits::apply_block_edits,
+                explosion::process_explosions
+                    .after(edits::apply_block_edits),
+                growth::random_tick_growth
+                    .after(blocks::load_block_tags),
+                (
+                    time::advance_world_time,
+                    time::send_world_time,
+                ).chain(),
             ))
         ;
     }
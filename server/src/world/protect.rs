@@ -0,0 +1,43 @@
+//! Regions of the world only some players may build in, like the spawn.
+//!
+//! Regions are defined with `/protect` and saved next to the region files of the world,
+//! see `Protections`. Block edits of players in a region that doesn't allow them are
+//! rejected like edits out of reach, so the client rolls them back.
+
+use std::path::Path;
+
+use bevy::prelude::*;
+use data::fs::save::Protections;
+
+use crate::world::loader::WorldLoader;
+
+/// The protected regions of the world.
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct ProtectedRegions(Protections);
+
+impl ProtectedRegions {
+    /// Save the regions to the region directory, after they were changed.
+    pub fn save(&self, region_dir: &Path) {
+        if let Err(e) = self.0.save(region_dir) {
+            error!(
+                "[S162] Failed to save the protected regions in '{}': {e}",
+                region_dir.display()
+            );
+        }
+    }
+}
+
+/// Read the protected regions of the world on startup.
+pub fn load_protections(loader: Res<WorldLoader>, mut regions: ResMut<ProtectedRegions>) {
+    let region_dir = loader.region_dir();
+    match Protections::load(region_dir) {
+        Ok(loaded) => {
+            info!("Loaded {} protected regions.", loaded.iter().count());
+            regions.0 = loaded;
+        }
+        Err(e) => error!(
+            "[S161] Failed to read the protected regions in '{}', every region can be built in: {e}",
+            region_dir.display()
+        ),
+    }
+}
@@ -0,0 +1,170 @@
+//! Saving the chunks that changed to their region files.
+//!
+//! At the end of every tick, the chunks that changed are zipped into `UnsavedChunks`.
+//! They are written to their region files every `autosave_interval` of the config, and
+//! when the server stops. The map is shared with the watchdog, which writes it to the
+//! region files itself before it aborts a hung server, since the hung tick may never
+//! give the `WorldLoader` back.
+
+use std::{
+    io,
+    path::PathBuf,
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+
+use bevy::prelude::*;
+use fxhash::{FxHashMap, FxHashSet};
+use world::{
+    World,
+    region::{
+        chunk::{ChunkId, flags::ChunkState, zip_cache::ZipCache},
+        format::ZippedChunk,
+    },
+};
+
+use crate::{
+    config::Config,
+    world::loader::{WorldLoader, write_chunks_to_files},
+};
+
+/// Zips of the chunks that changed since they were last written to their region files.
+#[derive(Resource, Clone, Default)]
+pub struct UnsavedChunks(Arc<Mutex<Unsaved>>);
+
+#[derive(Default)]
+struct Unsaved {
+    /// Region directory of the world, to open the region files in without the loader.
+    region_dir: PathBuf,
+
+    /// Lowest Y and height of the world, to create the region files with.
+    min_y: i32,
+    height: i32,
+
+    /// Zip of each chunk that changed.
+    chunks: FxHashMap<ChunkId, ZippedChunk>,
+}
+
+impl UnsavedChunks {
+    /// Write the chunks to their region files without the `WorldLoader`,
+    /// returning how many were written, see `loader::write_chunks_to_files`.
+    pub fn write_to_files(&self) -> io::Result<usize> {
+        // a panic in the hung tick may have poisoned the lock, the zips are still whole.
+        let unsaved = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        write_chunks_to_files(
+            &unsaved.region_dir,
+            unsaved.min_y,
+            unsaved.height,
+            &unsaved.chunks,
+        )
+    }
+}
+
+/// Zip the chunks that changed this tick into the `UnsavedChunks`.
+pub fn collect_unsaved_chunks(
+    loader: Res<WorldLoader>,
+    unsaved: Res<UnsavedChunks>,
+    mut world: ResMut<World>,
+    mut zip_cache: ResMut<ZipCache>,
+) {
+    let mut changed = Vec::new();
+    for region in world.regions_mut() {
+        for chunk in region.chunks_mut() {
+            if !chunk.needs_save() || chunk.load_state() != ChunkState::Loaded {
+                continue;
+            }
+
+            // the zip is cached, so the next send of the chunk doesn't zip it again.
+            let zip = chunk.get_cached_or_zip_with(
+                &mut zip_cache,
+                loader.algorithm(),
+                loader.zip_level(),
+            );
+            chunk.mark_saved();
+            changed.push((chunk.id(), zip));
+        }
+    }
+
+    if changed.is_empty() {
+        return;
+    }
+
+    let mut unsaved = unsaved.0.lock().unwrap_or_else(PoisonError::into_inner);
+    if unsaved.region_dir != loader.region_dir() {
+        unsaved.region_dir = loader.region_dir().to_path_buf();
+    }
+    unsaved.min_y = world.min_y();
+    unsaved.height = world.height();
+    unsaved.chunks.extend(changed);
+}
+
+/// Write the unsaved chunks to their region files every `autosave_interval` of the config.
+pub fn autosave_world(
+    config: Res<Config>,
+    unsaved: Res<UnsavedChunks>,
+    mut loader: ResMut<WorldLoader>,
+    mut next_save: Local<Option<Instant>>,
+) {
+    let now = Instant::now();
+    let interval = Duration::from_secs(config.autosave_interval as u64);
+    let next = next_save.get_or_insert(now + interval);
+    if now < *next {
+        return;
+    }
+
+    *next = now + interval;
+    save_unsaved_chunks(&unsaved, &mut loader);
+}
+
+/// Write the unsaved chunks to their region files when the server stops.
+pub fn save_world_on_exit(unsaved: Res<UnsavedChunks>, mut loader: ResMut<WorldLoader>) {
+    save_unsaved_chunks(&unsaved, &mut loader);
+}
+
+fn save_unsaved_chunks(unsaved: &UnsavedChunks, loader: &mut WorldLoader) {
+    let chunks = std::mem::take(
+        &mut unsaved
+            .0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .chunks,
+    );
+    if chunks.is_empty() {
+        return;
+    }
+
+    let total = chunks.len();
+    let mut regions = FxHashSet::default();
+    let mut failed = Vec::new();
+    for (id, zip) in chunks {
+        match loader.write_chunk(id, &zip) {
+            Ok(()) => {
+                regions.insert(id.to_region_id());
+            }
+            Err(e) => {
+                error!(
+                    "[S178] Failed to save chunk {} with error: '{e:?}'",
+                    id.as_ivec2()
+                );
+                failed.push((id, zip));
+            }
+        }
+    }
+
+    for &region in &regions {
+        loader.save_region(region);
+    }
+    debug!(
+        "Saved {} chunks in {} regions.",
+        total - failed.len(),
+        regions.len()
+    );
+
+    // the chunks that failed are saved again by the next save, unless they changed since.
+    if !failed.is_empty() {
+        let mut unsaved = unsaved.0.lock().unwrap_or_else(PoisonError::into_inner);
+        for (id, zip) in failed {
+            unsaved.chunks.entry(id).or_insert(zip);
+        }
+    }
+}
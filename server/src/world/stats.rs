@@ -0,0 +1,216 @@
+//! Statistics of the voxels and chunks of the world, reported by the /stats command.
+//!
+//! Voxels are counted per subchunk from the palette indices, so each voxel is only
+//! counted as an index, and each value in the palette is only looked up once.
+//!
+//! `/stats all` also reads every region file, which may take a while on large worlds,
+//! so the files are read on the compute pool by a `StatsScans` task, and the sender
+//! gets the reply once it finishes.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use bevy::{
+    prelude::*,
+    tasks::{AsyncComputeTaskPool, Task, futures_lite},
+};
+use fxhash::{FxHashMap, FxHashSet};
+use world::{
+    Chunk, World,
+    region::{RegionId, chunk::flags::ChunkState, format::decode_chunk_bytes},
+    voxel::Voxel,
+};
+
+use crate::{
+    player::identity::PlayerId,
+    world::loader::{Header, WorldLoader, chunk_origin},
+};
+
+/// Counts of the voxels and chunks of some regions of the world.
+#[derive(Default)]
+pub struct WorldStats {
+    /// Number of regions that were counted.
+    pub regions: usize,
+
+    /// Number of chunks that were counted.
+    pub chunks: usize,
+
+    /// Number of voxels with each value.
+    pub voxels: FxHashMap<Voxel, u64>,
+
+    /// Origin and compressed size in bytes of each counted chunk that has data.
+    pub sizes: Vec<(IVec2, usize)>,
+
+    /// Number of region files that couldn't be read, and chunks that failed to decode.
+    pub errors: usize,
+}
+
+impl WorldStats {
+    /// Count the regions in the world and their loaded chunks.
+    /// The size of a chunk is the size of its data in the region file,
+    /// or of its cached zip if it wasn't written yet.
+    pub fn loaded(world: &World, loader: &WorldLoader) -> Self {
+        let mut stats = Self::default();
+        for region in world.regions() {
+            stats.regions += 1;
+            for chunk in region.chunks() {
+                if chunk.load_state() != ChunkState::Loaded {
+                    continue;
+                }
+
+                let origin = chunk.origin().xz();
+                let size = match loader.read_chunk_raw(origin) {
+                    Ok(data) => Some(data.len()),
+                    Err(_) => chunk.get_cached_zip().map(|zip| zip.0.len()),
+                };
+                stats.add_chunk(chunk, origin, size);
+            }
+        }
+        stats
+    }
+
+    /// Also count the regions in the region files of this directory that aren't `loaded`.
+    /// Their chunks are decoded one at a time, and dropped once they are counted.
+    pub fn scan_region_files(&mut self, dir: &Path, loaded: &FxHashSet<RegionId>) {
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                warn!(
+                    "[S152] Failed to read the region directory '{}' for stats: {e}",
+                    dir.display()
+                );
+                self.errors += 1;
+                return;
+            }
+        };
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().is_some_and(|ext| ext == "ovr") {
+                self.scan_region_file(&path, loaded);
+            }
+        }
+    }
+
+    fn scan_region_file(&mut self, path: &Path, loaded: &FxHashSet<RegionId>) {
+        let header = fs::read(path)
+            .ok()
+            .and_then(|data| Header::read(&data).map(|header| (header, data)));
+        let Some((header, data)) = header.filter(|(header, _)| header.is_magic_valid()) else {
+            warn!(
+                "[S153] Failed to read '{}' as a region file for stats.",
+                path.display()
+            );
+            self.errors += 1;
+            return;
+        };
+
+        // loaded regions were counted from the world, which may be newer than the file.
+        let origin = header.origin();
+        if loaded.contains(&RegionId::from(origin)) {
+            return;
+        }
+
+        self.regions += 1;
+        for index in 0..256 {
+            let segment = header.segment(index);
+            if segment.is_empty() {
+                continue;
+            }
+
+            let expected = chunk_origin(origin.xz(), index);
+            let decoded = data
+                .get(segment.byte_range())
+                .and_then(|bytes| {
+                    decode_chunk_bytes(bytes, origin.y + header.height(), origin.y).ok()
+                })
+                .filter(|(_, success)| success.origin.xz() == expected);
+            let chunk = decoded
+                .as_ref()
+                .and_then(|(chunk_world, _)| chunk_world.get_chunk(expected));
+            match chunk {
+                Some(chunk) => self.add_chunk(chunk, expected, Some(segment.len())),
+                None => self.errors += 1,
+            }
+        }
+    }
+
+    fn add_chunk(&mut self, chunk: &Chunk, origin: IVec2, size: Option<usize>) {
+        self.chunks += 1;
+        chunk.count_voxels(|voxel, n| *self.voxels.entry(voxel).or_default() += n as u64);
+        if let Some(size) = size {
+            self.sizes.push((origin, size));
+        }
+    }
+
+    /// Total number of voxels that were counted.
+    pub fn total_voxels(&self) -> u64 {
+        self.voxels.values().sum()
+    }
+
+    /// The `n` most common voxel values and their counts, most common first.
+    pub fn most_common(&self, n: usize) -> Vec<(Voxel, u64)> {
+        let mut voxels = self
+            .voxels
+            .iter()
+            .map(|(voxel, count)| (*voxel, *count))
+            .collect::<Vec<_>>();
+        voxels.sort_unstable_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        voxels.truncate(n);
+        voxels
+    }
+
+    /// Origins and sizes of the `n` largest chunks, largest first.
+    pub fn largest_chunks(&self, n: usize) -> Vec<(IVec2, usize)> {
+        let mut sizes = self.sizes.clone();
+        sizes.sort_unstable_by(|a, b| b.1.cmp(&a.1).then(a.0.to_array().cmp(&b.0.to_array())));
+        sizes.truncate(n);
+        sizes
+    }
+}
+
+/// Counts of every region file started by `/stats all`, and the player to reply to.
+/// Players are kept by id, since their session may be reused once they leave.
+#[derive(Resource, Default)]
+pub struct StatsScans(Vec<(PlayerId, Task<WorldStats>)>);
+
+impl StatsScans {
+    /// Whether a scan is still running. Only one runs at a time,
+    /// since each of them reads every region file.
+    pub fn is_running(&self) -> bool {
+        !self.0.is_empty()
+    }
+
+    /// Count the loaded regions now, and the region files that aren't loaded on the
+    /// compute pool. The counts are returned by `poll` once every file was read.
+    pub fn start(&mut self, player: PlayerId, world: &World, loader: &WorldLoader) {
+        let mut stats = WorldStats::loaded(world, loader);
+        let loaded = world
+            .regions()
+            .map(|region| region.id())
+            .collect::<FxHashSet<_>>();
+        let dir: PathBuf = loader.region_dir().to_path_buf();
+        let task = AsyncComputeTaskPool::get().spawn(async move {
+            stats.scan_region_files(&dir, &loaded);
+            stats
+        });
+        self.0.push((player, task));
+    }
+
+    /// Take the scans that finished, with the player that started them.
+    pub fn poll(&mut self) -> Vec<(PlayerId, WorldStats)> {
+        let mut finished = Vec::new();
+        self.0.retain_mut(|(player, task)| {
+            match futures_lite::future::block_on(futures_lite::future::poll_once(task)) {
+                Some(stats) => {
+                    finished.push((*player, stats));
+                    false
+                }
+                None => true,
+            }
+        });
+        finished
+    }
+}
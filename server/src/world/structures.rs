@@ -0,0 +1,106 @@
+//! Structure templates of the data directory and data packs, and placing them into the world.
+
+use bevy::prelude::*;
+use data::{
+    blocks::Block,
+    blockstates::BlockState,
+    registry::Registry,
+    structures::{StructureTemplate, read_structures},
+};
+use world::{World, region::chunk::flags::ChunkState, voxel::Voxel};
+
+use crate::packs::DataDirs;
+
+/// Read the structure templates of the data directory and data packs, on startup and on
+/// reload. Templates with block states that don't exist, and blocks that grow into
+/// templates that don't exist, are reported, since they can't be placed.
+pub fn load_structures(
+    dirs: Res<DataDirs>,
+    states: Res<Registry<BlockState>>,
+    blocks: Res<Registry<Block>>,
+    mut structures: ResMut<Registry<StructureTemplate>>,
+) {
+    let (loaded, errors) = read_structures(&dirs.dirs());
+    for err in errors {
+        error!("{err}");
+    }
+
+    for template in loaded.entries() {
+        for (_, name) in &template.blocks {
+            if states.resolve(name).is_none() {
+                warn!(
+                    "[S158] Structure '{}' has the unknown block state '{name}', which won't be placed.",
+                    template.name
+                );
+            }
+        }
+    }
+
+    for block in blocks.entries() {
+        if let Some(name) = block.growth.as_ref().and_then(|g| g.structure.as_ref())
+            && loaded.resolve(name).is_none()
+        {
+            warn!(
+                "[S157] Block '{}' grows into the unknown structure '{name}', so it won't grow into it.",
+                block.name
+            );
+        }
+    }
+
+    info!("Loaded {} structure templates.", loaded.iter().count());
+    *structures = loaded;
+}
+
+/// Place the blocks of a template with its origin at `pos`, returning the voxels that
+/// changed. Blocks are only placed over blocks that don't block movement and aren't liquids,
+/// like air and grass, except at the origin, which is always replaced, with air if the
+/// template doesn't have a block there.
+///
+/// Nothing is placed if part of the template is in a chunk that isn't loaded,
+/// so structures aren't cut off at the edge of the loaded world.
+pub fn place_structure(
+    world: &mut World,
+    states: &Registry<BlockState>,
+    template: &StructureTemplate,
+    pos: IVec3,
+) -> Option<Vec<(IVec3, Voxel)>> {
+    let mut placed = Vec::new();
+    if !template
+        .blocks
+        .iter()
+        .any(|(offset, _)| *offset == IVec3::ZERO)
+    {
+        placed.push((pos, Voxel::AIR));
+    }
+
+    for (offset, name) in &template.blocks {
+        let target = pos + *offset;
+        let loaded = world
+            .get_chunk(target.xz())
+            .is_some_and(|chunk| chunk.load_state() == ChunkState::Loaded);
+        if !loaded {
+            return None;
+        }
+
+        let (Some(id), Some(current)) = (states.resolve(name), world.get_state(target)) else {
+            continue;
+        };
+
+        let replaceable = *offset == IVec3::ZERO
+            || states
+                .get(current.voxel)
+                .is_some_and(|state| !state.blocks_movement() && !state.liquid);
+        if replaceable {
+            placed.push((target, Voxel::from(id)));
+        }
+    }
+
+    for (target, voxel) in &placed {
+        world.set_voxel(*target, *voxel);
+        if let Some(chunk) = world.get_chunk_mut(target.xz()) {
+            chunk.clear_cached_zip();
+        }
+    }
+
+    Some(placed)
+}
@@ -15,22 +15,25 @@ use aligned_vec::{AVec, CACHELINE_ALIGN};
 use bevy::prelude::*;
 use data::{queue::Queue, registry::Registry};
 use fxhash::FxHashMap;
-use math::{activity::Activity, space::area::IArea};
+use math::{activity::Ema, space::area::IArea};
 use protocol::{
     ChannelId, Packet,
+    bytes::Bytes,
     session::{Session, SessionMap},
+    types::{ChunkRequest, ChunkRevision, ChunkUnload},
 };
 use world::{
     World,
     region::{
         RegionId,
         attribute::ChunkMask,
-        chunk::{ChunkId, flags::ChunkState},
+        chunk::{ChunkId, flags::ChunkState, zip_cache::ZipCache},
         format::UnzippedChunk,
     },
 };
 
 use crate::{
+    config::Config,
     events::{SubscChangeKind, SubscChanged, SubscInterest},
     net::{Server, channel::Channel},
     player::Player,
@@ -47,7 +50,22 @@ const SUBSCRIPTION_RECOMPUTATION_DISTANCE: i32 = 32;
 const ACTIVITY_RISE_ALPHA: f32 = 1.0;
 
 /// Rate of change of tracker activity when no recomputation occurs.
-const ACTIVITY_FALL_ALPHA: f32 = -0.1;
+const ACTIVITY_FALL_ALPHA: f32 = 0.1;
+
+/// How much further away a chunk directly behind the player is treated as being,
+/// compared to one directly in front, when ordering the chunks to send.
+const BEHIND_WEIGHT: f32 = 1.0;
+
+/// The send queue is re-ordered when the player turns further than this
+/// from the direction it was ordered for, as the cosine of the angle.
+const FACING_RESORT_COS: f32 = 0.7;
+
+/// Most chunk requests accepted from a player per tick, the rest are dropped.
+const CHUNK_REQUESTS_PER_TICK: usize = 16;
+
+/// Most chunk revisions remembered per player, the rest are dropped
+/// and those chunks are sent in full.
+const MAX_CLIENT_REVISIONS: usize = 4096;
 
 /// Structure that keeps track of which regions/chunks players are subscribed to.
 #[derive(Resource)]
@@ -58,9 +76,40 @@ pub struct Subscriber {
     trackers: SessionMap<Tracker>,
     buckets: FxHashMap<RegionId, Bucket>,
     changes: Vec<SubscChanged>,
+
+    /// Whether every player needs recomputation, because the distances changed.
+    recompute_all: bool,
 }
 
 impl Subscriber {
+    /// Change the draw and simulation distances, in blocks.
+    /// The subscriptions of every player are recomputed on the next tick.
+    pub fn set_distances(&mut self, draw_distance: u32, sim_distance: u32) {
+        if self.draw_distance == draw_distance && self.sim_distance == sim_distance {
+            return;
+        }
+
+        self.draw_distance = draw_distance;
+        self.sim_distance = sim_distance;
+        for (_, tracker) in self.trackers.iter_mut() {
+            tracker.recompute = true;
+        }
+        self.recompute_all = true;
+    }
+
+    /// Change the most chunks sent to each player per tick.
+    pub fn set_sends_per_tick_limit(&mut self, limit: u32) {
+        self.sends_per_tick_limit = limit;
+    }
+
+    /// Number of chunks waiting to be sent, to every player.
+    pub fn queued_chunks(&self) -> usize {
+        self.trackers
+            .iter()
+            .map(|(_, tracker)| tracker.send_queue.len())
+            .sum()
+    }
+
     /// Get all players in-range of a point.
     ///
     /// Note that the position checked against will be the position used
@@ -191,6 +240,13 @@ impl Subscriber {
                 // update chunk tracker bitfields
                 let chunks = &mut tracker.vals[i];
                 chunks.in_draw = ChunkMask::from_area(&cell.intersection(&draw_area).unwrap());
+
+                // Chunks that left draw distance are dropped by the client,
+                // so they need to be sent again if they come back into range.
+                let left = chunks.sent.difference(&chunks.in_draw);
+                chunks.sent = chunks.sent & chunks.in_draw;
+                tracker.unloads.extend(left.iter_positions(chunks.origin));
+
                 if let Some(area) = cell.intersection(&sim_area) {
                     chunks.in_sim = ChunkMask::from_area(&area);
 
@@ -237,9 +293,12 @@ impl Subscriber {
                         interest: SubscInterest::Visual,
                     });
 
-                    // remove entry from tracker.
+                    // remove entry from tracker, the client drops the chunks it was sent.
                     tracker.keys.swap_remove(i);
-                    tracker.vals.swap_remove(i);
+                    let chunks = tracker.vals.swap_remove(i);
+                    tracker
+                        .unloads
+                        .extend(chunks.sent.iter_positions(chunks.origin));
                 } else {
                     // write subscription to bucket
                     if let Some(bucket) = self.buckets.get_mut(&id) {
@@ -268,6 +327,7 @@ impl Default for Subscriber {
             trackers: SessionMap::new(),
             buckets: FxHashMap::default(),
             changes: Vec::new(),
+            recompute_all: false,
         }
     }
 }
@@ -299,13 +359,14 @@ pub fn recompute_subscriptions(
 ) {
     // Determine which players need recomputation.
     // If no players need recomputation, we can skip it entirely.
-    let mut needs_recompute = false;
+    let mut needs_recompute = std::mem::take(&mut subscriber.recompute_all);
     for (pos, player) in &q {
         if let Some(tracker) = subscriber.trackers.get_mut(player.session) {
             tracker.exists = true;
             if tracker.needs_recompute(pos.translation.as_ivec3().xz()) {
                 needs_recompute = true;
             }
+            tracker.face((pos.rotation * Vec3::NEG_Z).xz().normalize_or_zero());
         } else {
             info!("Inserting Subscription Tracker: {:?}", player.session);
 
@@ -344,19 +405,68 @@ pub fn recompute_subscriptions(
     }
 }
 
+/// Apply the distances and send limit of the config when it changes.
+pub fn apply_config(config: Res<Config>, mut subscriber: ResMut<Subscriber>) {
+    subscriber.set_distances(config.draw_distance, config.sim_distance);
+    subscriber.set_sends_per_tick_limit(config.chunk_sends_per_tick);
+}
+
+/// Move chunks players requested to the front of their send queues.
+pub fn recv_chunk_requests(channels: Res<Registry<Channel>>, mut subscriber: ResMut<Subscriber>) {
+    let mut counts: FxHashMap<u64, usize> = FxHashMap::default();
+    for packet in channels.get_by_name("chunk-request").unwrap() {
+        let Some(request) = packet.cast::<ChunkRequest>() else {
+            continue;
+        };
+
+        let count = counts.entry(packet.session.0).or_default();
+        *count += 1;
+        if *count > CHUNK_REQUESTS_PER_TICK {
+            continue;
+        }
+
+        if let Some(tracker) = subscriber.get_mut(packet.session) {
+            tracker.request_chunk(request.origin);
+        }
+    }
+}
+
+/// Remember the revisions of the chunks players kept a copy of.
+pub fn recv_chunk_revisions(channels: Res<Registry<Channel>>, mut subscriber: ResMut<Subscriber>) {
+    for packet in channels.get_by_name("chunk-revisions").unwrap() {
+        if let Some(tracker) = subscriber.get_mut(packet.session) {
+            for rev in packet.cast_slice::<ChunkRevision>() {
+                tracker.add_client_revision(rev.origin, rev.revision);
+            }
+        }
+    }
+}
+
 /// Sends one chunk from each tracker's send queues.
 pub fn process_chunk_send_queues(
     mut subscriber: ResMut<Subscriber>,
     mut generator: ResMut<WorldGenerator>,
     mut server: ResMut<Server>,
     mut loader: ResMut<WorldLoader>,
+    mut zip_cache: ResMut<ZipCache>,
     channels: Res<Registry<Channel>>,
     mut world: ResMut<World>,
 ) {
     let channel: ChannelId = channels.resolve("chunk-data").unwrap().into();
+    let unload_channel: ChannelId = channels.resolve("chunk-unload").unwrap().into();
+    let cached_channel: ChannelId = channels.resolve("chunk-cached").unwrap().into();
     let sends_limit = subscriber.sends_per_tick_limit;
 
     for (session, tracker) in subscriber.trackers.iter_mut() {
+        // unloads are sent first, so they arrive before the chunk is sent again.
+        for origin in tracker.unloads.drain(..) {
+            server.tcp_send(Packet {
+                payload: Bytes::copy_from_slice(bytemuck::bytes_of(&ChunkUnload { origin })),
+                session,
+                channel: unload_channel,
+            });
+        }
+
         let mut sends = 0;
         loop {
             if let Some((id, distance)) = tracker.peek_next_chunk() {
@@ -370,20 +480,35 @@ pub fn process_chunk_send_queues(
                         // Chunk is in the process of being generated.
                         ChunkState::Generating => {
                             // Push this chunk up in the queue.
-                            generator.enqueue(id, distance);
+                            generator.enqueue(id, distance, session);
                             break;
                         }
 
                         // Chunk is loaded and ready to be sent.
                         ChunkState::Loaded => {
-                            // zip the data if needed and send to client.
-                            server.tcp_send(Packet {
-                                payload: chunk
-                                    .get_cached_or_zip(loader.algorithm(), loader.zip_level())
-                                    .0,
-                                session,
-                                channel,
-                            });
+                            let revision = chunk.revision();
+                            if tracker.take_client_revision(origin) == Some(revision) {
+                                // the client's copy is up to date, tell it to use that.
+                                server.tcp_send(revision_packet(
+                                    session,
+                                    cached_channel,
+                                    origin,
+                                    revision,
+                                ));
+                            } else {
+                                // zip the data if needed and send to client.
+                                server.tcp_send(Packet {
+                                    payload: chunk
+                                        .get_cached_or_zip_with(
+                                            &mut zip_cache,
+                                            loader.algorithm(),
+                                            loader.zip_level(),
+                                        )
+                                        .0,
+                                    session,
+                                    channel,
+                                });
+                            }
 
                             // send successful, pop off the tracker.
                             tracker.pop_next_chunk();
@@ -402,21 +527,29 @@ pub fn process_chunk_send_queues(
                             world
                                 .read_unzipped_chunk(span, false)
                                 .expect("[S556] Chunk load fail.");
-                            world
-                                .get_chunk_mut(origin)
-                                .unwrap()
-                                .set_cached_zip(data.clone());
-                            server.tcp_send(Packet {
-                                payload: data.0,
-                                session,
-                                channel,
-                            });
+                            let chunk = world.get_chunk_mut(origin).unwrap();
+                            chunk.set_cached_zip(data.clone());
+                            let revision = chunk.revision();
+                            if tracker.take_client_revision(origin) == Some(revision) {
+                                server.tcp_send(revision_packet(
+                                    session,
+                                    cached_channel,
+                                    origin,
+                                    revision,
+                                ));
+                            } else {
+                                server.tcp_send(Packet {
+                                    payload: data.0,
+                                    session,
+                                    channel,
+                                });
+                            }
                             tracker.pop_next_chunk();
                         }
                         Err(ChunkReadError::NoData) => {
                             let chunk = world.get_chunk_mut(origin).unwrap();
                             *chunk.load_state_mut() = ChunkState::Generating;
-                            generator.enqueue(id, distance);
+                            generator.enqueue(id, distance, session);
                             break;
                         }
                         Err(ChunkReadError::RegionNotLoaded) => {
@@ -434,6 +567,15 @@ pub fn process_chunk_send_queues(
     }
 }
 
+/// Packet telling the client its copy of a chunk is up to date.
+fn revision_packet(session: Session, channel: ChannelId, origin: IVec2, revision: u64) -> Packet {
+    Packet {
+        payload: Bytes::copy_from_slice(bytemuck::bytes_of(&ChunkRevision { origin, revision })),
+        session,
+        channel,
+    }
+}
+
 /// Storage for regions the player is subscribed to.
 /// One of these exists per player.
 pub struct Tracker {
@@ -447,8 +589,12 @@ pub struct Tracker {
     /// The position of the player the last time they were updated.
     prev_pos: IVec2,
 
-    /// The frequency the player triggers recomputation.
-    activity: Activity,
+    /// Horizontal direction the player was looking when the send queue was ordered,
+    /// normalized, or zero if they were looking straight up or down.
+    facing: Vec2,
+
+    /// The frequency the player triggers recomputation, in the range 0.0..=1.0.
+    activity: Ema,
 
     /// Whether the player needs recomputation.
     /// Note that the player will still need to write its subscriptions
@@ -461,6 +607,14 @@ pub struct Tracker {
 
     /// Queue of chunks waiting to be sent to the player.
     send_queue: Vec<QueuedChunk>,
+
+    /// Origins of chunks the player was sent that left their draw distance,
+    /// waiting for the client to be told to drop them.
+    unloads: Vec<IVec2>,
+
+    /// Revisions of the chunks the client kept a copy of, by origin. A chunk
+    /// whose revision still matches isn't sent again, the client loads its copy.
+    client_revisions: FxHashMap<IVec2, u64>,
 }
 
 impl Tracker {
@@ -468,14 +622,33 @@ impl Tracker {
         Self {
             keys: Vec::new(),
             vals: Vec::new(),
-            activity: Activity::new(),
+            activity: Ema::new(ACTIVITY_RISE_ALPHA, ACTIVITY_FALL_ALPHA),
             prev_pos: pos,
+            facing: Vec2::ZERO,
             recompute: true,
             exists: true,
             send_queue: Vec::new(),
+            unloads: Vec::new(),
+            client_revisions: FxHashMap::default(),
         }
     }
 
+    /// Record that the client has a copy of a chunk at this revision.
+    /// Ignored if too many revisions are already recorded.
+    pub fn add_client_revision(&mut self, origin: IVec2, revision: u64) {
+        let origin = ChunkId::new(origin).as_ivec2();
+        if self.client_revisions.len() < MAX_CLIENT_REVISIONS
+            || self.client_revisions.contains_key(&origin)
+        {
+            self.client_revisions.insert(origin, revision);
+        }
+    }
+
+    /// Take the revision of the client's copy of a chunk, if it has one.
+    fn take_client_revision(&mut self, origin: IVec2) -> Option<u64> {
+        self.client_revisions.remove(&origin)
+    }
+
     pub fn peek_next_chunk(&self) -> Option<(ChunkId, u32)> {
         self.send_queue
             .last()
@@ -502,29 +675,72 @@ impl Tracker {
         None
     }
 
+    /// Move a chunk the client asked for to the front of the send queue, even if it
+    /// was sent before. Returns false if the chunk isn't in the player's draw distance.
+    pub fn request_chunk(&mut self, origin: IVec2) -> bool {
+        let id = ChunkId::new(origin);
+        let Some(i) = self.get_region_idx(id.to_region_id()) else {
+            return false;
+        };
+
+        let idx = id.to_chunk_idx();
+        if !self.vals[i].in_draw.index(idx) {
+            return false;
+        }
+
+        // the client doesn't have the chunk, so it's no longer sent.
+        self.vals[i].sent.set_index(idx, false);
+
+        // the queue is popped from the back, and stays there if it is re-ordered.
+        let mut queued = QueuedChunk::new(id.as_ivec2(), self.prev_pos, self.facing);
+        queued.weight = 0;
+        self.send_queue.retain(|q| q.rel != queued.rel);
+        self.send_queue.push(queued);
+        true
+    }
+
     fn needs_recompute(&mut self, pos: IVec2) -> bool {
         let yes =
             self.prev_pos.chebyshev_distance(pos) as i32 > SUBSCRIPTION_RECOMPUTATION_DISTANCE;
         if yes {
             // recomputations updates matter more than non-recomputations.
-            self.activity.update(ACTIVITY_RISE_ALPHA);
+            self.activity.update(1.0);
             self.prev_pos = pos;
             self.recompute = true;
         } else {
-            self.activity.update(ACTIVITY_FALL_ALPHA);
+            self.activity.update(0.0);
         }
         yes
     }
 
+    /// Update the direction the player is looking, re-ordering the send queue
+    /// so chunks in front of the player are sent first if they turned far enough.
+    fn face(&mut self, facing: Vec2) {
+        if facing == self.facing || facing.dot(self.facing) >= FACING_RESORT_COS {
+            return;
+        }
+
+        self.facing = facing;
+        for queued in &mut self.send_queue {
+            if queued.weight != 0 {
+                queued.weight = QueuedChunk::weight(queued.rel, queued.dist, facing);
+            }
+        }
+        self.send_queue.sort_unstable();
+    }
+
     fn rebuild_send_queue(&mut self) {
         let player_pos = self.prev_pos;
+        let facing = self.facing;
         self.send_queue.clear();
         for i in 0..self.vals.len() {
             let region_origin = self.vals[i].origin;
-            for offs in self.vals[i].in_draw_and_not_sent().iter_ones() {
-                let chunk_origin = region_origin + offs;
+            for chunk_origin in self.vals[i]
+                .in_draw_and_not_sent()
+                .iter_positions(region_origin)
+            {
                 self.send_queue
-                    .push(QueuedChunk::new(chunk_origin, player_pos));
+                    .push(QueuedChunk::new(chunk_origin, player_pos, facing));
             }
         }
 
@@ -560,19 +776,33 @@ struct QueuedChunk {
 
     /// distance to previous player position.
     dist: u32,
+
+    /// The distance, increased for chunks behind the player.
+    /// Chunks with the lowest weight are sent first.
+    weight: u32,
 }
 
 impl QueuedChunk {
-    fn new(chunk_origin: IVec2, player_pos: IVec2) -> Self {
+    fn new(chunk_origin: IVec2, player_pos: IVec2, facing: Vec2) -> Self {
         let rel = chunk_origin - player_pos;
         let dist = u32::max(rel.x.unsigned_abs(), rel.y.unsigned_abs());
-        Self { rel, dist }
+        let weight = Self::weight(rel, dist, facing);
+        Self { rel, dist, weight }
+    }
+
+    fn weight(rel: IVec2, dist: u32, facing: Vec2) -> u32 {
+        // direction to the center of the chunk.
+        let dir = (rel.as_vec2() + 16.0).normalize_or_zero();
+        let behind = (1.0 - dir.dot(facing)) * 0.5;
+        (dist as f32 * (1.0 + behind * BEHIND_WEIGHT)) as u32
     }
 }
 
 impl Ord for QueuedChunk {
     fn cmp(&self, other: &Self) -> Ordering {
-        if self.dist != other.dist {
+        if self.weight != other.weight {
+            other.weight.cmp(&self.weight)
+        } else if self.dist != other.dist {
             other.dist.cmp(&self.dist)
         } else if self.rel.x != other.rel.x {
             other.rel.x.cmp(&self.rel.x)
@@ -627,7 +857,7 @@ impl ChunkTracker {
     }
 
     fn in_draw_and_not_sent(&self) -> ChunkMask {
-        self.in_draw & !self.sent
+        self.in_draw.difference(&self.sent)
     }
 }
 
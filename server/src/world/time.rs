@@ -0,0 +1,78 @@
+//! Advancing the time of day, and keeping clients in sync with it.
+
+use bevy::prelude::*;
+use data::registry::Registry;
+use protocol::{ChannelId, Packet, bytes::Bytes, session::Session, types::WorldTimeSync};
+use world::time::{TICKS_PER_SECOND, WorldTime};
+
+use crate::{
+    config::Config,
+    events::PlayerJoined,
+    net::{Server, channel::Channel},
+    player::Player,
+    tick::TickStats,
+};
+
+/// Ticks between sending the time to every player.
+const TIME_SYNC_INTERVAL: u64 = 5 * TICKS_PER_SECOND;
+
+/// Most skipped ticks whose time is made up per tick, so after a stall
+/// the time of day runs at up to twice its speed until it caught up.
+const SLEW_PER_TICK: u64 = 1;
+
+/// Advance the time of day by the length of a tick, plus the time of a skipped tick
+/// while there are any left to make up. Runs every tick.
+///
+/// The time of day advances `TICKS_PER_SECOND` times per second at any tick rate,
+/// so at other rates than that a tick may advance it by more or less than one.
+pub fn advance_world_time(
+    config: Res<Config>,
+    mut stats: ResMut<TickStats>,
+    mut time: ResMut<WorldTime>,
+    mut fraction: Local<f64>,
+) {
+    let slewed = stats.time_debt.min(SLEW_PER_TICK);
+    stats.time_debt -= slewed;
+
+    *fraction += (1 + slewed) as f64 * TICKS_PER_SECOND as f64 / config.tick_rate.max(1) as f64;
+    let whole = fraction.floor();
+    *fraction -= whole;
+    time.tick += whole as u64;
+}
+
+/// Send the time to players when they join, and to every player periodically.
+pub fn send_world_time(
+    channels: Res<Registry<Channel>>,
+    time: Res<WorldTime>,
+    q: Query<&Player>,
+    mut joined_evs: MessageReader<PlayerJoined>,
+    mut server: ResMut<Server>,
+    mut next_sync: Local<u64>,
+) {
+    let channel: ChannelId = channels.resolve("world-time").unwrap().into();
+    let sync = WorldTimeSync {
+        tick: time.tick,
+        sky_darkness: time.sky_darkness(),
+        _pad: 0,
+    };
+    let payload = Bytes::copy_from_slice(bytemuck::bytes_of(&sync));
+    let mut send = |session: Session| {
+        server.tcp_send(Packet {
+            payload: payload.clone(),
+            session,
+            channel,
+        });
+    };
+
+    for ev in joined_evs.read() {
+        send(ev.session);
+    }
+
+    // the time doesn't always advance by one, so it may step over multiples of the interval.
+    if time.tick >= *next_sync {
+        *next_sync = time.tick + TIME_SYNC_INTERVAL;
+        for player in &q {
+            send(player.session);
+        }
+    }
+}
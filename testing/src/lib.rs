@@ -0,0 +1,420 @@
+//! Integration tests of the server, over a real loopback connection.
+//!
+//! `TestServer` runs the server App headless on the test thread, and is ticked by
+//! the test. `TestClient` speaks the protocol directly, without any of the systems
+//! of the client, so tests can script exactly what is sent. Tests tick both until
+//! something happens, then assert on the world of the server and on the packets
+//! the client received.
+//!
+//! ```ignore
+//! let mut server = TestServer::start();
+//! let mut client = TestClient::connect(&mut server);
+//! let chunk = client.wait_for(&mut server, "chunk-data");
+//! ```
+
+use std::{
+    io::{self, Write},
+    net::{SocketAddr, TcpStream, UdpSocket},
+    path::PathBuf,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use bevy::{
+    app::{App, TaskPoolPlugin},
+    asset::AssetPlugin,
+    diagnostic::DiagnosticsPlugin,
+    ecs::resource::Resource,
+    math::IVec3,
+    prelude::Mut,
+    state::app::StatesPlugin,
+    time::TimePlugin,
+    transform::TransformPlugin,
+};
+use bytemuck::Pod;
+use fxhash::FxHashMap;
+use protocol::{
+    ChannelId, ExitCode, Packet,
+    bytes::Bytes,
+    codec::{TcpDecoder, TcpEncoder, UdpDecoder, UdpEncoder},
+    session::Session,
+    types::{AuthAccepted, AuthRequest, PlayerLogin, RegistrySyncPacket},
+};
+use server::{
+    ServerPlugin,
+    net::Server,
+    player::{Player, identity::PlayerId},
+};
+use world::{Voxel, World};
+
+/// Seed of the world generator and loot tables of test servers,
+/// so every run generates the same world.
+pub const TEST_SEED: u64 = 0x5EED;
+
+/// Most ticks `TestClient::tick_until` waits before giving up.
+pub const MAX_TICKS: usize = 600;
+
+/// Time between ticks while waiting, so the network threads can keep up.
+const TICK_SLEEP: Duration = Duration::from_millis(2);
+
+/// Longest `TestClient::poll_until` waits for a server running on its own thread.
+pub const MAX_WAIT: Duration = Duration::from_secs(60);
+
+/// Used to give every test server its own directory, since tests run in parallel.
+static NEXT_DIR: AtomicUsize = AtomicUsize::new(0);
+
+/// A headless server, bound to an ephemeral localhost port.
+/// Its region files are written to a temporary directory, deleted on drop.
+pub struct TestServer {
+    app: App,
+    addr: SocketAddr,
+    dir: PathBuf,
+}
+
+impl TestServer {
+    /// Start a server with the default settings and `TEST_SEED`.
+    pub fn start() -> Self {
+        Self::with_plugin(ServerPlugin::default())
+    }
+
+    /// Start a server with these settings. The address and seeds are replaced with ones
+    /// suitable for tests, and so is the region directory, unless the plugin has one.
+    pub fn with_plugin(plugin: ServerPlugin) -> Self {
+        let dir = std::env::temp_dir().join(format!(
+            "openvoxel-test-{}-{}",
+            std::process::id(),
+            NEXT_DIR.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::create_dir_all(&dir).expect("Failed to create test region directory.");
+
+        let mut app = App::new();
+        app.add_plugins((
+            TaskPoolPlugin::default(),
+            TimePlugin,
+            TransformPlugin,
+            DiagnosticsPlugin,
+            AssetPlugin::default(),
+            StatesPlugin,
+            ServerPlugin {
+                addr: "127.0.0.1:0".parse().unwrap(),
+                region_dir: Some(plugin.region_dir.clone().unwrap_or_else(|| dir.clone())),
+                seed: Some(TEST_SEED),
+                loot_seed: Some(TEST_SEED),
+                ..plugin
+            },
+        ));
+        app.finish();
+        app.cleanup();
+
+        // the first update runs the startup schedules, which binds the server.
+        app.update();
+        let addr = app
+            .world()
+            .resource::<Server>()
+            .local_addr()
+            .expect("Test server failed to bind.");
+
+        Self { app, addr, dir }
+    }
+
+    /// Address the server is listening on.
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Run one tick of the server.
+    pub fn tick(&mut self) {
+        self.app.update();
+    }
+
+    pub fn app(&self) -> &App {
+        &self.app
+    }
+
+    pub fn app_mut(&mut self) -> &mut App {
+        &mut self.app
+    }
+
+    /// Get a resource of the server, panicking if it doesn't exist.
+    pub fn resource<R: Resource>(&self) -> &R {
+        self.app.world().resource::<R>()
+    }
+
+    pub fn resource_mut<R: Resource>(&mut self) -> Mut<'_, R> {
+        self.app.world_mut().resource_mut::<R>()
+    }
+
+    /// The voxel world of the server.
+    pub fn world(&self) -> &World {
+        self.resource::<World>()
+    }
+
+    /// The voxel at this position, if its chunk is loaded.
+    pub fn voxel(&self, pos: IVec3) -> Option<Voxel> {
+        self.world().get_voxel(pos)
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// A client that speaks the protocol directly, and keeps every packet it receives.
+pub struct TestClient {
+    stream: TcpStream,
+    tcp_encoder: TcpEncoder,
+    tcp_decoder: TcpDecoder,
+    udp_encoder: UdpEncoder,
+    udp_decoder: UdpDecoder,
+    session: Session,
+
+    /// Names of the entries of each registry, in the order of the server.
+    registries: Option<RegistrySyncPacket>,
+
+    /// Packets received on each channel, by channel id, oldest first.
+    received: FxHashMap<usize, Vec<Bytes>>,
+
+    /// The exit code the server disconnected with, if it did.
+    exit: Option<ExitCode>,
+}
+
+impl TestClient {
+    /// Connect to the server and tick until the connection is
+    /// accepted and the registries were received.
+    pub fn connect(server: &mut TestServer) -> Self {
+        let mut client = Self::connect_only(server.addr()).expect("Failed to connect to server.");
+        let accepted = client.tick_until(server, |client, _| client.registries.is_some());
+        assert!(accepted, "The server did not accept the connection.");
+        client
+    }
+
+    /// Connect to a server running on its own thread, like an `IntegratedServer`,
+    /// and poll until the connection is accepted and the registries were received.
+    pub fn connect_to(addr: SocketAddr) -> Self {
+        let mut client = Self::connect_only(addr).expect("Failed to connect to server.");
+        let accepted = client.poll_until(|client| client.registries.is_some());
+        assert!(accepted, "The server did not accept the connection.");
+        client
+    }
+
+    /// Connect and send the authentication request, without waiting for a response.
+    pub fn connect_only(addr: SocketAddr) -> io::Result<Self> {
+        let mut stream = TcpStream::connect(addr)?;
+        stream.set_nodelay(true)?;
+
+        let socket = Arc::new(UdpSocket::bind("127.0.0.1:0")?);
+        socket.set_nonblocking(true)?;
+
+        let auth = AuthRequest {
+            udp_addr: socket.local_addr()?,
+        };
+        stream.write_all(&auth.encode())?;
+        stream.set_nonblocking(true)?;
+
+        Ok(Self {
+            stream,
+            tcp_encoder: TcpEncoder::new(),
+            tcp_decoder: TcpDecoder::new(),
+            udp_encoder: UdpEncoder::new(Session::ZERO, socket.clone(), addr),
+            udp_decoder: UdpDecoder::new(socket),
+            session: Session::ZERO,
+            registries: None,
+            received: FxHashMap::default(),
+            exit: None,
+        })
+    }
+
+    /// The session the server assigned to this client, or zero if it wasn't accepted yet.
+    pub fn session(&self) -> Session {
+        self.session
+    }
+
+    /// The exit code the server disconnected this client with, if it did.
+    pub fn exit(&self) -> Option<&ExitCode> {
+        self.exit.as_ref()
+    }
+
+    /// Names of the entries of a registry the server sent, in the order of their ids.
+    pub fn registry(&self, name: &str) -> Option<&[String]> {
+        self.registries
+            .as_ref()?
+            .get(name)
+            .map(|names| names.as_slice())
+    }
+
+    /// Id of the channel with this name, panicking if the server doesn't have it.
+    pub fn channel(&self, name: &str) -> ChannelId {
+        self.registry("channels")
+            .and_then(|names| names.iter().position(|n| n == name))
+            .map(ChannelId)
+            .unwrap_or_else(|| panic!("The server has no channel named '{name}'."))
+    }
+
+    /// Send a payload on a channel over TCP. Sent on the next `poll`.
+    pub fn send(&mut self, channel: &str, payload: impl AsRef<[u8]>) {
+        let channel = self.channel(channel);
+        self.tcp_encoder.encode(channel, payload.as_ref());
+    }
+
+    /// Send a payload on a channel over UDP. Sent on the next `poll`.
+    pub fn send_udp(&mut self, channel: &str, payload: impl AsRef<[u8]>) {
+        let channel = self.channel(channel);
+        self.udp_encoder.encode(channel, payload.as_ref());
+    }
+
+    /// Send a `Pod` type on a channel over TCP.
+    pub fn send_pod<T: Pod>(&mut self, channel: &str, value: &T) {
+        self.send(channel, bytemuck::bytes_of(value));
+    }
+
+    /// Send the packets waiting to be sent, and receive the packets that arrived.
+    pub fn poll(&mut self) {
+        if self.exit.is_some() {
+            return;
+        }
+
+        self.udp_encoder.flush();
+        if let Err(exit) = self.tcp_encoder.flush(&mut self.stream) {
+            self.exit = Some(exit);
+            return;
+        }
+
+        let mut packets = Vec::new();
+        if let Err(exit) = self
+            .tcp_decoder
+            .collect(&mut self.stream, self.session, &mut packets)
+        {
+            self.exit = Some(exit);
+        }
+
+        while let Some((_, session)) = self.udp_decoder.read() {
+            if session == self.session {
+                while let Some((channel, payload)) = self.udp_decoder.decode() {
+                    packets.push(Packet {
+                        session,
+                        channel,
+                        payload,
+                    });
+                }
+            }
+        }
+
+        for packet in packets {
+            self.handle(packet);
+        }
+    }
+
+    fn handle(&mut self, packet: Packet) {
+        match packet.channel {
+            ChannelId::AUTH_REQ => {
+                let accepted: AuthAccepted = packet.json().expect("Invalid auth response.");
+                self.session = accepted.session;
+                self.udp_encoder.set_session(accepted.session);
+                self.udp_encoder.set_address(accepted.udp_addr);
+            }
+            ChannelId::SYNC_DATA => {
+                self.registries = Some(packet.json().expect("Invalid registry sync payload."));
+            }
+            ChannelId::EXIT_CODE => {
+                self.exit = Some(ExitCode::from_bytes(packet.payload));
+            }
+            channel => self
+                .received
+                .entry(channel.0)
+                .or_default()
+                .push(packet.payload),
+        }
+    }
+
+    /// Payloads received on a channel that weren't taken yet, oldest first.
+    pub fn received(&self, channel: &str) -> &[Bytes] {
+        let channel = self.channel(channel);
+        self.received
+            .get(&channel.0)
+            .map(|packets| packets.as_slice())
+            .unwrap_or(&[])
+    }
+
+    /// Take the payloads received on a channel, oldest first.
+    pub fn take(&mut self, channel: &str) -> Vec<Bytes> {
+        let channel = self.channel(channel);
+        self.received.remove(&channel.0).unwrap_or_default()
+    }
+
+    /// Take the payloads received on a channel, read as a `Pod` type.
+    /// Payloads of the wrong size are skipped.
+    pub fn take_pod<T: Pod>(&mut self, channel: &str) -> Vec<T> {
+        self.take(channel)
+            .iter()
+            .filter_map(|payload| bytemuck::try_pod_read_unaligned(payload).ok())
+            .collect()
+    }
+
+    /// Tick the server and poll the client until `done` returns true,
+    /// or `MAX_TICKS` have passed. Returns whether `done` returned true.
+    pub fn tick_until(
+        &mut self,
+        server: &mut TestServer,
+        mut done: impl FnMut(&mut Self, &mut TestServer) -> bool,
+    ) -> bool {
+        for _ in 0..MAX_TICKS {
+            self.poll();
+            server.tick();
+            self.poll();
+            if done(self, server) {
+                return true;
+            }
+            thread::sleep(TICK_SLEEP);
+        }
+        false
+    }
+
+    /// Poll the client until `done` returns true, or `MAX_WAIT` has passed, for servers
+    /// running on their own thread. Returns whether `done` returned true.
+    pub fn poll_until(&mut self, mut done: impl FnMut(&mut Self) -> bool) -> bool {
+        let start = Instant::now();
+        while start.elapsed() < MAX_WAIT {
+            self.poll();
+            if done(self) {
+                return true;
+            }
+            thread::sleep(TICK_SLEEP);
+        }
+        false
+    }
+
+    /// Log in with this key, and tick until the server gave the player the id of the key.
+    pub fn login(&mut self, server: &mut TestServer, key: [u64; 2]) -> PlayerId {
+        self.send_pod("player-login", &PlayerLogin { key });
+
+        let session = self.session;
+        let mut id = None;
+        let mut players = server.app_mut().world_mut().query::<(&Player, &PlayerId)>();
+        let logged_in = self.tick_until(server, |_, server| {
+            id = players
+                .iter(server.app().world())
+                .find(|(player, _)| player.session == session)
+                .map(|(_, id)| *id);
+            id.is_some()
+        });
+        assert!(logged_in, "The server did not log the client in.");
+        id.unwrap()
+    }
+
+    /// Tick until a packet is received on a channel, and take the oldest one.
+    /// Panics if nothing arrives within `MAX_TICKS`.
+    pub fn wait_for(&mut self, server: &mut TestServer, channel: &str) -> Bytes {
+        let id = self.channel(channel).0;
+        let arrived = self.tick_until(server, |client, _| {
+            client.received.get(&id).is_some_and(|p| !p.is_empty())
+        });
+        assert!(arrived, "Nothing was received on '{channel}'.");
+        self.received.get_mut(&id).unwrap().remove(0)
+    }
+}
@@ -0,0 +1,181 @@
+use bevy::{
+    math::{IVec2, IVec3, Vec3},
+    transform::components::Transform,
+};
+use protocol::types::{BlockEditRequest, BlockUpdate};
+use server::player::Player;
+use testing::{TestClient, TestServer};
+use world::{Voxel, World, region::chunk::flags::ChunkState};
+
+/// Height the player stands at, in the air above the terrain of the spawn chunk.
+const Y: i32 = 200;
+
+/// Top face of a voxel, `Axis::PosY`.
+const TOP: u8 = 2;
+
+/// Connect and log in, stand the player at `Y` once the spawn chunk is loaded,
+/// and return the id of the stone block state.
+fn setup() -> (TestServer, TestClient, u16) {
+    let mut server = TestServer::start();
+    let mut client = TestClient::connect(&mut server);
+    client.login(&mut server, [1, 2]);
+
+    let loaded = client.tick_until(&mut server, |_, server| {
+        server
+            .world()
+            .get_chunk(IVec2::ZERO)
+            .is_some_and(|chunk| chunk.load_state() == ChunkState::Loaded)
+    });
+    assert!(loaded, "The spawn chunk was not loaded.");
+
+    let world = server.app_mut().world_mut();
+    let mut players = world.query::<(&Player, &mut Transform)>();
+    for (_, mut transform) in players.iter_mut(world) {
+        transform.translation = Vec3::new(0.5, Y as f32, 0.5);
+    }
+
+    let stone = client
+        .registry("block_states")
+        .and_then(|names| names.iter().position(|name| name == "stone"))
+        .expect("The server has no stone block state.");
+    (server, client, stone as u16)
+}
+
+fn set_voxel(server: &mut TestServer, pos: IVec3, voxel: Voxel) {
+    assert!(server.resource_mut::<World>().set_voxel(pos, voxel));
+}
+
+/// Request an edit and wait for the answer of the server, which is always sent.
+fn edit(
+    client: &mut TestClient,
+    server: &mut TestServer,
+    action: u8,
+    pos: IVec3,
+    item: u16,
+) -> BlockUpdate {
+    client.send_pod(
+        "block-edit",
+        &BlockEditRequest {
+            sequence: 1,
+            pos,
+            face: TOP,
+            action,
+            item,
+        },
+    );
+    let update = client.wait_for(server, "block-update");
+    bytemuck::pod_read_unaligned(&update)
+}
+
+#[test]
+fn place_and_break() {
+    let (mut server, mut client, stone) = setup();
+    let below = IVec3::new(2, Y, 0);
+    let above = below + IVec3::Y;
+    set_voxel(&mut server, below, Voxel(stone));
+
+    // blocks are placed on the face that was clicked.
+    let update = edit(
+        &mut client,
+        &mut server,
+        BlockEditRequest::PLACE,
+        below,
+        stone,
+    );
+    assert_eq!(update.pos, above);
+    assert_eq!(update.voxel, stone);
+    assert_eq!(server.voxel(above), Some(Voxel(stone)));
+
+    let update = edit(&mut client, &mut server, BlockEditRequest::BREAK, above, 0);
+    assert_eq!(update.voxel, Voxel::AIR.0);
+    assert_eq!(server.voxel(above), Some(Voxel::AIR));
+}
+
+#[test]
+fn only_placeable_states_are_placed() {
+    let (mut server, mut client, stone) = setup();
+    let below = IVec3::new(2, Y, 0);
+    let above = below + IVec3::Y;
+    set_voxel(&mut server, below, Voxel(stone));
+
+    // ids that aren't block states, and states without a model.
+    for item in [u16::MAX, Voxel::AIR.0] {
+        let update = edit(
+            &mut client,
+            &mut server,
+            BlockEditRequest::PLACE,
+            below,
+            item,
+        );
+        assert_eq!(update.voxel, Voxel::AIR.0);
+        assert_eq!(server.voxel(above), Some(Voxel::AIR));
+    }
+}
+
+#[test]
+fn only_air_is_replaced() {
+    let (mut server, mut client, stone) = setup();
+    let below = IVec3::new(2, Y, 0);
+    let above = below + IVec3::Y;
+    set_voxel(&mut server, below, Voxel(stone));
+    set_voxel(&mut server, above, Voxel(stone));
+
+    // placing into a block, which the client only does when it is out of sync.
+    let update = edit(
+        &mut client,
+        &mut server,
+        BlockEditRequest::PLACE,
+        below,
+        stone,
+    );
+    assert_eq!(update.voxel, stone);
+    assert_eq!(server.voxel(above), Some(Voxel(stone)));
+
+    // breaking air leaves it air.
+    let air = IVec3::new(3, Y, 0);
+    let update = edit(&mut client, &mut server, BlockEditRequest::BREAK, air, 0);
+    assert_eq!(update.voxel, Voxel::AIR.0);
+}
+
+#[test]
+fn edits_out_of_reach_are_rejected() {
+    let (mut server, mut client, stone) = setup();
+
+    // too far from the player.
+    let far = IVec3::new(14, Y, 0);
+    set_voxel(&mut server, far, Voxel(stone));
+    let update = edit(&mut client, &mut server, BlockEditRequest::BREAK, far, 0);
+    assert_eq!(update.voxel, stone);
+    assert_eq!(server.voxel(far), Some(Voxel(stone)));
+
+    // close, but behind a wall two blocks thick.
+    for x in [2, 3] {
+        for y in [Y + 1, Y + 2] {
+            set_voxel(&mut server, IVec3::new(x, y, 0), Voxel(stone));
+        }
+    }
+    let hidden = IVec3::new(5, Y + 1, 0);
+    set_voxel(&mut server, hidden, Voxel(stone));
+    let update = edit(&mut client, &mut server, BlockEditRequest::BREAK, hidden, 0);
+    assert_eq!(update.voxel, stone);
+    assert_eq!(server.voxel(hidden), Some(Voxel(stone)));
+}
+
+#[test]
+fn reach_is_measured_from_the_eyes() {
+    let (mut server, mut client, stone) = setup();
+
+    // closer to the feet than the reach, but not to the eyes.
+    let below = IVec3::new(0, Y - 9, 0);
+    set_voxel(&mut server, below, Voxel(stone));
+    let update = edit(&mut client, &mut server, BlockEditRequest::BREAK, below, 0);
+    assert_eq!(update.voxel, stone);
+    assert_eq!(server.voxel(below), Some(Voxel(stone)));
+
+    // further from the feet than the reach, but not from the eyes.
+    let above = IVec3::new(0, Y + 10, 0);
+    set_voxel(&mut server, above, Voxel(stone));
+    let update = edit(&mut client, &mut server, BlockEditRequest::BREAK, above, 0);
+    assert_eq!(update.voxel, Voxel::AIR.0);
+    assert_eq!(server.voxel(above), Some(Voxel::AIR));
+}
@@ -0,0 +1,127 @@
+use bevy::math::{IVec2, IVec3, Vec3Swizzles};
+use protocol::{
+    bytes::Bytes,
+    types::{ChunkRequest, ChunkRevision},
+};
+use server::player::Player;
+use testing::{TestClient, TestServer};
+use world::{World, region::format::UnzippedChunk};
+
+/// Origin of the chunk the player spawns in.
+const SPAWN_CHUNK: IVec2 = IVec2::ZERO;
+
+/// Origin of a chunk's zipped data.
+fn origin(data: &Bytes) -> IVec2 {
+    let unzip = UnzippedChunk::unzip(data).expect("Chunk data failed to unzip.");
+    unzip
+        .header()
+        .expect("Chunk data has no header.")
+        .origin
+        .xz()
+}
+
+/// Tick until the chunk at this origin is received, skipping the others.
+fn wait_for_chunk(client: &mut TestClient, server: &mut TestServer, at: IVec2) -> Bytes {
+    let mut found = None;
+    let arrived = client.tick_until(server, |client, _| {
+        found = client
+            .take("chunk-data")
+            .into_iter()
+            .find(|data| origin(data) == at);
+        found.is_some()
+    });
+    assert!(arrived, "The chunk at {at} was not received.");
+    found.unwrap()
+}
+
+#[test]
+fn join() {
+    let mut server = TestServer::start();
+    let mut client = TestClient::connect(&mut server);
+
+    assert!(client.exit().is_none());
+    assert!(client.registry("channels").is_some());
+    client.channel("chunk-data");
+
+    let session = client.session();
+    let mut players = server.app_mut().world_mut().query::<&Player>();
+    let spawned = client.tick_until(&mut server, |_, server| {
+        players
+            .iter(server.app().world())
+            .any(|player| player.session == session)
+    });
+    assert!(spawned, "No player was spawned for the client.");
+}
+
+#[test]
+fn receives_spawn_chunk() {
+    let mut server = TestServer::start();
+    let mut client = TestClient::connect(&mut server);
+    let data = wait_for_chunk(&mut client, &mut server, SPAWN_CHUNK);
+
+    let mut world = World::new(256, -128);
+    let unzip = UnzippedChunk::unzip(&data).unwrap();
+    world.read_unzipped_chunk(unzip, true).unwrap();
+
+    // the chunk the client read is the chunk of the server.
+    for y in (-128..256).step_by(7) {
+        for (x, z) in [(0, 0), (5, 17), (31, 31), (12, 3)] {
+            let pos = IVec3::new(x, y, z);
+            assert_eq!(world.get_voxel(pos), server.voxel(pos), "at {pos}");
+        }
+    }
+}
+
+#[test]
+fn chunk_request_resends() {
+    let mut server = TestServer::start();
+    let mut client = TestClient::connect(&mut server);
+    let first = wait_for_chunk(&mut client, &mut server, SPAWN_CHUNK);
+
+    client.send_pod(
+        "chunk-request",
+        &ChunkRequest {
+            origin: SPAWN_CHUNK,
+        },
+    );
+    let again = wait_for_chunk(&mut client, &mut server, SPAWN_CHUNK);
+    assert_eq!(origin(&first), origin(&again));
+}
+
+#[test]
+fn unchanged_chunk_not_resent() {
+    let mut server = TestServer::start();
+    let mut client = TestClient::connect(&mut server);
+    let data = wait_for_chunk(&mut client, &mut server, SPAWN_CHUNK);
+    let revision = UnzippedChunk::unzip(&data)
+        .unwrap()
+        .header()
+        .unwrap()
+        .revision;
+
+    // the client kept a copy, so the server only confirms it.
+    client.send_pod(
+        "chunk-revisions",
+        &ChunkRevision {
+            origin: SPAWN_CHUNK,
+            revision,
+        },
+    );
+    client.send_pod(
+        "chunk-request",
+        &ChunkRequest {
+            origin: SPAWN_CHUNK,
+        },
+    );
+
+    let cached = client.wait_for(&mut server, "chunk-cached");
+    let cached = bytemuck::pod_read_unaligned::<ChunkRevision>(&cached);
+    assert_eq!(cached.origin, SPAWN_CHUNK);
+    assert_eq!(cached.revision, revision);
+    assert!(
+        client
+            .take("chunk-data")
+            .iter()
+            .all(|data| origin(data) != SPAWN_CHUNK)
+    );
+}
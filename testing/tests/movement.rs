@@ -0,0 +1,88 @@
+use bevy::{
+    math::{IVec2, Quat, Vec3},
+    transform::components::Transform,
+};
+use protocol::{
+    packet::Version,
+    types::{PlayerInputUpdate, PlayerPositionCorrection},
+};
+use server::player::Player;
+use testing::{TestClient, TestServer};
+use world::region::chunk::flags::ChunkState;
+
+/// Where the player stands, in the air above the terrain of the spawn chunk.
+const START: Vec3 = Vec3::new(0.5, 200.0, 0.5);
+
+/// Connect and log in, and stand the player at `START` once the spawn chunk is loaded.
+fn setup() -> (TestServer, TestClient) {
+    let mut server = TestServer::start();
+    let mut client = TestClient::connect(&mut server);
+    client.login(&mut server, [1, 2]);
+
+    let loaded = client.tick_until(&mut server, |_, server| {
+        server
+            .world()
+            .get_chunk(IVec2::ZERO)
+            .is_some_and(|chunk| chunk.load_state() == ChunkState::Loaded)
+    });
+    assert!(loaded, "The spawn chunk was not loaded.");
+
+    let world = server.app_mut().world_mut();
+    let mut players = world.query::<(&Player, &mut Transform)>();
+    for (_, mut transform) in players.iter_mut(world) {
+        transform.translation = START;
+    }
+    (server, client)
+}
+
+fn position(server: &mut TestServer) -> Vec3 {
+    let world = server.app_mut().world_mut();
+    let mut players = world.query::<(&Player, &Transform)>();
+    players.single(world).unwrap().1.translation
+}
+
+fn send_move(client: &mut TestClient, version: &mut Version, translation: Vec3, correction: u32) {
+    client.send_pod(
+        "player-input",
+        &PlayerInputUpdate {
+            version: version.next(),
+            translation,
+            look_dir: Quat::IDENTITY,
+            movement: 0,
+            correction,
+            _pad: [0; 2],
+        },
+    );
+}
+
+#[test]
+fn rejected_moves_are_corrected() {
+    let (mut server, mut client) = setup();
+    let mut version = Version::ZERO;
+
+    // further than a player can move at once, so the server keeps the player where they were.
+    send_move(&mut client, &mut version, START + Vec3::X * 100.0, 0);
+    let correction = client.wait_for(&mut server, "player-position");
+    let correction: PlayerPositionCorrection = bytemuck::pod_read_unaligned(&correction);
+    assert_eq!(correction.correction, 1);
+    assert_eq!(correction.translation, START);
+    assert_eq!(position(&mut server), START);
+
+    // moves sent before the client applied the correction are ignored.
+    send_move(&mut client, &mut version, START + Vec3::X * 0.5, 0);
+    for _ in 0..10 {
+        client.poll();
+        server.tick();
+    }
+    assert_eq!(position(&mut server), START);
+    assert!(client.take("player-position").is_empty());
+
+    // and moves from the corrected position are applied.
+    let to = START + Vec3::Z * 0.5;
+    send_move(&mut client, &mut version, to, correction.correction);
+    let moved = client.tick_until(&mut server, |_, server| position(server) == to);
+    assert!(
+        moved,
+        "The move from the corrected position was not applied."
+    );
+}
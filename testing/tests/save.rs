@@ -0,0 +1,167 @@
+use std::{panic, path::PathBuf};
+
+use bevy::{
+    app::AppExit,
+    math::{IVec2, IVec3, Vec3Swizzles},
+};
+use data::fs::save::{REGIONS_DIR, REGISTRY_IDS_FILE, WorldInfo};
+use protocol::types::{BlockEditRequest, BlockUpdate, PlayerLogin};
+use server::{IntegratedServer, ServerPlugin, world::loader::WorldLoader};
+use testing::{TEST_SEED, TestClient, TestServer};
+use world::{
+    Voxel, World,
+    region::{chunk::flags::ChunkState, format::decode_chunk_bytes},
+};
+
+#[test]
+fn changed_chunks_are_saved_on_exit() {
+    let mut server = TestServer::start();
+    let mut client = TestClient::connect(&mut server);
+    let loaded = client.tick_until(&mut server, |_, server| {
+        server
+            .world()
+            .get_chunk(IVec2::ZERO)
+            .is_some_and(|chunk| chunk.load_state() == ChunkState::Loaded)
+    });
+    assert!(loaded, "The spawn chunk was not loaded.");
+
+    let pos = IVec3::new(3, 200, 5);
+    {
+        let mut world = server.resource_mut::<World>();
+        assert!(world.set_voxel(pos, Voxel(1)));
+        world.get_chunk_mut(pos.xz()).unwrap().clear_cached_zip();
+    }
+    server.tick();
+
+    // the autosave interval hasn't passed, so the chunk is written when the server stops.
+    server.app_mut().world_mut().write_message(AppExit::Success);
+    server.tick();
+
+    let world = server.world();
+    let data = server
+        .resource::<WorldLoader>()
+        .read_chunk_raw(pos.xz())
+        .expect("The changed chunk was not saved.");
+    let (saved, _) = decode_chunk_bytes(data, world.min_y() + world.height(), world.min_y())
+        .expect("The saved chunk couldn't be decoded.");
+    assert_eq!(saved.get_voxel(pos), Some(Voxel(1)));
+}
+
+/// Directory of a singleplayer world, deleted on drop.
+struct WorldDir(PathBuf);
+
+impl Drop for WorldDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Break and place stone at the position, and poll until both edits were answered
+/// and the stone was placed. Edits are ignored until the player logged in, and
+/// rejected until the chunk is loaded, so the pair is sent again until it is.
+fn place_stone(client: &mut TestClient, pos: IVec3, stone: u16) -> bool {
+    let mut sequence = 0;
+    let mut answers = Vec::new();
+    let mut waited = usize::MAX;
+    client.poll_until(|client| {
+        answers.extend(
+            client
+                .take_pod::<BlockUpdate>("block-update")
+                .into_iter()
+                .filter(|update| update.sequence == sequence)
+                .map(|update| update.voxel),
+        );
+        if answers == [Voxel::AIR.0, stone] {
+            return true;
+        }
+
+        waited = waited.saturating_add(1);
+        if answers.len() >= 2 || waited > 100 {
+            sequence += 1;
+            answers.clear();
+            waited = 0;
+            for action in [BlockEditRequest::BREAK, BlockEditRequest::PLACE] {
+                client.send_pod(
+                    "block-edit",
+                    &BlockEditRequest {
+                        sequence,
+                        pos,
+                        face: u8::MAX,
+                        action,
+                        item: stone,
+                    },
+                );
+            }
+        }
+        false
+    })
+}
+
+#[test]
+fn singleplayer_edits_are_saved_when_the_server_stops() {
+    let dir = WorldDir(std::env::temp_dir().join(format!(
+        "openvoxel-test-singleplayer-{}",
+        std::process::id()
+    )));
+    let info = WorldInfo {
+        seed: TEST_SEED as i64,
+        ..Default::default()
+    };
+
+    // next to the eyes of the player, who spawns at (0, 64, 0).
+    let pos = IVec3::new(1, 65, 0);
+    let stone = {
+        let server = IntegratedServer::spawn(&dir.0, &info).expect("Failed to start the server.");
+        let mut client = TestClient::connect_to(server.addr());
+        client.send_pod("player-login", &PlayerLogin { key: [1, 2] });
+        let stone = client
+            .registry("block_states")
+            .and_then(|names| names.iter().position(|name| name == "stone"))
+            .expect("The server has no stone block state.") as u16;
+        assert!(
+            place_stone(&mut client, pos, stone),
+            "The stone was not placed."
+        );
+        stone
+    };
+
+    // the autosave interval hasn't passed, so the stone is only there if it was saved on drop.
+    let mut server = TestServer::with_plugin(ServerPlugin {
+        region_dir: Some(dir.0.join(REGIONS_DIR)),
+        max_y: info.max_y,
+        min_y: info.min_y,
+        ..Default::default()
+    });
+    let mut client = TestClient::connect(&mut server);
+    let loaded = client.tick_until(&mut server, |_, server| {
+        server
+            .world()
+            .get_chunk(pos.xz())
+            .is_some_and(|chunk| chunk.load_state() == ChunkState::Loaded)
+    });
+    assert!(loaded, "The chunk of the stone was not loaded.");
+    assert_eq!(server.voxel(pos), Some(Voxel(stone)));
+}
+
+#[test]
+fn unreadable_registry_ids_are_not_replaced() {
+    let dir = WorldDir(
+        std::env::temp_dir().join(format!("openvoxel-test-registries-{}", std::process::id())),
+    );
+    let file = dir.0.join(REGISTRY_IDS_FILE);
+    std::fs::create_dir_all(&dir.0).unwrap();
+    std::fs::write(&file, "block_states = [\"air\", \"sto").unwrap();
+
+    // the server refuses to open the world, since the ids of its voxels are unknown.
+    let started = panic::catch_unwind(|| {
+        TestServer::with_plugin(ServerPlugin {
+            region_dir: Some(dir.0.clone()),
+            ..Default::default()
+        })
+    });
+    assert!(started.is_err());
+    assert_eq!(
+        std::fs::read_to_string(&file).unwrap(),
+        "block_states = [\"air\", \"sto"
+    );
+}